@@ -0,0 +1,137 @@
+//! Build metadata for the boot splash, and the BDF fonts
+//!
+//! BUILD_HASH is the short git hash of the checkout being built, or empty
+//! when git or the repository is not available (e.g. a source tarball).
+//!
+//! Each `src/assets/bdf/NAME.bdf` becomes `pub static NAME: PropFont` (the
+//! file name in capitals) in `$OUT_DIR/bdf_fonts.rs`, which
+//! `assets::fonts` includes. Characters a font lacks draw as its '?'.
+//!
+//! Every `pub static NAME: [u8 or u16; N]` table in the asset files gets an
+//! entry in `$OUT_DIR/asset_crcs.rs` with the checksum the CRC unit should
+//! give for it (core_logic::crc), which `asset_check` includes and verifies
+//! at boot.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const BDF_DIR: &str = "src/assets/bdf";
+// Asset files, by the module under `assets` that holds them
+const ASSET_FILES: [(&str, &str); 2] = [
+    ("assets", "src/assets/assets.rs"),
+    ("sounds", "src/assets/sounds.rs"),
+];
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=BUILD_HASH={}", hash.trim());
+
+    fonts();
+    asset_crcs();
+}
+
+fn fonts() {
+    println!("cargo:rerun-if-changed={}", BDF_DIR);
+    let mut paths: Vec<_> = fs::read_dir(BDF_DIR)
+        .expect("reading src/assets/bdf")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bdf"))
+        .collect();
+    paths.sort();
+
+    let mut out = String::new();
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = fs::read_to_string(&path).expect("reading a BDF font");
+        let font = core_logic::bdf::parse(&source)
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .expect("BDF file name")
+            .to_uppercase();
+        out.push_str(&core_logic::bdf::to_rust(&font, &name, '?'));
+    }
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("bdf_fonts.rs");
+    fs::write(dest, out).expect("writing bdf_fonts.rs");
+}
+
+// Each table's name, element width in bytes and values, in file order
+fn tables(source: &str) -> Vec<(String, usize, Vec<u32>)> {
+    let mut tables = Vec::new();
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let Some(rest) = line.strip_prefix("pub static ") else {
+            continue;
+        };
+        let Some((name, ty)) = rest.split_once(": [") else {
+            continue;
+        };
+        let width = match ty.split(';').next() {
+            Some("u8") => 1,
+            Some("u16") => 2,
+            _ => continue,
+        };
+        let mut values = Vec::new();
+        for line in lines.by_ref() {
+            let line = line.trim();
+            if line.starts_with("];") {
+                break;
+            }
+            for value in line.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+                let parsed = match value.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => value.parse(),
+                };
+                values.push(parsed.unwrap_or_else(|_| panic!("{}: bad value {}", name, value)));
+            }
+        }
+        tables.push((name.to_string(), width, values));
+    }
+    tables
+}
+
+// A table's name without its type suffix, for the warning screen
+fn short_name(name: &str) -> String {
+    let name = ["_IMG_DATA", "_IMAGE_DATA", "_ADPCM"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    name.to_lowercase()
+}
+
+fn asset_crcs() {
+    let mut entries = Vec::new();
+    for (module, path) in ASSET_FILES {
+        println!("cargo:rerun-if-changed={}", path);
+        let source = fs::read_to_string(path).unwrap_or_else(|_| panic!("reading {}", path));
+        for (name, width, values) in tables(&source) {
+            let bytes = values
+                .iter()
+                .flat_map(|value| value.to_le_bytes().into_iter().take(width));
+            let crc = core_logic::crc::mpeg2(core_logic::crc::le_words(bytes));
+            let data = if width == 1 { "Bytes" } else { "Halfwords" };
+            entries.push(format!(
+                "    Asset {{ name: \"{}\", data: AssetData::{}(&assets::{}::{}), crc: {:#010x} }},\n",
+                short_name(&name),
+                data,
+                module,
+                name,
+                crc
+            ));
+        }
+    }
+    let mut out = format!("pub static ASSETS: [Asset; {}] = [\n", entries.len());
+    out.extend(entries);
+    out.push_str("];\n");
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("asset_crcs.rs");
+    fs::write(dest, out).expect("writing asset_crcs.rs");
+}
@@ -0,0 +1,72 @@
+//! Plays the game rules on the host and dumps the frames
+//!
+//! cargo run -p core_logic --target x86_64-unknown-linux-gnu --features sim --example sim
+//!
+//! The bird steers itself toward the middle of each opening. Frames land in
+//! `sim_out/`; the layout is drawn with flat colors in place of the sprites.
+
+use core_logic::bird::Bird;
+use core_logic::config::*;
+use core_logic::lane::Lane;
+use core_logic::obstacle::ObstaclePair;
+use core_logic::render::RenderBackend;
+use core_logic::rules;
+use core_logic::sim::PpmBackend;
+
+const SKY: u16 = 0x9F5E;
+const SCOREBOARD: u16 = 0xFFFF;
+const PIPE: u16 = 0x0400;
+const GROUND: u16 = 0x8400;
+const BIRD: u16 = 0xFFE0;
+
+const FRAMES: u32 = 600;
+// Write every this many frames
+const EVERY: u32 = 10;
+
+fn draw(out: &mut impl RenderBackend, lane: Lane, bird: &Bird, pair: &ObstaclePair) {
+    let (w, _) = out.size();
+    out.fill_rect(0, lane.top, w, lane.score_height as u32, SCOREBOARD);
+    let field = (lane.ground - lane.score_height) as u32;
+    out.fill_rect(0, lane.y(lane.score_height), w, field, SKY);
+    let ground = (lane.height - lane.ground) as u32;
+    out.fill_rect(0, lane.y(lane.ground), w, ground, GROUND);
+
+    for pipe in pair.rects() {
+        out.fill_rect(pipe.x, pipe.y, pipe.w, pipe.h, PIPE);
+    }
+
+    let (x, y) = bird.xy();
+    out.fill_rect(x, y, PLAYER_WIDTH, PLAYER_HEIGHT, BIRD);
+}
+
+fn main() -> std::io::Result<()> {
+    let lane = Lane::FULL;
+    let mut out = PpmBackend::new(LCD_WIDTH, LCD_HEIGHT, "sim_out")?;
+    let mut bird = Bird::default();
+    let mut pair = ObstaclePair::new(lane);
+    let mut score = 0;
+
+    for frame in 0..FRAMES {
+        let (_, top, bottom) = pair.get_gap();
+        let target = (top + bottom) / 2 - PLAYER_HEIGHT as Coord / 2;
+        let (_, y) = bird.xy();
+        bird.move_to(y + (target - y).clamp(-FLAP_LIFT, 2));
+
+        pair.advance();
+        if rules::collides(&bird, &pair, lane.field()) {
+            println!("crashed at frame {frame}");
+            break;
+        }
+        if rules::passed(&bird, &mut pair) {
+            score += 1;
+        }
+        pair.wrap(score);
+
+        if frame % EVERY == 0 {
+            draw(&mut out, lane, &bird, &pair);
+            out.present();
+        }
+    }
+    println!("score {score}, {} frames in sim_out/", out.frames());
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Countdown, death and screen change animation timing
+//!
+//! Everything here is a function of the milliseconds elapsed since the
+//! animation began, so the game loop keeps running at full rate while they
+//! play. The death animation counts game time (`FixedStep::played_ms`),
+//! which `slowmo_scale` slows down while it plays.
+
+use crate::config::Coord;
+use crate::timestep::FULL_SPEED;
+
+// Length of each countdown step
+pub const COUNTDOWN_STEP_MS: u32 = 1000;
+pub const COUNTDOWN_FROM: u32 = 3;
+
+// White flash on death, fading out over this long
+pub const FLASH_MS: u32 = 200;
+// Bird falls as y = start + t^2 / FALL_DIVISOR (t in ms): ~200 px in 600 ms
+const FALL_DIVISOR: u32 = 1800;
+// Time from the collision until the game-over screen replaces the playfield
+pub const DEATH_MS: u32 = 1200;
+// Slow motion after a crash: down to SLOWMO_SCALE over the first
+// SLOWMO_IN_MS of real time, held until SLOWMO_HOLD_MS, back to full speed
+// by SLOWMO_MS
+pub const SLOWMO_SCALE: u32 = FULL_SPEED / 4;
+const SLOWMO_IN_MS: u32 = 100;
+const SLOWMO_HOLD_MS: u32 = 500;
+const SLOWMO_MS: u32 = 800;
+
+// Length of a change from one screen to the next
+pub const TRANSITION_MS: u32 = 400;
+
+/// How one screen gives way to the next
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Transition {
+    // The new picture sweeps in from the left edge over the old
+    Wipe,
+    // The old picture fades to black and the new one up out of it
+    Fade,
+    // The new picture pushes in from the right, slowing as it lands
+    Slide,
+}
+
+/// A transition part of the way through, across a picture `width` wide
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TransitionFrame {
+    // The new picture over columns 0..x, the old one right of it
+    Wipe(u32),
+    // The new picture's first x columns showing at the right edge
+    Slide(u32),
+    // Alpha of the picture out of 255, and whether it is the new one yet
+    Fade { alpha: u8, new: bool },
+}
+
+// `kind` at `elapsed` ms in, or None once it is over
+pub fn transition_frame(kind: Transition, elapsed: u32, width: u32) -> Option<TransitionFrame> {
+    if elapsed >= TRANSITION_MS {
+        return None;
+    }
+    Some(match kind {
+        Transition::Wipe => TransitionFrame::Wipe(width * elapsed / TRANSITION_MS),
+        Transition::Slide => {
+            // Ease out: the part still to come shrinks with the square of
+            // the time left
+            let left = (TRANSITION_MS - elapsed) as u64;
+            let total = (TRANSITION_MS as u64).pow(2);
+            let to_come = width as u64 * left * left / total;
+            TransitionFrame::Slide(width - to_come as u32)
+        }
+        Transition::Fade => {
+            let half = TRANSITION_MS / 2;
+            let new = elapsed >= half;
+            let t = if new { elapsed - half } else { half - elapsed };
+            TransitionFrame::Fade {
+                alpha: (255 * t / half) as u8,
+                new,
+            }
+        }
+    })
+}
+
+// Countdown digit to show `elapsed` ms after the countdown began, or None
+// once it has run out
+pub fn countdown_digit(elapsed: u32) -> Option<u32> {
+    let step = elapsed / COUNTDOWN_STEP_MS;
+    (step < COUNTDOWN_FROM).then(|| COUNTDOWN_FROM - step)
+}
+
+// Layer 1 constant alpha during the death flash. The caller sets a white
+// LTDC background first, so lowering Layer 1's alpha washes the picture out
+// to white; `brightness` is the alpha to settle back to.
+pub fn flash_alpha(elapsed: u32, brightness: u8) -> u8 {
+    let t = elapsed.min(FLASH_MS);
+    (brightness as u32 * t / FLASH_MS) as u8
+}
+
+// Time scale `elapsed` real ms after a crash, in 1/FULL_SPEED
+pub fn slowmo_scale(elapsed: u32) -> u32 {
+    let slowed = FULL_SPEED - SLOWMO_SCALE;
+    if elapsed < SLOWMO_IN_MS {
+        FULL_SPEED - slowed * elapsed / SLOWMO_IN_MS
+    } else if elapsed < SLOWMO_HOLD_MS {
+        SLOWMO_SCALE
+    } else if elapsed < SLOWMO_MS {
+        SLOWMO_SCALE + slowed * (elapsed - SLOWMO_HOLD_MS) / (SLOWMO_MS - SLOWMO_HOLD_MS)
+    } else {
+        FULL_SPEED
+    }
+}
+
+// Bird y position `elapsed` ms into the death fall, stopping at `floor`
+pub fn fall_y(start_y: Coord, elapsed: u32, floor: Coord) -> Coord {
+    let drop = (elapsed * elapsed / FALL_DIVISOR) as Coord;
+    (start_y + drop).min(floor.max(start_y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_runs_three_two_one() {
+        assert_eq!(countdown_digit(0), Some(3));
+        assert_eq!(countdown_digit(999), Some(3));
+        assert_eq!(countdown_digit(1000), Some(2));
+        assert_eq!(countdown_digit(2999), Some(1));
+        assert_eq!(countdown_digit(3000), None);
+        assert_eq!(countdown_digit(u32::MAX), None);
+    }
+
+    #[test]
+    fn wipe_sweeps_across_then_ends() {
+        let at = |t| transition_frame(Transition::Wipe, t, 240);
+        assert_eq!(at(0), Some(TransitionFrame::Wipe(0)));
+        assert_eq!(at(TRANSITION_MS / 2), Some(TransitionFrame::Wipe(120)));
+        assert_eq!(at(TRANSITION_MS - 1), Some(TransitionFrame::Wipe(239)));
+        assert_eq!(at(TRANSITION_MS), None);
+    }
+
+    #[test]
+    fn slide_slows_as_it_lands() {
+        let shown = |t| match transition_frame(Transition::Slide, t, 240) {
+            Some(TransitionFrame::Slide(x)) => x,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(shown(0), 0);
+        let steps: Vec<u32> = (0..4)
+            .map(|i| shown((i + 1) * 100 - 1) - shown(i * 100))
+            .collect();
+        assert!(
+            steps.windows(2).all(|pair| pair[0] > pair[1]),
+            "{:?}",
+            steps
+        );
+        assert!(shown(TRANSITION_MS - 1) <= 240);
+    }
+
+    #[test]
+    fn fade_goes_through_black() {
+        let at = |t| transition_frame(Transition::Fade, t, 240);
+        let half = TRANSITION_MS / 2;
+        assert_eq!(
+            at(0),
+            Some(TransitionFrame::Fade {
+                alpha: 255,
+                new: false
+            })
+        );
+        assert_eq!(
+            at(half - 1),
+            Some(TransitionFrame::Fade {
+                alpha: 1,
+                new: false
+            })
+        );
+        assert_eq!(
+            at(half),
+            Some(TransitionFrame::Fade {
+                alpha: 0,
+                new: true
+            })
+        );
+        assert_eq!(
+            at(TRANSITION_MS - 1),
+            Some(TransitionFrame::Fade {
+                alpha: 253,
+                new: true
+            })
+        );
+        assert_eq!(at(TRANSITION_MS), None);
+    }
+
+    #[test]
+    fn flash_fades_back_to_brightness() {
+        assert_eq!(flash_alpha(0, 0xFF), 0);
+        assert_eq!(flash_alpha(FLASH_MS / 2, 200), 100);
+        assert_eq!(flash_alpha(FLASH_MS, 0xFF), 0xFF);
+        assert_eq!(flash_alpha(10 * FLASH_MS, 0x80), 0x80);
+    }
+
+    #[test]
+    fn flash_never_exceeds_brightness() {
+        for t in (0..=FLASH_MS).step_by(7) {
+            assert!(flash_alpha(t, 0xC0) <= 0xC0);
+        }
+    }
+
+    #[test]
+    fn fall_accelerates() {
+        let a = fall_y(0, 100, 1000) - fall_y(0, 0, 1000);
+        let b = fall_y(0, 200, 1000) - fall_y(0, 100, 1000);
+        let c = fall_y(0, 300, 1000) - fall_y(0, 200, 1000);
+        assert!(a < b && b < c);
+    }
+
+    #[test]
+    fn fall_starts_in_place_and_stops_at_the_floor() {
+        assert_eq!(fall_y(50, 0, 180), 50);
+        assert_eq!(fall_y(50, 600, 180), 180);
+        assert_eq!(fall_y(50, DEATH_MS, 180), 180);
+    }
+
+    #[test]
+    fn slowmo_eases_down_to_a_quarter_and_back() {
+        assert_eq!(slowmo_scale(0), FULL_SPEED);
+        assert_eq!(slowmo_scale(SLOWMO_IN_MS), SLOWMO_SCALE);
+        assert_eq!(slowmo_scale(SLOWMO_HOLD_MS - 1), SLOWMO_SCALE);
+        assert_eq!(slowmo_scale(SLOWMO_MS), FULL_SPEED);
+        assert_eq!(slowmo_scale(u32::MAX), FULL_SPEED);
+        let mut last = FULL_SPEED;
+        for t in 0..SLOWMO_HOLD_MS {
+            assert!(slowmo_scale(t) <= last);
+            last = slowmo_scale(t);
+        }
+    }
+
+    #[test]
+    fn slowed_death_still_ends_in_two_seconds() {
+        let (mut real, mut played) = (0, 0);
+        while played < DEATH_MS * FULL_SPEED {
+            played += slowmo_scale(real);
+            real += 1;
+        }
+        assert!(real > DEATH_MS && real < 2000, "{real} ms");
+    }
+
+    #[test]
+    fn bird_below_the_floor_does_not_jump_up() {
+        assert_eq!(fall_y(200, 0, 180), 200);
+        assert_eq!(fall_y(200, 600, 180), 200);
+    }
+
+    #[test]
+    fn the_fall_fits_in_the_death_animation() {
+        // A bird at the top of the playfield lands before the game-over screen
+        assert_eq!(fall_y(30, DEATH_MS, 180), 180);
+    }
+}
@@ -0,0 +1,354 @@
+//! BDF font import, for the build script
+//!
+//! Reads a font in the Glyph Bitmap Distribution Format (the X11 bitmap
+//! font format most pixel font editors export) and writes it out as Rust
+//! source for a `font::PropFont`. Every glyph in the file is kept; pick the
+//! subset when making the file. Each glyph's bounding box is placed in a
+//! cell of the font's full height, so offsets above and below the baseline
+//! survive, and columns left of the origin are dropped.
+//!
+//! Needs `std`; only built for tests and with the `bdf` feature.
+
+use std::fmt::{self, Write};
+use std::string::String;
+use std::vec::Vec;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BdfGlyph {
+    pub code: u32,
+    pub width: u32,
+    pub advance: u32,
+    // Full font height, each row `width` long, true where set
+    pub rows: Vec<Vec<bool>>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BdfFont {
+    pub ascent: u32,
+    pub descent: u32,
+    // Sorted by code point
+    pub glyphs: Vec<BdfGlyph>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BdfError {
+    pub line: usize,
+    pub what: &'static str,
+}
+
+impl fmt::Display for BdfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.what)
+    }
+}
+
+// A glyph as read, before it goes into its cell
+#[derive(Default)]
+struct Pending {
+    code: Option<u32>,
+    advance: u32,
+    bbx: (u32, u32, i32, i32),
+    bitmap: Vec<Vec<bool>>,
+}
+
+fn numbers<const N: usize>(words: &[&str], line: usize) -> Result<[i32; N], BdfError> {
+    let mut out = [0; N];
+    for (slot, word) in out.iter_mut().zip(words.iter().skip(1)) {
+        *slot = word.parse().map_err(|_| BdfError {
+            line,
+            what: "bad number",
+        })?;
+    }
+    if words.len() <= N {
+        return Err(BdfError {
+            line,
+            what: "missing number",
+        });
+    }
+    Ok(out)
+}
+
+pub fn parse(source: &str) -> Result<BdfFont, BdfError> {
+    let mut ascent = None;
+    let mut descent = None;
+    let mut bounding = None;
+    let mut glyphs = Vec::new();
+    let mut pending: Option<Pending> = None;
+    let mut in_bitmap = false;
+
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let Some(&keyword) = words.first() else {
+            continue;
+        };
+        if in_bitmap && keyword != "ENDCHAR" {
+            let glyph = pending.as_mut().ok_or(BdfError {
+                line,
+                what: "bitmap outside a glyph",
+            })?;
+            let row = u64::from_str_radix(keyword, 16).map_err(|_| BdfError {
+                line,
+                what: "bad bitmap row",
+            })?;
+            let bits = keyword.len() as u32 * 4;
+            if glyph.bbx.0 > bits || bits > 64 {
+                return Err(BdfError {
+                    line,
+                    what: "bitmap row does not match BBX",
+                });
+            }
+            glyph.bitmap.push(
+                (0..glyph.bbx.0)
+                    .map(|x| row & (1 << (bits - 1 - x)) != 0)
+                    .collect(),
+            );
+            continue;
+        }
+        match keyword {
+            "FONT_ASCENT" => ascent = Some(numbers::<1>(&words, line)?[0]),
+            "FONT_DESCENT" => descent = Some(numbers::<1>(&words, line)?[0]),
+            "FONTBOUNDINGBOX" => bounding = Some(numbers::<4>(&words, line)?),
+            "STARTCHAR" => pending = Some(Pending::default()),
+            "ENCODING" => {
+                if let Some(glyph) = pending.as_mut() {
+                    // -1 is an unencoded glyph, which nothing can ask for
+                    let code = numbers::<1>(&words, line)?[0];
+                    glyph.code = u32::try_from(code).ok();
+                }
+            }
+            "DWIDTH" => {
+                if let Some(glyph) = pending.as_mut() {
+                    glyph.advance = numbers::<2>(&words, line)?[0].max(0) as u32;
+                }
+            }
+            "BBX" => {
+                if let Some(glyph) = pending.as_mut() {
+                    let [w, h, x, y] = numbers::<4>(&words, line)?;
+                    glyph.bbx = (w.max(0) as u32, h.max(0) as u32, x, y);
+                }
+            }
+            "BITMAP" => in_bitmap = true,
+            "ENDCHAR" => {
+                in_bitmap = false;
+                let glyph = pending.take().ok_or(BdfError {
+                    line,
+                    what: "ENDCHAR without STARTCHAR",
+                })?;
+                if glyph.bitmap.len() != glyph.bbx.1 as usize {
+                    return Err(BdfError {
+                        line,
+                        what: "bitmap rows do not match BBX",
+                    });
+                }
+                glyphs.push(glyph);
+            }
+            _ => {}
+        }
+    }
+
+    // Without the properties, the bounding box says where the baseline is
+    let (ascent, descent) = match (ascent, descent, bounding) {
+        (Some(a), Some(d), _) => (a, d),
+        (_, _, Some([_, h, _, y])) => (h + y, -y),
+        _ => {
+            return Err(BdfError {
+                line: 0,
+                what: "no FONT_ASCENT/FONT_DESCENT or FONTBOUNDINGBOX",
+            })
+        }
+    };
+    let (ascent, descent) = (ascent.max(0) as u32, descent.max(0) as u32);
+
+    let mut glyphs: Vec<BdfGlyph> = glyphs
+        .into_iter()
+        .filter_map(|glyph| Some(place(glyph.code?, &glyph, ascent, descent)))
+        .collect();
+    glyphs.sort_by_key(|glyph| glyph.code);
+    glyphs.dedup_by_key(|glyph| glyph.code);
+    Ok(BdfFont {
+        ascent,
+        descent,
+        glyphs,
+    })
+}
+
+// Put a glyph's bounding box into a cell of the font's height, its origin
+// at the left edge on the baseline
+fn place(code: u32, glyph: &Pending, ascent: u32, descent: u32) -> BdfGlyph {
+    let (w, h, x_off, y_off) = glyph.bbx;
+    let height = ascent + descent;
+    let left = x_off.max(0) as u32;
+    let width = left + w.saturating_sub(x_off.min(0).unsigned_abs());
+    // Row of the cell the bounding box's top row lands in
+    let top = ascent as i32 - (y_off + h as i32);
+    let mut rows = vec![vec![false; width as usize]; height as usize];
+    for (y, row) in glyph.bitmap.iter().enumerate() {
+        let Ok(cell_y) = usize::try_from(top + y as i32) else {
+            continue;
+        };
+        let Some(cell_row) = rows.get_mut(cell_y) else {
+            continue;
+        };
+        for (x, &set) in row.iter().enumerate() {
+            let cell_x = x as i32 + x_off;
+            if set && cell_x >= 0 {
+                cell_row[cell_x as usize] = true;
+            }
+        }
+    }
+    BdfGlyph {
+        code,
+        width,
+        advance: glyph.advance,
+        rows,
+    }
+}
+
+// Rust source for a `static NAME: PropFont`, with `PropFont`, `GlyphRange`
+// and `Glyph` in scope where it is included
+pub fn to_rust(font: &BdfFont, name: &str, fallback: char) -> String {
+    let mut ranges: Vec<(u32, u32, usize)> = Vec::new();
+    let mut glyphs = String::new();
+    let mut bitmap: Vec<u8> = Vec::new();
+    for (i, glyph) in font.glyphs.iter().enumerate() {
+        match ranges.last_mut() {
+            Some((_, last, _)) if *last + 1 == glyph.code => *last = glyph.code,
+            _ => ranges.push((glyph.code, glyph.code, i)),
+        }
+        let _ = writeln!(
+            glyphs,
+            "        Glyph {{ width: {}, advance: {}, offset: {} }}, // U+{:04X}",
+            glyph.width,
+            glyph.advance,
+            bitmap.len(),
+            glyph.code
+        );
+        for row in &glyph.rows {
+            for chunk in row.chunks(8) {
+                let byte = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (x, &set)| byte | ((set as u8) << (7 - x)));
+                bitmap.push(byte);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "pub static {}: PropFont = PropFont {{", name);
+    let _ = writeln!(out, "    height: {},", font.ascent + font.descent);
+    let _ = writeln!(out, "    ascent: {},", font.ascent);
+    let _ = writeln!(out, "    ranges: &[");
+    for (first, last, glyph) in ranges {
+        let _ = writeln!(
+            out,
+            "        GlyphRange {{ first: 0x{:04X}, last: 0x{:04X}, glyph: {} }},",
+            first, last, glyph
+        );
+    }
+    let _ = writeln!(out, "    ],");
+    let _ = writeln!(out, "    glyphs: &[");
+    out.push_str(&glyphs);
+    let _ = writeln!(out, "    ],");
+    let _ = writeln!(out, "    bitmap: &[");
+    for line in bitmap.chunks(16) {
+        let bytes: Vec<String> = line.iter().map(|b| format!("0x{:02X}", b)).collect();
+        let _ = writeln!(out, "        {},", bytes.join(", "));
+    }
+    let _ = writeln!(out, "    ],");
+    let _ = writeln!(out, "    fallback: '\\u{{{:x}}}',", fallback as u32);
+    let _ = writeln!(out, "}};");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+STARTFONT 2.1
+FONT -test-sample
+SIZE 4 75 75
+FONTBOUNDINGBOX 3 4 0 -1
+STARTPROPERTIES 2
+FONT_ASCENT 3
+FONT_DESCENT 1
+ENDPROPERTIES
+CHARS 3
+STARTCHAR B
+ENCODING 66
+DWIDTH 3 0
+BBX 2 3 0 0
+BITMAP
+C0
+80
+C0
+ENDCHAR
+STARTCHAR A
+ENCODING 65
+DWIDTH 4 0
+BBX 3 4 0 -1
+BITMAP
+40
+A0
+E0
+A0
+ENDCHAR
+STARTCHAR dot
+ENCODING 8226
+DWIDTH 3 0
+BBX 1 1 1 1
+BITMAP
+80
+ENDCHAR
+ENDFONT
+";
+
+    fn row(bits: &str) -> Vec<bool> {
+        bits.chars().map(|c| c == '#').collect()
+    }
+
+    #[test]
+    fn glyphs_are_sorted_and_placed_in_the_cell() {
+        let font = parse(SAMPLE).unwrap();
+        assert_eq!((font.ascent, font.descent), (3, 1));
+        let codes: Vec<u32> = font.glyphs.iter().map(|g| g.code).collect();
+        assert_eq!(codes, [65, 66, 8226]);
+
+        // B sits on the baseline, leaving the descender row empty
+        let b = &font.glyphs[1];
+        assert_eq!((b.width, b.advance), (2, 3));
+        assert_eq!(b.rows, [row("##"), row("#."), row("##"), row("..")]);
+
+        // The dot is one column in and one row above the baseline
+        let dot = &font.glyphs[2];
+        assert_eq!(dot.width, 2);
+        assert_eq!(dot.rows, [row(".."), row(".#"), row(".."), row("..")]);
+    }
+
+    #[test]
+    fn rust_output_has_ranges_glyphs_and_packed_rows() {
+        let source = to_rust(&parse(SAMPLE).unwrap(), "SAMPLE", '?');
+        assert!(source.contains("pub static SAMPLE: PropFont"));
+        assert!(source.contains("GlyphRange { first: 0x0041, last: 0x0042, glyph: 0 }"));
+        assert!(source.contains("GlyphRange { first: 0x2022, last: 0x2022, glyph: 2 }"));
+        assert!(source.contains("Glyph { width: 2, advance: 3, offset: 4 }, // U+0042"));
+        // A's rows, then B's
+        assert!(source.contains("0x40, 0xA0, 0xE0, 0xA0, 0xC0, 0x80, 0xC0, 0x00"));
+        assert!(source.contains("fallback: '\\u{3f}'"));
+    }
+
+    #[test]
+    fn malformed_input_says_where() {
+        let broken = SAMPLE.replace("BBX 2 3 0 0", "BBX 2 4 0 0");
+        assert_eq!(
+            parse(&broken),
+            Err(BdfError {
+                line: 18,
+                what: "bitmap rows do not match BBX"
+            })
+        );
+        assert!(parse("STARTFONT 2.1\nENDFONT\n").is_err());
+    }
+}
@@ -0,0 +1,183 @@
+//! The bird's position and vertical speed
+//!
+//! Crashes are judged on a box inside the sprite (`body`), so a pipe
+//! grazing a transparent corner does not count. A bird starts with the
+//! whole sprite as its body; the firmware gives it the one measured for the
+//! bird art.
+
+use crate::config::*;
+use crate::rect::Rect;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Bird {
+    x: Coord,
+    y: Coord,
+    // Change in y over the last move, in pixels per frame
+    vy: Coord,
+    // The part of the sprite that counts for crashes, from its top left
+    body: Rect,
+    // Pixels off each side of the body while shrunk
+    inset: u32,
+    // Rows sunk a move when asked to stay put
+    gravity: Coord,
+}
+
+impl Bird {
+    pub const fn new(x: Coord, y: Coord) -> Self {
+        Bird {
+            x,
+            y,
+            vy: 0,
+            body: Bird::SPRITE,
+            inset: 0,
+            gravity: GRAVITY,
+        }
+    }
+
+    // The whole sprite, as a body
+    pub const SPRITE: Rect = Rect::new(0, 0, PLAYER_WIDTH, PLAYER_HEIGHT);
+
+    // The same bird with crashes judged on `body`, relative to the sprite
+    pub const fn with_body(self, body: Rect) -> Self {
+        Bird { body, ..self }
+    }
+
+    // The same bird sinking `gravity` rows a move instead of GRAVITY
+    pub const fn with_gravity(self, gravity: Coord) -> Self {
+        Bird { gravity, ..self }
+    }
+
+    pub fn body(&self) -> Rect {
+        self.body
+    }
+
+    // Follow the input to `new_y`; asked to stay put, the bird sinks by
+    // its gravity instead
+    pub fn move_to(&mut self, new_y: Coord) {
+        let old_y = self.y;
+        if self.y == new_y {
+            self.y += self.gravity;
+        } else {
+            self.y = new_y;
+        }
+        self.vy = self.y - old_y;
+    }
+
+    // Place the bird directly, ignoring gravity (death animation)
+    pub fn set_y(&mut self, y: Coord) {
+        self.vy = y - self.y;
+        self.y = y;
+    }
+
+    pub fn xy(&self) -> (Coord, Coord) {
+        (self.x, self.y)
+    }
+
+    // Where the bird covers on screen
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, PLAYER_WIDTH, PLAYER_HEIGHT)
+    }
+
+    // The same bird with its body `px` in from each side for crashes
+    pub fn shrunk(self, px: u32) -> Self {
+        Bird { inset: px, ..self }
+    }
+
+    // The box that counts for crashes: the body where the bird is, less
+    // any shrinking, which always leaves at least a pixel or two
+    pub fn hitbox(&self) -> Rect {
+        let body = self.body;
+        let inset = self.inset.min((body.w.min(body.h) / 2).saturating_sub(1));
+        Rect::new(
+            self.x + body.x + inset as Coord,
+            self.y + body.y + inset as Coord,
+            body.w - 2 * inset,
+            body.h - 2 * inset,
+        )
+    }
+
+    pub fn velocity(&self) -> Coord {
+        self.vy
+    }
+}
+
+impl Default for Bird {
+    fn default() -> Self {
+        Bird::new(INIT_PLAYER_POS_X, INIT_PLAYER_POS_Y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_at_rest_at_the_spawn_point() {
+        let bird = Bird::default();
+        assert_eq!(bird.xy(), (INIT_PLAYER_POS_X, INIT_PLAYER_POS_Y));
+        assert_eq!(bird.velocity(), 0);
+    }
+
+    #[test]
+    fn shrinking_keeps_the_box_centered() {
+        let bird = Bird::new(60, 100);
+        assert_eq!(bird.hitbox(), bird.rect());
+        let hitbox = bird.shrunk(6).hitbox();
+        assert_eq!(
+            hitbox,
+            Rect::new(66, 106, PLAYER_WIDTH - 12, PLAYER_HEIGHT - 12)
+        );
+        assert_eq!(bird.shrunk(100).hitbox().w, 2);
+    }
+
+    #[test]
+    fn body_is_judged_where_the_bird_is() {
+        let body = Rect::new(2, 6, 24, 18);
+        let bird = Bird::new(60, 100).with_body(body);
+        assert_eq!(bird.rect(), Rect::new(60, 100, PLAYER_WIDTH, PLAYER_HEIGHT));
+        assert_eq!(bird.hitbox(), Rect::new(62, 106, 24, 18));
+        assert_eq!(bird.shrunk(4).hitbox(), Rect::new(66, 110, 16, 10));
+        assert_eq!(bird.shrunk(100).hitbox().h, 2);
+    }
+
+    #[test]
+    fn moving_records_velocity() {
+        let mut bird = Bird::new(60, 100);
+        bird.move_to(80);
+        assert_eq!(bird.xy(), (60, 80));
+        assert_eq!(bird.velocity(), -20);
+        bird.move_to(95);
+        assert_eq!(bird.velocity(), 15);
+    }
+
+    #[test]
+    fn staying_put_applies_gravity() {
+        let mut bird = Bird::new(60, 100);
+        bird.move_to(100);
+        assert_eq!(bird.xy().1, 100 + GRAVITY);
+        assert_eq!(bird.velocity(), GRAVITY);
+        let mut bird = bird.with_gravity(3);
+        bird.move_to(bird.xy().1);
+        assert_eq!(bird.velocity(), 3);
+    }
+
+    #[test]
+    fn set_y_ignores_gravity() {
+        let mut bird = Bird::new(60, 100);
+        bird.set_y(100);
+        assert_eq!(bird.xy().1, 100);
+        assert_eq!(bird.velocity(), 0);
+        bird.set_y(140);
+        assert_eq!(bird.velocity(), 40);
+    }
+
+    #[test]
+    fn x_never_changes() {
+        let mut bird = Bird::new(42, 100);
+        for y in [10, 200, 50] {
+            bird.move_to(y);
+            bird.set_y(y + 1);
+        }
+        assert_eq!(bird.xy().0, 42);
+    }
+}
@@ -0,0 +1,393 @@
+//! The last things that happened, kept through a reset
+//!
+//! A `Ring` is laid out to sit in battery-backed SRAM as it is: the
+//! firmware writes game events and hardware errors into it as they happen,
+//! and after a reset nobody asked for (the watchdog, a brownout, a fault
+//! left to the watchdog) they are still there to read back. Each entry is
+//! three words written in one go, so a reset part way through costs that
+//! entry at worst. An entry the same as the one before only counts the one
+//! before up, so a sensor failing ten times a second does not push
+//! everything else out.
+//!
+//! `ResetCause` reads the reset flags in RCC_CSR.
+
+use core::fmt;
+
+use crate::state::GameState;
+
+const MAGIC: u32 = 0x424C_4B31; // "BLK1"
+
+// RCC_CSR reset flags
+const LPWRRSTF: u32 = 1 << 31;
+const WWDGRSTF: u32 = 1 << 30;
+const IWDGRSTF: u32 = 1 << 29;
+const SFTRSTF: u32 = 1 << 28;
+const PORRSTF: u32 = 1 << 27;
+const PINRSTF: u32 = 1 << 26;
+const BORRSTF: u32 = 1 << 25;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ResetCause {
+    PowerOn,
+    // The reset button, or the probe
+    Pin,
+    // SYSRESETREQ, e.g. a probe flashing new firmware
+    Software,
+    Watchdog,
+    WindowWatchdog,
+    // Entering standby or stop the wrong way
+    LowPower,
+    // The supply dipped below the brownout level
+    Brownout,
+    // No flag set, or flags cleared before they were read
+    Unknown,
+}
+
+impl ResetCause {
+    const ALL: [ResetCause; 8] = [
+        ResetCause::PowerOn,
+        ResetCause::Pin,
+        ResetCause::Software,
+        ResetCause::Watchdog,
+        ResetCause::WindowWatchdog,
+        ResetCause::LowPower,
+        ResetCause::Brownout,
+        ResetCause::Unknown,
+    ];
+
+    // A power-on also sets the pin and brownout flags, and a software reset
+    // the pin flag, so the one that says the most wins
+    pub fn from_csr(csr: u32) -> Self {
+        const ORDER: [(u32, ResetCause); 7] = [
+            (IWDGRSTF, ResetCause::Watchdog),
+            (WWDGRSTF, ResetCause::WindowWatchdog),
+            (LPWRRSTF, ResetCause::LowPower),
+            (PORRSTF, ResetCause::PowerOn),
+            (BORRSTF, ResetCause::Brownout),
+            (SFTRSTF, ResetCause::Software),
+            (PINRSTF, ResetCause::Pin),
+        ];
+        ORDER
+            .iter()
+            .find(|(flag, _)| csr & flag != 0)
+            .map_or(ResetCause::Unknown, |&(_, cause)| cause)
+    }
+
+    // A reset that cut the last session short
+    pub fn abnormal(self) -> bool {
+        matches!(
+            self,
+            ResetCause::Watchdog
+                | ResetCause::WindowWatchdog
+                | ResetCause::LowPower
+                | ResetCause::Brownout
+        )
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ResetCause::PowerOn => "power on",
+            ResetCause::Pin => "reset pin",
+            ResetCause::Software => "software",
+            ResetCause::Watchdog => "watchdog",
+            ResetCause::WindowWatchdog => "window watchdog",
+            ResetCause::LowPower => "low power",
+            ResetCause::Brownout => "brownout",
+            ResetCause::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Event {
+    // The firmware started, and why
+    Boot(ResetCause),
+    State { from: GameState, to: GameState },
+    // The score now
+    Point(u32),
+    // A crash, at this score
+    Crash(u32),
+    // A transfer to `address` on I2C `bus` (1 or 3) failed
+    I2c { bus: u8, address: u8 },
+    // New LTDC FIFO underruns, this many since boot
+    Underrun(u32),
+    // A fault stopped the firmware at this pc; 0 for a stack overflow
+    Fault(u32),
+}
+
+impl Event {
+    // Kind in the top byte, a small argument in the next, the rest in
+    // `value`
+    fn encode(self) -> (u32, u32) {
+        let (kind, arg, value) = match self {
+            Event::Boot(cause) => (1, cause as u8, 0),
+            Event::State { from, to } => (2, from as u8, to as u32),
+            Event::Point(score) => (3, 0, score),
+            Event::Crash(score) => (4, 0, score),
+            Event::I2c { bus, address } => (5, bus, address as u32),
+            Event::Underrun(total) => (6, 0, total),
+            Event::Fault(pc) => (7, 0, pc),
+        };
+        ((kind << 24) | (arg as u32) << 16, value)
+    }
+
+    fn decode(code: u32, value: u32) -> Option<Self> {
+        let arg = (code >> 16) as u8;
+        let state = |index: u32| GameState::ALL.get(index as usize).copied();
+        Some(match code >> 24 {
+            1 => Event::Boot(*ResetCause::ALL.get(arg as usize)?),
+            2 => Event::State {
+                from: state(arg as u32)?,
+                to: state(value)?,
+            },
+            3 => Event::Point(value),
+            4 => Event::Crash(value),
+            5 => Event::I2c {
+                bus: arg,
+                address: value as u8,
+            },
+            6 => Event::Underrun(value),
+            7 => Event::Fault(value),
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Event::Boot(cause) => write!(f, "boot ({})", cause.name()),
+            Event::State { from, to } => write!(f, "{} -> {}", from.name(), to.name()),
+            Event::Point(score) => write!(f, "point, score {}", score),
+            Event::Crash(score) => write!(f, "crash at {}", score),
+            Event::I2c { bus, address } => write!(f, "I2C{} {:#04x} failed", bus, address),
+            Event::Underrun(total) => write!(f, "LTDC underrun, {} so far", total),
+            Event::Fault(0) => write!(f, "stack overflow"),
+            Event::Fault(pc) => write!(f, "fault at {:08x}", pc),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Entry {
+    // Milliseconds since its boot
+    pub at: u32,
+    // The event, with how many times in a row it happened, less one, in
+    // the low half
+    code: u32,
+    value: u32,
+}
+
+impl Entry {
+    const EMPTY: Entry = Entry {
+        at: 0,
+        code: 0,
+        value: 0,
+    };
+
+    pub fn new(at: u32, event: Event) -> Self {
+        let (code, value) = event.encode();
+        Entry { at, code, value }
+    }
+
+    // None for memory that never held an entry
+    pub fn event(&self) -> Option<Event> {
+        Event::decode(self.code & !0xFFFF, self.value)
+    }
+
+    pub fn count(&self) -> u32 {
+        (self.code & 0xFFFF) + 1
+    }
+
+    fn repeats(&self, other: &Entry) -> bool {
+        self.code & !0xFFFF == other.code && self.value == other.value
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>8} ", self.at)?;
+        match self.event() {
+            Some(event) => write!(f, "{}", event)?,
+            None => write!(f, "? {:08x} {:08x}", self.code, self.value)?,
+        }
+        match self.count() {
+            1 => Ok(()),
+            n => write!(f, " x{}", n),
+        }
+    }
+}
+
+/// The last `N` entries, oldest overwritten first
+#[repr(C)]
+pub struct Ring<const N: usize> {
+    magic: u32,
+    // Where the next entry goes
+    next: u32,
+    len: u32,
+    entries: [Entry; N],
+}
+
+impl<const N: usize> Ring<N> {
+    pub const fn new() -> Self {
+        Ring {
+            magic: MAGIC,
+            next: 0,
+            len: 0,
+            entries: [Entry::EMPTY; N],
+        }
+    }
+
+    // Whether this is a ring `push` has been keeping; anything else found
+    // in the memory is garbage
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC && self.next < N as u32 && self.len <= N as u32
+    }
+
+    pub fn push(&mut self, at: u32, event: Event) {
+        let entry = Entry::new(at, event);
+        if let Some(last) = self.last_mut() {
+            if last.repeats(&entry) && last.count() < 0x1_0000 {
+                last.code += 1;
+                return;
+            }
+        }
+        self.entries[self.next as usize] = entry;
+        self.next = (self.next + 1) % N as u32;
+        self.len = (self.len + 1).min(N as u32);
+    }
+
+    fn last_mut(&mut self) -> Option<&mut Entry> {
+        if self.len == 0 {
+            return None;
+        }
+        let at = (self.next as usize + N - 1) % N;
+        Some(&mut self.entries[at])
+    }
+
+    pub fn last(&self) -> Option<&Entry> {
+        self.iter().last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        let first = (self.next as usize + N - self.len as usize) % N;
+        (0..self.len as usize).map(move |i| &self.entries[(first + i) % N])
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl<const N: usize> Default for Ring<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::ToString;
+    use std::vec::Vec;
+
+    fn events<const N: usize>(ring: &Ring<N>) -> Vec<Event> {
+        ring.iter().filter_map(Entry::event).collect()
+    }
+
+    #[test]
+    fn the_oldest_entries_go_first() {
+        let mut ring = Ring::<3>::new();
+        for score in 1..=5 {
+            ring.push(score * 10, Event::Point(score));
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(
+            events(&ring),
+            [Event::Point(3), Event::Point(4), Event::Point(5)]
+        );
+        assert_eq!(ring.last().unwrap().at, 50);
+    }
+
+    #[test]
+    fn repeats_count_up_instead_of_pushing_out() {
+        let mut ring = Ring::<4>::new();
+        let failed = Event::I2c {
+            bus: 1,
+            address: 0x68,
+        };
+        ring.push(0, Event::Boot(ResetCause::PowerOn));
+        for at in 0..100 {
+            ring.push(at, failed);
+        }
+        ring.push(200, Event::Crash(7));
+        assert_eq!(
+            events(&ring),
+            [Event::Boot(ResetCause::PowerOn), failed, Event::Crash(7)]
+        );
+        let entry = ring.iter().nth(1).unwrap();
+        assert_eq!(entry.count(), 100);
+        assert_eq!(entry.to_string(), "       0 I2C1 0x68 failed x100");
+    }
+
+    #[test]
+    fn every_event_comes_back_as_written() {
+        let all = [
+            Event::Boot(ResetCause::Watchdog),
+            Event::State {
+                from: GameState::Running,
+                to: GameState::Dying,
+            },
+            Event::Point(12),
+            Event::Crash(12),
+            Event::I2c {
+                bus: 3,
+                address: 0x41,
+            },
+            Event::Underrun(40),
+            Event::Fault(0x0800_1234),
+        ];
+        for event in all {
+            assert_eq!(Entry::new(5, event).event(), Some(event));
+        }
+    }
+
+    #[test]
+    fn garbage_is_not_a_ring() {
+        let mut ring = Ring::<4>::new();
+        assert!(ring.is_valid());
+        ring.next = 9;
+        assert!(!ring.is_valid());
+        ring.clear();
+        assert!(ring.is_valid() && ring.is_empty());
+        assert_eq!(Entry::EMPTY.event(), None);
+    }
+
+    #[test]
+    fn the_most_telling_reset_flag_wins() {
+        let power_on = PORRSTF | PINRSTF | BORRSTF;
+        assert_eq!(ResetCause::from_csr(power_on), ResetCause::PowerOn);
+        assert_eq!(
+            ResetCause::from_csr(SFTRSTF | PINRSTF),
+            ResetCause::Software
+        );
+        assert_eq!(
+            ResetCause::from_csr(IWDGRSTF | PINRSTF),
+            ResetCause::Watchdog
+        );
+        assert_eq!(ResetCause::from_csr(BORRSTF), ResetCause::Brownout);
+        assert_eq!(ResetCause::from_csr(0), ResetCause::Unknown);
+        assert!(ResetCause::Watchdog.abnormal());
+        assert!(!ResetCause::Pin.abnormal());
+    }
+}
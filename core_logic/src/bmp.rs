@@ -0,0 +1,296 @@
+//! Images decoded where they lie: Windows BMP files and raw RGB565
+//!
+//! Artwork flashed as a data blob is read straight out of memory a row at
+//! a time, so nothing is copied into RAM first and the firmware needs no
+//! rebuild to change it. BMP rows are padded to four bytes and stored
+//! bottom row first unless the height is negative; `row` hides both.
+//! Uncompressed 16-bit (RGB555, or RGB565 by bitfields), 24-bit and 32-bit
+//! files are understood. A raw blob is `RAW_MAGIC`, the width and the
+//! height as little-endian u16s, then little-endian RGB565 pixels top row
+//! first with no padding.
+
+use crate::color::Rgb565;
+
+pub const RAW_MAGIC: [u8; 4] = *b"R565";
+
+// BITMAPFILEHEADER is 14 bytes, BITMAPINFOHEADER the 40 after it
+const FILE_HEADER: usize = 14;
+const INFO_HEADER: usize = 40;
+const RAW_HEADER: usize = 8;
+// biCompression
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+// Largest image taken, either way; anything bigger is taken as a bad header
+const MAX_SIDE: u32 = 4096;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BmpError {
+    // Ends before the header or the pixels it promises
+    Short,
+    // Neither "BM" nor `RAW_MAGIC`
+    Magic,
+    // Sizes or offsets that make no sense
+    Header,
+    // RLE and the like
+    Compression(u32),
+    // Bits per pixel other than 16, 24 or 32
+    Depth(u16),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Format {
+    Rgb555,
+    Rgb565,
+    Bgr888,
+    Bgrx8888,
+}
+
+impl Format {
+    const fn bytes(self) -> usize {
+        match self {
+            Format::Rgb555 | Format::Rgb565 => 2,
+            Format::Bgr888 => 3,
+            Format::Bgrx8888 => 4,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Bitmap<'a> {
+    pub width: u32,
+    pub height: u32,
+    format: Format,
+    // Bytes from one stored row to the next
+    stride: usize,
+    bottom_up: bool,
+    pixels: &'a [u8],
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+impl<'a> Bitmap<'a> {
+    // A BMP file or a raw blob, told apart by the first bytes
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, BmpError> {
+        if bytes.starts_with(b"BM") {
+            Self::bmp(bytes)
+        } else if bytes.starts_with(&RAW_MAGIC) {
+            Self::raw(bytes)
+        } else {
+            Err(BmpError::Magic)
+        }
+    }
+
+    fn bmp(bytes: &'a [u8]) -> Result<Self, BmpError> {
+        let info = |at| u32_at(bytes, FILE_HEADER + at).ok_or(BmpError::Short);
+        let offset = u32_at(bytes, 10).ok_or(BmpError::Short)? as usize;
+        let info_size = info(0)? as usize;
+        let width = info(4)? as i32;
+        let height = info(8)? as i32;
+        let depth = u16_at(bytes, FILE_HEADER + 14).ok_or(BmpError::Short)?;
+        let compression = info(16)?;
+        if info_size < INFO_HEADER || offset < FILE_HEADER + info_size {
+            return Err(BmpError::Header);
+        }
+
+        let format = match (depth, compression) {
+            (16, BI_RGB) => Format::Rgb555,
+            (24, BI_RGB) => Format::Bgr888,
+            (32, BI_RGB) => Format::Bgrx8888,
+            // The masks follow the info header, or are part of a V4/V5 one
+            (16, BI_BITFIELDS) => {
+                let masks = [info(40)?, info(44)?, info(48)?];
+                match masks {
+                    [0xF800, 0x07E0, 0x001F] => Format::Rgb565,
+                    [0x7C00, 0x03E0, 0x001F] => Format::Rgb555,
+                    _ => return Err(BmpError::Compression(compression)),
+                }
+            }
+            (16 | 24 | 32, _) => return Err(BmpError::Compression(compression)),
+            _ => return Err(BmpError::Depth(depth)),
+        };
+
+        let (w, h) = (width.unsigned_abs(), height.unsigned_abs());
+        if width <= 0 || h == 0 || w > MAX_SIDE || h > MAX_SIDE {
+            return Err(BmpError::Header);
+        }
+        let stride = (w as usize * format.bytes()).next_multiple_of(4);
+        Self::over(bytes, offset, w, h, format, stride, height > 0)
+    }
+
+    fn raw(bytes: &'a [u8]) -> Result<Self, BmpError> {
+        let w = u16_at(bytes, 4).ok_or(BmpError::Short)? as u32;
+        let h = u16_at(bytes, 6).ok_or(BmpError::Short)? as u32;
+        if w == 0 || h == 0 {
+            return Err(BmpError::Header);
+        }
+        Self::over(
+            bytes,
+            RAW_HEADER,
+            w,
+            h,
+            Format::Rgb565,
+            w as usize * 2,
+            false,
+        )
+    }
+
+    // The image whose rows start at `offset`, if `bytes` holds them all
+    fn over(
+        bytes: &'a [u8],
+        offset: usize,
+        width: u32,
+        height: u32,
+        format: Format,
+        stride: usize,
+        bottom_up: bool,
+    ) -> Result<Self, BmpError> {
+        // The last row need not be padded out
+        let len = stride * (height as usize - 1) + width as usize * format.bytes();
+        let pixels = bytes
+            .get(offset..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(BmpError::Short)?;
+        Ok(Bitmap {
+            width,
+            height,
+            format,
+            stride,
+            bottom_up,
+            pixels,
+        })
+    }
+
+    // Decode row `y`, counted from the top, from column `x` on into `out`
+    // as RGB565; returns how many pixels went in, 0 off the image
+    pub fn row(&self, y: u32, x: u32, out: &mut [u16]) -> usize {
+        if y >= self.height || x >= self.width {
+            return 0;
+        }
+        let stored = if self.bottom_up {
+            self.height - 1 - y
+        } else {
+            y
+        };
+        let bytes = self.format.bytes();
+        let start = stored as usize * self.stride;
+        let row = &self.pixels[start + x as usize * bytes..start + self.width as usize * bytes];
+        let mut n = 0;
+        for (px, out) in row.chunks_exact(bytes).zip(out.iter_mut()) {
+            *out = match self.format {
+                Format::Rgb565 => u16::from_le_bytes([px[0], px[1]]),
+                Format::Rgb555 => {
+                    let v = u16::from_le_bytes([px[0], px[1]]);
+                    // Green gets its sixth bit from its top one
+                    ((v & 0x7FE0) << 1) | ((v >> 4) & 0x20) | (v & 0x1F)
+                }
+                Format::Bgr888 | Format::Bgrx8888 => Rgb565::from_rgb(px[2], px[1], px[0]).0,
+            };
+            n += 1;
+        }
+        n
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u16> {
+        let mut px = [0u16; 1];
+        (self.row(y, x, &mut px) == 1).then_some(px[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    // A BMP file of `rows` as stored, bottom row first unless `height` < 0
+    fn bmp(width: i32, height: i32, depth: u16, compression: u32, rows: &[u8]) -> Vec<u8> {
+        let masks: &[u32] = if compression == BI_BITFIELDS {
+            &[0xF800, 0x07E0, 0x001F]
+        } else {
+            &[]
+        };
+        let offset = (FILE_HEADER + INFO_HEADER + masks.len() * 4) as u32;
+        let mut file = Vec::new();
+        file.extend_from_slice(b"BM");
+        file.extend_from_slice(&(offset + rows.len() as u32).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&offset.to_le_bytes());
+        file.extend_from_slice(&(INFO_HEADER as u32).to_le_bytes());
+        file.extend_from_slice(&width.to_le_bytes());
+        file.extend_from_slice(&height.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&depth.to_le_bytes());
+        file.extend_from_slice(&compression.to_le_bytes());
+        file.extend_from_slice(&[0; 20]);
+        for mask in masks {
+            file.extend_from_slice(&mask.to_le_bytes());
+        }
+        file.extend_from_slice(rows);
+        file
+    }
+
+    fn rows_of(image: &Bitmap) -> Vec<Vec<u16>> {
+        (0..image.height)
+            .map(|y| {
+                let mut row = vec![0; image.width as usize];
+                assert_eq!(image.row(y, 0, &mut row), row.len());
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bottom_up_24_bit_rows_come_out_top_first_without_padding() {
+        // 1x2: stored bottom row (blue) first, each row padded to 4 bytes
+        let rows = [0xFF, 0, 0, 0, 0, 0, 0xFF, 0];
+        let file = bmp(1, 2, 24, BI_RGB, &rows);
+        let image = Bitmap::parse(&file).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(rows_of(&image), [[0xF800], [0x001F]]);
+        assert_eq!(image.row(2, 0, &mut [0; 1]), 0);
+    }
+
+    #[test]
+    fn top_down_565_and_555_match() {
+        let white_red = [0xFF, 0xFF, 0x00, 0xF8];
+        let file = bmp(2, -1, 16, BI_BITFIELDS, &white_red);
+        let image = Bitmap::parse(&file).unwrap();
+        assert_eq!(rows_of(&image), [[0xFFFF, 0xF800]]);
+
+        let white_red = [0xFF, 0x7F, 0x00, 0x7C];
+        let file = bmp(2, -1, 16, BI_RGB, &white_red);
+        let image = Bitmap::parse(&file).unwrap();
+        assert_eq!(rows_of(&image), [[0xFFFF, 0xF800]]);
+        assert_eq!(image.pixel(1, 0), Some(0xF800));
+        assert_eq!(image.pixel(2, 0), None);
+        let mut right = [0; 2];
+        assert_eq!(image.row(0, 1, &mut right), 1);
+        assert_eq!(right[0], 0xF800);
+    }
+
+    #[test]
+    fn raw_blobs_are_top_down_rgb565() {
+        let mut blob = Vec::from(RAW_MAGIC);
+        blob.extend_from_slice(&[1, 0, 2, 0, 0x1F, 0x00, 0xE0, 0x07]);
+        let image = Bitmap::parse(&blob).unwrap();
+        assert_eq!(rows_of(&image), [[0x001F], [0x07E0]]);
+        // A row wider than the buffer is cut short
+        assert_eq!(image.row(0, 0, &mut []), 0);
+    }
+
+    #[test]
+    fn bad_blobs_are_refused() {
+        assert_eq!(Bitmap::parse(&[0xFF; 64]).err(), Some(BmpError::Magic));
+        let file = bmp(4, 4, 24, BI_RGB, &[0; 12]);
+        assert_eq!(Bitmap::parse(&file).err(), Some(BmpError::Short));
+        let file = bmp(1, 1, 8, BI_RGB, &[0; 4]);
+        assert_eq!(Bitmap::parse(&file).err(), Some(BmpError::Depth(8)));
+        let file = bmp(1, 1, 24, 1, &[0; 4]);
+        assert_eq!(Bitmap::parse(&file).err(), Some(BmpError::Compression(1)));
+    }
+}
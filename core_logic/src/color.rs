@@ -0,0 +1,196 @@
+//! Pixel colors
+//!
+//! The game draws in RGB565, the panel's native format; LTDC layers and the
+//! menu overlay may be ARGB8888. `Rgb565` and `Argb8888` wrap the raw pixel
+//! values so the two can't be mixed up, and carry the conversions and the
+//! per-channel arithmetic used for fades, dimming and gradients. Everything
+//! is `const fn` so palettes can be built at compile time.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Rgb565(pub u16);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Argb8888(pub u32);
+
+// `t` of the way from `a` to `b`, with t = 0..=255 (255 lands on `b`)
+pub const fn lerp_u8(a: u8, b: u8, t: u8) -> u8 {
+    (a as i32 + (b as i32 - a as i32) * t as i32 / 255) as u8
+}
+
+impl Rgb565 {
+    pub const BLACK: Rgb565 = Rgb565(0x0000);
+    pub const WHITE: Rgb565 = Rgb565(0xFFFF);
+    pub const RED: Rgb565 = Rgb565(0xF800);
+    pub const GREEN: Rgb565 = Rgb565(0x07E0);
+    pub const BLUE: Rgb565 = Rgb565(0x001F);
+    pub const YELLOW: Rgb565 = Rgb565(0xFFE0);
+    pub const CYAN: Rgb565 = Rgb565(0x07FF);
+    pub const MAGENTA: Rgb565 = Rgb565(0xF81F);
+    pub const GRAY: Rgb565 = Rgb565(0x8410);
+
+    // From 8-bit channels; the low bits are dropped
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Rgb565(((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3))
+    }
+
+    // Channels widened to 8 bits by replicating the high bits, so full
+    // scale stays 255 and converting back gives the same color
+    pub const fn to_rgb(self) -> (u8, u8, u8) {
+        let r = (self.0 >> 11) as u8 & 0x1F;
+        let g = (self.0 >> 5) as u8 & 0x3F;
+        let b = self.0 as u8 & 0x1F;
+        (
+            (r << 3) | (r >> 2),
+            (g << 2) | (g >> 4),
+            (b << 3) | (b >> 2),
+        )
+    }
+
+    // Opaque
+    pub const fn to_argb8888(self) -> Argb8888 {
+        let (r, g, b) = self.to_rgb();
+        Argb8888::new(0xFF, r, g, b)
+    }
+
+    // Half brightness, for scanlines
+    pub const fn halve(self) -> Self {
+        Rgb565((self.0 >> 1) & 0x7BEF)
+    }
+
+    // Halfway between the two, per channel
+    pub const fn average(self, other: Self) -> Self {
+        Rgb565(((self.0 & 0xF7DE) >> 1) + ((other.0 & 0xF7DE) >> 1))
+    }
+
+    // `t` of the way to `other`; see `lerp_u8`
+    pub const fn lerp(self, other: Self, t: u8) -> Self {
+        let (r0, g0, b0) = self.to_rgb();
+        let (r1, g1, b1) = other.to_rgb();
+        Rgb565::from_rgb(lerp_u8(r0, r1, t), lerp_u8(g0, g1, t), lerp_u8(b0, b1, t))
+    }
+}
+
+impl Argb8888 {
+    pub const BLACK: Argb8888 = Argb8888(0xFF00_0000);
+    pub const WHITE: Argb8888 = Argb8888(0xFFFF_FFFF);
+    pub const TRANSPARENT: Argb8888 = Argb8888(0x0000_0000);
+
+    pub const fn new(a: u8, r: u8, g: u8, b: u8) -> Self {
+        Argb8888(((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+    }
+
+    pub const fn alpha(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub const fn to_rgb(self) -> (u8, u8, u8) {
+        ((self.0 >> 16) as u8, (self.0 >> 8) as u8, self.0 as u8)
+    }
+
+    // Alpha is dropped and the channels truncated
+    pub const fn to_rgb565(self) -> Rgb565 {
+        let (r, g, b) = self.to_rgb();
+        Rgb565::from_rgb(r, g, b)
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Argb8888((self.0 & 0x00FF_FFFF) | ((a as u32) << 24))
+    }
+
+    // Half brightness, keeping alpha
+    pub const fn halve(self) -> Self {
+        Argb8888((self.0 & 0xFF00_0000) | ((self.0 >> 1) & 0x007F_7F7F))
+    }
+
+    // `t` of the way to `other`, alpha included; see `lerp_u8`
+    pub const fn lerp(self, other: Self, t: u8) -> Self {
+        let (r0, g0, b0) = self.to_rgb();
+        let (r1, g1, b1) = other.to_rgb();
+        Argb8888::new(
+            lerp_u8(self.alpha(), other.alpha(), t),
+            lerp_u8(r0, r1, t),
+            lerp_u8(g0, g1, t),
+            lerp_u8(b0, b1, t),
+        )
+    }
+
+    // This color painted over `dst` with its own alpha, the way LTDC blends
+    // a layer with constant alpha 0xFF. The result keeps `dst`'s alpha.
+    pub const fn blend_over(self, dst: Self) -> Self {
+        dst.lerp(self, self.alpha()).with_alpha(dst.alpha())
+    }
+}
+
+impl From<Rgb565> for Argb8888 {
+    fn from(color: Rgb565) -> Self {
+        color.to_argb8888()
+    }
+}
+
+impl From<Argb8888> for Rgb565 {
+    fn from(color: Argb8888) -> Self {
+        color.to_rgb565()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb565_round_trips_through_argb8888() {
+        for raw in [0x0000, 0xFFFF, 0x9F5E, 0x10A6, 0x8410, 0x0821, 0xF81F] {
+            let color = Rgb565(raw);
+            assert_eq!(color.to_argb8888().to_rgb565(), color);
+        }
+    }
+
+    #[test]
+    fn full_scale_widens_to_255() {
+        assert_eq!(Rgb565::WHITE.to_argb8888(), Argb8888::WHITE);
+        assert_eq!(Rgb565::BLACK.to_argb8888(), Argb8888::BLACK);
+        assert_eq!(Rgb565::RED.to_rgb(), (255, 0, 0));
+        assert_eq!(Rgb565::GREEN.to_rgb(), (0, 255, 0));
+    }
+
+    #[test]
+    fn argb8888_truncates_to_rgb565() {
+        assert_eq!(Argb8888(0x80FF_0000).to_rgb565(), Rgb565::RED);
+        assert_eq!(Argb8888(0xFF07_0307).to_rgb565(), Rgb565::BLACK);
+        assert_eq!(Argb8888(0xFF10_1830).to_rgb565(), Rgb565(0x10C6));
+    }
+
+    #[test]
+    fn lerp_hits_both_ends() {
+        assert_eq!(lerp_u8(10, 200, 0), 10);
+        assert_eq!(lerp_u8(10, 200, 255), 200);
+        assert_eq!(lerp_u8(200, 10, 255), 10);
+        assert_eq!(Rgb565::BLACK.lerp(Rgb565::WHITE, 255), Rgb565::WHITE);
+        assert_eq!(
+            Argb8888::TRANSPARENT.lerp(Argb8888::WHITE, 0),
+            Argb8888::TRANSPARENT
+        );
+    }
+
+    #[test]
+    fn lerp_halfway_matches_average() {
+        let a = Rgb565(0x9F5E);
+        let b = Rgb565::BLACK;
+        assert_eq!(a.lerp(b, 128), a.average(b));
+        assert_eq!(Rgb565::WHITE.average(Rgb565::BLACK), Rgb565(0x7BEF));
+    }
+
+    #[test]
+    fn halve_keeps_alpha() {
+        assert_eq!(Argb8888(0xFFFF_FFFF).halve(), Argb8888(0xFF7F_7F7F));
+        assert_eq!(Rgb565::WHITE.halve(), Rgb565(0x7BEF));
+    }
+
+    #[test]
+    fn blend_over_weights_by_source_alpha() {
+        let dst = Argb8888::BLACK;
+        assert_eq!(Argb8888(0xFFFF_FFFF).blend_over(dst), Argb8888::WHITE);
+        assert_eq!(Argb8888(0x00FF_FFFF).blend_over(dst), Argb8888::BLACK);
+        assert_eq!(Argb8888(0x80FF_0000).blend_over(dst), Argb8888(0xFF80_0000));
+    }
+}
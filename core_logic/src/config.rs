@@ -0,0 +1,48 @@
+//! Screen geometry and gameplay tuning
+
+pub use crate::geometry::Orientation;
+pub use crate::rect::Rect;
+
+pub type Coord = i32;
+
+// The panel in its native portrait layout
+pub const PANEL_WIDTH: u32 = 240;
+pub const PANEL_HEIGHT: u32 = 320;
+
+// How gameplay is laid out on the panel at startup
+pub const ORIENTATION: Orientation = Orientation::Portrait;
+
+// The game's screen, as the player holds it; LCD_WIDTH and LCD_HEIGHT are
+// the names the game code uses for it
+pub const GAME_WIDTH: u32 = ORIENTATION.game_size(PANEL_WIDTH, PANEL_HEIGHT).0;
+pub const GAME_HEIGHT: u32 = ORIENTATION.game_size(PANEL_WIDTH, PANEL_HEIGHT).1;
+pub const LCD_WIDTH: u32 = GAME_WIDTH;
+pub const LCD_HEIGHT: u32 = GAME_HEIGHT;
+// Width of a column of the playfield's tile map; obstacles fill two
+pub const TILE: u32 = 16;
+pub const OBSTACLE_WIDTH: u32 = 2 * TILE;
+// Height of the opening in the full-screen game's obstacles
+pub const OBSTACLE_GAP: u32 = 50;
+
+pub const SCORE_BOARD_HEIGHT: u32 = 30;
+pub const PLANTS_HEIGHT: u32 = 30;
+
+pub const LCD_BIGIN: Coord = 0;
+pub const LCD_END: Coord = LCD_WIDTH as Coord;
+
+pub const INIT_PLAYER_POS_X: Coord = 60;
+pub const INIT_PLAYER_POS_Y: Coord = (SCORE_BOARD_HEIGHT + 10) as Coord;
+pub const PLAYER_WIDTH: u32 = 30;
+pub const PLAYER_HEIGHT: u32 = 30;
+
+pub const GRAVITY: i32 = 0;
+
+// How far one button (or agent) flap lifts the bird
+pub const FLAP_LIFT: Coord = 20;
+
+pub const GROUND_Y_POS: Coord = 210;
+
+pub const PLAYER_Y_MIN: Coord = SCORE_BOARD_HEIGHT as Coord;
+pub const PLAYER_Y_MAX: Coord = (LCD_HEIGHT - PLANTS_HEIGHT - PLAYER_HEIGHT) as Coord;
+
+pub const SPEED: u32 = 2;
@@ -0,0 +1,242 @@
+//! Control schemes: how input moves the bird
+//!
+//! Input devices report a height and whether they are pressed (tilted past
+//! the threshold, a finger down, a button flap). `Controls` turns that into
+//! where the bird goes each tick, in one of three ways:
+//!
+//! - `DirectTilt` puts the bird where the input points while it is pressed,
+//!   the game's original feel.
+//! - `FlapOnTap` is the classic game: each new press is an upward kick and
+//!   the bird falls in between, with the height the input points at unused.
+//! - `HybridAssist` kicks on a press like `FlapOnTap`, and while the press
+//!   is held eases the bird toward where the input points instead of
+//!   letting it fall.
+//!
+//! Speeds are in sixteenths of a pixel per tick, like the particles.
+
+use crate::config::Coord;
+use crate::lang::Msg;
+
+// Fractional bits of the bird's speed and position here
+const SUBPIXEL_SHIFT: u32 = 4;
+// Upward speed a flap gives
+const FLAP_SPEED: Coord = 80;
+// Added to the speed each tick while falling, and the fastest fall
+const FALL_ACCEL: Coord = 6;
+const MAX_FALL: Coord = 96;
+// Hybrid easing toward the input: speed per pixel still to go, the change
+// in speed per tick and the fastest it goes there
+const ASSIST_GAIN: Coord = 4;
+const ASSIST_ACCEL: Coord = 8;
+const ASSIST_MAX: Coord = 48;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ControlScheme {
+    FlapOnTap,
+    DirectTilt,
+    HybridAssist,
+}
+
+impl ControlScheme {
+    pub const ALL: [ControlScheme; 3] = [
+        ControlScheme::DirectTilt,
+        ControlScheme::FlapOnTap,
+        ControlScheme::HybridAssist,
+    ];
+
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ControlScheme::FlapOnTap),
+            1 => Some(ControlScheme::DirectTilt),
+            2 => Some(ControlScheme::HybridAssist),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ControlScheme::FlapOnTap => "flap",
+            ControlScheme::DirectTilt => "tilt",
+            ControlScheme::HybridAssist => "hybrid",
+        }
+    }
+
+    // Its name on screen, in the player's language
+    pub fn msg(self) -> Msg {
+        match self {
+            ControlScheme::FlapOnTap => Msg::Flap,
+            ControlScheme::DirectTilt => Msg::Tilt,
+            ControlScheme::HybridAssist => Msg::Hybrid,
+        }
+    }
+
+    // The next one in ALL, for a menu to step through
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// One player's controls between ticks
+#[derive(Copy, Clone, Debug)]
+pub struct Controls {
+    scheme: ControlScheme,
+    was_pressed: bool,
+    // Where the input points while pressed
+    target: Option<Coord>,
+    // DirectTilt: the target is taken on the next tick only
+    fresh: bool,
+    // FlapOnTap and HybridAssist: a flap waiting for the next tick
+    flap: bool,
+    vy: Coord,
+    frac: Coord,
+}
+
+impl Controls {
+    pub const fn new(scheme: ControlScheme) -> Self {
+        Controls {
+            scheme,
+            was_pressed: false,
+            target: None,
+            fresh: false,
+            flap: false,
+            vy: 0,
+            frac: 0,
+        }
+    }
+
+    pub fn scheme(&self) -> ControlScheme {
+        self.scheme
+    }
+
+    // Switch schemes, starting from rest
+    pub fn set_scheme(&mut self, scheme: ControlScheme) {
+        *self = Controls::new(scheme);
+    }
+
+    // Take this frame's input: the height it points at and whether it is
+    // pressed. True when this is a new press, for the flap sound.
+    pub fn input(&mut self, (y, pressed): (Coord, bool)) -> bool {
+        let started = pressed && !self.was_pressed;
+        self.was_pressed = pressed;
+        self.target = pressed.then_some(y);
+        self.fresh = pressed;
+        self.flap |= started;
+        started
+    }
+
+    // Where the bird at `y` goes this tick; None to leave it where it is
+    pub fn tick(&mut self, y: Coord) -> Option<Coord> {
+        match self.scheme {
+            ControlScheme::DirectTilt => {
+                let fresh = core::mem::take(&mut self.fresh);
+                self.target.filter(|_| fresh)
+            }
+            ControlScheme::FlapOnTap => {
+                self.kick_or_fall();
+                Some(self.advance(y))
+            }
+            ControlScheme::HybridAssist => {
+                match self.target {
+                    Some(target) if !self.flap => {
+                        let want = ((target - y) * ASSIST_GAIN).clamp(-ASSIST_MAX, ASSIST_MAX);
+                        self.vy += (want - self.vy).clamp(-ASSIST_ACCEL, ASSIST_ACCEL);
+                    }
+                    _ => self.kick_or_fall(),
+                }
+                Some(self.advance(y))
+            }
+        }
+    }
+
+    fn kick_or_fall(&mut self) {
+        if core::mem::take(&mut self.flap) {
+            self.vy = -FLAP_SPEED;
+        } else {
+            self.vy = (self.vy + FALL_ACCEL).min(MAX_FALL);
+        }
+    }
+
+    fn advance(&mut self, y: Coord) -> Coord {
+        let pos = (y << SUBPIXEL_SHIFT) + self.frac + self.vy;
+        self.frac = pos & ((1 << SUBPIXEL_SHIFT) - 1);
+        pos >> SUBPIXEL_SHIFT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run `ticks` ticks from `y` with no new input, clamped like the game
+    fn run(controls: &mut Controls, mut y: Coord, ticks: u32) -> Coord {
+        for _ in 0..ticks {
+            if let Some(next) = controls.tick(y) {
+                y = next.clamp(0, 200);
+            }
+        }
+        y
+    }
+
+    #[test]
+    fn direct_tilt_goes_where_pointed_once_per_frame() {
+        let mut controls = Controls::new(ControlScheme::DirectTilt);
+        assert!(controls.input((50, true)));
+        assert_eq!(controls.tick(100), Some(50));
+        assert_eq!(controls.tick(50), None);
+        assert!(!controls.input((60, true)));
+        assert_eq!(controls.tick(50), Some(60));
+        controls.input((70, false));
+        assert_eq!(controls.tick(60), None);
+    }
+
+    #[test]
+    fn a_flap_kicks_up_then_the_bird_falls() {
+        let mut controls = Controls::new(ControlScheme::FlapOnTap);
+        controls.input((0, true));
+        let top = run(&mut controls, 100, 10);
+        assert!(top < 100);
+        let later = run(&mut controls, top, 30);
+        assert!(later > top);
+    }
+
+    #[test]
+    fn holding_is_one_flap() {
+        let mut held = Controls::new(ControlScheme::FlapOnTap);
+        let mut tapped = Controls::new(ControlScheme::FlapOnTap);
+        let (mut y_held, mut y_tapped) = (100, 100);
+        for frame in 0..20 {
+            held.input((0, true));
+            tapped.input((0, frame % 4 == 0));
+            y_held = run(&mut held, y_held, 1);
+            y_tapped = run(&mut tapped, y_tapped, 1);
+        }
+        assert!(y_tapped < y_held);
+    }
+
+    #[test]
+    fn hybrid_eases_to_the_input_and_falls_without_it() {
+        let mut controls = Controls::new(ControlScheme::HybridAssist);
+        let mut y = 150;
+        for _ in 0..60 {
+            controls.input((80, true));
+            y = run(&mut controls, y, 1);
+        }
+        assert!((y - 80).abs() <= 2, "settled at {}", y);
+        controls.input((80, false));
+        assert!(run(&mut controls, y, 20) > y);
+    }
+
+    #[test]
+    fn schemes_round_trip_and_cycle() {
+        for scheme in ControlScheme::ALL {
+            assert_eq!(ControlScheme::from_u32(scheme as u32), Some(scheme));
+        }
+        assert_eq!(ControlScheme::from_u32(3), None);
+        let mut scheme = ControlScheme::DirectTilt;
+        for _ in 0..ControlScheme::ALL.len() {
+            scheme = scheme.next();
+        }
+        assert_eq!(scheme, ControlScheme::DirectTilt);
+    }
+}
@@ -0,0 +1,226 @@
+//! Custom courses: obstacle openings placed by hand
+//!
+//! A `Pattern` lists where the opening of each obstacle in turn sits, as
+//! laid out in the course editor. Played as a `GameMode` every obstacle is
+//! a static pipe with the lane's opening moved to the pattern's next row,
+//! and once the pattern runs out it starts over. Rows count down from the
+//! top of the playfield to the top of the opening, which keeps the lane's
+//! height.
+//!
+//! Stored as `WORDS` words: the number of steps, then the rows four to a
+//! word, the first in the low byte.
+
+use crate::config::*;
+use crate::lane::Lane;
+use crate::mode::GameMode;
+use crate::obstacle::ObstacleKind;
+
+pub const MAX_STEPS: usize = 32;
+pub const WORDS: usize = 1 + MAX_STEPS / 4;
+// Lowest row the opening can start at in the full-screen playfield
+pub const MAX_ROW: u8 =
+    (Lane::FULL.field().h as Coord - (Lane::FULL.gap_bottom - Lane::FULL.gap_top)) as u8;
+// Where the classic game's opening sits
+pub const DEFAULT_ROW: u8 = (Lane::FULL.gap_top - Lane::FULL.score_height) as u8;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Pattern {
+    len: u8,
+    rows: [u8; MAX_STEPS],
+}
+
+impl Pattern {
+    pub const EMPTY: Pattern = Pattern {
+        len: 0,
+        rows: [0; MAX_STEPS],
+    };
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == MAX_STEPS
+    }
+
+    pub fn rows(&self) -> &[u8] {
+        &self.rows[..self.len()]
+    }
+
+    pub fn row(&self, step: usize) -> Option<u8> {
+        self.rows().get(step).copied()
+    }
+
+    // Add a step at the end; false when there is no room
+    pub fn push(&mut self, row: u8) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.rows[self.len()] = row.min(MAX_ROW);
+        self.len += 1;
+        true
+    }
+
+    // Take the last step off
+    pub fn pop(&mut self) -> Option<u8> {
+        let row = self.rows().last().copied()?;
+        self.len -= 1;
+        Some(row)
+    }
+
+    // Move the opening of `step`, kept inside the playfield
+    pub fn set(&mut self, step: usize, row: u8) {
+        if step < self.len() {
+            self.rows[step] = row.min(MAX_ROW);
+        }
+    }
+
+    pub fn to_words(&self) -> [u32; WORDS] {
+        let mut words = [0; WORDS];
+        words[0] = self.len as u32;
+        for (word, rows) in words[1..].iter_mut().zip(self.rows.chunks_exact(4)) {
+            *word = u32::from_le_bytes([rows[0], rows[1], rows[2], rows[3]]);
+        }
+        words
+    }
+
+    // None unless `words` is a pattern `to_words` could have made
+    pub fn from_words(words: &[u32]) -> Option<Self> {
+        let (&len, rest) = words.split_first()?;
+        if len as usize > MAX_STEPS || rest.len() != WORDS - 1 {
+            return None;
+        }
+        let mut pattern = Pattern::EMPTY;
+        for (rows, word) in pattern.rows.chunks_exact_mut(4).zip(rest) {
+            rows.copy_from_slice(&word.to_le_bytes());
+        }
+        pattern.len = len as u8;
+        if pattern.rows().iter().any(|&row| row > MAX_ROW) {
+            return None;
+        }
+        Some(pattern)
+    }
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Pattern::EMPTY
+    }
+}
+
+impl GameMode for Pattern {
+    fn name(&self) -> &'static str {
+        "course"
+    }
+
+    fn first(&self) -> ObstacleKind {
+        ObstacleKind::Static
+    }
+
+    fn next(&self, _current: ObstacleKind, _score: u32, _roll: u32) -> ObstacleKind {
+        ObstacleKind::Static
+    }
+
+    // The steps in order, over and over; an empty pattern leaves the
+    // opening where the lane has it
+    fn gap(&self, index: u32) -> Option<Coord> {
+        if self.is_empty() {
+            return None;
+        }
+        self.row(index as usize % self.len()).map(Coord::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::ObstaclePair;
+
+    fn pattern(rows: &[u8]) -> Pattern {
+        let mut pattern = Pattern::EMPTY;
+        for &row in rows {
+            assert!(pattern.push(row));
+        }
+        pattern
+    }
+
+    #[test]
+    fn rows_stay_inside_the_playfield() {
+        let mut course = pattern(&[0, 255]);
+        assert_eq!(course.rows(), [0, MAX_ROW]);
+        course.set(0, 200);
+        assert_eq!(course.row(0), Some(MAX_ROW));
+        course.set(5, 10);
+        assert_eq!(course.len(), 2);
+        assert_eq!(MAX_ROW, 130);
+        assert_eq!(DEFAULT_ROW, 100);
+    }
+
+    #[test]
+    fn holds_up_to_max_steps() {
+        let mut course = Pattern::EMPTY;
+        for step in 0..MAX_STEPS {
+            assert!(course.push(step as u8));
+        }
+        assert!(course.is_full());
+        assert!(!course.push(1));
+        assert_eq!(course.pop(), Some(MAX_STEPS as u8 - 1));
+        assert_eq!(course.len(), MAX_STEPS - 1);
+        assert_eq!(Pattern::default().pop(), None);
+    }
+
+    #[test]
+    fn words_round_trip() {
+        let course = pattern(&[10, 20, 30, 40, 50, MAX_ROW]);
+        let words = course.to_words();
+        assert_eq!(words[0], 6);
+        assert_eq!(words[1], 0x281E_140A);
+        assert_eq!(Pattern::from_words(&words), Some(course));
+        assert_eq!(
+            Pattern::from_words(&Pattern::EMPTY.to_words()),
+            Some(Pattern::EMPTY)
+        );
+    }
+
+    #[test]
+    fn bad_words_are_refused() {
+        let mut words = pattern(&[1, 2]).to_words();
+        assert_eq!(Pattern::from_words(&words[..WORDS - 1]), None);
+        words[1] = 0xFF;
+        assert_eq!(Pattern::from_words(&words), None);
+        words[1] = 0;
+        words[0] = MAX_STEPS as u32 + 1;
+        assert_eq!(Pattern::from_words(&words), None);
+        assert_eq!(Pattern::from_words(&[]), None);
+    }
+
+    #[test]
+    fn plays_the_steps_over_and_over() {
+        let course = pattern(&[0, 60, 130]);
+        let gaps: std::vec::Vec<_> = (0..5).map(|i| course.gap(i)).collect();
+        assert_eq!(gaps, [Some(0), Some(60), Some(130), Some(0), Some(60)]);
+        assert_eq!(Pattern::EMPTY.gap(3), None);
+    }
+
+    #[test]
+    fn obstacles_follow_the_pattern() {
+        let course = pattern(&[0, MAX_ROW]);
+        let field = Lane::FULL.field();
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.start_with(&course);
+        assert_eq!(pair.get_gap(), (LCD_END, field.y, field.y + 50));
+        pair.set_speed(LCD_END as u32);
+        pair.advance();
+        assert!(pair.wrap_with(0, &course));
+        let (_, top, bottom) = pair.get_gap();
+        assert_eq!(bottom, field.y + field.h as Coord);
+        assert_eq!(top, field.y + MAX_ROW as Coord);
+        pair.advance();
+        assert!(pair.wrap_with(1, &course));
+        assert_eq!(pair.get_gap().1, field.y);
+    }
+}
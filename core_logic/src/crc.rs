@@ -0,0 +1,74 @@
+//! The STM32 CRC unit's checksum, in software
+//!
+//! The CRC peripheral computes CRC-32/MPEG-2 over 32-bit words, most
+//! significant bit first, with no final inversion. `mpeg2` gives the same
+//! result on the host, so the build script can work out what the firmware
+//! should read back from the hardware for a table in flash. Byte tables go
+//! in as little-endian words (`le_words`), the last one padded with zeros.
+
+const POLY: u32 = 0x04C1_1DB7;
+
+// What the CRC unit reads back after being reset and fed `words`
+pub fn mpeg2(words: impl IntoIterator<Item = u32>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for word in words {
+        crc ^= word;
+        for _ in 0..32 {
+            let mask = (crc >> 31).wrapping_neg();
+            crc = (crc << 1) ^ (POLY & mask);
+        }
+    }
+    crc
+}
+
+// `bytes` four at a time as little-endian words, zero-padded at the end
+pub fn le_words(bytes: impl IntoIterator<Item = u8>) -> impl Iterator<Item = u32> {
+    let mut bytes = bytes.into_iter().peekable();
+    core::iter::from_fn(move || {
+        bytes.peek()?;
+        let mut word = [0u8; 4];
+        for (slot, byte) in word.iter_mut().zip(&mut bytes) {
+            *slot = byte;
+        }
+        Some(u32::from_le_bytes(word))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    // CRC-32/MPEG-2 a byte at a time, as the catalogues define it
+    fn mpeg2_bytes(bytes: &[u8]) -> u32 {
+        let mut crc = 0xFFFF_FFFFu32;
+        for &byte in bytes {
+            crc ^= (byte as u32) << 24;
+            for _ in 0..8 {
+                let mask = (crc >> 31).wrapping_neg();
+                crc = (crc << 1) ^ (POLY & mask);
+            }
+        }
+        crc
+    }
+
+    #[test]
+    fn matches_the_catalogue_check_value() {
+        assert_eq!(mpeg2_bytes(b"123456789"), 0x0376_E6E7);
+    }
+
+    #[test]
+    fn a_word_goes_in_most_significant_byte_first() {
+        let bytes = b"12345678";
+        let words = [u32::from_be_bytes(*b"1234"), u32::from_be_bytes(*b"5678")];
+        assert_eq!(mpeg2(words), mpeg2_bytes(bytes));
+        assert_eq!(mpeg2([]), 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn bytes_pack_little_endian_and_pad() {
+        let words: Vec<u32> = le_words([1, 2, 3, 4, 5]).collect();
+        assert_eq!(words, [0x0403_0201, 0x0000_0005]);
+        assert_eq!(le_words([]).count(), 0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Palette-cycled water and lava
+//!
+//! The old trick for moving water without redrawing it: every pixel of the
+//! strip holds an index into a ring of colors, and only the ring turns.
+//! `index_at` lays the indices out as sloping ripples, and `color` looks an
+//! index up at a phase; one step of the phase shifts every pixel to the
+//! next color along the ring, so the ripples seem to run sideways.
+
+use crate::color::Rgb565;
+use crate::config::Coord;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Cycle {
+    colors: &'static [Rgb565],
+    // How long each phase shows for
+    step_ms: u32,
+}
+
+// Deep to bright and back, so the ring has no seam
+const WATER: [Rgb565; 6] = [
+    Rgb565::from_rgb(8, 48, 136),
+    Rgb565::from_rgb(16, 80, 176),
+    Rgb565::from_rgb(40, 120, 216),
+    Rgb565::from_rgb(120, 184, 248),
+    Rgb565::from_rgb(40, 120, 216),
+    Rgb565::from_rgb(16, 80, 176),
+];
+const LAVA: [Rgb565; 6] = [
+    Rgb565::from_rgb(128, 0, 0),
+    Rgb565::from_rgb(192, 32, 0),
+    Rgb565::from_rgb(248, 72, 0),
+    Rgb565::from_rgb(248, 184, 32),
+    Rgb565::from_rgb(248, 72, 0),
+    Rgb565::from_rgb(192, 32, 0),
+];
+
+// Columns a ripple is wide
+const RIPPLE: Coord = 4;
+
+impl Cycle {
+    pub const WATER: Cycle = Cycle {
+        colors: &WATER,
+        step_ms: 150,
+    };
+    // Slower and thicker than water
+    pub const LAVA: Cycle = Cycle {
+        colors: &LAVA,
+        step_ms: 250,
+    };
+
+    fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    // Phase at `now_ms` on the millisecond clock
+    pub fn phase(&self, now_ms: u32) -> usize {
+        (now_ms / self.step_ms) as usize % self.len()
+    }
+
+    // Ring index of the pixel at column `x`, `row` rows into the strip
+    pub fn index_at(&self, x: Coord, row: Coord) -> usize {
+        (x / RIPPLE + row).rem_euclid(self.len() as Coord) as usize
+    }
+
+    pub fn color(&self, index: usize, phase: usize) -> Rgb565 {
+        self.colors[(index + self.len() - phase % self.len()) % self.len()]
+    }
+
+    // `row` of the strip at `phase`, a color per column from column 0
+    pub fn fill_row(&self, row: Coord, phase: usize, colors: &mut [u16]) {
+        for (x, color) in colors.iter_mut().enumerate() {
+            *color = self.color(self.index_at(x as Coord, row), phase).0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_steps_round_the_ring() {
+        let water = Cycle::WATER;
+        assert_eq!(water.phase(0), 0);
+        assert_eq!(water.phase(149), 0);
+        assert_eq!(water.phase(150), 1);
+        assert_eq!(water.phase(150 * water.len() as u32), 0);
+    }
+
+    #[test]
+    fn a_step_moves_the_ripples_one_along() {
+        let water = Cycle::WATER;
+        let mut before = [0u16; 32];
+        let mut after = [0u16; 32];
+        water.fill_row(2, 3, &mut before);
+        water.fill_row(2, 4, &mut after);
+        // A ripple further right
+        assert_eq!(after[RIPPLE as usize..], before[..32 - RIPPLE as usize]);
+        assert_ne!(after, before);
+    }
+
+    #[test]
+    fn every_color_shows_in_a_row() {
+        for cycle in [Cycle::WATER, Cycle::LAVA] {
+            let mut row = [0u16; 32];
+            cycle.fill_row(0, 0, &mut row);
+            for color in cycle.colors {
+                assert!(row.contains(&color.0));
+            }
+        }
+    }
+}
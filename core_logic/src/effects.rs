@@ -0,0 +1,265 @@
+//! Screen shake, score pop and toast timing
+//!
+//! Like the animations in `anim`, each effect is a function of the
+//! milliseconds since it started, so it plays out while the game loop runs
+//! at full rate. `Effects` keeps the few running at once: a point scored
+//! while the screen still shakes pops the score as well. A toast is a note
+//! on the HUD that is simply up for `TOAST_MS`. The warning near the
+//! ceiling or the ground flashes (`edge_alpha`) and fades away from the
+//! edge (`edge_fade`).
+
+use crate::config::Coord;
+
+// Shake on death: a few pixels either way, dying down over this long
+pub const SHAKE_MS: u32 = 300;
+const SHAKE_PX: Coord = 4;
+// The shake moves on to the next direction this often
+const SHAKE_STEP_MS: u32 = 30;
+// Directions it goes through in turn, never the same way twice running
+const SHAKE_PATTERN: [(Coord, Coord); 4] = [(1, -1), (-1, 1), (1, 1), (-1, -1)];
+
+// Score pop: grows to POP_PERCENT of its size and back over this long
+pub const POP_MS: u32 = 250;
+pub const POP_PERCENT: u32 = 140;
+
+// A toast stays up this long
+pub const TOAST_MS: u32 = 1000;
+
+// The edge warning flashes bright and dim this often, dim at this share
+const EDGE_FLASH_MS: u32 = 160;
+const EDGE_DIM_PERCENT: u32 = 40;
+
+// More than ever overlap in practice
+const MAX_EFFECTS: usize = 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum EffectKind {
+    Shake,
+    Pop,
+    Toast,
+}
+
+impl EffectKind {
+    pub const fn duration_ms(self) -> u32 {
+        match self {
+            EffectKind::Shake => SHAKE_MS,
+            EffectKind::Pop => POP_MS,
+            EffectKind::Toast => TOAST_MS,
+        }
+    }
+}
+
+// Offset of the whole picture `elapsed` ms into a shake
+pub fn shake_offset(elapsed: u32) -> (Coord, Coord) {
+    if elapsed >= SHAKE_MS {
+        return (0, 0);
+    }
+    let amplitude = SHAKE_PX * (SHAKE_MS - elapsed) as Coord / SHAKE_MS as Coord;
+    let (dx, dy) = SHAKE_PATTERN[(elapsed / SHAKE_STEP_MS) as usize % SHAKE_PATTERN.len()];
+    (dx * amplitude, dy * amplitude)
+}
+
+// Score size in percent `elapsed` ms into a pop: up to POP_PERCENT halfway
+// through, then back down to 100
+pub fn pop_percent(elapsed: u32) -> u32 {
+    if elapsed >= POP_MS {
+        return 100;
+    }
+    let half = POP_MS / 2;
+    let from_peak = elapsed.abs_diff(half);
+    100 + (POP_PERCENT - 100) * (half - from_peak.min(half)) / half
+}
+
+// Opacity of the edge warning at `now` for a bird `closeness` (0..=255)
+// near the edge
+pub fn edge_alpha(closeness: u8, now: u32) -> u8 {
+    let bright = (now / EDGE_FLASH_MS).is_multiple_of(2);
+    if bright {
+        closeness
+    } else {
+        (closeness as u32 * EDGE_DIM_PERCENT / 100) as u8
+    }
+}
+
+// Opacity `row` rows in from the edge of a warning `rows` deep that is
+// `alpha` at the edge itself
+pub fn edge_fade(alpha: u8, row: u32, rows: u32) -> u8 {
+    (alpha as u32 * rows.saturating_sub(row) / rows.max(1)) as u8
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Effect {
+    kind: EffectKind,
+    start: u32,
+}
+
+impl Effect {
+    fn elapsed(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.start)
+    }
+
+    fn is_done(&self, now: u32) -> bool {
+        self.elapsed(now) >= self.kind.duration_ms()
+    }
+}
+
+/// The effects playing now, by start time
+pub struct Effects {
+    queue: [Option<Effect>; MAX_EFFECTS],
+}
+
+impl Effects {
+    pub const fn new() -> Self {
+        Effects {
+            queue: [None; MAX_EFFECTS],
+        }
+    }
+
+    // Start `kind` at `now`. One already playing starts over rather than
+    // stacking; with the queue full the oldest makes room.
+    pub fn start(&mut self, kind: EffectKind, now: u32) {
+        let effect = Some(Effect { kind, start: now });
+        if let Some(slot) = self
+            .queue
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|e| e.kind == kind))
+        {
+            *slot = effect;
+        } else if let Some(slot) = self.queue.iter_mut().find(|slot| slot.is_none()) {
+            *slot = effect;
+        } else if let Some(slot) = self
+            .queue
+            .iter_mut()
+            .max_by_key(|slot| slot.map_or(0, |e| e.elapsed(now)))
+        {
+            *slot = effect;
+        }
+    }
+
+    // Drop the effects that have played out
+    pub fn expire(&mut self, now: u32) {
+        for slot in self.queue.iter_mut() {
+            if slot.is_some_and(|e| e.is_done(now)) {
+                *slot = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue = [None; MAX_EFFECTS];
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.queue.iter().all(Option::is_none)
+    }
+
+    // Offset of the whole picture at `now`
+    pub fn offset(&self, now: u32) -> (Coord, Coord) {
+        self.playing(EffectKind::Shake, now)
+            .map(|e| shake_offset(e.elapsed(now)))
+            .fold((0, 0), |(x, y), (dx, dy)| (x + dx, y + dy))
+    }
+
+    // Score size in percent at `now`; 100 with no pop playing
+    pub fn score_percent(&self, now: u32) -> u32 {
+        self.playing(EffectKind::Pop, now)
+            .map(|e| pop_percent(e.elapsed(now)))
+            .max()
+            .unwrap_or(100)
+    }
+
+    pub fn toast_shown(&self, now: u32) -> bool {
+        self.playing(EffectKind::Toast, now).next().is_some()
+    }
+
+    fn playing(&self, kind: EffectKind, now: u32) -> impl Iterator<Item = Effect> + '_ {
+        self.queue
+            .iter()
+            .flatten()
+            .copied()
+            .filter(move |e| e.kind == kind && !e.is_done(now))
+    }
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Effects::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shake_dies_down_to_nothing() {
+        let (x0, y0) = shake_offset(0);
+        assert_eq!((x0.abs(), y0.abs()), (SHAKE_PX, SHAKE_PX));
+        let late = shake_offset(SHAKE_MS - 1);
+        assert!(late.0.abs() <= 1 && late.1.abs() <= 1);
+        assert_eq!(shake_offset(SHAKE_MS), (0, 0));
+        assert_eq!(shake_offset(u32::MAX), (0, 0));
+    }
+
+    #[test]
+    fn pop_peaks_halfway_and_settles() {
+        assert_eq!(pop_percent(0), 100);
+        assert_eq!(pop_percent(POP_MS / 2), POP_PERCENT);
+        assert!(pop_percent(POP_MS / 4) > 100 && pop_percent(POP_MS / 4) < POP_PERCENT);
+        assert_eq!(pop_percent(POP_MS), 100);
+    }
+
+    #[test]
+    fn the_edge_warning_flashes_and_fades_inward() {
+        assert_eq!(edge_alpha(200, 0), 200);
+        assert_eq!(edge_alpha(200, EDGE_FLASH_MS), 80);
+        assert_eq!(edge_alpha(200, 2 * EDGE_FLASH_MS), 200);
+        assert_eq!(edge_fade(200, 0, 8), 200);
+        assert_eq!(edge_fade(200, 4, 8), 100);
+        assert_eq!(edge_fade(200, 8, 8), 0);
+    }
+
+    #[test]
+    fn effects_play_together_and_expire() {
+        let mut effects = Effects::new();
+        effects.start(EffectKind::Shake, 1000);
+        effects.start(EffectKind::Pop, 1000);
+        let now = 1000 + POP_MS / 2;
+        assert_ne!(effects.offset(now), (0, 0));
+        assert_eq!(effects.score_percent(now), POP_PERCENT);
+
+        effects.expire(1000 + SHAKE_MS + POP_MS);
+        assert!(effects.is_idle());
+        assert_eq!(effects.offset(1000 + SHAKE_MS + POP_MS), (0, 0));
+        assert_eq!(effects.score_percent(1000 + SHAKE_MS + POP_MS), 100);
+    }
+
+    #[test]
+    fn a_toast_is_up_for_its_time() {
+        let mut effects = Effects::new();
+        assert!(!effects.toast_shown(0));
+        effects.start(EffectKind::Toast, 100);
+        assert!(effects.toast_shown(100));
+        assert!(effects.toast_shown(100 + TOAST_MS - 1));
+        assert!(!effects.toast_shown(100 + TOAST_MS));
+        assert_eq!(effects.score_percent(100), 100);
+    }
+
+    #[test]
+    fn starting_again_restarts_rather_than_stacks() {
+        let mut effects = Effects::new();
+        effects.start(EffectKind::Shake, 0);
+        effects.start(EffectKind::Shake, 200);
+        assert_eq!(effects.offset(200), shake_offset(0));
+        assert_eq!(effects.queue.iter().flatten().count(), 1);
+    }
+
+    #[test]
+    fn the_clock_wrapping_does_not_end_an_effect() {
+        let mut effects = Effects::new();
+        effects.start(EffectKind::Pop, u32::MAX - 10);
+        effects.expire(POP_MS / 2);
+        assert!(!effects.is_idle());
+        assert!(effects.score_percent(POP_MS / 2) > 100);
+    }
+}
@@ -0,0 +1,633 @@
+//! Score upload through an ESP8266 running Espressif's AT firmware
+//!
+//! An ESP-01 on a UART takes text commands (`AT+CWJAP="ssid","pass"`) and
+//! answers in lines: "OK", "ERROR" or "FAIL" to end a command, and some of
+//! its own such as "WIFI DISCONNECT". `Client` is the conversation without
+//! the UART: `next` gives the command to send when one is due, `write`
+//! spells it out, and each line that comes back goes to `line`. It resets
+//! the module and joins the access point, then for each score queued opens
+//! a TCP connection to the server, sends one HTTP POST and closes it.
+//!
+//! Whatever fails (an error, or no answer within the step's timeout) is
+//! tried again after a wait that doubles each time, from `RETRY_MS` up to
+//! `MAX_RETRY_MS`. A score that has failed `MAX_TRIES` times is dropped, so
+//! one the server keeps turning away does not hold up the rest, and with
+//! `QUEUE` waiting the oldest not already on its way goes to make room.
+//! Losing the access point starts the join over. Times are milliseconds on
+//! a wrapping clock.
+//!
+//! String arguments go out quoted, with `"`, `,` and `\` in them escaped by
+//! a backslash as the AT firmware expects, so a password holding one cannot
+//! end the argument early or add another.
+
+use core::fmt::{self, Write};
+
+use crate::leaderboard::INITIALS;
+
+// Scores kept while one is being sent or the access point is away
+pub const QUEUE: usize = 4;
+pub const MAX_TRIES: u32 = 5;
+pub const RETRY_MS: u32 = 1_000;
+pub const MAX_RETRY_MS: u32 = 60_000;
+
+/// Where to connect and where to post
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Config {
+    pub ssid: &'static str,
+    pub password: &'static str,
+    pub host: &'static str,
+    pub port: u16,
+    pub path: &'static str,
+}
+
+/// One finished game, as posted
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Upload {
+    pub mode: &'static str,
+    pub score: u32,
+    pub play_ms: u32,
+    pub initials: [u8; INITIALS],
+}
+
+impl Upload {
+    fn write_body(&self, out: &mut impl Write) -> fmt::Result {
+        let initials = core::str::from_utf8(&self.initials).unwrap_or("???");
+        write!(
+            out,
+            "mode={}&score={}&ms={}&initials={}",
+            self.mode, self.score, self.play_ms, initials
+        )
+    }
+
+    // The HTTP request for it, headers and form body
+    pub fn write_request(&self, config: &Config, out: &mut impl Write) -> fmt::Result {
+        write!(
+            out,
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Content-Type: application/x-www-form-urlencoded\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            config.path,
+            config.host,
+            counted(|out| self.write_body(out))
+        )?;
+        self.write_body(out)
+    }
+
+    // Bytes `write_request` writes, for AT+CIPSEND
+    pub fn request_len(&self, config: &Config) -> usize {
+        counted(|out| self.write_request(config, out))
+    }
+}
+
+// A string argument of an AT command, escaped to go between its quotes
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for ch in self.0.chars() {
+            if matches!(ch, '"' | ',' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(ch)?;
+        }
+        Ok(())
+    }
+}
+
+// Bytes `write` writes
+fn counted(write: impl FnOnce(&mut Count) -> fmt::Result) -> usize {
+    let mut count = Count(0);
+    let _ = write(&mut count);
+    count.0
+}
+
+struct Count(usize);
+
+impl Write for Count {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// A step of the conversation
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    // AT+RST; the module says "ready" once it is back up
+    Reset,
+    // ATE0, so commands are not echoed back
+    EchoOff,
+    // AT+CWMODE=1, a station rather than an access point
+    StationMode,
+    // AT+CWJAP with the access point's name and password
+    Join,
+    // AT+CIPSTART, TCP to the server
+    Open,
+    // AT+CIPSEND with the request's length; the module says "OK", then
+    // prompts with ">"
+    Length(usize),
+    // The request itself; "SEND OK" once it is out
+    Request,
+    // AT+CIPCLOSE; "ERROR" when the server closed first, which is as good
+    Close,
+}
+
+impl Command {
+    fn timeout_ms(self) -> u32 {
+        match self {
+            Command::Reset => 5_000,
+            Command::Join => 20_000,
+            Command::Open | Command::Request => 10_000,
+            Command::EchoOff | Command::StationMode | Command::Length(_) | Command::Close => 2_000,
+        }
+    }
+}
+
+/// How the link is doing, for the HUD and the shell
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    // Not started
+    Off,
+    // Resetting the module or joining the access point
+    Joining,
+    // Joining failed; trying again after the backoff
+    Offline,
+    // On the access point with nothing to send
+    Online,
+    // A score on its way
+    Sending,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Off => "off",
+            Status::Joining => "joining",
+            Status::Offline => "offline",
+            Status::Online => "online",
+            Status::Sending => "sending",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Phase {
+    Off,
+    // `command` goes out at `at`
+    Due { command: Command, at: u32 },
+    // `command` went out at `since`; its answer is awaited
+    Waiting { command: Command, since: u32 },
+    // On the access point with nothing going on
+    Idle,
+}
+
+pub struct Client {
+    config: Config,
+    phase: Phase,
+    joined: bool,
+    // Failures in a row, for the backoff
+    failures: u32,
+    // Failed attempts at the oldest score
+    tries: u32,
+    // Oldest first
+    queue: [Option<Upload>; QUEUE],
+    sent: u32,
+    dropped: u32,
+}
+
+impl Client {
+    pub const fn new(config: Config) -> Self {
+        Client {
+            config,
+            phase: Phase::Off,
+            joined: false,
+            failures: 0,
+            tries: 0,
+            queue: [None; QUEUE],
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Reset the module and join from `now`
+    pub fn start(&mut self, now: u32) {
+        self.joined = false;
+        self.failures = 0;
+        self.phase = Phase::Due {
+            command: Command::Reset,
+            at: now,
+        };
+    }
+
+    // Send `upload` when it can go; nothing until started
+    pub fn queue(&mut self, upload: Upload) {
+        if self.phase == Phase::Off {
+            return;
+        }
+        if self.queue[QUEUE - 1].is_some() {
+            // The oldest may be the one on its way, announced to the module
+            // by length; then the next oldest goes instead
+            let sending = self.joined && self.phase != Phase::Idle;
+            self.remove(usize::from(sending));
+            self.dropped += 1;
+        }
+        if let Some(free) = self.queue.iter_mut().find(|slot| slot.is_none()) {
+            *free = Some(upload);
+        }
+    }
+
+    // Scores waiting, sent and dropped
+    pub fn counts(&self) -> (usize, u32, u32) {
+        let waiting = self.queue.iter().flatten().count();
+        (waiting, self.sent, self.dropped)
+    }
+
+    pub fn status(&self) -> Status {
+        match (self.phase, self.joined) {
+            (Phase::Off, _) => Status::Off,
+            (_, false) if self.failures > 0 => Status::Offline,
+            (_, false) => Status::Joining,
+            (Phase::Idle, true) => Status::Online,
+            (_, true) => Status::Sending,
+        }
+    }
+
+    // The command to send now, if one is due; it is then awaited
+    pub fn next(&mut self, now: u32) -> Option<Command> {
+        let command = match self.phase {
+            Phase::Due { command, at } if now.wrapping_sub(at) as i32 >= 0 => command,
+            Phase::Idle if self.queue[0].is_some() => Command::Open,
+            Phase::Waiting { command, since } => {
+                if now.wrapping_sub(since) >= command.timeout_ms() {
+                    self.fail(command, now);
+                }
+                return None;
+            }
+            _ => return None,
+        };
+        self.phase = Phase::Waiting {
+            command,
+            since: now,
+        };
+        Some(command)
+    }
+
+    // `command` as the module takes it
+    pub fn write(&self, command: Command, out: &mut impl Write) -> fmt::Result {
+        let config = &self.config;
+        match command {
+            Command::Reset => out.write_str("AT+RST\r\n"),
+            Command::EchoOff => out.write_str("ATE0\r\n"),
+            Command::StationMode => out.write_str("AT+CWMODE=1\r\n"),
+            Command::Join => write!(
+                out,
+                "AT+CWJAP=\"{}\",\"{}\"\r\n",
+                Quoted(config.ssid),
+                Quoted(config.password)
+            ),
+            Command::Open => write!(
+                out,
+                "AT+CIPSTART=\"TCP\",\"{}\",{}\r\n",
+                Quoted(config.host),
+                config.port
+            ),
+            Command::Length(len) => write!(out, "AT+CIPSEND={}\r\n", len),
+            Command::Request => match &self.queue[0] {
+                Some(upload) => upload.write_request(config, out),
+                None => Ok(()),
+            },
+            Command::Close => out.write_str("AT+CIPCLOSE\r\n"),
+        }
+    }
+
+    // A line from the module, without its line ending; the ">" prompt
+    // counts as one
+    pub fn line(&mut self, line: &str, now: u32) {
+        let line = line.trim();
+        if line == "WIFI DISCONNECT" && self.joined {
+            self.joined = false;
+            self.phase = Phase::Due {
+                command: Command::Join,
+                at: now.wrapping_add(self.backoff()),
+            };
+            return;
+        }
+        let Phase::Waiting { command, .. } = self.phase else {
+            return;
+        };
+        let done = match (command, line) {
+            (Command::Reset, "ready") => true,
+            (Command::Reset, _) => return,
+            (Command::Open, "ALREADY CONNECTED") => true,
+            (Command::Length(_), ">") => true,
+            // Comes before the prompt; the request waits for that
+            (Command::Length(_), "OK") => return,
+            (Command::Request, "SEND OK") => true,
+            (Command::Request, "SEND FAIL") => false,
+            (Command::Close, "OK" | "ERROR") => true,
+            (_, "OK") => true,
+            (_, "ERROR" | "FAIL") => false,
+            (Command::Open, "CLOSED") => false,
+            _ => return,
+        };
+        if done {
+            self.advance(command, now);
+        } else {
+            self.fail(command, now);
+        }
+    }
+
+    fn advance(&mut self, command: Command, now: u32) {
+        let next = match command {
+            Command::Reset => Command::EchoOff,
+            Command::EchoOff => Command::StationMode,
+            Command::StationMode => Command::Join,
+            Command::Join => {
+                self.joined = true;
+                self.failures = 0;
+                self.phase = Phase::Idle;
+                return;
+            }
+            Command::Open => match &self.queue[0] {
+                Some(upload) => Command::Length(upload.request_len(&self.config)),
+                None => Command::Close,
+            },
+            Command::Length(_) => Command::Request,
+            Command::Request => {
+                self.pop();
+                self.sent += 1;
+                self.tries = 0;
+                self.failures = 0;
+                Command::Close
+            }
+            Command::Close => {
+                self.phase = Phase::Idle;
+                return;
+            }
+        };
+        self.phase = Phase::Due {
+            command: next,
+            at: now,
+        };
+    }
+
+    // `command` failed at `now`: the join over again, or the connection
+    // closed and the score tried again, after the backoff
+    fn fail(&mut self, command: Command, now: u32) {
+        self.failures += 1;
+        let next = if !self.joined {
+            match command {
+                Command::Join => Command::Join,
+                _ => Command::Reset,
+            }
+        } else {
+            self.tries += 1;
+            if self.tries >= MAX_TRIES {
+                self.pop();
+                self.dropped += 1;
+                self.tries = 0;
+            }
+            Command::Close
+        };
+        self.phase = Phase::Due {
+            command: next,
+            at: now.wrapping_add(self.backoff()),
+        };
+    }
+
+    // Wait before the next try, doubling with each failure in a row
+    fn backoff(&self) -> u32 {
+        let doublings = self.failures.saturating_sub(1).min(16);
+        (RETRY_MS << doublings).min(MAX_RETRY_MS)
+    }
+
+    fn pop(&mut self) {
+        self.remove(0);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if index == 0 {
+            self.tries = 0;
+        }
+        self.queue[index..].rotate_left(1);
+        self.queue[QUEUE - 1] = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: Config = Config {
+        ssid: "home",
+        password: "secret",
+        host: "scores.example",
+        port: 8080,
+        path: "/runs",
+    };
+
+    const RUN: Upload = Upload {
+        mode: "solo",
+        score: 12,
+        play_ms: 43_210,
+        initials: *b"ABC",
+    };
+
+    fn text(client: &Client, command: Command) -> String {
+        let mut out = String::new();
+        client.write(command, &mut out).unwrap();
+        out
+    }
+
+    // Reset, join and reach Online at `now`
+    fn joined(now: u32) -> Client {
+        let mut client = Client::new(CONFIG);
+        client.start(now);
+        for (command, answer) in [
+            (Command::Reset, "ready"),
+            (Command::EchoOff, "OK"),
+            (Command::StationMode, "OK"),
+            (Command::Join, "OK"),
+        ] {
+            assert_eq!(client.next(now), Some(command));
+            client.line(answer, now);
+        }
+        client
+    }
+
+    #[test]
+    fn joins_the_access_point() {
+        let mut client = Client::new(CONFIG);
+        assert_eq!(client.status(), Status::Off);
+        client.queue(RUN);
+        assert_eq!(client.counts(), (0, 0, 0));
+        client.start(0);
+        assert_eq!(client.next(0), Some(Command::Reset));
+        // Boot chatter and the echo are passed over
+        client.line("AT+RST", 5);
+        client.line("OK", 5);
+        assert_eq!(client.next(10), None);
+        client.line("ready", 400);
+        assert_eq!(client.next(400), Some(Command::EchoOff));
+        client.line("OK", 410);
+        assert_eq!(client.next(410), Some(Command::StationMode));
+        client.line("OK", 420);
+        assert_eq!(client.next(420), Some(Command::Join));
+        assert_eq!(
+            text(&client, Command::Join),
+            "AT+CWJAP=\"home\",\"secret\"\r\n"
+        );
+        client.line("WIFI CONNECTED", 3_000);
+        client.line("WIFI GOT IP", 3_500);
+        assert_eq!(client.status(), Status::Joining);
+        client.line("OK", 3_600);
+        assert_eq!(client.status(), Status::Online);
+        assert_eq!(client.next(5_000), None);
+    }
+
+    #[test]
+    fn quotes_commas_and_backslashes_are_escaped() {
+        let client = Client::new(Config {
+            ssid: "cafe, \"upstairs\"",
+            password: "a\\b\",\"x",
+            host: "scores,example",
+            ..CONFIG
+        });
+        assert_eq!(
+            text(&client, Command::Join),
+            "AT+CWJAP=\"cafe\\, \\\"upstairs\\\"\",\"a\\\\b\\\"\\,\\\"x\"\r\n"
+        );
+        assert_eq!(
+            text(&client, Command::Open),
+            "AT+CIPSTART=\"TCP\",\"scores\\,example\",8080\r\n"
+        );
+        assert_eq!(Quoted("plain").to_string(), "plain");
+    }
+
+    #[test]
+    fn posts_a_queued_score_and_closes() {
+        let mut client = joined(0);
+        client.queue(RUN);
+        assert_eq!(client.next(10), Some(Command::Open));
+        assert_eq!(client.status(), Status::Sending);
+        assert_eq!(
+            text(&client, Command::Open),
+            "AT+CIPSTART=\"TCP\",\"scores.example\",8080\r\n"
+        );
+        client.line("CONNECT", 50);
+        client.line("OK", 50);
+        let request = text(&client, Command::Request);
+        assert_eq!(
+            client.next(50),
+            Some(Command::Length(RUN.request_len(&CONFIG)))
+        );
+        assert_eq!(request.len(), RUN.request_len(&CONFIG));
+        assert!(request.starts_with("POST /runs HTTP/1.1\r\nHost: scores.example\r\n"));
+        assert!(request.ends_with("Content-Length: 40\r\nConnection: close\r\n\r\nmode=solo&score=12&ms=43210&initials=ABC"));
+        client.line("OK", 55);
+        assert_eq!(client.next(55), None);
+        client.line(">", 60);
+        assert_eq!(client.next(60), Some(Command::Request));
+        client.line("Recv 170 bytes", 70);
+        client.line("SEND OK", 80);
+        assert_eq!(client.counts(), (0, 1, 0));
+        assert_eq!(client.next(80), Some(Command::Close));
+        // Closed by the server already
+        client.line("ERROR", 90);
+        assert_eq!(client.status(), Status::Online);
+        assert_eq!(client.next(100), None);
+    }
+
+    #[test]
+    fn backs_off_doubling_and_rejoins_when_dropped() {
+        let mut client = Client::new(CONFIG);
+        client.start(0);
+        for command in [Command::Reset, Command::EchoOff, Command::StationMode] {
+            client.next(0);
+            client.line(
+                if command == Command::Reset {
+                    "ready"
+                } else {
+                    "OK"
+                },
+                0,
+            );
+        }
+        assert_eq!(client.next(0), Some(Command::Join));
+        client.line("FAIL", 100);
+        assert_eq!(client.status(), Status::Offline);
+        assert_eq!(client.next(100 + RETRY_MS - 1), None);
+        assert_eq!(client.next(100 + RETRY_MS), Some(Command::Join));
+        // No answer at all
+        let since = 100 + RETRY_MS;
+        assert_eq!(client.next(since + 20_000), None);
+        assert_eq!(client.next(since + 20_000 + 2 * RETRY_MS - 1), None);
+        assert_eq!(
+            client.next(since + 20_000 + 2 * RETRY_MS),
+            Some(Command::Join)
+        );
+        client.line("OK", 30_000);
+        assert_eq!(client.status(), Status::Online);
+
+        client.line("WIFI DISCONNECT", 40_000);
+        assert_eq!(client.status(), Status::Joining);
+        assert_eq!(client.next(40_000 + RETRY_MS), Some(Command::Join));
+    }
+
+    #[test]
+    fn gives_up_on_a_score_after_max_tries() {
+        let mut client = joined(0);
+        client.queue(RUN);
+        client.queue(Upload { score: 3, ..RUN });
+        let mut now = 0;
+        for _ in 0..MAX_TRIES {
+            assert_eq!(client.next(now), Some(Command::Open));
+            client.line("ERROR", now);
+            now += MAX_RETRY_MS;
+            assert_eq!(client.next(now), Some(Command::Close));
+            client.line("OK", now);
+        }
+        assert_eq!(client.counts(), (1, 0, 1));
+        assert_eq!(client.next(now), Some(Command::Open));
+        assert!(text(&client, Command::Request).ends_with("score=3&ms=43210&initials=ABC"));
+    }
+
+    #[test]
+    fn a_full_queue_keeps_the_score_being_sent() {
+        // Idle, the oldest makes room
+        let mut client = joined(0);
+        for score in 0..=QUEUE as u32 {
+            client.queue(Upload { score, ..RUN });
+        }
+        assert_eq!(client.counts(), (QUEUE, 0, 1));
+        assert!(text(&client, Command::Request).contains("score=1&"));
+
+        // Once its length is announced, the one on its way stays
+        let mut client = joined(0);
+        client.queue(RUN);
+        assert_eq!(client.next(10), Some(Command::Open));
+        client.line("OK", 20);
+        let length = client.next(20);
+        assert_eq!(length, Some(Command::Length(RUN.request_len(&CONFIG))));
+        for score in 0..QUEUE as u32 {
+            client.queue(Upload { score, ..RUN });
+        }
+        assert_eq!(client.counts(), (QUEUE, 0, 1));
+        client.line("OK", 30);
+        client.line(">", 30);
+        assert_eq!(client.next(30), Some(Command::Request));
+        let request = text(&client, Command::Request);
+        assert_eq!(request.len(), RUN.request_len(&CONFIG));
+        assert!(request.ends_with("score=12&ms=43210&initials=ABC"));
+        client.line("SEND OK", 40);
+        assert_eq!(client.counts(), (QUEUE - 1, 1, 1));
+        // Score 0 made room; 1 is next
+        assert!(text(&client, Command::Request).contains("score=1&"));
+    }
+}
@@ -0,0 +1,144 @@
+//! What happens in a game, for whoever wants to hear of it
+//!
+//! The game publishes a `GameEvent` when the bird flaps, scores or crashes
+//! and on every change of state. Sound, particles, the screen shake and the
+//! telemetry subscribe to the ones they react to, so the game's update does
+//! not call each of them itself. Subscribers are plain functions in a fixed
+//! number of slots, called in the order they subscribed, on the publisher's
+//! stack; they should do no more than queue or start something.
+
+use crate::config::Coord;
+use crate::rect::Rect;
+use crate::state::GameState;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    // The bird flapped; `bird` is where it is
+    Flap {
+        bird: Rect,
+    },
+    // Points for the bird passing an obstacle at `x`, or with `pickup` for
+    // collecting one at `x`; `score` is the total now
+    ScorePoint {
+        x: Coord,
+        bird: Rect,
+        score: u32,
+        pickup: bool,
+    },
+    // The bird crashed; with `respawn` a practice run puts it back at its
+    // checkpoint instead of ending
+    Death {
+        bird: Rect,
+        respawn: bool,
+    },
+    // The obstacle just passed was a close call, `rows` from the pipe at
+    // the closest; its bonus is in the ScorePoint that goes with it
+    NearMiss {
+        bird: Rect,
+        rows: u32,
+    },
+    StateChange {
+        from: GameState,
+        to: GameState,
+    },
+}
+
+pub type Subscriber = fn(GameEvent);
+
+/// Every slot of the bus is taken
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct BusFull;
+
+/// Up to `N` subscribers
+pub struct EventBus<const N: usize> {
+    slots: [Option<Subscriber>; N],
+}
+
+impl<const N: usize> EventBus<N> {
+    pub const fn new() -> Self {
+        EventBus { slots: [None; N] }
+    }
+
+    pub fn subscribe(&mut self, subscriber: Subscriber) -> Result<(), BusFull> {
+        let slot = self
+            .slots
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(BusFull)?;
+        *slot = Some(subscriber);
+        Ok(())
+    }
+
+    pub fn publish(&self, event: GameEvent) {
+        for subscriber in self.slots.iter().flatten() {
+            subscriber(event);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const N: usize> Default for EventBus<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static HEARD: RefCell<Vec<(u8, GameEvent)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn first(event: GameEvent) {
+        HEARD.with(|heard| heard.borrow_mut().push((1, event)));
+    }
+
+    fn second(event: GameEvent) {
+        HEARD.with(|heard| heard.borrow_mut().push((2, event)));
+    }
+
+    fn take() -> Vec<(u8, GameEvent)> {
+        HEARD.with(|heard| heard.take())
+    }
+
+    const FLAP: GameEvent = GameEvent::Flap {
+        bird: Rect::new(10, 20, 8, 8),
+    };
+
+    #[test]
+    fn subscribers_hear_events_in_order() {
+        let mut bus: EventBus<2> = EventBus::new();
+        bus.publish(FLAP);
+        assert!(take().is_empty());
+        bus.subscribe(first).unwrap();
+        bus.subscribe(second).unwrap();
+        let change = GameEvent::StateChange {
+            from: GameState::Running,
+            to: GameState::Paused,
+        };
+        bus.publish(FLAP);
+        bus.publish(change);
+        assert_eq!(take(), vec![(1, FLAP), (2, FLAP), (1, change), (2, change)]);
+    }
+
+    #[test]
+    fn full_bus_turns_subscribers_away() {
+        let mut bus: EventBus<1> = EventBus::new();
+        assert!(bus.is_empty());
+        assert_eq!(bus.subscribe(first), Ok(()));
+        assert_eq!(bus.subscribe(second), Err(BusFull));
+        assert_eq!(bus.len(), 1);
+        bus.publish(FLAP);
+        assert_eq!(take(), vec![(1, FLAP)]);
+    }
+}
@@ -0,0 +1,205 @@
+//! A minimal async executor for a fixed set of tasks
+//!
+//! Tasks are futures that live for the whole program, pinned by whoever
+//! owns them (the firmware pins them in `main`, which never returns). The
+//! executor keeps one state per task: waking a task marks it to be polled
+//! on the next pass, and `poll` goes round polling the marked ones. Nothing
+//! allocates, and a wake is one atomic store, so an interrupt handler can
+//! wake a task through a `Waker` it was handed.
+//!
+//! When a pass finds nothing to poll the caller idles until an interrupt
+//! (WFI on the board); whatever the interrupt wakes is polled on the pass
+//! after.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+// Per-task states
+const IDLE: u8 = 0;
+const WOKEN: u8 = 1;
+const DONE: u8 = 2;
+
+pub type Task<'a> = Pin<&'a mut dyn Future<Output = ()>>;
+
+pub struct Executor<const N: usize> {
+    states: [AtomicU8; N],
+}
+
+impl<const N: usize> Executor<N> {
+    // Every task starts woken, so each is polled once to get going
+    pub const fn new() -> Self {
+        Executor {
+            states: [const { AtomicU8::new(WOKEN) }; N],
+        }
+    }
+
+    // Poll each woken task once; false when none was woken. Tasks past the
+    // executor's N are never polled, and a finished task is not polled
+    // again.
+    pub fn poll(&'static self, tasks: &mut [Task<'_>]) -> bool {
+        let mut ran = false;
+        for (state, task) in self.states.iter().zip(tasks.iter_mut()) {
+            // Clear the mark before polling, so a wake during the poll
+            // brings the task back round
+            if state
+                .compare_exchange(WOKEN, IDLE, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                continue;
+            }
+            ran = true;
+            let waker = waker(state);
+            let mut cx = Context::from_waker(&waker);
+            if task.as_mut().poll(&mut cx).is_ready() {
+                state.store(DONE, Ordering::Release);
+            }
+        }
+        ran
+    }
+
+    // True if a task is waiting to be polled, for deciding whether to idle
+    pub fn has_woken(&self) -> bool {
+        self.states
+            .iter()
+            .any(|state| state.load(Ordering::Acquire) == WOKEN)
+    }
+
+    // True while any task has not finished
+    pub fn is_running(&self) -> bool {
+        self.states
+            .iter()
+            .any(|state| state.load(Ordering::Acquire) != DONE)
+    }
+}
+
+impl<const N: usize> Default for Executor<N> {
+    fn default() -> Self {
+        Executor::new()
+    }
+}
+
+// The waker's data is the task's state, which is 'static
+fn waker(state: &'static AtomicU8) -> Waker {
+    let raw = RawWaker::new(state as *const AtomicU8 as *const (), &VTABLE);
+    unsafe { Waker::from_raw(raw) }
+}
+
+static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+unsafe fn clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn wake(data: *const ()) {
+    let state = unsafe { &*(data as *const AtomicU8) };
+    // A finished task stays finished
+    let _ = state.compare_exchange(IDLE, WOKEN, Ordering::AcqRel, Ordering::Acquire);
+}
+
+unsafe fn drop(_: *const ()) {}
+
+/// Gives the other tasks a turn, then carries on
+pub struct YieldNow {
+    yielded: bool,
+}
+
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::pin::pin;
+    use std::vec::Vec;
+
+    #[test]
+    fn tasks_take_turns_at_each_yield() {
+        static EXECUTOR: Executor<2> = Executor::new();
+        let order = Cell::new(Vec::new());
+        let log = |entry: &'static str| {
+            let mut v = order.take();
+            v.push(entry);
+            order.set(v);
+        };
+        let a = pin!(async {
+            log("a1");
+            yield_now().await;
+            log("a2");
+        });
+        let b = pin!(async {
+            log("b1");
+            yield_now().await;
+            log("b2");
+        });
+        let mut tasks: [Task; 2] = [a, b];
+        while EXECUTOR.poll(&mut tasks) {}
+        assert_eq!(order.take(), ["a1", "b1", "a2", "b2"]);
+        assert!(!EXECUTOR.is_running());
+    }
+
+    // Pending until `ready` is set, keeping the waker for whoever sets it
+    struct Flag<'a> {
+        ready: &'a Cell<bool>,
+        waker: &'a Cell<Option<Waker>>,
+    }
+
+    impl Future for Flag<'_> {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.ready.get() {
+                return Poll::Ready(());
+            }
+            self.waker.set(Some(cx.waker().clone()));
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn a_waiting_task_is_polled_only_once_woken() {
+        static EXECUTOR: Executor<1> = Executor::new();
+        let ready = Cell::new(false);
+        let waker = Cell::new(None);
+        let polls = Cell::new(0);
+        let task = pin!(async {
+            polls.set(polls.get() + 1);
+            Flag {
+                ready: &ready,
+                waker: &waker,
+            }
+            .await;
+            polls.set(polls.get() + 1);
+        });
+        let mut tasks: [Task; 1] = [task];
+
+        assert!(EXECUTOR.poll(&mut tasks));
+        assert!(!EXECUTOR.poll(&mut tasks));
+        assert_eq!(polls.get(), 1);
+
+        ready.set(true);
+        waker.take().unwrap().wake();
+        assert!(EXECUTOR.has_woken());
+        assert!(EXECUTOR.poll(&mut tasks));
+        assert_eq!(polls.get(), 2);
+        assert!(!EXECUTOR.is_running());
+        // Waking a finished task does nothing
+        assert!(!EXECUTOR.poll(&mut tasks));
+    }
+}
@@ -0,0 +1,91 @@
+//! Filling and copying runs of pixels a word at a time
+//!
+//! Framebuffer memory is written in spans: a row of a rectangle, a whole
+//! buffer, a pattern row copied down. A 16- or 8-bit pixel stored on its own
+//! costs a bus write per pixel, so the fills here split a span into the
+//! pixels before the first word boundary, whole 32-bit words holding two or
+//! four copies of the pixel, and the pixels left after the last one.
+
+// Set every pixel of `span` to `value`, two to a word
+pub fn fill_u16(span: &mut [u16], value: u16) {
+    // SAFETY: every bit pattern is a valid u16 and u32, and align_to_mut
+    // only puts whole, aligned words in the middle
+    let (head, words, tail) = unsafe { span.align_to_mut::<u32>() };
+    head.fill(value);
+    words.fill(value as u32 * 0x0001_0001);
+    tail.fill(value);
+}
+
+// Set every pixel of `span` to `value`, four to a word
+pub fn fill_u8(span: &mut [u8], value: u8) {
+    // SAFETY: as fill_u16
+    let (head, words, tail) = unsafe { span.align_to_mut::<u32>() };
+    head.fill(value);
+    words.fill(value as u32 * 0x0101_0101);
+    tail.fill(value);
+}
+
+// Copy the `width` pixels of the row starting at `first` down over the
+// `rows - 1` rows under it, in a buffer `width` pixels a row
+pub fn repeat_rows<T: Copy>(pixels: &mut [T], width: usize, first: usize, rows: usize) {
+    for row in 1..rows {
+        pixels.copy_within(first..first + width, first + row * width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_any_span_of_halfwords() {
+        for start in 0..3 {
+            for len in 0..9 {
+                let mut buf = [0u16; 12];
+                fill_u16(&mut buf[start..start + len], 0xABCD);
+                for (i, &pixel) in buf.iter().enumerate() {
+                    let inside = (start..start + len).contains(&i);
+                    assert_eq!(
+                        pixel,
+                        if inside { 0xABCD } else { 0 },
+                        "{} {} {}",
+                        start,
+                        len,
+                        i
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fills_any_span_of_bytes() {
+        for start in 0..5 {
+            for len in 0..11 {
+                let mut buf = [0u8; 16];
+                fill_u8(&mut buf[start..start + len], 0x5A);
+                for (i, &pixel) in buf.iter().enumerate() {
+                    let inside = (start..start + len).contains(&i);
+                    assert_eq!(
+                        pixel,
+                        if inside { 0x5A } else { 0 },
+                        "{} {} {}",
+                        start,
+                        len,
+                        i
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn repeats_a_row_down() {
+        let mut buf = [1u32, 2, 3, 0, 0, 0, 0, 0, 0, 9, 9, 9];
+        repeat_rows(&mut buf, 3, 0, 3);
+        assert_eq!(buf, [1, 2, 3, 1, 2, 3, 1, 2, 3, 9, 9, 9]);
+        // One row copies nothing
+        repeat_rows(&mut buf, 3, 9, 1);
+        assert_eq!(buf[9..], [9, 9, 9]);
+    }
+}
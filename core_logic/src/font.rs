@@ -0,0 +1,160 @@
+//! Proportional bitmap fonts over chosen Unicode ranges
+//!
+//! The fixed fonts in the firmware cover ASCII 32–126 in equal cells. A
+//! `PropFont` instead holds any set of code point ranges (arrows, a heart,
+//! a music note next to the letters), each glyph with its own width and
+//! advance. Glyph bitmaps are the full font height, one row after another,
+//! each row `(width + 7) / 8` bytes with the leftmost pixel in the top bit.
+//! No kerning: a string is as wide as its advances added up.
+//!
+//! Fonts are not written by hand; `bdf` converts a BDF file into one at
+//! build time.
+
+/// Code points `first..=last`, whose glyphs follow one another from `glyph`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GlyphRange {
+    pub first: u32,
+    pub last: u32,
+    pub glyph: u16,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Glyph {
+    // Bitmap columns, and how far the pen moves on after drawing them
+    pub width: u8,
+    pub advance: u8,
+    // Start of the bitmap in `PropFont::bitmap`
+    pub offset: u32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PropFont {
+    pub height: u8,
+    // Rows from the top of a glyph down to the baseline
+    pub ascent: u8,
+    // Sorted by code point, not overlapping
+    pub ranges: &'static [GlyphRange],
+    pub glyphs: &'static [Glyph],
+    pub bitmap: &'static [u8],
+    // Drawn for characters the font lacks
+    pub fallback: char,
+}
+
+impl PropFont {
+    pub fn glyph(&self, ch: char) -> Option<&Glyph> {
+        let code = ch as u32;
+        let range = self
+            .ranges
+            .iter()
+            .find(|range| (range.first..=range.last).contains(&code))?;
+        self.glyphs
+            .get(range.glyph as usize + (code - range.first) as usize)
+    }
+
+    // The glyph for `ch`, or the fallback's; None only if neither is there
+    pub fn glyph_or_fallback(&self, ch: char) -> Option<&Glyph> {
+        self.glyph(ch).or_else(|| self.glyph(self.fallback))
+    }
+
+    // Pen movement for all of `text`
+    pub fn text_width(&self, text: &str) -> u32 {
+        text.chars()
+            .filter_map(|ch| self.glyph_or_fallback(ch))
+            .map(|glyph| glyph.advance as u32)
+            .sum()
+    }
+
+    // Whether `glyph` sets the pixel at column `x`, row `y`
+    pub fn pixel(&self, glyph: &Glyph, x: u32, y: u32) -> bool {
+        if x >= glyph.width as u32 || y >= self.height as u32 {
+            return false;
+        }
+        let stride = (glyph.width as usize).div_ceil(8);
+        let byte = glyph.offset as usize + y as usize * stride + x as usize / 8;
+        self.bitmap
+            .get(byte)
+            .is_some_and(|bits| bits & (0x80 >> (x % 8)) != 0)
+    }
+
+    // The set pixels of `glyph` as (column, row), row by row
+    pub fn pixels<'a>(&'a self, glyph: &'a Glyph) -> impl Iterator<Item = (u32, u32)> + 'a {
+        (0..self.height as u32).flat_map(move |y| {
+            (0..glyph.width as u32)
+                .filter(move |&x| self.pixel(glyph, x, y))
+                .map(move |x| (x, y))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 'A' and 'B' two columns wide, an arrow at U+2192 three wide; two rows
+    const FONT: PropFont = PropFont {
+        height: 2,
+        ascent: 2,
+        ranges: &[
+            GlyphRange {
+                first: 'A' as u32,
+                last: 'B' as u32,
+                glyph: 0,
+            },
+            GlyphRange {
+                first: 0x2192,
+                last: 0x2192,
+                glyph: 2,
+            },
+        ],
+        glyphs: &[
+            Glyph {
+                width: 2,
+                advance: 3,
+                offset: 0,
+            },
+            Glyph {
+                width: 2,
+                advance: 3,
+                offset: 2,
+            },
+            Glyph {
+                width: 3,
+                advance: 4,
+                offset: 4,
+            },
+        ],
+        bitmap: &[
+            0b1100_0000,
+            0b0100_0000,
+            0b1000_0000,
+            0b1100_0000,
+            0b0100_0000,
+            0b1110_0000,
+        ],
+        fallback: 'B',
+    };
+
+    #[test]
+    fn glyphs_are_found_across_ranges() {
+        assert_eq!(FONT.glyph('B').map(|g| g.offset), Some(2));
+        assert_eq!(FONT.glyph('→').map(|g| g.width), Some(3));
+        assert!(FONT.glyph('C').is_none());
+        assert_eq!(FONT.glyph_or_fallback('C'), FONT.glyph('B'));
+    }
+
+    #[test]
+    fn width_adds_up_advances_including_fallbacks() {
+        assert_eq!(FONT.text_width("AB→"), 3 + 3 + 4);
+        assert_eq!(FONT.text_width("A?"), 6);
+        assert_eq!(FONT.text_width(""), 0);
+    }
+
+    #[test]
+    fn pixels_read_back_the_bitmap() {
+        let arrow = FONT.glyph('→').unwrap();
+        let set: Vec<(u32, u32)> = FONT.pixels(arrow).collect();
+        assert_eq!(set, [(1, 0), (0, 1), (1, 1), (2, 1)]);
+        assert!(!FONT.pixel(arrow, 3, 0));
+        assert!(!FONT.pixel(arrow, 0, 2));
+    }
+}
@@ -0,0 +1,176 @@
+//! Game coordinates to panel coordinates
+//!
+//! The game draws in its own coordinates, origin top left of the screen as
+//! the player holds it. The panel's memory is always addressed in its
+//! native portrait layout, `PANEL_WIDTH` x `PANEL_HEIGHT`. `Orientation`
+//! says how the two line up; every draw path maps through `game_to_lcd`
+//! (or `Orientation::map` for buffers other than the full screen), so the
+//! rotation math lives only here.
+
+use crate::config::{Coord, PANEL_HEIGHT, PANEL_WIDTH};
+
+/// How the game is turned relative to the panel
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Orientation {
+    // The panel's native layout
+    Portrait,
+    // Turned a quarter turn: game x runs up the panel from its bottom edge
+    // and game y to the right, so game (0, 0) is panel bottom left
+    Landscape,
+    PortraitFlipped,
+    LandscapeFlipped,
+}
+
+impl Orientation {
+    pub const fn is_landscape(self) -> bool {
+        matches!(self, Orientation::Landscape | Orientation::LandscapeFlipped)
+    }
+
+    // Game-space size of a panel-space area of w x h
+    pub const fn game_size(self, w: u32, h: u32) -> (u32, u32) {
+        if self.is_landscape() {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    }
+
+    // Panel position of game point (x, y) in a panel area of w x h. Points
+    // outside the area come out outside it too.
+    pub const fn map(self, (x, y): (Coord, Coord), (w, h): (u32, u32)) -> (Coord, Coord) {
+        let (w, h) = (w as Coord, h as Coord);
+        match self {
+            Orientation::Portrait => (x, y),
+            Orientation::Landscape => (y, h - 1 - x),
+            Orientation::PortraitFlipped => (w - 1 - x, h - 1 - y),
+            Orientation::LandscapeFlipped => (w - 1 - y, x),
+        }
+    }
+
+    // Inverse of `map`: the game point shown at panel (x, y)
+    pub const fn unmap(self, (x, y): (Coord, Coord), (w, h): (u32, u32)) -> (Coord, Coord) {
+        let (w, h) = (w as Coord, h as Coord);
+        match self {
+            Orientation::Portrait => (x, y),
+            Orientation::Landscape => (h - 1 - y, x),
+            Orientation::PortraitFlipped => (w - 1 - x, h - 1 - y),
+            Orientation::LandscapeFlipped => (y, w - 1 - x),
+        }
+    }
+
+    // Quarter turns clockwise from the panel's native layout
+    const fn quarters(self) -> u32 {
+        match self {
+            Orientation::Portrait => 0,
+            Orientation::Landscape => 1,
+            Orientation::PortraitFlipped => 2,
+            Orientation::LandscapeFlipped => 3,
+        }
+    }
+
+    // This mapping followed by `turn`'s, as one: for a panel that is itself
+    // mounted turned from the way it scans (see `panel`)
+    pub const fn turned(self, turn: Orientation) -> Orientation {
+        match (self.quarters() + turn.quarters()) % 4 {
+            0 => Orientation::Portrait,
+            1 => Orientation::Landscape,
+            2 => Orientation::PortraitFlipped,
+            _ => Orientation::LandscapeFlipped,
+        }
+    }
+
+    // Panel rectangle (x, y, w, h) covering the game rectangle; None when it
+    // is empty
+    pub fn map_rect(
+        self,
+        (x, y, w, h): (Coord, Coord, u32, u32),
+        area: (u32, u32),
+    ) -> Option<(Coord, Coord, u32, u32)> {
+        if w == 0 || h == 0 {
+            return None;
+        }
+        let (ax, ay) = self.map((x, y), area);
+        let (bx, by) = self.map((x + w as Coord - 1, y + h as Coord - 1), area);
+        Some((
+            ax.min(bx),
+            ay.min(by),
+            (ax - bx).unsigned_abs() + 1,
+            (ay - by).unsigned_abs() + 1,
+        ))
+    }
+}
+
+/// Full-screen mapping: game point to panel pixel
+pub const fn game_to_lcd(point: (Coord, Coord), orientation: Orientation) -> (Coord, Coord) {
+    orientation.map(point, (PANEL_WIDTH, PANEL_HEIGHT))
+}
+
+/// Full-screen inverse, e.g. for touch panel readings
+pub const fn lcd_to_game(point: (Coord, Coord), orientation: Orientation) -> (Coord, Coord) {
+    orientation.unmap(point, (PANEL_WIDTH, PANEL_HEIGHT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Orientation; 4] = [
+        Orientation::Portrait,
+        Orientation::Landscape,
+        Orientation::PortraitFlipped,
+        Orientation::LandscapeFlipped,
+    ];
+
+    #[test]
+    fn corners_land_on_the_panel() {
+        for o in ALL {
+            let (w, h) = o.game_size(PANEL_WIDTH, PANEL_HEIGHT);
+            for (x, y) in [(0, 0), (w as Coord - 1, 0), (0, h as Coord - 1)] {
+                let (px, py) = game_to_lcd((x, y), o);
+                assert!((0..PANEL_WIDTH as Coord).contains(&px), "{:?}", o);
+                assert!((0..PANEL_HEIGHT as Coord).contains(&py), "{:?}", o);
+            }
+        }
+    }
+
+    #[test]
+    fn landscape_matches_the_old_rotation() {
+        // lcd_x = game_y; lcd_y = GAME_WIDTH - 1 - game_x
+        assert_eq!(game_to_lcd((0, 0), Orientation::Landscape), (0, 319));
+        assert_eq!(game_to_lcd((319, 239), Orientation::Landscape), (239, 0));
+    }
+
+    #[test]
+    fn unmap_inverts_map() {
+        for o in ALL {
+            for p in [(0, 0), (17, 5), (100, 200)] {
+                assert_eq!(lcd_to_game(game_to_lcd(p, o), o), p, "{:?}", o);
+            }
+        }
+    }
+
+    #[test]
+    fn turning_maps_like_both_turns_in_a_row() {
+        let portrait = (PANEL_WIDTH, PANEL_HEIGHT);
+        for turn in ALL {
+            // The area `turn` maps the portrait panel into
+            let scan = turn.game_size(PANEL_WIDTH, PANEL_HEIGHT);
+            for o in ALL {
+                for p in [(0, 0), (17, 5), (100, 200)] {
+                    let twice = turn.map(o.map(p, portrait), scan);
+                    assert_eq!(o.turned(turn).map(p, scan), twice, "{:?} {:?}", o, turn);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rect_keeps_its_area() {
+        let r = Orientation::Landscape.map_rect((10, 20, 30, 5), (240, 320));
+        assert_eq!(r, Some((20, 280, 5, 30)));
+        assert_eq!(
+            Orientation::Portrait.map_rect((0, 0, 0, 5), (240, 320)),
+            None
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Which glyphs of a line of text need drawing again
+//!
+//! Text the firmware keeps up to date every frame, such as the score, mostly
+//! comes out the same as last time, or with only its last digit different.
+//! `ShownText` remembers what a field of fixed-width cells shows and, given
+//! the text it should show now, says which cells changed; only those are
+//! redrawn. Cells the new text no longer reaches are blanked with spaces.
+//! Anything that draws over the field (a new background, a menu) has to
+//! `invalidate` it so every cell goes again.
+
+/// What the `N` cells of a text field show, as bytes; up to 32 cells, one
+/// bit each in the mask `update` returns
+pub struct ShownText<const N: usize> {
+    cells: [u8; N],
+    // Cells holding text; the rest are blank
+    len: usize,
+    // Whether `cells` is what is on screen
+    valid: bool,
+}
+
+impl<const N: usize> ShownText<N> {
+    pub const fn new() -> Self {
+        ShownText {
+            cells: [b' '; N],
+            len: 0,
+            valid: false,
+        }
+    }
+
+    // The screen under the field was drawn over
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    // Take `text` (cut to `N` cells) as shown, returning a mask of the cells
+    // to draw, bit `i` for cell `i`; what to draw in each is `cell(i)`
+    pub fn update(&mut self, text: &[u8]) -> u32 {
+        let text = &text[..text.len().min(N)];
+        let reach = if self.valid {
+            text.len().max(self.len)
+        } else {
+            N
+        };
+        let mut changed = 0;
+        for i in 0..reach {
+            let byte = text.get(i).copied().unwrap_or(b' ');
+            if !self.valid || self.cells[i] != byte {
+                self.cells[i] = byte;
+                changed |= 1 << i;
+            }
+        }
+        self.len = text.len();
+        self.valid = true;
+        changed
+    }
+
+    pub fn cell(&self, i: usize) -> u8 {
+        self.cells[i]
+    }
+}
+
+impl<const N: usize> Default for ShownText<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_digits_that_changed_go_again() {
+        let mut shown = ShownText::<3>::new();
+        assert_eq!(shown.update(b"008"), 0b111);
+        assert_eq!(shown.update(b"009"), 0b100);
+        assert_eq!(shown.update(b"010"), 0b110);
+        assert_eq!(shown.update(b"010"), 0);
+        assert_eq!(shown.cell(1), b'1');
+    }
+
+    #[test]
+    fn shorter_text_blanks_what_it_no_longer_covers() {
+        let mut shown = ShownText::<6>::new();
+        shown.update(b"PAUSED");
+        assert_eq!(shown.update(b"PAW"), 0b111_100);
+        assert_eq!(shown.cell(3), b' ');
+        assert_eq!(shown.update(b"PAWS AGAIN"), 0b10_1000);
+        assert_eq!(shown.cell(5), b'A');
+    }
+
+    #[test]
+    fn invalidating_draws_every_cell() {
+        let mut shown = ShownText::<4>::new();
+        shown.update(b"12");
+        shown.invalidate();
+        assert_eq!(shown.update(b"12"), 0b1111);
+    }
+}
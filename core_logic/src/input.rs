@@ -0,0 +1,413 @@
+//! Input events and gestures
+//!
+//! Devices report what happened as `InputEvent`s, stamped with the
+//! millisecond it happened, into an `EventQueue`; the button does so from
+//! its interrupt, so a tap between two frames is still there when the game
+//! looks. The game drains the queue once a frame and feeds each source's
+//! presses and releases through `Gestures`, which turns them into taps,
+//! double taps and holds.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::Coord;
+
+// Presses shorter than this are contact bounce
+pub const DEBOUNCE_MS: u32 = 20;
+// Held this long, a press is a long press rather than a tap
+pub const LONG_PRESS_MS: u32 = 700;
+// Still held this long, it is reported once more as a hold
+pub const HOLD_MS: u32 = 3000;
+// A tap starting within this long of the last one ending is a double tap
+pub const DOUBLE_TAP_MS: u32 = 300;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputSource {
+    Button,
+    Touch,
+    // Tilted past the calibration threshold counts as pressed
+    Tilt,
+    // The thumb-stick pushed past its press point (joystick)
+    Stick,
+    // A switch or receiver on the external trigger pin
+    Trigger,
+}
+
+// Whole-board movement the accelerometer's detectors report
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Motion {
+    Shake,
+    FreeFall,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputEvent {
+    Pressed { source: InputSource, at: u32 },
+    Released { source: InputSource, at: u32 },
+    // Where the tilt or the stick puts the bird, in game rows
+    TiltChanged { y: Coord },
+    Moved { motion: Motion, at: u32 },
+}
+
+/// Lock-free single-producer, single-consumer queue of events
+///
+/// The producer only moves `head` and the consumer only `tail`, so an
+/// interrupt can push while the game loop pops. More than one producer
+/// must keep their pushes from interleaving, for example by pushing with
+/// interrupts masked. Holds `N - 1` events.
+pub struct EventQueue<const N: usize> {
+    buf: UnsafeCell<[InputEvent; N]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+// Each slot is written by the producer before `head` is published past it
+// and read by the consumer before `tail` gives it back
+unsafe impl<const N: usize> Sync for EventQueue<N> {}
+
+impl<const N: usize> EventQueue<N> {
+    pub const fn new() -> Self {
+        EventQueue {
+            buf: UnsafeCell::new([InputEvent::TiltChanged { y: 0 }; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    // False, and the event dropped, when the queue is full
+    pub fn push(&self, event: InputEvent) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % N;
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+        unsafe { (*self.buf.get())[head] = event };
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    pub fn pop(&self) -> Option<InputEvent> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        let event = unsafe { (*self.buf.get())[tail] };
+        self.tail.store((tail + 1) % N, Ordering::Release);
+        Some(event)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tail.load(Ordering::Acquire) == self.head.load(Ordering::Acquire)
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        EventQueue::new()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Gesture {
+    // Released before LONG_PRESS_MS
+    Tap,
+    // A tap soon after another; the first was reported as a Tap already
+    DoubleTap,
+    // Held for LONG_PRESS_MS, reported while still held and not on release
+    LongPress,
+    // Still held at HOLD_MS
+    Hold,
+}
+
+/// Turns one source's presses and releases into gestures
+///
+/// A tap is reported on release; long presses and holds as soon as their
+/// time is up, which `poll` checks, so it must be called every frame.
+#[derive(Copy, Clone, Debug)]
+pub struct Gestures {
+    pressed_at: Option<u32>,
+    long_sent: bool,
+    hold_sent: bool,
+    // When the last tap was released, while a second may still follow
+    last_tap: Option<u32>,
+}
+
+impl Gestures {
+    pub const fn new() -> Self {
+        Gestures {
+            pressed_at: None,
+            long_sent: false,
+            hold_sent: false,
+            last_tap: None,
+        }
+    }
+
+    // A second press without a release in between continues the first
+    pub fn press(&mut self, at: u32) {
+        if self.pressed_at.is_none() {
+            self.pressed_at = Some(at);
+            self.long_sent = false;
+            self.hold_sent = false;
+        }
+    }
+
+    pub fn release(&mut self, at: u32) -> Option<Gesture> {
+        let start = self.pressed_at.take()?;
+        if self.long_sent || at.wrapping_sub(start) < DEBOUNCE_MS {
+            return None;
+        }
+        match self.last_tap.take() {
+            Some(end) if start.wrapping_sub(end) <= DOUBLE_TAP_MS => Some(Gesture::DoubleTap),
+            _ => {
+                self.last_tap = Some(at);
+                Some(Gesture::Tap)
+            }
+        }
+    }
+
+    // A long press or hold that has come due by `now`
+    pub fn poll(&mut self, now: u32) -> Option<Gesture> {
+        let held = now.wrapping_sub(self.pressed_at?);
+        if !self.long_sent && held >= LONG_PRESS_MS {
+            self.long_sent = true;
+            self.last_tap = None;
+            Some(Gesture::LongPress)
+        } else if !self.hold_sent && held >= HOLD_MS {
+            self.hold_sent = true;
+            Some(Gesture::Hold)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Gestures::new()
+    }
+}
+
+/// Debounce by lockout: a change of level counts at once, then the line
+/// is ignored for `ms`
+///
+/// Reading the line again once the lockout is over, when nothing else
+/// prompts a look, picks up a change whose edge fell inside it.
+#[derive(Copy, Clone, Debug)]
+pub struct Lockout {
+    level: bool,
+    changed_at: Option<u32>,
+    ms: u32,
+}
+
+impl Lockout {
+    pub const fn new(ms: u32) -> Self {
+        Lockout {
+            level: false,
+            changed_at: None,
+            ms,
+        }
+    }
+
+    pub fn set_ms(&mut self, ms: u32) {
+        self.ms = ms;
+    }
+
+    // The line read `level` at `now`; Some(level) if that counts as a change
+    pub fn sample(&mut self, level: bool, now: u32) -> Option<bool> {
+        if level == self.level {
+            return None;
+        }
+        if let Some(at) = self.changed_at {
+            if now.wrapping_sub(at) < self.ms {
+                return None;
+            }
+        }
+        self.level = level;
+        self.changed_at = Some(now);
+        Some(level)
+    }
+
+    pub fn level(&self) -> bool {
+        self.level
+    }
+
+    // Take `level` as the line's state without it counting as a change
+    pub fn reset(&mut self, level: bool) {
+        self.level = level;
+        self.changed_at = None;
+    }
+}
+
+/// Quadrature encoder counts to detents, handed out one at a time
+///
+/// The timer's counter wraps at 16 bits; only the change since the last
+/// look matters, so it may wrap any number of times as long as less than
+/// half its range passes between looks.
+#[derive(Copy, Clone, Debug)]
+pub struct Detents {
+    last: u16,
+    // Counts seen but not yet handed out as detents
+    pending: i32,
+    per_detent: i32,
+}
+
+impl Detents {
+    pub const fn new(count: u16, per_detent: u16) -> Self {
+        Detents {
+            last: count,
+            pending: 0,
+            per_detent: per_detent as i32,
+        }
+    }
+
+    // Take in the counter's value now
+    pub fn update(&mut self, count: u16) {
+        self.pending += count.wrapping_sub(self.last) as i16 as i32;
+        self.last = count;
+    }
+
+    // One detent turned, if there is one: 1 counting up, -1 down, else 0
+    pub fn take(&mut self) -> i32 {
+        if self.pending >= self.per_detent {
+            self.pending -= self.per_detent;
+            1
+        } else if self.pending <= -self.per_detent {
+            self.pending += self.per_detent;
+            -1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pressed(at: u32) -> InputEvent {
+        InputEvent::Pressed {
+            source: InputSource::Button,
+            at,
+        }
+    }
+
+    #[test]
+    fn queue_is_first_in_first_out_and_bounded() {
+        let queue: EventQueue<4> = EventQueue::new();
+        assert!(queue.push(pressed(1)));
+        assert!(queue.push(pressed(2)));
+        assert!(queue.push(pressed(3)));
+        assert!(!queue.push(pressed(4)));
+        assert_eq!(queue.pop(), Some(pressed(1)));
+        assert!(queue.push(pressed(5)));
+        assert_eq!(queue.pop(), Some(pressed(2)));
+        assert_eq!(queue.pop(), Some(pressed(3)));
+        assert_eq!(queue.pop(), Some(pressed(5)));
+        assert_eq!(queue.pop(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn short_press_is_a_tap_and_bounce_is_nothing() {
+        let mut gestures = Gestures::new();
+        gestures.press(100);
+        assert_eq!(gestures.release(100 + DEBOUNCE_MS - 1), None);
+        gestures.press(200);
+        assert_eq!(gestures.poll(250), None);
+        assert_eq!(gestures.release(300), Some(Gesture::Tap));
+        assert!(!gestures.is_down());
+    }
+
+    #[test]
+    fn second_tap_soon_after_is_a_double() {
+        let mut gestures = Gestures::new();
+        gestures.press(0);
+        assert_eq!(gestures.release(100), Some(Gesture::Tap));
+        gestures.press(100 + DOUBLE_TAP_MS);
+        assert_eq!(gestures.release(500), Some(Gesture::DoubleTap));
+        // A third starts over rather than making another double
+        gestures.press(550);
+        assert_eq!(gestures.release(600), Some(Gesture::Tap));
+        gestures.press(600 + DOUBLE_TAP_MS + 1);
+        assert_eq!(gestures.release(1000), Some(Gesture::Tap));
+    }
+
+    #[test]
+    fn holding_reports_long_press_then_hold_once_each() {
+        let mut gestures = Gestures::new();
+        gestures.press(1000);
+        assert_eq!(
+            gestures.poll(1000 + LONG_PRESS_MS),
+            Some(Gesture::LongPress)
+        );
+        assert_eq!(gestures.poll(1000 + LONG_PRESS_MS + 1), None);
+        assert_eq!(gestures.poll(1000 + HOLD_MS), Some(Gesture::Hold));
+        assert_eq!(gestures.poll(1000 + HOLD_MS * 2), None);
+        assert_eq!(gestures.release(1000 + HOLD_MS * 2), None);
+    }
+
+    #[test]
+    fn lockout_takes_the_first_edge_and_ignores_bounce() {
+        let mut lockout = Lockout::new(10);
+        assert_eq!(lockout.sample(true, 100), Some(true));
+        assert_eq!(lockout.sample(false, 102), None);
+        assert_eq!(lockout.sample(true, 104), None);
+        assert!(lockout.level());
+        assert_eq!(lockout.sample(false, 110), Some(false));
+        // A release lost in the lockout is found by a later look
+        assert_eq!(lockout.sample(true, 200), Some(true));
+        assert_eq!(lockout.sample(false, 205), None);
+        assert_eq!(lockout.sample(false, 215), Some(false));
+    }
+
+    #[test]
+    fn lockout_reset_is_not_a_change() {
+        let mut lockout = Lockout::new(50);
+        lockout.reset(true);
+        assert_eq!(lockout.sample(true, 0), None);
+        assert_eq!(lockout.sample(false, 1), Some(false));
+        lockout.set_ms(0);
+        assert_eq!(lockout.sample(true, 1), Some(true));
+    }
+
+    #[test]
+    fn detents_come_out_one_at_a_time() {
+        let mut detents = Detents::new(100, 4);
+        detents.update(103);
+        assert_eq!(detents.take(), 0);
+        detents.update(111);
+        assert_eq!(detents.take(), 1);
+        assert_eq!(detents.take(), 1);
+        assert_eq!(detents.take(), 0);
+        // Turning back uses up the remainder first
+        detents.update(104);
+        assert_eq!(detents.take(), -1);
+        assert_eq!(detents.take(), 0);
+    }
+
+    #[test]
+    fn detents_follow_the_counter_round_its_wrap() {
+        let mut detents = Detents::new(2, 4);
+        detents.update(u16::MAX - 5);
+        assert_eq!(detents.take(), -1);
+        assert_eq!(detents.take(), -1);
+        assert_eq!(detents.take(), 0);
+        detents.update(6);
+        assert_eq!(detents.take(), 1);
+        assert_eq!(detents.take(), 1);
+        assert_eq!(detents.take(), 1);
+        assert_eq!(detents.take(), 0);
+    }
+
+    #[test]
+    fn timing_survives_the_clock_wrapping() {
+        let mut gestures = Gestures::new();
+        gestures.press(u32::MAX - 10);
+        assert_eq!(gestures.poll(5), None);
+        assert_eq!(gestures.release(40), Some(Gesture::Tap));
+    }
+}
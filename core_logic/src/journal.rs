@@ -0,0 +1,495 @@
+//! Records kept across two flash sectors, safe against a reset mid-write
+//!
+//! Flash is programmed a word at a time and erased a whole sector at a time,
+//! and the power can go or the watchdog fire part way through either. A
+//! `Journal` keeps a few kinds of record (the settings, each course slot) in
+//! a pair of sectors, A and B. A save appends a record to the sector in use;
+//! every record carries a sequence number one past the newest anywhere and a
+//! CRC over all of it, and reading takes the newest intact record of each
+//! kind from either sector. A record cut short fails its CRC and the one
+//! before it is still there. Once the sector in use is full, the other one
+//! is erased and the newest of every kind is written into it, the new record
+//! last; the full sector is left as it was until the next time round, so a
+//! reset during that erase or those writes still finds every record in one
+//! sector or the other. A kind whose newest record is left behind in the
+//! old sector that way is carried forward by the next save.
+//!
+//! Record layout, in words:
+//!   0     MAGIC
+//!   1     kind in the top byte, payload length in words in the low half
+//!   2     sequence number
+//!   3..   payload
+//!   last  CRC-32/MPEG-2 of the words before it (see `crc`)
+//!
+//! `Slots` is the same idea for a record in RAM that survives a reset, such
+//! as backup SRAM: two copies with sequence numbers, the older one
+//! overwritten each time.
+
+use crate::crc;
+
+pub const MAGIC: u32 = 0x4A52_4E31; // "JRN1"
+pub const HEADER_WORDS: usize = 3;
+// Longest payload, in words
+pub const MAX_PAYLOAD: usize = 32;
+const MAX_RECORD: usize = HEADER_WORDS + MAX_PAYLOAD + 1;
+// Erased flash reads as all ones
+const ERASED: u32 = 0xFFFF_FFFF;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum JournalError {
+    // Erasing or programming failed
+    Flash,
+    // A kind out of range, or a payload over MAX_PAYLOAD words
+    BadRecord,
+    // The newest of every kind does not fit in a sector
+    Full,
+}
+
+/// The two sectors, word-addressed from the start of each
+pub trait Sectors {
+    // Words in a sector
+    fn words(&self) -> u32;
+
+    fn read(&self, sector: usize, index: u32) -> u32;
+
+    fn erase(&mut self, sector: usize) -> Result<(), JournalError>;
+
+    // Program `words` from `index` on, into erased flash
+    fn program(&mut self, sector: usize, index: u32, words: &[u32]) -> Result<(), JournalError>;
+
+    // What the firmware has the CRC unit for
+    fn crc(&self, words: &[u32]) -> u32 {
+        crc::mpeg2(words.iter().copied())
+    }
+}
+
+// Whether sequence number `a` comes after `b`, allowing for wrap-around
+pub fn newer(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) > 0
+}
+
+fn record_len(header: u32) -> Option<usize> {
+    let len = (header & 0xFFFF) as usize;
+    (len <= MAX_PAYLOAD).then_some(HEADER_WORDS + len + 1)
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Found {
+    sector: usize,
+    index: u32,
+    seq: u32,
+    len: usize,
+}
+
+/// What is in both sectors
+pub struct Scan<const KINDS: usize> {
+    // The newest intact record of each kind
+    newest: [Option<Found>; KINDS],
+    // Where the next record goes in each sector; None for a sector that is
+    // full or holds something other than records
+    end: [Option<u32>; 2],
+    // The newest sequence number in each, intact records only
+    top: [Option<u32>; 2],
+}
+
+impl<const KINDS: usize> Scan<KINDS> {
+    // The sector the newest record is in
+    pub fn active(&self) -> Option<usize> {
+        match self.top {
+            [Some(a), Some(b)] => Some(if newer(b, a) { 1 } else { 0 }),
+            [Some(_), None] => Some(0),
+            [None, Some(_)] => Some(1),
+            [None, None] => None,
+        }
+    }
+
+    fn next_seq(&self) -> u32 {
+        match self.active() {
+            Some(sector) => self.top[sector].unwrap_or(0).wrapping_add(1),
+            None => 1,
+        }
+    }
+
+    // Words free at the end of `sector`
+    fn room(&self, sector: usize, words: u32) -> u32 {
+        self.end[sector].map_or(0, |end| words - end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.iter().all(Option::is_none)
+    }
+}
+
+pub struct Journal<S: Sectors, const KINDS: usize> {
+    sectors: S,
+}
+
+impl<S: Sectors, const KINDS: usize> Journal<S, KINDS> {
+    pub const fn new(sectors: S) -> Self {
+        Journal { sectors }
+    }
+
+    pub fn sectors(&self) -> &S {
+        &self.sectors
+    }
+
+    // Read the record at `index` of `sector` into `buf`; its length, or None
+    // if there is no intact record there. A header past repair also ends
+    // the sector.
+    fn read_record(
+        &self,
+        sector: usize,
+        index: u32,
+        buf: &mut [u32; MAX_RECORD],
+    ) -> Result<Option<usize>, ()> {
+        let words = self.sectors.words();
+        let len = record_len(self.sectors.read(sector, index + 1)).ok_or(())?;
+        if index + len as u32 > words {
+            return Err(());
+        }
+        for (i, word) in buf[..len].iter_mut().enumerate() {
+            *word = self.sectors.read(sector, index + i as u32);
+        }
+        let (body, crc) = buf[..len].split_at(len - 1);
+        let kind = (body[1] >> 24) as usize;
+        let intact = crc[0] == self.sectors.crc(body) && kind < KINDS;
+        Ok(intact.then_some(len))
+    }
+
+    pub fn scan(&self) -> Scan<KINDS> {
+        let mut scan = Scan {
+            newest: [None; KINDS],
+            end: [None; 2],
+            top: [None; 2],
+        };
+        let words = self.sectors.words();
+        let mut buf = [0; MAX_RECORD];
+        for sector in 0..2 {
+            let mut index = 0;
+            while index < words {
+                match self.sectors.read(sector, index) {
+                    ERASED => {
+                        scan.end[sector] = Some(index);
+                        break;
+                    }
+                    MAGIC => {}
+                    _ => break,
+                }
+                let Ok(read) = self.read_record(sector, index, &mut buf) else {
+                    break;
+                };
+                if let Some(len) = read {
+                    let (kind, seq) = ((buf[1] >> 24) as usize, buf[2]);
+                    let found = Found {
+                        sector,
+                        index,
+                        seq,
+                        len,
+                    };
+                    if scan.newest[kind].is_none_or(|old| newer(seq, old.seq)) {
+                        scan.newest[kind] = Some(found);
+                    }
+                    if scan.top[sector].is_none_or(|top| newer(seq, top)) {
+                        scan.top[sector] = Some(seq);
+                    }
+                }
+                index += match read {
+                    Some(len) => len as u32,
+                    // Cut short: skip it by the length it says it has
+                    None => record_len(buf[1]).unwrap_or(1) as u32,
+                };
+            }
+        }
+        scan
+    }
+
+    // The newest intact payload of `kind` into `payload`, and its length
+    pub fn read(&self, kind: usize, payload: &mut [u32]) -> Option<usize> {
+        let found = self.scan().newest.get(kind).copied().flatten()?;
+        self.copy_payload(found, payload)
+    }
+
+    fn copy_payload(&self, found: Found, payload: &mut [u32]) -> Option<usize> {
+        let len = found.len - HEADER_WORDS - 1;
+        let out = payload.get_mut(..len)?;
+        for (i, word) in out.iter_mut().enumerate() {
+            *word = self
+                .sectors
+                .read(found.sector, found.index + (HEADER_WORDS + i) as u32);
+        }
+        Some(len)
+    }
+
+    fn record(&self, kind: usize, seq: u32, payload: &[u32], buf: &mut [u32; MAX_RECORD]) -> usize {
+        let len = HEADER_WORDS + payload.len() + 1;
+        buf[0] = MAGIC;
+        buf[1] = (kind as u32) << 24 | payload.len() as u32;
+        buf[2] = seq;
+        buf[HEADER_WORDS..len - 1].copy_from_slice(payload);
+        buf[len - 1] = self.sectors.crc(&buf[..len - 1]);
+        len
+    }
+
+    // Store `payload` as the newest of `kind`. When the sector in use has
+    // to be left, kinds with nothing in flash yet are written from
+    // `current` (which may have them from elsewhere, like an older format)
+    // if it has them.
+    pub fn write(
+        &mut self,
+        kind: usize,
+        payload: &[u32],
+        current: impl Fn(usize, &mut [u32]) -> Option<usize>,
+    ) -> Result<(), JournalError> {
+        if kind >= KINDS || payload.len() > MAX_PAYLOAD {
+            return Err(JournalError::BadRecord);
+        }
+        let scan = self.scan();
+        let words = self.sectors.words();
+        let mut seq = scan.next_seq();
+        let mut buf = [0; MAX_RECORD];
+
+        // Kinds whose newest record is in the other sector go along, so the
+        // other sector never holds the only copy of anything
+        if let Some(active) = scan.active() {
+            let behind =
+                |k: usize| k != kind && scan.newest[k].is_some_and(|found| found.sector != active);
+            let carried: u32 = (0..KINDS)
+                .filter(|&k| behind(k))
+                .map(|k| scan.newest[k].map_or(0, |found| found.len as u32))
+                .sum();
+            let needed = carried + (HEADER_WORDS + payload.len() + 1) as u32;
+            if needed <= scan.room(active, words) {
+                let mut at = scan.end[active].unwrap_or(0);
+                let mut old = [0; MAX_PAYLOAD];
+                for k in (0..KINDS).filter(|&k| behind(k)) {
+                    let Some(len) = scan.newest[k].and_then(|f| self.copy_payload(f, &mut old))
+                    else {
+                        continue;
+                    };
+                    let n = self.record(k, seq, &old[..len], &mut buf);
+                    self.sectors.program(active, at, &buf[..n])?;
+                    at += n as u32;
+                    seq = seq.wrapping_add(1);
+                }
+                let n = self.record(kind, seq, payload, &mut buf);
+                return self.sectors.program(active, at, &buf[..n]);
+            }
+        }
+
+        // Start the other sector afresh: everything there is to keep is read
+        // before it is erased, since some of it may be there
+        let to = match scan.active() {
+            Some(active) => 1 - active,
+            None if self.sectors.read(1, 0) == ERASED => 1,
+            None => 0,
+        };
+        let mut kept = [[0; MAX_PAYLOAD]; KINDS];
+        let mut lens = [None; KINDS];
+        for k in (0..KINDS).filter(|&k| k != kind) {
+            lens[k] = match scan.newest[k] {
+                Some(found) => self.copy_payload(found, &mut kept[k]),
+                None => current(k, &mut kept[k]),
+            };
+        }
+        self.sectors.erase(to)?;
+        let mut at = 0;
+        for k in 0..KINDS {
+            let Some(len) = lens[k].filter(|&len| len <= MAX_PAYLOAD) else {
+                continue;
+            };
+            let n = self.record(k, seq, &kept[k][..len], &mut buf);
+            if at + n as u32 > words {
+                return Err(JournalError::Full);
+            }
+            self.sectors.program(to, at, &buf[..n])?;
+            at += n as u32;
+            seq = seq.wrapping_add(1);
+        }
+        let n = self.record(kind, seq, payload, &mut buf);
+        if at + n as u32 > words {
+            return Err(JournalError::Full);
+        }
+        self.sectors.program(to, at, &buf[..n])
+    }
+}
+
+/// A record kept twice over in memory that survives a reset, the older copy
+/// written each time
+pub struct Slots;
+
+impl Slots {
+    // Which of two copies to read: the newer of those intact, given each
+    // one's sequence number if it checks out
+    pub fn newest(a: Option<u32>, b: Option<u32>) -> Option<usize> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if newer(b, a) { 1 } else { 0 }),
+            (Some(_), None) => Some(0),
+            (None, Some(_)) => Some(1),
+            (None, None) => None,
+        }
+    }
+
+    // Which copy to write and the sequence number it gets
+    pub fn next(a: Option<u32>, b: Option<u32>) -> (usize, u32) {
+        match Slots::newest(a, b) {
+            Some(0) => (1, a.unwrap_or(0).wrapping_add(1)),
+            Some(_) => (0, b.unwrap_or(0).wrapping_add(1)),
+            None => (0, 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec;
+    use std::vec::Vec;
+
+    // Two small sectors in RAM; programming stops dead, as a reset would,
+    // once `budget` words have gone
+    struct Ram {
+        sectors: [Vec<u32>; 2],
+        budget: Option<usize>,
+        erases: usize,
+    }
+
+    impl Ram {
+        fn new(words: usize) -> Self {
+            Ram {
+                sectors: [vec![ERASED; words], vec![ERASED; words]],
+                budget: None,
+                erases: 0,
+            }
+        }
+    }
+
+    impl Sectors for Ram {
+        fn words(&self) -> u32 {
+            self.sectors[0].len() as u32
+        }
+
+        fn read(&self, sector: usize, index: u32) -> u32 {
+            self.sectors[sector][index as usize]
+        }
+
+        fn erase(&mut self, sector: usize) -> Result<(), JournalError> {
+            if self.budget == Some(0) {
+                return Err(JournalError::Flash);
+            }
+            self.erases += 1;
+            self.sectors[sector].fill(ERASED);
+            Ok(())
+        }
+
+        fn program(
+            &mut self,
+            sector: usize,
+            index: u32,
+            words: &[u32],
+        ) -> Result<(), JournalError> {
+            for (i, &word) in words.iter().enumerate() {
+                if let Some(budget) = self.budget.as_mut() {
+                    if *budget == 0 {
+                        return Err(JournalError::Flash);
+                    }
+                    *budget -= 1;
+                }
+                let at = &mut self.sectors[sector][index as usize + i];
+                assert_eq!(*at, ERASED, "programming over a programmed word");
+                *at = word;
+            }
+            Ok(())
+        }
+    }
+
+    fn none(_: usize, _: &mut [u32]) -> Option<usize> {
+        None
+    }
+
+    fn read<const K: usize>(journal: &Journal<Ram, K>, kind: usize) -> Option<Vec<u32>> {
+        let mut buf = [0; MAX_PAYLOAD];
+        let len = journal.read(kind, &mut buf)?;
+        Some(buf[..len].to_vec())
+    }
+
+    #[test]
+    fn the_newest_of_each_kind_is_read_back() {
+        let mut journal = Journal::<_, 3>::new(Ram::new(64));
+        assert_eq!(read(&journal, 0), None);
+        journal.write(0, &[1, 2], none).unwrap();
+        journal.write(1, &[7], none).unwrap();
+        journal.write(0, &[3, 4, 5], none).unwrap();
+        assert_eq!(read(&journal, 0), Some(vec![3, 4, 5]));
+        assert_eq!(read(&journal, 1), Some(vec![7]));
+        assert_eq!(read(&journal, 2), None);
+    }
+
+    #[test]
+    fn a_full_sector_moves_everything_to_the_other() {
+        let mut journal = Journal::<_, 2>::new(Ram::new(24));
+        journal.write(1, &[42], none).unwrap();
+        for i in 0..20 {
+            journal.write(0, &[i, i], none).unwrap();
+        }
+        assert!(journal.sectors().erases > 1);
+        assert_eq!(read(&journal, 0), Some(vec![19, 19]));
+        assert_eq!(read(&journal, 1), Some(vec![42]));
+    }
+
+    #[test]
+    fn a_reset_at_any_word_keeps_the_old_or_the_new() {
+        // Saves that go round both sectors, cut short at every word in turn
+        for cut in 0..200 {
+            let mut journal = Journal::<_, 2>::new(Ram::new(20));
+            journal.write(1, &[9, 9, 9], none).unwrap();
+            let mut last = None;
+            journal.sectors.budget = Some(cut);
+            for i in 0..12 {
+                if journal.write(0, &[i; 4], none).is_err() {
+                    break;
+                }
+                last = Some(i);
+            }
+            journal.sectors.budget = None;
+            let got = read(&journal, 0);
+            let expected_old = last.map(|i| vec![i; 4]);
+            let expected_new = Some(vec![last.map_or(0, |i| i + 1); 4]);
+            assert!(
+                got == expected_old || got == expected_new,
+                "cut at {cut}: {got:?}"
+            );
+            assert_eq!(read(&journal, 1), Some(vec![9, 9, 9]), "cut at {cut}");
+            // And it carries on from there
+            journal.write(0, &[100], none).unwrap();
+            assert_eq!(read(&journal, 0), Some(vec![100]));
+            assert_eq!(read(&journal, 1), Some(vec![9, 9, 9]), "cut at {cut}");
+        }
+    }
+
+    #[test]
+    fn kinds_not_in_flash_come_from_elsewhere_on_the_first_move() {
+        let mut ram = Ram::new(16);
+        // Something in an older format fills both sectors
+        ram.sectors[0].fill(0x5345_5447);
+        ram.sectors[1].fill(0x4352_5345);
+        let mut journal = Journal::<_, 2>::new(ram);
+        let older = |kind: usize, buf: &mut [u32]| {
+            buf[0] = 50 + kind as u32;
+            Some(1)
+        };
+        journal.write(0, &[1], older).unwrap();
+        assert_eq!(read(&journal, 0), Some(vec![1]));
+        assert_eq!(read(&journal, 1), Some(vec![51]));
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        assert!(newer(1, 0));
+        assert!(newer(0, u32::MAX));
+        assert!(!newer(5, 5));
+        assert_eq!(Slots::newest(Some(u32::MAX), Some(0)), Some(1));
+        assert_eq!(Slots::next(Some(3), Some(2)), (1, 4));
+        assert_eq!(Slots::next(None, Some(8)), (0, 9));
+        assert_eq!(Slots::next(None, None), (0, 1));
+    }
+}
@@ -0,0 +1,160 @@
+//! Analog thumb-stick readings to game input
+//!
+//! Each axis of the stick is a potentiometer read by the ADC, 0 to
+//! `FULL_SCALE`. Where an axis rests is taken as its centre when the stick
+//! is found, and how far it reaches either way is learned as it is pushed:
+//! the range starts at `MIN_TRAVEL` each side of the centre and widens to
+//! the furthest reading seen, so a stick that never reaches the rails still
+//! gets the whole screen. Readings within the deadzone of the centre count
+//! as the centre, so a stick that does not quite spring back does not
+//! drift the bird.
+//!
+//! The Y axis puts the bird in the playfield, like the tilt: a reading
+//! above the centre is up, and pushing it a quarter of the way either way
+//! is a press, as tilting past the threshold is. Pushing the X axis past
+//! halfway to the right is a press too, to flap without moving the bird.
+
+use crate::config::Coord;
+
+// Largest ADC reading, 12 bits
+pub const FULL_SCALE: u16 = 4095;
+// Reach either side of the centre assumed until the stick goes further
+pub const MIN_TRAVEL: u16 = 1024;
+// Deflection is scaled to -DEFLECTION..=DEFLECTION
+pub const DEFLECTION: i32 = 1000;
+// Readings this close to the centre are the centre, about 6% of the scale
+pub const DEFAULT_DEADZONE: u16 = 250;
+// Y deflection either way, and X to the right, past which the stick counts
+// as pressed
+const PRESS_Y: i32 = DEFLECTION / 4;
+const PRESS_X: i32 = DEFLECTION / 2;
+
+/// One axis: where it rests and the furthest it has been seen each way
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Axis {
+    pub center: u16,
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Axis {
+    // An axis resting at `center`, its reach not yet seen
+    pub fn centered(center: u16) -> Self {
+        let center = center.min(FULL_SCALE);
+        Axis {
+            center,
+            min: center.saturating_sub(MIN_TRAVEL),
+            max: (center + MIN_TRAVEL).min(FULL_SCALE),
+        }
+    }
+
+    // Widen the reach to take in `raw`
+    pub fn observe(&mut self, raw: u16) {
+        self.min = self.min.min(raw);
+        self.max = self.max.max(raw.min(FULL_SCALE));
+    }
+
+    // `raw` from the centre, -DEFLECTION (min) to DEFLECTION (max); 0
+    // within `deadzone` of the centre, counting from its edge beyond
+    pub fn deflection(&self, raw: u16, deadzone: u16) -> i32 {
+        let offset = raw as i32 - self.center as i32;
+        let (reach, past) = if offset >= 0 {
+            (self.max as i32 - self.center as i32, offset)
+        } else {
+            (self.center as i32 - self.min as i32, -offset)
+        };
+        let travel = reach - deadzone as i32;
+        let past = past - deadzone as i32;
+        if past <= 0 || travel <= 0 {
+            return 0;
+        }
+        let scaled = (past * DEFLECTION / travel).min(DEFLECTION);
+        scaled * offset.signum()
+    }
+}
+
+/// A two-axis stick with its calibration
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Joystick {
+    pub x: Axis,
+    pub y: Axis,
+    pub deadzone: u16,
+}
+
+impl Joystick {
+    // A stick resting at (`x`, `y`)
+    pub fn centered(x: u16, y: u16) -> Self {
+        Joystick {
+            x: Axis::centered(x),
+            y: Axis::centered(y),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+
+    // Where a reading of (`x`, `y`) puts the bird between `y_min` and
+    // `y_max`, and whether it is pressed; learns the reach as it goes
+    pub fn sample(&mut self, x: u16, y: u16, y_min: Coord, y_max: Coord) -> (Coord, bool) {
+        self.x.observe(x);
+        self.y.observe(y);
+        let up = self.y.deflection(y, self.deadzone);
+        let middle = (y_min + y_max) / 2;
+        let half = (y_max - y_min) / 2;
+        let row = (middle - up * half / DEFLECTION).clamp(y_min, y_max);
+        let pressed = up.abs() > PRESS_Y || self.x.deflection(x, self.deadzone) > PRESS_X;
+        (row, pressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_reads_as_the_centre() {
+        let axis = Axis::centered(2000);
+        assert_eq!(axis.deflection(2000, 100), 0);
+        assert_eq!(axis.deflection(2099, 100), 0);
+        assert_eq!(axis.deflection(1901, 100), 0);
+        assert!(axis.deflection(2150, 100) > 0);
+        assert!(axis.deflection(1850, 100) < 0);
+    }
+
+    #[test]
+    fn deflection_counts_from_the_deadzone_edge() {
+        let axis = Axis::centered(2000);
+        assert_eq!(axis.deflection(2000 + MIN_TRAVEL, 24), DEFLECTION);
+        assert_eq!(axis.deflection(2000 - MIN_TRAVEL, 24), -DEFLECTION);
+        assert_eq!(axis.deflection(2024 + 500, 24), DEFLECTION / 2);
+        // Past the reach seen so far is still full
+        assert_eq!(axis.deflection(FULL_SCALE, 24), DEFLECTION);
+    }
+
+    #[test]
+    fn reach_widens_as_the_stick_is_pushed() {
+        let mut axis = Axis::centered(2048);
+        assert_eq!((axis.min, axis.max), (1024, 3072));
+        axis.observe(3500);
+        axis.observe(100);
+        assert_eq!((axis.min, axis.max), (100, 3500));
+        assert_eq!(
+            axis.deflection(3072, 0),
+            (3072 - 2048) * DEFLECTION / (3500 - 2048)
+        );
+        // Near the rails the reach is clipped to the scale
+        assert_eq!(Axis::centered(4000).max, FULL_SCALE);
+        assert_eq!(Axis::centered(500).min, 0);
+    }
+
+    #[test]
+    fn y_places_the_bird_and_x_presses() {
+        let mut stick = Joystick::centered(2048, 2048);
+        assert_eq!(stick.sample(2048, 2048, 0, 200), (100, false));
+        assert_eq!(stick.sample(2048, 3072, 0, 200), (0, true));
+        assert_eq!(stick.sample(2048, 1024, 0, 200), (200, true));
+        // A nudge moves the bird without pressing
+        let (row, pressed) = stick.sample(2048, 2048 + 400, 0, 200);
+        assert!(row < 100 && !pressed, "{}", row);
+        assert_eq!(stick.sample(3072, 2048, 0, 200), (100, true));
+        assert_eq!(stick.sample(1024, 2048, 0, 200), (100, false));
+    }
+}
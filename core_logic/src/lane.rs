@@ -0,0 +1,217 @@
+//! Horizontal strips of the screen that each hold a playfield
+//!
+//! The normal game plays in `Lane::FULL`; the two-player split screen stacks
+//! the two `Lane::SPLIT` lanes. Rows in a `Lane` are relative to its `top`.
+//!
+//! `Margins` give the full-screen game a taller or shorter score bar and
+//! ground, for an enclosure whose bezel covers more or less of the panel;
+//! the opening stays on the same rows.
+
+use crate::config::*;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Lane {
+    // Screen row of the lane's first line
+    pub top: Coord,
+    pub height: Coord,
+    // Scoreboard strip above the playfield
+    pub score_height: Coord,
+    // Rows the obstacle opening spans
+    pub gap_top: Coord,
+    pub gap_bottom: Coord,
+    // First row of the ground (plants)
+    pub ground: Coord,
+}
+
+const SPLIT_HEIGHT: Coord = LCD_HEIGHT as Coord / 2;
+
+impl Lane {
+    pub const FULL: Lane = Lane {
+        top: 0,
+        height: GROUND_Y_POS + PLANTS_HEIGHT as Coord,
+        score_height: SCORE_BOARD_HEIGHT as Coord,
+        gap_top: 130,
+        gap_bottom: 130 + OBSTACLE_GAP as Coord,
+        ground: GROUND_Y_POS,
+    };
+
+    // Top and bottom halves of the screen; a smaller scoreboard and obstacles
+    // leave the same 50-row opening as the full-screen game
+    pub const SPLIT: [Lane; 2] = [Lane::split(0), Lane::split(1)];
+
+    const fn split(index: Coord) -> Lane {
+        Lane {
+            top: index * SPLIT_HEIGHT,
+            height: SPLIT_HEIGHT,
+            score_height: 22,
+            gap_top: 62,
+            gap_bottom: 112,
+            ground: SPLIT_HEIGHT - PLANTS_HEIGHT as Coord,
+        }
+    }
+
+    // Screen row of a lane row
+    pub fn y(&self, row: Coord) -> Coord {
+        self.top + row
+    }
+
+    // Screen rows the bird's top edge may take without leaving the playfield
+    pub fn player_y_range(&self) -> (Coord, Coord) {
+        (
+            self.y(self.score_height),
+            self.y(self.ground) - PLAYER_HEIGHT as Coord,
+        )
+    }
+
+    // The lane's area of the screen
+    pub const fn rect(&self) -> Rect {
+        Rect::new(0, self.top, LCD_WIDTH, self.height as u32)
+    }
+
+    // The playfield between the scoreboard and the ground, where the sky is
+    pub const fn field(&self) -> Rect {
+        let rows = (self.ground - self.score_height) as u32;
+        Rect::new(0, self.top + self.score_height, LCD_WIDTH, rows)
+    }
+
+    // The part of rows y..y+h (screen rows) inside the lane
+    pub fn clip(&self, y: Coord, h: u32) -> Option<(Coord, u32)> {
+        let start = y.max(self.top);
+        let end = (y + h as Coord).min(self.top + self.height);
+        (end > start).then(|| (start, (end - start) as u32))
+    }
+}
+
+/// Rows a lane keeps for its score bar at the top and its ground at the
+/// bottom
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Margins {
+    pub top: Coord,
+    pub bottom: Coord,
+}
+
+impl Margins {
+    pub const DEFAULT: Margins = Margins {
+        top: SCORE_BOARD_HEIGHT as Coord,
+        bottom: PLANTS_HEIGHT as Coord,
+    };
+    // Rows each may take, inclusive. The score and the HUD need the whole
+    // default bar; the ground only a strip to crash into. Either at its
+    // most leaves the full-screen opening clear.
+    pub const TOP_RANGE: (Coord, Coord) = (SCORE_BOARD_HEIGHT as Coord, 60);
+    pub const BOTTOM_RANGE: (Coord, Coord) = (8, 50);
+
+    pub fn new(top: Coord, bottom: Coord) -> Option<Self> {
+        let within = |(min, max): (Coord, Coord), rows: Coord| (min..=max).contains(&rows);
+        (within(Self::TOP_RANGE, top) && within(Self::BOTTOM_RANGE, bottom))
+            .then_some(Margins { top, bottom })
+    }
+
+    // `lane` with this score bar and ground
+    pub fn lane(&self, lane: Lane) -> Lane {
+        Lane {
+            score_height: self.top,
+            ground: lane.height - self.bottom,
+            ..lane
+        }
+    }
+
+    // The top in the low byte, the bottom in the next
+    pub fn to_u32(self) -> u32 {
+        (self.top as u32 & 0xFF) | (self.bottom as u32 & 0xFF) << 8
+    }
+
+    pub fn from_u32(word: u32) -> Option<Self> {
+        if word >> 16 != 0 {
+            return None;
+        }
+        Margins::new((word & 0xFF) as Coord, (word >> 8 & 0xFF) as Coord)
+    }
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Margins::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lanes_tile_the_screen() {
+        let [a, b] = Lane::SPLIT;
+        assert_eq!(a.top, 0);
+        assert_eq!(b.top, a.top + a.height);
+        assert_eq!(b.top + b.height, LCD_HEIGHT as Coord);
+    }
+
+    #[test]
+    fn every_lane_has_room_for_the_bird() {
+        for lane in [Lane::FULL, Lane::SPLIT[0], Lane::SPLIT[1]] {
+            assert!(lane.gap_bottom - lane.gap_top > PLAYER_HEIGHT as Coord);
+            assert!(lane.score_height < lane.gap_top);
+            assert!(lane.gap_bottom < lane.ground);
+            assert!(lane.ground < lane.height);
+            let (min, max) = lane.player_y_range();
+            assert!(min < max);
+        }
+    }
+
+    #[test]
+    fn y_is_relative_to_the_lane_top() {
+        assert_eq!(Lane::SPLIT[1].y(0), 160);
+        assert_eq!(Lane::SPLIT[1].y(22), 182);
+        assert_eq!(Lane::FULL.y(30), 30);
+    }
+
+    #[test]
+    fn field_runs_from_scoreboard_to_ground() {
+        assert_eq!(Lane::FULL.field(), Rect::new(0, 30, LCD_WIDTH, 180));
+        assert_eq!(Lane::SPLIT[1].field(), Rect::new(0, 182, LCD_WIDTH, 108));
+    }
+
+    #[test]
+    fn clip_keeps_rows_inside() {
+        let lane = Lane::SPLIT[1];
+        assert_eq!(lane.clip(170, 10), Some((170, 10)));
+        assert_eq!(lane.clip(150, 20), Some((160, 10)));
+        assert_eq!(lane.clip(310, 30), Some((310, 10)));
+        assert_eq!(lane.clip(100, 60), None);
+        assert_eq!(lane.clip(320, 5), None);
+    }
+
+    #[test]
+    fn margins_move_the_edges_and_leave_the_opening() {
+        assert_eq!(Margins::DEFAULT.lane(Lane::FULL), Lane::FULL);
+        let (top, bottom) = (Margins::TOP_RANGE.1, Margins::BOTTOM_RANGE.1);
+        let lane = Margins::new(top, bottom).unwrap().lane(Lane::FULL);
+        assert_eq!(lane.field(), Rect::new(0, 60, LCD_WIDTH, 130));
+        assert_eq!(
+            (lane.gap_top, lane.gap_bottom),
+            (Lane::FULL.gap_top, Lane::FULL.gap_bottom)
+        );
+        assert!(lane.score_height < lane.gap_top && lane.gap_bottom < lane.ground);
+        assert_eq!(lane.player_y_range(), (60, 190 - PLAYER_HEIGHT as Coord));
+    }
+
+    #[test]
+    fn margins_pack_into_a_word_and_refuse_what_does_not_fit() {
+        let margins = Margins::new(44, 12).unwrap();
+        assert_eq!(Margins::from_u32(margins.to_u32()), Some(margins));
+        assert_eq!(Margins::new(10, 30), None);
+        assert_eq!(Margins::new(30, 51), None);
+        assert_eq!(Margins::from_u32(0xFFFF_FFFF), None);
+    }
+
+    #[test]
+    fn clip_of_the_whole_lane_is_the_lane() {
+        let lane = Lane::SPLIT[0];
+        assert_eq!(lane.clip(-20, 400), Some((0, 160)));
+        assert_eq!(
+            Rect::new(-20, -20, 400, 400).intersection(&lane.rect()),
+            Some(lane.rect())
+        );
+    }
+}
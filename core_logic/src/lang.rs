@@ -0,0 +1,719 @@
+//! Text shown to the player, in each language the game speaks
+//!
+//! Every caption, label and hint on the game's screens is a `Msg`, looked
+//! up in the table for the `Language` picked in the settings. Adding a
+//! language is one more table; adding a message is one more arm in each,
+//! which the compiler insists on. Names of things the shell also takes
+//! (game modes, themes, input devices) stay as they are in every language,
+//! as do the score box's digits and "WIN".
+//!
+//! The fonts only draw ASCII, so the tables are written in it: umlauts as
+//! "ae", "oe", "ue", and Spanish without its accents.
+
+/// A language the tables cover, as stored in the settings
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Language {
+    En = 0,
+    De = 1,
+    Es = 2,
+}
+
+impl Language {
+    pub const ALL: [Language; 3] = [Language::En, Language::De, Language::Es];
+
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Language::ALL.get(value as usize).copied()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+            Language::Es => "es",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Language::ALL
+            .into_iter()
+            .find(|language| language.as_str() == name)
+    }
+
+    pub fn next(self) -> Self {
+        Language::ALL[(self as usize + 1) % Language::ALL.len()]
+    }
+
+    pub fn text(self, msg: Msg) -> &'static str {
+        match self {
+            Language::En => en(msg),
+            Language::De => de(msg),
+            Language::Es => es(msg),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Msg {
+    // Pause menu; the ones followed by a value are drawn "Name: value"
+    Paused,
+    Resume,
+    Restart,
+    Mode,
+    Input,
+    Controls,
+    Bright,
+    Retro,
+    // The value of Retro, Invert and the other switches
+    Off,
+    On,
+    RetroLines,
+    Theme,
+    Gamma,
+    Invert,
+    Settings,
+    // Settings page, opened from the pause menu
+    FrameRate,
+    Language,
+    TiltAxis,
+    TiltReversed,
+    DeadZone,
+    TiltCurve,
+    Music,
+    GapPreview,
+    Sound,
+    Difficulty,
+    // Values drawn after a name in the pause menu and settings page
+    Easy,
+    Normal,
+    Hard,
+    Classic,
+    Runner,
+    Signature,
+    TimeAttack,
+    Practice,
+    TwoPlayers,
+    CourseMode,
+    Tilt,
+    Touch,
+    Joystick,
+    Button,
+    Trigger,
+    Flap,
+    Hybrid,
+    Day,
+    Night,
+    Green,
+    Red,
+    HighContrast,
+    Standard,
+    Vivid,
+    Soft,
+    AxisX,
+    AxisY,
+    AxisZ,
+    Linear,
+    Expo,
+    CalibrateTilt,
+    Back,
+    // Title screen
+    GameStartsIn,
+    // Stats page
+    Stats,
+    GamesPlayed,
+    PlayTime,
+    BestScore,
+    BestSetOn,
+    Time,
+    SensorTemp,
+    HoldForEditor,
+    PressForTopTen,
+    // Leaderboard, and the initials for a run that made it
+    TopTen,
+    TimeAttackTopTen,
+    NoRunsYet,
+    MadeTopTen,
+    Rank,
+    PressLetterHoldNext,
+    // Tilt calibration wizard
+    CalibrateTitle,
+    NoTiltSensor,
+    PressToGoBack,
+    MovedTryAgain,
+    LayTheBoard,
+    AsYouWill,
+    HoldIt,
+    PressStartHoldCancel,
+    HoldStill,
+    HoldToCancel,
+    Sensitivity,
+    PressChangeHoldSave,
+    Calibrated,
+    Low,
+    Medium,
+    High,
+    // Course editor
+    Course,
+    Delete,
+    Slot,
+    Save,
+    Play,
+    Saved,
+    SaveFailed,
+    // HUD, over the attempts left in practice
+    Try,
+    // HUD, a moment after a near miss
+    CloseCall,
+    // HUD, the input in use
+    InputTilt,
+    InputTouch,
+    InputStick,
+    InputButton,
+    InputTrigger,
+    // Profile picker, at boot
+    WhoIsPlaying,
+    Guest,
+    NewProfile,
+    Best,
+    // Versus, when the game is over
+    Player1Wins,
+    Player2Wins,
+    Draw,
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Paused => "PAUSED",
+        Msg::Resume => "Resume",
+        Msg::Restart => "Restart",
+        Msg::Mode => "Mode",
+        Msg::Input => "Input",
+        Msg::Controls => "Controls",
+        Msg::Bright => "Bright",
+        Msg::Retro => "Retro",
+        Msg::Off => "off",
+        Msg::On => "on",
+        Msg::RetroLines => "lines",
+        Msg::Theme => "Theme",
+        Msg::Gamma => "Gamma",
+        Msg::Invert => "Invert",
+        Msg::Settings => "Settings",
+        Msg::CalibrateTilt => "Calibrate tilt",
+        Msg::FrameRate => "Frame rate",
+        Msg::Language => "Language",
+        Msg::TiltAxis => "Tilt axis",
+        Msg::TiltReversed => "Axis reversed",
+        Msg::DeadZone => "Dead zone",
+        Msg::TiltCurve => "Curve",
+        Msg::Music => "Music",
+        Msg::GapPreview => "Gap preview",
+        Msg::Sound => "Sound",
+        Msg::Difficulty => "Difficulty",
+        Msg::Easy => "easy",
+        Msg::Normal => "normal",
+        Msg::Hard => "hard",
+        Msg::Classic => "classic",
+        Msg::Runner => "runner",
+        Msg::Signature => "signature",
+        Msg::TimeAttack => "time attack",
+        Msg::Practice => "practice",
+        Msg::TwoPlayers => "2 players",
+        Msg::CourseMode => "course",
+        Msg::Tilt => "tilt",
+        Msg::Touch => "touch",
+        Msg::Joystick => "joystick",
+        Msg::Button => "button",
+        Msg::Trigger => "trigger",
+        Msg::Flap => "flap",
+        Msg::Hybrid => "hybrid",
+        Msg::Day => "Day",
+        Msg::Night => "Night",
+        Msg::Green => "Green",
+        Msg::Red => "Red",
+        Msg::HighContrast => "High contrast",
+        Msg::Standard => "standard",
+        Msg::Vivid => "vivid",
+        Msg::Soft => "soft",
+        Msg::AxisX => "x",
+        Msg::AxisY => "y",
+        Msg::AxisZ => "z",
+        Msg::Linear => "linear",
+        Msg::Expo => "expo",
+        Msg::Back => "Back",
+        Msg::GameStartsIn => "Game Starts In",
+        Msg::Stats => "STATS",
+        Msg::GamesPlayed => "GAMES PLAYED",
+        Msg::PlayTime => "PLAY TIME",
+        Msg::BestScore => "BEST SCORE",
+        Msg::BestSetOn => "BEST SET ON",
+        Msg::Time => "TIME",
+        Msg::SensorTemp => "SENSOR TEMP",
+        Msg::HoldForEditor => "HOLD FOR THE COURSE EDITOR",
+        Msg::PressForTopTen => "PRESS FOR THE TOP 10",
+        Msg::TopTen => "TOP 10",
+        Msg::TimeAttackTopTen => "TOP 10 IN 60 S",
+        Msg::NoRunsYet => "NO RUNS YET",
+        Msg::MadeTopTen => "NEW TOP 10 RUN",
+        Msg::Rank => "RANK",
+        Msg::PressLetterHoldNext => "PRESS: LETTER  HOLD: NEXT",
+        Msg::CalibrateTitle => "CALIBRATE TILT",
+        Msg::NoTiltSensor => "No tilt sensor",
+        Msg::PressToGoBack => "Press to go back",
+        Msg::MovedTryAgain => "Moved, try again",
+        Msg::LayTheBoard => "Lay the board",
+        Msg::AsYouWill => "as you will",
+        Msg::HoldIt => "hold it",
+        Msg::PressStartHoldCancel => "Press to start, hold to cancel",
+        Msg::HoldStill => "Hold still...",
+        Msg::HoldToCancel => "Hold to cancel",
+        Msg::Sensitivity => "Sensitivity",
+        Msg::PressChangeHoldSave => "Press to change, hold to save",
+        Msg::Calibrated => "Calibrated",
+        Msg::Low => "Low",
+        Msg::Medium => "Medium",
+        Msg::High => "High",
+        Msg::Course => "Course",
+        Msg::Delete => "Del",
+        Msg::Slot => "Slot",
+        Msg::Save => "Save",
+        Msg::Play => "Play",
+        Msg::Saved => "Saved",
+        Msg::SaveFailed => "Save failed",
+        Msg::Try => "TRY",
+        Msg::CloseCall => "CLOSE CALL",
+        Msg::InputTilt => "TILT",
+        Msg::InputTouch => "TOUCH",
+        Msg::InputStick => "STICK",
+        Msg::InputButton => "BUTTON",
+        Msg::InputTrigger => "TRIGGER",
+        Msg::WhoIsPlaying => "WHO IS PLAYING?",
+        Msg::Guest => "Guest",
+        Msg::NewProfile => "new",
+        Msg::Best => "best",
+        Msg::Player1Wins => "PLAYER 1 WINS",
+        Msg::Player2Wins => "PLAYER 2 WINS",
+        Msg::Draw => "DRAW",
+    }
+}
+
+fn de(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Paused => "PAUSE",
+        Msg::Resume => "Weiter",
+        Msg::Restart => "Neustart",
+        Msg::Mode => "Modus",
+        Msg::Input => "Eingabe",
+        Msg::Controls => "Steuerung",
+        Msg::Bright => "Hell",
+        Msg::Retro => "Retro",
+        Msg::Off => "aus",
+        Msg::On => "an",
+        Msg::RetroLines => "Zeilen",
+        Msg::Theme => "Thema",
+        Msg::Gamma => "Gamma",
+        Msg::Invert => "Invertiert",
+        Msg::Settings => "Einstellungen",
+        Msg::CalibrateTilt => "Neigung kalibrieren",
+        Msg::FrameRate => "Bildrate",
+        Msg::Language => "Sprache",
+        Msg::TiltAxis => "Neigeachse",
+        Msg::TiltReversed => "Achse umgekehrt",
+        Msg::DeadZone => "Totzone",
+        Msg::TiltCurve => "Kurve",
+        Msg::Music => "Musik",
+        Msg::GapPreview => "Lueckenvorschau",
+        Msg::Sound => "Ton",
+        Msg::Difficulty => "Schwierigkeit",
+        Msg::Easy => "leicht",
+        Msg::Normal => "normal",
+        Msg::Hard => "schwer",
+        Msg::Classic => "klassisch",
+        Msg::Runner => "Laeufer",
+        Msg::Signature => "Signatur",
+        Msg::TimeAttack => "Zeitrennen",
+        Msg::Practice => "Training",
+        Msg::TwoPlayers => "2 Spieler",
+        Msg::CourseMode => "Strecke",
+        Msg::Tilt => "Neigen",
+        Msg::Touch => "Touch",
+        Msg::Joystick => "Joystick",
+        Msg::Button => "Taste",
+        Msg::Trigger => "Ausloeser",
+        Msg::Flap => "Flattern",
+        Msg::Hybrid => "hybrid",
+        Msg::Day => "Tag",
+        Msg::Night => "Nacht",
+        Msg::Green => "Gruen",
+        Msg::Red => "Rot",
+        Msg::HighContrast => "Kontrast",
+        Msg::Standard => "Standard",
+        Msg::Vivid => "kraeftig",
+        Msg::Soft => "sanft",
+        Msg::AxisX => "x",
+        Msg::AxisY => "y",
+        Msg::AxisZ => "z",
+        Msg::Linear => "linear",
+        Msg::Expo => "expo",
+        Msg::Back => "Zurueck",
+        Msg::GameStartsIn => "Spiel startet in",
+        Msg::Stats => "STATISTIK",
+        Msg::GamesPlayed => "SPIELE",
+        Msg::PlayTime => "SPIELZEIT",
+        Msg::BestScore => "BESTWERT",
+        Msg::BestSetOn => "BESTWERT VOM",
+        Msg::Time => "UHRZEIT",
+        Msg::SensorTemp => "SENSORTEMPERATUR",
+        Msg::HoldForEditor => "HALTEN FUER DEN STRECKENEDITOR",
+        Msg::PressForTopTen => "DRUECKEN FUER DIE BESTENLISTE",
+        Msg::TopTen => "BESTENLISTE",
+        Msg::TimeAttackTopTen => "BESTENLISTE 60 S",
+        Msg::NoRunsYet => "NOCH KEINE LAEUFE",
+        Msg::MadeTopTen => "IN DEN TOP 10",
+        Msg::Rank => "PLATZ",
+        Msg::PressLetterHoldNext => "DRUECKEN: BUCHSTABE  HALTEN: WEITER",
+        Msg::CalibrateTitle => "NEIGUNG KALIBRIEREN",
+        Msg::NoTiltSensor => "Kein Neigungssensor",
+        Msg::PressToGoBack => "Druecken fuer zurueck",
+        Msg::MovedTryAgain => "Bewegt, nochmal",
+        Msg::LayTheBoard => "Halte das Board",
+        Msg::AsYouWill => "so, wie du",
+        Msg::HoldIt => "spielen wirst",
+        Msg::PressStartHoldCancel => "Druecken: Start, halten: Abbruch",
+        Msg::HoldStill => "Stillhalten...",
+        Msg::HoldToCancel => "Halten: Abbruch",
+        Msg::Sensitivity => "Empfindlichkeit",
+        Msg::PressChangeHoldSave => "Druecken: aendern, halten: sichern",
+        Msg::Calibrated => "Kalibriert",
+        Msg::Low => "Niedrig",
+        Msg::Medium => "Mittel",
+        Msg::High => "Hoch",
+        Msg::Course => "Strecke",
+        Msg::Delete => "Entf",
+        Msg::Slot => "Platz",
+        Msg::Save => "Sich.",
+        Msg::Play => "Los",
+        Msg::Saved => "Gesichert",
+        Msg::SaveFailed => "Fehler",
+        Msg::Try => "VERS",
+        Msg::CloseCall => "KNAPP!",
+        Msg::InputTilt => "NEIGEN",
+        Msg::InputTouch => "TOUCH",
+        Msg::InputStick => "STICK",
+        Msg::InputButton => "TASTE",
+        Msg::InputTrigger => "AUSLOESER",
+        Msg::WhoIsPlaying => "WER SPIELT?",
+        Msg::Guest => "Gast",
+        Msg::NewProfile => "neu",
+        Msg::Best => "Rekord",
+        Msg::Player1Wins => "SPIELER 1 GEWINNT",
+        Msg::Player2Wins => "SPIELER 2 GEWINNT",
+        Msg::Draw => "UNENTSCHIEDEN",
+    }
+}
+
+fn es(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Paused => "PAUSA",
+        Msg::Resume => "Continuar",
+        Msg::Restart => "Reiniciar",
+        Msg::Mode => "Modo",
+        Msg::Input => "Entrada",
+        Msg::Controls => "Control",
+        Msg::Bright => "Brillo",
+        Msg::Retro => "Retro",
+        Msg::Off => "no",
+        Msg::On => "si",
+        Msg::RetroLines => "lineas",
+        Msg::Theme => "Tema",
+        Msg::Gamma => "Gamma",
+        Msg::Invert => "Invertir",
+        Msg::Settings => "Ajustes",
+        Msg::CalibrateTilt => "Calibrar inclinacion",
+        Msg::FrameRate => "Imagenes/s",
+        Msg::Language => "Idioma",
+        Msg::TiltAxis => "Eje de inclinacion",
+        Msg::TiltReversed => "Eje invertido",
+        Msg::DeadZone => "Zona muerta",
+        Msg::TiltCurve => "Curva",
+        Msg::Music => "Musica",
+        Msg::GapPreview => "Ver el hueco",
+        Msg::Sound => "Sonido",
+        Msg::Difficulty => "Dificultad",
+        Msg::Easy => "facil",
+        Msg::Normal => "normal",
+        Msg::Hard => "dificil",
+        Msg::Classic => "clasico",
+        Msg::Runner => "corredor",
+        Msg::Signature => "firma",
+        Msg::TimeAttack => "contrarreloj",
+        Msg::Practice => "practica",
+        Msg::TwoPlayers => "2 jugadores",
+        Msg::CourseMode => "pista",
+        Msg::Tilt => "inclinar",
+        Msg::Touch => "tactil",
+        Msg::Joystick => "joystick",
+        Msg::Button => "boton",
+        Msg::Trigger => "disparador",
+        Msg::Flap => "aletear",
+        Msg::Hybrid => "hibrido",
+        Msg::Day => "Dia",
+        Msg::Night => "Noche",
+        Msg::Green => "Verde",
+        Msg::Red => "Rojo",
+        Msg::HighContrast => "Contraste",
+        Msg::Standard => "estandar",
+        Msg::Vivid => "vivo",
+        Msg::Soft => "suave",
+        Msg::AxisX => "x",
+        Msg::AxisY => "y",
+        Msg::AxisZ => "z",
+        Msg::Linear => "lineal",
+        Msg::Expo => "expo",
+        Msg::Back => "Volver",
+        Msg::GameStartsIn => "Empieza en",
+        Msg::Stats => "ESTADISTICAS",
+        Msg::GamesPlayed => "PARTIDAS",
+        Msg::PlayTime => "TIEMPO DE JUEGO",
+        Msg::BestScore => "MEJOR PUNTUACION",
+        Msg::BestSetOn => "MEJOR DEL",
+        Msg::Time => "HORA",
+        Msg::SensorTemp => "TEMP. DEL SENSOR",
+        Msg::HoldForEditor => "MANTEN PARA EL EDITOR DE PISTAS",
+        Msg::PressForTopTen => "PULSA PARA VER LOS 10 MEJORES",
+        Msg::TopTen => "LOS 10 MEJORES",
+        Msg::TimeAttackTopTen => "MEJORES EN 60 S",
+        Msg::NoRunsYet => "SIN PARTIDAS AUN",
+        Msg::MadeTopTen => "ENTRE LOS 10 MEJORES",
+        Msg::Rank => "PUESTO",
+        Msg::PressLetterHoldNext => "PULSA: LETRA  MANTEN: SIGUIENTE",
+        Msg::CalibrateTitle => "CALIBRAR INCLINACION",
+        Msg::NoTiltSensor => "Sin sensor de giro",
+        Msg::PressToGoBack => "Pulsa para volver",
+        Msg::MovedTryAgain => "Se movio, otra vez",
+        Msg::LayTheBoard => "Sujeta la placa",
+        Msg::AsYouWill => "como la vas",
+        Msg::HoldIt => "a sujetar",
+        Msg::PressStartHoldCancel => "Pulsa: empezar, manten: cancelar",
+        Msg::HoldStill => "Quieto...",
+        Msg::HoldToCancel => "Manten: cancelar",
+        Msg::Sensitivity => "Sensibilidad",
+        Msg::PressChangeHoldSave => "Pulsa: cambiar, manten: guardar",
+        Msg::Calibrated => "Calibrado",
+        Msg::Low => "Baja",
+        Msg::Medium => "Media",
+        Msg::High => "Alta",
+        Msg::Course => "Pista",
+        Msg::Delete => "Borr",
+        Msg::Slot => "Ranura",
+        Msg::Save => "Guard",
+        Msg::Play => "Jugar",
+        Msg::Saved => "Guardada",
+        Msg::SaveFailed => "Error",
+        Msg::Try => "INT",
+        Msg::CloseCall => "POR POCO!",
+        Msg::InputTilt => "INCLINAR",
+        Msg::InputTouch => "TACTIL",
+        Msg::InputStick => "PALANCA",
+        Msg::InputButton => "BOTON",
+        Msg::InputTrigger => "GATILLO",
+        Msg::WhoIsPlaying => "QUIEN JUEGA?",
+        Msg::Guest => "Invitado",
+        Msg::NewProfile => "nuevo",
+        Msg::Best => "record",
+        Msg::Player1Wins => "GANA JUGADOR 1",
+        Msg::Player2Wins => "GANA JUGADOR 2",
+        Msg::Draw => "EMPATE",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every message, to walk the tables
+    const ALL: [Msg; 110] = [
+        Msg::Paused,
+        Msg::Resume,
+        Msg::Restart,
+        Msg::Mode,
+        Msg::Input,
+        Msg::Controls,
+        Msg::Bright,
+        Msg::Retro,
+        Msg::Off,
+        Msg::On,
+        Msg::RetroLines,
+        Msg::Theme,
+        Msg::Gamma,
+        Msg::Invert,
+        Msg::Settings,
+        Msg::CalibrateTilt,
+        Msg::FrameRate,
+        Msg::Language,
+        Msg::TiltAxis,
+        Msg::TiltReversed,
+        Msg::DeadZone,
+        Msg::TiltCurve,
+        Msg::Music,
+        Msg::GapPreview,
+        Msg::Sound,
+        Msg::Difficulty,
+        Msg::Easy,
+        Msg::Normal,
+        Msg::Hard,
+        Msg::Classic,
+        Msg::Runner,
+        Msg::Signature,
+        Msg::TimeAttack,
+        Msg::Practice,
+        Msg::TwoPlayers,
+        Msg::CourseMode,
+        Msg::Tilt,
+        Msg::Touch,
+        Msg::Joystick,
+        Msg::Button,
+        Msg::Trigger,
+        Msg::Flap,
+        Msg::Hybrid,
+        Msg::Day,
+        Msg::Night,
+        Msg::Green,
+        Msg::Red,
+        Msg::HighContrast,
+        Msg::Standard,
+        Msg::Vivid,
+        Msg::Soft,
+        Msg::AxisX,
+        Msg::AxisY,
+        Msg::AxisZ,
+        Msg::Linear,
+        Msg::Expo,
+        Msg::Back,
+        Msg::GameStartsIn,
+        Msg::Stats,
+        Msg::GamesPlayed,
+        Msg::PlayTime,
+        Msg::BestScore,
+        Msg::BestSetOn,
+        Msg::Time,
+        Msg::SensorTemp,
+        Msg::HoldForEditor,
+        Msg::PressForTopTen,
+        Msg::TopTen,
+        Msg::TimeAttackTopTen,
+        Msg::NoRunsYet,
+        Msg::MadeTopTen,
+        Msg::Rank,
+        Msg::PressLetterHoldNext,
+        Msg::CalibrateTitle,
+        Msg::NoTiltSensor,
+        Msg::PressToGoBack,
+        Msg::MovedTryAgain,
+        Msg::LayTheBoard,
+        Msg::AsYouWill,
+        Msg::HoldIt,
+        Msg::PressStartHoldCancel,
+        Msg::HoldStill,
+        Msg::HoldToCancel,
+        Msg::Sensitivity,
+        Msg::PressChangeHoldSave,
+        Msg::Calibrated,
+        Msg::Low,
+        Msg::Medium,
+        Msg::High,
+        Msg::Course,
+        Msg::Delete,
+        Msg::Slot,
+        Msg::Save,
+        Msg::Play,
+        Msg::Saved,
+        Msg::SaveFailed,
+        Msg::Try,
+        Msg::CloseCall,
+        Msg::InputTilt,
+        Msg::InputTouch,
+        Msg::InputStick,
+        Msg::InputButton,
+        Msg::InputTrigger,
+        Msg::WhoIsPlaying,
+        Msg::Guest,
+        Msg::NewProfile,
+        Msg::Best,
+        Msg::Player1Wins,
+        Msg::Player2Wins,
+        Msg::Draw,
+    ];
+
+    // Characters across the 240-pixel screen in the font each is drawn in:
+    // hints and captions in 6x10, the editor's tool buttons in 10x20 a
+    // third of the width, the HUD labels beside the attempts and the score,
+    // and the rest in 10x20 (or the narrower UI18)
+    fn room(msg: Msg) -> usize {
+        match msg {
+            Msg::HoldForEditor
+            | Msg::PressForTopTen
+            | Msg::PressLetterHoldNext
+            | Msg::Rank
+            | Msg::PressToGoBack
+            | Msg::PressStartHoldCancel
+            | Msg::HoldToCancel
+            | Msg::PressChangeHoldSave
+            | Msg::GamesPlayed
+            | Msg::PlayTime
+            | Msg::BestScore
+            | Msg::BestSetOn
+            | Msg::Time
+            | Msg::SensorTemp => 40,
+            Msg::Delete | Msg::Slot | Msg::Save | Msg::Play => 6,
+            Msg::Try => 4,
+            Msg::CloseCall
+            | Msg::InputTilt
+            | Msg::InputTouch
+            | Msg::InputStick
+            | Msg::InputButton
+            | Msg::InputTrigger => 10,
+            _ => 24,
+        }
+    }
+
+    #[test]
+    fn every_message_is_drawable_ascii_that_fits() {
+        for language in Language::ALL {
+            for msg in ALL {
+                let text = language.text(msg);
+                assert!(!text.is_empty(), "{:?} {:?}", language, msg);
+                assert!(
+                    text.bytes().all(|b| (b' '..=b'~').contains(&b)),
+                    "{:?} {:?}: {}",
+                    language,
+                    msg,
+                    text
+                );
+                assert!(
+                    text.len() <= room(msg),
+                    "{:?} {:?}: {}",
+                    language,
+                    msg,
+                    text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn languages_round_trip_their_codes() {
+        for language in Language::ALL {
+            assert_eq!(Language::from_u32(language as u32), Some(language));
+            assert_eq!(Language::parse(language.as_str()), Some(language));
+        }
+        assert_eq!(Language::from_u32(3), None);
+        assert_eq!(Language::parse("fr"), None);
+        assert_eq!(Language::Es.next(), Language::En);
+    }
+
+    #[test]
+    fn languages_differ() {
+        assert_eq!(Language::En.text(Msg::Resume), "Resume");
+        assert_eq!(Language::De.text(Msg::Resume), "Weiter");
+        assert_eq!(Language::Es.text(Msg::Resume), "Continuar");
+    }
+}
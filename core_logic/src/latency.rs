@@ -0,0 +1,207 @@
+//! Input latency: from an input coming in to the frame that acted on it
+//!
+//! Every input the bird can act on is stamped as it arrives: a press or
+//! release from its EXTI interrupt or the touch sampling as a `Kind::Tap`,
+//! a tilt or stick sample that moved as a `Kind::Steer`. Each kind keeps a
+//! stamp of its own, so a flap is never timed from a tilt sample that came
+//! in after the press. When a frame's update flaps the bird, the latest tap
+//! stamp becomes that frame's, and when it steers on a new sample the
+//! latest steer stamp does; once the frame has gone out to the panel the
+//! time since each is one sample. The latest rather than the earliest,
+//! because the input that acts is the last one in: a button flaps on its
+//! release, not its press.
+//!
+//! An input that came in before a frame started and that the frame did not
+//! act on is dropped at its end, so a press on a menu is not timed against
+//! a flap much later. One that came in part way through a frame, after the
+//! input was read, waits for the next.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Report {
+    pub samples: u32,
+    pub average_us: u32,
+    pub worst_us: u32,
+    pub last_us: u32,
+}
+
+/// What an input does to the bird
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Kind {
+    // A press or release, which flaps
+    Tap,
+    // A tilt or stick sample, which steers
+    Steer,
+}
+
+const KINDS: usize = 2;
+
+#[derive(Copy, Clone, Debug)]
+pub struct InputLatency {
+    // Latest input of each kind not yet acted on, and the ones this frame
+    // acted on
+    pending: [Option<u64>; KINDS],
+    acting: [Option<u64>; KINDS],
+    frame_start: u64,
+    samples: u32,
+    total_us: u64,
+    worst_us: u32,
+    last_us: u32,
+}
+
+impl InputLatency {
+    pub const fn new() -> Self {
+        InputLatency {
+            pending: [None; KINDS],
+            acting: [None; KINDS],
+            frame_start: 0,
+            samples: 0,
+            total_us: 0,
+            worst_us: 0,
+            last_us: 0,
+        }
+    }
+
+    // An input of `kind` came in at `at_us`
+    pub fn arrived(&mut self, kind: Kind, at_us: u64) {
+        self.pending[kind as usize] = Some(at_us);
+    }
+
+    // This frame's update moved the bird on the input of `kind` so far
+    pub fn acted(&mut self, kind: Kind) {
+        if let Some(at) = self.pending[kind as usize].take() {
+            self.acting[kind as usize] = Some(at);
+        }
+    }
+
+    // The frame is on the panel at `now_us`; the longer of its samples, if
+    // it acted on anything
+    pub fn end_frame(&mut self, now_us: u64) -> Option<u32> {
+        let mut longest = None;
+        for acting in self.acting.iter_mut() {
+            let Some(at) = acting.take() else {
+                continue;
+            };
+            let us = now_us.saturating_sub(at).min(u32::MAX as u64) as u32;
+            self.samples += 1;
+            self.total_us += us as u64;
+            self.worst_us = self.worst_us.max(us);
+            self.last_us = us;
+            longest = longest.max(Some(us));
+        }
+        for pending in self.pending.iter_mut() {
+            if pending.is_some_and(|at| at < self.frame_start) {
+                *pending = None;
+            }
+        }
+        self.frame_start = now_us;
+        longest
+    }
+
+    // Forget the samples so far; inputs in flight are still timed
+    pub fn reset(&mut self) {
+        self.samples = 0;
+        self.total_us = 0;
+        self.worst_us = 0;
+        self.last_us = 0;
+    }
+
+    pub fn report(&self) -> Report {
+        Report {
+            samples: self.samples,
+            average_us: (self.total_us / self.samples.max(1) as u64) as u32,
+            worst_us: self.worst_us,
+            last_us: self.last_us,
+        }
+    }
+}
+
+impl Default for InputLatency {
+    fn default() -> Self {
+        InputLatency::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_from_the_latest_input_to_the_frame_out() {
+        let mut latency = InputLatency::new();
+        latency.end_frame(1_000);
+        latency.arrived(Kind::Tap, 1_500);
+        latency.arrived(Kind::Tap, 2_000);
+        latency.acted(Kind::Tap);
+        assert_eq!(latency.end_frame(17_000), Some(15_000));
+        // Nothing acted on, nothing timed
+        assert_eq!(latency.end_frame(33_000), None);
+        assert_eq!(latency.report().samples, 1);
+    }
+
+    #[test]
+    fn an_input_left_alone_for_a_frame_is_dropped() {
+        let mut latency = InputLatency::new();
+        latency.end_frame(1_000);
+        // Part way through a frame, after its input was read
+        latency.arrived(Kind::Tap, 10_000);
+        assert_eq!(latency.end_frame(17_000), None);
+        // Still in time for the next one
+        latency.acted(Kind::Tap);
+        assert_eq!(latency.end_frame(33_000), Some(23_000));
+
+        latency.arrived(Kind::Tap, 40_000);
+        latency.end_frame(49_000);
+        latency.end_frame(65_000);
+        latency.acted(Kind::Tap);
+        assert_eq!(latency.end_frame(81_000), None);
+    }
+
+    #[test]
+    fn taps_and_steers_are_timed_apart() {
+        let mut latency = InputLatency::new();
+        latency.end_frame(1_000);
+        latency.arrived(Kind::Tap, 2_000);
+        // Tilt samples keep coming after the press
+        latency.arrived(Kind::Steer, 9_000);
+        latency.arrived(Kind::Steer, 12_000);
+        latency.acted(Kind::Tap);
+        assert_eq!(latency.end_frame(17_000), Some(15_000));
+        assert_eq!(latency.report().samples, 1);
+
+        // A steer on its own sample, then both in one frame
+        latency.arrived(Kind::Steer, 20_000);
+        latency.acted(Kind::Steer);
+        assert_eq!(latency.end_frame(33_000), Some(13_000));
+        latency.arrived(Kind::Tap, 34_000);
+        latency.arrived(Kind::Steer, 40_000);
+        latency.acted(Kind::Tap);
+        latency.acted(Kind::Steer);
+        assert_eq!(latency.end_frame(49_000), Some(15_000));
+        let report = latency.report();
+        assert_eq!(report.samples, 4);
+        assert_eq!(report.worst_us, 15_000);
+    }
+
+    #[test]
+    fn reports_average_and_worst_until_reset() {
+        let mut latency = InputLatency::new();
+        for (frame, us) in [10_000, 30_000, 20_000].into_iter().enumerate() {
+            let end = 100_000 * (frame as u64 + 1);
+            latency.arrived(Kind::Steer, end - us);
+            latency.acted(Kind::Steer);
+            latency.end_frame(end);
+        }
+        let report = latency.report();
+        assert_eq!(
+            report,
+            Report {
+                samples: 3,
+                average_us: 20_000,
+                worst_us: 30_000,
+                last_us: 20_000,
+            }
+        );
+        latency.reset();
+        assert_eq!(latency.report(), Report::default());
+    }
+}
@@ -0,0 +1,227 @@
+//! The ten best runs, and the initials put to them
+//!
+//! A `Table` keeps its entries best first. A run makes the table when there
+//! is still room or it beats the last entry; a run that ties an entry goes
+//! below it, so the earlier run keeps its place. A run that scored nothing
+//! never makes it.
+//!
+//! `Initials` is the entry of the three letters, one at a time: the letter
+//! under the cursor steps through A to Z, wrapping, and confirming it moves
+//! on to the next.
+
+pub const SIZE: usize = 10;
+pub const INITIALS: usize = 3;
+
+/// One run on the table
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Entry {
+    pub score: u32,
+    // ASCII capitals
+    pub initials: [u8; INITIALS],
+    // When it was run, rtc timestamp
+    pub at: u32,
+}
+
+impl Entry {
+    pub fn initials(&self) -> &str {
+        core::str::from_utf8(&self.initials).unwrap_or("???")
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Table {
+    entries: [Entry; SIZE],
+    len: u32,
+}
+
+impl Table {
+    pub const fn new() -> Self {
+        Table {
+            entries: [Entry {
+                score: 0,
+                initials: [0; INITIALS],
+                at: 0,
+            }; SIZE],
+            len: 0,
+        }
+    }
+
+    // Best first
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries[..(self.len as usize).min(SIZE)]
+    }
+
+    // Where a run of `score` would go, counting from 0, or None if it does
+    // not make the table
+    pub fn rank(&self, score: u32) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        let entries = self.entries();
+        let rank = entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(entries.len());
+        (rank < SIZE).then_some(rank)
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        self.rank(score).is_some()
+    }
+
+    // Put `entry` in its place, pushing the last one off a full table; its
+    // rank, or None if it did not make it
+    pub fn insert(&mut self, entry: Entry) -> Option<usize> {
+        let rank = self.rank(entry.score)?;
+        let len = (self.len as usize).min(SIZE);
+        let end = if len < SIZE { len + 1 } else { SIZE };
+        self.entries.copy_within(rank..end - 1, rank + 1);
+        self.entries[rank] = entry;
+        self.len = end as u32;
+        Some(rank)
+    }
+
+    // Whether every entry is in order and spelled in capitals, as a table
+    // read back from storage should be
+    pub fn is_valid(&self) -> bool {
+        let entries = self.entries();
+        self.len as usize <= SIZE
+            && entries
+                .windows(2)
+                .all(|pair| pair[0].score >= pair[1].score)
+            && entries
+                .iter()
+                .all(|entry| entry.score != 0 && entry.initials.iter().all(u8::is_ascii_uppercase))
+    }
+}
+
+/// Three letters being entered
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Initials {
+    letters: [u8; INITIALS],
+    cursor: usize,
+}
+
+impl Initials {
+    // Starting from `letters`, as the last ones entered; anything but
+    // capitals starts from A
+    pub fn new(letters: [u8; INITIALS]) -> Self {
+        Initials {
+            letters: letters.map(|b| if b.is_ascii_uppercase() { b } else { b'A' }),
+            cursor: 0,
+        }
+    }
+
+    pub fn letters(&self) -> [u8; INITIALS] {
+        self.letters
+    }
+
+    // Which letter is being changed
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // Step the letter under the cursor, wrapping Z to A and back
+    pub fn step(&mut self, forward: bool) {
+        let letter = &mut self.letters[self.cursor];
+        let index = *letter - b'A';
+        let index = if forward {
+            (index + 1) % 26
+        } else {
+            (index + 25) % 26
+        };
+        *letter = b'A' + index;
+    }
+
+    // Keep the letter under the cursor and move on; true once the last
+    // one is kept
+    pub fn confirm(&mut self) -> bool {
+        if self.cursor + 1 < INITIALS {
+            self.cursor += 1;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(score: u32) -> Entry {
+        Entry {
+            score,
+            initials: *b"ABC",
+            at: score,
+        }
+    }
+
+    fn scores(table: &Table) -> Vec<u32> {
+        table.entries().iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn keeps_the_best_ten_best_first() {
+        let mut table = Table::new();
+        assert!(table.entries().is_empty());
+        assert_eq!(table.insert(run(5)), Some(0));
+        assert_eq!(table.insert(run(9)), Some(0));
+        assert_eq!(table.insert(run(7)), Some(1));
+        assert_eq!(scores(&table), [9, 7, 5]);
+        for score in 10..17 {
+            table.insert(run(score));
+        }
+        assert_eq!(scores(&table), [16, 15, 14, 13, 12, 11, 10, 9, 7, 5]);
+        // Full: the last entry is pushed off, and a run no better is turned away
+        assert_eq!(table.insert(run(8)), Some(8));
+        assert_eq!(scores(&table), [16, 15, 14, 13, 12, 11, 10, 9, 8, 7]);
+        assert!(!table.qualifies(7));
+        assert_eq!(table.insert(run(3)), None);
+        assert_eq!(scores(&table).len(), SIZE);
+        assert!(table.is_valid());
+    }
+
+    #[test]
+    fn ties_go_below_and_zero_never_counts() {
+        let mut table = Table::new();
+        assert!(!table.qualifies(0));
+        table.insert(run(4));
+        let mut tie = run(4);
+        tie.initials = *b"ZZZ";
+        assert_eq!(table.insert(tie), Some(1));
+        assert_eq!(table.entries()[0].initials(), "ABC");
+        assert_eq!(table.entries()[1].initials(), "ZZZ");
+    }
+
+    #[test]
+    fn garbage_is_not_a_table() {
+        assert!(Table::new().is_valid());
+        let mut table = Table::new();
+        table.insert(run(3));
+        let mut bad = table;
+        bad.len = 11;
+        assert!(!bad.is_valid());
+        let mut bad = table;
+        bad.entries[0].initials = [0; INITIALS];
+        assert!(!bad.is_valid());
+    }
+
+    #[test]
+    fn initials_step_and_wrap_one_letter_at_a_time() {
+        let mut initials = Initials::new(*b"A\0Q");
+        assert_eq!(initials.letters(), *b"AAQ");
+        initials.step(false);
+        assert_eq!(initials.letters(), *b"ZAQ");
+        initials.step(true);
+        initials.step(true);
+        assert!(!initials.confirm());
+        assert_eq!(initials.cursor(), 1);
+        initials.step(true);
+        assert!(!initials.confirm());
+        assert!(initials.confirm());
+        assert_eq!(initials.letters(), *b"BBQ");
+    }
+}
@@ -0,0 +1,93 @@
+//! How far the bird is turned as drawn
+//!
+//! The bird noses up as it climbs and down as it falls, from `MIN_TURN`
+//! (about 30 degrees up) to `MAX_TURN` (straight down), in the sprite
+//! cache's steps of a 64th of a turn. On top of that it leans with the
+//! board while the board is being turned in its own plane, read from the
+//! gyro's Z rate: turning the board counterclockwise tips the nose down, as
+//! if the bird kept level while the screen turned under it. `Lean` eases
+//! towards that target a share of the way each frame, so the bird swings
+//! round smoothly instead of snapping from one step to the next.
+
+use crate::config::Coord;
+
+pub const MIN_TURN: i8 = -5;
+pub const MAX_TURN: i8 = 16;
+// Velocity, in pixels a tick, for each step of turn
+const VELOCITY_PER_STEP: Coord = 5;
+// Gyro rate for each step of turn, in raw counts at the sensor's +-250
+// degrees a second range (131 counts a degree a second): 30 degrees a second
+const RATE_PER_STEP: i32 = 131 * 30;
+// Fractions of a step `Lean` keeps between frames
+const FRACTION: i32 = 16;
+// Share of the way to the target covered each frame, 1 / EASE
+const EASE: i32 = 4;
+
+// Steps to turn the bird for `velocity` (pixels a tick, down positive) and
+// the gyro's Z `rate` (raw, counterclockwise positive)
+pub fn target(velocity: Coord, rate: i32) -> i8 {
+    let steps = velocity / VELOCITY_PER_STEP + rate / RATE_PER_STEP;
+    steps.clamp(MIN_TURN as i32, MAX_TURN as i32) as i8
+}
+
+/// The turn the bird is drawn at, kept to a fraction of a step
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Lean {
+    fraction: i32,
+}
+
+impl Lean {
+    // Move `1 / EASE` of the way to `target`, and at least a fraction
+    pub fn ease_to(&mut self, target: i8) {
+        let gap = target as i32 * FRACTION - self.fraction;
+        let step = gap / EASE;
+        self.fraction += if step != 0 { step } else { gap.signum() };
+    }
+
+    // Whole steps, rounded to the nearest
+    pub fn turn(&self) -> i8 {
+        ((self.fraction + FRACTION / 2).div_euclid(FRACTION)) as i8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_follows_velocity_within_limits() {
+        assert_eq!(target(0, 0), 0);
+        assert_eq!(target(-10, 0), -2);
+        assert_eq!(target(12, 0), 2);
+        assert_eq!(target(-100, 0), MIN_TURN);
+        assert_eq!(target(200, 0), MAX_TURN);
+    }
+
+    #[test]
+    fn turning_the_board_leans_the_bird() {
+        assert_eq!(target(0, 3 * RATE_PER_STEP), 3);
+        assert_eq!(target(0, -RATE_PER_STEP), -1);
+        assert_eq!(target(10, -2 * RATE_PER_STEP), 0);
+        // Sensor noise at rest does not show
+        assert_eq!(target(0, 200), 0);
+    }
+
+    #[test]
+    fn eases_round_and_settles() {
+        let mut lean = Lean::default();
+        lean.ease_to(8);
+        let first = lean.turn();
+        assert!(first > 0 && first < 8, "{}", first);
+        let mut last = first;
+        for _ in 0..40 {
+            lean.ease_to(8);
+            assert!(lean.turn() >= last);
+            last = lean.turn();
+        }
+        assert_eq!(lean.turn(), 8);
+        for _ in 0..60 {
+            lean.ease_to(MIN_TURN);
+        }
+        assert_eq!(lean.turn(), MIN_TURN);
+    }
+}
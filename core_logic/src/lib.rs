@@ -0,0 +1,84 @@
+//! Game rules with no hardware behind them
+//!
+//! Physics, obstacle movement, collision, scoring, animation timing and the
+//! game states are plain integer arithmetic on game state. They build into
+//! the firmware as a `no_std` dependency and also for the host, so the unit
+//! tests run on a PC without the board:
+//!
+//! ```text
+//! cargo test-core        # alias for the x86_64 Linux host, see .cargo/config.toml
+//! cargo test -p core_logic --target <host triple>
+//! ```
+//!
+//! With the `sim` feature the crate also builds `sim`, a host render
+//! backend, for trying out the screen layout on a PC, and with `bdf` the
+//! font converter the firmware's build script runs.
+#![cfg_attr(not(any(test, feature = "sim", feature = "bdf")), no_std)]
+
+pub mod anim;
+#[cfg(any(test, feature = "bdf"))]
+pub mod bdf;
+pub mod bird;
+pub mod blackbox;
+pub mod bmp;
+pub mod color;
+pub mod config;
+pub mod controls;
+pub mod course;
+pub mod crc;
+pub mod cycle;
+pub mod effects;
+pub mod esp_at;
+pub mod events;
+pub mod executor;
+pub mod fill;
+pub mod font;
+pub mod geometry;
+pub mod glyphs;
+pub mod input;
+pub mod journal;
+pub mod joystick;
+pub mod lane;
+pub mod lang;
+pub mod latency;
+pub mod leaderboard;
+pub mod lean;
+pub mod live;
+pub mod mode;
+pub mod obstacle;
+pub mod palette;
+pub mod panel;
+pub mod particles;
+pub mod pickup;
+pub mod pipe;
+pub mod pllsai;
+pub mod powerup;
+pub mod practice;
+pub mod profile;
+pub mod raster;
+pub mod rect;
+pub mod render;
+pub mod replay;
+pub mod rng;
+pub mod rules;
+pub mod scheduler;
+pub mod screensaver;
+pub mod script;
+pub mod scroll;
+pub mod settings;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod sky;
+pub mod skyline;
+pub mod sprite_batch;
+pub mod sprite_cache;
+pub mod state;
+pub mod test_pattern;
+pub mod tilemap;
+pub mod tilt_map;
+pub mod time_attack;
+pub mod timestep;
+pub mod tracker;
+pub mod tuning;
+
+pub use config::Coord;
@@ -0,0 +1,198 @@
+//! Commands of the live tuning channel
+//!
+//! A host with a debug probe attached changes gameplay numbers while the
+//! game runs, and picks which of the game's variables the board streams
+//! back. One command a line:
+//!
+//! - `set <key> <value>`: a `TuningParams` value by its shell name, or
+//!   `tilt` for the tilt threshold, or `alpha` for how much of each new
+//!   tilt reading the bird's position takes, in percent
+//! - `watch [<var>...]`: stream these variables; none stops the stream
+//!
+//! `parse` only checks a line; the firmware applies it.
+
+use crate::tuning::Param;
+
+/// Something `set` changes
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Key {
+    Tuning(Param),
+    Tilt,
+    Alpha,
+}
+
+impl Key {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "tilt" => Some(Key::Tilt),
+            "alpha" => Some(Key::Alpha),
+            _ => Param::parse(name).map(Key::Tuning),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Key::Tuning(param) => param.name(),
+            Key::Tilt => "tilt",
+            Key::Alpha => "alpha",
+        }
+    }
+
+    // Values it takes, inclusive
+    pub fn range(self) -> (i32, i32) {
+        match self {
+            Key::Tuning(param) => param.range(),
+            // Raw accelerometer counts, 16384 to the g
+            Key::Tilt => (1000, 30000),
+            // 100 follows the sensor as it is
+            Key::Alpha => (1, 100),
+        }
+    }
+}
+
+/// A variable the board can stream
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Var {
+    Score,
+    Y,
+    Vy,
+    Speed,
+    Fps,
+}
+
+impl Var {
+    pub const ALL: [Var; 5] = [Var::Score, Var::Y, Var::Vy, Var::Speed, Var::Fps];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Var::Score => "score",
+            Var::Y => "y",
+            Var::Vy => "vy",
+            Var::Speed => "speed",
+            Var::Fps => "fps",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Var::ALL.into_iter().find(|var| var.name() == name)
+    }
+}
+
+/// The streamed variables, a bit each in `Var` order
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Watched(u8);
+
+impl Watched {
+    pub const NONE: Watched = Watched(0);
+
+    pub fn contains(self, var: Var) -> bool {
+        self.0 & 1 << var as u8 != 0
+    }
+
+    pub fn insert(&mut self, var: Var) {
+        self.0 |= 1 << var as u8;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Var> {
+        Var::ALL.into_iter().filter(move |&var| self.contains(var))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Command {
+    Set(Key, i32),
+    Watch(Watched),
+}
+
+/// What was wrong with a line, for the reply
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    // Not a command at all
+    Usage,
+    // A key or variable nobody knows
+    Name,
+    // Not a number, or outside the key's range
+    Value(Key),
+}
+
+pub fn parse(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_ascii_whitespace();
+    match words.next() {
+        Some("set") => {
+            let key = words
+                .next()
+                .ok_or(ParseError::Usage)
+                .and_then(|name| Key::parse(name).ok_or(ParseError::Name))?;
+            let value = words
+                .next()
+                .and_then(|value| value.parse().ok())
+                .filter(|value| {
+                    let (min, max) = key.range();
+                    (min..=max).contains(value)
+                })
+                .ok_or(ParseError::Value(key))?;
+            if words.next().is_some() {
+                return Err(ParseError::Usage);
+            }
+            Ok(Command::Set(key, value))
+        }
+        Some("watch") => {
+            let mut watched = Watched::NONE;
+            for name in words {
+                watched.insert(Var::parse(name).ok_or(ParseError::Name)?);
+            }
+            Ok(Command::Watch(watched))
+        }
+        _ => Err(ParseError::Usage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_takes_tuning_names_and_the_extra_keys() {
+        assert_eq!(
+            parse("set gravity -2"),
+            Ok(Command::Set(Key::Tuning(Param::Gravity), -2))
+        );
+        assert_eq!(parse(" set  tilt 9000 "), Ok(Command::Set(Key::Tilt, 9000)));
+        assert_eq!(parse("set alpha 40"), Ok(Command::Set(Key::Alpha, 40)));
+        assert_eq!(parse("set wind 3"), Err(ParseError::Name));
+        assert_eq!(
+            parse("set speed"),
+            Err(ParseError::Value(Key::Tuning(Param::Speed)))
+        );
+        assert_eq!(parse("set speed 50 60"), Err(ParseError::Usage));
+    }
+
+    #[test]
+    fn values_outside_the_range_are_refused() {
+        assert_eq!(parse("set alpha 0"), Err(ParseError::Value(Key::Alpha)));
+        assert_eq!(parse("set tilt fast"), Err(ParseError::Value(Key::Tilt)));
+        assert_eq!(
+            parse("set gravity 8"),
+            Err(ParseError::Value(Key::Tuning(Param::Gravity)))
+        );
+    }
+
+    #[test]
+    fn watch_collects_variables_and_nothing_stops() {
+        let Ok(Command::Watch(watched)) = parse("watch fps y") else {
+            panic!("watch fps y");
+        };
+        assert!(watched.contains(Var::Y) && watched.contains(Var::Fps));
+        assert!(!watched.contains(Var::Score));
+        // Streamed in `Var` order whatever order they were asked for in
+        assert!(watched.iter().eq([Var::Y, Var::Fps]));
+        assert_eq!(parse("watch"), Ok(Command::Watch(Watched::NONE)));
+        assert_eq!(parse("watch y altitude"), Err(ParseError::Name));
+        assert_eq!(parse(""), Err(ParseError::Usage));
+        assert_eq!(parse("get y"), Err(ParseError::Usage));
+    }
+}
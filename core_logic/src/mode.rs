@@ -0,0 +1,274 @@
+//! What kind of game is being played
+//!
+//! Every mode flies the same bird through the same obstacles and scores a
+//! point for each one passed. A `GameMode` decides the rest: which kinds
+//! of obstacle come along, what counts as a crash and whether the game can
+//! be won. `Classic` is the original game. In `Runner` the obstacles are
+//! ground spikes and overhead bars that mostly take turns, so the bird has
+//! to go from low to high and back. The edges of the playfield are safe
+//! there, and reaching `RUNNER_GOAL` wins the run. `TimeAttack` flies the
+//! classic pipes against the clock (see `time_attack`). A custom course
+//! (`course::Pattern`) places each opening itself, and a script
+//! (`script::Script`) lays out whole sections: kinds, openings and spacing.
+
+use crate::bird::Bird;
+use crate::config::Coord;
+use crate::obstacle::{ObstacleKind, ObstaclePair};
+use crate::rect::Rect;
+use crate::rules;
+use crate::script;
+
+// Points that win a runner game
+pub const RUNNER_GOAL: u32 = 50;
+// Score from which a runner obstacle can repeat the last one's side
+const RUNNER_REPEATS_FROM: u32 = 10;
+
+pub trait GameMode {
+    fn name(&self) -> &'static str;
+
+    // The kind a new game's obstacle starts as
+    fn first(&self) -> ObstacleKind;
+
+    // The kind to bring back after `current` at `score`, `roll` a random
+    // number
+    fn next(&self, current: ObstacleKind, score: u32, roll: u32) -> ObstacleKind;
+
+    // Whether the bird has crashed into `obstacle` or out of `field`
+    fn crashed(&self, bird: &Bird, obstacle: &ObstaclePair, field: Rect) -> bool {
+        rules::collides(bird, obstacle, field)
+    }
+
+    // Whether flying into the ceiling or the ground is a crash, and so
+    // worth warning of
+    fn deadly_edges(&self) -> bool {
+        true
+    }
+
+    // Whether `score` ends the game as a win
+    fn won(&self, _score: u32) -> bool {
+        false
+    }
+
+    // Where the opening of a game's `index`th obstacle starts, in rows below
+    // the top of the playfield; None leaves it where its kind puts it
+    fn gap(&self, _index: u32) -> Option<Coord> {
+        None
+    }
+
+    // The kind of a game's `index`th obstacle, if the mode has one down for
+    // it; None has `first` or `next` pick
+    fn kind(&self, _index: u32) -> Option<ObstacleKind> {
+        None
+    }
+
+    // Extra tiles of sky ahead of a game's `index`th obstacle
+    fn spacing(&self, _index: u32) -> u32 {
+        0
+    }
+}
+
+/// Pipes with openings to fly through, with no end
+pub struct Classic;
+
+impl GameMode for Classic {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+
+    fn first(&self) -> ObstacleKind {
+        ObstacleKind::Static
+    }
+
+    fn next(&self, _current: ObstacleKind, score: u32, roll: u32) -> ObstacleKind {
+        ObstacleKind::pick(score, roll)
+    }
+}
+
+/// Spikes and bars, low and high in turn, up to a goal
+pub struct Runner;
+
+impl GameMode for Runner {
+    fn name(&self) -> &'static str {
+        "runner"
+    }
+
+    fn first(&self) -> ObstacleKind {
+        ObstacleKind::Spike
+    }
+
+    // The other side to the last, but once a few points in one time in four
+    // the same side again
+    fn next(&self, current: ObstacleKind, score: u32, roll: u32) -> ObstacleKind {
+        let repeat = score >= RUNNER_REPEATS_FROM && roll.is_multiple_of(4);
+        match (current, repeat) {
+            (ObstacleKind::Spike, false) | (ObstacleKind::Bar, true) => ObstacleKind::Bar,
+            _ => ObstacleKind::Spike,
+        }
+    }
+
+    // Only the pipe: the bird can skim the ceiling and the ground
+    fn crashed(&self, bird: &Bird, obstacle: &ObstaclePair, _field: Rect) -> bool {
+        let bird = bird.hitbox();
+        obstacle.rects().any(|pipe| bird.intersects(&pipe))
+    }
+
+    fn deadly_edges(&self) -> bool {
+        false
+    }
+
+    fn won(&self, score: u32) -> bool {
+        score >= RUNNER_GOAL
+    }
+}
+
+/// The classic pipes, for as many points as a minute allows; the clock
+/// and the respawns are the game's (see `time_attack`)
+pub struct TimeAttack;
+
+impl GameMode for TimeAttack {
+    fn name(&self) -> &'static str {
+        "time attack"
+    }
+
+    fn first(&self) -> ObstacleKind {
+        Classic.first()
+    }
+
+    fn next(&self, current: ObstacleKind, score: u32, roll: u32) -> ObstacleKind {
+        Classic.next(current, score, roll)
+    }
+}
+
+/// A mode to choose from the pause menu
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ModeId {
+    Classic,
+    Runner,
+    Signature,
+    TimeAttack,
+}
+
+impl ModeId {
+    // In declaration order, as `self as u32` numbers them
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ModeId::Classic),
+            1 => Some(ModeId::Runner),
+            2 => Some(ModeId::Signature),
+            3 => Some(ModeId::TimeAttack),
+            _ => None,
+        }
+    }
+
+    pub fn mode(self) -> &'static dyn GameMode {
+        match self {
+            ModeId::Classic => &Classic,
+            ModeId::Runner => &Runner,
+            ModeId::Signature => &script::SIGNATURE,
+            ModeId::TimeAttack => &TimeAttack,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.mode().name()
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ModeId::Classic => ModeId::Runner,
+            ModeId::Runner => ModeId::Signature,
+            ModeId::Signature => ModeId::TimeAttack,
+            ModeId::TimeAttack => ModeId::Classic,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::lane::Lane;
+
+    // Pair at the bird's column, shaped as `kind`
+    fn pair_at_bird(kind: ObstacleKind) -> ObstaclePair {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed((LCD_END - INIT_PLAYER_POS_X) as u32);
+        pair.advance();
+        pair.set_kind(kind);
+        pair
+    }
+
+    #[test]
+    fn classic_picks_as_before() {
+        for roll in 0..50 {
+            assert_eq!(
+                Classic.next(ObstacleKind::Static, 30, roll),
+                ObstacleKind::pick(30, roll)
+            );
+        }
+        assert_eq!(Classic.first(), ObstacleKind::Static);
+        assert!(!Classic.won(1000));
+    }
+
+    #[test]
+    fn runner_takes_turns_early_on() {
+        let mut kind = Runner.first();
+        for score in 0..RUNNER_REPEATS_FROM {
+            let next = Runner.next(kind, score, 0);
+            assert_ne!(next, kind);
+            kind = next;
+        }
+    }
+
+    #[test]
+    fn runner_sometimes_repeats_later() {
+        let kinds = (0..8).map(|roll| Runner.next(ObstacleKind::Bar, 20, roll));
+        let repeats = kinds.filter(|kind| *kind == ObstacleKind::Bar).count();
+        assert_eq!(repeats, 2);
+        assert_eq!(
+            Runner.next(ObstacleKind::Static, 20, 1),
+            ObstacleKind::Spike
+        );
+    }
+
+    #[test]
+    fn runner_edges_are_safe_but_pipe_is_not() {
+        let field = Lane::FULL.field();
+        let spike = pair_at_bird(ObstacleKind::Spike);
+        let (_, top, bottom) = spike.get_gap();
+        let high = Bird::new(INIT_PLAYER_POS_X, field.y);
+        assert!(!Runner.crashed(&high, &spike, field));
+        assert!(Classic.crashed(&high, &spike, field));
+        let low = Bird::new(INIT_PLAYER_POS_X, bottom);
+        assert!(Runner.crashed(&low, &spike, field));
+        assert_eq!(top, field.y);
+
+        let bar = pair_at_bird(ObstacleKind::Bar);
+        let ground = Bird::new(INIT_PLAYER_POS_X, GROUND_Y_POS - PLAYER_HEIGHT as Coord);
+        assert!(!Runner.crashed(&ground, &bar, field));
+        assert!(Runner.crashed(&high, &bar, field));
+    }
+
+    #[test]
+    fn runner_is_won_at_the_goal() {
+        assert!(!Runner.won(RUNNER_GOAL - 1));
+        assert!(Runner.won(RUNNER_GOAL));
+    }
+
+    #[test]
+    fn ids_name_their_modes() {
+        assert_eq!(ModeId::Classic.as_str(), "classic");
+        assert_eq!(ModeId::Runner.as_str(), "runner");
+        assert_eq!(ModeId::Signature.as_str(), "signature");
+        assert_eq!(ModeId::TimeAttack.as_str(), "time attack");
+        assert_eq!(ModeId::Classic.next().next().next().next(), ModeId::Classic);
+        for id in [
+            ModeId::Classic,
+            ModeId::Runner,
+            ModeId::Signature,
+            ModeId::TimeAttack,
+        ] {
+            assert_eq!(ModeId::from_u32(id as u32), Some(id));
+        }
+    }
+}
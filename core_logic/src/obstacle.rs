@@ -0,0 +1,676 @@
+//! One obstacle scrolling right to left
+//!
+//! The obstacle spans a lane's playfield as a column of pipe with one or two
+//! openings cut into it. What the openings look like is its `ObstacleKind`.
+//! Once it has scrolled off the left edge it comes back at the right as a
+//! new kind, picked by the game's `GameMode`: in the classic game at
+//! random, with the harder kinds turning up more often as the score rises.
+//! A mode can also say where the opening goes (`GameMode::gap`).
+//!
+//! What comes back is decided only when it wraps, but it can be worked out
+//! ahead (`peek_next`): the beginners' gap preview shows the next opening
+//! at the right edge before its pipe scrolls on (`gap_preview`).
+
+use crate::config::*;
+use crate::lane::Lane;
+use crate::mode::{Classic, GameMode};
+use crate::rect::Rect;
+use crate::rng::Rng;
+use crate::scroll::{self, Scroller};
+use crate::timestep::lerp;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ObstacleKind {
+    // The lane's opening, standing still
+    Static,
+    // An opening of the same size sliding up and down as it scrolls
+    Moving,
+    // Two narrower openings, one above the other, with a pipe between
+    Double,
+    // Pipe up from the ground, open above it (runner mode)
+    Spike,
+    // Pipe down from the top, open below it (runner mode)
+    Bar,
+}
+
+// Rows kept as pipe above and below a moving opening at either end of its
+// travel
+const MOVING_MARGIN: Coord = 10;
+// Rows a moving opening shifts per frame
+const MOVING_STEP: Coord = 1;
+// Size of each of the two openings in a double obstacle, and of the pipe
+// between them
+const DOUBLE_GAP: Coord = PLAYER_HEIGHT as Coord + 10;
+const DOUBLE_DIVIDER: Coord = 16;
+// Percent of the playfield a spike or a bar fills
+const RUNNER_PIPE_PERCENT: Coord = 55;
+// Pixels off the right edge from which the next opening is previewed
+pub const PREVIEW_RANGE: Coord = 96;
+
+impl ObstacleKind {
+    // Relative odds of each kind at `score`, in declaration order. Only
+    // static pipes until a few points in; then moving ones, and later double
+    // ones, grow more likely up to a cap.
+    pub fn weights(score: u32) -> [u32; 3] {
+        let moving = (score / 5).min(6);
+        let double = (score.saturating_sub(10) / 5).min(4);
+        [8, moving, double]
+    }
+
+    // The kind `roll` lands on, weighted for `score`
+    pub fn pick(score: u32, roll: u32) -> Self {
+        let weights = Self::weights(score);
+        let mut roll = roll % weights.iter().sum::<u32>();
+        for (kind, weight) in [Self::Static, Self::Moving, Self::Double]
+            .into_iter()
+            .zip(weights)
+        {
+            if roll < weight {
+                return kind;
+            }
+            roll -= weight;
+        }
+        Self::Static
+    }
+}
+
+/// The next opening, shown ahead of its pipe
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct GapPreview {
+    // As in `gaps`
+    pub gaps: [Option<(Coord, Coord)>; 2],
+    // 255 as it comes in range, down to 0 as the pipe reaches the edge
+    pub strength: u8,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ObstaclePair {
+    kind: ObstacleKind,
+    lane: Lane,
+    x: Coord,
+    // Screen row of the top of the (upper) opening, and its height
+    gap_y: Coord,
+    gap_h: Coord,
+    // Rows the opening moves per frame, negative going up
+    drift: Coord,
+    scroll: Scroller,
+    // Picks the next kind
+    rng: Rng,
+    // Obstacles brought in this game before this one
+    count: u32,
+    pub already_scored: bool,
+    // Fewest rows there were between the bird and the pipe while it was
+    // alongside; see `rules::track_clearance`
+    pub closest: Option<u32>,
+}
+
+impl ObstaclePair {
+    // Static obstacle at the right edge of `lane`'s playfield
+    pub fn new(lane: Lane) -> Self {
+        let mut pair = ObstaclePair {
+            kind: ObstacleKind::Static,
+            lane,
+            x: LCD_END,
+            gap_y: 0,
+            gap_h: 0,
+            drift: 0,
+            scroll: Scroller::new(scroll::pixels(SPEED)),
+            rng: Rng::new(1),
+            count: 0,
+            already_scored: false,
+            closest: None,
+        };
+        pair.set_kind(ObstacleKind::Static);
+        pair
+    }
+
+    // Start the sequence of kinds from `seed`; pairs seeded alike see the
+    // same obstacles for the same scores
+    pub fn reseed(&mut self, seed: u32) {
+        self.rng = Rng::new(seed);
+    }
+
+    // Where the obstacle is drawn `alpha` of a tick on from `before` to
+    // `self` (see `timestep`). One that has wrapped in between is shown
+    // where it now is.
+    pub fn between(&self, before: &ObstaclePair, alpha: u8) -> ObstaclePair {
+        if self.x > before.x || self.kind != before.kind {
+            return *self;
+        }
+        ObstaclePair {
+            x: lerp(before.x, self.x, alpha),
+            gap_y: lerp(before.gap_y, self.gap_y, alpha),
+            ..*self
+        }
+    }
+
+    pub fn kind(&self) -> ObstacleKind {
+        self.kind
+    }
+
+    // Reshape the openings for `kind`, keeping the position
+    pub fn set_kind(&mut self, kind: ObstacleKind) {
+        let lane = self.lane;
+        self.kind = kind;
+        self.drift = 0;
+        match kind {
+            ObstacleKind::Static => {
+                self.gap_y = lane.y(lane.gap_top);
+                self.gap_h = lane.gap_bottom - lane.gap_top;
+            }
+            ObstacleKind::Moving => {
+                self.gap_y = lane.y(lane.gap_top);
+                self.gap_h = lane.gap_bottom - lane.gap_top;
+                self.drift = -MOVING_STEP;
+            }
+            ObstacleKind::Double => {
+                // Both openings and the divider, centered on the playfield
+                let field = lane.field();
+                let span = 2 * DOUBLE_GAP + DOUBLE_DIVIDER;
+                self.gap_y = field.y + (field.h as Coord - span) / 2;
+                self.gap_h = DOUBLE_GAP;
+            }
+            ObstacleKind::Spike | ObstacleKind::Bar => {
+                let field = lane.field();
+                let pipe = field.h as Coord * RUNNER_PIPE_PERCENT / 100;
+                self.gap_h = field.h as Coord - pipe;
+                self.gap_y = match kind {
+                    ObstacleKind::Spike => field.y,
+                    _ => field.y + pipe,
+                };
+            }
+        }
+    }
+
+    // Shape it as the first obstacle of a game of `mode`
+    pub fn start_with(&mut self, mode: &dyn GameMode) {
+        self.count = 0;
+        self.set_kind(mode.kind(0).unwrap_or_else(|| mode.first()));
+        self.place(mode);
+    }
+
+    // Move the opening to where `mode` has it for this obstacle, if
+    // anywhere
+    fn place(&mut self, mode: &dyn GameMode) {
+        let Some(row) = mode.gap(self.count) else {
+            return;
+        };
+        let field = self.lane.field();
+        let lowest = (field.h as Coord - self.gap_h).max(0);
+        self.gap_y = field.y + row.clamp(0, lowest);
+    }
+
+    // Scroll left by one frame's worth, moving the opening of a moving
+    // obstacle and turning it round at either end of its travel. Returns
+    // the whole pixels moved.
+    pub fn advance(&mut self) -> u32 {
+        let dx = self.scroll.step();
+        self.x -= dx as Coord;
+        if self.drift != 0 {
+            let field = self.lane.field();
+            let min = field.y + MOVING_MARGIN;
+            let max = field.y + field.h as Coord - MOVING_MARGIN - self.gap_h;
+            self.gap_y += self.drift;
+            if self.gap_y <= min || self.gap_y >= max {
+                self.gap_y = self.gap_y.clamp(min, max);
+                self.drift = -self.drift;
+            }
+        }
+        dx
+    }
+
+    // Back to the right edge once off the left as a new kind picked for
+    // `score`, ready to be scored again. True when it came back. It comes
+    // back as far past the edge as it went past the left one, to a TILE, so
+    // it keeps to the tile map's columns.
+    pub fn wrap(&mut self, score: u32) -> bool {
+        self.wrap_with(score, &Classic)
+    }
+
+    // `wrap`, with the next kind picked by `mode`, and spaced out further
+    // if it says so
+    pub fn wrap_with(&mut self, score: u32, mode: &dyn GameMode) -> bool {
+        if self.x > LCD_BIGIN {
+            return false;
+        }
+        self.count = self.count.wrapping_add(1);
+        let spacing = mode.spacing(self.count) * TILE;
+        self.x = LCD_END - (LCD_BIGIN - self.x) % TILE as Coord + spacing as Coord;
+        self.already_scored = false;
+        self.closest = None;
+        // Rolled either way, so a script leaves the random picks after it
+        // as they were
+        let roll = self.rng.next_u32();
+        let kind = mode
+            .kind(self.count)
+            .unwrap_or_else(|| mode.next(self.kind, score, roll));
+        self.set_kind(kind);
+        self.place(mode);
+        true
+    }
+
+    // The obstacle that comes back after this one, for `score` and `mode`
+    // as they are now, as far off the right edge as it will be once this
+    // one has gone. Its rng is stepped on a copy, so this one still comes
+    // back as it would have. A moving opening is where it starts.
+    pub fn peek_next(&self, score: u32, mode: &dyn GameMode) -> ObstaclePair {
+        let mut next = *self;
+        let ahead = self.x - LCD_BIGIN;
+        next.x = LCD_BIGIN;
+        next.wrap_with(score, mode);
+        next.x += ahead.max(0);
+        next
+    }
+
+    // The opening of the next pipe to come on screen, while it is still
+    // within PREVIEW_RANGE of the right edge: this one if it has not come
+    // on yet, else the one after it
+    pub fn gap_preview(&self, score: u32, mode: &dyn GameMode) -> Option<GapPreview> {
+        let next = if self.x >= LCD_END {
+            *self
+        } else {
+            self.peek_next(score, mode)
+        };
+        let distance = next.x - LCD_END;
+        if distance <= 0 || distance > PREVIEW_RANGE {
+            return None;
+        }
+        Some(GapPreview {
+            gaps: next.gaps(),
+            strength: (distance * 255 / PREVIEW_RANGE) as u8,
+        })
+    }
+
+    pub fn get_xy_top(&self) -> (Coord, Coord) {
+        (self.x, self.lane.y(self.lane.score_height))
+    }
+
+    pub fn get_xy_bottom(&self) -> (Coord, Coord) {
+        let rect = self.bottom_rect();
+        (rect.x, rect.y)
+    }
+
+    // The openings as (top edge, bottom edge) screen rows, top first
+    pub fn gaps(&self) -> [Option<(Coord, Coord)>; 2] {
+        let first = (self.gap_y, self.gap_y + self.gap_h);
+        match self.kind {
+            ObstacleKind::Double => {
+                let y = first.1 + DOUBLE_DIVIDER;
+                [Some(first), Some((y, y + self.gap_h))]
+            }
+            _ => [Some(first), None],
+        }
+    }
+
+    // Pipe from the scoreboard down to the first opening
+    pub fn top_rect(&self) -> Rect {
+        let (_, y) = self.get_xy_top();
+        Rect::new(self.x, y, OBSTACLE_WIDTH, (self.gap_y - y) as u32)
+    }
+
+    // Pipe between the two openings of a double obstacle
+    pub fn middle_rect(&self) -> Option<Rect> {
+        let [Some((_, y)), Some((bottom, _))] = self.gaps() else {
+            return None;
+        };
+        Some(Rect::new(self.x, y, OBSTACLE_WIDTH, (bottom - y) as u32))
+    }
+
+    // Pipe from the last opening down to the ground
+    pub fn bottom_rect(&self) -> Rect {
+        let y = self
+            .gaps()
+            .into_iter()
+            .flatten()
+            .last()
+            .map_or(0, |(_, y)| y);
+        let ground = self.lane.y(self.lane.ground);
+        Rect::new(self.x, y, OBSTACLE_WIDTH, (ground - y) as u32)
+    }
+
+    // Every piece of pipe, top to bottom; a spike has none above its
+    // opening and a bar none below
+    pub fn rects(&self) -> impl Iterator<Item = Rect> {
+        [
+            Some(self.top_rect()),
+            self.middle_rect(),
+            Some(self.bottom_rect()),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|rect| rect.h > 0)
+    }
+
+    pub fn get_height(&self) -> (u32, u32) {
+        (self.top_rect().h, self.bottom_rect().h)
+    }
+
+    // Opening the player has to fly through: (x, top edge, bottom edge). For
+    // a double obstacle, the upper one.
+    pub fn get_gap(&self) -> (Coord, Coord, Coord) {
+        (self.x, self.gap_y, self.gap_y + self.gap_h)
+    }
+
+    // Whole pixels moved per frame
+    pub fn speed(&self) -> u32 {
+        self.scroll.whole()
+    }
+
+    // Pixels moved per frame; the next restart goes back to SPEED
+    pub fn set_speed(&mut self, speed: u32) {
+        self.scroll.set_velocity(scroll::pixels(speed));
+    }
+
+    // Speed in 1/SUBPIXELS of a pixel per frame (see `scroll`), and the
+    // fraction of a pixel it has moved past its last whole one
+    pub fn scroll(&self) -> &Scroller {
+        &self.scroll
+    }
+
+    pub fn set_velocity(&mut self, velocity: u32) {
+        self.scroll.set_velocity(velocity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_lane_pair_matches_the_original_layout() {
+        let pair = ObstaclePair::new(Lane::FULL);
+        assert_eq!(pair.get_xy_top(), (240, 30));
+        assert_eq!(pair.get_xy_bottom(), (240, 180));
+        assert_eq!(pair.get_height(), (100, 30));
+        assert_eq!(pair.get_gap(), (240, 130, 180));
+        assert_eq!(pair.rects().count(), 2);
+    }
+
+    #[test]
+    fn split_lane_pair_sits_in_its_lane() {
+        let lane = Lane::SPLIT[1];
+        let pair = ObstaclePair::new(lane);
+        let (_, top) = pair.get_xy_top();
+        let (_, bottom) = pair.get_xy_bottom();
+        let (h_top, h_btm) = pair.get_height();
+        assert_eq!(top, lane.y(lane.score_height));
+        assert_eq!(top + h_top as Coord, lane.y(lane.gap_top));
+        assert_eq!(bottom + h_btm as Coord, lane.y(lane.ground));
+    }
+
+    #[test]
+    fn advance_moves_both_halves_by_speed() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed(5);
+        pair.advance();
+        assert_eq!(pair.get_xy_top().0, 235);
+        assert_eq!(pair.get_xy_bottom().0, 235);
+    }
+
+    #[test]
+    fn wraps_to_the_right_edge_and_rearms_scoring() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.already_scored = true;
+        let frames = LCD_END / SPEED as Coord;
+        for _ in 0..frames - 1 {
+            pair.advance();
+            pair.wrap(0);
+        }
+        assert_eq!(pair.get_xy_top().0, SPEED as Coord);
+        assert!(pair.already_scored);
+        pair.advance();
+        pair.wrap(0);
+        assert_eq!(pair.get_xy_top().0, LCD_END);
+        assert_eq!(pair.get_xy_bottom().0, LCD_END);
+        assert!(!pair.already_scored);
+    }
+
+    #[test]
+    fn wrap_keeps_to_the_tile_columns() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed(7);
+        let mut scrolled = 0;
+        for _ in 0..200 {
+            pair.advance();
+            scrolled += 7;
+            pair.wrap(0);
+            // Where it is in the world, counting what has scrolled by
+            assert_eq!((pair.get_xy_top().0 + scrolled) % TILE as Coord, 0);
+        }
+    }
+
+    #[test]
+    fn wrap_does_nothing_on_screen() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.advance();
+        let before = pair;
+        pair.wrap(100);
+        assert_eq!(pair, before);
+    }
+
+    #[test]
+    fn speed_zero_stands_still() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed(0);
+        for _ in 0..10 {
+            pair.advance();
+        }
+        assert_eq!(pair.get_xy_top().0, LCD_END);
+    }
+
+    #[test]
+    fn fractional_velocity_moves_in_whole_pixels() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_velocity(scroll::pixels(2) + scroll::SUBPIXELS / 4);
+        let moved = [(); 4].map(|_| pair.advance());
+        assert_eq!(moved, [2, 2, 2, 3]);
+        assert_eq!(pair.get_xy_top().0, LCD_END - 9);
+        assert_eq!(pair.speed(), 2);
+    }
+
+    #[test]
+    fn drawn_between_ticks() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_kind(ObstacleKind::Moving);
+        pair.set_speed(4);
+        pair.advance();
+        let before = pair;
+        pair.advance();
+        let shown = pair.between(&before, 128);
+        assert_eq!(shown.get_xy_top().0, 234);
+        assert_eq!(pair.between(&before, 255), pair);
+        assert_eq!(pair.between(&before, 0).get_gap(), before.get_gap());
+    }
+
+    #[test]
+    fn wrapped_pair_is_not_drawn_between() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed(LCD_END as u32 - 10);
+        pair.advance();
+        let before = pair;
+        pair.advance();
+        assert!(pair.wrap(0));
+        assert_eq!(pair.between(&before, 128), pair);
+    }
+
+    #[test]
+    fn only_static_pipes_early_on() {
+        for roll in 0..100 {
+            assert_eq!(ObstacleKind::pick(0, roll), ObstacleKind::Static);
+            assert_eq!(ObstacleKind::pick(4, roll), ObstacleKind::Static);
+        }
+        assert_eq!(ObstacleKind::weights(10)[2], 0);
+    }
+
+    #[test]
+    fn harder_kinds_grow_likelier_up_to_a_cap() {
+        assert!(ObstacleKind::weights(20)[1] > ObstacleKind::weights(5)[1]);
+        assert!(ObstacleKind::weights(30)[2] > ObstacleKind::weights(15)[2]);
+        assert_eq!(ObstacleKind::weights(1000), ObstacleKind::weights(100));
+        let [_, moving, double] = ObstacleKind::weights(100);
+        assert_eq!(ObstacleKind::pick(100, 8), ObstacleKind::Moving);
+        assert_eq!(ObstacleKind::pick(100, 8 + moving), ObstacleKind::Double);
+        assert_eq!(
+            ObstacleKind::pick(100, 8 + moving + double),
+            ObstacleKind::Static
+        );
+    }
+
+    #[test]
+    fn same_seed_same_kinds() {
+        let kinds = |seed| {
+            let mut pair = ObstaclePair::new(Lane::FULL);
+            pair.reseed(seed);
+            pair.set_speed(LCD_END as u32);
+            let mut kinds = [ObstacleKind::Static; 20];
+            for kind in kinds.iter_mut() {
+                pair.advance();
+                pair.wrap(100);
+                *kind = pair.kind();
+            }
+            kinds
+        };
+        assert_eq!(kinds(42), kinds(42));
+        assert!(kinds(42).contains(&ObstacleKind::Double));
+        assert!(kinds(42).contains(&ObstacleKind::Moving));
+    }
+
+    #[test]
+    fn moving_opening_stays_in_the_field() {
+        for lane in [Lane::FULL, Lane::SPLIT[0], Lane::SPLIT[1]] {
+            let mut pair = ObstaclePair::new(lane);
+            pair.set_kind(ObstacleKind::Moving);
+            pair.set_speed(0);
+            let field = lane.field();
+            let (mut lowest, mut highest) = (Coord::MAX, Coord::MIN);
+            for _ in 0..500 {
+                pair.advance();
+                let (_, top, bottom) = pair.get_gap();
+                assert_eq!(bottom - top, lane.gap_bottom - lane.gap_top);
+                assert!(top > field.y && bottom < field.y + field.h as Coord);
+                lowest = lowest.min(top);
+                highest = highest.max(top);
+            }
+            assert_eq!(lowest, field.y + MOVING_MARGIN);
+            assert_eq!(
+                highest,
+                field.y + field.h as Coord - MOVING_MARGIN - pair.gap_h
+            );
+        }
+    }
+
+    #[test]
+    fn double_openings_fit_the_bird_and_the_lane() {
+        for lane in [Lane::FULL, Lane::SPLIT[0]] {
+            let mut pair = ObstaclePair::new(lane);
+            pair.set_kind(ObstacleKind::Double);
+            let [Some(upper), Some(lower)] = pair.gaps() else {
+                panic!("double obstacle without two openings");
+            };
+            assert!(upper.1 - upper.0 > PLAYER_HEIGHT as Coord);
+            assert!(lower.1 - lower.0 > PLAYER_HEIGHT as Coord);
+            let rects: Vec<Rect> = pair.rects().collect();
+            assert_eq!(rects.len(), 3);
+            assert!(rects.iter().all(|r| r.h > 0));
+            // Pipe and openings cover the playfield without overlap
+            let field = lane.field();
+            let pipe: u32 = rects.iter().map(|r| r.h).sum();
+            assert_eq!(pipe + 2 * DOUBLE_GAP as u32, field.h);
+        }
+    }
+
+    #[test]
+    fn spikes_and_bars_leave_room_for_the_bird() {
+        for lane in [Lane::FULL, Lane::SPLIT[0], Lane::SPLIT[1]] {
+            let field = lane.field();
+            let ground = field.y + field.h as Coord;
+            let mut pair = ObstaclePair::new(lane);
+            pair.set_kind(ObstacleKind::Spike);
+            let (_, top, bottom) = pair.get_gap();
+            assert_eq!(top, field.y);
+            assert!(bottom - top > PLAYER_HEIGHT as Coord);
+            let rects: Vec<Rect> = pair.rects().collect();
+            assert_eq!(
+                rects,
+                [Rect::new(
+                    LCD_END,
+                    bottom,
+                    OBSTACLE_WIDTH,
+                    (ground - bottom) as u32
+                )]
+            );
+
+            pair.set_kind(ObstacleKind::Bar);
+            let (_, top, bottom) = pair.get_gap();
+            assert_eq!(bottom, ground);
+            assert!(bottom - top > PLAYER_HEIGHT as Coord);
+            let rects: Vec<Rect> = pair.rects().collect();
+            assert_eq!(
+                rects,
+                [Rect::new(
+                    LCD_END,
+                    field.y,
+                    OBSTACLE_WIDTH,
+                    (top - field.y) as u32
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn wrap_with_asks_the_mode() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_kind(ObstacleKind::Spike);
+        pair.set_speed(LCD_END as u32);
+        pair.advance();
+        assert!(pair.wrap_with(0, &crate::mode::Runner));
+        assert_eq!(pair.kind(), ObstacleKind::Bar);
+    }
+
+    #[test]
+    fn peek_next_sees_what_wrap_brings() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.reseed(42);
+        pair.set_speed(8);
+        for _ in 0..200 {
+            let next = pair.peek_next(100, &Classic);
+            let before = pair;
+            pair.advance();
+            if pair.wrap(100) {
+                assert_eq!(pair.kind(), next.kind());
+                assert_eq!(pair.gaps(), next.gaps());
+                // Where the peek had it, one frame on
+                assert_eq!(pair.get_xy_top().0, next.get_xy_top().0 - 8);
+            } else {
+                assert_eq!(before.peek_next(100, &Classic), next);
+            }
+        }
+    }
+
+    #[test]
+    fn gap_preview_fades_as_the_pipe_comes_in() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed(4);
+        let mut last: Option<GapPreview> = None;
+        let mut shown = 0;
+        for _ in 0..LCD_END / 4 {
+            pair.advance();
+            pair.wrap(0);
+            let preview = pair.gap_preview(0, &Classic);
+            if let (Some(before), Some(now)) = (last, preview) {
+                assert!(now.strength < before.strength);
+            }
+            if let Some(now) = preview {
+                assert_eq!(now.gaps, [Some((130, 180)), None]);
+                shown += 1;
+            }
+            last = preview;
+        }
+        // Only the last PREVIEW_RANGE pixels before it comes back on
+        assert_eq!(shown, PREVIEW_RANGE / 4);
+        // Back on screen, the one after it is too far off to show
+        assert_eq!(pair.get_xy_top().0, LCD_END);
+        pair.advance();
+        assert_eq!(pair.gap_preview(0, &Classic), None);
+    }
+}
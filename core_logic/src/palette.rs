@@ -0,0 +1,143 @@
+//! Color palettes for 8-bit indexed images
+//!
+//! An indexed layer stores one byte per pixel and LTDC turns it into a
+//! color through the layer's lookup table. `Palette` collects the colors of
+//! an image into such a table, with the transparent key color first so the
+//! hardware color key can drop index 0.
+
+use crate::color::Rgb565;
+use crate::render::{Image, ImageTransform};
+
+// Entries in an LTDC color lookup table
+pub const PALETTE_SIZE: usize = 256;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Palette {
+    colors: [u16; PALETTE_SIZE],
+    len: usize,
+}
+
+impl Palette {
+    pub const fn new() -> Self {
+        Palette {
+            colors: [0; PALETTE_SIZE],
+            len: 0,
+        }
+    }
+
+    // Index 0 holds `key`, the rest every other color in `image`; None if
+    // there are more than the table holds
+    pub fn from_image(image: &Image, key: u16) -> Option<Self> {
+        let mut palette = Palette::new();
+        palette.add(key)?;
+        for &color in image.data {
+            palette.add(color)?;
+        }
+        Some(palette)
+    }
+
+    // Index of `color`, added if it is new; None once the table is full
+    pub fn add(&mut self, color: u16) -> Option<u8> {
+        if let Some(index) = self.index_of(color) {
+            return Some(index);
+        }
+        if self.len == PALETTE_SIZE {
+            return None;
+        }
+        self.colors[self.len] = color;
+        self.len += 1;
+        Some((self.len - 1) as u8)
+    }
+
+    pub fn index_of(&self, color: u16) -> Option<u8> {
+        self.colors()
+            .iter()
+            .position(|&c| c == color)
+            .map(|i| i as u8)
+    }
+
+    pub fn colors(&self) -> &[u16] {
+        &self.colors[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The table as RGB888 words, the way LTDC takes it
+    pub fn to_rgb888(&self) -> impl Iterator<Item = u32> + '_ {
+        self.colors()
+            .iter()
+            .map(|&c| Rgb565(c).to_argb8888().0 & 0x00FF_FFFF)
+    }
+
+    // Index of each pixel of `image` as drawn after `transform`, row by
+    // row; colors missing from the palette come out as 0, the key
+    pub fn indices<'a>(
+        &'a self,
+        image: &'a Image,
+        transform: ImageTransform,
+    ) -> impl Iterator<Item = u8> + 'a {
+        let (w, h) = transform.size(image);
+        (0..h).flat_map(move |row| {
+            (0..w).map(move |col| {
+                image
+                    .pixel(col, row, transform)
+                    .and_then(|color| self.index_of(color))
+                    .unwrap_or(0)
+            })
+        })
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: u16 = 0x9F5E;
+
+    #[test]
+    fn key_comes_first_and_colors_are_shared() {
+        let data = [0x1234, KEY, 0x1234, 0xFFFF];
+        let image = Image::new(2, 2, &data);
+        let palette = Palette::from_image(&image, KEY).unwrap();
+        assert_eq!(palette.colors(), &[KEY, 0x1234, 0xFFFF]);
+        assert_eq!(palette.index_of(0xFFFF), Some(2));
+    }
+
+    #[test]
+    fn too_many_colors_do_not_fit() {
+        let data: [u16; PALETTE_SIZE] = core::array::from_fn(|i| i as u16 + 1);
+        let image = Image::new(PALETTE_SIZE as u32, 1, &data);
+        assert!(Palette::from_image(&image, 0).is_none());
+        assert!(Palette::from_image(&image, 1).is_some());
+    }
+
+    #[test]
+    fn indices_follow_the_transform() {
+        let data = [0x0001, 0x0002, 0x0003, KEY];
+        let image = Image::new(2, 2, &data);
+        let palette = Palette::from_image(&image, KEY).unwrap();
+        let flipped: Vec<u8> = palette.indices(&image, ImageTransform::FLIP_Y).collect();
+        assert_eq!(flipped, [3, 0, 1, 2]);
+    }
+
+    #[test]
+    fn table_is_rgb888() {
+        let mut palette = Palette::new();
+        palette.add(0xFFFF);
+        palette.add(0x0000);
+        let table: Vec<u32> = palette.to_rgb888().collect();
+        assert_eq!(table, [0x00FF_FFFF, 0]);
+    }
+}
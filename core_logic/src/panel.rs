@@ -0,0 +1,147 @@
+//! Panels the game can be scanned out on
+//!
+//! The game draws on a surface the size of the DISCO board's own panel,
+//! `PANEL_WIDTH` x `PANEL_HEIGHT`, through `geometry`. A `Panel` says what
+//! LTDC drives: its resolution and timings, and whether it is mounted a
+//! quarter turn from the way it scans, as a landscape panel has to be to
+//! stand the portrait surface upright. The surface is then stored turned,
+//! the way that panel reads it. A panel bigger than the surface letterboxes
+//! it in the middle, the LTDC background color filling the border; LTDC
+//! cannot scale, so there is no stretching to fit.
+
+use crate::config::{PANEL_HEIGHT, PANEL_WIDTH};
+use crate::geometry::Orientation;
+
+/// Sync pulse and porches, in pixel clocks and lines
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Timings {
+    pub hsync: u32,
+    pub hbp: u32,
+    pub hfp: u32,
+    pub vsync: u32,
+    pub vbp: u32,
+    pub vfp: u32,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Panel {
+    pub name: &'static str,
+    // Active area as the panel scans it
+    pub width: u32,
+    pub height: u32,
+    pub timings: Timings,
+    // Fastest pixel clock it takes
+    pub pclk_max_hz: u32,
+    // Mounted a quarter turn from its scan direction
+    pub turned: bool,
+    // An ILI9341 whose RGB interface is set up over SPI first, and which
+    // can take over drawing on its own (SPI rendering)
+    pub ili9341: bool,
+}
+
+impl Panel {
+    // The DISCO board's 2.4" ILI9341
+    pub const DISCO: Panel = Panel {
+        name: "disco",
+        width: PANEL_WIDTH,
+        height: PANEL_HEIGHT,
+        timings: Timings {
+            hsync: 5,
+            hbp: 24,
+            hfp: 16,
+            vsync: 5,
+            vbp: 4,
+            vfp: 4,
+        },
+        // 100 ns minimum DOTCLK cycle
+        pclk_max_hz: 10_000_000,
+        turned: false,
+        ili9341: true,
+    };
+
+    // 4.3" 480x272 RGB panels (RK043FN48H and the like) on the LTDC pins
+    pub const RGB_480X272: Panel = Panel {
+        name: "480x272",
+        width: 480,
+        height: 272,
+        timings: Timings {
+            hsync: 41,
+            hbp: 13,
+            hfp: 32,
+            vsync: 10,
+            vbp: 2,
+            vfp: 2,
+        },
+        pclk_max_hz: 12_000_000,
+        turned: true,
+        ili9341: false,
+    };
+
+    pub const ALL: [Panel; 2] = [Panel::DISCO, Panel::RGB_480X272];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Panel::ALL.into_iter().find(|panel| panel.name == name)
+    }
+
+    // How the surface is turned onto the panel's scan lines
+    pub const fn turn(&self) -> Orientation {
+        if self.turned {
+            Orientation::Landscape
+        } else {
+            Orientation::Portrait
+        }
+    }
+
+    // The surface as the panel scans it, width by height
+    pub const fn surface(&self) -> (u32, u32) {
+        self.turn().game_size(PANEL_WIDTH, PANEL_HEIGHT)
+    }
+
+    pub const fn fits(&self) -> bool {
+        let (w, h) = self.surface();
+        w <= self.width && h <= self.height
+    }
+
+    // Panel pixel the surface's top left corner goes on: the middle, or
+    // the corner of a panel too small for it, which then shows what fits
+    pub const fn origin(&self) -> (u32, u32) {
+        let (w, h) = self.surface();
+        (
+            self.width.saturating_sub(w) / 2,
+            self.height.saturating_sub(h) / 2,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_disco_panel_is_the_surface() {
+        let disco = Panel::DISCO;
+        assert_eq!(disco.surface(), (PANEL_WIDTH, PANEL_HEIGHT));
+        assert_eq!(disco.origin(), (0, 0));
+        assert!(disco.fits());
+        assert_eq!(
+            Orientation::Landscape.turned(disco.turn()),
+            Orientation::Landscape
+        );
+    }
+
+    #[test]
+    fn a_wide_panel_letterboxes_the_surface_turned() {
+        let wide = Panel::RGB_480X272;
+        assert_eq!(wide.surface(), (320, 240));
+        assert!(wide.fits());
+        // 80 columns either side, 16 rows above and below
+        assert_eq!(wide.origin(), (80, 16));
+    }
+
+    #[test]
+    fn panels_are_found_by_name() {
+        assert_eq!(Panel::parse("480x272"), Some(Panel::RGB_480X272));
+        assert_eq!(Panel::parse("disco"), Some(Panel::DISCO));
+        assert_eq!(Panel::parse("vga"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! Feather, sparkle and burst particles
+//!
+//! A fixed pool of small squares with integer physics: positions and
+//! velocities are in sixteenths of a pixel and move on once a game tick,
+//! with gravity pulling each kind down at its own rate. A particle fades
+//! out over its life and is dropped when that runs out. With the pool full
+//! a new burst takes what room is left and the rest is not emitted.
+
+use crate::config::Coord;
+use crate::rng::Rng;
+
+pub const MAX_PARTICLES: usize = 64;
+// Particles are drawn as squares this many pixels across
+pub const PARTICLE_SIZE: u32 = 2;
+// Fractional bits of positions and velocities
+const SUBPIXEL_SHIFT: u32 = 4;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Effect {
+    // A few feathers drifting back from a flap
+    Feathers,
+    // A twinkle where a pickup was collected
+    Sparkle,
+    // Bits flying every way when the bird crashes
+    Burst,
+}
+
+// How an effect's particles start out
+struct Spec {
+    count: usize,
+    // Ticks until gone
+    life: u8,
+    // Velocity range in 1/16 px per tick, from the emit point
+    vx: (Coord, Coord),
+    vy: (Coord, Coord),
+    // Added to vy each tick, 1/16 px
+    gravity: Coord,
+    colors: &'static [u16],
+}
+
+impl Effect {
+    fn spec(self) -> Spec {
+        match self {
+            Effect::Feathers => Spec {
+                count: 4,
+                life: 24,
+                vx: (-24, -8),
+                vy: (-8, 8),
+                gravity: 1,
+                colors: &[0xFFFF, 0xE71C, 0xFFDF],
+            },
+            Effect::Sparkle => Spec {
+                count: 6,
+                life: 16,
+                vx: (-24, 24),
+                vy: (-24, 24),
+                gravity: 0,
+                colors: &[0xFFE0, 0xFFFF, 0xFEA0],
+            },
+            Effect::Burst => Spec {
+                count: 24,
+                life: 40,
+                vx: (-48, 48),
+                vy: (-56, 24),
+                gravity: 3,
+                colors: &[0xF800, 0xFD20, 0xFFE0, 0xFFFF],
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Particle {
+    x: Coord,
+    y: Coord,
+    vx: Coord,
+    vy: Coord,
+    gravity: Coord,
+    life: u8,
+    max_life: u8,
+    pub color: u16,
+}
+
+impl Particle {
+    // Top left of its square, in pixels
+    pub fn xy(&self) -> (Coord, Coord) {
+        (self.x >> SUBPIXEL_SHIFT, self.y >> SUBPIXEL_SHIFT)
+    }
+
+    // Opacity, 255 when new down to nearly clear as it runs out
+    pub fn alpha(&self) -> u8 {
+        (self.life as u32 * 255 / self.max_life as u32) as u8
+    }
+
+    fn step(&mut self) {
+        self.x += self.vx;
+        self.y += self.vy;
+        self.vy += self.gravity;
+        self.life -= 1;
+    }
+}
+
+pub struct Particles {
+    pool: [Option<Particle>; MAX_PARTICLES],
+    rng: Rng,
+}
+
+impl Particles {
+    pub const fn new(seed: u32) -> Self {
+        Particles {
+            pool: [None; MAX_PARTICLES],
+            rng: Rng::new(seed),
+        }
+    }
+
+    // Start `effect`'s particles at pixel (x, y)
+    pub fn emit(&mut self, effect: Effect, x: Coord, y: Coord) {
+        let spec = effect.spec();
+        let mut left = spec.count;
+        for slot in self.pool.iter_mut().filter(|slot| slot.is_none()) {
+            if left == 0 {
+                break;
+            }
+            left -= 1;
+            let color = spec.colors[self.rng.below(spec.colors.len() as u32) as usize];
+            *slot = Some(Particle {
+                x: x << SUBPIXEL_SHIFT,
+                y: y << SUBPIXEL_SHIFT,
+                vx: pick(&mut self.rng, spec.vx),
+                vy: pick(&mut self.rng, spec.vy),
+                gravity: spec.gravity,
+                life: spec.life,
+                max_life: spec.life,
+                color,
+            });
+        }
+    }
+
+    // Move everything on one tick and drop what has run out
+    pub fn step(&mut self) {
+        for slot in self.pool.iter_mut() {
+            if let Some(particle) = slot.as_mut() {
+                particle.step();
+                if particle.life == 0 {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pool = [None; MAX_PARTICLES];
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.pool.iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool.iter().all(Option::is_none)
+    }
+}
+
+// Somewhere in lo..=hi
+fn pick(rng: &mut Rng, (lo, hi): (Coord, Coord)) -> Coord {
+    lo + rng.below((hi - lo + 1) as u32) as Coord
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emit_starts_at_the_point() {
+        let mut particles = Particles::new(1);
+        particles.emit(Effect::Sparkle, 50, 60);
+        assert_eq!(particles.len(), Effect::Sparkle.spec().count);
+        assert!(particles
+            .iter()
+            .all(|p| p.xy() == (50, 60) && p.alpha() == 255));
+    }
+
+    #[test]
+    fn the_pool_does_not_overflow() {
+        let mut particles = Particles::new(1);
+        for _ in 0..10 {
+            particles.emit(Effect::Burst, 0, 0);
+        }
+        assert_eq!(particles.len(), MAX_PARTICLES);
+    }
+
+    #[test]
+    fn particles_fade_and_run_out() {
+        let mut particles = Particles::new(1);
+        particles.emit(Effect::Feathers, 0, 0);
+        particles.step();
+        assert!(particles.iter().all(|p| p.alpha() < 255));
+        for _ in 1..Effect::Feathers.spec().life {
+            particles.step();
+        }
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn gravity_pulls_a_burst_down() {
+        let mut particles = Particles::new(3);
+        particles.emit(Effect::Burst, 100, 100);
+        let lowest = |particles: &Particles| particles.iter().map(|p| p.xy().1).max();
+        let before = lowest(&particles);
+        for _ in 0..30 {
+            particles.step();
+        }
+        assert!(lowest(&particles) > before);
+    }
+
+    #[test]
+    fn feathers_drift_back() {
+        let mut particles = Particles::new(5);
+        particles.emit(Effect::Feathers, 100, 100);
+        for _ in 0..16 {
+            particles.step();
+        }
+        assert!(particles.iter().all(|p| p.xy().0 < 100));
+    }
+}
@@ -0,0 +1,189 @@
+//! Coins and stars to collect for bonus points, and power-ups
+//!
+//! A pickup is spawned in an opening of a freshly wrapped obstacle and
+//! scrolls along with it. Touching it collects it: the bonus is scored once,
+//! then it floats up and blinks for COLLECT_FRAMES frames before it is gone.
+
+use crate::bird::Bird;
+use crate::config::*;
+use crate::obstacle::{ObstacleKind, ObstaclePair};
+use crate::powerup::PowerUp;
+use crate::rect::Rect;
+
+// Pickups are square
+pub const PICKUP_SIZE: u32 = 14;
+// Length of the collect animation
+pub const COLLECT_FRAMES: u32 = 16;
+// Rows a collected pickup rises per frame
+const COLLECT_RISE: Coord = 2;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PickupKind {
+    Coin,
+    Star,
+    // Worth no points, but see `powerup`
+    Power(PowerUp),
+}
+
+impl PickupKind {
+    pub fn points(self) -> u32 {
+        match self {
+            PickupKind::Coin => 1,
+            PickupKind::Star => 5,
+            PickupKind::Power(_) => 0,
+        }
+    }
+
+    // What an obstacle brings along for `roll`: nothing half the time,
+    // mostly coins otherwise and now and then a star
+    pub fn pick(roll: u32) -> Option<Self> {
+        match roll % 8 {
+            0..=3 => None,
+            4..=6 => Some(PickupKind::Coin),
+            _ => Some(PickupKind::Star),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    x: Coord,
+    y: Coord,
+    // Frames since it was collected, None while it is still up for grabs
+    collected: Option<u32>,
+}
+
+impl Pickup {
+    pub const fn new(kind: PickupKind, x: Coord, y: Coord) -> Self {
+        Pickup {
+            kind,
+            x,
+            y,
+            collected: None,
+        }
+    }
+
+    // A pickup centered in opening `gap` (0 or 1) of `obstacle`. None when
+    // there is no such opening, or it moves and would carry the pipe into
+    // the pickup.
+    pub fn in_gap(kind: PickupKind, obstacle: &ObstaclePair, gap: usize) -> Option<Self> {
+        if obstacle.kind() == ObstacleKind::Moving {
+            return None;
+        }
+        let (top, bottom) = (*obstacle.gaps().get(gap)?)?;
+        let (x, _) = obstacle.get_xy_top();
+        let inset = (OBSTACLE_WIDTH - PICKUP_SIZE) as Coord / 2;
+        let y = (top + bottom - PICKUP_SIZE as Coord) / 2;
+        Some(Pickup::new(kind, x + inset, y))
+    }
+
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.x, self.y, PICKUP_SIZE, PICKUP_SIZE)
+    }
+
+    // Scroll left by `speed`; once collected, also float up
+    pub fn advance(&mut self, speed: u32) {
+        self.x -= speed as Coord;
+        if let Some(frames) = self.collected.as_mut() {
+            *frames += 1;
+            self.y -= COLLECT_RISE;
+        }
+    }
+
+    // Points for the bird touching the pickup, the first time only
+    pub fn collect(&mut self, bird: &Bird) -> Option<u32> {
+        if self.collected.is_some() || !bird.rect().intersects(&self.rect()) {
+            return None;
+        }
+        self.collected = Some(0);
+        Some(self.kind.points())
+    }
+
+    pub fn is_collected(&self) -> bool {
+        self.collected.is_some()
+    }
+
+    // Whether to draw it this frame; collected pickups blink
+    pub fn is_visible(&self) -> bool {
+        match self.collected {
+            Some(frames) => frames % 4 < 2,
+            None => true,
+        }
+    }
+
+    // Off the left edge, or done with the collect animation
+    pub fn is_gone(&self) -> bool {
+        self.x + PICKUP_SIZE as Coord <= LCD_BIGIN
+            || self
+                .collected
+                .is_some_and(|frames| frames >= COLLECT_FRAMES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lane::Lane;
+
+    #[test]
+    fn sits_in_the_middle_of_the_opening() {
+        let pair = ObstaclePair::new(Lane::FULL);
+        let pickup = Pickup::in_gap(PickupKind::Coin, &pair, 0).unwrap();
+        let (x, top, bottom) = pair.get_gap();
+        let rect = pickup.rect();
+        assert_eq!(rect.x, x + 9);
+        assert_eq!(rect.y - top, bottom - (rect.y + PICKUP_SIZE as Coord));
+        assert!(pair.rects().all(|pipe| !pipe.intersects(&rect)));
+        assert_eq!(Pickup::in_gap(PickupKind::Coin, &pair, 1), None);
+    }
+
+    #[test]
+    fn double_obstacles_offer_both_openings_and_moving_ones_none() {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_kind(ObstacleKind::Double);
+        let upper = Pickup::in_gap(PickupKind::Star, &pair, 0).unwrap();
+        let lower = Pickup::in_gap(PickupKind::Star, &pair, 1).unwrap();
+        assert!(upper.rect().y < lower.rect().y);
+        assert!(pair.rects().all(|pipe| !pipe.intersects(&lower.rect())));
+        pair.set_kind(ObstacleKind::Moving);
+        assert_eq!(Pickup::in_gap(PickupKind::Star, &pair, 0), None);
+    }
+
+    #[test]
+    fn collects_once_then_floats_away() {
+        let mut pickup = Pickup::new(PickupKind::Star, INIT_PLAYER_POS_X + 4, 100);
+        let bird = Bird::new(INIT_PLAYER_POS_X, 95);
+        assert_eq!(pickup.collect(&bird), Some(5));
+        assert_eq!(pickup.collect(&bird), None);
+        let mut frames = 0;
+        while !pickup.is_gone() {
+            pickup.advance(0);
+            frames += 1;
+        }
+        assert_eq!(frames, COLLECT_FRAMES);
+        assert_eq!(
+            pickup.rect().y,
+            100 - COLLECT_RISE * COLLECT_FRAMES as Coord
+        );
+    }
+
+    #[test]
+    fn missed_pickups_scroll_off() {
+        let mut pickup = Pickup::new(PickupKind::Coin, 20, 100);
+        let bird = Bird::new(INIT_PLAYER_POS_X, 200);
+        assert_eq!(pickup.collect(&bird), None);
+        assert!(pickup.is_visible());
+        pickup.advance(20);
+        assert!(!pickup.is_gone());
+        pickup.advance(PICKUP_SIZE);
+        assert!(pickup.is_gone());
+    }
+
+    #[test]
+    fn about_half_the_obstacles_bring_one() {
+        let picks: Vec<_> = (0..8).map(PickupKind::pick).collect();
+        assert_eq!(picks.iter().filter(|p| p.is_none()).count(), 4);
+        assert!(picks.contains(&Some(PickupKind::Star)));
+    }
+}
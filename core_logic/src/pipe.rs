@@ -0,0 +1,208 @@
+//! Pipe art worked out from a theme's colors
+//!
+//! Each theme names a pipe color and a cap color; the art is built from
+//! them at compile time. The body is a strip `BODY_ROWS` tall that tiles
+//! down a pipe of any height, the cap a block `CAP_ROWS` tall that sits
+//! where the pipe meets an opening. Both are an obstacle wide and shaded
+//! across as if lit from the left: `EDGE` pixels of highlight down the left
+//! side, `EDGE` of shadow down the right, and the color between them
+//! brightest a quarter of the way in. The body has a darker seam every
+//! strip, the cap a dark rim along its top and bottom. Rows are stored
+//! bottom first, as the flash assets are. The cap is the same upside down,
+//! so only its first half is stored (`CAP_MIRROR`).
+//!
+//! For the high-contrast theme there is flat art instead (`outlined_body`,
+//! `outlined_cap`): one fill, `OUTLINE` pixels of a second color down both
+//! sides and, on the cap, along its rims too.
+
+use crate::color::Rgb565;
+use crate::config::{OBSTACLE_WIDTH, TILE};
+use crate::render::Mirror;
+
+pub const WIDTH: usize = OBSTACLE_WIDTH as usize;
+pub const BODY_ROWS: usize = 8;
+pub const CAP_ROWS: usize = TILE as usize;
+pub const EDGE: usize = 2;
+pub const OUTLINE: usize = 3;
+pub const BODY_PIXELS: usize = WIDTH * BODY_ROWS;
+pub const CAP_MIRROR: Mirror = Mirror::DOWN;
+pub const CAP_PIXELS: usize = CAP_MIRROR.pixels(WIDTH as u32, CAP_ROWS as u32);
+
+// How far towards white the highlight goes, and towards black the shadow,
+// seam and rim, out of 255
+const HIGHLIGHT: u8 = 110;
+const SHADOW: u8 = 120;
+const SEAM: u8 = 50;
+const RIM: u8 = 140;
+// Most the shading between the edges lightens or darkens, out of 255
+const SHADING: i32 = 70;
+
+// Column `col` of a pipe in `base`
+const fn across(base: Rgb565, col: usize) -> Rgb565 {
+    if col < EDGE {
+        return base.lerp(Rgb565::WHITE, HIGHLIGHT);
+    }
+    if col >= WIDTH - EDGE {
+        return base.lerp(Rgb565::BLACK, SHADOW);
+    }
+    // Lighter up to a quarter of the way in, then darker towards the
+    // shadowed right
+    let peak = (WIDTH / 4) as i32;
+    let (first, last) = (EDGE as i32, (WIDTH - EDGE) as i32 - 1);
+    let col = col as i32;
+    let light = if col <= peak {
+        SHADING * (col - first + 1) / (peak - first + 1)
+    } else {
+        SHADING - 2 * SHADING * (col - peak) / (last - peak)
+    };
+    if light >= 0 {
+        base.lerp(Rgb565::WHITE, light as u8)
+    } else {
+        base.lerp(Rgb565::BLACK, -light as u8)
+    }
+}
+
+// The body strip for a pipe in `base`
+pub const fn body(base: u16) -> [u16; BODY_PIXELS] {
+    let base = Rgb565(base);
+    let mut pixels = [0; BODY_PIXELS];
+    let mut i = 0;
+    while i < BODY_PIXELS {
+        let (row, col) = (i / WIDTH, i % WIDTH);
+        let mut color = across(base, col);
+        // The seam is the strip's top row, stored last
+        if row == BODY_ROWS - 1 {
+            color = color.lerp(Rgb565::BLACK, SEAM);
+        }
+        pixels[i] = color.0;
+        i += 1;
+    }
+    pixels
+}
+
+// The stored half of the cap for a pipe with caps in `base`; its other
+// rim is the first row read back
+pub const fn cap(base: u16) -> [u16; CAP_PIXELS] {
+    let base = Rgb565(base);
+    let mut pixels = [0; CAP_PIXELS];
+    let mut i = 0;
+    while i < CAP_PIXELS {
+        let (row, col) = (i / WIDTH, i % WIDTH);
+        let mut color = across(base, col);
+        if row == 0 {
+            color = color.lerp(Rgb565::BLACK, RIM);
+        }
+        pixels[i] = color.0;
+        i += 1;
+    }
+    pixels
+}
+
+// Flat body strip in `fill`, outlined down both sides in `outline`
+pub const fn outlined_body(fill: u16, outline: u16) -> [u16; BODY_PIXELS] {
+    let mut pixels = [0; BODY_PIXELS];
+    let mut i = 0;
+    while i < BODY_PIXELS {
+        let col = i % WIDTH;
+        let edge = col < OUTLINE || col >= WIDTH - OUTLINE;
+        pixels[i] = if edge { outline } else { fill };
+        i += 1;
+    }
+    pixels
+}
+
+// The stored half of a flat cap in `fill`, outlined all round in `outline`
+pub const fn outlined_cap(fill: u16, outline: u16) -> [u16; CAP_PIXELS] {
+    let mut pixels = [0; CAP_PIXELS];
+    let mut i = 0;
+    while i < CAP_PIXELS {
+        let (row, col) = (i / WIDTH, i % WIDTH);
+        let edge = row < OUTLINE || col < OUTLINE || col >= WIDTH - OUTLINE;
+        pixels[i] = if edge { outline } else { fill };
+        i += 1;
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Image;
+
+    const GREEN: u16 = 0x2DE5;
+
+    fn luma(rgb565: u16) -> u32 {
+        let (r, g, b) = Rgb565(rgb565).to_rgb();
+        r as u32 * 3 + g as u32 * 6 + b as u32
+    }
+
+    #[test]
+    fn edges_are_highlight_and_shadow() {
+        let strip = body(GREEN);
+        let row = &strip[..WIDTH];
+        let middle = luma(GREEN);
+        for col in 0..EDGE {
+            assert!(luma(row[col]) > middle, "{}", col);
+            assert!(luma(row[WIDTH - 1 - col]) < middle, "{}", col);
+        }
+        // Brightest inside the edges a quarter of the way in
+        let inner = &row[EDGE..WIDTH - EDGE];
+        let brightest = (0..inner.len()).max_by_key(|&i| luma(inner[i])).unwrap();
+        assert_eq!(brightest + EDGE, WIDTH / 4);
+    }
+
+    #[test]
+    fn shading_falls_off_towards_the_shadow() {
+        let strip = body(GREEN);
+        let row = &strip[..WIDTH];
+        for col in WIDTH / 4..WIDTH - EDGE - 1 {
+            assert!(luma(row[col]) >= luma(row[col + 1]), "{}", col);
+        }
+    }
+
+    #[test]
+    fn body_rows_match_but_for_the_seam() {
+        let strip = body(GREEN);
+        for row in 1..BODY_ROWS - 1 {
+            assert_eq!(strip[row * WIDTH..][..WIDTH], strip[..WIDTH]);
+        }
+        let seam = &strip[(BODY_ROWS - 1) * WIDTH..];
+        assert!((0..WIDTH).all(|col| luma(seam[col]) <= luma(strip[col])));
+    }
+
+    #[test]
+    fn cap_has_a_rim_top_and_bottom() {
+        let pixels = cap(GREEN);
+        let cap = Image::mirrored(WIDTH as u32, CAP_ROWS as u32, CAP_MIRROR, &pixels);
+        let row = |row: usize| (0..WIDTH).map(move |col| cap.at(col as u32, row as u32).unwrap());
+        assert!(row(0).eq(row(CAP_ROWS - 1)));
+        assert!(row(1).eq(row(CAP_ROWS - 2)));
+        assert!(row(0)
+            .zip(row(1))
+            .all(|(rim, inside)| luma(rim) < luma(inside)));
+        assert_eq!(pixels.len(), WIDTH * CAP_ROWS / 2);
+    }
+
+    #[test]
+    fn outlined_art_is_flat_inside_a_thick_outline() {
+        const FILL: u16 = 0xFFFF;
+        const LINE: u16 = 0xFFE0;
+        let strip = outlined_body(FILL, LINE);
+        for row in strip.chunks(WIDTH) {
+            assert!(row[..OUTLINE]
+                .iter()
+                .chain(&row[WIDTH - OUTLINE..])
+                .all(|&p| p == LINE));
+            assert!(row[OUTLINE..WIDTH - OUTLINE].iter().all(|&p| p == FILL));
+        }
+        let pixels = outlined_cap(FILL, LINE);
+        let cap = Image::mirrored(WIDTH as u32, CAP_ROWS as u32, CAP_MIRROR, &pixels);
+        let at = |col: usize, row: usize| cap.at(col as u32, row as u32).unwrap();
+        for col in 0..WIDTH {
+            assert_eq!(at(col, 0), LINE);
+            assert_eq!(at(col, CAP_ROWS - 1), LINE);
+        }
+        assert_eq!(at(WIDTH / 2, CAP_ROWS / 2), FILL);
+        assert_eq!(at(0, CAP_ROWS / 2), LINE);
+    }
+}
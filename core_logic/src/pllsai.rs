@@ -0,0 +1,177 @@
+//! PLLSAI settings for an LTDC pixel clock
+//!
+//! The pixel clock is the PLL input (HSE / PLLM) times N, divided by R and
+//! then by DIVR. N runs 50..=432 with the VCO it makes between 100 and
+//! 432 MHz, R runs 2..=7 and DIVR is 2, 4, 8 or 16, so any target takes
+//! some working out. `PllSai::solve` tries every combination and keeps the
+//! one closest to the target; it is const, so a fixed target is worked out
+//! at compile time.
+
+pub const N_MIN: u32 = 50;
+pub const N_MAX: u32 = 432;
+pub const R_MIN: u32 = 2;
+pub const R_MAX: u32 = 7;
+pub const VCO_MIN_HZ: u32 = 100_000_000;
+pub const VCO_MAX_HZ: u32 = 432_000_000;
+
+/// One PLLSAI setting, as the RCC fields hold it
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PllSai {
+    pub n: u16,
+    pub r: u8,
+    // PLLSAIDIVR: 0..=3 divide by 2, 4, 8, 16
+    pub divr_bits: u8,
+}
+
+impl PllSai {
+    pub const fn divr(&self) -> u32 {
+        2 << self.divr_bits
+    }
+
+    pub const fn vco_hz(&self, input_hz: u32) -> u32 {
+        input_hz * self.n as u32
+    }
+
+    // Pixel clock this setting makes from `input_hz`
+    pub const fn hz(&self, input_hz: u32) -> u32 {
+        self.vco_hz(input_hz) / self.r as u32 / self.divr()
+    }
+
+    // The setting closest to `target_hz` from `input_hz`; between equally
+    // close ones the higher VCO, which jitters less. None if no N keeps the
+    // VCO in range for this input.
+    pub const fn solve(input_hz: u32, target_hz: u32) -> Option<Self> {
+        if input_hz == 0 {
+            return None;
+        }
+        // The N range the VCO limits leave
+        let n_low = max(N_MIN, VCO_MIN_HZ.div_ceil(input_hz));
+        let n_high = min(N_MAX, VCO_MAX_HZ / input_hz);
+        if n_low > n_high {
+            return None;
+        }
+        let mut best: Option<(PllSai, u32)> = None;
+        let mut divr_bits = 0;
+        while divr_bits < 4 {
+            let mut r = R_MIN;
+            while r <= R_MAX {
+                // Nearest N for this R and DIVR, held to the VCO range
+                let per_n = input_hz as u64;
+                let wanted = target_hz as u64 * r as u64 * (2u64 << divr_bits);
+                let n = (wanted + per_n / 2) / per_n;
+                let n = if n < n_low as u64 {
+                    n_low
+                } else if n > n_high as u64 {
+                    n_high
+                } else {
+                    n as u32
+                };
+                let pll = PllSai {
+                    n: n as u16,
+                    r: r as u8,
+                    divr_bits,
+                };
+                let error = pll.hz(input_hz).abs_diff(target_hz);
+                best = match best {
+                    Some((kept, kept_error))
+                        if kept_error < error
+                            || (kept_error == error
+                                && kept.vco_hz(input_hz) >= pll.vco_hz(input_hz)) =>
+                    {
+                        Some((kept, kept_error))
+                    }
+                    _ => Some((pll, error)),
+                };
+                r += 1;
+            }
+            divr_bits += 1;
+        }
+        match best {
+            Some((pll, _)) => Some(pll),
+            None => None,
+        }
+    }
+}
+
+const fn max(a: u32, b: u32) -> u32 {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+const fn min(a: u32, b: u32) -> u32 {
+    if a < b {
+        a
+    } else {
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MHZ: u32 = 1_000_000;
+
+    fn valid(pll: &PllSai, input_hz: u32) -> bool {
+        (N_MIN..=N_MAX).contains(&(pll.n as u32))
+            && (R_MIN..=R_MAX).contains(&(pll.r as u32))
+            && (VCO_MIN_HZ..=VCO_MAX_HZ).contains(&pll.vco_hz(input_hz))
+    }
+
+    #[test]
+    fn hits_exact_targets_with_the_highest_vco() {
+        let pll = PllSai::solve(MHZ, 18 * MHZ).unwrap();
+        assert_eq!(pll.hz(MHZ), 18 * MHZ);
+        assert_eq!(
+            pll,
+            PllSai {
+                n: 432,
+                r: 6,
+                divr_bits: 1
+            }
+        );
+        let pll = PllSai::solve(MHZ, 6 * MHZ).unwrap();
+        assert_eq!(pll.hz(MHZ), 6 * MHZ);
+        assert!(valid(&pll, MHZ));
+    }
+
+    #[test]
+    fn gets_close_to_odd_targets() {
+        for target in [4_800_000, 6_250_000, 9_500_000, 12_345_678, 25_175_000] {
+            let pll = PllSai::solve(MHZ, target).unwrap();
+            assert!(valid(&pll, MHZ), "{}", target);
+            // Steps at the slowest are 1 MHz / (7 * 16), under 9 kHz
+            assert!(
+                pll.hz(MHZ).abs_diff(target) < 60_000,
+                "{} {:?}",
+                target,
+                pll
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_reach_targets_get_the_nearest_end() {
+        let low = PllSai::solve(MHZ, 1).unwrap();
+        assert_eq!(low.hz(MHZ), VCO_MIN_HZ / 7 / 16);
+        let high = PllSai::solve(MHZ, u32::MAX).unwrap();
+        assert_eq!(high.hz(MHZ), VCO_MAX_HZ / 2 / 2);
+        assert!(valid(&low, MHZ) && valid(&high, MHZ));
+    }
+
+    #[test]
+    fn no_setting_without_a_usable_input() {
+        assert_eq!(PllSai::solve(0, 18 * MHZ), None);
+        // 500 MHz in puts even N = 50 past the VCO limit
+        assert_eq!(PllSai::solve(500 * MHZ, 18 * MHZ), None);
+    }
+
+    #[test]
+    fn solves_at_compile_time() {
+        const PLL: Option<PllSai> = PllSai::solve(MHZ, 18 * MHZ);
+        assert!(PLL.is_some());
+    }
+}
@@ -0,0 +1,187 @@
+//! Power-ups: help for a while, picked up in the openings
+//!
+//! A power-up sits in an opening the way a coin does and is collected the
+//! same way (see `pickup`). `Shield` takes the next crash and breaks, and
+//! for a moment after nothing counts as one, so the bird can get clear of
+//! whatever it hit. `SlowTime` plays the game at half speed for five
+//! seconds. `Shrink` takes a few pixels off every side of the bird's box
+//! for crashes. The timed ones count down in game ticks, and collecting
+//! one again starts it over.
+
+use crate::timestep::FULL_SPEED;
+
+// Half speed for five seconds of real time at 60 ticks a second
+pub const SLOW_TICKS: u32 = 150;
+pub const SLOW_SCALE: u32 = FULL_SPEED / 2;
+pub const SHRINK_TICKS: u32 = 480;
+// Pixels off each side of the bird's box while shrunk
+pub const SHRINK_INSET: u32 = 6;
+// Ticks after the shield breaks that nothing counts as a crash
+pub const GRACE_TICKS: u32 = 30;
+// One obstacle in this many brings a power-up
+const ONE_IN: u32 = 12;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PowerUp {
+    Shield,
+    SlowTime,
+    Shrink,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 3] = [PowerUp::Shield, PowerUp::SlowTime, PowerUp::Shrink];
+
+    // Now and then one of them, for `roll`
+    pub fn pick(roll: u32) -> Option<Self> {
+        if !roll.is_multiple_of(ONE_IN) {
+            return None;
+        }
+        Some(Self::ALL[(roll / ONE_IN) as usize % Self::ALL.len()])
+    }
+
+    // Ticks it lasts for; the shield lasts until it is hit
+    pub fn ticks(self) -> Option<u32> {
+        match self {
+            PowerUp::Shield => None,
+            PowerUp::SlowTime => Some(SLOW_TICKS),
+            PowerUp::Shrink => Some(SHRINK_TICKS),
+        }
+    }
+}
+
+/// The power-ups the bird has now
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Powers {
+    shield: bool,
+    // Ticks left of each; 0 when off
+    grace: u32,
+    slow: u32,
+    shrink: u32,
+}
+
+impl Powers {
+    pub const fn new() -> Self {
+        Powers {
+            shield: false,
+            grace: 0,
+            slow: 0,
+            shrink: 0,
+        }
+    }
+
+    pub fn grant(&mut self, power: PowerUp) {
+        match power {
+            PowerUp::Shield => self.shield = true,
+            PowerUp::SlowTime => self.slow = SLOW_TICKS,
+            PowerUp::Shrink => self.shrink = SHRINK_TICKS,
+        }
+    }
+
+    // One tick on
+    pub fn tick(&mut self) {
+        self.grace = self.grace.saturating_sub(1);
+        self.slow = self.slow.saturating_sub(1);
+        self.shrink = self.shrink.saturating_sub(1);
+    }
+
+    // The bird crashed: true if the shield (or the grace after it broke)
+    // takes it
+    pub fn absorb(&mut self) -> bool {
+        if self.grace > 0 {
+            return true;
+        }
+        if self.shield {
+            self.shield = false;
+            self.grace = GRACE_TICKS;
+            return true;
+        }
+        false
+    }
+
+    // Game clock speed, in 1/FULL_SPEED
+    pub fn time_scale(&self) -> u32 {
+        if self.slow > 0 {
+            SLOW_SCALE
+        } else {
+            FULL_SPEED
+        }
+    }
+
+    // Pixels off each side of the bird's box for crashes
+    pub fn inset(&self) -> u32 {
+        if self.shrink > 0 {
+            SHRINK_INSET
+        } else {
+            0
+        }
+    }
+
+    // How much of `power` is left, 0..=255 of its full length; a shield is
+    // whole until it breaks. None when it is not on.
+    pub fn remaining(&self, power: PowerUp) -> Option<u8> {
+        let left = match power {
+            PowerUp::Shield => return self.shield.then_some(255),
+            PowerUp::SlowTime => self.slow,
+            PowerUp::Shrink => self.shrink,
+        };
+        let total = power.ticks()?;
+        (left > 0).then(|| (left * 255 / total) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_each_now_and_then() {
+        let picks: std::vec::Vec<_> = (0..ONE_IN * 3).filter_map(PowerUp::pick).collect();
+        assert_eq!(picks, PowerUp::ALL);
+    }
+
+    #[test]
+    fn shield_takes_one_crash_then_a_moment_more() {
+        let mut powers = Powers::new();
+        assert!(!powers.absorb());
+        powers.grant(PowerUp::Shield);
+        assert_eq!(powers.remaining(PowerUp::Shield), Some(255));
+        assert!(powers.absorb());
+        assert_eq!(powers.remaining(PowerUp::Shield), None);
+        for _ in 0..GRACE_TICKS {
+            assert!(powers.absorb());
+            powers.tick();
+        }
+        assert!(!powers.absorb());
+    }
+
+    #[test]
+    fn slow_time_runs_out() {
+        let mut powers = Powers::new();
+        powers.grant(PowerUp::SlowTime);
+        assert_eq!(powers.time_scale(), SLOW_SCALE);
+        assert_eq!(powers.remaining(PowerUp::SlowTime), Some(255));
+        for _ in 0..SLOW_TICKS / 2 {
+            powers.tick();
+        }
+        assert_eq!(powers.remaining(PowerUp::SlowTime), Some(127));
+        for _ in 0..SLOW_TICKS / 2 {
+            powers.tick();
+        }
+        assert_eq!(powers.time_scale(), FULL_SPEED);
+        assert_eq!(powers.remaining(PowerUp::SlowTime), None);
+    }
+
+    #[test]
+    fn shrink_starts_over_when_collected_again() {
+        let mut powers = Powers::new();
+        powers.grant(PowerUp::Shrink);
+        for _ in 0..SHRINK_TICKS - 1 {
+            powers.tick();
+        }
+        assert_eq!(powers.inset(), SHRINK_INSET);
+        powers.grant(PowerUp::Shrink);
+        powers.tick();
+        assert_eq!(powers.remaining(PowerUp::Shrink), Some(254));
+        assert_eq!(powers.inset(), SHRINK_INSET);
+    }
+}
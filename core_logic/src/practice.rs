@@ -0,0 +1,90 @@
+//! Practice runs: a crash goes back to the last obstacle passed
+//!
+//! The game keeps a checkpoint from the moment each obstacle is passed and
+//! puts the world back to it on a crash. `Practice` counts the tries at
+//! the obstacle after the checkpoint and keeps the bird from crashing for
+//! the first few ticks after coming back, so it is not put straight into
+//! the pipe it just hit.
+
+// Ticks after coming back that nothing counts as a crash
+pub const INVULNERABLE_TICKS: u32 = 3;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Practice {
+    // Tries at the obstacle ahead, this one included
+    attempts: u32,
+    // Ticks of invulnerability left
+    invulnerable: u32,
+}
+
+impl Practice {
+    pub const fn new() -> Self {
+        Practice {
+            attempts: 1,
+            invulnerable: 0,
+        }
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    // An obstacle was passed: the next one starts on its first try
+    pub fn passed(&mut self) {
+        self.attempts = 1;
+    }
+
+    // Back at the checkpoint after a crash
+    pub fn respawned(&mut self) {
+        self.attempts += 1;
+        self.invulnerable = INVULNERABLE_TICKS;
+    }
+
+    // One tick on; true if a crash this tick counts
+    pub fn tick(&mut self) -> bool {
+        if self.invulnerable > 0 {
+            self.invulnerable -= 1;
+            return false;
+        }
+        true
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0
+    }
+}
+
+impl Default for Practice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_tries_at_each_obstacle() {
+        let mut practice = Practice::new();
+        assert_eq!(practice.attempts(), 1);
+        practice.respawned();
+        practice.respawned();
+        assert_eq!(practice.attempts(), 3);
+        practice.passed();
+        assert_eq!(practice.attempts(), 1);
+    }
+
+    #[test]
+    fn crashes_do_not_count_just_after_coming_back() {
+        let mut practice = Practice::new();
+        assert!(practice.tick());
+        practice.respawned();
+        for _ in 0..INVULNERABLE_TICKS {
+            assert!(practice.is_invulnerable());
+            assert!(!practice.tick());
+        }
+        assert!(!practice.is_invulnerable());
+        assert!(practice.tick());
+    }
+}
@@ -0,0 +1,228 @@
+//! Player profiles: who is playing, and what they have done so far
+//!
+//! A board shared by a few people keeps a `Profile` for each of up to
+//! `SLOTS` of them: their initials, their best score, the games they have
+//! finished and the `Achievement`s those earned. Each is stored as its own
+//! record (see `to_words`), followed by that player's settings, so one
+//! person's progress and options never overwrite another's.
+//!
+//! Achievements are earned once and kept. Every one is a milestone a
+//! finished game either reaches or not, so `record_game` is all that
+//! awards them.
+
+use crate::leaderboard::INITIALS;
+
+pub const SLOTS: usize = 3;
+// Format of the stored words; a record of any other is passed over
+pub const VERSION: u32 = 1;
+// Stored words: version, initials, best score, games, achievements
+pub const WORDS: usize = 5;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    // A game played to the end
+    FirstFlight,
+    Score10,
+    Score25,
+    Score50,
+    Score100,
+    // Games played to the end
+    Regular,
+}
+
+// Finished games for `Regular`
+const REGULAR_GAMES: u32 = 25;
+
+impl Achievement {
+    pub const ALL: [Achievement; 6] = [
+        Achievement::FirstFlight,
+        Achievement::Score10,
+        Achievement::Score25,
+        Achievement::Score50,
+        Achievement::Score100,
+        Achievement::Regular,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstFlight => "first flight",
+            Achievement::Score10 => "10 pipes",
+            Achievement::Score25 => "25 pipes",
+            Achievement::Score50 => "50 pipes",
+            Achievement::Score100 => "100 pipes",
+            Achievement::Regular => "regular",
+        }
+    }
+
+    // Whether a game of `score`, the `games`th finished, earns it
+    fn earned_by(self, score: u32, games: u32) -> bool {
+        match self {
+            Achievement::FirstFlight => games >= 1,
+            Achievement::Score10 => score >= 10,
+            Achievement::Score25 => score >= 25,
+            Achievement::Score50 => score >= 50,
+            Achievement::Score100 => score >= 100,
+            Achievement::Regular => games >= REGULAR_GAMES,
+        }
+    }
+
+    const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// A set of achievements, one bit each
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Achievements(u32);
+
+impl Achievements {
+    pub const NONE: Achievements = Achievements(0);
+    const KNOWN: u32 = (1 << Achievement::ALL.len()) - 1;
+
+    pub fn has(self, achievement: Achievement) -> bool {
+        self.0 & achievement.bit() != 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    // The ones held, in `Achievement::ALL` order
+    pub fn iter(self) -> impl Iterator<Item = Achievement> {
+        Achievement::ALL.into_iter().filter(move |a| self.has(*a))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Profile {
+    // ASCII capitals, as on the leaderboard
+    pub initials: [u8; INITIALS],
+    pub best_score: u32,
+    // Games played to the end
+    pub games: u32,
+    pub achievements: Achievements,
+}
+
+impl Profile {
+    // A fresh profile for slot `slot`, named PL1, PL2, ... until its player
+    // signs a run
+    pub fn new(slot: usize) -> Self {
+        Profile {
+            initials: [b'P', b'L', b'1' + (slot % SLOTS) as u8],
+            best_score: 0,
+            games: 0,
+            achievements: Achievements::NONE,
+        }
+    }
+
+    pub fn initials(&self) -> &str {
+        core::str::from_utf8(&self.initials).unwrap_or("???")
+    }
+
+    // Fold in a finished game of `score`; the achievements it newly earned
+    pub fn record_game(&mut self, score: u32) -> Achievements {
+        self.games = self.games.saturating_add(1);
+        self.best_score = self.best_score.max(score);
+        let earned = Achievement::ALL
+            .into_iter()
+            .filter(|a| !self.achievements.has(*a) && a.earned_by(score, self.games))
+            .fold(0, |bits, a| bits | a.bit());
+        self.achievements.0 |= earned;
+        Achievements(earned)
+    }
+
+    pub fn to_words(&self) -> [u32; WORDS] {
+        let [a, b, c] = self.initials;
+        [
+            VERSION,
+            u32::from_le_bytes([a, b, c, 0]),
+            self.best_score,
+            self.games,
+            self.achievements.0,
+        ]
+    }
+
+    // The profile at the start of `words`; None unless it is this version
+    // and spelled and earned as a saved one can be
+    pub fn from_words(words: &[u32]) -> Option<Self> {
+        let &[version, initials, best_score, games, achievements] = words.get(..WORDS)? else {
+            return None;
+        };
+        let [a, b, c, _] = initials.to_le_bytes();
+        let initials = [a, b, c];
+        let spelled = initials
+            .iter()
+            .all(|l| l.is_ascii_uppercase() || l.is_ascii_digit());
+        (version == VERSION && spelled && achievements & !Achievements::KNOWN == 0).then_some(
+            Profile {
+                initials,
+                best_score,
+                games,
+                achievements: Achievements(achievements),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_words() {
+        let mut profile = Profile::new(1);
+        assert_eq!(profile.initials(), "PL2");
+        profile.initials = *b"ABC";
+        profile.record_game(27);
+        assert_eq!(Profile::from_words(&profile.to_words()), Some(profile));
+        // Trailing words are the settings', not the profile's
+        let mut words = [0; WORDS + 2];
+        words[..WORDS].copy_from_slice(&profile.to_words());
+        assert_eq!(Profile::from_words(&words), Some(profile));
+    }
+
+    #[test]
+    fn rejects_what_was_never_saved() {
+        let words = Profile::new(0).to_words();
+        assert_eq!(Profile::from_words(&words[..WORDS - 1]), None);
+        let mut other = words;
+        other[0] = VERSION + 1;
+        assert_eq!(Profile::from_words(&other), None);
+        let mut lower = words;
+        lower[1] = u32::from_le_bytes(*b"abc\0");
+        assert_eq!(Profile::from_words(&lower), None);
+        let mut unknown = words;
+        unknown[4] = 1 << 31;
+        assert_eq!(Profile::from_words(&unknown), None);
+        assert_eq!(Profile::from_words(&[u32::MAX; WORDS]), None);
+    }
+
+    #[test]
+    fn achievements_are_earned_once_and_kept() {
+        let mut profile = Profile::new(0);
+        let earned = profile.record_game(12);
+        assert_eq!(
+            earned.iter().collect::<Vec<_>>(),
+            [Achievement::FirstFlight, Achievement::Score10]
+        );
+        // A worse game takes nothing away, and earns nothing again
+        assert!(profile.record_game(3).is_empty());
+        assert_eq!(profile.best_score, 12);
+        assert_eq!(profile.achievements.count(), 2);
+
+        let earned = profile.record_game(60);
+        assert_eq!(
+            earned.iter().collect::<Vec<_>>(),
+            [Achievement::Score25, Achievement::Score50]
+        );
+        for _ in profile.games..REGULAR_GAMES - 1 {
+            assert!(profile.record_game(0).is_empty());
+        }
+        assert!(profile.record_game(0).has(Achievement::Regular));
+        assert_eq!(profile.games, REGULAR_GAMES);
+    }
+}
@@ -0,0 +1,255 @@
+//! Per-scanline raster effects
+//!
+//! A raster effect changes how each panel scanline is copied to the screen,
+//! the way old consoles rewrote scroll registers between lines. For every
+//! scanline it gives a `Line`: how far to slide the pixels along it and
+//! what color to tint them towards, within a stretch of the line (`start`,
+//! `len`) so the effect can keep to part of the screen. The game picture
+//! itself is untouched; only the copy that is shown is bent.
+//!
+//! `Wave` rolls the whole screen in a slow sine, tinted, like looking
+//! through water. `Shimmer` jitters a band of scanlines a pixel or two this
+//! way and that, like hot air over the ground. Both are pure functions of
+//! the scanline and the frame, so the same frame always looks the same.
+
+use crate::color::Rgb565;
+
+/// What one scanline looks like
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Line {
+    // Pixels the span's contents move towards the end of the line (towards
+    // the start when negative)
+    pub shift: i32,
+    // Color to blend the span towards and how far (0..=255)
+    pub tint: Option<(Rgb565, u8)>,
+    // The stretch of the line the effect covers
+    pub start: u32,
+    pub len: u32,
+}
+
+impl Line {
+    // The line as drawn
+    pub const PLAIN: Line = Line {
+        shift: 0,
+        tint: None,
+        start: 0,
+        len: 0,
+    };
+
+    pub fn is_plain(&self) -> bool {
+        self.len == 0 || (self.shift == 0 && self.tint.is_none())
+    }
+}
+
+pub trait RasterEffect {
+    // Scanline `row` at `frame` frames into the effect
+    fn line(&self, row: u32, frame: u32) -> Line;
+}
+
+// Quarter of a sine wave in SINE_STEPS / 4 steps, scaled to SINE_ONE
+const QUARTER_SINE: [i32; 17] = [
+    0, 25, 50, 74, 98, 121, 142, 162, 181, 198, 213, 226, 237, 245, 251, 255, 256,
+];
+pub(crate) const SINE_STEPS: u32 = 64;
+pub(crate) const SINE_ONE: i32 = 256;
+
+// sin(2 pi step / SINE_STEPS) * SINE_ONE
+pub(crate) fn sine(step: u32) -> i32 {
+    let quarter = SINE_STEPS / 4;
+    let step = step % SINE_STEPS;
+    let within = step % quarter;
+    match step / quarter {
+        0 => QUARTER_SINE[within as usize],
+        1 => QUARTER_SINE[(quarter - within) as usize],
+        2 => -QUARTER_SINE[within as usize],
+        _ => -QUARTER_SINE[(quarter - within) as usize],
+    }
+}
+
+/// The whole screen swaying in a slow sine, as if under water
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Wave {
+    // Pixels the lines swing either way
+    pub amplitude: i32,
+    // Scanlines from one crest to the next
+    pub wavelength: u32,
+    // Frames for a crest to roll on to where the next one was
+    pub period: u32,
+    // Scanline length
+    pub width: u32,
+    pub tint: Option<(Rgb565, u8)>,
+}
+
+impl Wave {
+    // Murky blue-green, a few pixels of sway
+    pub const fn underwater(width: u32) -> Self {
+        Wave {
+            amplitude: 4,
+            wavelength: 48,
+            period: 40,
+            width,
+            tint: Some((Rgb565::from_rgb(0, 96, 160), 64)),
+        }
+    }
+}
+
+impl RasterEffect for Wave {
+    fn line(&self, row: u32, frame: u32) -> Line {
+        let phase =
+            row * SINE_STEPS / self.wavelength.max(1) + frame * SINE_STEPS / self.period.max(1);
+        Line {
+            shift: self.amplitude * sine(phase) / SINE_ONE,
+            tint: self.tint,
+            start: 0,
+            len: self.width,
+        }
+    }
+}
+
+/// Scanlines `top..bottom` trembling in place, as in heat haze
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Shimmer {
+    pub top: u32,
+    pub bottom: u32,
+    pub start: u32,
+    pub len: u32,
+    // Pixels a line can move either way
+    pub amplitude: u32,
+    // Frames each jitter holds for
+    pub hold: u32,
+}
+
+impl Shimmer {
+    // A pixel of haze, changing every other frame, over scanlines
+    // top..bottom and columns start..start + len
+    pub const fn heat(top: u32, bottom: u32, start: u32, len: u32) -> Self {
+        Shimmer {
+            top,
+            bottom,
+            start,
+            len,
+            amplitude: 1,
+            hold: 2,
+        }
+    }
+}
+
+// Scrambles a scanline and frame into an unrelated number
+fn hash(row: u32, frame: u32) -> u32 {
+    let mut x = row.wrapping_mul(0x9E37_79B9) ^ frame.wrapping_mul(0x85EB_CA6B);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2C1B_3C6D);
+    x ^ (x >> 12)
+}
+
+impl RasterEffect for Shimmer {
+    fn line(&self, row: u32, frame: u32) -> Line {
+        if !(self.top..self.bottom).contains(&row) {
+            return Line::PLAIN;
+        }
+        let spread = 2 * self.amplitude + 1;
+        let shift = (hash(row, frame / self.hold.max(1)) % spread) as i32 - self.amplitude as i32;
+        Line {
+            shift,
+            tint: None,
+            start: self.start,
+            len: self.len,
+        }
+    }
+}
+
+// Copy scanline `src` into `dst` as `line` says, leaving the tint to the
+// caller. Outside the span pixels go across as they are; inside they move
+// by the shift, and the gap it opens at one end repeats the span's end
+// pixel there.
+pub fn shift_into<T: Copy>(src: &[T], dst: &mut [T], line: &Line) {
+    let width = src.len().min(dst.len());
+    dst[..width].copy_from_slice(&src[..width]);
+    let start = (line.start as usize).min(width);
+    let end = (start + line.len as usize).min(width);
+    if end == start || line.shift == 0 {
+        return;
+    }
+    let last = (end - start) as i32 - 1;
+    for (i, out) in dst[start..end].iter_mut().enumerate() {
+        let from = (i as i32 - line.shift).clamp(0, last) as usize;
+        *out = src[start + from];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_has_the_right_shape() {
+        assert_eq!(sine(0), 0);
+        assert_eq!(sine(16), SINE_ONE);
+        assert_eq!(sine(32), 0);
+        assert_eq!(sine(48), -SINE_ONE);
+        assert_eq!(sine(8), -sine(40));
+        assert_eq!(sine(64 + 5), sine(5));
+    }
+
+    #[test]
+    fn wave_stays_within_its_amplitude_and_rolls() {
+        let wave = Wave::underwater(240);
+        for row in 0..320 {
+            let line = wave.line(row, 7);
+            assert!(line.shift.abs() <= wave.amplitude);
+            assert_eq!(line.len, 240);
+            assert!(!line.is_plain());
+        }
+        let crest = wave.wavelength / 4;
+        assert_eq!(wave.line(crest, 0).shift, wave.amplitude);
+        assert_ne!(wave.line(crest, wave.period / 4).shift, wave.amplitude);
+        assert_eq!(wave.line(crest, wave.period).shift, wave.amplitude);
+    }
+
+    #[test]
+    fn shimmer_keeps_to_its_band() {
+        let shimmer = Shimmer::heat(280, 300, 0, 240);
+        assert_eq!(shimmer.line(279, 3), Line::PLAIN);
+        assert_eq!(shimmer.line(300, 3), Line::PLAIN);
+        let shifts: std::vec::Vec<i32> = (280..300).map(|row| shimmer.line(row, 3).shift).collect();
+        assert!(shifts.iter().all(|shift| shift.abs() <= 1));
+        assert!(shifts.iter().any(|shift| *shift != 0));
+        // Held for `hold` frames, then changes
+        assert_eq!(shimmer.line(290, 4), shimmer.line(290, 5));
+        let later: std::vec::Vec<i32> = (280..300).map(|row| shimmer.line(row, 6).shift).collect();
+        assert_ne!(shifts, later);
+    }
+
+    #[test]
+    fn shift_repeats_the_span_ends() {
+        let src = [1, 2, 3, 4, 5, 6];
+        let mut dst = [0; 6];
+        let right = Line {
+            shift: 2,
+            tint: None,
+            start: 1,
+            len: 4,
+        };
+        shift_into(&src, &mut dst, &right);
+        assert_eq!(dst, [1, 2, 2, 2, 3, 6]);
+        let left = Line { shift: -1, ..right };
+        shift_into(&src, &mut dst, &left);
+        assert_eq!(dst, [1, 3, 4, 5, 5, 6]);
+        shift_into(&src, &mut dst, &Line::PLAIN);
+        assert_eq!(dst, src);
+    }
+
+    #[test]
+    fn span_is_clipped_to_the_line() {
+        let src = [1, 2, 3];
+        let mut dst = [0; 3];
+        let line = Line {
+            shift: 1,
+            tint: None,
+            start: 1,
+            len: 10,
+        };
+        shift_into(&src, &mut dst, &line);
+        assert_eq!(dst, [1, 2, 2]);
+    }
+}
@@ -0,0 +1,310 @@
+//! Screen rectangles
+//!
+//! A `Rect` is a position in game coordinates plus a size. The position may
+//! be off-screen or negative; edges are worked out in i64 so no combination
+//! of position and size overflows. Drawing clips a `Rect` to the screen
+//! instead of rejecting it, and collision tests overlap between `Rect`s.
+//!
+//! A position or size beyond `DRAW_LIMIT` is not something scrolling off
+//! the edge but arithmetic gone wrong (a wrapped subtraction, say). The
+//! draw calls check for it with `draw_area`: development builds reject it,
+//! `production` builds only clip.
+
+use crate::config::{Coord, LCD_HEIGHT, LCD_WIDTH};
+
+// Furthest from the origin a draw call's position or size may reach
+pub const DRAW_LIMIT: u32 = u16::MAX as u32;
+
+/// Which part of a draw call's area is beyond `DRAW_LIMIT`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AreaError {
+    X,
+    Y,
+    Width,
+    Height,
+}
+
+impl AreaError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AreaError::X => "X co-ordinate is out of range",
+            AreaError::Y => "y co-ordinate is out of range",
+            AreaError::Width => "width out of range",
+            AreaError::Height => "height out of range",
+        }
+    }
+}
+
+/// How draw calls treat an area beyond `DRAW_LIMIT`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum AreaCheck {
+    // Reject it; development builds panic on the error
+    Strict,
+    // Clip it like any other; `production` builds
+    Clip,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: Coord,
+    pub y: Coord,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    // The whole game screen
+    pub const SCREEN: Rect = Rect::new(0, 0, LCD_WIDTH, LCD_HEIGHT);
+
+    pub const fn new(x: Coord, y: Coord, w: u32, h: u32) -> Self {
+        Rect { x, y, w, h }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    // One past the last column and row
+    const fn right(&self) -> i64 {
+        self.x as i64 + self.w as i64
+    }
+
+    const fn bottom(&self) -> i64 {
+        self.y as i64 + self.h as i64
+    }
+
+    pub const fn contains(&self, x: Coord, y: Coord) -> bool {
+        self.x <= x && (x as i64) < self.right() && self.y <= y && (y as i64) < self.bottom()
+    }
+
+    // The part both rectangles cover, None if they do not overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = self.right().min(other.right());
+        let y1 = self.bottom().min(other.bottom());
+        if x0 as i64 >= x1 || y0 as i64 >= y1 {
+            return None;
+        }
+        Some(Rect::new(
+            x0,
+            y0,
+            (x1 - x0 as i64) as u32,
+            (y1 - y0 as i64) as u32,
+        ))
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    // The part inside a w x h area at the origin
+    pub fn clamp_to(&self, w: u32, h: u32) -> Option<Rect> {
+        self.intersection(&Rect::new(0, 0, w, h))
+    }
+
+    // The part on the game screen, None when it is all off-screen
+    pub fn clip_to_screen(&self) -> Option<Rect> {
+        self.intersection(&Rect::SCREEN)
+    }
+
+    // What a draw call covering this rectangle draws: the part on the
+    // screen, None when it is all off-screen. `Strict` rejects a position
+    // or size beyond DRAW_LIMIT first.
+    pub fn draw_area(&self, check: AreaCheck) -> Result<Option<Rect>, AreaError> {
+        if check == AreaCheck::Strict {
+            if self.x.unsigned_abs() > DRAW_LIMIT {
+                return Err(AreaError::X);
+            }
+            if self.y.unsigned_abs() > DRAW_LIMIT {
+                return Err(AreaError::Y);
+            }
+            if self.w > DRAW_LIMIT {
+                return Err(AreaError::Width);
+            }
+            if self.h > DRAW_LIMIT {
+                return Err(AreaError::Height);
+            }
+        }
+        Ok(self.clip_to_screen())
+    }
+
+    // Same size, moved by (dx, dy)
+    pub const fn offset(&self, dx: Coord, dy: Coord) -> Rect {
+        Rect::new(
+            self.x.saturating_add(dx),
+            self.y.saturating_add(dy),
+            self.w,
+            self.h,
+        )
+    }
+
+    // The one-pixel edges along the top, bottom, left and right, for
+    // drawing it as an outline; the side edges leave out the corners
+    pub fn outline(&self) -> [Rect; 4] {
+        let bottom = self.y + self.h.saturating_sub(1) as Coord;
+        let right = self.x + self.w.saturating_sub(1) as Coord;
+        let side = self.h.saturating_sub(2);
+        [
+            Rect::new(self.x, self.y, self.w, 1.min(self.h)),
+            Rect::new(self.x, bottom, self.w, (self.h > 1) as u32),
+            Rect::new(self.x, self.y + 1, 1.min(self.w), side),
+            Rect::new(right, self.y + 1, (self.w > 1) as u32, side),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection_of_overlapping() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, -5, 10, 10);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 0, 5, 5)));
+        assert!(a.intersects(&b));
+    }
+
+    #[test]
+    fn shared_edge_is_not_an_overlap() {
+        let a = Rect::new(0, 0, 10, 10);
+        assert_eq!(a.intersection(&Rect::new(10, 0, 5, 5)), None);
+        assert!(!a.intersects(&Rect::new(0, 10, 5, 5)));
+    }
+
+    #[test]
+    fn clipping_negative_and_huge() {
+        let r = Rect::new(-20, 300, u32::MAX, u32::MAX);
+        assert_eq!(
+            r.clip_to_screen(),
+            Some(Rect::new(0, 300, LCD_WIDTH, LCD_HEIGHT - 300))
+        );
+        assert_eq!(Rect::new(-5, 0, 5, 5).clip_to_screen(), None);
+        assert_eq!(Rect::new(0, 0, 0, 5).clip_to_screen(), None);
+    }
+
+    #[test]
+    fn contains_is_half_open() {
+        let r = Rect::new(2, 2, 3, 3);
+        assert!(r.contains(2, 4));
+        assert!(!r.contains(5, 2));
+        assert!(!Rect::new(Coord::MAX, 0, 10, 1).contains(Coord::MIN, 0));
+    }
+
+    #[test]
+    fn outline_covers_the_border_once() {
+        let r = Rect::new(10, 20, 5, 4);
+        let edges = r.outline();
+        let area: u32 = edges.iter().map(|e| e.w * e.h).sum();
+        assert_eq!(area, 2 * 5 + 2 * 2);
+        for (i, a) in edges.iter().enumerate() {
+            assert!(edges[i + 1..].iter().all(|b| !a.intersects(b)));
+        }
+        assert_eq!(edges[3], Rect::new(14, 21, 1, 2));
+        let dot: u32 = Rect::new(0, 0, 1, 1)
+            .outline()
+            .iter()
+            .map(|e| e.w * e.h)
+            .sum();
+        assert_eq!(dot, 1);
+    }
+
+    // Each public draw call checks the area it draws the same way; a
+    // development build rejects the wrapped values, a production build
+    // clips them to what is on screen
+    fn both(rect: Rect) -> (Result<Option<Rect>, AreaError>, Option<Rect>) {
+        let clipped = rect.draw_area(AreaCheck::Clip).expect("clip never rejects");
+        (rect.draw_area(AreaCheck::Strict), clipped)
+    }
+
+    #[test]
+    fn draw_image_out_of_range() {
+        let (strict, clipped) = both(Rect::new(10, 20, u32::MAX, 8));
+        assert_eq!(strict, Err(AreaError::Width));
+        assert_eq!(clipped, Some(Rect::new(10, 20, LCD_WIDTH - 10, 8)));
+    }
+
+    #[test]
+    fn draw_tiled_out_of_range() {
+        let (strict, clipped) = both(Rect::new(-70_000, 0, 32, 32));
+        assert_eq!(strict, Err(AreaError::X));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn draw_rect_angle_out_of_range() {
+        let (strict, clipped) = both(Rect::new(0, 300, 5, 0u32.wrapping_sub(1)));
+        assert_eq!(strict, Err(AreaError::Height));
+        assert_eq!(clipped, Some(Rect::new(0, 300, 5, LCD_HEIGHT - 300)));
+    }
+
+    #[test]
+    fn fill_rows_out_of_range() {
+        let (strict, clipped) = both(Rect::new(0, Coord::MAX, LCD_WIDTH, 4));
+        assert_eq!(strict, Err(AreaError::Y));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn set_background_color_is_never_out_of_range() {
+        assert_eq!(
+            both(Rect::SCREEN),
+            (Ok(Some(Rect::SCREEN)), Some(Rect::SCREEN))
+        );
+    }
+
+    #[test]
+    fn write_string_out_of_range() {
+        // The first glyph cell, 16 x 26 at the string's position
+        let (strict, clipped) = both(Rect::new(Coord::MIN, 40, 16, 26));
+        assert_eq!(strict, Err(AreaError::X));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn draw_glyph_out_of_range() {
+        let (strict, clipped) = both(Rect::new(100, -100_000, 16, 26));
+        assert_eq!(strict, Err(AreaError::Y));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn draw_pixel_off_screen_is_in_range() {
+        // Its u16 position cannot pass DRAW_LIMIT, only miss the screen
+        let (strict, clipped) = both(Rect::new(u16::MAX as Coord, u16::MAX as Coord, 1, 1));
+        assert_eq!(strict, Ok(None));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn draw_sprite_out_of_range() {
+        // A 40 000 pixel wide image drawn at double size
+        let w = crate::render::Scale::DOUBLE.of(40_000);
+        let (strict, clipped) = both(Rect::new(-8, 8, w, 16));
+        assert_eq!(strict, Err(AreaError::Width));
+        assert_eq!(clipped, Some(Rect::new(0, 8, LCD_WIDTH, 16)));
+    }
+
+    #[test]
+    fn blend_rect_out_of_range() {
+        let (strict, clipped) = both(Rect::new(0, 0, 70_000, 70_000));
+        assert_eq!(strict, Err(AreaError::Width));
+        assert_eq!(clipped, Some(Rect::SCREEN));
+    }
+
+    #[test]
+    fn blit_argb_out_of_range() {
+        // The rows of pixels below a wrapped-around y
+        let (strict, clipped) = both(Rect::new(0, -(DRAW_LIMIT as Coord) - 1, 4, 2));
+        assert_eq!(strict, Err(AreaError::Y));
+        assert_eq!(clipped, None);
+    }
+
+    #[test]
+    fn limit_itself_is_in_range() {
+        let limit = DRAW_LIMIT as Coord;
+        let rect = Rect::new(-limit, limit, DRAW_LIMIT, DRAW_LIMIT);
+        assert_eq!(rect.draw_area(AreaCheck::Strict), Ok(None));
+    }
+}
@@ -0,0 +1,460 @@
+//! Drawing surface the game renders to
+//!
+//! On the board `RenderBackend` is the LTDC framebuffer in SDRAM; on a PC
+//! (feature `sim`) it is an in-memory picture dumped to image files, so the
+//! screen layout can be iterated on without flashing. Colors are RGB565
+//! and coordinates are game coordinates; a backend clips at its edges.
+//! Images go on mirrored and scaled as their `ImageTransform` says, the
+//! scaling nearest-neighbor in half steps.
+
+use crate::config::Coord;
+use crate::rect::Rect;
+
+/// RGB565 image data with its dimensions
+#[derive(Copy, Clone)]
+pub struct Image<'a> {
+    pub w: u32,
+    pub h: u32,
+    pub data: &'a [u16],
+    // Which half of the image `data` leaves out
+    pub mirror: Mirror,
+}
+
+impl<'a> Image<'a> {
+    pub const fn new(w: u32, h: u32, data: &'a [u16]) -> Self {
+        Self {
+            w,
+            h,
+            data,
+            mirror: Mirror::NONE,
+        }
+    }
+
+    // A `w` x `h` image that is its own mirror image as `mirror` says, of
+    // which `data` holds only the first half (see `Mirror::stored`)
+    pub const fn mirrored(w: u32, h: u32, mirror: Mirror, data: &'a [u16]) -> Self {
+        Self { w, h, data, mirror }
+    }
+
+    // Pixel at (col, row) of the image as drawn, after `transform`
+    pub fn pixel(&self, col: u32, row: u32, transform: ImageTransform) -> Option<u16> {
+        let (col, row) = (transform.scale.source(col), transform.scale.source(row));
+        let img_row = if transform.flip_y {
+            self.h.checked_sub(1 + row)?
+        } else {
+            row
+        };
+        let img_col = if transform.flip_x {
+            self.w.checked_sub(1 + col)?
+        } else {
+            col
+        };
+        self.at(img_col, img_row)
+    }
+
+    // Pixel at (col, row) of the image as stored, with the half that is
+    // not stored read back from the half that is
+    pub fn at(&self, col: u32, row: u32) -> Option<u16> {
+        let (stride, _) = self.mirror.stored(self.w, self.h);
+        let col = if self.mirror.across && col < self.w {
+            col.min(self.w - 1 - col)
+        } else {
+            col
+        };
+        let row = if self.mirror.down && row < self.h {
+            row.min(self.h - 1 - row)
+        } else {
+            row
+        };
+        self.data.get((row * stride + col) as usize).copied()
+    }
+}
+
+/// Art that is its own mirror image needs only half of it in flash: the
+/// left half when it is the same flipped across, the first half of the
+/// rows when it is the same flipped down, a quarter when both. The other
+/// half is the stored one read back flipped, so it costs a fold of the
+/// coordinates (see `Image::at`), and nothing once the sprite cache has
+/// the whole image converted.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Mirror {
+    pub across: bool,
+    pub down: bool,
+}
+
+impl Mirror {
+    pub const NONE: Mirror = Mirror {
+        across: false,
+        down: false,
+    };
+    pub const ACROSS: Mirror = Mirror {
+        across: true,
+        down: false,
+    };
+    pub const DOWN: Mirror = Mirror {
+        across: false,
+        down: true,
+    };
+    pub const BOTH: Mirror = Mirror {
+        across: true,
+        down: true,
+    };
+
+    // Columns and rows kept of a `w` x `h` image; a middle column or row
+    // is kept with the first half
+    pub const fn stored(self, w: u32, h: u32) -> (u32, u32) {
+        (
+            if self.across { w.div_ceil(2) } else { w },
+            if self.down { h.div_ceil(2) } else { h },
+        )
+    }
+
+    // Pixels kept of a `w` x `h` image, for sizing its table
+    pub const fn pixels(self, w: u32, h: u32) -> usize {
+        let (w, h) = self.stored(w, h);
+        (w * h) as usize
+    }
+
+    // `transform` with any flip this art does not show dropped, so every
+    // way of drawing it that looks the same is one
+    pub fn canonical(self, transform: ImageTransform) -> ImageTransform {
+        ImageTransform {
+            flip_x: transform.flip_x && !self.across,
+            flip_y: transform.flip_y && !self.down,
+            ..transform
+        }
+    }
+}
+
+/// Nearest-neighbor size an image is drawn at, in half steps: `HALF` takes
+/// every other pixel of every other row, `DOUBLE` draws each pixel as two
+/// by two
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Scale {
+    halves: u8,
+}
+
+impl Scale {
+    pub const HALF: Scale = Scale { halves: 1 };
+    pub const ONE: Scale = Scale { halves: 2 };
+    pub const DOUBLE: Scale = Scale { halves: 4 };
+    // Largest scale, 8x
+    pub const MAX_HALVES: u8 = 16;
+
+    // `halves` half steps, 1 (half size) to MAX_HALVES
+    pub const fn halves(halves: u8) -> Option<Scale> {
+        if halves == 0 || halves > Self::MAX_HALVES {
+            return None;
+        }
+        Some(Scale { halves })
+    }
+
+    // `len` pixels of image, drawn
+    pub const fn of(self, len: u32) -> u32 {
+        len * self.halves as u32 / 2
+    }
+
+    // Image pixel under drawn pixel `drawn`
+    pub const fn source(self, drawn: u32) -> u32 {
+        drawn * 2 / self.halves as u32
+    }
+
+    // The factor, if it is a whole one
+    pub const fn whole(self) -> Option<u32> {
+        if self.halves.is_multiple_of(2) {
+            Some(self.halves as u32 / 2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Mirroring and scaling applied while copying an image into the
+/// framebuffer
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ImageTransform {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub scale: Scale,
+}
+
+impl ImageTransform {
+    pub const NONE: ImageTransform = ImageTransform {
+        flip_x: false,
+        flip_y: false,
+        scale: Scale::ONE,
+    };
+    // Asset images are stored bottom row first
+    pub const FLIP_Y: ImageTransform = ImageTransform {
+        flip_x: false,
+        flip_y: true,
+        scale: Scale::ONE,
+    };
+
+    // The same, drawn at `scale`
+    pub const fn scaled(self, scale: Scale) -> ImageTransform {
+        ImageTransform { scale, ..self }
+    }
+
+    // Size of `image` as drawn
+    pub const fn size(self, image: &Image) -> (u32, u32) {
+        (self.scale.of(image.w), self.scale.of(image.h))
+    }
+}
+
+pub trait RenderBackend {
+    // Drawing area in game coordinates
+    fn size(&self) -> (u32, u32);
+
+    // One pixel; anything off the surface is dropped
+    fn set_pixel(&mut self, x: Coord, y: Coord, rgb565: u16);
+
+    fn fill_rect(&mut self, x: Coord, y: Coord, w: u32, h: u32, rgb565: u16) {
+        for row in 0..h {
+            for col in 0..w {
+                self.set_pixel(x + col as Coord, y + row as Coord, rgb565);
+            }
+        }
+    }
+
+    // Copy `image` with its top left at (x, y); pixels equal to `key` are
+    // left alone
+    fn blit(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+    ) {
+        let (w, h) = transform.size(image);
+        for row in 0..h {
+            for col in 0..w {
+                match image.pixel(col, row, transform) {
+                    Some(rgb565) if key != Some(rgb565) => {
+                        self.set_pixel(x + col as Coord, y + row as Coord, rgb565)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // As `blit`, only the part `region` of the image as drawn (relative to
+    // its top left, after `transform`), which the caller has already
+    // clipped; the image's top left still goes at (x, y). See `sprite_batch`.
+    fn blit_region(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        region: Rect,
+    ) {
+        for row in region.y as u32..region.y as u32 + region.h {
+            for col in region.x as u32..region.x as u32 + region.w {
+                match image.pixel(col, row, transform) {
+                    Some(rgb565) if key != Some(rgb565) => {
+                        self.set_pixel(x + col as Coord, y + row as Coord, rgb565)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Cover a rectangle by repeating `tile` (stored bottom row first) from
+    // its top-left corner
+    fn fill_tiled(&mut self, x: Coord, y: Coord, w: u32, h: u32, tile: &Image) {
+        self.fill_tiled_from(x, y, w, h, tile, (0, 0));
+    }
+
+    // As `fill_tiled`, starting `skip` (columns, rows) into the tile, so
+    // fills side by side or one above another can carry on one pattern
+    fn fill_tiled_from(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        w: u32,
+        h: u32,
+        tile: &Image,
+        skip: (u32, u32),
+    ) {
+        if tile.w == 0 || tile.h == 0 {
+            return;
+        }
+        for row in 0..h {
+            let tile_row = (row + skip.1) % tile.h;
+            for col in 0..w {
+                let tile_col = (col + skip.0) % tile.w;
+                if let Some(rgb565) = tile.pixel(tile_col, tile_row, ImageTransform::FLIP_Y) {
+                    self.set_pixel(x + col as Coord, y + row as Coord, rgb565);
+                }
+            }
+        }
+    }
+
+    // Fill a rectangle a row at a time with the color `row_color` gives for
+    // each screen row, for vertical gradients. The color is worked out once
+    // per row, not per pixel.
+    fn fill_rows(&mut self, x: Coord, y: Coord, w: u32, h: u32, row_color: &dyn Fn(Coord) -> u16) {
+        for row in 0..h {
+            let y = y + row as Coord;
+            self.fill_rect(x, y, w, 1, row_color(y));
+        }
+    }
+
+    // Make everything drawn so far visible
+    fn present(&mut self);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x3 surface recording pixels, for checking the provided methods
+    struct Grid([[u16; 4]; 3]);
+
+    impl RenderBackend for Grid {
+        fn size(&self) -> (u32, u32) {
+            (4, 3)
+        }
+
+        fn set_pixel(&mut self, x: Coord, y: Coord, rgb565: u16) {
+            if (0..4).contains(&x) && (0..3).contains(&y) {
+                self.0[y as usize][x as usize] = rgb565;
+            }
+        }
+
+        fn present(&mut self) {}
+    }
+
+    #[test]
+    fn fill_rect_clips() {
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.fill_rect(2, -1, 5, 2, 7);
+        assert_eq!(grid.0, [[0, 0, 7, 7], [0; 4], [0; 4]]);
+    }
+
+    #[test]
+    fn blit_flips_and_skips_the_key() {
+        let data = [1, 2, 3, 4];
+        let image = Image::new(2, 2, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.blit(1, 1, &image, ImageTransform::FLIP_Y, Some(4));
+        assert_eq!(grid.0, [[0; 4], [0, 3, 0, 0], [0, 1, 2, 0]]);
+    }
+
+    #[test]
+    fn blit_scales_by_half_steps() {
+        let data = [1, 2, 3, 4];
+        let image = Image::new(2, 2, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        let double = ImageTransform::FLIP_Y.scaled(Scale::DOUBLE);
+        assert_eq!(double.size(&image), (4, 4));
+        grid.blit(0, 0, &image, double, None);
+        assert_eq!(grid.0, [[3, 3, 4, 4], [3, 3, 4, 4], [1, 1, 2, 2]]);
+
+        let data = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let image = Image::new(3, 3, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        let half = ImageTransform::NONE.scaled(Scale::HALF);
+        assert_eq!(half.size(&image), (1, 1));
+        grid.blit(1, 1, &image, half, None);
+        assert_eq!(grid.0, [[0; 4], [0, 1, 0, 0], [0; 4]]);
+
+        let one_and_a_half = Scale::halves(3).unwrap();
+        assert_eq!((one_and_a_half.of(2), one_and_a_half.whole()), (3, None));
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.blit(
+            0,
+            0,
+            &Image::new(2, 1, &[1, 2]),
+            ImageTransform::NONE.scaled(one_and_a_half),
+            None,
+        );
+        assert_eq!(grid.0[0], [1, 1, 2, 0]);
+        assert_eq!(Scale::halves(0), None);
+        assert_eq!(Scale::DOUBLE.whole(), Some(2));
+    }
+
+    #[test]
+    fn blit_region_draws_only_the_region() {
+        let data = [1, 2, 3, 4, 5, 6];
+        let image = Image::new(3, 2, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        let region = Rect::new(1, 0, 2, 1);
+        grid.blit_region(0, 1, &image, ImageTransform::FLIP_Y, None, region);
+        assert_eq!(grid.0, [[0; 4], [0, 5, 6, 0], [0; 4]]);
+    }
+
+    #[test]
+    fn fill_tiled_repeats_bottom_row_first() {
+        let data = [1, 2];
+        let tile = Image::new(1, 2, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.fill_tiled(0, 0, 2, 3, &tile);
+        assert_eq!(grid.0, [[2, 2, 0, 0], [1, 1, 0, 0], [2, 2, 0, 0]]);
+    }
+
+    #[test]
+    fn fill_tiled_from_carries_on_the_strip() {
+        let data = [1, 2, 3];
+        let tile = Image::new(3, 1, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.fill_tiled_from(0, 0, 4, 1, &tile, (2, 0));
+        assert_eq!(grid.0[0], [3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn fill_tiled_from_carries_on_down() {
+        let data = [1, 2, 3];
+        let tile = Image::new(1, 3, &data);
+        let mut grid = Grid([[0; 4]; 3]);
+        // Stored bottom row first, so the top row of the tile is 3
+        grid.fill_tiled_from(0, 0, 1, 3, &tile, (0, 1));
+        grid.fill_tiled_from(1, 0, 1, 3, &tile, (1, 5));
+        assert_eq!(grid.0, [[2, 1, 0, 0], [1, 3, 0, 0], [3, 2, 0, 0]]);
+    }
+
+    #[test]
+    fn fill_rows_colors_by_screen_row() {
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.fill_rows(1, 1, 2, 5, &|y| y as u16 * 10);
+        assert_eq!(grid.0, [[0; 4], [0, 10, 10, 0], [0, 20, 20, 0]]);
+    }
+
+    #[test]
+    fn image_pixel_out_of_range() {
+        let data = [1, 2, 3, 4];
+        let image = Image::new(2, 2, &data);
+        assert_eq!(image.pixel(1, 0, ImageTransform::NONE), Some(2));
+        assert_eq!(image.pixel(0, 0, ImageTransform::FLIP_Y), Some(3));
+        assert_eq!(image.pixel(2, 0, ImageTransform::FLIP_Y), None);
+        assert_eq!(image.pixel(0, 2, ImageTransform::FLIP_Y), None);
+    }
+
+    #[test]
+    fn mirrored_images_read_back_the_stored_half() {
+        // 3x3, the left two columns and top two rows kept
+        let data = [1, 2, 3, 4];
+        let image = Image::mirrored(3, 3, Mirror::BOTH, &data);
+        assert_eq!(Mirror::BOTH.pixels(3, 3), data.len());
+        let mut grid = Grid([[0; 4]; 3]);
+        grid.blit(0, 0, &image, ImageTransform::NONE, None);
+        assert_eq!(grid.0, [[1, 2, 1, 0], [3, 4, 3, 0], [1, 2, 1, 0]]);
+
+        // Flipped across, an image the same both ways is one key
+        let across = Mirror::ACROSS.canonical(ImageTransform {
+            flip_x: true,
+            flip_y: true,
+            scale: Scale::ONE,
+        });
+        assert_eq!(across, ImageTransform::FLIP_Y);
+        assert_eq!(
+            Mirror::NONE.canonical(ImageTransform::FLIP_Y),
+            ImageTransform::FLIP_Y
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Replay of a run, for a host to play back
+//!
+//! A replay is what the game's rules need to play a single-player run over
+//! again: the random seed its obstacles and pickups were made from, its
+//! mode, control scheme, tuning and starting speed, then for every frame it
+//! was running the input the controls were given and how many ticks the
+//! frame was allowed. Fed the same frames, the core's rules (bird, controls,
+//! obstacles, pickups, scoring) come out the same; the frames carry the
+//! ticks the fixed step handed out, so hit-stops and slowed time are in
+//! them already.
+//!
+//! Layout, little-endian:
+//!
+//! ```text
+//! magic u32 "RPL1" | mode u8 | scheme u8 | flags u8 | 0 u8 | seed u32 |
+//! tuning u32 | velocity u32 | frames u32 | score u32 | frame...
+//! ```
+//!
+//! `mode` is a `ModeId` and `scheme` a `ControlScheme`, both in declaration
+//! order; `tuning` is `TuningParams::to_u32`; `velocity` the obstacle speed
+//! the run started at, in 1/SUBPIXELS of a pixel a tick. `flags` are
+//! `PRACTICE`, `COURSE` (the custom course is not in the replay) and
+//! `TRUNCATED` (the recording ran out of room before the run ended).
+//!
+//! A frame is one byte: the ticks in bits 0-2, a tap in bit 3 and in the
+//! top nibble how far the input row moved since the last frame plus
+//! `DELTA_MAX`. A move further than that is `ESCAPE` in the top nibble,
+//! the new row following as an i16.
+
+use crate::config::Coord;
+use crate::controls::ControlScheme;
+use crate::mode::ModeId;
+use crate::tuning::TuningParams;
+
+pub const MAGIC: u32 = 0x314C_5052; // "RPL1"
+pub const HEADER_LEN: usize = 28;
+// Longest frame, in bytes
+pub const MAX_FRAME_LEN: usize = 3;
+
+pub const PRACTICE: u8 = 1 << 0;
+pub const COURSE: u8 = 1 << 1;
+pub const TRUNCATED: u8 = 1 << 2;
+
+const TICKS_MASK: u8 = 0x07;
+const TAP: u8 = 0x08;
+const DELTA_MAX: Coord = 7;
+const ESCAPE: u8 = 0xF;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ReplayError {
+    Short,
+    Magic,
+    Mode,
+    Scheme,
+    Tuning,
+    // A frame cut off part way
+    Frame,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Header {
+    pub mode: ModeId,
+    pub scheme: ControlScheme,
+    pub flags: u8,
+    pub seed: u32,
+    pub tuning: TuningParams,
+    pub velocity: u32,
+    pub frames: u32,
+    pub score: u32,
+}
+
+impl Header {
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut out = [0; HEADER_LEN];
+        out[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        out[4] = self.mode as u8;
+        out[5] = self.scheme as u8;
+        out[6] = self.flags;
+        let words = [
+            self.seed,
+            self.tuning.to_u32(),
+            self.velocity,
+            self.frames,
+            self.score,
+        ];
+        for (i, word) in words.iter().enumerate() {
+            out[8 + 4 * i..12 + 4 * i].copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Header, ReplayError> {
+        let bytes = bytes.get(..HEADER_LEN).ok_or(ReplayError::Short)?;
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        if word(0) != MAGIC {
+            return Err(ReplayError::Magic);
+        }
+        Ok(Header {
+            mode: ModeId::from_u32(bytes[4] as u32).ok_or(ReplayError::Mode)?,
+            scheme: ControlScheme::from_u32(bytes[5] as u32).ok_or(ReplayError::Scheme)?,
+            flags: bytes[6],
+            seed: word(8),
+            tuning: TuningParams::from_u32(word(12)).ok_or(ReplayError::Tuning)?,
+            velocity: word(16),
+            frames: word(20),
+            score: word(24),
+        })
+    }
+}
+
+/// One running frame's input
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Frame {
+    // The row the input sent the bird to, and whether it tapped
+    pub y: Coord,
+    pub tap: bool,
+    // Ticks the fixed step gave the frame, 0..=7
+    pub ticks: u8,
+}
+
+/// Turns frames into bytes, each against the one before
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Encoder {
+    last_y: Coord,
+}
+
+impl Encoder {
+    pub const fn new() -> Self {
+        Encoder { last_y: 0 }
+    }
+
+    // `frame` into `out`; the bytes it took
+    pub fn encode(&mut self, frame: Frame, out: &mut [u8; MAX_FRAME_LEN]) -> usize {
+        let low = (frame.ticks & TICKS_MASK) | if frame.tap { TAP } else { 0 };
+        let y = frame.y.clamp(i16::MIN as Coord, i16::MAX as Coord);
+        let delta = y - self.last_y;
+        self.last_y = y;
+        if (-DELTA_MAX..=DELTA_MAX).contains(&delta) {
+            out[0] = low | ((delta + DELTA_MAX) as u8) << 4;
+            return 1;
+        }
+        out[0] = low | ESCAPE << 4;
+        out[1..3].copy_from_slice(&(y as i16).to_le_bytes());
+        3
+    }
+}
+
+/// The frames after a header, in order
+pub struct Frames<'a> {
+    bytes: &'a [u8],
+    last_y: Coord,
+}
+
+impl<'a> Frames<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Frames { bytes, last_y: 0 }
+    }
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<Frame, ReplayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&first, rest) = self.bytes.split_first()?;
+        let y = match first >> 4 {
+            ESCAPE => {
+                let Some(row) = rest.get(..2) else {
+                    self.bytes = &[];
+                    return Some(Err(ReplayError::Frame));
+                };
+                self.bytes = &rest[2..];
+                i16::from_le_bytes([row[0], row[1]]) as Coord
+            }
+            nibble => {
+                self.bytes = rest;
+                self.last_y + nibble as Coord - DELTA_MAX
+            }
+        };
+        self.last_y = y;
+        Some(Ok(Frame {
+            y,
+            tap: first & TAP != 0,
+            ticks: first & TICKS_MASK,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    fn header() -> Header {
+        Header {
+            mode: ModeId::TimeAttack,
+            scheme: ControlScheme::HybridAssist,
+            flags: PRACTICE | TRUNCATED,
+            seed: 0xDEAD_BEEF,
+            tuning: TuningParams::DEFAULT,
+            velocity: 384,
+            frames: 3600,
+            score: 42,
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let bytes = header().to_bytes();
+        assert_eq!(&bytes[..4], b"RPL1");
+        assert_eq!(Header::parse(&bytes), Ok(header()));
+        assert_eq!(
+            Header::parse(&bytes[..HEADER_LEN - 1]),
+            Err(ReplayError::Short)
+        );
+        let mut bad = bytes;
+        bad[4] = 99;
+        assert_eq!(Header::parse(&bad), Err(ReplayError::Mode));
+        bad[0] ^= 1;
+        assert_eq!(Header::parse(&bad), Err(ReplayError::Magic));
+    }
+
+    #[test]
+    fn frames_round_trip_small_and_large_moves() {
+        let frames = [
+            Frame {
+                y: 0,
+                tap: false,
+                ticks: 1,
+            },
+            Frame {
+                y: 7,
+                tap: true,
+                ticks: 2,
+            },
+            Frame {
+                y: 0,
+                tap: false,
+                ticks: 0,
+            },
+            Frame {
+                y: 120,
+                tap: false,
+                ticks: 4,
+            },
+            Frame {
+                y: 113,
+                tap: true,
+                ticks: 1,
+            },
+            Frame {
+                y: -40,
+                tap: false,
+                ticks: 7,
+            },
+        ];
+        let mut encoder = Encoder::new();
+        let mut bytes = Vec::new();
+        let mut out = [0; MAX_FRAME_LEN];
+        for frame in frames {
+            let len = encoder.encode(frame, &mut out);
+            bytes.extend_from_slice(&out[..len]);
+        }
+        // Three small moves, two large ones and a small one back
+        assert_eq!(bytes.len(), 1 + 1 + 1 + 3 + 1 + 3);
+        let decoded: Vec<Frame> = Frames::new(&bytes).map(Result::unwrap).collect();
+        assert_eq!(decoded, frames);
+    }
+
+    #[test]
+    fn a_frame_cut_short_is_an_error() {
+        let mut encoder = Encoder::new();
+        let mut out = [0; MAX_FRAME_LEN];
+        let len = encoder.encode(
+            Frame {
+                y: 200,
+                tap: false,
+                ticks: 1,
+            },
+            &mut out,
+        );
+        assert_eq!(len, 3);
+        let mut frames = Frames::new(&out[..2]);
+        assert_eq!(frames.next(), Some(Err(ReplayError::Frame)));
+        assert_eq!(frames.next(), None);
+    }
+}
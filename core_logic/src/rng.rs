@@ -0,0 +1,49 @@
+//! Small pseudo-random numbers for gameplay
+//!
+//! xorshift32: fast, tiny and deterministic, so a seed replays the same
+//! obstacles and pickups. Not for anything that has to be unpredictable.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rng(u32);
+
+impl Rng {
+    pub const fn new(seed: u32) -> Self {
+        // xorshift never leaves zero
+        Rng(if seed == 0 { 1 } else { seed })
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+
+    // 0..n, n > 0; the slight bias towards low values does not matter here
+    pub fn below(&mut self, n: u32) -> u32 {
+        self.next_u32() % n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        for _ in 0..100 {
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+    }
+
+    #[test]
+    fn zero_seed_still_moves() {
+        let mut rng = Rng::new(0);
+        assert_ne!(rng.next_u32(), 0);
+        assert!((0..100).all(|_| rng.below(6) < 6));
+    }
+}
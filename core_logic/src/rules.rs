@@ -0,0 +1,321 @@
+//! Collision and scoring
+//!
+//! An obstacle passed scores a point. While the bird is alongside one the
+//! game keeps the closest it came to the pipe above or below
+//! (`track_clearance`); passed within `NEAR_MISS_PX` rows it is a close call
+//! and earns `NEAR_MISS_BONUS` more. Within `EDGE_WARN_PX` rows of the
+//! ceiling or the ground the bird is warned (`edge_warning`).
+
+use crate::bird::Bird;
+use crate::config::*;
+use crate::obstacle::ObstaclePair;
+use crate::rect::Rect;
+
+// Rows between the bird's hitbox and the pipe that make a close call
+pub const NEAR_MISS_PX: u32 = 3;
+pub const NEAR_MISS_BONUS: u32 = 1;
+// Rows from the ceiling or the ground inside which the bird is warned
+pub const EDGE_WARN_PX: u32 = 12;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Edge {
+    Ceiling,
+    Ground,
+}
+
+// Whether the bird has left `field`, the playfield between the score bar
+// (ceiling) and the ground, or hit any of the obstacle's pipe. Resting on
+// an edge row counts as a hit, so the bird's box is taken one row taller at
+// the top and bottom.
+pub fn collides(bird: &Bird, obstacle: &ObstaclePair, field: Rect) -> bool {
+    let bird = bird.hitbox();
+    let hitbox = Rect::new(bird.x, bird.y - 1, bird.w, bird.h + 2);
+    let inside =
+        hitbox.y >= field.y && hitbox.y as i64 + hitbox.h as i64 <= field.y as i64 + field.h as i64;
+
+    !inside || obstacle.rects().any(|pipe| hitbox.intersects(&pipe))
+}
+
+// True the first frame the bird is clear of the obstacle pair; marks it
+// scored so each pair counts once
+pub fn passed(bird: &Bird, obstacle: &mut ObstaclePair) -> bool {
+    let (player_x, _) = bird.xy();
+    let (x_top, _) = obstacle.get_xy_top();
+    if player_x > (x_top + OBSTACLE_WIDTH as Coord) && !obstacle.already_scored {
+        obstacle.already_scored = true;
+        return true;
+    }
+    false
+}
+
+// Rows between the bird and the nearest pipe above or below it while it is
+// alongside the obstacle; None when it is not
+pub fn clearance(bird: &Bird, obstacle: &ObstaclePair) -> Option<u32> {
+    let bird = bird.hitbox();
+    let (left, right) = (bird.x, bird.x + bird.w as Coord);
+    let bottom = bird.y + bird.h as Coord;
+    obstacle
+        .rects()
+        .filter(|pipe| pipe.x < right && left < pipe.x + pipe.w as Coord)
+        .map(|pipe| {
+            if pipe.y >= bottom {
+                (pipe.y - bottom) as u32
+            } else {
+                (bird.y - (pipe.y + pipe.h as Coord)).max(0) as u32
+            }
+        })
+        .min()
+}
+
+// Keep the obstacle's closest clearance up to date; call every tick
+pub fn track_clearance(bird: &Bird, obstacle: &mut ObstaclePair) {
+    if let Some(rows) = clearance(bird, obstacle) {
+        obstacle.closest = Some(obstacle.closest.map_or(rows, |closest| closest.min(rows)));
+    }
+}
+
+// The edge of `field` the bird's hitbox is within EDGE_WARN_PX rows of,
+// and how close as 1..=255, 255 touching it; None clear of both
+pub fn edge_warning(bird: &Bird, field: Rect) -> Option<(Edge, u8)> {
+    let bird = bird.hitbox();
+    let above = (bird.y - field.y).max(0) as u32;
+    let below = (field.y + field.h as Coord - (bird.y + bird.h as Coord)).max(0) as u32;
+    let (edge, rows) = if above <= below {
+        (Edge::Ceiling, above)
+    } else {
+        (Edge::Ground, below)
+    };
+    (rows < EDGE_WARN_PX).then(|| (edge, (255 * (EDGE_WARN_PX - rows) / EDGE_WARN_PX) as u8))
+}
+
+// Whether the bird came within NEAR_MISS_PX of the obstacle's pipe
+pub fn near_miss(obstacle: &ObstaclePair) -> bool {
+    obstacle.closest.is_some_and(|rows| rows <= NEAR_MISS_PX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lane::Lane;
+
+    const FIELD: Rect = Lane::FULL.field();
+
+    // Pair scrolled until its left edge is at `x`
+    fn pair_at(x: Coord) -> ObstaclePair {
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_speed((LCD_END - x) as u32);
+        pair.advance();
+        pair.set_speed(SPEED);
+        pair
+    }
+
+    // Bird centered in the FULL lane's opening
+    fn bird_in_gap() -> Bird {
+        let lane = Lane::FULL;
+        let center = (lane.gap_top + lane.gap_bottom) / 2 - PLAYER_HEIGHT as Coord / 2;
+        Bird::new(INIT_PLAYER_POS_X, center)
+    }
+
+    #[test]
+    fn flying_through_the_gap_is_safe() {
+        let bird = bird_in_gap();
+        for x in (0..LCD_END).step_by(2) {
+            assert!(!collides(&bird, &pair_at(x), FIELD), "x = {x}");
+        }
+    }
+
+    #[test]
+    fn hitting_the_top_half() {
+        let bird = Bird::new(INIT_PLAYER_POS_X, 100);
+        assert!(collides(&bird, &pair_at(INIT_PLAYER_POS_X), FIELD));
+    }
+
+    #[test]
+    fn hitting_the_bottom_half() {
+        let bird = Bird::new(INIT_PLAYER_POS_X, 160);
+        assert!(collides(&bird, &pair_at(INIT_PLAYER_POS_X), FIELD));
+    }
+
+    #[test]
+    fn touching_edges_count_as_a_hit() {
+        // Bottom of the top half is row 130; the bird's top on it hits
+        let bird = Bird::new(INIT_PLAYER_POS_X, 130);
+        assert!(collides(&bird, &pair_at(INIT_PLAYER_POS_X), FIELD));
+        // Bird's bottom on row 180, the top of the bottom half
+        let bird = Bird::new(INIT_PLAYER_POS_X, 150);
+        assert!(collides(&bird, &pair_at(INIT_PLAYER_POS_X), FIELD));
+    }
+
+    #[test]
+    fn out_of_line_with_the_pair_is_safe_at_any_height() {
+        let x = INIT_PLAYER_POS_X + PLAYER_WIDTH as Coord;
+        for y in [40, 100, 150, 170] {
+            let bird = Bird::new(INIT_PLAYER_POS_X, y);
+            assert!(!collides(&bird, &pair_at(x), FIELD), "y = {y}");
+            let behind = INIT_PLAYER_POS_X - OBSTACLE_WIDTH as Coord;
+            assert!(!collides(&bird, &pair_at(behind), FIELD), "y = {y}");
+        }
+    }
+
+    #[test]
+    fn partial_horizontal_overlap_hits() {
+        let bird = Bird::new(INIT_PLAYER_POS_X, 100);
+        let x = INIT_PLAYER_POS_X + PLAYER_WIDTH as Coord - 1;
+        assert!(collides(&bird, &pair_at(x), FIELD));
+        let x = INIT_PLAYER_POS_X - OBSTACLE_WIDTH as Coord + 1;
+        assert!(collides(&bird, &pair_at(x), FIELD));
+    }
+
+    #[test]
+    fn hitting_the_ground() {
+        let pair = pair_at(LCD_END);
+        let bird = Bird::new(INIT_PLAYER_POS_X, GROUND_Y_POS - PLAYER_HEIGHT as Coord);
+        assert!(collides(&bird, &pair, FIELD));
+        let bird = Bird::new(INIT_PLAYER_POS_X, GROUND_Y_POS - PLAYER_HEIGHT as Coord - 1);
+        assert!(!collides(&bird, &pair, FIELD));
+    }
+
+    #[test]
+    fn hitting_the_ceiling() {
+        let pair = pair_at(LCD_END);
+        let bird = Bird::new(INIT_PLAYER_POS_X, PLAYER_Y_MIN);
+        assert!(collides(&bird, &pair, FIELD));
+        let bird = Bird::new(INIT_PLAYER_POS_X, PLAYER_Y_MIN + 1);
+        assert!(!collides(&bird, &pair, FIELD));
+    }
+
+    #[test]
+    fn nearing_an_edge_warns_more_the_closer() {
+        let bird = |y| Bird::new(INIT_PLAYER_POS_X, y);
+        assert_eq!(edge_warning(&bird_in_gap(), FIELD), None);
+        assert_eq!(
+            edge_warning(&bird(PLAYER_Y_MIN), FIELD),
+            Some((Edge::Ceiling, 255))
+        );
+        let Some((Edge::Ceiling, far)) = edge_warning(&bird(PLAYER_Y_MIN + 8), FIELD) else {
+            panic!("no ceiling warning");
+        };
+        assert!(far < 255);
+        let last = EDGE_WARN_PX as Coord;
+        assert_eq!(edge_warning(&bird(PLAYER_Y_MIN + last), FIELD), None);
+
+        let low = GROUND_Y_POS - PLAYER_HEIGHT as Coord - 1;
+        assert!(matches!(
+            edge_warning(&bird(low), FIELD),
+            Some((Edge::Ground, _))
+        ));
+    }
+
+    #[test]
+    fn ground_is_per_lane() {
+        let lane = Lane::SPLIT[0];
+        let pair = ObstaclePair::new(lane);
+        let (min, max) = lane.player_y_range();
+        let bird = Bird::new(INIT_PLAYER_POS_X, min);
+        assert!(collides(&bird, &pair, lane.field()));
+        let bird = Bird::new(INIT_PLAYER_POS_X, max);
+        assert!(collides(&bird, &pair, lane.field()));
+        let bird = Bird::new(INIT_PLAYER_POS_X, max - 1);
+        assert!(!collides(&bird, &pair, lane.field()));
+    }
+
+    #[test]
+    fn passing_scores_once() {
+        let bird = bird_in_gap();
+        let mut pair = pair_at(INIT_PLAYER_POS_X - OBSTACLE_WIDTH as Coord - 1);
+        assert!(passed(&bird, &mut pair));
+        assert!(!passed(&bird, &mut pair));
+    }
+
+    #[test]
+    fn not_passed_while_overlapping() {
+        let bird = bird_in_gap();
+        let mut pair = pair_at(INIT_PLAYER_POS_X - OBSTACLE_WIDTH as Coord);
+        assert!(!passed(&bird, &mut pair));
+        assert!(!pair.already_scored);
+    }
+
+    #[test]
+    fn a_full_lap_scores_one() {
+        let bird = bird_in_gap();
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        let mut score = 0;
+        for _ in 0..LCD_END / SPEED as Coord {
+            pair.advance();
+            assert!(!collides(&bird, &pair, FIELD));
+            if passed(&bird, &mut pair) {
+                score += 1;
+            }
+            pair.wrap(score);
+        }
+        assert_eq!(score, 1);
+    }
+
+    // A lap of the FULL lane's pair with the bird `rows` under the opening's
+    // top, tracking the clearance; whether it was a near miss
+    fn lap_below_the_top(rows: Coord) -> bool {
+        let lane = Lane::FULL;
+        let bird = Bird::new(INIT_PLAYER_POS_X, lane.gap_top + rows);
+        let mut pair = ObstaclePair::new(lane);
+        loop {
+            pair.advance();
+            track_clearance(&bird, &mut pair);
+            if passed(&bird, &mut pair) {
+                return near_miss(&pair);
+            }
+        }
+    }
+
+    #[test]
+    fn clearance_only_alongside() {
+        let bird = bird_in_gap();
+        assert_eq!(clearance(&bird, &pair_at(LCD_END)), None);
+        let behind = INIT_PLAYER_POS_X - OBSTACLE_WIDTH as Coord;
+        assert_eq!(clearance(&bird, &pair_at(behind)), None);
+        assert!(clearance(&bird, &pair_at(INIT_PLAYER_POS_X)).is_some());
+    }
+
+    #[test]
+    fn clearance_is_to_the_nearer_pipe() {
+        let lane = Lane::FULL;
+        let pair = pair_at(INIT_PLAYER_POS_X);
+        let bird = Bird::new(INIT_PLAYER_POS_X, lane.gap_top + 2);
+        assert_eq!(clearance(&bird, &pair), Some(2));
+        let h = bird.hitbox().h as Coord;
+        let bird = Bird::new(INIT_PLAYER_POS_X, lane.gap_bottom - h - 5);
+        assert_eq!(clearance(&bird, &pair), Some(5));
+    }
+
+    #[test]
+    fn close_passes_are_near_misses() {
+        assert!(lap_below_the_top(1));
+        assert!(lap_below_the_top(NEAR_MISS_PX as Coord));
+        assert!(!lap_below_the_top(NEAR_MISS_PX as Coord + 1));
+    }
+
+    #[test]
+    fn wrapping_forgets_the_clearance() {
+        let mut pair = pair_at(INIT_PLAYER_POS_X);
+        track_clearance(&bird_in_gap(), &mut pair);
+        assert!(pair.closest.is_some());
+        while !pair.wrap(0) {
+            pair.advance();
+        }
+        assert_eq!(pair.closest, None);
+    }
+
+    #[test]
+    fn laps_keep_scoring() {
+        let bird = bird_in_gap();
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        let mut score = 0;
+        for _ in 0..10 * LCD_END / SPEED as Coord {
+            pair.advance();
+            if passed(&bird, &mut pair) {
+                score += 1;
+            }
+            pair.wrap(score);
+        }
+        assert_eq!(score, 10);
+    }
+}
@@ -0,0 +1,148 @@
+//! Periodic jobs on a millisecond clock
+//!
+//! Each of N jobs has an interval. `tick`, called every millisecond (from
+//! SysTick on the board), marks the jobs whose time has come, and whoever
+//! runs them takes the marked ones in turn with `take`. A job marked again
+//! before it was taken runs once, and one that fell more than an interval
+//! behind starts counting again from now instead of running a burst to
+//! catch up. Every job is due on the first tick.
+
+/// Jobs one scheduler holds at most, one bit each
+pub const MAX_JOBS: usize = 32;
+
+pub struct Scheduler<const N: usize> {
+    intervals: [u32; N],
+    // When each job is next due
+    next: [u32; N],
+    // Marked and not yet taken, a bit per job
+    due: u32,
+}
+
+impl<const N: usize> Scheduler<N> {
+    pub const fn new(intervals: [u32; N]) -> Self {
+        assert!(N <= MAX_JOBS);
+        Scheduler {
+            intervals,
+            next: [0; N],
+            due: 0,
+        }
+    }
+
+    // Mark the jobs whose time has come by `now`; true if any was not
+    // marked already
+    pub fn tick(&mut self, now: u32) -> bool {
+        let mut marked = false;
+        for job in 0..N {
+            let late = now.wrapping_sub(self.next[job]);
+            // Wrapping times: a next time "ahead" of now reads as a huge lag
+            if late > u32::MAX / 2 {
+                continue;
+            }
+            let interval = self.intervals[job].max(1);
+            self.next[job] = if late < interval {
+                self.next[job].wrapping_add(interval)
+            } else {
+                now.wrapping_add(interval)
+            };
+            marked |= self.due & (1 << job) == 0;
+            self.due |= 1 << job;
+        }
+        marked
+    }
+
+    // The first marked job, unmarking it
+    pub fn take(&mut self) -> Option<usize> {
+        if self.due == 0 {
+            return None;
+        }
+        let job = self.due.trailing_zeros() as usize;
+        self.due &= !(1 << job);
+        Some(job)
+    }
+
+    pub fn interval(&self, job: usize) -> u32 {
+        self.intervals[job]
+    }
+
+    // Run `job` every `ms` from now on, the first time `ms` after `now`
+    pub fn set_interval(&mut self, job: usize, ms: u32, now: u32) {
+        self.intervals[job] = ms;
+        self.next[job] = now.wrapping_add(ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taken<const N: usize>(scheduler: &mut Scheduler<N>) -> Vec<usize> {
+        core::iter::from_fn(|| scheduler.take()).collect()
+    }
+
+    #[test]
+    fn every_job_is_due_first_then_at_its_interval() {
+        let mut scheduler = Scheduler::new([10, 100]);
+        assert!(scheduler.tick(0));
+        assert_eq!(taken(&mut scheduler), [0, 1]);
+        for now in 1..100 {
+            scheduler.tick(now);
+            let expected: &[usize] = if now % 10 == 0 { &[0] } else { &[] };
+            assert_eq!(taken(&mut scheduler), expected, "at {}", now);
+        }
+        scheduler.tick(100);
+        assert_eq!(taken(&mut scheduler), [0, 1]);
+    }
+
+    #[test]
+    fn a_job_not_taken_runs_once() {
+        let mut scheduler = Scheduler::new([10]);
+        assert!(scheduler.tick(0));
+        assert!(!scheduler.tick(10));
+        assert_eq!(taken(&mut scheduler), [0]);
+    }
+
+    #[test]
+    fn a_late_job_counts_again_from_now() {
+        let mut scheduler = Scheduler::new([10]);
+        scheduler.tick(0);
+        scheduler.take();
+        // Ticks stopped for a while, as while the board slept
+        scheduler.tick(55);
+        assert_eq!(taken(&mut scheduler), [0]);
+        for now in 56..65 {
+            scheduler.tick(now);
+            assert_eq!(scheduler.take(), None);
+        }
+        scheduler.tick(65);
+        assert_eq!(scheduler.take(), Some(0));
+    }
+
+    #[test]
+    fn set_interval_starts_from_now() {
+        let mut scheduler = Scheduler::new([10]);
+        scheduler.tick(0);
+        scheduler.take();
+        scheduler.set_interval(0, 1000, 5);
+        assert_eq!(scheduler.interval(0), 1000);
+        for now in 6..1005 {
+            scheduler.tick(now);
+            assert_eq!(scheduler.take(), None);
+        }
+        scheduler.tick(1005);
+        assert_eq!(scheduler.take(), Some(0));
+    }
+
+    #[test]
+    fn keeps_time_across_the_clock_wrapping() {
+        let mut scheduler = Scheduler::new([10]);
+        scheduler.set_interval(0, 10, u32::MAX - 4);
+        scheduler.tick(u32::MAX);
+        assert_eq!(scheduler.take(), None);
+        scheduler.tick(5);
+        assert_eq!(scheduler.take(), Some(0));
+        scheduler.tick(14);
+        assert_eq!(scheduler.take(), None);
+        scheduler.tick(15);
+        assert_eq!(scheduler.take(), Some(0));
+    }
+}
@@ -0,0 +1,98 @@
+//! What an idle menu screen shows as time passes
+//!
+//! A menu left up with nobody at the board would burn its text into the
+//! panel. Once the screen has been idle for the configured delay it dims,
+//! and from then on the picture creeps: every `SHIFT_PERIOD_MS` it moves to
+//! the next of `SHIFTS`, a few pixels round a small square, so no edge sits
+//! on the same pixels for long. `BLANK_AFTER_MS` after dimming the screen
+//! goes dark until input comes.
+
+use crate::config::Coord;
+
+// Longest delay the settings take, an hour
+pub const MAX_DELAY_S: u16 = 3600;
+pub const SHIFT_PERIOD_MS: u32 = 60_000;
+// Dimmed time before the screen goes blank
+pub const BLANK_AFTER_MS: u32 = 5 * 60_000;
+// How far the picture creeps from where it belongs, each way
+pub const SHIFT_PX: Coord = 2;
+
+const S: Coord = SHIFT_PX;
+// Round the square, each offset next to the one before and the last next
+// to the first
+const SHIFTS: [(Coord, Coord); 9] = [
+    (0, 0),
+    (S, 0),
+    (S, S),
+    (0, S),
+    (-S, S),
+    (-S, 0),
+    (-S, -S),
+    (0, -S),
+    (S, -S),
+];
+
+/// How far along an idle screen is
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Stage {
+    Awake,
+    // Dimmed, the picture moved by the offset
+    Dimmed((Coord, Coord)),
+    Blank,
+}
+
+// The stage after `idle_ms` without input, dimming after `delay_s`; a delay
+// of 0 never leaves Awake
+pub fn stage(idle_ms: u32, delay_s: u16) -> Stage {
+    if delay_s == 0 {
+        return Stage::Awake;
+    }
+    let Some(dimmed_ms) = idle_ms.checked_sub(delay_s as u32 * 1000) else {
+        return Stage::Awake;
+    };
+    if dimmed_ms >= BLANK_AFTER_MS {
+        return Stage::Blank;
+    }
+    let step = (dimmed_ms / SHIFT_PERIOD_MS) as usize;
+    Stage::Dimmed(SHIFTS[step % SHIFTS.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dims_after_the_delay_then_blanks() {
+        assert_eq!(stage(0, 30), Stage::Awake);
+        assert_eq!(stage(29_999, 30), Stage::Awake);
+        assert_eq!(stage(30_000, 30), Stage::Dimmed((0, 0)));
+        assert_eq!(
+            stage(30_000 + BLANK_AFTER_MS - 1, 30),
+            Stage::Dimmed((-S, S))
+        );
+        assert_eq!(stage(30_000 + BLANK_AFTER_MS, 30), Stage::Blank);
+        assert_eq!(stage(u32::MAX, 30), Stage::Blank);
+    }
+
+    #[test]
+    fn zero_delay_never_saves() {
+        assert_eq!(stage(u32::MAX, 0), Stage::Awake);
+    }
+
+    #[test]
+    fn creeps_a_step_a_minute() {
+        let at = |minute: u32| match stage(minute * SHIFT_PERIOD_MS, 60) {
+            Stage::Dimmed(offset) => offset,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(at(1), (0, 0));
+        assert_eq!(at(2), (S, 0));
+        assert_eq!(at(3), (S, S));
+        // Each step moves one notch along at most
+        for pair in SHIFTS.windows(2).chain([[SHIFTS[8], SHIFTS[0]].as_slice()]) {
+            let (a, b) = (pair[0], pair[1]);
+            assert!((a.0 - b.0).abs() <= S && (a.1 - b.1).abs() <= S);
+            assert_ne!(a, b);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Scripted obstacle sequences
+//!
+//! A `Script` is a few bytes saying what each obstacle in turn looks like,
+//! so a designer can lay out a staircase or a tunnel and have it come out
+//! the same every game, with stretches of the classic game's random picks
+//! in between. Played as a `GameMode` it runs from the start again once it
+//! runs out. Each byte is one op:
+//!
+//! ```text
+//! 0x0k   kind k      obstacles from here on are kind k (Static, Moving,
+//!                    Double, Spike, Bar)
+//! 0x1n   spacing n   they come in n tiles further apart than usual
+//! 0x2n   random n    n + 1 obstacles as the classic game picks them
+//! 0x3n   repeat n    the last obstacle op n + 1 more times
+//! 0x4n   up n        an obstacle with the opening n * STEP_ROWS higher
+//! 0x5n   down n      ... lower, than the last scripted one
+//! 0x80|r at r        an obstacle with the opening r rows below the top of
+//!                    the playfield
+//! ```
+//!
+//! Rows are kept between 0 and `course::MAX_ROW`. Scripts are byte tables
+//! in flash; `op` builds the bytes so they read as the ops they are.
+
+use crate::config::Coord;
+use crate::course::{DEFAULT_ROW, MAX_ROW};
+use crate::mode::GameMode;
+use crate::obstacle::ObstacleKind;
+
+// Rows one step of `up` or `down` moves the opening
+pub const STEP_ROWS: Coord = 4;
+
+const KIND: u8 = 0x00;
+const SPACING: u8 = 0x10;
+const RANDOM: u8 = 0x20;
+const REPEAT: u8 = 0x30;
+const UP: u8 = 0x40;
+const DOWN: u8 = 0x50;
+const AT: u8 = 0x80;
+const KINDS: [ObstacleKind; 5] = [
+    ObstacleKind::Static,
+    ObstacleKind::Moving,
+    ObstacleKind::Double,
+    ObstacleKind::Spike,
+    ObstacleKind::Bar,
+];
+
+/// The ops as bytes, for writing scripts
+pub mod op {
+    use super::*;
+
+    pub const fn kind(kind: ObstacleKind) -> u8 {
+        KIND | kind as u8
+    }
+
+    pub const fn spacing(tiles: u8) -> u8 {
+        SPACING | (tiles & 0x0F)
+    }
+
+    // 1 to 16 obstacles
+    pub const fn random(count: u8) -> u8 {
+        RANDOM | ((count - 1) & 0x0F)
+    }
+
+    // 1 to 16 more times
+    pub const fn repeat(times: u8) -> u8 {
+        REPEAT | ((times - 1) & 0x0F)
+    }
+
+    pub const fn up(steps: u8) -> u8 {
+        UP | (steps & 0x0F)
+    }
+
+    pub const fn down(steps: u8) -> u8 {
+        DOWN | (steps & 0x0F)
+    }
+
+    pub const fn at(row: u8) -> u8 {
+        AT | (row & 0x7F)
+    }
+}
+
+/// One obstacle as the script has it
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    // None for the mode to pick
+    pub kind: Option<ObstacleKind>,
+    // Rows from the top of the playfield to the opening; None leaves it
+    // where the kind puts it
+    pub row: Option<Coord>,
+    // Extra tiles of sky ahead of it
+    pub spacing: u32,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Script {
+    name: &'static str,
+    bytes: &'static [u8],
+}
+
+impl Script {
+    pub const fn new(name: &'static str, bytes: &'static [u8]) -> Self {
+        Script { name, bytes }
+    }
+
+    // The position of the first byte that is not an op
+    pub fn check(&self) -> Result<(), usize> {
+        match self
+            .bytes
+            .iter()
+            .position(|&b| b & 0x80 == 0 && b >> 4 > DOWN >> 4)
+        {
+            Some(at) => Err(at),
+            None => Ok(()),
+        }
+    }
+
+    // One pass through the script
+    pub fn steps(&self) -> Steps<'static> {
+        Steps {
+            bytes: self.bytes,
+            pc: 0,
+            kind: ObstacleKind::Static,
+            spacing: 0,
+            row: DEFAULT_ROW as Coord,
+            last: None,
+            pending: None,
+        }
+    }
+
+    // The game's `index`th obstacle, the script played over and over; None
+    // for a script with no obstacles in it
+    pub fn step(&self, index: u32) -> Option<Step> {
+        let len = self.steps().count() as u32;
+        if len == 0 {
+            return None;
+        }
+        self.steps().nth((index % len) as usize)
+    }
+}
+
+impl GameMode for Script {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn first(&self) -> ObstacleKind {
+        ObstacleKind::Static
+    }
+
+    // Only asked for the random stretches
+    fn next(&self, _current: ObstacleKind, score: u32, roll: u32) -> ObstacleKind {
+        ObstacleKind::pick(score, roll)
+    }
+
+    fn kind(&self, index: u32) -> Option<ObstacleKind> {
+        self.step(index)?.kind
+    }
+
+    fn gap(&self, index: u32) -> Option<Coord> {
+        self.step(index)?.row
+    }
+
+    fn spacing(&self, index: u32) -> u32 {
+        self.step(index).map_or(0, |step| step.spacing)
+    }
+}
+
+// An op that brings an obstacle, as `repeat` plays it again
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Emit {
+    Random,
+    Move(Coord),
+    At(Coord),
+}
+
+pub struct Steps<'a> {
+    bytes: &'a [u8],
+    pc: usize,
+    kind: ObstacleKind,
+    spacing: u32,
+    // The last scripted opening
+    row: Coord,
+    last: Option<Emit>,
+    // An op still owed this many more obstacles
+    pending: Option<(Emit, u8)>,
+}
+
+impl Steps<'_> {
+    fn emit(&mut self, emit: Emit) -> Step {
+        self.last = Some(emit);
+        let row = match emit {
+            Emit::Random => None,
+            Emit::Move(delta) => Some(self.row + delta),
+            Emit::At(row) => Some(row),
+        };
+        if let Some(row) = row {
+            self.row = row.clamp(0, MAX_ROW as Coord);
+        }
+        Step {
+            kind: (emit != Emit::Random).then_some(self.kind),
+            row: row.map(|_| self.row),
+            spacing: self.spacing,
+        }
+    }
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        if let Some((emit, left)) = self.pending {
+            self.pending = (left > 1).then_some((emit, left - 1));
+            return Some(self.emit(emit));
+        }
+        loop {
+            let &byte = self.bytes.get(self.pc)?;
+            self.pc += 1;
+            let n = byte & 0x0F;
+            let steps = n as Coord * STEP_ROWS;
+            let emit = match byte & 0xF0 {
+                _ if byte & AT != 0 => Emit::At((byte & !AT) as Coord),
+                KIND => {
+                    self.kind = KINDS.get(n as usize).copied().unwrap_or(self.kind);
+                    continue;
+                }
+                SPACING => {
+                    self.spacing = n as u32;
+                    continue;
+                }
+                RANDOM => {
+                    self.pending = (n > 0).then_some((Emit::Random, n));
+                    Emit::Random
+                }
+                REPEAT => match self.last {
+                    Some(last) => {
+                        self.pending = (n > 0).then_some((last, n));
+                        last
+                    }
+                    None => continue,
+                },
+                UP => Emit::Move(-steps),
+                DOWN => Emit::Move(steps),
+                _ => continue,
+            };
+            return Some(self.emit(emit));
+        }
+    }
+}
+
+/// Random stretches with a signature section after each: a staircase up
+/// and back down, a tunnel of openings at one height, and a slalom
+pub const SIGNATURE: Script = Script::new(
+    "signature",
+    &[
+        op::random(6),
+        // Staircase
+        op::kind(ObstacleKind::Static),
+        op::at(110),
+        op::up(5),
+        op::repeat(4),
+        op::down(5),
+        op::repeat(4),
+        op::random(6),
+        // Tunnel, spaced out so each opening has to be held
+        op::spacing(2),
+        op::at(30),
+        op::repeat(4),
+        op::spacing(0),
+        op::random(6),
+        // Slalom between moving openings high and low
+        op::kind(ObstacleKind::Moving),
+        op::at(10),
+        op::at(120),
+        op::at(10),
+        op::at(120),
+    ],
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::*;
+    use crate::lane::Lane;
+    use crate::obstacle::ObstaclePair;
+    use std::vec::Vec;
+
+    fn steps(bytes: &[u8]) -> Vec<Step> {
+        Script::new("test", bytes.to_vec().leak()).steps().collect()
+    }
+
+    #[test]
+    fn a_staircase_climbs_from_where_it_starts() {
+        let rows: Vec<_> = steps(&[op::at(100), op::up(2), op::repeat(2), op::down(5)])
+            .iter()
+            .map(|step| step.row)
+            .collect();
+        assert_eq!(rows, [Some(100), Some(92), Some(84), Some(76), Some(96)]);
+    }
+
+    #[test]
+    fn random_stretches_leave_the_pick_to_the_mode() {
+        let all = steps(&[op::random(3), op::kind(ObstacleKind::Double), op::at(40)]);
+        assert_eq!(all.len(), 4);
+        assert!(all[..3]
+            .iter()
+            .all(|step| step.kind.is_none() && step.row.is_none()));
+        assert_eq!(all[3].kind, Some(ObstacleKind::Double));
+        assert_eq!(all[3].row, Some(40));
+    }
+
+    #[test]
+    fn rows_stay_in_the_playfield_and_spacing_carries_on() {
+        let all = steps(&[
+            op::spacing(3),
+            op::at(4),
+            op::up(15),
+            op::at(127),
+            op::down(15),
+        ]);
+        let rows: Vec<_> = all.iter().map(|step| step.row.unwrap()).collect();
+        assert_eq!(rows, [4, 0, 127, MAX_ROW as Coord]);
+        assert!(all.iter().all(|step| step.spacing == 3));
+    }
+
+    #[test]
+    fn the_script_plays_over_and_over() {
+        const LOOP: Script = Script::new("loop", &[op::at(10), op::at(20)]);
+        let rows: Vec<_> = (0..5).map(|i| LOOP.gap(i)).collect();
+        assert_eq!(rows, [Some(10), Some(20), Some(10), Some(20), Some(10)]);
+        assert_eq!(Script::new("empty", &[0x11]).gap(0), None);
+    }
+
+    #[test]
+    fn bad_bytes_are_found_and_the_signature_is_clean() {
+        assert_eq!(Script::new("bad", &[0x81, 0x60]).check(), Err(1));
+        assert_eq!(SIGNATURE.check(), Ok(()));
+        assert!(SIGNATURE.steps().count() > 20);
+    }
+
+    #[test]
+    fn obstacles_come_in_spaced_as_scripted() {
+        const TUNNEL: Script = Script::new("tunnel", &[op::spacing(2), op::at(30)]);
+        let field = Lane::FULL.field();
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.start_with(&TUNNEL);
+        pair.set_speed(LCD_END as u32);
+        pair.advance();
+        assert!(pair.wrap_with(0, &TUNNEL));
+        let (x, top, _) = pair.get_gap();
+        assert_eq!(x, LCD_END + 2 * TILE as Coord);
+        assert_eq!(top, field.y + 30);
+    }
+}
@@ -0,0 +1,205 @@
+//! Scroll speed in fractions of a pixel
+//!
+//! Things can only be drawn a whole pixel at a time, but whole pixels per
+//! tick are coarse speeds: 2 to 3 is half as fast again. A `Scroller` keeps
+//! its velocity in 1/`SUBPIXELS` of a pixel per tick and carries what a
+//! tick moves past its whole pixels on to the next, so at 2.1 pixels per
+//! tick it moves 2 most ticks and 3 every tenth, and speeds can ramp up in
+//! small steps. The carried fraction is there for code that wants to know
+//! how close the next extra pixel is.
+//!
+//! Not everything on screen scrolls with the obstacles: a `Depth` says how
+//! far back a layer is, which is both the order layers are drawn in and how
+//! fast a layer scrolls next to the gameplay layer, for a sense of distance.
+
+// Steps in a pixel of velocity
+pub const SUBPIXELS: u32 = 256;
+
+// `px` whole pixels per tick, as a velocity
+pub const fn pixels(px: u32) -> u32 {
+    px * SUBPIXELS
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Scroller {
+    // 1/SUBPIXELS of a pixel per tick
+    velocity: u32,
+    // Movement not yet turned into a whole pixel, under SUBPIXELS
+    fraction: u32,
+}
+
+impl Scroller {
+    pub const fn new(velocity: u32) -> Self {
+        Scroller {
+            velocity,
+            fraction: 0,
+        }
+    }
+
+    pub fn velocity(&self) -> u32 {
+        self.velocity
+    }
+
+    // Change speed, keeping the fraction moved so far
+    pub fn set_velocity(&mut self, velocity: u32) {
+        self.velocity = velocity;
+    }
+
+    // Whole pixels per tick, rounded down
+    pub fn whole(&self) -> u32 {
+        self.velocity / SUBPIXELS
+    }
+
+    // 1/SUBPIXELS of a pixel moved but not yet stepped
+    pub fn fraction(&self) -> u32 {
+        self.fraction
+    }
+
+    // One tick on: the whole pixels to move by
+    pub fn step(&mut self) -> u32 {
+        let total = self.fraction + self.velocity;
+        self.fraction = total % SUBPIXELS;
+        total / SUBPIXELS
+    }
+}
+
+/// How far back a layer is
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Depth {
+    // Drawing order, lowest first; the gameplay layer is 0
+    pub z: i8,
+    // Speed next to the gameplay layer, in 1/SUBPIXELS
+    pub rate: u32,
+}
+
+impl Depth {
+    // The obstacles, what the bird can hit, and what moves with them
+    pub const PLAY: Depth = Depth {
+        z: 0,
+        rate: SUBPIXELS,
+    };
+
+    pub const fn behind(z: i8, rate: u32) -> Self {
+        Depth { z, rate }
+    }
+
+    // Only things on the gameplay layer can be hit; whatever is further back
+    // or in front is scenery
+    pub fn is_play(&self) -> bool {
+        self.z == 0
+    }
+
+    // The layer's velocity with the gameplay layer moving at `play`
+    pub fn velocity(&self, play: u32) -> u32 {
+        (play as u64 * self.rate as u64 / SUBPIXELS as u64) as u32
+    }
+}
+
+impl Default for Depth {
+    fn default() -> Self {
+        Self::PLAY
+    }
+}
+
+// A velocity written as pixels per tick, "2" or "2.1", to two decimal
+// places
+pub fn parse(text: &str) -> Option<u32> {
+    let (whole, decimals) = text.split_once('.').unwrap_or((text, ""));
+    if decimals.len() > 2 || !decimals.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole: u32 = whole.parse().ok()?;
+    let mut hundredths = 0;
+    for (i, digit) in decimals.bytes().enumerate() {
+        hundredths += (digit - b'0') as u32 * if i == 0 { 10 } else { 1 };
+    }
+    let velocity = whole.checked_mul(SUBPIXELS)?;
+    velocity.checked_add((hundredths * SUBPIXELS + 50) / 100)
+}
+
+// `velocity` as (whole pixels, hundredths), to show it the way `parse`
+// reads it
+pub fn hundredths(velocity: u32) -> (u32, u32) {
+    let hundredths = (velocity % SUBPIXELS * 100 + SUBPIXELS / 2) / SUBPIXELS;
+    if hundredths == 100 {
+        (velocity / SUBPIXELS + 1, 0)
+    } else {
+        (velocity / SUBPIXELS, hundredths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_pixel_speeds_step_evenly() {
+        let mut scroller = Scroller::new(pixels(3));
+        for _ in 0..10 {
+            assert_eq!(scroller.step(), 3);
+            assert_eq!(scroller.fraction(), 0);
+        }
+    }
+
+    #[test]
+    fn fractional_speed_averages_out() {
+        let mut scroller = Scroller::new(parse("2.1").unwrap());
+        let steps: u32 = (0..100).map(|_| scroller.step()).sum();
+        // 2.1 is 538/256, a shade over: 210.2 pixels in 100 ticks
+        assert_eq!(steps, 210);
+        let mut scroller = Scroller::new(parse("2.1").unwrap());
+        let mut counts = [0; 4];
+        for _ in 0..100 {
+            counts[scroller.step() as usize] += 1;
+        }
+        assert_eq!(counts[..2], [0, 0]);
+        assert!(counts[3] >= 9 && counts[3] <= 11);
+    }
+
+    #[test]
+    fn changing_speed_keeps_the_fraction() {
+        let mut scroller = Scroller::new(SUBPIXELS / 2);
+        assert_eq!(scroller.step(), 0);
+        assert_eq!(scroller.fraction(), SUBPIXELS / 2);
+        scroller.set_velocity(pixels(1));
+        assert_eq!(scroller.step(), 1);
+        assert_eq!(scroller.fraction(), SUBPIXELS / 2);
+        assert_eq!(scroller.whole(), 1);
+    }
+
+    #[test]
+    fn a_layer_at_half_rate_moves_half_as_far() {
+        let far = Depth::behind(-2, SUBPIXELS / 2);
+        let mut near = Scroller::new(parse("2.5").unwrap());
+        let mut back = Scroller::new(far.velocity(near.velocity()));
+        let near_px: u32 = (0..100).map(|_| near.step()).sum();
+        let back_px: u32 = (0..100).map(|_| back.step()).sum();
+        assert_eq!((near_px, back_px), (250, 125));
+        assert_eq!(Depth::PLAY.velocity(pixels(3)), pixels(3));
+        assert!(Depth::PLAY.is_play() && !far.is_play());
+    }
+
+    #[test]
+    fn parse_reads_up_to_two_decimals() {
+        assert_eq!(parse("2"), Some(pixels(2)));
+        assert_eq!(parse("2.5"), Some(pixels(2) + SUBPIXELS / 2));
+        assert_eq!(parse("2.50"), parse("2.5"));
+        assert_eq!(parse("0.25"), Some(SUBPIXELS / 4));
+        assert_eq!(parse("2."), Some(pixels(2)));
+        assert_eq!(parse("2.125"), None);
+        assert_eq!(parse("2.x"), None);
+        assert_eq!(parse(".5"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn hundredths_round_trip() {
+        for text in ["1", "2.1", "2.2", "3.75", "7.99"] {
+            let (whole, hundredths) = hundredths(parse(text).unwrap());
+            let shown = std::format!("{whole}.{hundredths:02}");
+            assert_eq!(parse(&shown), parse(text), "{text}");
+        }
+        assert_eq!(hundredths(pixels(2)), (2, 0));
+        assert_eq!(hundredths(pixels(3) - 1), (3, 0));
+    }
+}
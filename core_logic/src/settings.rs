@@ -0,0 +1,880 @@
+//! The user settings and the words they are stored as
+//!
+//! The options the player can tune are one `Settings` value. The firmware
+//! keeps it in flash (its `settings` and `store`) as a record of words, the
+//! format version and then one word per field (`to_words`). How many fields
+//! there are depends on the version, and `from_words` reads every version
+//! there has been, so older records still load after an upgrade: fields a
+//! version predates keep their defaults, and a value out of range falls
+//! back to its own.
+//!
+//! The types of the fields the firmware applies are here too, as plain
+//! values. What the hardware does with them stays with the modules that
+//! apply them: the themes in `theme`, the gamma tables in `ili9341`, the
+//! trigger pin in `trigger` and the accelerometer in `input_device`.
+
+use crate::controls::ControlScheme;
+use crate::lane::Margins;
+use crate::lang::{Language, Msg};
+use crate::screensaver;
+use crate::scroll;
+use crate::tilt_map::TiltMap;
+use crate::tuning::TuningParams;
+
+// "SETG", first word of the settings blocks older firmware kept
+pub const MAGIC: u32 = 0x5345_5447;
+// Version 2 added the tilt offsets, 3 the temperature they were taken at,
+// 4 the gamma curve, 5 the control scheme, 6 the frame rate, 7 the
+// external trigger, 8 the language, 9 the screensaver delay, 10 the
+// gameplay tuning, 11 the playfield margins, 12 the panel inversion, 13
+// the tilt axis and its shaping, 14 the music, 15 the gap preview
+pub const VERSION: u32 = 15;
+pub const FIELDS: usize = 21;
+// A legacy block's magic and version, before its fields
+pub const HEADER_WORDS: usize = 2;
+// A legacy block at its longest: header, fields and checksum
+pub const MAX_BLOCK_WORDS: usize = HEADER_WORDS + FIELDS + 1;
+// Stored in place of an unknown calibration temperature
+const NO_TEMP: i32 = i32::MIN;
+
+// Fields stored by each format version
+pub fn field_count(version: u32) -> Option<usize> {
+    match version {
+        1 => Some(5),
+        2 => Some(8),
+        3 => Some(9),
+        4 => Some(10),
+        5 => Some(11),
+        6 => Some(12),
+        7 => Some(13),
+        8 => Some(14),
+        9 => Some(15),
+        10 => Some(16),
+        11 => Some(17),
+        12 => Some(18),
+        13 => Some(19),
+        14 => Some(20),
+        15 => Some(21),
+        _ => None,
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy = 0,
+    Normal = 1,
+    Hard = 2,
+}
+
+impl Difficulty {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(Difficulty::Easy),
+            1 => Some(Difficulty::Normal),
+            2 => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    // Its name on screen, in the player's language
+    pub fn msg(self) -> Msg {
+        match self {
+            Difficulty::Easy => Msg::Easy,
+            Difficulty::Normal => Msg::Normal,
+            Difficulty::Hard => Msg::Hard,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        (0..3)
+            .filter_map(Difficulty::from_u32)
+            .find(|difficulty| difficulty.as_str() == name)
+    }
+
+    // The next one up, back to easy after hard, for the settings page
+    pub fn next(self) -> Self {
+        Difficulty::from_u32((self as u32 + 1) % 3).unwrap_or(Difficulty::Normal)
+    }
+
+    // Obstacle speed a game starts at, in 1/SUBPIXELS of a pixel per frame
+    // (see `core_logic::scroll`)
+    pub fn velocity(self) -> u32 {
+        match self {
+            Difficulty::Easy => scroll::pixels(1),
+            Difficulty::Normal => scroll::pixels(2),
+            Difficulty::Hard => scroll::pixels(3),
+        }
+    }
+}
+
+/// How often the game draws. Either way the logic steps at the fixed tick
+/// rate (see `core_logic::timestep`); the battery saver only presents every
+/// other vertical blank, halving the drawing and the SDRAM traffic it
+/// makes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FrameRate {
+    Full = 0,
+    Saver = 1,
+}
+
+impl FrameRate {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(FrameRate::Full),
+            1 => Some(FrameRate::Saver),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FrameRate::Full => "60",
+            FrameRate::Saver => "30",
+        }
+    }
+
+    // The other one, for the settings page to switch to
+    pub fn next(self) -> Self {
+        match self {
+            FrameRate::Full => FrameRate::Saver,
+            FrameRate::Saver => FrameRate::Full,
+        }
+    }
+
+    // Vertical blanks from one frame to the next
+    pub fn vblanks(self) -> u32 {
+        match self {
+            FrameRate::Full => 1,
+            FrameRate::Saver => 2,
+        }
+    }
+
+    // Shortest frame, for when there are no vertical blanks to wait for
+    pub fn frame_ms(self) -> u32 {
+        match self {
+            FrameRate::Full => 16,
+            FrameRate::Saver => 33,
+        }
+    }
+}
+
+/// Color and art theme; the themes themselves are the firmware's `theme`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ThemeId {
+    Day = 0,
+    Night = 1,
+    GreenPipe = 2,
+    RedPipe = 3,
+    HighContrast = 4,
+}
+
+pub const THEME_COUNT: usize = 5;
+
+impl ThemeId {
+    pub fn from_u32(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(ThemeId::Day),
+            1 => Some(ThemeId::Night),
+            2 => Some(ThemeId::GreenPipe),
+            3 => Some(ThemeId::RedPipe),
+            4 => Some(ThemeId::HighContrast),
+            _ => None,
+        }
+    }
+
+    // Its name on screen, in the player's language
+    pub fn msg(self) -> Msg {
+        match self {
+            ThemeId::Day => Msg::Day,
+            ThemeId::Night => Msg::Night,
+            ThemeId::GreenPipe => Msg::Green,
+            ThemeId::RedPipe => Msg::Red,
+            ThemeId::HighContrast => Msg::HighContrast,
+        }
+    }
+}
+
+/// Gamma curves offered in the settings; their tables are the panel
+/// driver's (`ili9341`)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GammaProfile {
+    Standard = 0,
+    Vivid = 1,
+    Soft = 2,
+}
+
+const GAMMA_PROFILES: u32 = 3;
+
+impl GammaProfile {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(GammaProfile::Standard),
+            1 => Some(GammaProfile::Vivid),
+            2 => Some(GammaProfile::Soft),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GammaProfile::Standard => "standard",
+            GammaProfile::Vivid => "vivid",
+            GammaProfile::Soft => "soft",
+        }
+    }
+
+    pub fn msg(self) -> Msg {
+        match self {
+            GammaProfile::Standard => Msg::Standard,
+            GammaProfile::Vivid => Msg::Vivid,
+            GammaProfile::Soft => Msg::Soft,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        GammaProfile::from_u32((self as u32 + 1) % GAMMA_PROFILES).unwrap_or(GammaProfile::Standard)
+    }
+}
+
+/// Which level of the trigger pin is pressed, and the debounce
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TriggerConfig {
+    pub active_high: bool,
+    pub debounce_ms: u16,
+}
+
+impl TriggerConfig {
+    // A switch to ground, which is also how IR receiver modules drive
+    pub const DEFAULT: TriggerConfig = TriggerConfig {
+        active_high: false,
+        debounce_ms: 10,
+    };
+    pub const MAX_DEBOUNCE_MS: u16 = 500;
+    const ACTIVE_HIGH_BIT: u32 = 1 << 16;
+
+    // One settings word: the debounce in the low half, bit 16 for active high
+    pub fn to_u32(self) -> u32 {
+        let level = if self.active_high {
+            Self::ACTIVE_HIGH_BIT
+        } else {
+            0
+        };
+        level | self.debounce_ms as u32
+    }
+
+    pub fn from_u32(word: u32) -> Option<Self> {
+        let debounce_ms = (word & 0xFFFF) as u16;
+        let valid =
+            word & !(Self::ACTIVE_HIGH_BIT | 0xFFFF) == 0 && debounce_ms <= Self::MAX_DEBOUNCE_MS;
+        valid.then_some(TriggerConfig {
+            active_high: word & Self::ACTIVE_HIGH_BIT != 0,
+            debounce_ms,
+        })
+    }
+
+    pub fn level_str(self) -> &'static str {
+        if self.active_high {
+            "high"
+        } else {
+            "low"
+        }
+    }
+}
+
+/// How the board sits at rest and how far it must tilt to flap
+///
+/// Set by the calibration wizard. `offset` is the resting reading per axis
+/// (x, y, z), taken off every reading before it is mapped, so "level" is
+/// however the board lay while calibrating.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TiltCalibration {
+    pub offset: [i32; 3],
+    /// Minimum acceleration from rest to register as "tapped" (typically 4000-12000)
+    pub threshold: i32,
+    /// Sensor temperature when `offset` was measured, hundredths of a degree C
+    pub temp: Option<i32>,
+}
+
+impl TiltCalibration {
+    pub const DEFAULT: TiltCalibration = TiltCalibration {
+        offset: [0; 3],
+        threshold: 8000,
+        temp: None,
+    };
+
+    /// The offsets moved by the sensor's drift between the calibration
+    /// temperature and `temp`, `drift_at` giving its zero-g offset per axis
+    /// at a temperature; unchanged when either is unknown
+    pub fn at_temperature(&self, temp: Option<i32>, drift_at: fn(i32) -> [i32; 3]) -> Self {
+        let (Some(then), Some(now)) = (self.temp, temp) else {
+            return *self;
+        };
+        let (before, after) = (drift_at(then), drift_at(now));
+        let mut offset = self.offset;
+        for axis in 0..3 {
+            offset[axis] += after[axis] - before[axis];
+        }
+        TiltCalibration {
+            offset,
+            temp: Some(now),
+            ..*self
+        }
+    }
+
+    /// `reading` (x, y, z) relative to the resting orientation
+    pub fn apply(&self, reading: [i32; 3]) -> [i32; 3] {
+        let [x, y, z] = reading;
+        [x - self.offset[0], y - self.offset[1], z - self.offset[2]]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Settings {
+    // From the tilt calibration wizard
+    pub tilt: TiltCalibration,
+    // Backlight percentage
+    pub brightness: u8,
+    pub theme: ThemeId,
+    pub sound: bool,
+    pub difficulty: Difficulty,
+    // Panel gamma curve
+    pub gamma: GammaProfile,
+    // How input moves the bird
+    pub controls: ControlScheme,
+    pub frame_rate: FrameRate,
+    // Level and debounce of the external trigger pin
+    pub trigger: TriggerConfig,
+    // Language of the on-screen text
+    pub language: Language,
+    // Seconds a menu waits before the screensaver starts, 0 for never
+    pub screensaver_s: u16,
+    // Gameplay numbers tuned from the shell
+    pub tuning: TuningParams,
+    // Score bar and ground rows of the full-screen game, for the bezel
+    pub margins: Margins,
+    // Panel colors inverted, for players who see them better that way
+    pub invert: bool,
+    // Which accelerometer axis steers and how, for the way the board is held
+    pub tilt_map: TiltMap,
+    // Background music, apart from the sound effects
+    pub music: bool,
+    // Beginners' assist: the next opening shown at the right edge before
+    // its pipe comes on
+    pub gap_preview: bool,
+}
+
+// Tilt readings are raw MPU6050 counts at +-2 g
+const TILT_THRESHOLD_RANGE: (i32, i32) = (2000, 16000);
+
+impl Settings {
+    pub const DEFAULT: Settings = Settings {
+        tilt: TiltCalibration::DEFAULT,
+        brightness: 100,
+        theme: ThemeId::Day,
+        sound: true,
+        difficulty: Difficulty::Normal,
+        gamma: GammaProfile::Standard,
+        controls: ControlScheme::DirectTilt,
+        frame_rate: FrameRate::Full,
+        trigger: TriggerConfig::DEFAULT,
+        language: Language::En,
+        screensaver_s: 60,
+        tuning: TuningParams::DEFAULT,
+        margins: Margins::DEFAULT,
+        invert: false,
+        tilt_map: TiltMap::DEFAULT,
+        music: true,
+        gap_preview: false,
+    };
+
+    pub fn to_words(self) -> [u32; FIELDS] {
+        let [x, y, z] = self.tilt.offset;
+        [
+            self.tilt.threshold as u32,
+            self.brightness as u32,
+            self.theme as u32,
+            self.sound as u32,
+            self.difficulty as u32,
+            x as u32,
+            y as u32,
+            z as u32,
+            self.tilt.temp.unwrap_or(NO_TEMP) as u32,
+            self.gamma as u32,
+            self.controls as u32,
+            self.frame_rate as u32,
+            self.trigger.to_u32(),
+            self.language as u32,
+            self.screensaver_s as u32,
+            self.tuning.to_u32(),
+            self.margins.to_u32(),
+            self.invert as u32,
+            self.tilt_map.to_u32(),
+            self.music as u32,
+            self.gap_preview as u32,
+        ]
+    }
+
+    // Fields as stored by format `version`; out-of-range values fall back
+    // to their defaults one by one, and fields the version predates keep
+    // theirs
+    pub fn from_words(version: u32, words: &[u32]) -> Option<Self> {
+        let default = Settings::DEFAULT;
+        // Every version starts with the same five
+        let (&[threshold, brightness, theme, sound, difficulty], rest) =
+            words.split_first_chunk::<5>()?;
+        let (
+            offset,
+            temp,
+            gamma,
+            controls,
+            frame_rate,
+            trigger,
+            language,
+            screensaver_s,
+            tuning,
+            margins,
+            invert,
+            tilt_map,
+            music,
+            gap_preview,
+        ) = match (version, rest) {
+            (1, &[]) => (
+                default.tilt.offset,
+                default.tilt.temp,
+                default.gamma,
+                default.controls,
+                default.frame_rate,
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (2, &[x, y, z]) => (
+                [x as i32, y as i32, z as i32],
+                default.tilt.temp,
+                default.gamma,
+                default.controls,
+                default.frame_rate,
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (3, &[x, y, z, temp]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                default.gamma,
+                default.controls,
+                default.frame_rate,
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (4, &[x, y, z, temp, gamma]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                default.controls,
+                default.frame_rate,
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (5, &[x, y, z, temp, gamma, controls]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                default.frame_rate,
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (6, &[x, y, z, temp, gamma, controls, frame_rate]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                default.trigger,
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (7, &[x, y, z, temp, gamma, controls, frame_rate, trigger]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                default.language,
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (8, &[x, y, z, temp, gamma, controls, frame_rate, trigger, language]) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                default.screensaver_s,
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                9,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, screensaver_s],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(screensaver_s)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                default.tuning,
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                10,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                default.margins,
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                11,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning, margins],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                Margins::from_u32(margins).unwrap_or(default.margins),
+                default.invert,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                12,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning, margins, invert],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                Margins::from_u32(margins).unwrap_or(default.margins),
+                invert != 0,
+                default.tilt_map,
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                13,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning, margins, invert, tilt_map],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                Margins::from_u32(margins).unwrap_or(default.margins),
+                invert != 0,
+                TiltMap::from_u32(tilt_map).unwrap_or(default.tilt_map),
+                default.music,
+                default.gap_preview,
+            ),
+            (
+                14,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning, margins, invert, tilt_map, music],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                Margins::from_u32(margins).unwrap_or(default.margins),
+                invert != 0,
+                TiltMap::from_u32(tilt_map).unwrap_or(default.tilt_map),
+                music != 0,
+                default.gap_preview,
+            ),
+            (
+                15,
+                &[x, y, z, temp, gamma, controls, frame_rate, trigger, language, saver, tuning, margins, invert, tilt_map, music, gap_preview],
+            ) => (
+                [x as i32, y as i32, z as i32],
+                Some(temp as i32).filter(|&t| t != NO_TEMP),
+                GammaProfile::from_u32(gamma).unwrap_or(default.gamma),
+                ControlScheme::from_u32(controls).unwrap_or(default.controls),
+                FrameRate::from_u32(frame_rate).unwrap_or(default.frame_rate),
+                TriggerConfig::from_u32(trigger).unwrap_or(default.trigger),
+                Language::from_u32(language).unwrap_or(default.language),
+                u16::try_from(saver)
+                    .ok()
+                    .filter(|&s| s <= screensaver::MAX_DELAY_S)
+                    .unwrap_or(default.screensaver_s),
+                TuningParams::from_u32(tuning).unwrap_or(default.tuning),
+                Margins::from_u32(margins).unwrap_or(default.margins),
+                invert != 0,
+                TiltMap::from_u32(tilt_map).unwrap_or(default.tilt_map),
+                music != 0,
+                gap_preview != 0,
+            ),
+            _ => return None,
+        };
+        let (min, max) = TILT_THRESHOLD_RANGE;
+        let threshold = threshold as i32;
+        Some(Settings {
+            tilt: TiltCalibration {
+                offset,
+                temp,
+                threshold: if (min..=max).contains(&threshold) {
+                    threshold
+                } else {
+                    default.tilt.threshold
+                },
+            },
+            brightness: if brightness <= 100 {
+                brightness as u8
+            } else {
+                default.brightness
+            },
+            theme: ThemeId::from_u32(theme).unwrap_or(default.theme),
+            sound: sound != 0,
+            difficulty: Difficulty::from_u32(difficulty).unwrap_or(default.difficulty),
+            gamma,
+            controls,
+            frame_rate,
+            trigger,
+            language,
+            screensaver_s,
+            tuning,
+            margins,
+            invert,
+            tilt_map,
+            music,
+            gap_preview,
+        })
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings::DEFAULT
+    }
+}
+
+// `settings` as the store keeps them, into `out`; the words written
+pub fn payload(settings: &Settings, out: &mut [u32]) -> Option<usize> {
+    let out = out.get_mut(..1 + FIELDS)?;
+    out[0] = VERSION;
+    out[1..].copy_from_slice(&settings.to_words());
+    Some(out.len())
+}
+
+// The settings `payload` wrote into `words`, if this firmware reads their
+// version
+pub fn from_payload(words: &[u32]) -> Option<Settings> {
+    let (&version, fields) = words.split_first()?;
+    (field_count(version)? == fields.len()).then_some(())?;
+    Settings::from_words(version, fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom() -> Settings {
+        Settings {
+            tilt: TiltCalibration {
+                offset: [-120, 340, 16100],
+                threshold: 6000,
+                temp: Some(2650),
+            },
+            brightness: 60,
+            theme: ThemeId::Night,
+            difficulty: Difficulty::Hard,
+            gamma: GammaProfile::Soft,
+            controls: ControlScheme::HybridAssist,
+            frame_rate: FrameRate::Saver,
+            language: Language::De,
+            screensaver_s: 0,
+            invert: true,
+            music: false,
+            gap_preview: true,
+            ..Settings::DEFAULT
+        }
+    }
+
+    #[test]
+    fn round_trips_through_a_payload() {
+        for settings in [Settings::DEFAULT, custom()] {
+            let mut words = [0; 1 + FIELDS];
+            assert_eq!(payload(&settings, &mut words), Some(1 + FIELDS));
+            assert_eq!(words[0], VERSION);
+            assert_eq!(from_payload(&words), Some(settings));
+        }
+        assert_eq!(payload(&Settings::DEFAULT, &mut [0; FIELDS]), None);
+    }
+
+    #[test]
+    fn older_versions_keep_the_defaults_of_what_they_predate() {
+        let words = custom().to_words();
+        for version in 1..VERSION {
+            let fields = field_count(version).unwrap();
+            let settings = Settings::from_words(version, &words[..fields]).unwrap();
+            assert_eq!(settings.brightness, 60);
+            assert_eq!(settings.gap_preview, Settings::DEFAULT.gap_preview);
+            assert_eq!(Settings::from_words(version, &words[..fields + 1]), None);
+        }
+        // 14 added the music, which a block from 13 cannot have turned off
+        let v13 = Settings::from_words(13, &words[..19]).unwrap();
+        assert!(v13.invert && v13.music);
+        assert_eq!(field_count(VERSION + 1), None);
+    }
+
+    #[test]
+    fn sound_and_difficulty_persist() {
+        let mut settings = Settings::DEFAULT;
+        settings.sound = !settings.sound;
+        settings.difficulty = settings.difficulty.next();
+        let mut words = [0; 1 + FIELDS];
+        payload(&settings, &mut words).unwrap();
+        let stored = from_payload(&words).unwrap();
+        assert!(!stored.sound);
+        assert_eq!(stored.difficulty, Difficulty::Hard);
+        assert_eq!(Difficulty::Hard.next(), Difficulty::Easy);
+        assert_eq!(Difficulty::parse("easy"), Some(Difficulty::Easy));
+        assert_eq!(Difficulty::parse("insane"), None);
+    }
+
+    #[test]
+    fn a_value_out_of_range_falls_back_on_its_own() {
+        let mut words = custom().to_words();
+        words[1] = 101;
+        words[2] = THEME_COUNT as u32;
+        let settings = Settings::from_words(VERSION, &words).unwrap();
+        assert_eq!(settings.brightness, Settings::DEFAULT.brightness);
+        assert_eq!(settings.theme, Settings::DEFAULT.theme);
+        assert_eq!(settings.gamma, GammaProfile::Soft);
+    }
+
+    #[test]
+    fn calibration_follows_the_temperature() {
+        let drift_at = |centi_c: i32| [centi_c / 100, 0, -centi_c / 50];
+        let calibration = custom().tilt;
+        let warm = calibration.at_temperature(Some(3650), drift_at);
+        assert_eq!(warm.offset, [-110, 340, 16080]);
+        assert_eq!(warm.temp, Some(3650));
+        assert_eq!(calibration.at_temperature(None, drift_at), calibration);
+        assert_eq!(warm.apply([-110, 1340, 16080]), [0, 1000, 0]);
+    }
+}
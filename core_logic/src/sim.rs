@@ -0,0 +1,73 @@
+//! Host render backend: frames dumped as PPM images
+//!
+//! Every `present` writes the picture to `<dir>/frame_NNNNN.ppm`, which any
+//! image viewer opens (or `ffmpeg -i frame_%05d.ppm` turns into a video).
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::color::Rgb565;
+use crate::config::Coord;
+use crate::render::RenderBackend;
+
+pub struct PpmBackend {
+    width: u32,
+    height: u32,
+    pixels: Vec<u16>,
+    dir: PathBuf,
+    frame: u32,
+}
+
+impl PpmBackend {
+    pub fn new(width: u32, height: u32, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(PpmBackend {
+            width,
+            height,
+            pixels: vec![0; (width * height) as usize],
+            dir,
+            frame: 0,
+        })
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u16> {
+        (x < self.width && y < self.height).then(|| self.pixels[(y * self.width + x) as usize])
+    }
+
+    // Frames written so far
+    pub fn frames(&self) -> u32 {
+        self.frame
+    }
+
+    fn write_frame(&self) -> io::Result<()> {
+        let path = self.dir.join(format!("frame_{:05}.ppm", self.frame));
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for &p in &self.pixels {
+            let (r, g, b) = Rgb565(p).to_rgb();
+            out.write_all(&[r, g, b])?;
+        }
+        out.flush()
+    }
+}
+
+impl RenderBackend for PpmBackend {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: Coord, y: Coord, rgb565: u16) {
+        if (0..self.width as Coord).contains(&x) && (0..self.height as Coord).contains(&y) {
+            self.pixels[(y as u32 * self.width + x as u32) as usize] = rgb565;
+        }
+    }
+
+    fn present(&mut self) {
+        if let Err(err) = self.write_frame() {
+            eprintln!("sim: frame {} not written: {}", self.frame, err);
+        }
+        self.frame += 1;
+    }
+}
@@ -0,0 +1,110 @@
+//! Day/night sky
+//!
+//! The sky is a vertical gradient, one color per row, from `top` at the
+//! scoreboard down to `bottom` at the ground. As the score goes up it moves
+//! through day, dusk, night and dawn and back to day, blending between
+//! neighbouring keyframes so each point shifts it only a little.
+
+use crate::color::Rgb565;
+use crate::config::Coord;
+use crate::rect::Rect;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Sky {
+    pub top: Rgb565,
+    pub bottom: Rgb565,
+}
+
+// Points from one keyframe to the next
+pub const POINTS_PER_PHASE: u32 = 10;
+
+// Day, dusk, night, dawn. Day's horizon is the original background color
+// and night's the Night theme's.
+const KEYFRAMES: [Sky; 4] = [
+    Sky::new(Rgb565::from_rgb(72, 160, 232), Rgb565(0x9F5E)),
+    Sky::new(
+        Rgb565::from_rgb(48, 40, 120),
+        Rgb565::from_rgb(248, 152, 72),
+    ),
+    Sky::new(Rgb565::from_rgb(0, 0, 24), Rgb565(0x10A6)),
+    Sky::new(
+        Rgb565::from_rgb(88, 72, 160),
+        Rgb565::from_rgb(248, 168, 160),
+    ),
+];
+
+// Score after which the cycle starts over
+pub const POINTS_PER_CYCLE: u32 = POINTS_PER_PHASE * KEYFRAMES.len() as u32;
+
+impl Sky {
+    pub const fn new(top: Rgb565, bottom: Rgb565) -> Self {
+        Sky { top, bottom }
+    }
+
+    // One color all the way down
+    pub const fn flat(color: Rgb565) -> Self {
+        Sky::new(color, color)
+    }
+
+    // The day/night sky at `score`
+    pub fn at(score: u32) -> Self {
+        let score = score % POINTS_PER_CYCLE;
+        let phase = (score / POINTS_PER_PHASE) as usize;
+        let from = KEYFRAMES[phase];
+        let to = KEYFRAMES[(phase + 1) % KEYFRAMES.len()];
+        let t = ((score % POINTS_PER_PHASE) * 255 / POINTS_PER_PHASE) as u8;
+        Sky::new(from.top.lerp(to.top, t), from.bottom.lerp(to.bottom, t))
+    }
+
+    // Color of screen row `y` with the gradient stretched over the rows of
+    // `field`; rows outside it get the nearest end
+    pub fn row_color(&self, y: Coord, field: Rect) -> Rgb565 {
+        if field.h <= 1 {
+            return self.top;
+        }
+        let last = field.h as i64 - 1;
+        let row = (y as i64 - field.y as i64).clamp(0, last);
+        self.top.lerp(self.bottom, (row * 255 / last) as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELD: Rect = Rect::new(0, 30, 240, 180);
+
+    #[test]
+    fn starts_on_day_and_wraps() {
+        assert_eq!(Sky::at(0), KEYFRAMES[0]);
+        assert_eq!(Sky::at(POINTS_PER_CYCLE), Sky::at(0));
+        assert_eq!(Sky::at(2 * POINTS_PER_PHASE), KEYFRAMES[2]);
+    }
+
+    #[test]
+    fn every_point_moves_towards_the_next_keyframe() {
+        assert_ne!(Sky::at(1), Sky::at(0));
+        assert_ne!(Sky::at(POINTS_PER_PHASE - 1), KEYFRAMES[1]);
+    }
+
+    #[test]
+    fn gradient_ends_on_the_field_edges() {
+        let sky = KEYFRAMES[1];
+        assert_eq!(sky.row_color(30, FIELD), sky.top);
+        assert_eq!(sky.row_color(209, FIELD), sky.bottom);
+        assert_eq!(sky.row_color(0, FIELD), sky.top);
+        assert_eq!(sky.row_color(239, FIELD), sky.bottom);
+    }
+
+    #[test]
+    fn flat_sky_is_one_color() {
+        let sky = Sky::flat(Rgb565(0x10A6));
+        for y in [30, 100, 209] {
+            assert_eq!(sky.row_color(y, FIELD), Rgb565(0x10A6));
+        }
+        assert_eq!(
+            KEYFRAMES[0].row_color(50, Rect::new(0, 50, 240, 1)),
+            KEYFRAMES[0].top
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Distant towers behind the obstacles
+//!
+//! A row of tower silhouettes standing on the ground, repeating every
+//! `PERIOD` columns. They are scenery on a layer behind the gameplay one
+//! (see `scroll::Depth`), scrolling slower than the obstacles so they look
+//! far off; nothing collides with them. A `Skyline` is how far the layer has
+//! scrolled, and says where each tower is on screen.
+
+use crate::config::Coord;
+use crate::rect::Rect;
+
+// Width, height, then the columns to the next tower
+const TOWERS: [(u32, u32, u32); 6] = [
+    (14, 30, 6),
+    (10, 44, 12),
+    (18, 22, 4),
+    (12, 36, 16),
+    (16, 26, 8),
+    (8, 40, 14),
+];
+
+// Columns before the towers repeat
+pub const PERIOD: u32 = {
+    let mut total = 0;
+    let mut i = 0;
+    while i < TOWERS.len() {
+        total += TOWERS[i].0 + TOWERS[i].2;
+        i += 1;
+    }
+    total
+};
+
+// The tallest tower, so the rows the skyline can cover
+pub const HEIGHT: u32 = 44;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Skyline {
+    // Columns scrolled since the game started
+    pub scroll: u32,
+}
+
+impl Skyline {
+    pub const fn new(scroll: u32) -> Self {
+        Skyline { scroll }
+    }
+
+    // The rows above `ground` the towers can cover, `width` columns wide
+    pub fn band(ground: Coord, width: u32) -> Rect {
+        Rect::new(0, ground - HEIGHT as Coord, width, HEIGHT)
+    }
+
+    // Every tower at least partly in columns 0..width, standing on `ground`
+    pub fn towers(&self, ground: Coord, width: u32) -> impl Iterator<Item = Rect> {
+        let start = (self.scroll % PERIOD) as Coord;
+        let repeats = width / PERIOD + 2;
+        (0..repeats).flat_map(move |repeat| {
+            let mut x = (repeat * PERIOD) as Coord - start;
+            TOWERS.iter().filter_map(move |&(w, h, gap)| {
+                let tower = Rect::new(x, ground - h as Coord, w, h);
+                x += (w + gap) as Coord;
+                (tower.x + (w as Coord) > 0 && tower.x < width as Coord).then_some(tower)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn towers_move_left_and_come_round_again() {
+        let at = |scroll| -> Vec<Rect> { Skyline::new(scroll).towers(200, 240).collect() };
+        let first = at(0);
+        assert_eq!(first[0], Rect::new(0, 170, 14, 30));
+        assert_eq!(at(5)[0].x, -5);
+        assert_eq!(at(PERIOD), first);
+        assert_eq!(at(PERIOD * 3 + 7), at(7));
+    }
+
+    #[test]
+    fn only_towers_on_screen_stand_on_the_ground() {
+        for scroll in [0, 13, PERIOD - 1] {
+            let towers: Vec<Rect> = Skyline::new(scroll).towers(200, 240).collect();
+            assert!(!towers.is_empty());
+            let band = Skyline::band(200, 240);
+            for tower in towers {
+                assert!(tower.x + tower.w as Coord > 0 && tower.x < 240);
+                assert_eq!(tower.y + tower.h as Coord, 200);
+                assert!(tower.y >= band.y);
+            }
+        }
+    }
+}
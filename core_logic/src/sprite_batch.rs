@@ -0,0 +1,186 @@
+//! Several images drawn in one pass
+//!
+//! Drawing a handful of sprites one `blit` at a time sets up the target,
+//! clips and converts each pixel on its own for every one. A `SpriteBatch`
+//! collects them first: each sprite is an image, where its top left goes,
+//! its mirroring and its transparent key. `draw` then clips every sprite
+//! to the batch's clip rectangle once, drops the ones wholly outside, and
+//! hands the rest to the backend's `blit_region`, which on the board works
+//! out the panel addressing once per sprite instead of once per pixel. The
+//! list is kept in drawing order, so it can later be handed to DMA2D as
+//! one chain of transfers.
+
+use crate::rect::Rect;
+use crate::render::{Image, ImageTransform, RenderBackend};
+
+/// One image in a batch
+#[derive(Copy, Clone)]
+pub struct Sprite<'a> {
+    pub image: Image<'a>,
+    pub x: i32,
+    pub y: i32,
+    pub transform: ImageTransform,
+    // Pixels of this color are left out
+    pub key: Option<u16>,
+}
+
+impl Sprite<'_> {
+    // Where it covers
+    pub fn rect(&self) -> Rect {
+        let (w, h) = self.transform.size(&self.image);
+        Rect::new(self.x, self.y, w, h)
+    }
+
+    // The part inside `clip`, relative to the sprite's top left; None when
+    // none of it is
+    pub fn visible(&self, clip: Rect) -> Option<Rect> {
+        let rect = self.rect();
+        let inside = rect.intersection(&clip)?;
+        Some(inside.offset(-rect.x, -rect.y))
+    }
+}
+
+/// Every batch slot is taken
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct BatchFull;
+
+/// Up to `N` sprites, drawn in the order pushed
+pub struct SpriteBatch<'a, const N: usize> {
+    sprites: [Option<Sprite<'a>>; N],
+    len: usize,
+    clip: Rect,
+}
+
+impl<'a, const N: usize> SpriteBatch<'a, N> {
+    // An empty batch drawing only inside `clip`
+    pub const fn new(clip: Rect) -> Self {
+        SpriteBatch {
+            sprites: [None; N],
+            len: 0,
+            clip,
+        }
+    }
+
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    pub fn push(&mut self, sprite: Sprite<'a>) -> Result<(), BatchFull> {
+        let slot = self.sprites.get_mut(self.len).ok_or(BatchFull)?;
+        *slot = Some(sprite);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn sprites(&self) -> impl Iterator<Item = &Sprite<'a>> {
+        self.sprites[..self.len].iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites = [None; N];
+        self.len = 0;
+    }
+
+    // Draw every sprite into `target`, clipped; the caller presents
+    pub fn draw(&self, target: &mut (impl RenderBackend + ?Sized)) {
+        for sprite in self.sprites() {
+            if let Some(region) = sprite.visible(self.clip) {
+                target.blit_region(
+                    sprite.x,
+                    sprite.y,
+                    &sprite.image,
+                    sprite.transform,
+                    sprite.key,
+                    region,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x4 surface recording pixels and how many were written
+    struct Grid {
+        pixels: [[u16; 4]; 4],
+        writes: usize,
+    }
+
+    impl RenderBackend for Grid {
+        fn size(&self) -> (u32, u32) {
+            (4, 4)
+        }
+
+        fn set_pixel(&mut self, x: i32, y: i32, rgb565: u16) {
+            self.writes += 1;
+            if (0..4).contains(&x) && (0..4).contains(&y) {
+                self.pixels[y as usize][x as usize] = rgb565;
+            }
+        }
+
+        fn present(&mut self) {}
+    }
+
+    const DATA: [u16; 4] = [1, 2, 3, 4];
+
+    fn sprite(x: i32, y: i32, key: Option<u16>) -> Sprite<'static> {
+        Sprite {
+            image: Image::new(2, 2, &DATA),
+            x,
+            y,
+            transform: ImageTransform::NONE,
+            key,
+        }
+    }
+
+    #[test]
+    fn draws_in_order_clipped() {
+        let mut batch: SpriteBatch<3> = SpriteBatch::new(Rect::new(0, 0, 3, 4));
+        batch.push(sprite(0, 0, None)).unwrap();
+        batch.push(sprite(1, 1, Some(4))).unwrap();
+        // Wholly outside the clip
+        batch.push(sprite(3, 0, None)).unwrap();
+        let mut grid = Grid {
+            pixels: [[0; 4]; 4],
+            writes: 0,
+        };
+        batch.draw(&mut grid);
+        assert_eq!(
+            grid.pixels,
+            [[1, 2, 0, 0], [3, 1, 2, 0], [0, 3, 0, 0], [0; 4]]
+        );
+        // Only what is inside the clip is touched
+        assert_eq!(grid.writes, 4 + 3);
+    }
+
+    #[test]
+    fn visible_part_is_relative_to_the_sprite() {
+        let clip = Rect::new(0, 0, 4, 4);
+        assert_eq!(
+            sprite(-1, 3, None).visible(clip),
+            Some(Rect::new(1, 0, 1, 1))
+        );
+        assert_eq!(sprite(4, 0, None).visible(clip), None);
+    }
+
+    #[test]
+    fn full_batch_turns_sprites_away() {
+        let mut batch: SpriteBatch<1> = SpriteBatch::new(Rect::SCREEN);
+        assert!(batch.is_empty());
+        assert_eq!(batch.push(sprite(0, 0, None)), Ok(()));
+        assert_eq!(batch.push(sprite(0, 0, None)), Err(BatchFull));
+        assert_eq!(batch.len(), 1);
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+}
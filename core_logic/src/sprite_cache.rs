@@ -0,0 +1,195 @@
+//! Bookkeeping for the firmware's SDRAM sprite cache
+//!
+//! The firmware keeps sprites it has already converted for drawing (turned
+//! to the bird's tilt, mirrored, turned into ARGB8888) in SDRAM slots, so the
+//! per-pixel work is done once instead of every frame. This is the part with
+//! no memory behind it: which `Key` each slot holds and which slot goes when
+//! a new one is wanted (`Lru`) and where each pixel of a turned image comes
+//! from (`turned_from`).
+//!
+//! Turns are in steps of a 64th of a full turn, clockwise as drawn; see
+//! `lean` for how far the bird is turned.
+
+use crate::raster::{sine, SINE_ONE, SINE_STEPS};
+use crate::render::ImageTransform;
+
+/// What a cached image was made from
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Key {
+    pub asset: u8,
+    pub transform: ImageTransform,
+    pub turn: i8,
+}
+
+/// Where a looked-up key is
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Lookup {
+    // Already converted in this slot
+    Hit(usize),
+    // Given this slot, to be converted into before use
+    Miss(usize),
+}
+
+/// Up to `N` slots, of which the first `slots` are used, handed out least
+/// recently used first
+pub struct Lru<const N: usize> {
+    keys: [Option<Key>; N],
+    // `clock` when each slot was last looked up
+    used: [u32; N],
+    slots: usize,
+    clock: u32,
+    hits: u32,
+    misses: u32,
+}
+
+impl<const N: usize> Lru<N> {
+    pub const fn new(slots: usize) -> Self {
+        Lru {
+            keys: [None; N],
+            used: [0; N],
+            slots: if slots < N { slots } else { N },
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    // (hits, misses) since the cache was made
+    pub fn counts(&self) -> (u32, u32) {
+        (self.hits, self.misses)
+    }
+
+    // The slot holding `key`, or the one to convert it into: an empty slot
+    // if there is one, else the least recently used. None without slots.
+    pub fn lookup(&mut self, key: Key) -> Option<Lookup> {
+        self.clock = self.clock.wrapping_add(1);
+        let slots = 0..self.slots;
+        if let Some(slot) = slots.clone().find(|&slot| self.keys[slot] == Some(key)) {
+            self.used[slot] = self.clock;
+            self.hits = self.hits.wrapping_add(1);
+            return Some(Lookup::Hit(slot));
+        }
+        let clock = self.clock;
+        let slot = slots
+            .clone()
+            .find(|&slot| self.keys[slot].is_none())
+            .or_else(|| slots.max_by_key(|&slot| clock.wrapping_sub(self.used[slot])))?;
+        self.keys[slot] = Some(key);
+        self.used[slot] = clock;
+        self.misses = self.misses.wrapping_add(1);
+        Some(Lookup::Miss(slot))
+    }
+
+    // Drop a slot whose conversion could not be finished
+    pub fn forget(&mut self, slot: usize) {
+        if let Some(key) = self.keys.get_mut(slot) {
+            *key = None;
+        }
+    }
+
+    // Drop everything, for when the source art changes
+    pub fn clear(&mut self) {
+        self.keys = [None; N];
+    }
+}
+
+// The pixel of a `w` x `h` image that lands at (col, row) once the image
+// is turned `turn` steps about its middle; None where the turned image
+// leaves the corner empty
+pub fn turned_from(w: u32, h: u32, col: u32, row: u32, turn: i8) -> Option<(u32, u32)> {
+    let step = (turn as i32).rem_euclid(SINE_STEPS as i32) as u32;
+    let sin = sine(step);
+    let cos = sine(step + SINE_STEPS / 4);
+    // Doubled, so the middle of an even-sized image is a whole number
+    let (w, h) = (w as i32, h as i32);
+    let dx = 2 * col as i32 + 1 - w;
+    let dy = 2 * row as i32 + 1 - h;
+    let round = SINE_ONE / 2;
+    let sx = (cos * dx + sin * dy + round).div_euclid(SINE_ONE);
+    let sy = (cos * dy - sin * dx + round).div_euclid(SINE_ONE);
+    let src_col = (sx + w - 1).div_euclid(2);
+    let src_row = (sy + h - 1).div_euclid(2);
+    ((0..w).contains(&src_col) && (0..h).contains(&src_row))
+        .then_some((src_col as u32, src_row as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(asset: u8, turn: i8) -> Key {
+        Key {
+            asset,
+            transform: ImageTransform::FLIP_Y,
+            turn,
+        }
+    }
+
+    #[test]
+    fn converts_once_then_hits() {
+        let mut lru: Lru<4> = Lru::new(4);
+        assert_eq!(lru.lookup(key(0, 1)), Some(Lookup::Miss(0)));
+        assert_eq!(lru.lookup(key(0, 2)), Some(Lookup::Miss(1)));
+        assert_eq!(lru.lookup(key(0, 1)), Some(Lookup::Hit(0)));
+        assert_eq!(lru.lookup(key(1, 1)), Some(Lookup::Miss(2)));
+        assert_eq!(lru.counts(), (1, 3));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let mut lru: Lru<4> = Lru::new(3);
+        for turn in 0..3 {
+            lru.lookup(key(0, turn));
+        }
+        lru.lookup(key(0, 0));
+        assert_eq!(lru.lookup(key(0, 3)), Some(Lookup::Miss(1)));
+        assert_eq!(lru.lookup(key(0, 1)), Some(Lookup::Miss(2)));
+        assert_eq!(lru.lookup(key(0, 0)), Some(Lookup::Hit(0)));
+    }
+
+    #[test]
+    fn slots_are_capped_and_may_be_none() {
+        assert_eq!(Lru::<2>::new(5).slots(), 2);
+        let mut none: Lru<2> = Lru::new(0);
+        assert_eq!(none.lookup(key(0, 0)), None);
+    }
+
+    #[test]
+    fn forgotten_and_cleared_slots_convert_again() {
+        let mut lru: Lru<2> = Lru::new(2);
+        lru.lookup(key(0, 0));
+        lru.lookup(key(1, 0));
+        lru.forget(0);
+        assert_eq!(lru.lookup(key(0, 0)), Some(Lookup::Miss(0)));
+        lru.clear();
+        assert_eq!(lru.lookup(key(1, 0)), Some(Lookup::Miss(0)));
+    }
+
+    #[test]
+    fn unturned_is_the_same_image() {
+        for row in 0..3 {
+            for col in 0..4 {
+                assert_eq!(turned_from(4, 3, col, row, 0), Some((col, row)));
+            }
+        }
+    }
+
+    #[test]
+    fn quarter_turn_is_clockwise() {
+        // The top left of a 2x2 image turned clockwise was its bottom left
+        assert_eq!(turned_from(2, 2, 0, 0, 16), Some((0, 1)));
+        assert_eq!(turned_from(2, 2, 1, 0, 16), Some((0, 0)));
+        assert_eq!(turned_from(2, 2, 0, 0, -16), Some((1, 0)));
+    }
+
+    #[test]
+    fn turned_corners_are_empty() {
+        assert_eq!(turned_from(30, 30, 0, 0, 8), None);
+        // The middle stays put
+        assert_eq!(turned_from(31, 31, 15, 15, 8), Some((15, 15)));
+    }
+}
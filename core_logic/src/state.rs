@@ -0,0 +1,109 @@
+//! The game's top-level states
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameState {
+    Initializing,
+    // Title screen, waiting for the player (or for attract mode to kick in)
+    Ready,
+    // Stats page over the title screen
+    Stats,
+    // Leaderboard, opened from the stats page
+    Leaderboard,
+    // Hidden debug menu, held open from the stats page
+    Debug,
+    Start,
+    Running,
+    // Death flash and fall, then on to the game-over screen
+    Dying,
+    Paused,
+    // Tilt calibration wizard, opened from the settings page
+    Calibrating,
+    // Course editor, opened from the stats page
+    Editor,
+    End,
+    // Initials for a run that made the leaderboard, before the game-over
+    // screen takes input
+    Initials,
+    Halt,
+    // Profile picker, up at boot before the title screen; last, as the
+    // black box stores states by their place in ALL
+    Profiles,
+}
+
+impl GameState {
+    // Every state, in declaration order
+    pub const ALL: [GameState; 15] = [
+        GameState::Initializing,
+        GameState::Ready,
+        GameState::Stats,
+        GameState::Leaderboard,
+        GameState::Debug,
+        GameState::Start,
+        GameState::Running,
+        GameState::Dying,
+        GameState::Paused,
+        GameState::Calibrating,
+        GameState::Editor,
+        GameState::End,
+        GameState::Initials,
+        GameState::Halt,
+        GameState::Profiles,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameState::Initializing => "Initializing",
+            GameState::Ready => "Ready",
+            GameState::Stats => "Stats",
+            GameState::Leaderboard => "Leaderboard",
+            GameState::Debug => "Debug",
+            GameState::Start => "Start",
+            GameState::Running => "Running",
+            GameState::Dying => "Dying",
+            GameState::Paused => "Paused",
+            GameState::Calibrating => "Calibrating",
+            GameState::Editor => "Editor",
+            GameState::End => "End",
+            GameState::Initials => "Initials",
+            GameState::Halt => "Halt",
+            GameState::Profiles => "Profiles",
+        }
+    }
+
+    // Whether the playfield is live: obstacles moving, input steering the bird
+    pub fn is_playing(self) -> bool {
+        matches!(self, GameState::Running)
+    }
+
+    pub fn is_over(self) -> bool {
+        matches!(self, GameState::Halt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_distinct() {
+        for (i, a) in GameState::ALL.iter().enumerate() {
+            for b in &GameState::ALL[i + 1..] {
+                assert_ne!(a.name(), b.name());
+            }
+        }
+    }
+
+    #[test]
+    fn only_running_is_playing() {
+        for state in GameState::ALL {
+            assert_eq!(state.is_playing(), state == GameState::Running);
+        }
+    }
+
+    #[test]
+    fn only_halt_is_over() {
+        for state in GameState::ALL {
+            assert_eq!(state.is_over(), state == GameState::Halt);
+        }
+    }
+}
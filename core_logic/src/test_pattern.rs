@@ -0,0 +1,253 @@
+//! Test patterns for bringing up a panel
+//!
+//! Pictures whose faults are easy to see, for checking the LTDC timing,
+//! sync polarities and color wiring of a new RGB panel: a shift or roll
+//! moves the grid's border off the edge, a swapped or missing color line
+//! puts the wrong bar or primary up, a pixel clock on the wrong edge smears
+//! the one-pixel grid lines, and banding in a ramp shows a dropped low bit.
+//! The moving box tears where the frame is not swapped in blanking.
+//!
+//! Every pattern is worked out per pixel in panel coordinates, 0..w by
+//! 0..h, as ARGB8888; the box is a `Rect` for each frame, drawn over
+//! `BOX_BACKGROUND`.
+
+use crate::config::Coord;
+use crate::rect::Rect;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Pattern {
+    // Big gray squares, the pattern the firmware has always started with
+    Checkerboard,
+    // White, yellow, cyan, green, magenta, red, blue, black
+    ColorBars,
+    // Red, green, blue and gray, dark on the left to full on the right
+    Ramps,
+    Red,
+    Green,
+    Blue,
+    White,
+    Black,
+    // One-pixel lines every GRID_STEP, a red border on the outermost pixels
+    Grid,
+    MovingBox,
+}
+
+const CHECKER_SIZE: u32 = 64;
+const CHECKER_DARK: u32 = 0xFF40_4040;
+const CHECKER_LIGHT: u32 = 0xFFC0_C0C0;
+
+const BARS: [u32; 8] = [
+    0xFFFF_FFFF,
+    0xFFFF_FF00,
+    0xFF00_FFFF,
+    0xFF00_FF00,
+    0xFFFF_00FF,
+    0xFFFF_0000,
+    0xFF00_00FF,
+    0xFF00_0000,
+];
+
+// Red, green, blue, gray
+const RAMP_MASKS: [u32; 4] = [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0x00FF_FFFF];
+
+pub const GRID_STEP: u32 = 16;
+const GRID_LINE: u32 = 0xFFFF_FFFF;
+const GRID_BORDER: u32 = 0xFFFF_0000;
+const GRID_BACKGROUND: u32 = 0xFF00_0000;
+
+pub const BOX_SIZE: u32 = 32;
+pub const BOX_COLOR: u32 = 0xFFFF_FFFF;
+pub const BOX_BACKGROUND: u32 = 0xFF20_2040;
+// Pixels a frame across and down
+const BOX_SPEED: (u32, u32) = (2, 3);
+
+impl Pattern {
+    // In the order they are stepped through
+    pub const ALL: [Pattern; 10] = [
+        Pattern::Checkerboard,
+        Pattern::ColorBars,
+        Pattern::Ramps,
+        Pattern::Red,
+        Pattern::Green,
+        Pattern::Blue,
+        Pattern::White,
+        Pattern::Black,
+        Pattern::Grid,
+        Pattern::MovingBox,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pattern::Checkerboard => "checker",
+            Pattern::ColorBars => "bars",
+            Pattern::Ramps => "ramps",
+            Pattern::Red => "red",
+            Pattern::Green => "green",
+            Pattern::Blue => "blue",
+            Pattern::White => "white",
+            Pattern::Black => "black",
+            Pattern::Grid => "grid",
+            Pattern::MovingBox => "box",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Pattern> {
+        Pattern::ALL.into_iter().find(|p| p.name() == text)
+    }
+
+    fn index(self) -> usize {
+        Pattern::ALL.iter().position(|&p| p == self).unwrap_or(0)
+    }
+
+    pub fn next(self) -> Pattern {
+        Pattern::ALL[(self.index() + 1) % Pattern::ALL.len()]
+    }
+
+    pub fn prev(self) -> Pattern {
+        let len = Pattern::ALL.len();
+        Pattern::ALL[(self.index() + len - 1) % len]
+    }
+
+    // Redrawn every frame
+    pub fn is_animated(self) -> bool {
+        self == Pattern::MovingBox
+    }
+
+    // Rows at a time the pattern is the same down, out of `h`, so a fill
+    // can work out one row of each band and copy it
+    pub fn band(self, h: u32) -> u32 {
+        match self {
+            Pattern::Checkerboard => CHECKER_SIZE,
+            Pattern::ColorBars
+            | Pattern::Red
+            | Pattern::Green
+            | Pattern::Blue
+            | Pattern::White
+            | Pattern::Black
+            | Pattern::MovingBox => h.max(1),
+            Pattern::Ramps | Pattern::Grid => 1,
+        }
+    }
+
+    // The ARGB8888 color at `x`, `y` of a `w` by `h` panel; for the moving
+    // box, the background it moves over
+    pub fn argb(self, x: u32, y: u32, w: u32, h: u32) -> u32 {
+        let (w, h) = (w.max(1), h.max(1));
+        match self {
+            Pattern::Checkerboard => {
+                if ((x / CHECKER_SIZE) ^ (y / CHECKER_SIZE)) & 1 != 0 {
+                    CHECKER_LIGHT
+                } else {
+                    CHECKER_DARK
+                }
+            }
+            Pattern::ColorBars => BARS[(x * BARS.len() as u32 / w) as usize % BARS.len()],
+            Pattern::Ramps => {
+                let mask =
+                    RAMP_MASKS[(y * RAMP_MASKS.len() as u32 / h) as usize % RAMP_MASKS.len()];
+                let level = if w > 1 { x * 0xFF / (w - 1) } else { 0xFF };
+                0xFF00_0000 | (level * 0x0001_0101) & mask
+            }
+            Pattern::Red => 0xFFFF_0000,
+            Pattern::Green => 0xFF00_FF00,
+            Pattern::Blue => 0xFF00_00FF,
+            Pattern::White => 0xFFFF_FFFF,
+            Pattern::Black => 0xFF00_0000,
+            Pattern::Grid => {
+                if x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+                    GRID_BORDER
+                } else if x.is_multiple_of(GRID_STEP) || y.is_multiple_of(GRID_STEP) {
+                    GRID_LINE
+                } else {
+                    GRID_BACKGROUND
+                }
+            }
+            Pattern::MovingBox => BOX_BACKGROUND,
+        }
+    }
+}
+
+// 0 up to `span` and back down again as `t` counts up
+fn bounce(t: u32, span: u32) -> u32 {
+    if span == 0 {
+        return 0;
+    }
+    let t = t % (2 * span);
+    if t < span {
+        t
+    } else {
+        2 * span - t
+    }
+}
+
+// Where the moving box is on `frame` of a `w` by `h` panel
+pub fn box_at(frame: u32, w: u32, h: u32) -> Rect {
+    let size = BOX_SIZE.min(w).min(h);
+    let x = bounce(frame.wrapping_mul(BOX_SPEED.0), w - size);
+    let y = bounce(frame.wrapping_mul(BOX_SPEED.1), h - size);
+    Rect::new(x as Coord, y as Coord, size, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip_and_stepping_visits_every_pattern() {
+        for pattern in Pattern::ALL {
+            assert_eq!(Pattern::parse(pattern.name()), Some(pattern));
+            assert_eq!(pattern.next().prev(), pattern);
+        }
+        assert_eq!(Pattern::parse("plaid"), None);
+        let mut pattern = Pattern::Checkerboard;
+        for _ in 0..Pattern::ALL.len() {
+            pattern = pattern.next();
+        }
+        assert_eq!(pattern, Pattern::Checkerboard);
+    }
+
+    #[test]
+    fn bars_ramps_and_grid_cover_the_panel() {
+        let (w, h) = (240, 320);
+        assert_eq!(Pattern::ColorBars.argb(0, 100, w, h), 0xFFFF_FFFF);
+        assert_eq!(Pattern::ColorBars.argb(w - 1, 100, w, h), 0xFF00_0000);
+        assert_eq!(Pattern::Ramps.argb(0, 0, w, h), 0xFF00_0000);
+        assert_eq!(Pattern::Ramps.argb(w - 1, 0, w, h), 0xFFFF_0000);
+        assert_eq!(Pattern::Ramps.argb(w - 1, h - 1, w, h), 0xFFFF_FFFF);
+        for (x, y) in [(0, 5), (w - 1, 5), (5, 0), (5, h - 1)] {
+            assert_eq!(Pattern::Grid.argb(x, y, w, h), GRID_BORDER);
+        }
+        assert_eq!(Pattern::Grid.argb(GRID_STEP, 5, w, h), GRID_LINE);
+        assert_eq!(Pattern::Grid.argb(5, 5, w, h), GRID_BACKGROUND);
+    }
+
+    #[test]
+    fn rows_within_a_band_match() {
+        let (w, h) = (240, 320);
+        for pattern in Pattern::ALL {
+            let band = pattern.band(h);
+            for y in (0..h).step_by(band as usize) {
+                for row in y..(y + band).min(h) {
+                    for x in (0..w).step_by(7) {
+                        assert_eq!(pattern.argb(x, row, w, h), pattern.argb(x, y, w, h));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn box_bounces_inside_the_panel() {
+        let (w, h) = (240, 320);
+        let screen = Rect::new(0, 0, w, h);
+        let mut edges = (false, false);
+        for frame in 0..1000 {
+            let rect = box_at(frame, w, h);
+            assert_eq!(rect.intersection(&screen), Some(rect));
+            edges.0 |= rect.x as u32 + rect.w == w;
+            edges.1 |= rect.y as u32 + rect.h == h;
+        }
+        assert_eq!(edges, (true, true));
+        assert_ne!(box_at(1, w, h), box_at(0, w, h));
+    }
+}
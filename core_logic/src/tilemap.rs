@@ -0,0 +1,322 @@
+//! The scrolling playfield as columns of tiles
+//!
+//! The world is cut into columns TILE pixels wide, numbered from where the
+//! game started, and slides left under the screen a pixel offset at a time.
+//! Down a column the tiles are sky, pipe body, pipe caps where a pipe ends
+//! at an opening, and the ground along the bottom. The openings come from
+//! the obstacle and can sit on any row, so a column's tiles stack in runs
+//! (`Span`s) from the pipe ends rather than on a fixed row grid. Obstacles
+//! keep to the columns (see `ObstaclePair::wrap`), so a pipe always fills
+//! whole ones.
+//!
+//! Sky looks the same wherever a column of it sits, so drawing only has to
+//! touch the columns that hold pipe and the ones a pipe has just left or
+//! come into; `Shown` remembers which columns were plain sky when last
+//! drawn. The ground moves with every step of the scroll.
+
+use crate::config::*;
+use crate::lane::Lane;
+use crate::obstacle::ObstaclePair;
+use crate::timestep::lerp;
+
+// Columns on screen at once: one more than fit, for the part-columns at
+// either edge
+pub const VISIBLE: usize = (LCD_WIDTH / TILE) as usize + 1;
+// A double obstacle's column: pipe, cap, opening, divider, opening, cap,
+// pipe and ground
+const MAX_SPANS: usize = 8;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Tile {
+    Sky,
+    PipeBody,
+    PipeCap,
+    Ground,
+}
+
+/// Screen rows `top..bottom` of a column, filled with one kind of tile
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub tile: Tile,
+    pub top: Coord,
+    pub bottom: Coord,
+}
+
+/// A column's tiles, top to bottom
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Column {
+    spans: [Option<Span>; MAX_SPANS],
+    len: usize,
+}
+
+impl Column {
+    const fn new() -> Self {
+        Column {
+            spans: [None; MAX_SPANS],
+            len: 0,
+        }
+    }
+
+    pub fn spans(&self) -> impl Iterator<Item = Span> + '_ {
+        self.spans.iter().map_while(|span| *span)
+    }
+
+    // Nothing but sky over the ground, so it looks the same wherever it is
+    pub fn is_plain(&self) -> bool {
+        self.spans()
+            .all(|span| matches!(span.tile, Tile::Sky | Tile::Ground))
+    }
+
+    fn push(&mut self, tile: Tile, top: Coord, bottom: Coord) {
+        if bottom > top && self.len < MAX_SPANS {
+            self.spans[self.len] = Some(Span { tile, top, bottom });
+            self.len += 1;
+        }
+    }
+
+    // Pipe over rows top..bottom, with a cap TILE rows deep at each end
+    // that meets an opening; one too short for its caps is all cap
+    fn push_pipe(&mut self, top: Coord, bottom: Coord, capped_above: bool, capped_below: bool) {
+        let cap = TILE as Coord;
+        let body_top = if capped_above { top + cap } else { top };
+        let body_bottom = if capped_below { bottom - cap } else { bottom };
+        if body_top >= body_bottom {
+            self.push(Tile::PipeCap, top, bottom);
+            return;
+        }
+        if capped_above {
+            self.push(Tile::PipeCap, top, body_top);
+        }
+        self.push(Tile::PipeBody, body_top, body_bottom);
+        if capped_below {
+            self.push(Tile::PipeCap, body_bottom, bottom);
+        }
+    }
+}
+
+/// How far a lane's world has scrolled
+#[derive(Clone)]
+pub struct TileMap {
+    lane: Lane,
+    // World pixels gone past the left edge, now and a tick ago
+    scroll: u32,
+    before: u32,
+}
+
+impl TileMap {
+    pub fn new(lane: Lane) -> Self {
+        TileMap {
+            lane,
+            scroll: 0,
+            before: 0,
+        }
+    }
+
+    // One tick on, `dx` pixels: as far as the obstacle went
+    pub fn advance(&mut self, dx: u32) {
+        self.before = self.scroll;
+        self.scroll += dx;
+    }
+
+    pub fn scroll(&self) -> u32 {
+        self.scroll
+    }
+
+    // The scroll `alpha` of a tick on from a tick ago; see `timestep`
+    pub fn scroll_at(&self, alpha: u8) -> u32 {
+        lerp(self.before as Coord, self.scroll as Coord, alpha) as u32
+    }
+
+    // The world column the left edge of `pair`, where it now is, sits in
+    pub fn obstacle_column(&self, pair: &ObstaclePair) -> u32 {
+        let (x, _) = pair.get_xy_top();
+        (x + self.scroll as Coord) as u32 / TILE
+    }
+
+    // The columns on screen at `scroll`, as (world column, screen x), left
+    // to right
+    pub fn visible(scroll: u32) -> impl Iterator<Item = (u32, Coord)> {
+        let first = scroll / TILE;
+        let last = (scroll + LCD_WIDTH - 1) / TILE;
+        (first..=last).map(move |index| (index, (index * TILE) as Coord - scroll as Coord))
+    }
+
+    // The tiles of world column `index`. `obstacle` is the column the
+    // obstacle starts in and `shown` where its openings are drawn.
+    pub fn column(&self, index: u32, obstacle: u32, shown: &ObstaclePair) -> Column {
+        let lane = self.lane;
+        let field = lane.field();
+        let ground = lane.y(lane.ground);
+        let mut column = Column::new();
+        let pipe_columns = OBSTACLE_WIDTH / TILE;
+        if (obstacle..obstacle + pipe_columns).contains(&index) {
+            let mut y = field.y;
+            for (top, bottom) in shown.gaps().into_iter().flatten() {
+                column.push_pipe(y, top, y != field.y, true);
+                column.push(Tile::Sky, top, bottom);
+                y = bottom;
+            }
+            column.push_pipe(y, ground, true, false);
+        } else {
+            column.push(Tile::Sky, field.y, ground);
+        }
+        column.push(Tile::Ground, ground, lane.y(lane.height));
+        column
+    }
+}
+
+/// Which of the columns on screen were plain sky when last drawn
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Shown {
+    pub scroll: u32,
+    plain: [bool; VISIBLE],
+}
+
+impl Shown {
+    // Nothing noted yet: every column counts as plain
+    pub const fn new(scroll: u32) -> Self {
+        Shown {
+            scroll,
+            plain: [true; VISIBLE],
+        }
+    }
+
+    // Note whether world column `index`, on screen at this scroll, was
+    // plain
+    pub fn note(&mut self, index: u32, plain: bool) {
+        if let Some(slot) = self.slot(index) {
+            self.plain[slot] = plain;
+        }
+    }
+
+    // True if screen columns x..x+w all showed plain sky
+    pub fn was_plain(&self, x: Coord, w: u32) -> bool {
+        let start = x.max(0) as u32;
+        let end = (x + w as Coord).min(LCD_WIDTH as Coord);
+        if end <= start as Coord {
+            return true;
+        }
+        let first = (start + self.scroll) / TILE;
+        let last = (end as u32 - 1 + self.scroll) / TILE;
+        (first..=last).all(|index| self.slot(index).is_none_or(|slot| self.plain[slot]))
+    }
+
+    fn slot(&self, index: u32) -> Option<usize> {
+        let slot = index.checked_sub(self.scroll / TILE)? as usize;
+        (slot < VISIBLE).then_some(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obstacle::ObstacleKind;
+    use crate::scroll;
+    use std::vec::Vec;
+
+    fn tiles(column: &Column) -> Vec<Tile> {
+        column.spans().map(|span| span.tile).collect()
+    }
+
+    #[test]
+    fn visible_columns_cover_the_screen() {
+        let columns: Vec<_> = TileMap::visible(0).collect();
+        assert_eq!(columns.len(), VISIBLE - 1);
+        assert_eq!(columns[0], (0, 0));
+
+        let columns: Vec<_> = TileMap::visible(21).collect();
+        assert_eq!(columns.len(), VISIBLE);
+        assert_eq!(columns[0], (1, -5));
+        let (index, x) = *columns.last().unwrap();
+        assert!(x < LCD_WIDTH as Coord && x + TILE as Coord >= LCD_WIDTH as Coord);
+        assert_eq!(index, 16);
+    }
+
+    #[test]
+    fn scroll_follows_the_speed_between_ticks() {
+        let mut map = TileMap::new(Lane::FULL);
+        map.advance(4);
+        map.advance(4);
+        assert_eq!(map.scroll(), 8);
+        assert_eq!(map.scroll_at(0), 4);
+        assert_eq!(map.scroll_at(128), 6);
+    }
+
+    #[test]
+    fn obstacle_stays_in_its_columns_as_it_scrolls() {
+        let mut map = TileMap::new(Lane::FULL);
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_velocity(scroll::parse("2.1").unwrap());
+        let start = map.obstacle_column(&pair);
+        assert_eq!(start, LCD_END as u32 / TILE);
+        for _ in 0..50 {
+            map.advance(pair.advance());
+            assert_eq!(map.obstacle_column(&pair), start);
+        }
+    }
+
+    #[test]
+    fn static_pipe_column_caps_the_opening() {
+        let map = TileMap::new(Lane::FULL);
+        let pair = ObstaclePair::new(Lane::FULL);
+        let column = map.column(15, 15, &pair);
+        use Tile::*;
+        assert_eq!(
+            tiles(&column),
+            [PipeBody, PipeCap, Sky, PipeCap, PipeBody, Ground]
+        );
+        let (_, top, bottom) = pair.get_gap();
+        let sky = column.spans().find(|span| span.tile == Sky).unwrap();
+        assert_eq!((sky.top, sky.bottom), (top, bottom));
+        assert!(!column.is_plain());
+    }
+
+    #[test]
+    fn spans_cover_the_lane_without_gaps() {
+        let map = TileMap::new(Lane::FULL);
+        for kind in [
+            ObstacleKind::Static,
+            ObstacleKind::Moving,
+            ObstacleKind::Double,
+            ObstacleKind::Spike,
+            ObstacleKind::Bar,
+        ] {
+            let mut pair = ObstaclePair::new(Lane::FULL);
+            pair.set_kind(kind);
+            for index in [14, 15, 16, 17] {
+                let column = map.column(index, 15, &pair);
+                let mut y = Lane::FULL.field().y;
+                for span in column.spans() {
+                    assert_eq!(span.top, y);
+                    y = span.bottom;
+                }
+                assert_eq!(y, Lane::FULL.y(Lane::FULL.height));
+            }
+        }
+        let mut pair = ObstaclePair::new(Lane::FULL);
+        pair.set_kind(ObstacleKind::Double);
+        assert_eq!(map.column(15, 15, &pair).spans().count(), MAX_SPANS);
+    }
+
+    #[test]
+    fn other_columns_are_plain_sky() {
+        let map = TileMap::new(Lane::SPLIT[1]);
+        let pair = ObstaclePair::new(Lane::SPLIT[1]);
+        let column = map.column(3, 15, &pair);
+        assert_eq!(tiles(&column), [Tile::Sky, Tile::Ground]);
+        assert!(column.is_plain());
+    }
+
+    #[test]
+    fn shown_remembers_pipe_columns_by_screen_position() {
+        let mut shown = Shown::new(20);
+        // Column 3 sat at screen x 28..44
+        shown.note(3, false);
+        assert!(shown.was_plain(0, 28));
+        assert!(!shown.was_plain(27, 2));
+        assert!(!shown.was_plain(43, 1));
+        assert!(shown.was_plain(44, 16));
+        // Off the screen is never drawn on
+        assert!(shown.was_plain(-20, 10));
+    }
+}
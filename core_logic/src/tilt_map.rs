@@ -0,0 +1,268 @@
+//! How the tilt sensor's reading steers the bird
+//!
+//! The accelerometer gives three axes; which one is up and down on screen
+//! depends on how the board is mounted. Flat on a desk, as it always was,
+//! that is Y. Stood on its edge it is Z, and on its side X; upside down the
+//! axis reads the other way round (`invert`). The reading on that axis,
+//! resting offset already taken off, is then shaped before it places the
+//! bird: within `dead_zone` counts of rest it counts as rest, so a hand
+//! that is not quite still does not jitter the bird, and the rest of the
+//! range is stretched to make up the full scale. The `Expo` curve makes
+//! small tilts gentler and keeps the full reach, for finer control around
+//! the middle.
+//!
+//! Whether a tilt is far enough to flap is judged on the chosen axis too,
+//! before any shaping.
+
+use crate::lang::Msg;
+
+/// Accelerometer axis, in the sensor's x, y, z order
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Axis> {
+        Axis::ALL.into_iter().find(|axis| axis.name() == text)
+    }
+
+    // Its name on the settings page
+    pub fn msg(self) -> Msg {
+        match self {
+            Axis::X => Msg::AxisX,
+            Axis::Y => Msg::AxisY,
+            Axis::Z => Msg::AxisZ,
+        }
+    }
+
+    // The next one in ALL, for the settings page to step through
+    pub fn next(self) -> Self {
+        Axis::ALL[(self as usize + 1) % Axis::ALL.len()]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Curve {
+    Linear = 0,
+    // Half linear, half cubic
+    Expo = 1,
+}
+
+impl Curve {
+    pub const ALL: [Curve; 2] = [Curve::Linear, Curve::Expo];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Linear => "linear",
+            Curve::Expo => "expo",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Curve> {
+        Curve::ALL.into_iter().find(|curve| curve.name() == text)
+    }
+
+    pub fn msg(self) -> Msg {
+        match self {
+            Curve::Linear => Msg::Linear,
+            Curve::Expo => Msg::Expo,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        Curve::ALL[(self as usize + 1) % Curve::ALL.len()]
+    }
+}
+
+// Full scale of a reading, 2 g at +-2 g
+pub const FULL_SCALE: i32 = 32767;
+// Widest dead zone, a quarter of the scale
+pub const MAX_DEAD_ZONE: u16 = 8192;
+// What one pick on the settings page widens the dead zone by
+pub const DEAD_ZONE_STEP: u16 = 1024;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TiltMap {
+    pub axis: Axis,
+    pub invert: bool,
+    // Counts either side of rest that count as rest
+    pub dead_zone: u16,
+    pub curve: Curve,
+}
+
+impl TiltMap {
+    // The board flat, as the game has always read it
+    pub const DEFAULT: TiltMap = TiltMap {
+        axis: Axis::Y,
+        invert: false,
+        dead_zone: 0,
+        curve: Curve::Linear,
+    };
+
+    // The dead zone a step wider, back to none after the widest; one set
+    // off the steps from the shell goes to the next step up
+    pub fn next_dead_zone(dead_zone: u16) -> u16 {
+        if dead_zone >= MAX_DEAD_ZONE {
+            0
+        } else {
+            (dead_zone / DEAD_ZONE_STEP + 1) * DEAD_ZONE_STEP
+        }
+    }
+
+    // The chosen axis of `reading` (x, y, z), the right way round
+    pub fn pick(&self, reading: [i32; 3]) -> i32 {
+        let value = reading[self.axis as usize];
+        if self.invert {
+            value.saturating_neg()
+        } else {
+            value
+        }
+    }
+
+    // `value` from `pick` through the dead zone and the curve, still
+    // -FULL_SCALE..=FULL_SCALE
+    pub fn shape(&self, value: i32) -> i32 {
+        let value = value.clamp(-FULL_SCALE, FULL_SCALE);
+        let dead = self.dead_zone.min(MAX_DEAD_ZONE) as i32;
+        let past = value.abs() - dead;
+        if past <= 0 {
+            return 0;
+        }
+        let linear = past as i64 * FULL_SCALE as i64 / (FULL_SCALE - dead) as i64;
+        let shaped = match self.curve {
+            Curve::Linear => linear,
+            Curve::Expo => {
+                let full = FULL_SCALE as i64;
+                (linear + linear * linear * linear / (full * full)) / 2
+            }
+        };
+        shaped as i32 * value.signum()
+    }
+
+    // Packed into a settings word: axis in bits 0-1, invert bit 2, curve
+    // bit 3, dead zone in the top half
+    pub fn to_u32(self) -> u32 {
+        self.axis as u32
+            | (self.invert as u32) << 2
+            | (self.curve as u32) << 3
+            | (self.dead_zone as u32) << 16
+    }
+
+    pub fn from_u32(word: u32) -> Option<Self> {
+        let axis = *Axis::ALL.get((word & 0x3) as usize)?;
+        let curve = *Curve::ALL.get((word >> 3 & 0x1) as usize)?;
+        let dead_zone = (word >> 16) as u16;
+        if word & 0xFFF0 != 0 || dead_zone > MAX_DEAD_ZONE {
+            return None;
+        }
+        Some(TiltMap {
+            axis,
+            invert: word & 1 << 2 != 0,
+            dead_zone,
+            curve,
+        })
+    }
+}
+
+impl Default for TiltMap {
+    fn default() -> Self {
+        TiltMap::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_and_inverts_the_axis() {
+        let reading = [100, -200, 300];
+        assert_eq!(TiltMap::DEFAULT.pick(reading), -200);
+        let edge = TiltMap {
+            axis: Axis::Z,
+            invert: true,
+            ..TiltMap::DEFAULT
+        };
+        assert_eq!(edge.pick(reading), -300);
+        assert_eq!(edge.pick([0, 0, i32::MIN]), i32::MAX);
+        for axis in Axis::ALL {
+            assert_eq!(Axis::parse(axis.name()), Some(axis));
+        }
+    }
+
+    #[test]
+    fn dead_zone_holds_rest_and_keeps_the_full_reach() {
+        let map = TiltMap {
+            dead_zone: 2000,
+            ..TiltMap::DEFAULT
+        };
+        assert_eq!(map.shape(1999), 0);
+        assert_eq!(map.shape(-2000), 0);
+        assert!(map.shape(2100) > 0);
+        assert!(map.shape(-2100) < 0);
+        assert_eq!(map.shape(FULL_SCALE), FULL_SCALE);
+        assert_eq!(map.shape(-40000), -FULL_SCALE);
+        assert_eq!(TiltMap::DEFAULT.shape(1234), 1234);
+    }
+
+    #[test]
+    fn expo_is_gentler_in_the_middle() {
+        let expo = TiltMap {
+            curve: Curve::Expo,
+            ..TiltMap::DEFAULT
+        };
+        let mut last = 0;
+        for value in (0..=FULL_SCALE).step_by(1024) {
+            let shaped = expo.shape(value);
+            assert!(shaped <= value);
+            assert!(shaped >= last);
+            assert_eq!(expo.shape(-value), -shaped);
+            last = shaped;
+        }
+        assert!(expo.shape(FULL_SCALE / 4) < FULL_SCALE / 4 * 2 / 3);
+        assert_eq!(expo.shape(FULL_SCALE), FULL_SCALE);
+    }
+
+    #[test]
+    fn steps_through_the_choices_and_wraps() {
+        assert_eq!(Axis::Z.next(), Axis::X);
+        assert_eq!(Curve::Expo.next(), Curve::Linear);
+        let mut dead_zone = 0;
+        for _ in 0..MAX_DEAD_ZONE / DEAD_ZONE_STEP {
+            dead_zone = TiltMap::next_dead_zone(dead_zone);
+        }
+        assert_eq!(dead_zone, MAX_DEAD_ZONE);
+        assert_eq!(TiltMap::next_dead_zone(MAX_DEAD_ZONE), 0);
+        assert_eq!(TiltMap::next_dead_zone(1500), 2048);
+    }
+
+    #[test]
+    fn round_trips_through_a_settings_word() {
+        let map = TiltMap {
+            axis: Axis::X,
+            invert: true,
+            dead_zone: 1500,
+            curve: Curve::Expo,
+        };
+        assert_eq!(TiltMap::from_u32(map.to_u32()), Some(map));
+        assert_eq!(
+            TiltMap::from_u32(TiltMap::DEFAULT.to_u32()),
+            Some(TiltMap::DEFAULT)
+        );
+        assert_eq!(TiltMap::from_u32(3), None);
+        assert_eq!(TiltMap::from_u32((MAX_DEAD_ZONE as u32 + 1) << 16), None);
+    }
+}
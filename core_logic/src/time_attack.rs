@@ -0,0 +1,112 @@
+//! Time attack: as many points as can be had in a minute
+//!
+//! The run has `LIMIT_S` seconds of game time and no lives to lose. A crash
+//! takes `PENALTY_S` seconds off what is left and puts the bird back at the
+//! last obstacle passed, as in practice (see `practice`), keeping the
+//! points already scored; for the first few ticks back nothing counts as a
+//! crash. The run ends when the clock runs out. `Clock` counts in the
+//! game's logic ticks, so a pause or a hit-stop stops it too.
+
+use crate::practice::INVULNERABLE_TICKS;
+
+pub const LIMIT_S: u32 = 60;
+pub const PENALTY_S: u32 = 3;
+// Seconds left from which the timer is drawn as running out
+pub const LOW_S: u32 = 10;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Clock {
+    // Logic ticks a second
+    hz: u32,
+    // Ticks left to play
+    left: u32,
+    crashes: u32,
+    // Ticks of invulnerability left
+    invulnerable: u32,
+}
+
+impl Clock {
+    // A run's clock, for a game ticking `hz` times a second
+    pub const fn new(hz: u32) -> Self {
+        Clock {
+            hz,
+            left: LIMIT_S * hz,
+            crashes: 0,
+            invulnerable: 0,
+        }
+    }
+
+    // Whole seconds left, rounded up so the last one shows as 1 and not 0
+    pub fn seconds_left(&self) -> u32 {
+        self.left.div_ceil(self.hz.max(1))
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.left == 0
+    }
+
+    pub fn crashes(&self) -> u32 {
+        self.crashes
+    }
+
+    // One tick on; true if a crash this tick counts
+    pub fn tick(&mut self) -> bool {
+        self.left = self.left.saturating_sub(1);
+        if self.invulnerable > 0 {
+            self.invulnerable -= 1;
+            return false;
+        }
+        true
+    }
+
+    // The bird crashed and is going back to the last obstacle passed
+    pub fn crashed(&mut self) {
+        self.crashes += 1;
+        self.left = self.left.saturating_sub(PENALTY_S * self.hz);
+        self.invulnerable = INVULNERABLE_TICKS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_out_after_the_limit() {
+        let mut clock = Clock::new(60);
+        assert_eq!(clock.seconds_left(), LIMIT_S);
+        for _ in 0..LIMIT_S * 60 - 1 {
+            clock.tick();
+        }
+        assert_eq!(clock.seconds_left(), 1);
+        assert!(!clock.is_up());
+        clock.tick();
+        assert!(clock.is_up());
+        assert_eq!(clock.seconds_left(), 0);
+        clock.tick();
+        assert!(clock.is_up());
+    }
+
+    #[test]
+    fn a_crash_costs_time_and_buys_a_moment_of_safety() {
+        let mut clock = Clock::new(60);
+        assert!(clock.tick());
+        clock.crashed();
+        assert_eq!(clock.crashes(), 1);
+        assert_eq!(clock.seconds_left(), LIMIT_S - PENALTY_S);
+        for _ in 0..INVULNERABLE_TICKS {
+            assert!(!clock.tick());
+        }
+        assert!(clock.tick());
+    }
+
+    #[test]
+    fn crashing_late_ends_the_run() {
+        let mut clock = Clock::new(60);
+        for _ in 0..(LIMIT_S - 2) * 60 {
+            clock.tick();
+        }
+        clock.crashed();
+        assert!(clock.is_up());
+    }
+}
@@ -0,0 +1,224 @@
+//! Fixed-rate game logic, whatever the render rate
+//!
+//! The game steps its logic in whole ticks of `1 / hz` seconds: each frame
+//! asks `advance` how many ticks have come due since the last, runs that
+//! many and draws once. Every per-frame speed in `config` (scroll, flap,
+//! gravity) is per tick, so the game plays at the same pace whether a frame
+//! takes one tick or three. Between ticks `alpha` says how far on the clock
+//! is, and drawing blends the last two positions with `lerp` so motion
+//! stays smooth when frames and ticks don't line up.
+//!
+//! The clock can also run slow or stand still without the game knowing:
+//! `set_scale` plays it at a fraction of real time (slow motion) and
+//! `hold` lets a few ticks' worth of real time go by unplayed (a hit-stop).
+//! Animations timed in milliseconds follow the same clock through
+//! `played_ms`.
+
+use crate::config::Coord;
+
+// Time scale of real time, in 1/FULL_SPEED
+pub const FULL_SPEED: u32 = 256;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FixedStep {
+    step_us: u64,
+    max_steps: u32,
+    // Clock at the last call, None until the first after a reset
+    last_us: Option<u64>,
+    // Time not yet spent on ticks, under one tick
+    pending_us: u64,
+    // Game time per real time, in 1/FULL_SPEED
+    scale: u32,
+    // Real time still to let pass unplayed
+    hold_us: u64,
+    // Game time played in all
+    played_us: u64,
+}
+
+impl FixedStep {
+    // `hz` ticks per second; a frame never runs more than `max_steps`, so
+    // a long stall (flash erase, debugger) drops time instead of racing to
+    // catch up
+    pub const fn new(hz: u32, max_steps: u32) -> Self {
+        FixedStep {
+            step_us: 1_000_000 / hz as u64,
+            max_steps,
+            last_us: None,
+            pending_us: 0,
+            scale: FULL_SPEED,
+            hold_us: 0,
+            played_us: 0,
+        }
+    }
+
+    // Forget the time so far, after the game has stood still (countdown,
+    // pause menu); the next `advance` starts counting from then
+    pub fn reset(&mut self) {
+        self.last_us = None;
+        self.pending_us = 0;
+        self.hold_us = 0;
+    }
+
+    // Play `scale` / FULL_SPEED of real time from now on
+    pub fn set_scale(&mut self, scale: u32) {
+        self.scale = scale;
+    }
+
+    // Stand still for the next `ticks` ticks of real time; the frame drawn
+    // stays where it is
+    pub fn hold(&mut self, ticks: u32) {
+        self.hold_us = self.hold_us.max(ticks as u64 * self.step_us);
+    }
+
+    // Whether a hold is still going
+    pub fn is_held(&self) -> bool {
+        self.hold_us > 0
+    }
+
+    // Milliseconds of game time played, to time animations by
+    pub fn played_ms(&self) -> u32 {
+        (self.played_us / 1000) as u32
+    }
+
+    // Ticks due at `now_us`, a microsecond clock
+    pub fn advance(&mut self, now_us: u64) -> u32 {
+        let Some(last) = self.last_us.replace(now_us) else {
+            return 0;
+        };
+        let mut real_us = now_us.saturating_sub(last);
+        let held = real_us.min(self.hold_us);
+        self.hold_us -= held;
+        real_us -= held;
+        let played = real_us * self.scale as u64 / FULL_SPEED as u64;
+        self.played_us += played;
+        self.pending_us += played;
+        let due = self.pending_us / self.step_us;
+        self.pending_us %= self.step_us;
+        if due > self.max_steps as u64 {
+            self.pending_us = 0;
+            return self.max_steps;
+        }
+        due as u32
+    }
+
+    // How far the clock is from the last tick to the next, 0..=255
+    pub fn alpha(&self) -> u8 {
+        (self.pending_us * 255 / self.step_us) as u8
+    }
+}
+
+// `alpha` of the way from `from` to `to`; 255 lands on `to`
+pub fn lerp(from: Coord, to: Coord, alpha: u8) -> Coord {
+    from + (to - from) * alpha as Coord / 255
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_call_only_starts_the_clock() {
+        let mut step = FixedStep::new(60, 4);
+        assert_eq!(step.advance(1_000_000), 0);
+        assert_eq!(step.alpha(), 0);
+    }
+
+    #[test]
+    fn counts_whole_ticks_and_carries_the_rest() {
+        let mut step = FixedStep::new(100, 4);
+        step.advance(0);
+        assert_eq!(step.advance(5_000), 0);
+        assert_eq!(step.alpha(), 127);
+        assert_eq!(step.advance(25_000), 2);
+        assert_eq!(step.alpha(), 127);
+        assert_eq!(step.advance(30_000), 1);
+        assert_eq!(step.alpha(), 0);
+    }
+
+    #[test]
+    fn same_pace_at_any_frame_rate() {
+        for frame_us in [4_000, 16_667, 33_333, 50_000] {
+            let mut step = FixedStep::new(60, 4);
+            let mut ticks = 0;
+            let mut now = 0;
+            step.advance(now);
+            while now < 1_000_000 {
+                now += frame_us;
+                ticks += step.advance(now);
+            }
+            assert!((60..=62).contains(&ticks), "{frame_us} us: {ticks}");
+        }
+    }
+
+    #[test]
+    fn a_stall_is_dropped_not_replayed() {
+        let mut step = FixedStep::new(60, 4);
+        step.advance(0);
+        assert_eq!(step.advance(2_000_000), 4);
+        assert_eq!(step.alpha(), 0);
+        assert_eq!(step.advance(2_010_000), 0);
+    }
+
+    #[test]
+    fn reset_forgets_the_gap() {
+        let mut step = FixedStep::new(60, 4);
+        step.advance(0);
+        step.reset();
+        assert_eq!(step.advance(1_000_000), 0);
+        assert_eq!(step.advance(1_016_666), 1);
+    }
+
+    #[test]
+    fn a_quarter_scale_plays_a_quarter_of_the_ticks() {
+        let mut step = FixedStep::new(100, 4);
+        step.set_scale(FULL_SPEED / 4);
+        step.advance(0);
+        let ticks: u32 = (1..=100).map(|ms| step.advance(ms * 10_000)).sum();
+        assert_eq!(ticks, 25);
+        assert_eq!(step.played_ms(), 250);
+    }
+
+    #[test]
+    fn hold_stands_still_then_carries_on() {
+        let mut step = FixedStep::new(100, 4);
+        step.advance(0);
+        step.advance(15_000);
+        let alpha = step.alpha();
+        step.hold(3);
+        assert!(step.is_held());
+        assert_eq!(step.advance(25_000), 0);
+        assert_eq!(step.advance(40_000), 0);
+        assert_eq!(step.alpha(), alpha);
+        // The last 5 ms of the hold, then 5 ms played
+        assert_eq!(step.advance(45_000), 0);
+        assert!(!step.is_held());
+        assert_eq!(step.advance(50_000), 1);
+        assert_eq!(step.played_ms(), 20);
+    }
+
+    #[test]
+    fn holds_do_not_stack() {
+        let mut step = FixedStep::new(100, 4);
+        step.advance(0);
+        step.hold(3);
+        step.hold(2);
+        assert_eq!(step.advance(30_000), 0);
+        assert_eq!(step.advance(40_000), 1);
+    }
+
+    #[test]
+    fn reset_drops_a_hold() {
+        let mut step = FixedStep::new(100, 4);
+        step.hold(3);
+        step.reset();
+        step.advance(0);
+        assert_eq!(step.advance(10_000), 1);
+    }
+
+    #[test]
+    fn lerp_ends_on_both_positions() {
+        assert_eq!(lerp(10, 20, 0), 10);
+        assert_eq!(lerp(10, 20, 255), 20);
+        assert_eq!(lerp(20, 10, 128), 15);
+    }
+}
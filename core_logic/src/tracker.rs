@@ -0,0 +1,241 @@
+//! Background music as a tiny tracker
+//!
+//! A song is const data: a tick length and a list of steps, each a note, how
+//! many ticks it lasts and the channel it plays on. The two channels read
+//! the same list, each taking only its own steps in order, so they run side
+//! by side and loop on their own when they reach the end. The melody is a
+//! square wave at the note's pitch, cut short an eighth of the way from the
+//! end so repeated notes stand apart. The noise channel is a 15-bit LFSR
+//! clocked at the note's pitch, higher notes hissing brighter, fading out
+//! over the step like a drum. A note of `REST` is silence on either.
+//!
+//! Notes are MIDI numbers, 60 middle C and 69 the 440 Hz A. `Player` turns
+//! a song into one mono sample at a time, at whatever rate the output runs.
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Channel {
+    Melody = 0,
+    Noise = 1,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Step {
+    pub note: u8,
+    // In the song's ticks
+    pub duration: u8,
+    pub channel: Channel,
+}
+
+// Silence for the step's duration
+pub const REST: u8 = 0;
+
+// Loudest each channel gets; the music sits under the sound effects
+pub const MELODY_LEVEL: i32 = 3000;
+pub const NOISE_LEVEL: i32 = 2000;
+
+pub struct Song {
+    pub tick_ms: u32,
+    pub steps: &'static [Step],
+}
+
+// Shorthand for writing songs out
+pub const fn melody(note: u8, duration: u8) -> Step {
+    Step {
+        note,
+        duration,
+        channel: Channel::Melody,
+    }
+}
+
+pub const fn noise(note: u8, duration: u8) -> Step {
+    Step {
+        note,
+        duration,
+        channel: Channel::Noise,
+    }
+}
+
+// C5 up to B5 in hundredths of a hertz
+const OCTAVE_CENTI_HZ: [u64; 12] = [
+    52325, 55437, 58733, 62225, 65926, 69846, 73999, 78399, 83061, 88000, 93233, 98777,
+];
+
+// Phase added each sample, out of 2^32 a cycle, for `note` at `rate`
+pub fn phase_step(note: u8, rate: u32) -> u32 {
+    let (octave, degree) = (note as i32 / 12, note as usize % 12);
+    // C5 is MIDI 72, octave 6
+    let shift = octave - 6;
+    let centi_hz = if shift >= 0 {
+        OCTAVE_CENTI_HZ[degree] << shift
+    } else {
+        OCTAVE_CENTI_HZ[degree] >> -shift
+    };
+    ((centi_hz << 32) / (100 * rate.max(1) as u64)).min(u32::MAX as u64) as u32
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Voice {
+    // Index of the step playing in the song
+    at: Option<usize>,
+    // Samples of it played, and how many it lasts
+    played: u32,
+    length: u32,
+    phase: u32,
+    step: u32,
+    lfsr: u16,
+    noise_bit: bool,
+}
+
+pub struct Player {
+    song: &'static Song,
+    voices: [Voice; 2],
+}
+
+impl Player {
+    pub fn new(song: &'static Song) -> Self {
+        let voice = Voice {
+            lfsr: 1,
+            ..Voice::default()
+        };
+        Player {
+            song,
+            voices: [voice; 2],
+        }
+    }
+
+    // The step after `from` on `channel`, looping; None if it has none
+    fn next_step(&self, channel: Channel, from: Option<usize>) -> Option<usize> {
+        let steps = self.song.steps;
+        let start = from.map_or(0, |at| at + 1);
+        (0..steps.len())
+            .map(|i| (start + i) % steps.len())
+            .find(|&i| steps[i].channel == channel)
+    }
+
+    // The next sample of the song at `rate` samples a second
+    pub fn next(&mut self, rate: u32) -> i16 {
+        let mut sum = 0;
+        for channel in [Channel::Melody, Channel::Noise] {
+            sum += self.channel_sample(channel, rate);
+        }
+        sum.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    }
+
+    fn channel_sample(&mut self, channel: Channel, rate: u32) -> i32 {
+        let mut voice = self.voices[channel as usize];
+        if voice.at.is_none() || voice.played >= voice.length {
+            let Some(at) = self.next_step(channel, voice.at) else {
+                return 0;
+            };
+            let step = self.song.steps[at];
+            voice.at = Some(at);
+            voice.played = 0;
+            voice.length = (rate as u64 * self.song.tick_ms as u64 * step.duration as u64 / 1000)
+                .max(1) as u32;
+            voice.step = phase_step(step.note, rate);
+        }
+        let note = self.song.steps[voice.at.unwrap_or(0)].note;
+        let sample = if note == REST {
+            0
+        } else {
+            match channel {
+                Channel::Melody => {
+                    let held = voice.played < voice.length - voice.length / 8;
+                    let high = voice.phase < 1 << 31;
+                    match (held, high) {
+                        (false, _) => 0,
+                        (true, true) => MELODY_LEVEL,
+                        (true, false) => -MELODY_LEVEL,
+                    }
+                }
+                Channel::Noise => {
+                    let left = (voice.length - voice.played) as i64;
+                    let level = NOISE_LEVEL as i64 * left / voice.length as i64;
+                    if voice.noise_bit {
+                        level as i32
+                    } else {
+                        -level as i32
+                    }
+                }
+            }
+        };
+        let (phase, wrapped) = voice.phase.overflowing_add(voice.step);
+        voice.phase = phase;
+        if wrapped {
+            let bit = (voice.lfsr ^ (voice.lfsr >> 1)) & 1;
+            voice.lfsr = (voice.lfsr >> 1) | (bit << 14);
+            voice.noise_bit = voice.lfsr & 1 != 0;
+        }
+        voice.played += 1;
+        self.voices[channel as usize] = voice;
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SONG: Song = Song {
+        tick_ms: 10,
+        steps: &[melody(69, 2), noise(60, 1), melody(REST, 1), noise(REST, 1)],
+    };
+    static MELODY_ONLY: Song = Song {
+        tick_ms: 10,
+        steps: &[melody(69, 2), melody(REST, 1)],
+    };
+    static ARTICULATED: Song = Song {
+        tick_ms: 8,
+        steps: &[melody(69, 1), melody(REST, 1)],
+    };
+    static SILENT: Song = Song {
+        tick_ms: 10,
+        steps: &[melody(REST, 4)],
+    };
+    const RATE: u32 = 8000;
+
+    #[test]
+    fn a_is_440_hz() {
+        let step = phase_step(69, RATE) as u64;
+        // 440 cycles a second, give or take rounding
+        let cycles = (step * RATE as u64) >> 32;
+        assert_eq!(cycles, 439);
+        assert_eq!(phase_step(81, RATE), 2 * phase_step(69, RATE));
+        assert_eq!(phase_step(57, RATE), phase_step(69, RATE) / 2);
+    }
+
+    #[test]
+    fn channels_play_side_by_side_and_loop() {
+        let mut player = Player::new(&SONG);
+        let mut melody_only = Player::new(&MELODY_ONLY);
+        let tick = (RATE * 10 / 1000) as usize;
+        let mut differs = [false; 6];
+        for i in 0..6 * tick {
+            let both = player.next(RATE);
+            let alone = melody_only.next(RATE);
+            differs[i / tick] |= both != alone;
+        }
+        // The melody loops every 3 ticks, the noise every 2, sounding in
+        // the first
+        assert_eq!(differs, [true, false, true, false, true, false]);
+    }
+
+    #[test]
+    fn melody_articulates_and_rests_are_silent() {
+        let mut player = Player::new(&ARTICULATED);
+        let tick = (RATE * 8 / 1000) as usize;
+        let samples: [i16; 192] = core::array::from_fn(|_| player.next(RATE));
+        assert!(samples[..tick * 7 / 8]
+            .iter()
+            .all(|s| s.unsigned_abs() as i32 == MELODY_LEVEL));
+        assert!(samples[tick * 7 / 8 + 1..tick].iter().all(|&s| s == 0));
+        assert!(samples[tick..2 * tick].iter().all(|&s| s == 0));
+        assert_ne!(samples[2 * tick], 0);
+    }
+
+    #[test]
+    fn a_song_without_noise_leaves_it_silent() {
+        let mut player = Player::new(&SILENT);
+        assert!((0..1000).all(|_| player.next(RATE) == 0));
+    }
+}
@@ -0,0 +1,232 @@
+//! Gameplay numbers that can be changed on the board
+//!
+//! The config constants are where a game starts; `TuningParams` holds the
+//! ones worth trying other values of without reflashing: how fast the bird
+//! sinks and how far a flap lifts it, how tall the obstacles' opening is,
+//! how much the obstacles' speed is scaled from the difficulty's, and how
+//! far the bird's hitbox is pulled in from its body. Each has a range and
+//! a name the shell knows it by. The whole set packs into one settings
+//! word, a byte or two nibbles per value.
+
+use crate::config::*;
+use crate::lane::Lane;
+
+/// One of the tunable values
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Param {
+    Gravity,
+    Flap,
+    Gap,
+    Speed,
+    Inset,
+}
+
+impl Param {
+    pub const ALL: [Param; 5] = [
+        Param::Gravity,
+        Param::Flap,
+        Param::Gap,
+        Param::Speed,
+        Param::Inset,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::Gravity => "gravity",
+            Param::Flap => "flap",
+            Param::Gap => "gap",
+            Param::Speed => "speed",
+            Param::Inset => "inset",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Param::ALL.into_iter().find(|param| param.name() == name)
+    }
+
+    // Values it takes, inclusive
+    pub fn range(self) -> (i32, i32) {
+        match self {
+            Param::Gravity => (-8, 7),
+            Param::Flap => (1, 60),
+            // Room for the bird at the least, and pipe left at the most
+            Param::Gap => (PLAYER_HEIGHT as i32 + 4, 120),
+            Param::Speed => (25, 250),
+            Param::Inset => (0, 12),
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Param::Gravity | Param::Flap => "px/frame",
+            Param::Gap | Param::Inset => "px",
+            Param::Speed => "%",
+        }
+    }
+}
+
+/// A value outside its parameter's range
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct OutOfRange;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TuningParams {
+    // Rows the bird sinks a frame when the input leaves it where it is
+    pub gravity: Coord,
+    // Rows a button flap lifts the bird
+    pub flap_lift: Coord,
+    // Height of a single opening in the full-screen lane
+    pub gap: Coord,
+    // Obstacle speed in percent of the difficulty's
+    pub speed_percent: u32,
+    // Pixels off each side of the bird's hitbox, on top of any power-up's
+    pub hitbox_inset: u32,
+}
+
+impl TuningParams {
+    pub const DEFAULT: TuningParams = TuningParams {
+        gravity: GRAVITY,
+        flap_lift: FLAP_LIFT,
+        gap: OBSTACLE_GAP as Coord,
+        speed_percent: 100,
+        hitbox_inset: 0,
+    };
+
+    pub fn get(&self, param: Param) -> i32 {
+        match param {
+            Param::Gravity => self.gravity,
+            Param::Flap => self.flap_lift,
+            Param::Gap => self.gap,
+            Param::Speed => self.speed_percent as i32,
+            Param::Inset => self.hitbox_inset as i32,
+        }
+    }
+
+    pub fn set(&mut self, param: Param, value: i32) -> Result<(), OutOfRange> {
+        let (min, max) = param.range();
+        if !(min..=max).contains(&value) {
+            return Err(OutOfRange);
+        }
+        match param {
+            Param::Gravity => self.gravity = value,
+            Param::Flap => self.flap_lift = value,
+            Param::Gap => self.gap = value,
+            Param::Speed => self.speed_percent = value as u32,
+            Param::Inset => self.hitbox_inset = value as u32,
+        }
+        Ok(())
+    }
+
+    // `velocity` (see `scroll`) scaled by the speed, at least a subpixel
+    pub fn velocity(&self, velocity: u32) -> u32 {
+        (velocity * self.speed_percent / 100).max(1)
+    }
+
+    // `lane` with its opening resized to the gap about the same middle
+    pub fn lane(&self, lane: Lane) -> Lane {
+        let middle = (lane.gap_top + lane.gap_bottom) / 2;
+        let gap_top = middle - self.gap / 2;
+        Lane {
+            gap_top,
+            gap_bottom: gap_top + self.gap,
+            ..lane
+        }
+    }
+
+    // Gravity in the low nibble, two's complement, and the inset in the
+    // next, then a byte each for flap, gap and speed
+    pub fn to_u32(self) -> u32 {
+        (self.gravity as u32 & 0xF)
+            | (self.hitbox_inset & 0xF) << 4
+            | (self.flap_lift as u32 & 0xFF) << 8
+            | (self.gap as u32 & 0xFF) << 16
+            | (self.speed_percent & 0xFF) << 24
+    }
+
+    pub fn from_u32(word: u32) -> Option<Self> {
+        let nibble = |shift: u32| (word >> shift & 0xF) as i32;
+        let byte = |shift: u32| (word >> shift & 0xFF) as i32;
+        let mut params = TuningParams::DEFAULT;
+        // Sign-extend the gravity's nibble
+        let gravity = (nibble(0) << 28) >> 28;
+        let values = [gravity, byte(8), byte(16), byte(24), nibble(4)];
+        for (param, value) in Param::ALL.into_iter().zip(values) {
+            params.set(param, value).ok()?;
+        }
+        Some(params)
+    }
+}
+
+impl Default for TuningParams {
+    fn default() -> Self {
+        TuningParams::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_leave_the_game_as_it_was() {
+        let tuning = TuningParams::DEFAULT;
+        assert_eq!(tuning.lane(Lane::FULL), Lane::FULL);
+        for lane in Lane::SPLIT {
+            assert_eq!(tuning.lane(lane), lane);
+        }
+        assert_eq!(tuning.velocity(512), 512);
+        for param in Param::ALL {
+            let (min, max) = param.range();
+            assert!((min..=max).contains(&tuning.get(param)), "{:?}", param);
+        }
+    }
+
+    #[test]
+    fn values_outside_their_range_are_refused() {
+        let mut tuning = TuningParams::DEFAULT;
+        assert_eq!(tuning.set(Param::Gravity, 8), Err(OutOfRange));
+        assert_eq!(
+            tuning.set(Param::Gap, PLAYER_HEIGHT as i32),
+            Err(OutOfRange)
+        );
+        assert_eq!(tuning, TuningParams::DEFAULT);
+        assert_eq!(tuning.set(Param::Gravity, -3), Ok(()));
+        assert_eq!(tuning.gravity, -3);
+    }
+
+    #[test]
+    fn packs_into_a_word_and_back() {
+        assert_eq!(
+            TuningParams::from_u32(TuningParams::DEFAULT.to_u32()),
+            Some(TuningParams::DEFAULT)
+        );
+        let mut tuning = TuningParams::DEFAULT;
+        for (param, value) in Param::ALL.into_iter().zip([-8, 60, 34, 250, 12]) {
+            tuning.set(param, value).unwrap();
+        }
+        assert_eq!(TuningParams::from_u32(tuning.to_u32()), Some(tuning));
+        // Erased flash and a zero flap are not settings
+        assert_eq!(TuningParams::from_u32(u32::MAX), None);
+        assert_eq!(TuningParams::from_u32(0), None);
+    }
+
+    #[test]
+    fn gap_and_speed_reshape_the_lane_and_velocity() {
+        let mut tuning = TuningParams::DEFAULT;
+        tuning.set(Param::Gap, 70).unwrap();
+        let lane = tuning.lane(Lane::FULL);
+        assert_eq!((lane.gap_top, lane.gap_bottom), (120, 190));
+        tuning.set(Param::Speed, 50).unwrap();
+        assert_eq!(tuning.velocity(512), 256);
+        tuning.set(Param::Speed, 25).unwrap();
+        assert_eq!(tuning.velocity(2), 1);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for param in Param::ALL {
+            assert_eq!(Param::parse(param.name()), Some(param));
+        }
+        assert_eq!(Param::parse("lift"), None);
+    }
+}
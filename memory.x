@@ -1,7 +1,10 @@
 /* Memory layout for flappy_bird_fresh */
 MEMORY
 {
-  FLASH : ORIGIN = 0x08000000, LENGTH = 2048K
+  /* The last five 128K sectors are left out: 19 for flash::ARTWORK,
+     20 for flash::COURSES, 21 for flash::SETTINGS, 22 and 23 for
+     flash::SPARE */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 1408K
   RAM   : ORIGIN = 0x20000000, LENGTH = 192K
 }
 
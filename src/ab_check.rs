@@ -0,0 +1,127 @@
+//! DMA2D against the CPU on the same frame
+//!
+//! Each case takes the picture on Layer 1 and draws it into two capture
+//! buffers of its own in SDRAM, once with DMA2D doing what it does and once
+//! with `dma2d::set_cpu_only` making every caller take its CPU fallback,
+//! then counts the pixels that came out different. Both captures are
+//! cleared to the same value first, so pixels a case leaves alone never
+//! count. Anything other than 0 means the two paths disagree: the safety
+//! net while more of the renderer moves over to DMA2D. Run from the debug
+//! menu and the shell (`abcheck`); the screen is not touched.
+#![allow(dead_code)]
+
+use core::fmt;
+
+use core_logic::raster::{Line, RasterEffect, Wave};
+
+use crate::display::{self, Backend};
+use crate::dma2d;
+use crate::framebuffer::FrameBuffer;
+use crate::lcd::{self, Layer1Buffer, DISPLAY_MEMORY};
+use crate::replay;
+use crate::sdram;
+use crate::sdram::arena::{Arena, Region};
+
+// A frame of the underwater sway that bends every line
+const WAVE_FRAME: u32 = 7;
+// Columns the slide case moves the picture over by
+const SLIDE_X: u32 = 37;
+
+const AFTER_REPLAY: Region = Region {
+    base: replay::BUFFER.end(),
+    size: DISPLAY_MEMORY.free.end() - replay::BUFFER.end(),
+};
+// What DMA2D drew and what the CPU drew
+pub const CAPTURES: (Layer1Buffer, Layer1Buffer) = {
+    let mut arena = Arena::new(AFTER_REPLAY);
+    (arena.alloc_framebuffer(), arena.alloc_framebuffer())
+};
+
+pub struct Case {
+    pub name: &'static str,
+    // Draw Layer 1 into `dst` the way some part of the renderer does
+    draw: fn(&Layer1Buffer),
+}
+
+pub const CASES: [Case; 3] = [
+    // A raster effect or transition bringing the picture across whole
+    Case {
+        name: "copy",
+        draw: |dst| FrameBuffer::copy_raster(&DISPLAY_MEMORY.layer1, dst, |_| Line::PLAIN),
+    },
+    // DMA2D only for the rows a wave leaves straight
+    Case {
+        name: "wave",
+        draw: |dst| {
+            let wave = Wave::underwater(lcd::surface().0);
+            FrameBuffer::copy_raster(&DISPLAY_MEMORY.layer1, dst, |row| {
+                wave.line(row, WAVE_FRAME)
+            });
+        },
+    },
+    // Part way through a slide transition
+    Case {
+        name: "slide",
+        draw: |dst| {
+            let width = lcd::surface().0;
+            FrameBuffer::copy_columns(
+                &DISPLAY_MEMORY.layer1,
+                dst,
+                0,
+                SLIDE_X,
+                width.saturating_sub(SLIDE_X),
+            );
+        },
+    },
+];
+
+pub struct Outcome {
+    pub name: &'static str,
+    pub mismatches: u32,
+    // Column and row in memory of the first pixel that differs
+    pub first: Option<(u32, u32)>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.first {
+            None => write!(f, "{} ok", self.name),
+            Some((x, y)) => write!(
+                f,
+                "{} {}px differ, first {},{}",
+                self.name, self.mismatches, x, y
+            ),
+        }
+    }
+}
+
+// Both captures live in SDRAM and the cases read Layer 1, which only the
+// LTDC backend draws
+pub fn available() -> bool {
+    sdram::available() && display::backend() == Backend::Ltdc
+}
+
+pub fn run(case: &Case) -> Outcome {
+    let (dma, cpu) = &CAPTURES;
+    let (mut dma_fb, mut cpu_fb) = (FrameBuffer::of(dma), FrameBuffer::of(cpu));
+    dma_fb.fill(0);
+    cpu_fb.fill(0);
+
+    (case.draw)(dma);
+    dma2d::set_cpu_only(true);
+    (case.draw)(cpu);
+    dma2d::set_cpu_only(false);
+
+    let (mismatches, first) = dma_fb.diff(&mut cpu_fb);
+    Outcome {
+        name: case.name,
+        mismatches,
+        first,
+    }
+}
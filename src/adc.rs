@@ -0,0 +1,105 @@
+//! ADC1 single conversions, and the thumb-stick pins on the expansion header
+//!
+//! A thumb-stick's two potentiometers go to PA7 (ADC12_IN7, X) and PC3
+//! (ADC123_IN13, Y), with their ends across 3 V and ground. PA5, the other
+//! free ADC pin on that header, is the DAC's sound output here.
+//!
+//! ADC1 is powered only for each conversion, as the VBAT reading has always
+//! done, so nothing is left running for the stick when it is not in use.
+//! Every channel samples for the longest time, 480 ADC clocks (about 23 us),
+//! which the VBAT bridge needs and a potentiometer's wiper does not mind.
+#![allow(dead_code)]
+
+use stm32f4::stm32f429 as pac;
+
+use crate::clock;
+use crate::resources;
+
+// Switches the VBAT bridge on for its conversion
+pub const VBAT_CHANNEL: u8 = 18;
+pub const STICK_X_CHANNEL: u8 = 7;
+pub const STICK_Y_CHANNEL: u8 = 13;
+// PA7 and PC3
+const STICK_X_LINE: u32 = 7;
+const STICK_Y_LINE: u32 = 3;
+// A conversion at 480 sampling cycles takes about 25 us
+const TIMEOUT_US: u32 = 100;
+const SMP_CYCLES480: u32 = 0b111;
+
+// One conversion of `channel` (0..=18); None if it never finished
+pub fn read(channel: u8) -> Option<u16> {
+    let dp = resources::pac();
+    dp.RCC.apb2enr.modify(|_, w| w.adc1en().enabled());
+    // APB2 is 84 MHz; /4 keeps ADCCLK under its 36 MHz limit
+    dp.ADC_COMMON
+        .ccr
+        .modify(|_, w| w.adcpre().div4().vbate().bit(channel == VBAT_CHANNEL));
+    // Three bits a channel: SMPR2 holds 0..=9, SMPR1 10..=18
+    if channel < 10 {
+        let shift = channel as u32 * 3;
+        dp.ADC1.smpr2.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b111 << shift) | SMP_CYCLES480 << shift)
+        });
+    } else {
+        let shift = (channel as u32 - 10) * 3;
+        dp.ADC1.smpr1.modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b111 << shift) | SMP_CYCLES480 << shift)
+        });
+    }
+    dp.ADC1.sqr3.write(|w| unsafe { w.sq1().bits(channel) });
+    dp.ADC1.sqr1.write(|w| w.l().bits(0));
+    dp.ADC1.cr2.write(|w| w.adon().enabled());
+    clock::delay_us(3);
+    dp.ADC1.cr2.modify(|_, w| w.swstart().start());
+
+    let start = clock::cycles();
+    let mut done = false;
+    while clock::cycles().wrapping_sub(start) < TIMEOUT_US * clock::CYCLES_PER_US {
+        if dp.ADC1.sr.read().eoc().is_complete() {
+            done = true;
+            break;
+        }
+    }
+    let raw = dp.ADC1.dr.read().data().bits();
+
+    dp.ADC1.cr2.write(|w| w.adon().disabled());
+    dp.ADC_COMMON.ccr.modify(|_, w| w.vbate().clear_bit());
+    dp.RCC.apb2enr.modify(|_, w| w.adc1en().disabled());
+
+    done.then_some(raw)
+}
+
+// Whether something drives both stick pins: an open pin follows the
+// internal pull-up and then the pull-down, a wiper holds it where it is.
+// Leaves the pins analog either way.
+pub fn probe_stick() -> bool {
+    let dp = resources::pac();
+    dp.RCC
+        .ahb1enr
+        .modify(|_, w| w.gpioaen().enabled().gpiocen().enabled());
+    let pa = pac::GPIOA::ptr();
+    let pc = pac::GPIOC::ptr() as *const pac::gpioa::RegisterBlock;
+    // SAFETY: the GPIO ports share one register layout, and these pins are
+    // the stick's alone
+    unsafe { driven(pa, STICK_X_LINE) && driven(pc, STICK_Y_LINE) }
+}
+
+unsafe fn driven(port: *const pac::gpioa::RegisterBlock, line: u32) -> bool {
+    let port = &*port;
+    let mask = 0b11 << (line * 2);
+    let pull = |bits: u32| {
+        port.pupdr
+            .modify(|r, w| w.bits(r.bits() & !mask | bits << (line * 2)));
+        // Long enough for a pull of about 40k to charge the pin
+        clock::delay_us(10);
+        port.idr.read().bits() & (1 << line) != 0
+    };
+    // Input, then each pull in turn
+    port.moder.modify(|r, w| w.bits(r.bits() & !mask));
+    let high = pull(0b01);
+    let low = pull(0b10);
+    port.pupdr.modify(|r, w| w.bits(r.bits() & !mask));
+    port.moder.modify(|r, w| w.bits(r.bits() | mask));
+    let floating = high && !low;
+    !floating
+}
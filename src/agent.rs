@@ -0,0 +1,73 @@
+//! Game-state introspection for an external agent on the host PC
+//!
+//! Debug builds with the `agent-api` feature publish a `GameSnapshot` over the
+//! telemetry link every running frame and accept flap commands back, so a
+//! PC-side experiment (e.g. reinforcement learning) can play the game.
+//!
+//! `KIND_GAME_STATE` payload, little-endian:
+//!
+//! ```text
+//! player_y i16 | player_vy i16 | score u16 | gap_count u8 |
+//! gap_count x (x i16 | top i16 | bottom i16)
+//! ```
+//!
+//! Flaps, points, crashes, state changes and near misses also go out as they happen, as
+//! `KIND_GAME_EVENT`: `event u8 | value u16`, where the event is 0 flap,
+//! 1 point (value: the score), 2 crash (value: 1 when a practice run
+//! respawns), 3 state change (value: the new `GameState`, in declaration
+//! order) or 4 near miss (value: rows from the pipe).
+//!
+//! `KIND_FLAP` carries no payload.
+#![allow(dead_code)]
+
+use crate::config::{Coord, FLAP_LIFT};
+use crate::game::GameSnapshot;
+use crate::game_events::GameEvent;
+use crate::telemetry;
+
+pub fn publish(snapshot: &GameSnapshot) {
+    let mut payload = [0u8; telemetry::MAX_PAYLOAD];
+    let mut len = 0;
+    let mut put = |bytes: [u8; 2]| {
+        payload[len..len + 2].copy_from_slice(&bytes);
+        len += 2;
+    };
+
+    put((snapshot.player_y as i16).to_le_bytes());
+    put((snapshot.player_vy as i16).to_le_bytes());
+    put((snapshot.score.min(u16::MAX as u32) as u16).to_le_bytes());
+
+    let gaps = snapshot.gaps.iter().flatten();
+    payload[len] = gaps.clone().count() as u8;
+    len += 1;
+    for gap in gaps {
+        for value in [gap.x, gap.top, gap.bottom] {
+            payload[len..len + 2].copy_from_slice(&(value as i16).to_le_bytes());
+            len += 2;
+        }
+    }
+
+    telemetry::send(telemetry::KIND_GAME_STATE, &payload[..len]);
+}
+
+pub fn on_game_event(event: GameEvent) {
+    let (code, value) = match event {
+        GameEvent::Flap { .. } => (0u8, 0u16),
+        GameEvent::ScorePoint { score, .. } => (1, score.min(u16::MAX as u32) as u16),
+        GameEvent::Death { respawn, .. } => (2, respawn as u16),
+        GameEvent::StateChange { to, .. } => (3, to as u16),
+        GameEvent::NearMiss { rows, .. } => (4, rows.min(u16::MAX as u32) as u16),
+    };
+    let [low, high] = value.to_le_bytes();
+    telemetry::send(telemetry::KIND_GAME_EVENT, &[code, low, high]);
+}
+
+// Return the y a pending flap command moves the bird to, if one arrived.
+// The game polls for it once per frame.
+pub fn take_flap(player_y: Coord) -> Option<Coord> {
+    if telemetry::take_flap() {
+        Some(player_y - FLAP_LIFT)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,105 @@
+//! Artwork flashed on its own instead of built into the firmware
+//!
+//! `flash::ARTWORK` may hold a BMP file or a raw RGB565 blob (see
+//! core_logic::bmp), written with the probe without rebuilding anything:
+//!
+//! ```text
+//! probe-rs download --chip STM32F429ZITx --binary-format bin --base-address 0x08160000 title.bmp
+//! ```
+//!
+//! It is decoded straight out of flash a row at a time as it is drawn, so
+//! it costs one line of RAM whatever its size. When there is one it takes
+//! the title logo's place on the splash and the start screen, centered on
+//! black; erased flash is no image, so erasing the sector brings the
+//! built-in logo back. `art` in the shell draws it anywhere on screen.
+#![allow(dead_code)]
+
+use core::slice;
+
+use core_logic::bmp::{Bitmap, BmpError};
+
+use crate::assets;
+use crate::color;
+use crate::config::{Coord, Rect, LCD_HEIGHT, LCD_WIDTH};
+use crate::display;
+use crate::flash;
+use crate::framebuffer::{FrameBuffer, Image, ImageTransform};
+
+// What the sector holds, if it is an image
+pub fn image() -> Result<Bitmap<'static>, BmpError> {
+    let region = flash::ARTWORK;
+    let bytes = unsafe { slice::from_raw_parts(region.base as *const u8, region.size as usize) };
+    Bitmap::parse(bytes)
+}
+
+// Top left corner that centers `image` on the screen; negative when it is
+// bigger, showing its middle
+pub fn centered(image: &Bitmap) -> (Coord, Coord) {
+    (
+        (LCD_WIDTH as Coord - image.width as Coord) / 2,
+        (LCD_HEIGHT as Coord - image.height as Coord) / 2,
+    )
+}
+
+// Hand the on-screen part of each row of `image` at (x, y) to `put` with
+// its y; the part starts at x, or the left edge
+fn rows(image: &Bitmap, x: Coord, y: Coord, mut put: impl FnMut(Coord, &[u16])) {
+    let mut line = [0u16; LCD_WIDTH as usize];
+    let first = (-y).max(0) as u32;
+    let last = (LCD_HEIGHT as Coord - y).clamp(0, image.height as Coord) as u32;
+    let from = (-x).max(0) as u32;
+    let visible = (LCD_WIDTH as Coord - x.max(0)).max(0) as usize;
+    for row in first..last {
+        let n = image.row(row, from, &mut line[..visible]);
+        if n > 0 {
+            put(y + row as Coord, &line[..n]);
+        }
+    }
+}
+
+// Draw `image` with its top left at (x, y) on the current render target
+pub fn draw(image: &Bitmap, x: Coord, y: Coord) {
+    let left = x.max(0);
+    rows(image, x, y, |row_y, line| {
+        let rect = Rect::new(left, row_y, line.len() as u32, 1);
+        let _ = display::draw_image_rust(rect, line);
+    });
+}
+
+// Draw `image` into `fb`, which need not be the render target
+pub fn blit(fb: &mut FrameBuffer, image: &Bitmap, x: Coord, y: Coord) {
+    let left = x.max(0);
+    rows(image, x, y, |row_y, line| {
+        let row = Image::new(line.len() as u32, 1, line);
+        fb.blit(left, row_y, &row, ImageTransform::NONE);
+    });
+}
+
+// The title logo across the screen, or the flashed artwork in its place
+pub fn draw_title() {
+    match image() {
+        Ok(art) => {
+            let _ = display::draw_rect_angle_rust(Rect::SCREEN, color::BLACK);
+            let (x, y) = centered(&art);
+            draw(&art, x, y);
+        }
+        Err(_) => {
+            let _ = display::draw_image_rust(Rect::SCREEN, &assets::GAME_NAME_IMG_DATA);
+        }
+    }
+}
+
+// The same into `fb`
+pub fn blit_title(fb: &mut FrameBuffer) {
+    match image() {
+        Ok(art) => {
+            fb.fill(fb.encode_rgb565(color::BLACK));
+            let (x, y) = centered(&art);
+            blit(fb, &art, x, y);
+        }
+        Err(_) => {
+            let logo = Image::new(LCD_WIDTH, LCD_HEIGHT, &assets::GAME_NAME_IMG_DATA);
+            fb.blit(0, 0, &logo, ImageTransform::FLIP_Y);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Asset tables checked against their build-time checksums
+//!
+//! build.rs works out what the CRC unit should give for every image and
+//! sound table in flash (`ASSETS`, generated). At boot `verify` runs each
+//! table through the unit and keeps the names of those that come out
+//! different, and `draw` puts them on a warning screen before the game
+//! starts. A bad table is only reported: the game goes on with it, drawing
+//! or playing whatever flash now holds.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use crate::assets;
+use crate::clock;
+use crate::color;
+use crate::config::*;
+use crate::crc;
+use crate::display;
+use crate::fmt_buf::FmtBuf;
+use crate::log;
+
+// How long the warning stays up
+const WARNING_MS: u32 = 4000;
+// The display font is 16x26
+const LINE_HEIGHT: Coord = 26;
+const MAX_BAD: usize = 8;
+
+#[derive(Copy, Clone)]
+pub enum AssetData {
+    Bytes(&'static [u8]),
+    Halfwords(&'static [u16]),
+}
+
+pub struct Asset {
+    pub name: &'static str,
+    pub data: AssetData,
+    // What the CRC unit gave for the table at build time
+    pub crc: u32,
+}
+
+impl Asset {
+    // The table through the CRC unit now, as little-endian bytes
+    pub fn checksum(&self) -> u32 {
+        match self.data {
+            AssetData::Bytes(bytes) => crc::hw_crc32_bytes(bytes.iter().copied()),
+            AssetData::Halfwords(halfwords) => {
+                crc::hw_crc32_bytes(halfwords.iter().flat_map(|h| h.to_le_bytes()))
+            }
+        }
+    }
+
+    pub fn is_intact(&self) -> bool {
+        self.checksum() == self.crc
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/asset_crcs.rs"));
+
+static mut BAD: [Option<&'static str>; MAX_BAD] = [None; MAX_BAD];
+
+// Check every table; past MAX_BAD only the log sees the rest
+pub fn verify() {
+    let bad = unsafe { &mut BAD };
+    *bad = [None; MAX_BAD];
+    let mut slots = bad.iter_mut();
+    for asset in ASSETS.iter().filter(|asset| !asset.is_intact()) {
+        log::error!("asset {} is corrupt", asset.name);
+        if let Some(slot) = slots.next() {
+            *slot = Some(asset.name);
+        }
+    }
+}
+
+pub fn corrupt() -> impl Iterator<Item = &'static str> {
+    unsafe { BAD.iter().map_while(|slot| *slot) }
+}
+
+pub fn is_clean() -> bool {
+    corrupt().next().is_none()
+}
+
+// The corrupt tables, one a line; nothing when all are intact
+pub fn draw() {
+    if is_clean() {
+        return;
+    }
+    let _ = display::set_background_color_rust(color::BLACK);
+    let _ = display::write_string_rust(0, 0, c"Corrupt assets", color::RED, color::BLACK);
+    let mut y = LINE_HEIGHT;
+    for name in corrupt() {
+        if y + LINE_HEIGHT > LCD_HEIGHT as Coord {
+            break;
+        }
+        let mut line = FmtBuf::<24>::new();
+        let _ = line.write_str(name);
+        let _ = display::write_string_rust(0, y, line.as_cstr(), color::WHITE, color::BLACK);
+        y += LINE_HEIGHT;
+    }
+    clock::delay_ms(WARNING_MS);
+}
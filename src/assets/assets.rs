@@ -8773,3 +8773,88 @@ pub static GAME_OVER_IMAGE_DATA: [u16; 12800] = [
     0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
     0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
 ];
+
+// 14x14 pickups, keyed on the sky color
+pub static COIN_IMG_DATA: [u16; 196] = [
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380,
+    0xb380, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0xfe40, 0xfe40, 0xfe40, 0xfe40,
+    0xfe40, 0xfe40, 0xb380, 0xb380, 0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0xfe40, 0xfe40, 0xfe40,
+    0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0xb380, 0x9f5e, 0x9f5e, 0xb380, 0xfe40, 0xfe40,
+    0xfe40, 0xfe40, 0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0x9f5e, 0x9f5e, 0xb380,
+    0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0x9f5e,
+    0xb380, 0xb380, 0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xfe40,
+    0xb380, 0xb380, 0xb380, 0xb380, 0xffd9, 0xfe40, 0xfe40, 0xfe40, 0xdcc0, 0xdcc0, 0xfe40, 0xfe40,
+    0xfe40, 0xfe40, 0xb380, 0xb380, 0x9f5e, 0xb380, 0xfe40, 0xffd9, 0xfe40, 0xfe40, 0xdcc0, 0xdcc0,
+    0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0x9f5e, 0x9f5e, 0xb380, 0xfe40, 0xffd9, 0xffd9, 0xfe40,
+    0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0xfe40,
+    0xffd9, 0xffd9, 0xdcc0, 0xdcc0, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0xb380, 0x9f5e, 0x9f5e, 0x9f5e,
+    0xb380, 0xb380, 0xfe40, 0xfe40, 0xffd9, 0xfe40, 0xfe40, 0xfe40, 0xb380, 0xb380, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0xb380, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xb380, 0xb380, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+];
+
+// Left half only; the right is it mirrored (render::Mirror::ACROSS)
+pub static STAR_IMG_DATA: [u16; 7 * 14] = [
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xcb00, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xcb00, 0xcb00, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0xff48, 0xff48, 0xcb00, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xcb00, 0xff48, 0xff48, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0xcb00, 0xff48, 0xff48, 0x9f5e, 0x9f5e, 0x9f5e, 0xcb00, 0xff48, 0xff48,
+    0xff48, 0x9f5e, 0x9f5e, 0xcb00, 0xff48, 0xff48, 0xff48, 0xff48, 0x9f5e, 0xcb00, 0xcb00, 0xcb00,
+    0xcb00, 0xcb00, 0xff48, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xff48, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xff48, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xcb00,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e,
+];
+
+// 14x14 power-ups, keyed on the sky color like the pickups: shield,
+// slow time and shrink
+pub static SHIELD_IMG_DATA: [u16; 196] = [
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xffff, 0xffff, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xffff, 0x2bde, 0x1ad9,
+    0xffff, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xffff, 0x2bde,
+    0x2bde, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0xffff,
+    0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0xffff, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e,
+    0x9f5e, 0xffff, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9,
+    0xffff, 0x9f5e, 0x9f5e, 0xffff, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9,
+    0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0xffff, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x1ad9,
+    0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0xffff, 0x2bde, 0x2bde, 0x2bde, 0x2bde,
+    0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0xffff, 0x2bde, 0x2bde,
+    0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e, 0x9f5e, 0xffff,
+    0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x2bde, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0x1ad9, 0xffff, 0x9f5e,
+    0x9f5e, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff,
+    0xffff, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+];
+
+pub static SLOW_IMG_DATA: [u16; 196] = [
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c,
+    0x194c, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff,
+    0xffff, 0xffff, 0x194c, 0x194c, 0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0xffff, 0xffff, 0xffff,
+    0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x194c, 0x9f5e, 0x9f5e, 0x194c, 0xffff, 0xffff,
+    0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x9f5e, 0x9f5e, 0x194c,
+    0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x9f5e,
+    0x194c, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0xffff,
+    0x194c, 0x194c, 0x194c, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x194c, 0x194c, 0x194c,
+    0x194c, 0xffff, 0x194c, 0x194c, 0x9f5e, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x194c,
+    0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x9f5e, 0x9f5e, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff,
+    0x194c, 0x194c, 0xffff, 0xffff, 0xffff, 0xffff, 0x194c, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0xffff,
+    0xffff, 0xffff, 0x194c, 0x194c, 0xffff, 0xffff, 0xffff, 0x194c, 0x194c, 0x9f5e, 0x9f5e, 0x9f5e,
+    0x194c, 0x194c, 0xffff, 0xffff, 0x194c, 0x194c, 0xffff, 0xffff, 0x194c, 0x194c, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x194c, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x194c, 0x194c, 0x9f5e, 0x9f5e,
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e,
+];
+
+// Top left quarter only; the rest is it mirrored (render::Mirror::BOTH)
+pub static SHRINK_IMG_DATA: [u16; 7 * 7] = [
+    0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x9f5e, 0x1305, 0xcfd9, 0xcfd9, 0xcfd9,
+    0xcfd9, 0xcfd9, 0x9f5e, 0xcfd9, 0x1305, 0xcfd9, 0xcfd9, 0xcfd9, 0xcfd9, 0x9f5e, 0xcfd9, 0xcfd9,
+    0x1305, 0xcfd9, 0xcfd9, 0xcfd9, 0x9f5e, 0xcfd9, 0xcfd9, 0xcfd9, 0x2e4a, 0x2e4a, 0x2e4a, 0x9f5e,
+    0xcfd9, 0xcfd9, 0xcfd9, 0x2e4a, 0x2e4a, 0x2e4a, 0x9f5e, 0xcfd9, 0xcfd9, 0xcfd9, 0x2e4a, 0x2e4a,
+    0x2e4a,
+];
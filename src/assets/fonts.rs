@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 #![allow(non_upper_case_globals)]
 
+use core_logic::font::{Glyph, GlyphRange, PropFont};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Font {
     pub width: u8,
@@ -599,3 +601,7 @@ pub const Font16x26: Font = Font {
         0x0000, 0x0000, 0x0000, 0x0000, // Ascii = [~]
     ],
 };
+
+// Proportional fonts built from src/assets/bdf (see build.rs). UI18 is the
+// 11x18 letters at their own widths plus arrows, a heart and a note.
+include!(concat!(env!("OUT_DIR"), "/bdf_fonts.rs"));
@@ -0,0 +1,10 @@
+// Boxes inside the sprites that count for collisions, each from its
+// sprite's top left. Measured from the art in assets.rs, they leave out the
+// edges of the drawing (wing tips, tail, beak), so a pipe grazing those is
+// not a crash. `hitbox on` in the shell outlines them for tuning.
+#![allow(dead_code)]
+
+use core_logic::rect::Rect;
+
+// BIRD_IMG_DATA, 30x30: the round of the body, rows 6..24
+pub const BIRD: Rect = Rect::new(2, 6, 24, 18);
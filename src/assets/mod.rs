@@ -1,5 +1,8 @@
 pub mod assets;
 pub mod fonts;
+pub mod hitboxes;
+pub mod music;
+pub mod sounds;
 
 // Re-export assets for easier access
 pub use assets::*;
\ No newline at end of file
@@ -0,0 +1,58 @@
+// Background music for core_logic::tracker: four bars of melody over a
+// one-beat drum pattern, sixteenth-note ticks at 120 BPM. Notes are MIDI
+// numbers; the two channels loop on their own.
+#![allow(dead_code)]
+
+use core_logic::tracker::{melody, noise, Song, REST};
+
+const C5: u8 = 72;
+const D5: u8 = 74;
+const E5: u8 = 76;
+const F5: u8 = 77;
+const G5: u8 = 79;
+const A5: u8 = 81;
+const C6: u8 = 84;
+// A low thud and a bright tick from the noise channel
+const KICK: u8 = 40;
+const HAT: u8 = 96;
+
+pub static THEME: Song = Song {
+    tick_ms: 125,
+    steps: &[
+        // Drums, a beat long
+        noise(KICK, 1),
+        noise(REST, 1),
+        noise(HAT, 1),
+        noise(REST, 1),
+        // Bar 1
+        melody(C5, 2),
+        melody(E5, 2),
+        melody(G5, 2),
+        melody(E5, 2),
+        melody(C5, 2),
+        melody(E5, 2),
+        melody(G5, 4),
+        // Bar 2
+        melody(A5, 2),
+        melody(G5, 2),
+        melody(E5, 2),
+        melody(C5, 2),
+        melody(D5, 4),
+        melody(REST, 4),
+        // Bar 3
+        melody(F5, 2),
+        melody(A5, 2),
+        melody(C6, 2),
+        melody(A5, 2),
+        melody(G5, 2),
+        melody(E5, 2),
+        melody(C5, 4),
+        // Bar 4
+        melody(D5, 2),
+        melody(E5, 2),
+        melody(F5, 2),
+        melody(D5, 2),
+        melody(C5, 6),
+        melody(REST, 2),
+    ],
+};
@@ -0,0 +1,180 @@
+// IMA ADPCM sound effects, 8 kHz mono, two samples per byte (low nibble first)
+// Generated procedurally; see audio::AdpcmDecoder for the matching decoder
+#![allow(dead_code)]
+
+pub static FLAP_ADPCM: [u8; 480] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0x0a, 0x68, 0x8a, 0x52, 0x80, 0xd3, 0x92, 0x9b, 0x48, 0x2a, 0x91,
+    0xd1, 0x8a, 0x03, 0x28, 0x90, 0x26, 0x1b, 0x58, 0xb1, 0xc4, 0x29, 0x0b, 0x2d, 0x02, 0xa4, 0x00,
+    0xa0, 0x34, 0x8a, 0x0f, 0x31, 0xc0, 0x19, 0x8d, 0xa9, 0x49, 0x89, 0x50, 0x0a, 0x41, 0x21, 0x9b,
+    0x5a, 0xc2, 0xaa, 0x08, 0xa2, 0x1c, 0x20, 0x17, 0x08, 0xd0, 0x12, 0x02, 0xaa, 0x1d, 0xab, 0x99,
+    0xb1, 0x89, 0x28, 0x7a, 0x96, 0x08, 0x98, 0x35, 0x88, 0xbb, 0x80, 0xa5, 0x6a, 0xa0, 0xb1, 0x51,
+    0x02, 0x8b, 0x3b, 0x20, 0xca, 0x44, 0x01, 0x00, 0x8e, 0xb9, 0x0b, 0x38, 0x87, 0x22, 0xb1, 0xbb,
+    0xaa, 0x63, 0x92, 0x20, 0x0e, 0x12, 0x90, 0x1c, 0x29, 0xb3, 0x5a, 0xd0, 0xaa, 0x14, 0x2a, 0x03,
+    0x8b, 0xcd, 0x14, 0x28, 0x29, 0xc1, 0x9b, 0x2a, 0x0a, 0x7b, 0x08, 0x31, 0x49, 0x89, 0xf8, 0x98,
+    0x4a, 0x09, 0x03, 0x09, 0x40, 0x0c, 0x8a, 0x03, 0x31, 0xa5, 0x90, 0x29, 0x8a, 0x79, 0x21, 0xe2,
+    0x28, 0xe1, 0x8a, 0x8a, 0x3a, 0x34, 0x2c, 0xb0, 0x43, 0x3b, 0x9a, 0x15, 0x8d, 0x98, 0x8a, 0xd3,
+    0x01, 0x8b, 0x13, 0x6c, 0x22, 0x9c, 0x2a, 0xa9, 0x60, 0x91, 0x3a, 0x10, 0xad, 0x82, 0x6b, 0x10,
+    0x3b, 0x3c, 0x99, 0x31, 0xcb, 0xa1, 0x9b, 0x8b, 0x11, 0x07, 0xa1, 0xb8, 0xb8, 0x27, 0x19, 0xa5,
+    0x82, 0x30, 0x8a, 0x62, 0x1a, 0x00, 0xbb, 0xae, 0x4a, 0xa8, 0x59, 0x13, 0xaa, 0x19, 0x1b, 0x79,
+    0x84, 0x3a, 0x9a, 0x8c, 0x01, 0x1a, 0x8c, 0x2a, 0xbb, 0x02, 0x27, 0x23, 0x42, 0xba, 0xb6, 0x01,
+    0x2d, 0x83, 0x10, 0x8d, 0x38, 0x11, 0x39, 0x89, 0xcf, 0x0a, 0x5a, 0x00, 0x01, 0xc8, 0x22, 0x09,
+    0xd0, 0xa2, 0x9b, 0x94, 0x53, 0x98, 0x18, 0x02, 0xf8, 0x8a, 0x92, 0xc1, 0x8a, 0x34, 0xa1, 0x81,
+    0xd0, 0xa5, 0x24, 0x8c, 0x22, 0xb9, 0x4a, 0x0a, 0x1b, 0xb5, 0x83, 0x03, 0x3c, 0xf9, 0x0a, 0x80,
+    0x89, 0x50, 0x3b, 0xb3, 0x05, 0xa2, 0x9a, 0x86, 0x99, 0xc9, 0x49, 0x49, 0x99, 0x90, 0x68, 0x11,
+    0x28, 0x91, 0xf8, 0x91, 0x01, 0xca, 0xa3, 0x88, 0x39, 0xa6, 0xa1, 0x80, 0xab, 0x79, 0x90, 0x33,
+    0xc9, 0xaa, 0xa8, 0x80, 0x64, 0x81, 0x09, 0x98, 0x8c, 0xa6, 0x11, 0xa2, 0x9a, 0x00, 0x26, 0xd1,
+    0x1a, 0x93, 0xc0, 0x48, 0x21, 0xd2, 0xaa, 0x20, 0x13, 0x18, 0x9a, 0x1f, 0x4a, 0xa0, 0x9b, 0x32,
+    0xad, 0x21, 0xa8, 0xe3, 0x18, 0x06, 0x92, 0x9a, 0x04, 0xba, 0x20, 0xa8, 0x63, 0xcb, 0x80, 0xa3,
+    0x33, 0xd8, 0x95, 0xa1, 0x2a, 0x59, 0xb8, 0x0a, 0x62, 0x8b, 0x5a, 0xaa, 0x1a, 0x25, 0x21, 0xc2,
+    0x3b, 0xd0, 0x80, 0x99, 0xba, 0x00, 0x97, 0x94, 0x39, 0x92, 0x0d, 0x38, 0x8a, 0xc4, 0x38, 0xc1,
+    0xaa, 0x85, 0x31, 0x9a, 0x04, 0x2b, 0xaa, 0x3d, 0x04, 0xd2, 0x09, 0x23, 0xba, 0x08, 0xb5, 0x12,
+    0x12, 0xd9, 0x9a, 0xe3, 0x29, 0xb9, 0x3b, 0x70, 0x23, 0xbb, 0x1b, 0x83, 0x8b, 0x43, 0x43, 0xf8,
+    0xa2, 0x90, 0xc2, 0x99, 0x6a, 0x80, 0x48, 0xa8, 0x03, 0xd4, 0x28, 0x11, 0x9d, 0x9a, 0x05, 0x91,
+    0x92, 0x2a, 0xe3, 0x00, 0x8a, 0x9a, 0x39, 0xb3, 0x0d, 0xa4, 0x88, 0x05, 0xba, 0x21, 0xc3, 0x5a,
+    0x88, 0x68, 0x09, 0x88, 0x34, 0xca, 0xb2, 0x5b, 0x28, 0x39, 0xc9, 0x1c, 0x32, 0x1c, 0x09, 0x9b,
+    0x72, 0x0a, 0x32, 0xac, 0x43, 0xc9, 0x92, 0x83, 0xe2, 0xa1, 0x28, 0xc2, 0x8a, 0x91, 0x8b, 0x54,
+];
+
+pub static SCORE_ADPCM: [u8; 1000] = [
+    0x70, 0x77, 0xff, 0x7f, 0x97, 0xff, 0x53, 0xb0, 0x8c, 0x43, 0xa0, 0x9c, 0x43, 0xb1, 0x9c, 0x52,
+    0x90, 0x9c, 0x42, 0xa1, 0xab, 0x52, 0x91, 0x9c, 0x41, 0x91, 0x9c, 0x31, 0xa3, 0xad, 0x41, 0x92,
+    0xac, 0x41, 0x82, 0xbc, 0x41, 0x82, 0xac, 0x30, 0x84, 0xac, 0x30, 0x03, 0xbd, 0x30, 0x85, 0xbb,
+    0x48, 0x84, 0xca, 0x38, 0x13, 0xbc, 0x49, 0x13, 0xdb, 0x39, 0x04, 0xca, 0x39, 0x04, 0xc9, 0x19,
+    0x14, 0xc9, 0x19, 0x14, 0xc9, 0x19, 0x14, 0xc9, 0x19, 0x14, 0xb9, 0x1b, 0x16, 0xb8, 0x1b, 0x34,
+    0xc9, 0x1b, 0x34, 0xc9, 0x0b, 0x25, 0xb8, 0x8b, 0x35, 0xc8, 0x8a, 0x43, 0xb0, 0x8c, 0x43, 0xb0,
+    0x8c, 0x43, 0xb0, 0x8c, 0x43, 0xa0, 0x8d, 0x32, 0xb1, 0x9c, 0x52, 0xa1, 0x9c, 0x42, 0xa1, 0xab,
+    0x52, 0x91, 0xac, 0x42, 0x91, 0xac, 0x42, 0x91, 0xbb, 0x51, 0x93, 0xad, 0x31, 0x93, 0xbc, 0x41,
+    0x83, 0xad, 0x30, 0x84, 0xac, 0x30, 0x03, 0xbd, 0x30, 0x04, 0xcb, 0x38, 0x04, 0xcb, 0x38, 0x04,
+    0xcb, 0x28, 0x05, 0xba, 0x39, 0x14, 0xda, 0x29, 0x14, 0xca, 0x29, 0x14, 0xca, 0x19, 0x24, 0xca,
+    0x19, 0x14, 0xb9, 0x2b, 0x25, 0xc9, 0x1a, 0x24, 0xc9, 0x1a, 0x33, 0xd8, 0x1b, 0x24, 0xc8, 0x0b,
+    0x34, 0xb8, 0x8c, 0x34, 0xb8, 0x8c, 0x34, 0xc0, 0x8b, 0x53, 0xb0, 0x9b, 0x34, 0xb1, 0x9d, 0x43,
+    0xa0, 0x9c, 0x43, 0xb1, 0x9c, 0x52, 0x90, 0xab, 0x52, 0x91, 0x9c, 0x41, 0x91, 0x9c, 0x31, 0xa3,
+    0x9d, 0x40, 0x92, 0x9c, 0x40, 0x92, 0xbb, 0x50, 0x93, 0xac, 0x40, 0x82, 0xcb, 0x40, 0x02, 0xbc,
+    0x30, 0x04, 0xac, 0x38, 0x04, 0xcb, 0x38, 0x04, 0xcb, 0x28, 0x05, 0xca, 0x28, 0x13, 0xcb, 0x39,
+    0x14, 0xda, 0x29, 0x14, 0xca, 0x29, 0x14, 0xba, 0x2a, 0x15, 0xc9, 0x2a, 0x14, 0xc9, 0x1a, 0x24,
+    0xb9, 0x1b, 0x25, 0xc8, 0x1b, 0x24, 0xc8, 0x1b, 0x24, 0xb8, 0x0c, 0x43, 0xb8, 0x8c, 0x34, 0xc0,
+    0x8b, 0x34, 0xc0, 0x8b, 0x53, 0xb0, 0x8b, 0x53, 0xa0, 0x9c, 0x33, 0xc2, 0x8c, 0x42, 0x90, 0x9c,
+    0x32, 0xa2, 0x9e, 0x32, 0xa2, 0x9d, 0x41, 0x91, 0x9c, 0x31, 0xa3, 0xad, 0x41, 0x92, 0xac, 0x41,
+    0x82, 0xbc, 0x41, 0x82, 0xac, 0x30, 0x84, 0xac, 0x30, 0x84, 0xcb, 0x48, 0x02, 0xcb, 0x38, 0x04,
+    0x2f, 0xb5, 0x1d, 0x04, 0x9c, 0x23, 0xd9, 0x30, 0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9,
+    0x48, 0xa1, 0x2b, 0x84, 0x9c, 0x14, 0xba, 0x51, 0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba,
+    0x50, 0xb0, 0x3a, 0x94, 0x0d, 0x13, 0xbb, 0x52, 0xb9, 0x48, 0xc2, 0x1a, 0x84, 0x9b, 0x24, 0xbb,
+    0x61, 0xb8, 0x39, 0xa3, 0x0d, 0x04, 0xab, 0x24, 0xc9, 0x30, 0xc1, 0x3a, 0x94, 0x0d, 0x13, 0xac,
+    0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c,
+    0x32, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xb1, 0x2b, 0x85, 0x9b,
+    0x24, 0xcb, 0x51, 0xb8, 0x49, 0x91, 0x1c, 0x03, 0x9c, 0x23, 0xd9, 0x30, 0xc1, 0x3a, 0x93, 0x8d,
+    0x14, 0xab, 0x42, 0xd8, 0x38, 0xb2, 0x2b, 0x84, 0x9c, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x1c,
+    0x04, 0x9c, 0x32, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c,
+    0x83, 0x9c, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c, 0x32, 0xca, 0x40, 0xc1, 0x29,
+    0x93, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x8d, 0x23, 0xcb, 0x51, 0xb8, 0x49,
+    0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba, 0x40, 0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48,
+    0xa1, 0x2b, 0x84, 0x9c, 0x14, 0xba, 0x51, 0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba, 0x40,
+    0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48, 0xa1, 0x2b, 0x84, 0x9c, 0x14, 0xba, 0x51,
+    0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba, 0x50, 0xb0, 0x3a, 0x94, 0x8c, 0x14, 0xbb, 0x43,
+    0xc9, 0x48, 0xb1, 0x1a, 0x85, 0x9b, 0x14, 0xba, 0x51, 0xb8, 0x49, 0xa2, 0x0c, 0x04, 0xab, 0x24,
+    0xc9, 0x30, 0xc1, 0x3a, 0x93, 0x0e, 0x13, 0xbb, 0x52, 0xc8, 0x38, 0xc2, 0x1a, 0x85, 0x9b, 0x14,
+    0xba, 0x51, 0xb8, 0x39, 0xa4, 0x0c, 0x04, 0xab, 0x43, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x0d, 0x13,
+    0xac, 0x42, 0xb9, 0x48, 0xa1, 0x1b, 0x85, 0x9b, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x0c, 0x05,
+    0xab, 0x43, 0xba, 0x40, 0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48, 0xa1, 0x1b, 0x85,
+    0x9b, 0x14, 0xba, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c, 0x32, 0xca, 0x40, 0xc1, 0x29, 0x93,
+    0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2,
+    0x1c, 0x13, 0x9d, 0x32, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48, 0xa1,
+    0x2b, 0x83, 0x8d, 0x23, 0xcb, 0x51, 0xb8, 0x49, 0xa2, 0x0c, 0x04, 0x9b, 0x42, 0xba, 0x50, 0xb0,
+    0x3a, 0x94, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24, 0xbb, 0x51, 0xc0,
+    0x39, 0xa2, 0x1c, 0x04, 0x9c, 0x32, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x0d, 0x13, 0xac, 0x42, 0xb9,
+    0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24, 0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c, 0x32, 0xca,
+    0x40, 0xc1, 0x29, 0x93, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x8d, 0x23, 0xcb,
+    0x51, 0xb8, 0x39, 0xa3, 0x1d, 0x03, 0xac, 0x24, 0xc9, 0x30, 0xc1, 0x3a, 0x93, 0x0e, 0x13, 0xbb,
+    0x52, 0xc8, 0x38, 0xc2, 0x1a, 0x84, 0x9b, 0x24, 0xcb, 0x51, 0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c,
+    0x42, 0xba, 0x40, 0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xb9, 0x59, 0xa1, 0x2b, 0x84, 0x9c,
+    0x14, 0xba, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c, 0x32, 0xba, 0x50, 0xb0, 0x3a, 0x94, 0x0d,
+    0x13, 0xac, 0x42, 0xc8, 0x38, 0xa1, 0x2c, 0x83, 0x8d, 0x23, 0xcb, 0x51, 0xb8, 0x49, 0xa2, 0x1c,
+    0x03, 0x9c, 0x42, 0xba, 0x40, 0xc1, 0x3a, 0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48, 0xa1, 0x2b,
+    0x84, 0x9c, 0x14, 0xba, 0x51, 0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba, 0x40, 0xc1, 0x3a,
+    0x93, 0x8d, 0x14, 0xab, 0x42, 0xc9, 0x48, 0xa1, 0x2b, 0x84, 0x9c, 0x14, 0xba, 0x51, 0xb8, 0x49,
+    0xa2, 0x1c, 0x03, 0x9c, 0x42, 0xba, 0x50, 0xb0, 0x3a, 0x94, 0x0d, 0x13, 0xbb, 0x52, 0xb9, 0x48,
+    0xc2, 0x1a, 0x84, 0x9b, 0x24, 0xbb, 0x61, 0xb8, 0x39, 0xa3, 0x0d, 0x04, 0xab, 0x24, 0xc9, 0x30,
+    0xc1, 0x3a, 0x94, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24, 0xbb, 0x51,
+    0xc0, 0x39, 0xa2, 0x1c, 0x13, 0x9d, 0x32, 0xca, 0x40, 0xc1, 0x29, 0x93, 0x8d, 0x14, 0xab, 0x42,
+    0xc9, 0x48, 0xa1, 0x2b, 0x83, 0x8d, 0x23, 0xcb, 0x51, 0xb8, 0x49, 0xa2, 0x1c, 0x03, 0x9c, 0x42,
+    0xba, 0x50, 0xb0, 0x3a, 0x94, 0x0d, 0x13, 0xac, 0x42, 0xb9, 0x48, 0xa1, 0x2c, 0x83, 0x9c, 0x24,
+    0xbb, 0x51, 0xc0, 0x39, 0xa2, 0x1c, 0x04, 0x9c,
+];
+
+pub static DEATH_ADPCM: [u8; 1200] = [
+    0x77, 0x77, 0x77, 0x77, 0x27, 0xdf, 0x08, 0x08, 0x08, 0x08, 0x27, 0x80, 0x80, 0x80, 0x80, 0xbf,
+    0x80, 0x80, 0x80, 0x80, 0x47, 0x08, 0x08, 0x08, 0x08, 0xaf, 0x08, 0x08, 0x08, 0x88, 0x37, 0x80,
+    0x80, 0x80, 0x80, 0xcf, 0x80, 0x80, 0x80, 0x80, 0x70, 0x83, 0x80, 0x80, 0x80, 0xf0, 0x0c, 0x08,
+    0x08, 0x08, 0x78, 0x02, 0x08, 0x80, 0x08, 0xf8, 0x0b, 0x80, 0x80, 0x08, 0x80, 0x47, 0x80, 0x80,
+    0x80, 0x08, 0xbf, 0x80, 0x80, 0x80, 0x80, 0x70, 0x04, 0x88, 0x00, 0x88, 0xf0, 0x0b, 0x08, 0x08,
+    0x08, 0x80, 0x47, 0x08, 0x80, 0x08, 0x80, 0xbf, 0x80, 0x00, 0x88, 0x00, 0x78, 0x84, 0x80, 0x80,
+    0x00, 0xf8, 0x0b, 0x80, 0x08, 0x80, 0x80, 0x47, 0x08, 0x08, 0x08, 0x08, 0xf8, 0x0b, 0x08, 0x08,
+    0x08, 0x08, 0x47, 0x80, 0x80, 0x80, 0x80, 0xbf, 0x80, 0x80, 0x80, 0x08, 0x70, 0x04, 0x08, 0x08,
+    0x08, 0x08, 0xcf, 0x80, 0x80, 0x80, 0x80, 0x78, 0x03, 0x08, 0x08, 0x08, 0x08, 0xdf, 0x80, 0x80,
+    0x80, 0x80, 0x70, 0x02, 0x08, 0x08, 0x08, 0x08, 0xcf, 0x80, 0x80, 0x80, 0x80, 0x70, 0x83, 0x80,
+    0x80, 0x08, 0x80, 0xcf, 0x08, 0x08, 0x08, 0x08, 0x70, 0x03, 0x08, 0x08, 0x08, 0x08, 0xdf, 0x08,
+    0x08, 0x80, 0x08, 0x80, 0x37, 0x80, 0x80, 0x80, 0x80, 0xf0, 0x0d, 0x08, 0x08, 0x88, 0x00, 0x27,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0xcf, 0x08, 0x08, 0x08, 0x08, 0x78, 0x84, 0x80, 0x80, 0x80, 0x80,
+    0xf0, 0x0c, 0x08, 0x08, 0x08, 0x08, 0x37, 0x08, 0x08, 0x08, 0x08, 0x08, 0xdf, 0x08, 0x08, 0x08,
+    0x08, 0x78, 0x03, 0x08, 0x08, 0x08, 0x88, 0xf0, 0x8d, 0x80, 0x80, 0x80, 0x00, 0x78, 0x84, 0x80,
+    0x80, 0x80, 0x80, 0xbf, 0x80, 0x80, 0x80, 0x80, 0x80, 0x57, 0x80, 0x80, 0x08, 0x08, 0x80, 0xbf,
+    0x08, 0x88, 0x00, 0x88, 0x00, 0x67, 0x08, 0x08, 0x08, 0x08, 0x08, 0xbf, 0x80, 0x80, 0x80, 0x80,
+    0x80, 0x57, 0x08, 0x80, 0x08, 0x08, 0x80, 0xbf, 0x08, 0x08, 0x08, 0x08, 0x08, 0x57, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0xf0, 0x0d, 0x08, 0x08, 0x08, 0x08, 0x78, 0x03, 0x08, 0x08, 0x08, 0x08, 0xf8,
+    0x8d, 0x80, 0x80, 0x80, 0x80, 0x70, 0x84, 0x80, 0x80, 0x80, 0x80, 0x80, 0xcf, 0x08, 0x08, 0x08,
+    0x08, 0x08, 0x47, 0x80, 0x80, 0x80, 0x80, 0x08, 0xf8, 0x0d, 0x08, 0x08, 0x88, 0x00, 0x88, 0x47,
+    0x80, 0x80, 0x80, 0x80, 0x00, 0xcf, 0x08, 0x08, 0x08, 0x08, 0x08, 0x78, 0x85, 0x00, 0x88, 0x80,
+    0x00, 0x88, 0xcf, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x85, 0x80, 0x80, 0x80, 0x80, 0x80, 0xcf,
+    0x80, 0x80, 0x80, 0x80, 0x08, 0x70, 0x04, 0x08, 0x08, 0x08, 0x08, 0x08, 0xdf, 0x08, 0x08, 0x08,
+    0x08, 0x08, 0x78, 0x85, 0x80, 0x80, 0x80, 0x80, 0x80, 0xcf, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70,
+    0x04, 0x08, 0x08, 0x08, 0x08, 0x08, 0xf8, 0x0e, 0x88, 0x00, 0x08, 0x88, 0x00, 0x37, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0x80, 0xff, 0x08, 0x08, 0x08, 0x08, 0x88, 0x70, 0x03, 0x08, 0x08, 0x08, 0x08,
+    0x80, 0xf8, 0x8f, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x84, 0x80, 0x80, 0x00, 0x88, 0x80, 0xf0,
+    0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x37, 0x80, 0x08, 0x80, 0x08, 0x08, 0x80, 0xff, 0x08, 0x08,
+    0x08, 0x08, 0x08, 0x80, 0x70, 0x04, 0x08, 0x08, 0x08, 0x88, 0x80, 0xf0, 0x8e, 0x00, 0x88, 0x00,
+    0x88, 0x00, 0x78, 0x04, 0x08, 0x08, 0x08, 0x08, 0x08, 0xf8, 0x0e, 0x08, 0x88, 0x00, 0x08, 0x88,
+    0x00, 0x57, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xdf, 0x80, 0x80, 0x08, 0x80, 0x08, 0x80, 0x70,
+    0x05, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xff, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x84,
+    0x80, 0x80, 0x80, 0x08, 0x08, 0x80, 0xef, 0x08, 0x08, 0x08, 0x08, 0x80, 0x80, 0x78, 0x05, 0x08,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0xef, 0x08, 0x08, 0x80, 0x80, 0x08, 0x80, 0x70, 0x85, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0x80, 0xef, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x67, 0x08, 0x08, 0x08,
+    0x08, 0x08, 0x80, 0x08, 0xef, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x04, 0x08, 0x08, 0x08,
+    0x08, 0x08, 0x80, 0xf8, 0x8f, 0x08, 0x80, 0x80, 0x08, 0x80, 0x80, 0x78, 0x06, 0x08, 0x08, 0x08,
+    0x08, 0x88, 0x80, 0xf0, 0x0e, 0x08, 0x88, 0x00, 0x88, 0x00, 0x08, 0x88, 0x77, 0x08, 0x80, 0x08,
+    0x80, 0x08, 0x80, 0x08, 0xdf, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x07, 0x08, 0x08,
+    0x88, 0x00, 0x88, 0x80, 0xf0, 0x8d, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x77, 0x00, 0x08,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0xf8, 0x0f, 0x88, 0x00, 0x88, 0x00, 0x88, 0x00, 0x08, 0x67, 0x08,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xf8, 0x0f, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x78,
+    0x87, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xdf, 0x08, 0x08, 0x08, 0x80, 0x08, 0x80, 0x80,
+    0x80, 0x77, 0x81, 0x80, 0x80, 0x08, 0x80, 0x08, 0x08, 0x80, 0xff, 0x09, 0x80, 0x08, 0x80, 0x80,
+    0x08, 0x80, 0x80, 0x77, 0x00, 0x08, 0x08, 0x08, 0x80, 0x08, 0x80, 0x08, 0xf8, 0x9f, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x17, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x88, 0x80, 0xff,
+    0x89, 0x80, 0x80, 0x00, 0x88, 0x00, 0x08, 0x08, 0x78, 0x27, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08,
+    0x08, 0x88, 0xff, 0x8a, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00, 0x88, 0x00, 0x77, 0x84, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0xf8, 0xaf, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70,
+    0x37, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x08, 0xf8, 0xdf, 0x80, 0x80, 0x80, 0x80, 0x80,
+    0x08, 0x80, 0x80, 0x70, 0x27, 0x80, 0x08, 0x08, 0x80, 0x08, 0x08, 0x08, 0x08, 0x08, 0xff, 0x8c,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x47, 0x80, 0x80, 0x08, 0x08, 0x08, 0x80,
+    0x08, 0x08, 0x08, 0xff, 0x0c, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x77, 0x04,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x88, 0x80, 0xff, 0x0d, 0x88, 0x00, 0x08, 0x88, 0x00,
+    0x08, 0x08, 0x08, 0x08, 0x77, 0x84, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x08, 0x08, 0x08, 0xff,
+    0x8c, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00, 0x08, 0x78, 0x67, 0x80, 0x08, 0x08, 0x80,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xff, 0x0c, 0x08, 0x08, 0x08, 0x08, 0x80, 0x08, 0x80, 0x80,
+    0x80, 0x80, 0x77, 0x06, 0x08, 0x08, 0x88, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xff, 0x0d,
+    0x08, 0x08, 0x08, 0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x67, 0x80, 0x08, 0x80, 0x08,
+    0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0xff, 0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08,
+    0x80, 0x80, 0x80, 0x70, 0x57, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08,
+    0xf8, 0xff, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x80, 0x80, 0x80, 0x80, 0x80, 0x77, 0x87,
+    0x80, 0x80, 0x00, 0x88, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xf0, 0xef, 0x80, 0x08, 0x80,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x70, 0x77, 0x00, 0x08, 0x08, 0x08, 0x08,
+    0x88, 0x80, 0x80, 0x80, 0x80, 0x80, 0x08, 0x08, 0xff, 0x9f, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+    0x80, 0x80, 0x80, 0x00, 0x08, 0x08, 0x70, 0x77, 0x82, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08,
+    0x08, 0x88, 0x80, 0x80, 0x08, 0xf8, 0xff, 0x8b, 0x00, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x80,
+    0x80, 0x00, 0x08, 0x80, 0x00, 0x77, 0x67, 0x08, 0x08, 0x08, 0x08, 0x08, 0x88, 0x80, 0x80, 0x80,
+    0x80, 0x08, 0x08, 0x88, 0x08, 0xf8, 0xff, 0x0b, 0x08, 0x08, 0x08, 0x08, 0x80, 0x80, 0x80, 0x00,
+    0x08, 0x80, 0x00, 0x08, 0x00, 0x00, 0x77, 0x77, 0x80, 0x08, 0x08, 0x08, 0x08, 0x08, 0x88, 0x80,
+    0x80, 0x08, 0x88, 0x80, 0x08, 0x88, 0x08, 0xff, 0xaf, 0x08, 0x08, 0x80, 0x80, 0x80, 0x00, 0x08,
+    0x80, 0x00, 0x80, 0x00, 0x00, 0x08, 0x01, 0x00, 0x10, 0x77, 0x77, 0x80, 0x08, 0x08, 0x08, 0x08,
+    0x88, 0x80, 0x08, 0x88, 0x80, 0x88, 0x88, 0x80, 0x89, 0x88, 0x98, 0xf8, 0xff, 0x0a, 0x80, 0x00,
+    0x08, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x11, 0x21, 0x21, 0x12, 0x33, 0x27,
+];
@@ -0,0 +1,373 @@
+//! Sound effect requests, stereo placement and background music
+//!
+//! Game code asks for a sound with `play` or `play_at`; requests are queued
+//! here until an output driver calls `mix` to render them into its buffer.
+//! The game's flaps, points and crashes arrive through `on_game_event`.
+//!
+//! Music is a core_logic::tracker song mixed in under the effects, centered,
+//! from when a run starts until it ends. While any effect plays the music
+//! ducks to a quarter of its level, fading down and back up over a few
+//! milliseconds so it does not click. It has its own switch in the
+//! settings, apart from the sound effects.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::state::GameState;
+use core_logic::tracker::{Player, Song};
+
+use crate::assets::{music, sounds};
+use crate::config::{Coord, LCD_WIDTH};
+#[cfg(all(feature = "dac-audio", not(feature = "i2s-audio")))]
+use crate::dac;
+use crate::error::HwError;
+use crate::game_events::GameEvent;
+#[cfg(feature = "i2s-audio")]
+use crate::i2s;
+use crate::subsystem::{Health, Subsystem};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SoundId {
+    Flap,
+    Score,
+    Death,
+}
+
+// Per-channel gain, 0 = silent, 255 = full scale
+#[derive(Copy, Clone, PartialEq)]
+pub struct StereoGain {
+    pub left: u8,
+    pub right: u8,
+}
+
+impl StereoGain {
+    pub const CENTER: StereoGain = StereoGain {
+        left: 0xFF,
+        right: 0xFF,
+    };
+}
+
+#[derive(Copy, Clone)]
+pub struct Voice {
+    pub sound: SoundId,
+    pub gain: StereoGain,
+}
+
+const MAX_PENDING: usize = 4;
+
+static mut PENDING: [Option<Voice>; MAX_PENDING] = [None; MAX_PENDING];
+
+// Pan a sound by the screen X position of the event that caused it.
+// The far side never drops below half gain so off-center sounds stay audible
+// on a single small speaker.
+pub fn pan_for_x(x: Coord) -> StereoGain {
+    let width = LCD_WIDTH as i32;
+    let x = x.clamp(0, width - 1);
+    let right = 0x80 + (x * 0x7F) / (width - 1);
+    let left = 0x80 + ((width - 1 - x) * 0x7F) / (width - 1);
+
+    StereoGain {
+        left: left as u8,
+        right: right as u8,
+    }
+}
+
+static mut ENABLED: bool = true;
+static mut MUSIC_ENABLED: bool = true;
+// A song to start, or None to stop, for the mixer to pick up
+static mut MUSIC_REQUEST: Option<Option<&'static Song>> = None;
+
+// Sound off drops new requests; whatever effect is already playing
+// finishes, but the music stops at once
+pub fn set_enabled(enabled: bool) {
+    unsafe { ENABLED = enabled };
+    if !enabled {
+        stop_music();
+    }
+}
+
+pub fn is_enabled() -> bool {
+    unsafe { ENABLED }
+}
+
+// Music off stops the song playing at once
+pub fn set_music_enabled(enabled: bool) {
+    unsafe { MUSIC_ENABLED = enabled };
+    if !enabled {
+        stop_music();
+    }
+}
+
+pub fn is_music_enabled() -> bool {
+    unsafe { MUSIC_ENABLED }
+}
+
+// Start `song` from the top, if sound and music are both on
+pub fn play_music(song: &'static Song) {
+    if is_enabled() && is_music_enabled() {
+        cortex_m::interrupt::free(|_| unsafe { MUSIC_REQUEST = Some(Some(song)) });
+    }
+}
+
+pub fn stop_music() {
+    cortex_m::interrupt::free(|_| unsafe { MUSIC_REQUEST = Some(None) });
+}
+
+// Queue a sound centered in the stereo field
+pub fn play(sound: SoundId) {
+    queue(Voice {
+        sound,
+        gain: StereoGain::CENTER,
+    });
+}
+
+// Queue a sound panned to where it happened on screen
+pub fn play_at(sound: SoundId, x: Coord) {
+    queue(Voice {
+        sound,
+        gain: pan_for_x(x),
+    });
+}
+
+// The sound for each game event, panned to where it happened
+pub fn on_game_event(event: GameEvent) {
+    match event {
+        GameEvent::Flap { .. } => play(SoundId::Flap),
+        GameEvent::ScorePoint { x, .. } => play_at(SoundId::Score, x),
+        GameEvent::Death { bird, .. } => play_at(SoundId::Death, bird.x),
+        // The music plays through a run, pauses included
+        GameEvent::StateChange {
+            from: GameState::Start,
+            to: GameState::Running,
+        } => play_music(&music::THEME),
+        GameEvent::StateChange {
+            to: GameState::Dying | GameState::End | GameState::Initializing,
+            ..
+        } => stop_music(),
+        GameEvent::NearMiss { .. } | GameEvent::StateChange { .. } => {}
+    }
+}
+
+fn queue(voice: Voice) {
+    if !is_enabled() {
+        return;
+    }
+    cortex_m::interrupt::free(|_| unsafe {
+        // Drop the request if the mixer is falling behind; a missed beep is
+        // better than stalling the frame
+        if let Some(slot) = PENDING.iter_mut().find(|v| v.is_none()) {
+            *slot = Some(voice);
+        }
+    });
+}
+
+// Hand the oldest queued request to the mixer
+pub fn take_pending() -> Option<Voice> {
+    cortex_m::interrupt::free(|_| unsafe {
+        let voice = PENDING[0].take();
+        if voice.is_some() {
+            PENDING.rotate_left(1);
+        }
+        voice
+    })
+}
+
+// Compressed sample data for each sound effect
+fn sample_data(sound: SoundId) -> &'static [u8] {
+    match sound {
+        SoundId::Flap => &sounds::FLAP_ADPCM,
+        SoundId::Score => &sounds::SCORE_ADPCM,
+        SoundId::Death => &sounds::DEATH_ADPCM,
+    }
+}
+
+const IMA_STEP: [i16; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+const IMA_INDEX: [i8; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Streaming IMA ADPCM decoder for one sound effect
+///
+/// Samples are stored two per byte, low nibble first, at `SAMPLE_RATE`.
+pub struct AdpcmDecoder {
+    data: &'static [u8],
+    pos: usize,
+    predictor: i32,
+    step_index: i32,
+}
+
+impl AdpcmDecoder {
+    pub fn new(data: &'static [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            predictor: 0,
+            step_index: 0,
+        }
+    }
+
+    pub fn next_sample(&mut self) -> Option<i16> {
+        let byte = *self.data.get(self.pos / 2)?;
+        let code = if self.pos.is_multiple_of(2) {
+            byte & 0x0F
+        } else {
+            byte >> 4
+        } as i32;
+        self.pos += 1;
+
+        let step = IMA_STEP[self.step_index as usize] as i32;
+        let mut diff = step >> 3;
+        if code & 4 != 0 {
+            diff += step;
+        }
+        if code & 2 != 0 {
+            diff += step >> 1;
+        }
+        if code & 1 != 0 {
+            diff += step >> 2;
+        }
+        if code & 8 != 0 {
+            self.predictor -= diff;
+        } else {
+            self.predictor += diff;
+        }
+        self.predictor = self.predictor.clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + IMA_INDEX[(code & 7) as usize] as i32).clamp(0, 88);
+
+        Some(self.predictor as i16)
+    }
+}
+
+// Rate of the stored sound effects
+pub const SAMPLE_RATE: u32 = 8_000;
+
+const MAX_VOICES: usize = 4;
+
+struct ActiveVoice {
+    decoder: AdpcmDecoder,
+    gain: StereoGain,
+    // Last decoded sample, repeated while upsampling to the output rate
+    current: i16,
+}
+
+static mut VOICES: [Option<ActiveVoice>; MAX_VOICES] = [const { None }; MAX_VOICES];
+
+// Music level out of 256, and what it is brought to while effects play
+const MUSIC_FULL: i32 = 256;
+const MUSIC_DUCKED: i32 = 64;
+
+static mut MUSIC: Option<Player> = None;
+static mut MUSIC_GAIN: i32 = MUSIC_FULL;
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<[Option<Voice>; MAX_PENDING]>()
+    + core::mem::size_of::<bool>() * 2
+    + core::mem::size_of::<Option<Option<&'static Song>>>()
+    + core::mem::size_of::<[Option<ActiveVoice>; MAX_VOICES]>()
+    + core::mem::size_of::<Option<Player>>()
+    + core::mem::size_of::<i32>();
+
+// Render queued and playing sounds into interleaved stereo frames (L, R).
+// `output_rate` must be a whole multiple of SAMPLE_RATE. Called from the
+// output driver, typically inside its DMA interrupt.
+pub fn mix(out: &mut [i16], output_rate: u32) {
+    let repeat = (output_rate / SAMPLE_RATE).max(1) as usize;
+    let voices = unsafe { &mut VOICES };
+
+    // Start or stop the music, if the game asked to
+    let music = unsafe { &mut MUSIC };
+    let gain = unsafe { &mut MUSIC_GAIN };
+    if let Some(request) = cortex_m::interrupt::free(|_| unsafe { MUSIC_REQUEST.take() }) {
+        *music = request.map(Player::new);
+    }
+
+    // Start anything the game asked for since the last buffer
+    while let Some(voice) = take_pending() {
+        let Some(slot) = voices.iter_mut().find(|v| v.is_none()) else {
+            break;
+        };
+        *slot = Some(ActiveVoice {
+            decoder: AdpcmDecoder::new(sample_data(voice.sound)),
+            gain: voice.gain,
+            current: 0,
+        });
+    }
+
+    for (i, frame) in out.chunks_exact_mut(2).enumerate() {
+        let mut left: i32 = 0;
+        let mut right: i32 = 0;
+
+        for slot in voices.iter_mut() {
+            let Some(voice) = slot else {
+                continue;
+            };
+            if i % repeat == 0 {
+                match voice.decoder.next_sample() {
+                    Some(sample) => voice.current = sample,
+                    None => {
+                        *slot = None;
+                        continue;
+                    }
+                }
+            }
+            left += (voice.current as i32 * voice.gain.left as i32) >> 8;
+            right += (voice.current as i32 * voice.gain.right as i32) >> 8;
+        }
+
+        if let Some(player) = music.as_mut() {
+            let ducked = voices.iter().any(Option::is_some);
+            let target = if ducked { MUSIC_DUCKED } else { MUSIC_FULL };
+            *gain += (target - *gain).signum();
+            let sample = (player.next(output_rate) as i32 * *gain) >> 8;
+            left += sample;
+            right += sample;
+        }
+
+        frame[0] = left.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        frame[1] = right.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    }
+}
+
+pub struct AudioSubsystem;
+
+impl Subsystem for AudioSubsystem {
+    fn name(&self) -> &'static str {
+        "audio"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        // Sound effects through an external I2S DAC, else the on-chip one
+        #[cfg(feature = "i2s-audio")]
+        i2s::init();
+        #[cfg(all(feature = "dac-audio", not(feature = "i2s-audio")))]
+        dac::init();
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        if cfg!(any(feature = "i2s-audio", feature = "dac-audio")) {
+            Health::Ok
+        } else {
+            Health::Disabled
+        }
+    }
+
+    fn suspend(&self) {
+        #[cfg(feature = "i2s-audio")]
+        i2s::stop();
+        #[cfg(all(feature = "dac-audio", not(feature = "i2s-audio")))]
+        dac::stop();
+        // Drop anything queued so it does not all play at once on resume
+        unsafe { PENDING = [None; MAX_PENDING] };
+    }
+
+    fn resume(&self) {
+        #[cfg(feature = "i2s-audio")]
+        i2s::init();
+        #[cfg(all(feature = "dac-audio", not(feature = "i2s-audio")))]
+        dac::init();
+    }
+}
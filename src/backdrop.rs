@@ -0,0 +1,116 @@
+//! Far-off towers scrolling behind the obstacles
+//!
+//! The skyline in `core_logic::skyline` on a layer of its own behind the
+//! gameplay one, at half the obstacles' speed (`DEPTH`). It is scenery: the
+//! rules only ever look at the world's obstacle, so nothing here can be hit.
+//! Being further back it is drawn first each frame (see `entity`), and it
+//! hands the renderer its towers so that anything painting sky afterwards,
+//! such as the world uncovering a column a pipe has left, paints them in
+//! too. When it moves it repaints its band of sky whole; the pipes are drawn
+//! over it after. `backdrop on|off` in the shell turns it on or off from the
+//! next game.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::cell::Cell;
+
+use core_logic::scroll::{Depth, Scroller, SUBPIXELS};
+use core_logic::skyline::Skyline;
+
+use crate::color::Rgb565;
+use crate::config::{Rect, LCD_WIDTH};
+use crate::entity::{Entity, Renderer};
+use crate::lane::Lane;
+
+pub const DEPTH: Depth = Depth::behind(-1, SUBPIXELS / 2);
+
+// What the towers are drawn in, the sky mixed in by how far off they are
+const HAZE: Rgb565 = Rgb565(0x52AA);
+const HAZE_MIX: u8 = 160;
+
+static mut SHOWN: bool = true;
+
+pub fn set_shown(on: bool) {
+    unsafe { SHOWN = on };
+}
+
+pub fn shown() -> bool {
+    unsafe { SHOWN }
+}
+
+// A tower's color over sky of `sky`
+pub fn tower_color(sky: Rgb565) -> Rgb565 {
+    sky.lerp(HAZE, HAZE_MIX)
+}
+
+pub struct Backdrop {
+    lane: Lane,
+    scroller: Scroller,
+    // Columns scrolled, now and a tick ago
+    scroll: u32,
+    before: u32,
+    // Off for this game
+    on: bool,
+    // Where it was last drawn; None draws it next frame
+    drawn: Cell<Option<u32>>,
+}
+
+impl Backdrop {
+    // A new game's skyline in `lane`, the obstacles moving at `velocity`
+    pub fn new(lane: Lane, velocity: u32) -> Self {
+        Backdrop {
+            lane,
+            scroller: Scroller::new(DEPTH.velocity(velocity)),
+            scroll: 0,
+            before: 0,
+            on: shown(),
+            drawn: Cell::new(None),
+        }
+    }
+
+    // The obstacles' speed changed to `velocity`
+    pub fn follow(&mut self, velocity: u32) {
+        self.scroller.set_velocity(DEPTH.velocity(velocity));
+    }
+
+    // The sky under it was painted over; draw it again next frame
+    pub fn redraw(&self) {
+        self.drawn.set(None);
+    }
+
+    fn band(&self) -> Rect {
+        Skyline::band(self.lane.y(self.lane.ground), LCD_WIDTH)
+    }
+}
+
+impl Entity for Backdrop {
+    fn update(&mut self, dt: u32) {
+        for _ in 0..dt {
+            self.before = self.scroll;
+            self.scroll = self.scroll.wrapping_add(self.scroller.step());
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        self.band()
+    }
+
+    fn depth(&self) -> Depth {
+        DEPTH
+    }
+
+    fn draw(&self, renderer: &mut Renderer) {
+        if !self.on {
+            return;
+        }
+        let moved = self.scroll.wrapping_sub(self.before);
+        let scroll = self
+            .before
+            .wrapping_add(moved * renderer.alpha() as u32 / 255);
+        renderer.set_skyline(Some(Skyline::new(scroll)));
+        if self.drawn.get() != Some(scroll) {
+            renderer.fill_sky(self.band());
+            self.drawn.set(Some(scroll));
+        }
+    }
+}
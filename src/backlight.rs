@@ -0,0 +1,131 @@
+//! Backlight brightness, 0..=100 %
+//!
+//! With the `backlight-pwm` feature the level is a 20 kHz PWM duty cycle on
+//! PB4 (TIM3_CH1, AF2), for a panel whose backlight enable is wired there.
+//! The DISCO board's own ILI9341 backlight is tied to the supply, so without
+//! the feature the level dims Layer 1 against the black LTDC background
+//! instead, which looks much the same.
+//!
+//! Fades are driven by `update`, called once per frame, from the millisecond
+//! clock, so they never block the game loop.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::clock;
+#[cfg(not(feature = "backlight-pwm"))]
+use crate::display;
+#[cfg(feature = "backlight-pwm")]
+use crate::resources;
+
+pub const FULL: u8 = 100;
+
+// TIM3 runs from the 84 MHz APB1 timer clock: 84 MHz / 4200 = 20 kHz
+#[cfg(feature = "backlight-pwm")]
+const PWM_PERIOD: u32 = 4200;
+
+struct Fade {
+    from: u8,
+    to: u8,
+    start_ms: u32,
+    duration_ms: u32,
+}
+
+static mut LEVEL: u8 = FULL;
+static mut FADE: Option<Fade> = None;
+
+pub fn init() {
+    #[cfg(feature = "backlight-pwm")]
+    {
+        let dp = resources::pac();
+        dp.RCC.ahb1enr.modify(|_, w| w.gpioben().enabled());
+        dp.RCC.apb1enr.modify(|_, w| w.tim3en().enabled());
+
+        // PB4 to AF2 (TIM3_CH1)
+        dp.GPIOB
+            .moder
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << 8)) | (0b10 << 8)) });
+        dp.GPIOB
+            .afrl
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0xF << 16)) | (2 << 16)) });
+
+        let tim = &dp.TIM3;
+        tim.psc.write(|w| w.psc().bits(0));
+        tim.arr.write(|w| unsafe { w.bits(PWM_PERIOD - 1) });
+        // CH1 PWM mode 1 (OC1M = 110) with preload
+        tim.ccmr1_output()
+            .modify(|r, w| unsafe { w.bits((r.bits() & !0xFF) | (0b110 << 4) | (1 << 3)) });
+        tim.ccer.modify(|_, w| w.cc1e().set_bit());
+        tim.cr1.modify(|_, w| w.arpe().set_bit().cen().set_bit());
+        apply(FULL);
+    }
+}
+
+pub fn brightness() -> u8 {
+    unsafe { LEVEL }
+}
+
+// Set the level immediately, cancelling any fade
+pub fn set_brightness(percent: u8) {
+    unsafe { FADE = None };
+    apply(percent.min(FULL));
+}
+
+// Move to `percent` over `duration_ms`
+pub fn fade_to(percent: u8, duration_ms: u32) {
+    let to = percent.min(FULL);
+    if duration_ms == 0 {
+        set_brightness(to);
+        return;
+    }
+    unsafe {
+        FADE = Some(Fade {
+            from: LEVEL,
+            to,
+            start_ms: clock::millis(),
+            duration_ms,
+        })
+    };
+}
+
+pub fn is_fading() -> bool {
+    unsafe { FADE.is_some() }
+}
+
+// Advance a running fade; the scheduler's backlight job
+pub fn update() {
+    let Some(fade) = (unsafe { FADE.as_ref() }) else {
+        return;
+    };
+    let elapsed = clock::millis().wrapping_sub(fade.start_ms);
+    if elapsed >= fade.duration_ms {
+        let to = fade.to;
+        unsafe { FADE = None };
+        apply(to);
+        return;
+    }
+    let (from, to) = (fade.from as i32, fade.to as i32);
+    let level = from + (to - from) * elapsed as i32 / fade.duration_ms as i32;
+    apply(level as u8);
+}
+
+// Layer 1 constant alpha that goes with the current level: always opaque
+// when the real backlight does the dimming
+pub fn layer_alpha() -> u8 {
+    if cfg!(feature = "backlight-pwm") {
+        0xFF
+    } else {
+        (brightness() as u32 * 0xFF / FULL as u32) as u8
+    }
+}
+
+fn apply(percent: u8) {
+    unsafe { LEVEL = percent };
+    #[cfg(feature = "backlight-pwm")]
+    {
+        let dp = resources::pac();
+        let duty = PWM_PERIOD * percent as u32 / FULL as u32;
+        dp.TIM3.ccr1().write(|w| unsafe { w.bits(duty) });
+    }
+    #[cfg(not(feature = "backlight-pwm"))]
+    let _ = display::set_brightness(layer_alpha());
+}
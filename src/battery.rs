@@ -0,0 +1,30 @@
+//! Backup battery voltage, for the HUD
+//!
+//! The STM32F429 routes VBAT through a divide-by-4 bridge to ADC1 channel
+//! 18. The bridge draws from the battery while it is on, so it is switched
+//! on only for the one conversion. The DISCO board ties VBAT to the 3 V
+//! supply unless a coin cell is fitted, in which case this reads the supply.
+#![allow(dead_code)]
+
+use crate::adc;
+
+// VBAT reaches the ADC divided by this
+const VBAT_DIVIDER: u32 = 4;
+// The reference on the DISCO board; readings are relative to it
+const VDDA_MV: u32 = 3000;
+const FULL_SCALE: u32 = 4095;
+
+// A CR2032 coin cell, new and spent
+const FULL_MV: u32 = 3000;
+const EMPTY_MV: u32 = 2000;
+
+// VBAT in millivolts; None if the conversion never finished
+pub fn read_millivolts() -> Option<u32> {
+    adc::read(adc::VBAT_CHANNEL).map(|raw| raw as u32 * VDDA_MV * VBAT_DIVIDER / FULL_SCALE)
+}
+
+// Charge left for a reading, 0..=100
+pub fn percent(millivolts: u32) -> u8 {
+    let above = millivolts.clamp(EMPTY_MV, FULL_MV) - EMPTY_MV;
+    (above * 100 / (FULL_MV - EMPTY_MV)) as u8
+}
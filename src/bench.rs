@@ -0,0 +1,191 @@
+//! Drawing microbenchmarks, run from the debug menu and the shell (`bench`)
+//!
+//! Each benchmark repeats one drawing operation over the whole screen
+//! `RUNS` times in the Layer 1 buffer that is not being scanned out, timed
+//! with the DWT cycle counter, and keeps the fastest run so an interrupt
+//! landing in one does not count. The figure is megapixels a second of
+//! what was drawn, for fills, blits in each transform and at double size, text and the
+//! DMA2D full-frame copy a raster effect or transition presents with, to
+//! check the fast paths against and catch them getting slower. Whatever the
+//! benchmarks leave in the buffer is painted over by the next screen drawn.
+#![allow(dead_code)]
+
+use core::fmt;
+
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use crate::clock::{self, CYCLES_PER_US};
+use crate::color::{self, Argb8888};
+use crate::framebuffer::{self, FrameBuffer, ImageTransform, Scale};
+use crate::lcd::DISPLAY_MEMORY;
+use crate::sprites::{self, SpriteId};
+
+const RUNS: u32 = 8;
+const TEXT: &str = "The quick brown fox jumps";
+
+pub struct Bench {
+    pub name: &'static str,
+    // Draw once; the pixels drawn
+    draw: fn(&mut FrameBuffer) -> u32,
+}
+
+pub const BENCHES: [Bench; 10] = [
+    Bench {
+        name: "fill",
+        draw: |fb| {
+            let native = fb.encode_argb(Argb8888::BLACK);
+            fb.fill(native);
+            fb.width() * fb.height()
+        },
+    },
+    Bench {
+        name: "rect",
+        // Tile-sized fills, as the world's erasing does
+        draw: |fb| {
+            let native = fb.encode_rgb565(color::GREEN);
+            for y in (0..fb.height()).step_by(16) {
+                for x in (0..fb.width()).step_by(16) {
+                    fb.fill_rect(x as i32, y as i32, 16, 16, native);
+                }
+            }
+            fb.width() * fb.height()
+        },
+    },
+    Bench {
+        name: "blend",
+        draw: |fb| {
+            let (w, h) = (fb.width(), fb.height());
+            fb.blend_rect(0, 0, w, h, color::WHITE, 0x80);
+            w * h
+        },
+    },
+    Bench {
+        name: "blit",
+        draw: |fb| blit_tiled(fb, ImageTransform::NONE),
+    },
+    Bench {
+        name: "blit-x",
+        draw: |fb| {
+            let flip_x = ImageTransform {
+                flip_x: true,
+                ..ImageTransform::NONE
+            };
+            blit_tiled(fb, flip_x)
+        },
+    },
+    Bench {
+        name: "blit-y",
+        draw: |fb| blit_tiled(fb, ImageTransform::FLIP_Y),
+    },
+    Bench {
+        name: "blit-xy",
+        draw: |fb| {
+            let both = ImageTransform {
+                flip_x: true,
+                ..ImageTransform::FLIP_Y
+            };
+            blit_tiled(fb, both)
+        },
+    },
+    Bench {
+        name: "blit-2x",
+        // The row-repeating upscale path
+        draw: |fb| blit_tiled(fb, ImageTransform::FLIP_Y.scaled(Scale::DOUBLE)),
+    },
+    Bench {
+        name: "text",
+        // Lines of the large font down the screen; a glyph's whole cell
+        // counts as drawn
+        draw: |fb| {
+            let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+            let (cell_w, cell_h) = (10, 20);
+            let per_line = (fb.width() / cell_w).min(TEXT.len() as u32) as usize;
+            let mut drawn = 0;
+            for y in (0..fb.height().saturating_sub(cell_h - 1)).step_by(cell_h as usize) {
+                let line = &TEXT[..per_line];
+                let _ = Text::with_baseline(line, Point::new(0, y as i32), style, Baseline::Top)
+                    .draw(fb);
+                drawn += per_line as u32 * cell_w * cell_h;
+            }
+            drawn
+        },
+    },
+    Bench {
+        name: "present",
+        // The back buffer onto itself, so the copy is a real full frame
+        // whichever buffer is on screen, without changing what it shows
+        draw: |fb| {
+            let back = &DISPLAY_MEMORY.layer1_b;
+            FrameBuffer::copy_columns(back, back, 0, 0, fb.width());
+            fb.width() * fb.height()
+        },
+    },
+];
+
+// The bird all over the screen, `transform`ed
+fn blit_tiled(fb: &mut FrameBuffer, transform: ImageTransform) -> u32 {
+    let Some(bird) = sprites::sprite(SpriteId::Bird).filter(|bird| bird.w > 0 && bird.h > 0) else {
+        return 0;
+    };
+    let (w, h) = transform.size(&bird);
+    let mut drawn = 0;
+    for y in (0..fb.height().saturating_sub(h - 1)).step_by(h as usize) {
+        for x in (0..fb.width().saturating_sub(w - 1)).step_by(w as usize) {
+            fb.blit(x as i32, y as i32, &bird, transform);
+            drawn += w * h;
+        }
+    }
+    drawn
+}
+
+pub fn find(name: &str) -> Option<&'static Bench> {
+    BENCHES.iter().find(|bench| bench.name == name)
+}
+
+/// The fastest of a benchmark's runs
+#[derive(Copy, Clone)]
+pub struct Timing {
+    pub name: &'static str,
+    pub pixels: u32,
+    pub cycles: u32,
+}
+
+impl Timing {
+    pub fn megapixels_per_s(&self) -> u32 {
+        (self.pixels as u64 * CYCLES_PER_US as u64 / self.cycles.max(1) as u64) as u32
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}px {}us {}Mpx/s",
+            self.name,
+            self.pixels,
+            self.cycles / CYCLES_PER_US,
+            self.megapixels_per_s()
+        )
+    }
+}
+
+pub fn run(bench: &Bench) -> Timing {
+    let mut fb = FrameBuffer::layer1_back();
+    let mut best = u32::MAX;
+    let mut pixels = 0;
+    for _ in 0..RUNS {
+        let start = clock::cycles();
+        pixels = (bench.draw)(&mut fb);
+        framebuffer::flush();
+        best = best.min(clock::cycles().wrapping_sub(start));
+    }
+    Timing {
+        name: bench.name,
+        pixels,
+        cycles: best,
+    }
+}
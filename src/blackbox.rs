@@ -0,0 +1,192 @@
+//! Black box: the last game events and hardware errors, kept through a reset
+//!
+//! A ring of `ENTRIES` (see core_logic::blackbox) sits in backup SRAM above
+//! the stats and the leaderboard. Every boot adds its reset cause; the game
+//! adds state changes, points and crashes through the event bus; I2C
+//! failures, LTDC underruns and faults are added where they are found.
+//! After a reset that cut the last session short (the watchdog, a brownout,
+//! or a fault the watchdog then cleared up), boot says so and offers the
+//! ring on screen and over serial at the press of the button. `blackbox` in
+//! the shell dumps it any time.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::{self, Write};
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use core_logic::blackbox::{Entry, Event, Ring};
+use core_logic::state::GameState;
+
+use crate::button;
+use crate::clock;
+use crate::config::{Coord, LCD_HEIGHT};
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::FrameBuffer;
+use crate::game_events::GameEvent;
+use crate::iwdg;
+use crate::log;
+use crate::serial;
+use crate::stats;
+
+// Where the ring starts in backup SRAM, clear of the stats records
+pub const OFFSET: u32 = 0x400;
+pub const ENTRIES: usize = 64;
+
+const _: () = assert!(OFFSET as usize + core::mem::size_of::<Ring<ENTRIES>>() <= 4096);
+
+// How long boot waits for the button before going on
+const OFFER_MS: u32 = 3000;
+// How long the dump stays up without a press
+const DUMP_MS: u32 = 20_000;
+const LINE_HEIGHT: Coord = 11;
+
+// Backup SRAM is on and the ring checked
+static mut READY: bool = false;
+// Why the last session ended early, if it did
+static mut CUT_SHORT: Option<&'static str> = None;
+// The score as of the last point, for the crash that ends the run
+static mut SCORE: u32 = 0;
+
+fn ring() -> &'static mut Ring<ENTRIES> {
+    // SAFETY: backup SRAM is enabled by stats::init and this part of it is
+    // reserved for the ring
+    unsafe { &mut *((stats::BKPSRAM_BASE + OFFSET) as *mut Ring<ENTRIES>) }
+}
+
+// Take up the ring left in backup SRAM, or start one, and note this boot;
+// after the storage subsystem is up
+pub fn init() {
+    let ring = ring();
+    if !ring.is_valid() {
+        ring.clear();
+    }
+    let cause = iwdg::reset_cause();
+    let faulted = matches!(ring.last().and_then(Entry::event), Some(Event::Fault(_)));
+    let cut_short = if cause.abnormal() {
+        Some(cause.name())
+    } else if faulted {
+        Some("fault")
+    } else {
+        None
+    };
+    unsafe {
+        CUT_SHORT = cut_short;
+        READY = true;
+    }
+    record(Event::Boot(cause));
+}
+
+// Safe from interrupts and the fault handler; nothing before `init`
+pub fn record(event: Event) {
+    if !unsafe { READY } {
+        return;
+    }
+    cortex_m::interrupt::free(|_| ring().push(clock::millis(), event));
+    cortex_m::asm::dsb();
+}
+
+// Subscribed to the game event bus; flaps come too often to be worth the
+// room
+pub fn on_game_event(event: GameEvent) {
+    let event = match event {
+        GameEvent::StateChange { from, to } => {
+            if to == GameState::Start {
+                unsafe { SCORE = 0 };
+            }
+            Event::State { from, to }
+        }
+        GameEvent::ScorePoint { score, .. } => {
+            unsafe { SCORE = score };
+            Event::Point(score)
+        }
+        GameEvent::Death { .. } => Event::Crash(unsafe { SCORE }),
+        GameEvent::Flap { .. } | GameEvent::NearMiss { .. } => return,
+    };
+    record(event);
+}
+
+pub fn clear() {
+    if unsafe { READY } {
+        cortex_m::interrupt::free(|_| ring().clear());
+    }
+}
+
+// Every entry, oldest first, a line each
+pub fn write(out: &mut impl Write) -> fmt::Result {
+    if !unsafe { READY } {
+        return write!(out, "black box not set up\r\n");
+    }
+    let ring = ring();
+    write!(out, "black box, {} entries (ms since boot)\r\n", ring.len())?;
+    for entry in ring.iter() {
+        write!(out, "{}\r\n", entry)?;
+    }
+    Ok(())
+}
+
+// After a reset that cut the last session short, say so and show the
+// ring on screen and over serial if the button is pressed in time
+pub fn offer() {
+    let Some(reason) = (unsafe { CUT_SHORT.take() }) else {
+        return;
+    };
+    log::warn!("last session ended by {}, black box kept", reason);
+    let mut fb = FrameBuffer::render_target();
+    let black = fb.encode_rgb565(0x0000);
+    fb.fill(black);
+    let mut line = FmtBuf::<48>::new();
+    let _ = write!(line, "Last session ended: {}", reason);
+    draw_line(&mut fb, 0, line.as_str(), Rgb565::YELLOW);
+    draw_line(&mut fb, 2, "Press the button to see the", Rgb565::WHITE);
+    draw_line(&mut fb, 3, "black box", Rgb565::WHITE);
+    if !wait_for_press(OFFER_MS) {
+        return;
+    }
+
+    let _ = write(&mut serial::Writer);
+    fb.fill(black);
+    // As many of the newest as fit under the heading
+    let rows = (LCD_HEIGHT as Coord / LINE_HEIGHT) as usize - 1;
+    let ring = ring();
+    draw_line(&mut fb, 0, "Black box (ms since boot)", Rgb565::YELLOW);
+    for (row, entry) in ring
+        .iter()
+        .skip(ring.len().saturating_sub(rows))
+        .enumerate()
+    {
+        line.clear();
+        let _ = write!(line, "{}", entry);
+        draw_line(&mut fb, row + 1, line.as_str(), Rgb565::WHITE);
+    }
+    wait_for_press(DUMP_MS);
+}
+
+fn draw_line(fb: &mut FrameBuffer, row: usize, text: &str, color: Rgb565) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(color)
+        .build();
+    let y = row as Coord * LINE_HEIGHT;
+    let _ = Text::with_baseline(text, Point::new(0, y), style, Baseline::Top).draw(fb);
+    cortex_m::asm::dsb();
+}
+
+// True if the button went down (from up) within `ms`
+fn wait_for_press(ms: u32) -> bool {
+    let start = clock::millis();
+    let mut was_down = button::is_down();
+    while clock::millis().wrapping_sub(start) < ms {
+        let down = button::is_down();
+        if down && !was_down {
+            return true;
+        }
+        was_down = down;
+        clock::delay_ms(10);
+    }
+    false
+}
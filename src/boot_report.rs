@@ -0,0 +1,106 @@
+//! What failed to come up at boot
+//!
+//! Bring-up records each failing part here instead of stopping, then main
+//! shows the list on screen for a moment before the game starts with
+//! whatever did work (no tilt without the MPU6050, SPI drawing without
+//! SDRAM or LTDC, and so on). Under the failures go the addresses that
+//! answered on each I2C bus, which tells a miswired or misaddressed MPU6050
+//! or touch controller from a missing one. The diagnostics page lists it
+//! again later, and `hw_report` probes every part, working or not, on
+//! request.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use crate::clock;
+use crate::color;
+use crate::config::*;
+use crate::display;
+use crate::error::HwError;
+use crate::fmt_buf::FmtBuf;
+use crate::i2c::{self, Bus, DeviceMap};
+use crate::log;
+
+const MAX_FAILURES: usize = 8;
+// How long the report stays up
+const REPORT_MS: u32 = 3000;
+// The display font is 16x26; the error goes under its part, indented
+const LINE_HEIGHT: Coord = 26;
+const INDENT: Coord = 32;
+
+static mut FAILURES: [Option<(&'static str, HwError)>; MAX_FAILURES] = [None; MAX_FAILURES];
+static mut SCANS: [Option<(Bus, Result<DeviceMap, HwError>)>; 2] = [None; 2];
+
+// Note that `part` did not come up; past MAX_FAILURES only the log sees it
+pub fn record(part: &'static str, error: HwError) {
+    log::error!("{} init failed: {}", part, error);
+    let failures = unsafe { &mut FAILURES };
+    if let Some(slot) = failures.iter_mut().find(|slot| slot.is_none()) {
+        *slot = Some((part, error));
+    }
+}
+
+pub fn failures() -> impl Iterator<Item = (&'static str, HwError)> {
+    unsafe { FAILURES.iter().map_while(|slot| *slot) }
+}
+
+pub fn is_clean() -> bool {
+    failures().next().is_none()
+}
+
+// Scan both I2C buses once their parts have been brought up, for the log
+// and the report
+pub fn scan_buses() {
+    let scans = unsafe { &mut SCANS };
+    for (slot, bus) in scans.iter_mut().zip([Bus::I2c1, Bus::I2c3]) {
+        let result = i2c::scan(bus);
+        match result {
+            Ok(map) => log::info!("{} devices: {}", bus.name(), map),
+            Err(error) => log::warn!("{} scan failed: {}", bus.name(), error),
+        }
+        *slot = Some((bus, result));
+    }
+}
+
+pub fn scans() -> impl Iterator<Item = (Bus, Result<DeviceMap, HwError>)> {
+    unsafe { SCANS.iter().map_while(|slot| *slot) }
+}
+
+// Each failing part with what went wrong under it; nothing when all came
+// up. Parts past the bottom of the screen are left to the log.
+pub fn draw() {
+    if is_clean() {
+        return;
+    }
+    let _ = display::set_background_color_rust(color::BLACK);
+    let _ = display::write_string_rust(0, 0, c"Boot errors", color::RED, color::BLACK);
+    let mut y = LINE_HEIGHT;
+    for (part, error) in failures() {
+        if y + 2 * LINE_HEIGHT > LCD_HEIGHT as Coord {
+            break;
+        }
+        write_line(0, y, part, color::WHITE);
+        write_line(INDENT, y + LINE_HEIGHT, error.as_str(), color::RED);
+        y += 2 * LINE_HEIGHT;
+    }
+    for (bus, result) in scans() {
+        if y + LINE_HEIGHT > LCD_HEIGHT as Coord {
+            break;
+        }
+        let mut line = FmtBuf::<24>::new();
+        let _ = match result {
+            Ok(map) => write!(line, "{} {}", bus.name(), map),
+            Err(error) => write!(line, "{} {}", bus.name(), error),
+        };
+        write_line(0, y, line.as_str(), color::WHITE);
+        y += LINE_HEIGHT;
+    }
+    clock::delay_ms(REPORT_MS);
+}
+
+fn write_line(x: Coord, y: Coord, text: &str, color: u16) {
+    let mut line = FmtBuf::<24>::new();
+    let _ = line.write_str(text);
+    let _ = display::write_string_rust(x, y, line.as_cstr(), color, color::BLACK);
+}
@@ -0,0 +1,177 @@
+//! Memory budget report
+//!
+//! Summarizes how much flash each asset group occupies and how much static RAM
+//! each subsystem reserves. Each module adds up its own statics as
+//! `STATIC_RAM_BYTES`; the linker's .data, .bss and .uninit, less those, is
+//! "other", so a module left off the list still counts and the RAM total is
+//! always what was linked. SDRAM framebuffers are deliberately excluded; they
+//! live outside the 192K of internal RAM this report is meant to watch. The
+//! diagnostics page draws the report and the shell's `mem` prints it.
+#![allow(dead_code)]
+
+use core::fmt::{self, Write};
+use core::mem::size_of_val;
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use crate::assets;
+use crate::assets::fonts;
+use crate::assets::sounds;
+use crate::audio;
+use crate::config::Coord;
+use crate::courses;
+use crate::display;
+use crate::executor;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::FrameBuffer;
+use crate::game;
+use crate::input_events;
+use crate::lcd;
+use crate::memory;
+use crate::profiler;
+use crate::replay;
+use crate::rtt;
+use crate::scheduler;
+use crate::serial;
+use crate::settings;
+use crate::store;
+use crate::telemetry;
+use crate::usb;
+use crate::wifi;
+
+const LINE_HEIGHT: Coord = 12;
+
+pub struct BudgetEntry {
+    pub name: &'static str,
+    pub bytes: usize,
+}
+
+// Flash used by each group of compiled-in assets
+pub fn flash_assets() -> [BudgetEntry; 6] {
+    [
+        BudgetEntry {
+            name: "title",
+            bytes: size_of_val(&assets::GAME_NAME_IMG_DATA),
+        },
+        BudgetEntry {
+            name: "game over",
+            bytes: size_of_val(&assets::GAME_OVER_IMAGE_DATA),
+        },
+        BudgetEntry {
+            name: "plant",
+            bytes: size_of_val(&assets::PLANT_IMG_DATA),
+        },
+        BudgetEntry {
+            name: "bird",
+            bytes: size_of_val(&assets::BIRD_IMG_DATA),
+        },
+        BudgetEntry {
+            name: "fonts",
+            bytes: size_of_val(fonts::Font7x10.data)
+                + size_of_val(fonts::Font11x18.data)
+                + size_of_val(fonts::Font16x26.data)
+                + size_of_val(fonts::UI18.bitmap)
+                + size_of_val(fonts::UI18.glyphs)
+                + size_of_val(fonts::UI18.ranges),
+        },
+        BudgetEntry {
+            name: "sounds",
+            bytes: size_of_val(&sounds::FLAP_ADPCM)
+                + size_of_val(&sounds::SCORE_ADPCM)
+                + size_of_val(&sounds::DEATH_ADPCM),
+        },
+    ]
+}
+
+// Where the remainder goes in `static_ram`; everything before it is in the
+// linked sections
+const OTHER: usize = 15;
+
+const fn entry(name: &'static str, bytes: usize) -> BudgetEntry {
+    BudgetEntry { name, bytes }
+}
+
+// Static RAM reserved by each subsystem (.data + .bss + .uninit, excluding
+// the stack), then the game that main keeps in its frame for good
+pub fn static_ram() -> [BudgetEntry; 17] {
+    let mut entries = [
+        entry("display", display::STATIC_RAM_BYTES),
+        entry("lcd", lcd::STATIC_RAM_BYTES),
+        entry("profiler", profiler::STATIC_RAM_BYTES),
+        entry("audio", audio::STATIC_RAM_BYTES),
+        entry("serial", serial::STATIC_RAM_BYTES),
+        entry("usb", usb::STATIC_RAM_BYTES),
+        entry("wifi", wifi::STATIC_RAM_BYTES),
+        entry("rtt", rtt::STATIC_RAM_BYTES),
+        entry("telemetry", telemetry::STATIC_RAM_BYTES),
+        entry("replay", replay::STATIC_RAM_BYTES),
+        entry("store", store::STATIC_RAM_BYTES),
+        entry("settings", settings::STATIC_RAM_BYTES),
+        entry("courses", courses::STATIC_RAM_BYTES),
+        entry("input", input_events::STATIC_RAM_BYTES),
+        entry(
+            "tasks",
+            executor::STATIC_RAM_BYTES + scheduler::STATIC_RAM_BYTES,
+        ),
+        entry("other", 0),
+        entry("game", game::STATIC_RAM_BYTES),
+    ];
+    let listed: usize = entries[..OTHER].iter().map(|e| e.bytes).sum();
+    let linked = memory::usage().static_bytes() as usize;
+    entries[OTHER].bytes = linked.saturating_sub(listed);
+    entries
+}
+
+fn write_section(out: &mut impl Write, title: &str, entries: &[BudgetEntry]) -> fmt::Result {
+    let total: usize = entries.iter().map(|e| e.bytes).sum();
+    write!(out, "{} {}\r\n", title, total)?;
+    for entry in entries {
+        write!(out, " {} {}\r\n", entry.name, entry.bytes)?;
+    }
+    Ok(())
+}
+
+// Write the whole report as plain text lines, for the serial console's
+// `mem`
+pub fn write_report(out: &mut impl Write) -> fmt::Result {
+    write_section(out, "FLASH", &flash_assets())?;
+    write_section(out, "RAM", &static_ram())
+}
+
+// Render the report on the diagnostics page, one entry per text line
+pub fn draw_report(x: Coord, y: Coord) {
+    let mut fb = FrameBuffer::layer1();
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::BLACK)
+        .background_color(Rgb565::WHITE)
+        .build();
+
+    let mut y = y;
+    let mut draw_section = |name: &str, entries: &[BudgetEntry]| {
+        let mut line: FmtBuf<40> = FmtBuf::new();
+        let total: usize = entries.iter().map(|e| e.bytes).sum();
+        let _ = write!(line, "{} {} bytes", name, total);
+        let _ = Text::with_baseline(line.as_str(), Point::new(x, y), style, Baseline::Top)
+            .draw(&mut fb);
+        y += LINE_HEIGHT;
+
+        // Two to a line, to fit the page
+        for pair in entries.chunks(2) {
+            line.clear();
+            for entry in pair {
+                let _ = write!(line, "  {:<10}{:>8}", entry.name, entry.bytes);
+            }
+            let _ = Text::with_baseline(line.as_str(), Point::new(x, y), style, Baseline::Top)
+                .draw(&mut fb);
+            y += LINE_HEIGHT;
+        }
+    };
+
+    draw_section("FLASH", &flash_assets());
+    draw_section("RAM", &static_ram());
+}
@@ -0,0 +1,143 @@
+//! Blue USER button (PA0, active high, external pull-down on the DISCO board)
+//!
+//! EXTI line 0 interrupts on both edges and the handler queues a press or
+//! release in `input_events`, so a tap shorter than a frame is not missed.
+//! The game turns those into `ButtonEvent`s (the timings are in core_logic
+//! `input`): a press shorter than `LONG_PRESS_MS` reports `Short` when
+//! released; holding it reports `Long` once, as soon as the threshold is
+//! crossed, and nothing on release. Keeping it held reports `Hold` once
+//! more at `HOLD_MS`.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::clock;
+use crate::encoder;
+use crate::error::HwError;
+use crate::input_events::{self, InputSource};
+use crate::resources;
+use crate::subsystem::{Health, Subsystem};
+use crate::trigger;
+
+const LINE: u32 = 0;
+// Held this long, the button is reported stuck
+const STUCK_MS: u32 = 10_000;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ButtonEvent {
+    Short,
+    Long,
+    // Still held well after Long; saves a screenshot
+    Hold,
+}
+
+// When the current press began, as the interrupt last saw it. Bounce
+// makes more edges than presses; only a change of level is queued.
+static mut PRESSED_AT: Option<u32> = None;
+
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.ahb1enr.modify(|_, w| w.gpioaen().enabled());
+    // PA0 as plain input, no internal pull (the board has one)
+    dp.GPIOA
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0b11) });
+    dp.GPIOA
+        .pupdr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0b11) });
+    // EXTI0 from port A is the reset routing; only the SYSCFG clock is needed
+    dp.RCC.apb2enr.modify(|_, w| w.syscfgen().enabled());
+    arm();
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::EXTI0) };
+}
+
+pub fn is_down() -> bool {
+    let dp = resources::pac();
+    dp.GPIOA.idr.read().idr0().bit_is_set()
+}
+
+// Interrupt on both edges of PA0. Sleep rearms the line for its wake
+// event and clears the edges on the way out, so resume calls this again.
+fn arm() {
+    let dp = resources::pac();
+    let bit = 1 << LINE;
+    dp.SYSCFG
+        .exticr1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0xF) });
+    dp.EXTI
+        .rtsr
+        .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    dp.EXTI
+        .ftsr
+        .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    dp.EXTI.pr.write(|w| unsafe { w.bits(bit) });
+    dp.EXTI.imr.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+}
+
+fn disarm() {
+    let dp = resources::pac();
+    dp.EXTI
+        .imr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << LINE)) });
+}
+
+#[interrupt]
+fn EXTI0() {
+    let dp = resources::pac();
+    dp.EXTI.pr.write(|w| unsafe { w.bits(1 << LINE) });
+
+    let pressed_at = unsafe { &mut PRESSED_AT };
+    match (pressed_at.is_some(), is_down()) {
+        (false, true) => {
+            *pressed_at = Some(clock::millis());
+            input_events::pressed(InputSource::Button);
+        }
+        (true, false) => {
+            *pressed_at = None;
+            input_events::released(InputSource::Button);
+        }
+        _ => {}
+    }
+}
+
+pub struct InputSubsystem;
+
+impl Subsystem for InputSubsystem {
+    fn name(&self) -> &'static str {
+        "input"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        init();
+        trigger::init();
+        encoder::init();
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        // A button that reads pressed for minutes is stuck or shorted
+        match unsafe { PRESSED_AT } {
+            Some(start) if clock::millis().wrapping_sub(start) > STUCK_MS => {
+                Health::Degraded("button stuck")
+            }
+            _ => Health::Ok,
+        }
+    }
+
+    fn suspend(&self) {
+        disarm();
+        trigger::disarm();
+    }
+
+    fn resume(&self) {
+        // Whatever happened while suspended is not a press, including the
+        // one that may have woken us and is still held: its release finds
+        // no press to end and is dropped
+        unsafe { PRESSED_AT = None };
+        input_events::clear();
+        arm();
+        trigger::resume();
+    }
+}
@@ -0,0 +1,294 @@
+//! Tilt calibration wizard, opened from the pause menu
+//!
+//! The player lays the board the way they will hold it and presses the
+//! button; the wizard then averages the accelerometer for SAMPLE_MS. If the
+//! board moved meanwhile it asks again. Otherwise the player picks a
+//! sensitivity (short press or the encoder to change, long press or the
+//! encoder's switch to keep) and the resting reading and the matching
+//! threshold go into the settings, where `accel_to_game_coords` picks them
+//! up.
+//!
+//! The game loop steps the wizard once a frame like any other state, so the
+//! watchdog heartbeat keeps going. Pages are drawn on the Layer 2 overlay.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::button::ButtonEvent;
+use crate::clock;
+use crate::color;
+use crate::config::{Coord, LCD_HEIGHT, LCD_WIDTH};
+use crate::encoder::MenuNav;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::input_device::TiltCalibration;
+use crate::lang::{self, Msg};
+use crate::log;
+use crate::mpu6050;
+use crate::settings;
+
+// How long the board has to lie still
+const SAMPLE_MS: u32 = 3000;
+// Largest spread between readings on any axis that still counts as still;
+// about 0.06 g at +-2 g full scale
+const STILL_SPREAD: i32 = 1000;
+// How long the closing page stays up
+const DONE_MS: u32 = 1500;
+
+// Names and thresholds offered, least sensitive first
+const SENSITIVITY: [(Msg, i32); 3] = [(Msg::Low, 12000), (Msg::Medium, 8000), (Msg::High, 4000)];
+
+const ROW_HEIGHT: Coord = 32;
+const HINT: Rgb565 = Rgb565::new(20, 40, 20);
+
+#[derive(Copy, Clone)]
+struct Samples {
+    sum: [i32; 3],
+    min: [i32; 3],
+    max: [i32; 3],
+    count: i32,
+}
+
+impl Samples {
+    const EMPTY: Samples = Samples {
+        sum: [0; 3],
+        min: [i32::MAX; 3],
+        max: [i32::MIN; 3],
+        count: 0,
+    };
+
+    fn add(&mut self, reading: [i32; 3]) {
+        for (axis, value) in reading.into_iter().enumerate() {
+            self.sum[axis] += value;
+            self.min[axis] = self.min[axis].min(value);
+            self.max[axis] = self.max[axis].max(value);
+        }
+        self.count += 1;
+    }
+
+    fn is_still(&self) -> bool {
+        (0..3).all(|axis| self.max[axis] - self.min[axis] <= STILL_SPREAD)
+    }
+
+    fn mean(&self) -> [i32; 3] {
+        let n = self.count.max(1);
+        [self.sum[0] / n, self.sum[1] / n, self.sum[2] / n]
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Step {
+    // Waiting for the board to be laid down and the button pressed
+    Intro,
+    Sampling {
+        start: u32,
+        samples: Samples,
+    },
+    // Picking a sensitivity for the offset just measured
+    Sensitivity {
+        offset: [i32; 3],
+        temp: Option<i32>,
+        choice: usize,
+    },
+    // Closing message, then back to the game
+    Done {
+        start: u32,
+    },
+}
+
+pub struct Wizard {
+    step: Step,
+    // Without the MPU6050 there is nothing to calibrate
+    sensor: bool,
+}
+
+impl Wizard {
+    pub fn new() -> Self {
+        let wizard = Wizard {
+            step: Step::Intro,
+            sensor: mpu6050::is_present(),
+        };
+        if wizard.sensor {
+            wizard.draw(None);
+        } else {
+            draw_page(
+                &[lang::text(Msg::NoTiltSensor)],
+                lang::text(Msg::PressToGoBack),
+            );
+        }
+        wizard
+    }
+
+    // One frame of the wizard; true once it is over, saved or not. A long
+    // press before the end leaves the old calibration alone. The encoder's
+    // switch stands in for a short press and its turns pick the
+    // sensitivity.
+    pub fn update(&mut self, button: Option<ButtonEvent>, knob: Option<MenuNav>) -> bool {
+        if !self.sensor {
+            return button.is_some() || knob == Some(MenuNav::Press);
+        }
+        let now = clock::millis();
+        match self.step {
+            Step::Intro => match (button, knob) {
+                (Some(ButtonEvent::Short), _) | (_, Some(MenuNav::Press)) => {
+                    self.start_sampling(now)
+                }
+                (Some(_), _) => return true,
+                _ => {}
+            },
+            Step::Sampling { start, mut samples } => {
+                if button == Some(ButtonEvent::Long) {
+                    return true;
+                }
+                let sample = match mpu6050::latest_accel() {
+                    Some(accel) => Ok(accel),
+                    None => mpu6050::read_accel_data(),
+                };
+                if let Ok(a) = sample {
+                    samples.add([a.accel_x, a.accel_y, a.accel_z]);
+                }
+                if !samples.is_still() {
+                    self.step = Step::Intro;
+                    self.draw(Some(lang::text(Msg::MovedTryAgain)));
+                } else if now.wrapping_sub(start) >= SAMPLE_MS && samples.count > 0 {
+                    // Start from the sensitivity in use
+                    let threshold = settings::get().tilt.threshold;
+                    let choice = SENSITIVITY
+                        .iter()
+                        .position(|&(_, t)| t == threshold)
+                        .unwrap_or(1);
+                    self.step = Step::Sensitivity {
+                        offset: samples.mean(),
+                        // For drift compensation as the board warms up
+                        temp: mpu6050::read_temperature().ok(),
+                        choice,
+                    };
+                    self.draw(None);
+                } else {
+                    self.step = Step::Sampling { start, samples };
+                }
+            }
+            Step::Sensitivity {
+                offset,
+                temp,
+                choice,
+            } => match (button, knob) {
+                (_, Some(MenuNav::Next)) | (_, Some(MenuNav::Prev)) => {
+                    let last = SENSITIVITY.len() - 1;
+                    let choice = if knob == Some(MenuNav::Next) {
+                        (choice + 1).min(last)
+                    } else {
+                        choice.saturating_sub(1)
+                    };
+                    self.step = Step::Sensitivity {
+                        offset,
+                        temp,
+                        choice,
+                    };
+                    self.draw(None);
+                }
+                (Some(ButtonEvent::Short), _) => {
+                    let choice = (choice + 1) % SENSITIVITY.len();
+                    self.step = Step::Sensitivity {
+                        offset,
+                        temp,
+                        choice,
+                    };
+                    self.draw(None);
+                }
+                (Some(ButtonEvent::Long), _) | (_, Some(MenuNav::Press)) => {
+                    let tilt = TiltCalibration {
+                        offset,
+                        threshold: SENSITIVITY[choice].1,
+                        temp,
+                    };
+                    log::info!(
+                        "tilt calibrated: offset {:?}, threshold {}",
+                        tilt.offset,
+                        tilt.threshold
+                    );
+                    settings::update(|settings| settings.tilt = tilt);
+                    self.step = Step::Done { start: now };
+                    self.draw(None);
+                }
+                _ => {}
+            },
+            Step::Done { start } => return now.wrapping_sub(start) >= DONE_MS,
+        }
+        false
+    }
+
+    fn start_sampling(&mut self, now: u32) {
+        self.step = Step::Sampling {
+            start: now,
+            samples: Samples::EMPTY,
+        };
+        self.draw(None);
+    }
+
+    fn draw(&self, note: Option<&str>) {
+        match self.step {
+            Step::Intro => {
+                let hint = lang::text(Msg::PressStartHoldCancel);
+                let lay = lang::text(Msg::LayTheBoard);
+                let will = lang::text(Msg::AsYouWill);
+                let hold = lang::text(Msg::HoldIt);
+                match note {
+                    Some(note) => draw_page(&[note, lay, will, hold], hint),
+                    None => draw_page(&[lay, will, hold], hint),
+                }
+            }
+            Step::Sampling { .. } => {
+                draw_page(&[lang::text(Msg::HoldStill)], lang::text(Msg::HoldToCancel))
+            }
+            Step::Sensitivity { choice, .. } => {
+                let mut line: FmtBuf<20> = FmtBuf::new();
+                let _ = write!(line, "< {} >", lang::text(SENSITIVITY[choice].0));
+                draw_page(
+                    &[lang::text(Msg::Sensitivity), line.as_str()],
+                    lang::text(Msg::PressChangeHoldSave),
+                );
+            }
+            Step::Done { .. } => draw_page(&[lang::text(Msg::Calibrated)], ""),
+        }
+    }
+}
+
+// Title, centered lines under it, and a small hint along the bottom
+fn draw_page(lines: &[&str], hint: &str) {
+    let mut fb = FrameBuffer::overlay();
+    let veil = fb.encode_argb(color::VEIL);
+    fb.fill(veil);
+
+    let center_x = LCD_WIDTH as Coord / 2;
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let big = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let small = MonoTextStyle::new(&FONT_6X10, HINT);
+
+    let top = LCD_HEIGHT as Coord / 2 - ROW_HEIGHT * (lines.len() as Coord + 1) / 2;
+    let _ = Text::with_text_style(
+        lang::text(Msg::CalibrateTitle),
+        Point::new(center_x, top),
+        big,
+        centered,
+    )
+    .draw(&mut fb);
+    for (i, line) in lines.iter().enumerate() {
+        let y = top + ROW_HEIGHT * (i as Coord + 1);
+        let _ = Text::with_text_style(line, Point::new(center_x, y), big, centered).draw(&mut fb);
+    }
+    let bottom = LCD_HEIGHT as Coord - ROW_HEIGHT;
+    let _ =
+        Text::with_text_style(hint, Point::new(center_x, bottom), small, centered).draw(&mut fb);
+
+    framebuffer::flush();
+}
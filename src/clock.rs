@@ -1,14 +1,25 @@
 //! Basic clock + SysTick setup matching libopencm3 example assumptions
+use core_logic::pllsai::PllSai;
 use cortex_m::peripheral::syst::SystClkSource;
-use cortex_m::peripheral::SYST;
+use cortex_m::peripheral::{DWT, SYST};
+use cortex_m_rt::exception;
 use stm32f4::stm32f429 as pac;
 
+use crate::error::HwError;
+use crate::executor;
+use crate::i2c;
+use crate::lcd;
+use crate::ltdc_check::PanelProfile;
+use crate::profiler::{self, Phase};
+use crate::resources;
+use crate::scheduler;
+
 // Configure system clock to 168MHz from 8MHz HSE, matching libopencm3's rcc_clock_setup_pll
 pub fn setup_system_clocks_168mhz() {
-    let dp = unsafe { pac::Peripherals::steal() };
-    let rcc = dp.RCC;
-    let pwr = dp.PWR;
-    let flash = dp.FLASH;
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+    let pwr = &dp.PWR;
+    let flash = &dp.FLASH;
 
     // Enable HSE
     rcc.cr.modify(|_, w| w.hseon().on());
@@ -38,7 +49,7 @@ pub fn setup_system_clocks_168mhz() {
         w.pllsrc()
             .hse()
             .pllm()
-            .bits(8)
+            .bits(PLL_M)
             .plln()
             .bits(336)
             .pllp()
@@ -68,34 +79,105 @@ pub fn setup_system_clocks_168mhz() {
     while !rcc.cfgr.read().sws().is_pll() {}
 }
 
+// STOP mode leaves the core on HSI with HSE and both PLLs off; bring the
+// 168 MHz clock tree and the LTDC pixel clock back
+pub fn restore_after_stop() {
+    setup_system_clocks_168mhz();
+    setup_pllsai_for_ltdc();
+}
+
 // Setup SysTick for basic timing - returns the configured SYST peripheral
 pub fn setup(mut syst: SYST) -> SYST {
     // Configure SysTick to tick every millisecond at 168MHz
-    // SysTick reload = (168MHz / 1000Hz) - 1 = 167999
-    syst.set_reload(167_999);
+    syst.set_reload(CYCLES_PER_MS - 1);
     syst.clear_current();
     syst.set_clock_source(SystClkSource::Core);
     syst.enable_counter();
-    // Note: We're not enabling interrupts for this simple implementation
+    // The SysTick exception below keeps the millisecond count
+    syst.enable_interrupt();
+
+    // Cycle counter for cycles(), micros() and the delays
+    let mut cp = unsafe { cortex_m::Peripherals::steal() };
+    cp.DCB.enable_trace();
+    DWT::unlock();
+    cp.DWT.set_cycle_count(0);
+    cp.DWT.enable_cycle_counter();
     syst
 }
 
+static mut MILLIS: u32 = 0;
+
+// Milliseconds since boot (wraps after ~49 days)
+pub fn millis() -> u32 {
+    unsafe { core::ptr::read_volatile(&raw const MILLIS) }
+}
+
+#[exception]
+fn SysTick() {
+    unsafe { MILLIS = MILLIS.wrapping_add(1) };
+    executor::on_tick(millis());
+    scheduler::on_tick(millis());
+
+    let now = cycles();
+    unsafe {
+        if now < LAST_CYCLES {
+            CYCLE_WRAPS += 1;
+        }
+        LAST_CYCLES = now;
+    }
+}
+
+// Divides HSE down to the 1 MHz input of both PLLs
+const PLL_M: u8 = 8;
+
+// Input to both PLLs
+const PLL_INPUT_HZ: u32 = HSE_HZ / PLL_M as u32;
+
+// Refresh the LTDC starts the panel at
+pub const BOOT_REFRESH_HZ: u32 = 70;
+
+const BOOT_PROFILE: PanelProfile = PanelProfile::of(lcd::BOOT_PANEL);
+
+// Pixel clock for that refresh, about 6.6 MHz on the DISCO panel
+pub const LTDC_TARGET_HZ: u32 = BOOT_PROFILE.pclk_hz_at(BOOT_REFRESH_HZ);
+// What PLLSAI makes of it; checked when the firmware builds
+pub const LTDC_HZ: u32 = match PllSai::solve(PLL_INPUT_HZ, LTDC_TARGET_HZ) {
+    Some(pll) => pll.hz(PLL_INPUT_HZ),
+    None => panic!("PLLSAI cannot run from this PLL input"),
+};
+
+// The boot check (ltdc_check::run_at_boot) would flag anything else; the
+// boot panel is Panel::DISCO unless the build picks another
+const _: () = assert!(
+    LTDC_HZ >= BOOT_PROFILE.pclk_min_hz() && LTDC_HZ <= BOOT_PROFILE.pclk_max_hz,
+    "boot pixel clock is outside the panel's range"
+);
+
+// Last pixel clock asked for, set up again after STOP, and what it came to
+static mut PIXEL_TARGET: u32 = LTDC_TARGET_HZ;
+static mut PIXEL_HZ: u32 = LTDC_HZ;
+
 // Configure PLLSAI for LTDC pixel clock
 pub fn setup_pllsai_for_ltdc() {
-    let dp = unsafe { pac::Peripherals::steal() };
-    let rcc = dp.RCC;
-
-    // Configure PLLSAI for LCD timing
-    // We only use busy-wait delays for now.
-
-    // PLLSAI configuration for LTDC - back to working values
-    // This is a simplified setup - adjust based on your exact timing requirements
-    rcc.pllsaicfgr.modify(|_, w| unsafe {
-        w.pllsain()
-            .bits(216) // VCO frequency (more stable)
-            .pllsair()
-            .bits(6) // Division factor for LCD clock
-    });
+    set_pixel_clock(unsafe { PIXEL_TARGET });
+}
+
+// Program PLLSAI for the pixel clock nearest `target_hz` it can make
+// (core_logic::pllsai) and return that. PLLSAI stops while it is changed,
+// so the LTDC misses a few lines. The target is kept for waking from STOP.
+pub fn set_pixel_clock(target_hz: u32) -> u32 {
+    let Some(pll) = PllSai::solve(PLL_INPUT_HZ, target_hz) else {
+        return unsafe { PIXEL_HZ };
+    };
+    let rcc = &resources::pac().RCC;
+
+    // N and R only take while PLLSAI is off
+    rcc.cr.modify(|_, w| w.pllsaion().off());
+    while rcc.cr.read().pllsairdy().is_ready() {}
+    rcc.pllsaicfgr
+        .modify(|_, w| unsafe { w.pllsain().bits(pll.n).pllsair().bits(pll.r) });
+    rcc.dckcfgr
+        .modify(|_, w| w.pllsaidivr().bits(pll.divr_bits));
 
     // Enable PLLSAI
     rcc.cr.modify(|_, w| w.pllsaion().on());
@@ -107,15 +189,218 @@ pub fn setup_pllsai_for_ltdc() {
     // Verify clocks are ready
     debug_assert!(rcc.cr.read().pllsairdy().is_ready());
     debug_assert!(rcc.apb2enr.read().ltdcen().is_enabled());
+
+    let hz = pll.hz(PLL_INPUT_HZ);
+    unsafe {
+        PIXEL_TARGET = target_hz;
+        PIXEL_HZ = hz;
+    }
+    hz
+}
+
+// The pixel clock set_pixel_clock last settled on
+pub fn pixel_clock_hz() -> u32 {
+    unsafe { PIXEL_HZ }
+}
+
+const HSI_HZ: u32 = 16_000_000;
+pub const HSE_HZ: u32 = 8_000_000;
+
+/// Clocks as the RCC is actually programmed, in Hz
+#[derive(Copy, Clone)]
+pub struct Frequencies {
+    pub sysclk: u32,
+    // AHB
+    pub hclk: u32,
+    pub pclk1: u32,
+    pub pclk2: u32,
+    // LTDC pixel clock; 0 while PLLSAI is off
+    pub ltdc: u32,
+}
+
+// Read back the clock tree, for the hardware report and the diagnostics
+// page
+pub fn report() -> Frequencies {
+    let rcc = &resources::pac().RCC;
+    let cfgr = rcc.cfgr.read();
+    let sysclk = match cfgr.sws().bits() {
+        0b01 => HSE_HZ,
+        0b10 => {
+            let pllcfgr = rcc.pllcfgr.read();
+            let input = if pllcfgr.pllsrc().bit_is_set() {
+                HSE_HZ
+            } else {
+                HSI_HZ
+            };
+            let pllm = pllcfgr.pllm().bits() as u32;
+            let plln = pllcfgr.plln().bits() as u32;
+            // PLLP 00..11 divides by 2, 4, 6, 8
+            let pllp = (pllcfgr.pllp().bits() as u32 + 1) * 2;
+            input
+                .checked_div(pllm)
+                .map_or(0, |vco_in| vco_in * plln / pllp)
+        }
+        _ => HSI_HZ,
+    };
+    // HPRE 1xxx divides by 2..512, skipping 32; PPREx 1xx by 2..16
+    let hpre = cfgr.hpre().bits() as u32;
+    let hclk = match hpre {
+        0b1000..=0b1011 => sysclk >> (hpre - 0b0111),
+        0b1100..=0b1111 => sysclk >> (hpre - 0b0110),
+        _ => sysclk,
+    };
+    let apb = |ppre: u32| match ppre {
+        0b100..=0b111 => hclk >> (ppre - 0b011),
+        _ => hclk,
+    };
+    Frequencies {
+        sysclk,
+        hclk,
+        pclk1: apb(cfgr.ppre1().bits() as u32),
+        pclk2: apb(cfgr.ppre2().bits() as u32),
+        ltdc: ltdc_hz(rcc),
+    }
+}
+
+// PLLSAI's LCD output divided down by PLLSAIDIVR; it shares PLLM with the
+// main PLL
+fn ltdc_hz(rcc: &pac::RCC) -> u32 {
+    if rcc.cr.read().pllsairdy().is_not_ready() {
+        return 0;
+    }
+    let pllcfgr = rcc.pllcfgr.read();
+    let input = if pllcfgr.pllsrc().bit_is_set() {
+        HSE_HZ
+    } else {
+        HSI_HZ
+    };
+    let pllm = pllcfgr.pllm().bits() as u32;
+    let saicfg = rcc.pllsaicfgr.read();
+    let plln = saicfg.pllsain().bits() as u32;
+    let pllr = saicfg.pllsair().bits() as u32;
+    // PLLSAIDIVR 00..11 divides by 2, 4, 8, 16
+    let divr = 2 << rcc.dckcfgr.read().pllsaidivr().bits();
+    if pllm == 0 || pllr == 0 {
+        return 0;
+    }
+    input / pllm * plln / pllr / divr
+}
+
+// Check at boot that the tree came out as set up: SYSCLK and AHB at full
+// speed and the pixel clock what set_pixel_clock worked out
+pub fn validate() -> Result<Frequencies, HwError> {
+    let f = report();
+    if f.sysclk != SYSCLK_HZ || f.hclk != SYSCLK_HZ || f.ltdc != pixel_clock_hz() {
+        return Err(HwError::ClockNotReady);
+    }
+    Ok(f)
+}
+
+/// What the MCO1 pin (PA8) puts out for a scope or counter
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Mco {
+    // The 8 MHz crystal as it is
+    Hse,
+    // The main PLL, SYSCLK before the AHB prescaler, divided by 5 to stay
+    // within what the pin can drive (33.6 MHz)
+    Pll,
+}
+
+impl Mco {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "hse" => Some(Mco::Hse),
+            "pll" | "sysclk" => Some(Mco::Pll),
+            _ => None,
+        }
+    }
+
+    // Frequency on the pin
+    pub fn hz(self) -> u32 {
+        match self {
+            Mco::Hse => HSE_HZ,
+            Mco::Pll => SYSCLK_HZ / 5,
+        }
+    }
+}
+
+// Put `source` out on MCO1, or with None hand PA8 back. PA8 is also I2C3's
+// SCL to the touch controller, so touch is gone while MCO is on; turning it
+// off sets I2C3 up again.
+pub fn set_mco(source: Option<Mco>) {
+    let dp = resources::pac();
+    let Some(source) = source else {
+        i2c::init_i2c3();
+        return;
+    };
+    dp.RCC.cfgr.modify(|_, w| match source {
+        Mco::Hse => w.mco1().hse().mco1pre().div1(),
+        Mco::Pll => w.mco1().pll().mco1pre().div5(),
+    });
+    dp.RCC.ahb1enr.modify(|_, w| w.gpioaen().enabled());
+    // AF0, push-pull at full speed
+    dp.GPIOA.afrh.modify(|_, w| w.afrh8().bits(0));
+    dp.GPIOA.otyper.modify(|_, w| w.ot8().push_pull());
+    dp.GPIOA.pupdr.modify(|_, w| w.pupdr8().floating());
+    dp.GPIOA
+        .ospeedr
+        .modify(|_, w| w.ospeedr8().very_high_speed());
+    dp.GPIOA.moder.modify(|_, w| w.moder8().alternate());
+}
+
+// Core clock once setup_system_clocks_168mhz has run. Delays and timestamps
+// before that (HSI, 16 MHz) run about 10x long.
+pub const SYSCLK_HZ: u32 = 168_000_000;
+pub const CYCLES_PER_MS: u32 = SYSCLK_HZ / 1_000;
+pub const CYCLES_PER_US: u32 = SYSCLK_HZ / 1_000_000;
+
+// Free-running core cycle count (DWT CYCCNT, started by setup).
+// Wraps every ~25 s, so only use it for differences with wrapping_sub.
+pub fn cycles() -> u32 {
+    cortex_m::peripheral::DWT::cycle_count()
+}
+
+// CYCCNT wraps seen by the SysTick handler, which runs far more often than
+// the counter wraps, and the count it last saw
+static mut CYCLE_WRAPS: u32 = 0;
+static mut LAST_CYCLES: u32 = 0;
+
+// Cycle count that does not wrap in practice
+pub fn cycles64() -> u64 {
+    cortex_m::interrupt::free(|_| {
+        let now = cycles();
+        let mut wraps = unsafe { CYCLE_WRAPS };
+        if now < unsafe { LAST_CYCLES } {
+            // Wrapped since the last tick
+            wraps += 1;
+        }
+        ((wraps as u64) << 32) | now as u64
+    })
+}
+
+// Microseconds since the cycle counter started
+pub fn micros() -> u64 {
+    cycles64() / CYCLES_PER_US as u64
+}
+
+// Busy-wait on the cycle counter
+pub fn delay_us(us: u32) {
+    let _idle = profiler::scope(Phase::Idle);
+    // Wait in steps short enough that wrapping_sub stays exact
+    const STEP_US: u32 = 10_000_000;
+    let mut left = us;
+    while left > 0 {
+        let step = left.min(STEP_US);
+        let start = cycles();
+        let wait = step * CYCLES_PER_US;
+        while cycles().wrapping_sub(start) < wait {}
+        left -= step;
+    }
 }
 
-// Crude busy-wait millisecond delay assuming SysTick at 1kHz
 pub fn delay_ms(ms: u32) {
-    // Fallback: busy loop scaled for ~168MHz (very rough)
-    //let cycles = 168_000 * ms;
-    let mut n = ms;
-    while n != 0 {
-        cortex_m::asm::nop();
-        n -= 1;
+    let _idle = profiler::scope(Phase::Idle);
+    for _ in 0..ms {
+        delay_us(1_000);
     }
 }
@@ -1,8 +1,17 @@
 #![allow(dead_code)]
 
-pub const BACKGROUND: u16 = 0x9F5E;
-pub const WHITE: u16 = 0xFFFF;
-pub const BLACK: u16 = 0x0000;
-pub const RED: u16 = 0xF800;
+// Colors that stay the same whatever the theme; the rest are in theme.rs.
+// The types and color math are shared with core_logic.
 
-pub const SCORE: u16 = 0xE71C;
+pub use core_logic::color::*;
+
+pub const WHITE: u16 = Rgb565::WHITE.0;
+pub const BLACK: u16 = Rgb565::BLACK.0;
+pub const RED: u16 = Rgb565::RED.0;
+pub const GREEN: u16 = Rgb565::GREEN.0;
+
+// The sky color in the flash sprites, drawn as transparent
+pub const SPRITE_KEY: u16 = 0x9F5E;
+
+// Dark blue behind the menu, stats and versus screens
+pub const VEIL: Argb8888 = Argb8888(0xFF10_1830);
@@ -0,0 +1,130 @@
+//! Custom courses, kept in flash
+//!
+//! The course editor saves up to `SLOTS` patterns (core_logic::course) in
+//! the flash `store`, a record per slot holding `Pattern::to_words`, next to
+//! the settings. A slot with no record holds an empty course.
+//!
+//! Before the store each save appended a block of its own to what is now
+//! the store's sector A, the newest intact block of a slot winning; those
+//! are still read for slots the store has nothing for. Block layout, in
+//! words:
+//!   0     MAGIC
+//!   1     slot
+//!   2..   the pattern
+//!   last  hw_crc32 of the words before it
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::course::{self, Pattern};
+
+use crate::crc;
+use crate::flash;
+use crate::log;
+use crate::sdram::arena::Region;
+use crate::store;
+
+pub const SLOTS: usize = 4;
+
+const MAGIC: u32 = 0x4352_5345; // "CRSE"
+const HEADER_WORDS: usize = 2;
+const BLOCK_WORDS: usize = HEADER_WORDS + course::WORDS + 1;
+const BLOCK_BYTES: u32 = BLOCK_WORDS as u32 * 4;
+
+static mut SAVED: [Pattern; SLOTS] = [Pattern::EMPTY; SLOTS];
+// What the course game plays
+static mut PLAYING: Pattern = Pattern::EMPTY;
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Pattern>() * (SLOTS + 1);
+
+// The sector the course blocks of older firmware are in
+const LEGACY: Region = flash::STORE[0];
+
+fn read_word(offset: u32) -> u32 {
+    // SAFETY: the store is internal flash outside the program image and
+    // always readable; callers keep `offset` inside it
+    unsafe { core::ptr::read_volatile((LEGACY.base + offset) as *const u32) }
+}
+
+// The slot and pattern of the block at `offset`, if it is an intact one
+fn decode(offset: u32) -> Option<(usize, Pattern)> {
+    let mut block = [0; BLOCK_WORDS];
+    for (i, word) in block.iter_mut().enumerate() {
+        *word = read_word(offset + i as u32 * 4);
+    }
+    let (body, crc) = block.split_at(BLOCK_WORDS - 1);
+    if crc[0] != crc::hw_crc32(body) {
+        return None;
+    }
+    let slot = body[1] as usize;
+    let pattern = Pattern::from_words(&body[HEADER_WORDS..])?;
+    (slot < SLOTS).then_some((slot, pattern))
+}
+
+// Read every block older firmware left into `saved`
+fn legacy(saved: &mut [Pattern; SLOTS]) {
+    let mut offset = 0;
+    while offset + BLOCK_BYTES <= LEGACY.size {
+        if read_word(offset) != MAGIC {
+            return;
+        }
+        // A block whose save was cut short fails its CRC and is passed over
+        if let Some((slot, pattern)) = decode(offset) {
+            saved[slot] = pattern;
+        }
+        offset += BLOCK_BYTES;
+    }
+}
+
+// Read the saved courses; the first one is played until the editor picks
+// another. Call once at boot.
+pub fn load() {
+    let mut saved = [Pattern::EMPTY; SLOTS];
+    legacy(&mut saved);
+    let mut words = [0; store::MAX_PAYLOAD];
+    for (slot, pattern) in saved.iter_mut().enumerate() {
+        let Some(len) = store::read(store::course(slot), &mut words) else {
+            continue;
+        };
+        match Pattern::from_words(&words[..len]) {
+            Some(stored) => *pattern = stored,
+            None => log::warn!("course {} unreadable", slot + 1),
+        }
+    }
+    unsafe {
+        SAVED = saved;
+        PLAYING = saved[0];
+    }
+}
+
+pub fn get(slot: usize) -> Pattern {
+    unsafe { SAVED[slot % SLOTS] }
+}
+
+// What `slot` holds as the store keeps it, into `out`; None for an empty
+// slot, which needs no record
+pub fn payload(slot: usize, out: &mut [u32]) -> Option<usize> {
+    let pattern = get(slot);
+    let out = out
+        .get_mut(..course::WORDS)
+        .filter(|_| !pattern.is_empty())?;
+    out.copy_from_slice(&pattern.to_words());
+    Some(out.len())
+}
+
+// Store `pattern` in `slot`. Once in a few thousand saves a sector is
+// erased first, a second or two with the CPU stalled.
+pub fn save(slot: usize, pattern: &Pattern) -> Result<(), ()> {
+    let slot = slot % SLOTS;
+    unsafe { SAVED[slot] = *pattern };
+    store::write(store::course(slot), &pattern.to_words())
+}
+
+// Play `pattern` in the next course game
+pub fn set_playing(pattern: &Pattern) {
+    unsafe { PLAYING = *pattern };
+}
+
+pub fn playing() -> &'static Pattern {
+    unsafe { &PLAYING }
+}
@@ -0,0 +1,40 @@
+#![allow(dead_code)]
+
+use crate::resources;
+
+// CRC-32 (IEEE, as zlib.crc32) over a byte stream
+pub fn crc32(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// CRC-32/MPEG-2 from the CRC unit, fed a word at a time: same polynomial as
+// crc32 but not bit-reflected and with no final inversion, so the two never
+// match. Cheap enough to check stored blocks on every read.
+pub fn hw_crc32(words: &[u32]) -> u32 {
+    hw_crc32_words(words.iter().copied())
+}
+
+// As hw_crc32, for words worked out on the way; core_logic::crc::mpeg2
+// gives the same on the host
+pub fn hw_crc32_words(words: impl IntoIterator<Item = u32>) -> u32 {
+    let dp = resources::pac();
+    dp.RCC.ahb1enr.modify(|_, w| w.crcen().enabled());
+    dp.CRC.cr.write(|w| w.reset().reset());
+    for word in words {
+        dp.CRC.dr.write(|w| w.dr().bits(word));
+    }
+    dp.CRC.dr.read().dr().bits()
+}
+
+// hw_crc32 of `bytes` as little-endian words, the last zero-padded
+pub fn hw_crc32_bytes(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    hw_crc32_words(core_logic::crc::le_words(bytes))
+}
@@ -0,0 +1,153 @@
+//! Sound effect output through the on-chip DAC
+//!
+//! DAC channel 2 drives PA5 (mono; channel 1's PA4 is the LTDC VSYNC line on
+//! the DISCO board). TIM6 triggers one conversion per sample, and DMA1
+//! stream 6 feeds the holding register from two buffers in double buffer
+//! mode. The transfer-complete interrupt mixes the stereo mixer output down
+//! into the idle buffer, the same way the I2S driver refills its halves.
+//!
+//! PA5 needs an RC low-pass and a small amplifier before a speaker; the DAC
+//! output buffer cannot drive one directly.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::audio;
+use crate::resources;
+
+// Output rate; TIM6 below is set up for this value
+pub const OUTPUT_RATE: u32 = 16_000;
+
+// Samples per DMA buffer (16 ms at 16 kHz)
+const FRAMES: usize = 256;
+
+// TIM6 runs from the 84 MHz APB1 timer clock: 84 MHz / 5250 = 16 kHz
+const TIM6_PERIOD: u32 = 84_000_000 / OUTPUT_RATE;
+
+static mut BUFFERS: [[u16; FRAMES]; 2] = [[0x8000; FRAMES]; 2];
+// Stereo frames from the mixer, folded to mono into BUFFERS
+static mut STEREO: [i16; FRAMES * 2] = [0; FRAMES * 2];
+
+pub fn init() {
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+    rcc.ahb1enr
+        .modify(|_, w| w.gpioaen().enabled().dma1en().enabled());
+    rcc.apb1enr
+        .modify(|_, w| w.dacen().enabled().tim6en().enabled());
+
+    // PA5 to analog; the DAC takes over the pin once enabled
+    dp.GPIOA
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() | (0b11 << 10)) });
+
+    // Prime both buffers so the first one out is already mixed audio
+    unsafe {
+        fill(0);
+        fill(1);
+    }
+
+    // DAC channel 2: TIM6 TRGO trigger (TSEL2 = 000), output buffer on, DMA
+    let dac = &dp.DAC;
+    dac.cr.modify(|_, w| {
+        w.tsel2()
+            .bits(0)
+            .ten2()
+            .enabled()
+            .boff2()
+            .clear_bit()
+            .dmaen2()
+            .enabled()
+    });
+
+    // DMA1 stream 6 channel 7 = DAC2, circular double buffer into the
+    // 12-bit left-aligned holding register
+    let stream = &dp.DMA1.st[6];
+    stream.cr.modify(|_, w| w.en().disabled());
+    while stream.cr.read().en().is_enabled() {}
+    stream
+        .par
+        .write(|w| unsafe { w.pa().bits(&dac.dhr12l2 as *const _ as u32) });
+    unsafe {
+        stream
+            .m0ar
+            .write(|w| w.m0a().bits(BUFFERS[0].as_ptr() as u32));
+        stream
+            .m1ar
+            .write(|w| w.m1a().bits(BUFFERS[1].as_ptr() as u32));
+    }
+    stream.ndtr.write(|w| w.ndt().bits(FRAMES as u16));
+    stream.cr.write(|w| {
+        w.chsel()
+            .bits(7)
+            .dbm()
+            .enabled()
+            .ct()
+            .memory0()
+            .pl()
+            .high()
+            .msize()
+            .bits16()
+            .psize()
+            .bits16()
+            .minc()
+            .incremented()
+            .pinc()
+            .fixed()
+            .circ()
+            .enabled()
+            .dir()
+            .memory_to_peripheral()
+            .tcie()
+            .enabled()
+    });
+    stream.cr.modify(|_, w| w.en().enabled());
+
+    dac.cr.modify(|_, w| w.en2().enabled());
+
+    // TIM6 update event as TRGO, one per sample
+    let tim = &dp.TIM6;
+    tim.psc.write(|w| w.psc().bits(0));
+    tim.arr.write(|w| unsafe { w.bits(TIM6_PERIOD - 1) });
+    tim.cr2.modify(|_, w| w.mms().update());
+    tim.cr1.modify(|_, w| w.cen().enabled());
+
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::DMA1_STREAM6) };
+}
+
+// Stop output and park the DAC at mid-scale so the speaker does not click
+pub fn stop() {
+    let dp = resources::pac();
+    cortex_m::peripheral::NVIC::mask(pac::Interrupt::DMA1_STREAM6);
+    dp.TIM6.cr1.modify(|_, w| w.cen().disabled());
+    dp.DMA1.st[6].cr.modify(|_, w| w.en().disabled());
+    dp.DAC
+        .cr
+        .modify(|_, w| w.dmaen2().disabled().ten2().disabled());
+    dp.DAC.dhr12l2.write(|w| unsafe { w.bits(0x8000) });
+}
+
+// Mix the next block and fold it to unsigned mono for the DAC
+unsafe fn fill(idx: usize) {
+    audio::mix(&mut STEREO, OUTPUT_RATE);
+    for (out, frame) in BUFFERS[idx].iter_mut().zip(STEREO.chunks_exact(2)) {
+        let mono = (frame[0] as i32 + frame[1] as i32) >> 1;
+        *out = (mono + 0x8000) as u16;
+    }
+}
+
+#[interrupt]
+fn DMA1_STREAM6() {
+    let dp = resources::pac();
+    dp.DMA1.hifcr.write(|w| w.ctcif6().set_bit());
+
+    // CT names the buffer DMA is reading now; refill the one it just left
+    let idle = if dp.DMA1.st[6].cr.read().ct().is_memory0() {
+        1
+    } else {
+        0
+    };
+    unsafe { fill(idle) };
+}
@@ -0,0 +1,197 @@
+//! Hidden debug menu
+//!
+//! Holding the button on the title screen opens the stats page at a long
+//! press and this menu at `HOLD_MS`. It turns on and off the drawing aids the
+//! renderer consults (hitbox outlines, dirty-rect outlines, the profiler's
+//! frame-time overlay) and runs the SDRAM spot-check test, the drawing
+//! benchmarks (`bench`, on a page of their own and over the serial link),
+//! the DMA2D against CPU check (`ab_check`), the panel test patterns
+//! (`test_pattern`, which close the menu) or a deliberate panic, to see
+//! the fault handler at work, and starts the game over without a reset
+//! (`system`). Like the shell it is in English only. It is
+//! drawn with `ui` on the overlay already up for the stats page.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use core_logic::test_pattern::Pattern;
+
+use crate::ab_check;
+use crate::bench::{self, Timing};
+use crate::button::ButtonEvent;
+use crate::encoder::MenuNav;
+use crate::entity;
+use crate::fmt_buf::FmtBuf;
+use crate::log;
+use crate::player;
+use crate::profiler;
+use crate::sdram;
+use crate::system;
+use crate::test_pattern;
+use crate::ui::{self, Focus, Nav, Ui};
+
+// Entries, in display order
+const ITEM_HITBOXES: usize = 0;
+const ITEM_DIRTY: usize = 1;
+const ITEM_FPS: usize = 2;
+const ITEM_SDRAM: usize = 3;
+const ITEM_BENCH: usize = 4;
+const ITEM_AB: usize = 5;
+const ITEM_PATTERNS: usize = 6;
+const ITEM_PANIC: usize = 7;
+const ITEM_RESTART: usize = 8;
+const ITEM_BACK: usize = 9;
+const ITEMS: usize = 10;
+
+pub struct DebugMenu {
+    focus: Focus,
+    // What the last test or benchmark found, shown under the list
+    result: FmtBuf<40>,
+    // Set while the benchmark page is up, until the next input
+    timings: Option<[Timing; bench::BENCHES.len()]>,
+}
+
+impl DebugMenu {
+    pub fn new() -> Self {
+        let mut menu = DebugMenu {
+            focus: Focus::new(),
+            result: FmtBuf::new(),
+            timings: None,
+        };
+        menu.draw(None);
+        menu
+    }
+
+    // One frame of the menu; true once Back is picked
+    pub fn update(&mut self, button: Option<ButtonEvent>, knob: Option<MenuNav>) -> bool {
+        let nav = match knob {
+            Some(MenuNav::Next) => Some(Nav::Next),
+            Some(MenuNav::Prev) => Some(Nav::Prev),
+            Some(MenuNav::Press) => Some(Nav::Activate),
+            None => Nav::from_button(button),
+        };
+        let Some(nav) = nav else {
+            return false;
+        };
+        if self.timings.take().is_some() {
+            self.draw(None);
+            return false;
+        }
+        match self.draw(Some(nav)) {
+            Some(ITEM_BACK) => return true,
+            // The game that owns the menu goes with it
+            Some(ITEM_RESTART) => {
+                system::request_warm_restart();
+                return true;
+            }
+            // The patterns take the screen; the game steps through them
+            Some(ITEM_PATTERNS) if test_pattern::show(Pattern::ALL[0]).is_ok() => return true,
+            Some(item) => {
+                self.select(item);
+                self.draw(None);
+            }
+            None => {}
+        }
+        false
+    }
+
+    fn select(&mut self, item: usize) {
+        self.result.clear();
+        match item {
+            // The bird made for the next game takes this up
+            ITEM_HITBOXES => player::show_hitboxes(!player::hitboxes_shown()),
+            ITEM_DIRTY => entity::show_dirty_rects(!entity::dirty_rects_shown()),
+            ITEM_FPS => profiler::show_overlay(!profiler::overlay_shown()),
+            ITEM_SDRAM if !sdram::available() => {
+                let _ = write!(self.result, "no sdram");
+            }
+            // Only the spot-check block, which nothing else lives in
+            ITEM_SDRAM => {
+                let _ = match sdram::self_test(sdram::SPOT_CHECK_BASE, sdram::SPOT_CHECK_SIZE) {
+                    Ok(()) => write!(self.result, "sdram ok"),
+                    Err(fault) => write!(self.result, "sdram FAIL @{:08x}", fault.addr),
+                };
+                sdram::arm_spot_check();
+            }
+            ITEM_BENCH => {
+                let timings = core::array::from_fn(|i| bench::run(&bench::BENCHES[i]));
+                for timing in &timings {
+                    log::info!("{}", timing);
+                }
+                self.timings = Some(timings);
+            }
+            ITEM_AB if !ab_check::available() => {
+                let _ = write!(self.result, "no sdram");
+            }
+            // Every case goes to the log; the menu has room for the total
+            ITEM_AB => {
+                let mut mismatches = 0;
+                for case in &ab_check::CASES {
+                    let outcome = ab_check::run(case);
+                    log::info!("{}", outcome);
+                    mismatches += outcome.mismatches;
+                }
+                let _ = match mismatches {
+                    0 => write!(self.result, "dma2d = cpu"),
+                    n => write!(self.result, "dma2d/cpu: {}px differ", n),
+                };
+            }
+            ITEM_PATTERNS => {
+                let _ = write!(self.result, "no ltdc");
+            }
+            ITEM_PANIC => panic!("debug menu"),
+            _ => {}
+        }
+    }
+
+    // Draw the menu, moved on by `nav`; the item `nav` activated. The
+    // benchmark page instead while it is up.
+    fn draw(&mut self, nav: Option<Nav>) -> Option<usize> {
+        if let Some(timings) = &self.timings {
+            draw_timings(timings);
+            return None;
+        }
+        let on_off = |on| if on { "on" } else { "off" };
+        let mut hitboxes: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(hitboxes, "Hitboxes: {}", on_off(player::hitboxes_shown()));
+        let mut dirty: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            dirty,
+            "Dirty rects: {}",
+            on_off(entity::dirty_rects_shown())
+        );
+        let mut fps: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(fps, "FPS: {}", on_off(profiler::overlay_shown()));
+
+        let mut ui = Ui::begin(&mut self.focus, nav, ui::centered_top(ITEMS + 2));
+        ui.label("Debug");
+        let chosen = ui.list(&[
+            hitboxes.as_str(),
+            dirty.as_str(),
+            fps.as_str(),
+            "SDRAM test",
+            "Benchmarks",
+            "DMA2D vs CPU",
+            "Test patterns",
+            "Panic",
+            "Restart game",
+            "Back",
+        ]);
+        ui.caption(self.result.as_str());
+        ui.end();
+        chosen
+    }
+}
+
+fn draw_timings(timings: &[Timing]) {
+    let mut ui = Ui::screen(ui::centered_top(timings.len() / 2 + 3));
+    ui.label("Benchmarks");
+    for timing in timings {
+        let mut line: FmtBuf<40> = FmtBuf::new();
+        let _ = write!(line, "{}", timing);
+        ui.caption(line.as_str());
+    }
+    ui.space(1);
+    ui.caption("press to go back");
+    ui.end();
+}
@@ -0,0 +1,142 @@
+//! Diagnostics page
+//!
+//! One screen of numbers for checking a firmware build on the device: frame
+//! times of the last finished session (from the stats store), LTDC error
+//! counters, the clock tree as read back, panel check results, subsystem health, stack and SDRAM use,
+//! then the memory budget report.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use crate::boot_report;
+use crate::budget;
+use crate::clock;
+use crate::config::Coord;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::FrameBuffer;
+use crate::lcd::LcdDriver;
+use crate::ltdc_check;
+use crate::memory;
+use crate::sdram::{self, SdramTest};
+use crate::stats;
+use crate::subsystem;
+
+const LINE_HEIGHT: Coord = 12;
+
+pub fn draw_page() {
+    let mut fb = FrameBuffer::layer1();
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::BLACK)
+        .background_color(Rgb565::WHITE)
+        .build();
+
+    let stats = stats::load();
+    let frames = stats.last_frames;
+    let mut y = 0;
+    let mut line: FmtBuf<40> = FmtBuf::new();
+    let mut draw_line = |line: &FmtBuf<40>| {
+        let _ = Text::with_baseline(line.as_str(), Point::new(0, y), style, Baseline::Top)
+            .draw(&mut fb);
+        y += LINE_HEIGHT;
+    };
+
+    let _ = write!(
+        line,
+        "FRAMES {} (session {})",
+        frames.frames, stats.sessions
+    );
+    draw_line(&line);
+    line.clear();
+    let _ = write!(
+        line,
+        "  p50 {}ms p95 {}ms p99 {}ms",
+        frames.p50_ms, frames.p95_ms, frames.p99_ms
+    );
+    draw_line(&line);
+    line.clear();
+    let _ = write!(line, "  max {}ms", frames.max_ms);
+    draw_line(&line);
+    line.clear();
+    let errors = LcdDriver::error_counts();
+    let _ = write!(
+        line,
+        "LTDC underrun {} terr {}",
+        errors.underruns, errors.transfer_errors
+    );
+    draw_line(&line);
+    line.clear();
+    match sdram::last_fault() {
+        Some(fault) => {
+            let test = match fault.test {
+                SdramTest::DataBus => "data bus",
+                SdramTest::AddressBus => "addr bus",
+                SdramTest::Cells => "cells",
+            };
+            let _ = write!(line, "SDRAM {} FAIL @{:08x}", test, fault.addr);
+        }
+        None => {
+            let _ = write!(line, "SDRAM self-test ok");
+        }
+    }
+    let spot = if sdram::spot_check() { "ok" } else { "BAD" };
+    let _ = write!(line, ", spot {}", spot);
+    draw_line(&line);
+    let clocks = clock::report();
+    let mhz = |hz: u32| hz / 1_000_000;
+    line.clear();
+    let _ = write!(
+        line,
+        "CLK {}/{}/{}/{}M LTDC {}.{}M",
+        mhz(clocks.sysclk),
+        mhz(clocks.hclk),
+        mhz(clocks.pclk1),
+        mhz(clocks.pclk2),
+        mhz(clocks.ltdc),
+        clocks.ltdc / 100_000 % 10
+    );
+    draw_line(&line);
+    let (mismatches, count) = ltdc_check::found();
+    line.clear();
+    let _ = write!(line, "PANEL CHECK {} mismatch", count);
+    draw_line(&line);
+    for mismatch in mismatches {
+        line.clear();
+        let _ = write!(line, "  {}", mismatch);
+        draw_line(&line);
+    }
+    let restarts = subsystem::restarts();
+    for (subsystem, restarts) in subsystem::registry().iter().zip(restarts) {
+        line.clear();
+        let health = subsystem.health_check();
+        let _ = write!(line, "{:<10} {}", subsystem.name(), health.as_str());
+        // Restarts by the supervisor, kept short to fit the line
+        if restarts > 0 {
+            let _ = write!(line, " x{}", restarts);
+        }
+        draw_line(&line);
+    }
+    for (part, error) in boot_report::failures() {
+        line.clear();
+        let _ = write!(line, "BOOT {} {}", part, error);
+        draw_line(&line);
+    }
+    let mem = memory::usage();
+    line.clear();
+    let _ = write!(
+        line,
+        "STACK {}/{} SDRAM {}K",
+        mem.stack_used(),
+        mem.stack_bytes,
+        mem.sdram_used / 1024
+    );
+    draw_line(&line);
+
+    budget::draw_report(0, y + LINE_HEIGHT);
+}
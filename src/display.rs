@@ -1,64 +1,76 @@
 #![allow(dead_code)]
 #![allow(static_mut_refs)]
 
+use crate::backlight;
+use crate::clock;
+use crate::color::{self, Argb8888, Rgb565};
 use crate::config::*;
-use crate::lcd::LcdDriver;
-use core::convert::TryInto;
+use crate::diagnostics;
+use crate::error::HwError;
+use crate::framebuffer::{self, FrameBuffer, Image, ImageTransform, RenderBackend, Scale};
+use crate::ili9341;
+use crate::lane;
+use crate::lcd::{
+    self, Layer, LayerConfig, LcdDriver, PixelFormat, CLUT_SIZE, DISPLAY_MEMORY, HUD_H,
+    LAYER1_FORMAT, LAYER2_H, LAYER2_W, SPRITE_FORMAT,
+};
+use crate::log;
+use crate::ltdc_check;
+use crate::profiler::{self, Phase};
+use crate::resources::ThreadOnly;
+use crate::sdram;
+use crate::spi_render::SpiPanel;
+use crate::subsystem::{Health, Subsystem};
 use core::ffi;
 use core::ffi::c_char;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub use crate::ili9341::GammaProfile;
+pub use core_logic::geometry::Orientation as DisplayOrientation;
+use core_logic::palette::Palette;
+use core_logic::rect::AreaCheck;
+use core_logic::sprite_batch::SpriteBatch;
+
+/// Where the game's drawing ends up
+#[derive(Copy, Clone, PartialEq)]
+pub enum Backend {
+    // Layer 1 in SDRAM, scanned out by LTDC over the RGB bus
+    Ltdc,
+    // Straight into the panel's GRAM over SPI; no SDRAM or LTDC needed
+    Spi,
+}
 
-#[allow(non_camel_case_types)]
-#[derive(Copy, Clone)]
-pub enum DisplayOrientation {
-    Portrait,
-    Landscape,
+// Feature `spi-render` boots straight into SPI rendering
+static mut BACKEND: Backend = if cfg!(feature = "spi-render") {
+    Backend::Spi
+} else {
+    Backend::Ltdc
+};
+
+/// What Layer 2 shows when no menu overlay is up
+#[derive(Copy, Clone, PartialEq)]
+pub enum Plane {
+    // The bird, in a small window moved around the screen
+    Sprite,
+    // The HUD strip over the score bar; the bird is drawn into Layer 1
+    Hud,
 }
 
+static mut PLANE: Plane = Plane::Sprite;
+
+// While the bandwidth saver is on, the pixel clock to go back to
+static mut SAVER: Option<u32> = None;
+// A menu has Layer 2, which the bandwidth saver lets it keep
+static mut OVERLAY_UP: bool = false;
+
+// What the bandwidth saver leaves of the pixel clock, in percent
+const SAVER_PCLK_PERCENT: u32 = 90;
+
 // ILI9341 LCD display constants for STM32F429ZI Discovery board
 pub const DISPLAY_WIDTH: u32 = 240;
 pub const DISPLAY_HEIGHT: u32 = 320;
 
-// ILI9341 command definitions (correct for STM32F429ZI Discovery board)
-const ILI9341_NOP: u8 = 0x00; // No operation
-const ILI9341_SWRESET: u8 = 0x01; // Software Reset
-const ILI9341_RDDID: u8 = 0x04; // Read Display ID
-const ILI9341_RDDST: u8 = 0x09; // Read Display Status
-const ILI9341_SLPIN: u8 = 0x10; // Enter Sleep Mode
-const ILI9341_SLPOUT: u8 = 0x11; // Sleep Out
-const ILI9341_PTLON: u8 = 0x12; // Partial Mode ON
-const ILI9341_NORON: u8 = 0x13; // Normal Display Mode ON
-const ILI9341_INVOFF: u8 = 0x20; // Display Inversion OFF
-const ILI9341_INVON: u8 = 0x21; // Display Inversion ON
-const ILI9341_GAMMASET: u8 = 0x26; // Gamma Set
-const ILI9341_DISPOFF: u8 = 0x28; // Display OFF
-const ILI9341_DISPON: u8 = 0x29; // Display ON
-const ILI9341_CASET: u8 = 0x2A; // Column Address Set
-const ILI9341_PASET: u8 = 0x2B; // Page Address Set
-const ILI9341_RAMWR: u8 = 0x2C; // Memory Write
-const ILI9341_RAMRD: u8 = 0x2E; // Memory Read
-const ILI9341_MADCTL: u8 = 0x36; // Memory Access Control
-const ILI9341_PIXFMT: u8 = 0x3A; // Pixel Format Set
-const ILI9341_FRMCTR1: u8 = 0xB1; // Frame Rate Control (In Normal Mode/Full Colors)
-const ILI9341_FRMCTR2: u8 = 0xB2; // Frame Rate Control (In Idle Mode/8 colors)
-const ILI9341_FRMCTR3: u8 = 0xB3; // Frame Rate Control (In Partial Mode/Full Colors)
-const ILI9341_INVCTR: u8 = 0xB4; // Display Inversion Control
-const ILI9341_DFUNCTR: u8 = 0xB6; // Display Function Control
-const ILI9341_PWCTR1: u8 = 0xC0; // Power Control 1
-const ILI9341_PWCTR2: u8 = 0xC1; // Power Control 2
-const ILI9341_PWCTR3: u8 = 0xC2; // Power Control 3
-const ILI9341_PWCTR4: u8 = 0xC3; // Power Control 4
-const ILI9341_PWCTR5: u8 = 0xC4; // Power Control 5
-const ILI9341_VMCTR1: u8 = 0xC5; // VCOM Control 1
-const ILI9341_VMCTR2: u8 = 0xC7; // VCOM Control 2
-const ILI9341_GMCTRP1: u8 = 0xE0; // Positive Gamma Correction
-const ILI9341_GMCTRN1: u8 = 0xE1; // Negative Gamma Correction
-
-// MADCTL bit definitions
-const MADCTL_MY: u8 = 0x80; // Bottom to top
-const MADCTL_MX: u8 = 0x40; // Right to left
-const MADCTL_MV: u8 = 0x20; // Reverse Mode
-const MADCTL_BGR: u8 = 0x08; // Blue-Green-Red pixel order
-
 // Default font definition
 pub static FONT_16X26: FontDef = FontDef {
     width: 16,
@@ -68,7 +80,6 @@ pub static FONT_16X26: FontDef = FontDef {
 
 pub struct Display {
     lcd_driver: LcdDriver,
-    orientation: DisplayOrientation,
 }
 
 #[derive(Copy, Clone)]
@@ -82,255 +93,134 @@ pub struct FontDef {
 // Safe wrapper for FontDef that can be shared between threads
 unsafe impl Sync for FontDef {}
 
-impl Display {
-    pub fn new() -> Self {
-        Self {
-            lcd_driver: LcdDriver::new(),
-            orientation: DisplayOrientation::Portrait,
-        }
-    }
+/// Why a display call did nothing
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DisplayError {
+    // Boot has not handed over the LCD driver yet
+    NotRegistered,
+    // A driver was handed over already; there is only ever one
+    AlreadyRegistered,
+    // LTDC scans out of SDRAM, which this board does not have
+    NoSdram,
+    // Drawing goes over SPI, with no LTDC layers or pixel clock to change
+    NoLtdc,
+}
 
-    pub fn register_driver(_driver: &LcdDriver) {
-        // This function maintained for compatibility but LcdDriver is already integrated
+impl DisplayError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DisplayError::NotRegistered => "no display registered",
+            DisplayError::AlreadyRegistered => "display already registered",
+            DisplayError::NoSdram => "ltdc needs sdram",
+            DisplayError::NoLtdc => "needs ltdc",
+        }
     }
+}
 
-    pub fn init(&mut self) {
-        // Initialize the GC9A01A display (ported from gc9a01a_init)
-        self.hw_reset();
-        self.configure();
-        self.set_orientation(DisplayOrientation::Landscape);
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
     }
+}
 
-    // Hardware reset function (ported from gc9a01a_hw_reset)
-    fn hw_reset(&self) {
-        // Simulate hardware reset sequence
-        // In real implementation would control GPIO pins:
-        // RST_HIGH -> delay -> RST_LOW -> delay -> RST_HIGH -> delay
-        // Using the LCD driver's delay mechanism if available
+impl Display {
+    pub fn new(lcd_driver: LcdDriver) -> Self {
+        Self { lcd_driver }
     }
 
-    // Configure the ILI9341 display (proper initialization for STM32F429ZI Discovery board)
-    fn configure(&self) {
-        // ILI9341 initialization sequence for STM32F429ZI Discovery board
-        self.write_cmd(ILI9341_SWRESET);
-        self.delay_ms(200);
-
-        // Power control A
-        self.write_cmd_with_data(0xCB, &[0x39, 0x2C, 0x00, 0x34, 0x02]);
-
-        // Power control B
-        self.write_cmd_with_data(0xCF, &[0x00, 0xC1, 0x30]);
-
-        // Driver timing control A
-        self.write_cmd_with_data(0xE8, &[0x85, 0x00, 0x78]);
-
-        // Driver timing control B
-        self.write_cmd_with_data(0xEA, &[0x00, 0x00]);
-
-        // Power on sequence control
-        self.write_cmd_with_data(0xED, &[0x64, 0x03, 0x12, 0x81]);
-
-        // Pump ratio control
-        self.write_cmd_with_data(0xF7, &[0x20]);
-
-        // Power control 1
-        self.write_cmd_with_data(ILI9341_PWCTR1, &[0x23]);
-
-        // Power control 2
-        self.write_cmd_with_data(ILI9341_PWCTR2, &[0x10]);
-
-        // VCOM control 1
-        self.write_cmd_with_data(ILI9341_VMCTR1, &[0x3e, 0x28]);
-
-        // VCOM control 2
-        self.write_cmd_with_data(ILI9341_VMCTR2, &[0x86]);
-
-        // Memory Access Control
-        self.write_cmd_with_data(ILI9341_MADCTL, &[0x48]);
-
-        // Pixel Format
-        self.write_cmd_with_data(ILI9341_PIXFMT, &[0x55]);
-
-        // Frame Rate Control
-        self.write_cmd_with_data(ILI9341_FRMCTR1, &[0x00, 0x18]);
-
-        // Display Function Control
-        self.write_cmd_with_data(ILI9341_DFUNCTR, &[0x08, 0x82, 0x27]);
-
-        // 3Gamma Function Disable
-        self.write_cmd_with_data(0xF2, &[0x00]);
-
-        // Gamma curve selected
-        self.write_cmd_with_data(ILI9341_GAMMASET, &[0x01]);
-
-        // Positive Gamma Correction
-        self.write_cmd_with_data(
-            ILI9341_GMCTRP1,
-            &[
-                0x0F, 0x31, 0x2B, 0x0C, 0x0E, 0x08, 0x4E, 0xF1, 0x37, 0x07, 0x10, 0x03, 0x0E, 0x09,
-                0x00,
-            ],
-        );
-
-        // Negative Gamma Correction
-        self.write_cmd_with_data(
-            ILI9341_GMCTRN1,
-            &[
-                0x00, 0x0E, 0x14, 0x03, 0x11, 0x07, 0x31, 0xC1, 0x48, 0x08, 0x0F, 0x0C, 0x31, 0x36,
-                0x0F,
-            ],
-        );
-
-        // Sleep out
-        self.write_cmd(ILI9341_SLPOUT);
-        self.delay_ms(120);
-
-        // Display on
-        self.write_cmd(ILI9341_DISPON);
-        self.delay_ms(20);
+    pub fn init(&mut self) {
+        self.set_orientation(ORIENTATION);
+        // An indexed sprite layer starts out all index 0; make that the key
+        // so it stays clear until the bird and its palette go in
+        if SPRITE_FORMAT.is_indexed() {
+            let mut blank = Palette::new();
+            blank.add(color::SPRITE_KEY);
+            self.set_sprite_palette(&blank);
+        }
     }
 
-    // Set display orientation (ported from gc9a01a_set_orientation)
+    // Turn the game's coordinates onto the panel; the caller redraws. The
+    // framebuffer mapping turns the LTDC picture and the panel turns SPI
+    // writes. The game's layout is sized for config::ORIENTATION, so
+    // switching to the other aspect at runtime only suits full-screen
+    // pictures.
     pub fn set_orientation(&mut self, orientation: DisplayOrientation) {
-        self.orientation = orientation;
-        match orientation {
-            DisplayOrientation::Landscape => {
-                self.write_cmd_with_data(ILI9341_CASET, &[0x00, 0x00, 0x01, 0x3F]); // 0-319
-                self.write_cmd_with_data(ILI9341_PASET, &[0x00, 0x00, 0x00, 0xEF]); // 0-239
-                self.write_cmd_with_data(ILI9341_MADCTL, &[MADCTL_MV | MADCTL_BGR]);
-            }
-            DisplayOrientation::Portrait => {
-                self.write_cmd_with_data(ILI9341_CASET, &[0x00, 0x00, 0x00, 0xEF]); // 0-239
-                self.write_cmd_with_data(ILI9341_PASET, &[0x00, 0x00, 0x01, 0x3F]); // 0-319
-                self.write_cmd_with_data(ILI9341_MADCTL, &[MADCTL_MX | MADCTL_BGR]);
-            }
-        }
+        framebuffer::set_orientation(orientation);
+        ili9341::set_orientation(orientation);
     }
 
-    // Draw image function (LTDC Layer 1 framebuffer approach for STM32F429ZI Discovery)
-    pub fn draw_image(&self, x: Coord, w: u32, y: Coord, h: u32, image_data: &[u16]) {
-        let x: u16 = x.try_into().expect("X co-ordinate is out of range");
-        let y: u16 = y.try_into().expect("y co-ordinate is out of range");
-        let w: u16 = w.try_into().expect("width out of range");
-        let h: u16 = h.try_into().expect("height out of range");
+    pub fn orientation(&self) -> DisplayOrientation {
+        framebuffer::orientation()
+    }
 
-        // Bounds checking
-        if x >= DISPLAY_WIDTH as u16 || y >= DISPLAY_HEIGHT as u16 {
+    // Draw an image filling `rect`; whatever hangs off the screen is clipped
+    pub fn draw_image(&self, rect: Rect, image_data: &[u16]) {
+        let _render = profiler::scope(Phase::Render);
+        if check_area(rect).is_none() {
             return;
         }
 
-        let w = if (x + w - 1) >= DISPLAY_WIDTH as u16 {
-            DISPLAY_WIDTH as u16 - x
-        } else {
-            w
-        };
-
-        let h = if (y + h - 1) >= DISPLAY_HEIGHT as u16 {
-            DISPLAY_HEIGHT as u16 - y
-        } else {
-            h
-        };
-
-        // Write directly to LTDC Layer 2 framebuffer
-        self.draw_image_to_framebuffer(x as u32, y as u32, w as u32, h as u32, image_data);
+        // Asset images are stored bottom row first, so a vertical flip gives
+        // the correct orientation for both text and images on the DISCO panel
+        let mut target = render_target();
+        target.blit(
+            rect.x,
+            rect.y,
+            &Image::new(rect.w, rect.h, image_data),
+            ImageTransform::FLIP_Y,
+            None,
+        );
+        target.present();
     }
 
-    // Helper function to draw image to LTDC Layer 1 framebuffer
-    fn draw_image_to_framebuffer(&self, x: u32, y: u32, w: u32, h: u32, image_data: &[u16]) {
-        use crate::lcd::{LAYER1_BASE, LCD_WIDTH};
-        use core::slice;
-
-        // Get Layer 1 framebuffer as ARGB8888 buffer
-        let framebuffer = unsafe {
-            slice::from_raw_parts_mut(
-                LAYER1_BASE as *mut u32,
-                (LCD_WIDTH * crate::lcd::LCD_HEIGHT) as usize,
-            )
-        };
-
-        // Image orientation for STM32F429ZI Discovery board LTDC framebuffer
-        // Mode 2 (vertical flip) is correct for proper text and image orientation
-        // 0: Normal (row, col) - text appears upside down
-        // 1: Horizontal flip (row, w-1-col) - text appears mirrored
-        // 2: Vertical flip (h-1-row, col) - CORRECT orientation ✓
-        // 3: Both flips (h-1-row, w-1-col) - text appears both upside down and mirrored
-        let orientation_mode = 2; // Vertical flip - correct for STM32F429ZI Discovery
-
-        for row in 0..h {
-            for col in 0..w {
-                let pixel_x = x + col;
-                let pixel_y = y + row;
-
-                // Bounds check
-                if pixel_x >= LCD_WIDTH || pixel_y >= crate::lcd::LCD_HEIGHT {
-                    continue;
-                }
-
-                // Calculate image data index based on orientation mode
-                let (img_row, img_col) = match orientation_mode {
-                    0 => (row, col),                 // Normal
-                    1 => (row, w - 1 - col),         // Horizontal flip
-                    2 => (h - 1 - row, col),         // Vertical flip
-                    3 => (h - 1 - row, w - 1 - col), // Both flips
-                    _ => (row, col),                 // Default to normal
-                };
-                let img_idx = (img_row * w + img_col) as usize;
-
-                if img_idx >= image_data.len() {
-                    continue;
-                }
-
-                // Convert RGB565 to ARGB8888
-                let rgb565 = image_data[img_idx];
-                let r = ((rgb565 >> 11) & 0x1F) as u32;
-                let g = ((rgb565 >> 5) & 0x3F) as u32;
-                let b = (rgb565 & 0x1F) as u32;
-
-                // Scale to 8-bit values
-                let r8 = (r * 255 / 31) as u32;
-                let g8 = (g * 255 / 63) as u32;
-                let b8 = (b * 255 / 31) as u32;
-
-                // Create ARGB8888 pixel (fully opaque)
-                let argb8888 = 0xFF000000 | (r8 << 16) | (g8 << 8) | b8;
-
-                // Write to framebuffer
-                let fb_index = (pixel_y * LCD_WIDTH + pixel_x) as usize;
-                framebuffer[fb_index] = argb8888;
-            }
+    // Cover a rectangle with a repeating texture
+    pub fn draw_tiled(&self, rect: Rect, tile: &Image) {
+        let _render = profiler::scope(Phase::Render);
+        if check_area(rect).is_none() {
+            return;
         }
-
-        // Memory barrier to ensure writes complete
-        cortex_m::asm::dsb();
+        let mut target = render_target();
+        target.fill_tiled(rect.x, rect.y, rect.w, rect.h, tile);
+        target.present();
     }
 
     // Fill screen with color (ported from gc9a01a_fill_screen)
-    pub fn set_background_color(&self, bg_color: u16) {
-        self.fill_rect(0, DISPLAY_WIDTH as u16, 0, DISPLAY_HEIGHT as u16, bg_color);
+    pub fn set_background_color(&self, bg_color: Rgb565) {
+        self.fill_rect(Rect::SCREEN, bg_color);
     }
 
     // Draw rectangle (ported from gc9a01a_fill_rect)
-    pub fn draw_rect_angle(&self, x: Coord, w: u32, y: Coord, h: u32, color: u16) {
-        let x: u16 = x.try_into().expect("X co-ordinate is out of range");
-        let y: u16 = y.try_into().expect("y co-ordinate is out of range");
-        let w: u16 = w.try_into().expect("width out of range");
-        let h: u16 = h.try_into().expect("height out of range");
-
-        self.fill_rect(x, w, y, h, color);
+    pub fn draw_rect_angle(&self, rect: Rect, color: Rgb565) {
+        let _render = profiler::scope(Phase::Render);
+        self.fill_rect(rect, color);
     }
 
     // Write string function (ported from gc9a01a_write_string)
-    pub fn write_string(&self, x: Coord, y: Coord, c_str: &ffi::CStr, color: u16, bgcolor: u16) {
-        let mut x: u16 = x.try_into().expect("X co-ordinate is out of range");
-        let mut y: u16 = y.try_into().expect("y co-ordinate is out of range");
+    pub fn write_string(
+        &self,
+        x: Coord,
+        y: Coord,
+        c_str: &ffi::CStr,
+        color: Rgb565,
+        bgcolor: Rgb565,
+    ) {
+        let _render = profiler::scope(Phase::Render);
+        check_area(Rect::new(
+            x,
+            y,
+            FONT_16X26.width as u32,
+            FONT_16X26.height as u32,
+        ));
+        let mut x = x;
+        let mut y = y;
 
         if let Ok(rust_str) = c_str.to_str() {
             for ch in rust_str.chars() {
                 // Handle line wrapping
-                if x + FONT_16X26.width as u16 >= DISPLAY_WIDTH as u16 {
+                if x + FONT_16X26.width as Coord >= DISPLAY_WIDTH as Coord {
                     x = 0;
-                    y += FONT_16X26.height as u16;
-                    if y + FONT_16X26.height as u16 >= DISPLAY_HEIGHT as u16 {
+                    y += FONT_16X26.height as Coord;
+                    if y + FONT_16X26.height as Coord >= DISPLAY_HEIGHT as Coord {
                         break;
                     }
 
@@ -340,247 +230,777 @@ impl Display {
                 }
 
                 self.write_char(x, y, ch as u8, FONT_16X26, color, bgcolor);
-                x += FONT_16X26.width as u16;
+                x += FONT_16X26.width as Coord;
             }
         }
     }
 
     // Write single character (LTDC framebuffer approach for STM32F429ZI Discovery)
-    fn write_char(&self, x: u16, y: u16, ch: u8, font: FontDef, color: u16, bgcolor: u16) {
-        use crate::lcd::{LAYER1_BASE, LCD_WIDTH};
-        use core::slice;
-
-        // Get Layer 1 framebuffer as ARGB8888 buffer
-        let framebuffer = unsafe {
-            slice::from_raw_parts_mut(
-                LAYER1_BASE as *mut u32,
-                (LCD_WIDTH * crate::lcd::LCD_HEIGHT) as usize,
-            )
+    fn write_char(
+        &self,
+        x: Coord,
+        y: Coord,
+        ch: u8,
+        font: FontDef,
+        color: Rgb565,
+        bgcolor: Rgb565,
+    ) {
+        let mut target = render_target();
+        draw_char(&mut target, x, y, ch, font, color, bgcolor);
+        target.present();
+    }
+
+    // Draw single pixel (ported from gc9a01a_draw_pixel)
+    pub fn draw_pixel(&self, x: u16, y: u16, color: Rgb565) {
+        check_area(Rect::new(x as Coord, y as Coord, 1, 1));
+        render_target().set_pixel(x as Coord, y as Coord, color.0);
+    }
+
+    // Fill rectangle helper (ported from gc9a01a_fill_rect)
+    fn fill_rect(&self, rect: Rect, color: Rgb565) {
+        let Some(rect) = check_area(rect) else {
+            return;
         };
+        let mut target = render_target();
+        target.fill_rect(rect.x, rect.y, rect.w, rect.h, color.0);
+        target.present();
+    }
 
-        for i in 0..font.height {
-            // Note: In real implementation, would read from font.data
-            // For now, using a simple pattern as font data is null
-            let mut b = if !font.data.is_null() {
-                unsafe { *font.data.add(((ch - 32) * font.height + i) as usize) as u16 }
-            } else {
-                // Simple pattern for demonstration when font data is null
-                if i < font.height / 2 {
-                    0xFF00
-                } else {
-                    0x00FF
-                }
-            };
+    // Each row of `rect` in its own color, for gradients
+    pub fn fill_rows(&self, rect: Rect, row_color: &dyn Fn(Coord) -> Rgb565) {
+        let _render = profiler::scope(Phase::Render);
+        let Some(rect) = check_area(rect) else {
+            return;
+        };
+        let mut target = render_target();
+        target.fill_rows(rect.x, rect.y, rect.w, rect.h, &|y| row_color(y).0);
+        target.present();
+    }
 
-            for j in 0..font.width {
-                let pixel_x = x as u32 + j as u32;
-                let pixel_y = y as u32 + i as u32;
+    // Move the hardware sprite layer (Layer 2) to a screen position
+    pub fn set_sprite_position(&self, x: Coord, y: Coord) {
+        if backend() == Backend::Spi || plane() != Plane::Sprite {
+            return;
+        }
+        // The window is the sprite's rectangle turned onto the panel
+        let turn = framebuffer::scan_orientation();
+        let (w, h) = turn.game_size(LAYER2_W, LAYER2_H);
+        let panel = turn.map_rect((x, y, w, h), lcd::surface());
+        if let Some((px, py, _, _)) = panel {
+            self.lcd_driver
+                .set_layer2_position(px.max(0) as u32, py.max(0) as u32);
+        }
+    }
 
-                // Bounds check
-                if pixel_x >= LCD_WIDTH || pixel_y >= crate::lcd::LCD_HEIGHT {
-                    continue;
-                }
+    // Constant alpha of the sprite layer; 0 hides it without touching its pixels
+    pub fn set_sprite_alpha(&self, alpha: u8) {
+        if backend() == Backend::Spi || plane() != Plane::Sprite {
+            return;
+        }
+        self.lcd_driver.set_layer2_alpha(alpha);
+    }
+
+    // Stretch Layer 2 over the whole screen, showing the overlay buffer at
+    // the given constant alpha (used for menus)
+    pub fn show_overlay(&self, alpha: u8) {
+        if backend() == Backend::Spi {
+            return;
+        }
+        self.key_sprite_layer(false);
+        self.lcd_driver
+            .configure_layer(Layer::Layer2, LayerConfig::full(&DISPLAY_MEMORY.overlay));
+        self.lcd_driver.set_layer2_alpha(alpha);
+        unsafe { OVERLAY_UP = true };
+        if bandwidth_saver() {
+            self.lcd_driver.set_layer_enabled(Layer::Layer2, true);
+        }
+    }
 
-                let pixel_color = if (b & 0x8000) != 0 { color } else { bgcolor };
+    // Put Layer 2 back to its plane; the caller repaints the sprite
+    pub fn hide_overlay(&self) {
+        if backend() == Backend::Spi {
+            return;
+        }
+        match plane() {
+            Plane::Sprite => {
+                self.lcd_driver
+                    .configure_layer(Layer::Layer2, LayerConfig::full(&DISPLAY_MEMORY.layer2));
+                self.key_sprite_layer(true);
+            }
+            Plane::Hud => {
+                self.key_sprite_layer(false);
+                self.show_hud_window();
+            }
+        }
+        unsafe { OVERLAY_UP = false };
+        if bandwidth_saver() {
+            self.lcd_driver.set_layer_enabled(Layer::Layer2, false);
+        }
+    }
 
-                // Convert RGB565 to ARGB8888
-                let r = ((pixel_color >> 11) & 0x1F) as u32;
-                let g = ((pixel_color >> 5) & 0x3F) as u32;
-                let b_val = (pixel_color & 0x1F) as u32;
+    // An indexed sprite is transparent where it is SPRITE_KEY (palette
+    // index 0), by the layer's color key; the ARGB8888 overlay and HUD use
+    // their own alpha and must not be keyed
+    fn key_sprite_layer(&self, on: bool) {
+        if !SPRITE_FORMAT.is_indexed() {
+            return;
+        }
+        if on {
+            let key = Rgb565(color::SPRITE_KEY).to_argb8888();
+            self.lcd_driver.set_color_key(Layer::Layer2, key.0);
+        } else {
+            self.lcd_driver.clear_color_key(Layer::Layer2);
+        }
+    }
 
-                // Scale to 8-bit values
-                let r8 = (r * 255 / 31) as u32;
-                let g8 = (g * 255 / 63) as u32;
-                let b8 = (b_val * 255 / 31) as u32;
+    // Colors for an indexed sprite layer; see `Player::show`
+    pub fn set_sprite_palette(&self, palette: &Palette) {
+        if backend() == Backend::Spi {
+            return;
+        }
+        let mut table = [0; CLUT_SIZE];
+        for (entry, rgb) in table.iter_mut().zip(palette.to_rgb888()) {
+            *entry = rgb;
+        }
+        self.lcd_driver
+            .load_clut(Layer::Layer2, &table[..palette.len()]);
+        self.key_sprite_layer(true);
+    }
 
-                // Create ARGB8888 pixel (fully opaque)
-                let argb8888 = 0xFF000000 | (r8 << 16) | (g8 << 8) | b8;
+    // Turn Layer 2 into `plane`, fully opaque for the HUD and hidden for
+    // the sprite until the caller shows the bird. Without LTDC there is no
+    // Layer 2 and it stays a sprite, so callers fall back to Layer 1; the
+    // bandwidth saver keeps it a sprite too.
+    pub fn set_plane(&self, next: Plane) {
+        if backend() == Backend::Spi || (next == Plane::Hud && bandwidth_saver()) {
+            return;
+        }
+        unsafe { PLANE = next };
+        self.hide_overlay();
+        self.lcd_driver
+            .set_layer2_alpha(if next == Plane::Hud { 0xFF } else { 0 });
+    }
 
-                // Write to framebuffer
-                let fb_index = (pixel_y * LCD_WIDTH + pixel_x) as usize;
-                framebuffer[fb_index] = argb8888;
+    // Layer 2 over the last HUD_H rows of the score bar, turned onto the
+    // panel; a taller top margin pushes it down clear of the bezel
+    fn show_hud_window(&self) {
+        let turn = framebuffer::scan_orientation();
+        let (surface_w, surface_h) = lcd::surface();
+        let (game_w, _) = turn.game_size(surface_w, surface_h);
+        let top = (lane::full().score_height - HUD_H as Coord).max(0);
+        let panel = turn.map_rect((0, top, game_w, HUD_H), (surface_w, surface_h));
+        let Some((x, y, w, h)) = panel else {
+            return;
+        };
+        self.lcd_driver.configure_layer(
+            Layer::Layer2,
+            LayerConfig {
+                x: x as u32,
+                y: y as u32,
+                w,
+                h,
+                ..LayerConfig::full(&DISPLAY_MEMORY.hud)
+            },
+        );
+    }
 
-                b <<= 1;
-            }
+    // Dim the game picture: 0xFF is full brightness
+    pub fn set_brightness(&self, level: u8) {
+        if backend() == Backend::Spi {
+            return;
         }
+        self.lcd_driver.set_layer1_alpha(level);
+    }
 
-        // Memory barrier to ensure writes complete
-        cortex_m::asm::dsb();
+    // Color the picture fades towards when dimmed (black by default; alpha
+    // is ignored)
+    pub fn set_backdrop(&self, color: Argb8888) {
+        if backend() == Backend::Spi {
+            return;
+        }
+        self.lcd_driver.set_background_color(color.0);
     }
 
-    // Draw single pixel (ported from gc9a01a_draw_pixel)
-    pub fn draw_pixel(&self, x: u16, y: u16, color: u16) {
-        if x >= DISPLAY_WIDTH as u16 || y >= DISPLAY_HEIGHT as u16 {
+    // Shift the whole Layer 1 picture by (dx, dy) panel pixels; the sprite
+    // and HUD on Layer 2 stay put. SPI rendering has no layer to move.
+    pub fn set_frame_offset(&self, dx: Coord, dy: Coord) {
+        if backend() == Backend::Spi {
             return;
         }
+        self.lcd_driver.set_layer1_offset(dx, dy);
+    }
 
-        self.set_address_window(x, x, y, y);
-        let color_high = (color >> 8) as u8;
-        let color_low = color as u8;
-        self.write_8bit(color_high);
-        self.write_8bit(color_low);
+    // Shift the menu overlay by (dx, dy) panel pixels, as set_frame_offset
+    // does Layer 1; show_overlay puts it back
+    pub fn set_overlay_offset(&self, dx: Coord, dy: Coord) {
+        if backend() == Backend::Spi {
+            return;
+        }
+        self.lcd_driver
+            .set_layer2_offset(LayerConfig::full(&DISPLAY_MEMORY.overlay), dx, dy);
     }
 
-    // Fill rectangle helper (ported from gc9a01a_fill_rect)
-    fn fill_rect(&self, x: u16, w: u16, y: u16, h: u16, color: u16) {
-        if x >= DISPLAY_WIDTH as u16 || y >= DISPLAY_HEIGHT as u16 {
+    // Turn both layers off, leaving the LTDC background, or back on; Layer
+    // 2 stays off under the bandwidth saver unless a menu has it
+    pub fn set_layers_visible(&self, on: bool) {
+        if backend() == Backend::Spi {
             return;
         }
+        let layer2 = on && (!bandwidth_saver() || unsafe { OVERLAY_UP });
+        self.lcd_driver.set_layer_enabled(Layer::Layer1, on);
+        self.lcd_driver.set_layer_enabled(Layer::Layer2, layer2);
+    }
 
-        let w = if (x + w - 1) >= DISPLAY_WIDTH as u16 {
-            DISPLAY_WIDTH as u16 - x
-        } else {
-            w
-        };
+    // The bandwidth saver, for panels that underrun while game blits keep
+    // SDRAM busy: Layer 1 drops to RGB565, Layer 2 is off but for menus
+    // (the bird and the score are drawn into Layer 1), and the pixel clock
+    // runs at SAVER_PCLK_PERCENT of what it was. Off puts all three back.
+    // The bird changes layers when it is next shown, so this is best
+    // switched between games.
+    pub fn set_bandwidth_saver(&self, on: bool) -> Result<(), DisplayError> {
+        if backend() == Backend::Spi {
+            return Err(DisplayError::NoLtdc);
+        }
+        match (on, unsafe { SAVER }) {
+            (true, None) => {
+                let hz = clock::pixel_clock_hz();
+                unsafe { SAVER = Some(hz) };
+                // Layer 2 goes off as the sprite's, now or when the menu
+                // that has it closes
+                if unsafe { OVERLAY_UP } {
+                    unsafe { PLANE = Plane::Sprite };
+                } else {
+                    self.set_plane(Plane::Sprite);
+                }
+                LcdDriver::set_layer1_format(PixelFormat::Rgb565);
+                let min_hz = ltdc_check::profile().pclk_min_hz();
+                clock::set_pixel_clock((hz / 100 * SAVER_PCLK_PERCENT).max(min_hz));
+            }
+            (false, Some(hz)) => {
+                unsafe { SAVER = None };
+                clock::set_pixel_clock(hz);
+                LcdDriver::set_layer1_format(LAYER1_FORMAT);
+                // Hidden, as set_plane leaves it, until the bird is shown
+                if !unsafe { OVERLAY_UP } {
+                    self.lcd_driver.set_layer2_alpha(0);
+                }
+                self.lcd_driver.set_layer_enabled(Layer::Layer2, true);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-        let h = if (y + h - 1) >= DISPLAY_HEIGHT as u16 {
-            DISPLAY_HEIGHT as u16 - y
-        } else {
-            h
-        };
+    // Panel gamma curve; the ILI9341 applies it whichever way pixels arrive
+    pub fn set_gamma(&self, profile: GammaProfile) {
+        ili9341::set_gamma(profile);
+    }
 
-        self.set_address_window(x, x + w - 1, y, y + h - 1);
+    // Invert colors function (ILI9341 compatible)
+    pub fn invert_colors(&self, invert: bool) {
+        ili9341::set_inversion(invert);
+    }
+} // Keep the old function API for backward compatibility during transition
 
-        let color_high = (color >> 8) as u8;
-        let color_low = color as u8;
+// Where the game's primitives draw; everything goes through `RenderBackend`
+// so the same calls work against the host simulator
+pub enum Target {
+    Ltdc(FrameBuffer),
+    Spi(SpiPanel),
+}
 
-        for _row in 0..h {
-            for _col in 0..w {
-                self.write_8bit(color_high);
-                self.write_8bit(color_low);
-            }
+impl RenderBackend for Target {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::size(fb),
+            Target::Spi(panel) => panel.size(),
         }
     }
 
-    // Set address window (ILI9341 compatible)
-    fn set_address_window(&self, x0: u16, x1: u16, y0: u16, y1: u16) {
-        // Set column address
-        self.write_cmd(ILI9341_CASET);
-        self.write_data(&[(x0 >> 8) as u8, x0 as u8, (x1 >> 8) as u8, x1 as u8]);
+    fn set_pixel(&mut self, x: Coord, y: Coord, rgb565: u16) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::set_pixel(fb, x, y, rgb565),
+            Target::Spi(panel) => panel.set_pixel(x, y, rgb565),
+        }
+    }
 
-        // Set row address
-        self.write_cmd(ILI9341_PASET);
-        self.write_data(&[(y0 >> 8) as u8, y0 as u8, (y1 >> 8) as u8, y1 as u8]);
+    fn fill_rect(&mut self, x: Coord, y: Coord, w: u32, h: u32, rgb565: u16) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::fill_rect(fb, x, y, w, h, rgb565),
+            Target::Spi(panel) => panel.fill_rect(x, y, w, h, rgb565),
+        }
+    }
 
-        // Write to RAM command
-        self.write_cmd(ILI9341_RAMWR);
+    fn blit(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+    ) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::blit(fb, x, y, image, transform, key),
+            Target::Spi(panel) => panel.blit(x, y, image, transform, key),
+        }
     }
 
-    // Low-level command functions (ported from gc9a01a.c)
-    fn write_cmd(&self, cmd: u8) {
-        // In real implementation would set DC pin low and write command
-        // For now, delegate to LCD driver or use SPI interface
-        self.write_8bit(cmd);
+    fn blit_region(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        region: Rect,
+    ) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::blit_region(fb, x, y, image, transform, key, region),
+            Target::Spi(panel) => panel.blit_region(x, y, image, transform, key, region),
+        }
     }
 
-    fn write_cmd_with_data(&self, cmd: u8, data: &[u8]) {
-        self.write_cmd(cmd);
-        self.write_data(data);
+    fn fill_tiled_from(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        w: u32,
+        h: u32,
+        tile: &Image,
+        skip: (u32, u32),
+    ) {
+        match self {
+            Target::Ltdc(fb) => RenderBackend::fill_tiled_from(fb, x, y, w, h, tile, skip),
+            Target::Spi(panel) => panel.fill_tiled_from(x, y, w, h, tile, skip),
+        }
     }
 
-    fn write_data(&self, data: &[u8]) {
-        // In real implementation would set DC pin high and write data
-        for &byte in data {
-            self.write_8bit(byte);
+    fn present(&mut self) {
+        match self {
+            Target::Ltdc(fb) => fb.present(),
+            Target::Spi(panel) => panel.present(),
         }
     }
+}
 
-    fn write_8bit(&self, data: u8) {
-        // In real implementation this would write to GPIO pins
-        // Following the pattern from GC9A01A_WRITE_8BIT macro
-        // For now, this is a stub that could interface with SPI or GPIO
-        let _ = data; // Suppress unused warning
+impl Target {
+    // `rgb565` over a rectangle at `alpha`; SPI cannot read the panel back,
+    // so there it is drawn solid from half alpha up and not at all below
+    pub fn blend_rect(&mut self, x: Coord, y: Coord, w: u32, h: u32, rgb565: u16, alpha: u8) {
+        check_area(Rect::new(x, y, w, h));
+        match self {
+            Target::Ltdc(fb) => fb.blend_rect(x, y, w, h, rgb565, alpha),
+            Target::Spi(panel) if alpha >= 0x80 => panel.fill_rect(x, y, w, h, rgb565),
+            Target::Spi(_) => {}
+        }
     }
 
-    fn delay_ms(&self, ms: u32) {
-        // In real implementation would use HAL_Delay or timer
-        // For now, simple loop (not accurate timing)
-        for _ in 0..(ms * 1000) {
-            cortex_m::asm::nop();
+    // ARGB8888 pixels, `w` to a row, leaving out those with alpha 0
+    pub fn blit_argb(&mut self, x: Coord, y: Coord, w: u32, pixels: &[u32]) {
+        check_area(Rect::new(x, y, w, pixels.len() as u32 / w.max(1)));
+        match self {
+            Target::Ltdc(fb) => fb.blit_argb(x, y, w, pixels),
+            Target::Spi(panel) => {
+                for (i, &argb) in pixels.iter().enumerate() {
+                    let argb = Argb8888(argb);
+                    if argb.alpha() != 0 {
+                        let (col, row) = (i as u32 % w, i as u32 / w);
+                        let (x, y) = (x + col as Coord, y + row as Coord);
+                        panel.set_pixel(x, y, argb.to_rgb565().0);
+                    }
+                }
+            }
         }
     }
+}
 
-    // Invert colors function (ILI9341 compatible)
-    pub fn invert_colors(&self, invert: bool) {
-        self.write_cmd(if invert {
-            ILI9341_INVON
+// One glyph of `font` at x, y, background and all, without presenting
+fn draw_char(
+    target: &mut Target,
+    x: Coord,
+    y: Coord,
+    ch: u8,
+    font: FontDef,
+    color: Rgb565,
+    bgcolor: Rgb565,
+) {
+    for i in 0..font.height {
+        // Note: In real implementation, would read from font.data
+        // For now, using a simple pattern as font data is null
+        let mut b = if !font.data.is_null() {
+            // Control characters have no glyph; draw them as blanks
+            let glyph = ch.saturating_sub(32) as usize;
+            unsafe { *font.data.add(glyph * font.height as usize + i as usize) as u16 }
         } else {
-            ILI9341_INVOFF
-        });
+            // Simple pattern for demonstration when font data is null
+            if i < font.height / 2 {
+                0xFF00
+            } else {
+                0x00FF
+            }
+        };
+
+        for j in 0..font.width {
+            let pixel_color = if (b & 0x8000) != 0 { color } else { bgcolor };
+            target.set_pixel(x + j as Coord, y + i as Coord, pixel_color.0);
+
+            b <<= 1;
+        }
     }
-} // Keep the old function API for backward compatibility during transition
-pub fn register_driver(driver: &LcdDriver) {
-    Display::register_driver(driver);
 }
 
-// Global display instance for C compatibility
-static mut DISPLAY: Option<Display> = None;
+// A glyph of the score font into a frame being drawn; see `text_field`
+pub fn draw_glyph(target: &mut Target, x: Coord, y: Coord, ch: u8, color: Rgb565, bgcolor: Rgb565) {
+    check_area(Rect::new(
+        x,
+        y,
+        FONT_16X26.width as u32,
+        FONT_16X26.height as u32,
+    ));
+    draw_char(target, x, y, ch, FONT_16X26, color, bgcolor);
+}
+
+// Cells of the score font, width and height
+pub fn glyph_size() -> (u32, u32) {
+    (FONT_16X26.width as u32, FONT_16X26.height as u32)
+}
+
+pub fn render_target() -> Target {
+    match backend() {
+        Backend::Ltdc => Target::Ltdc(FrameBuffer::render_target()),
+        Backend::Spi => Target::Spi(SpiPanel::new()),
+    }
+}
 
-// Initialize the global display instance
-fn get_display() -> &'static mut Display {
-    unsafe {
-        if DISPLAY.is_none() {
-            DISPLAY = Some(Display::new());
+pub fn backend() -> Backend {
+    unsafe { BACKEND }
+}
+
+pub fn bandwidth_saver() -> bool {
+    unsafe { SAVER.is_some() }
+}
+
+// Whether the bird can have Layer 2: not with SPI rendering, which has no
+// Layer 2, nor with the bandwidth saver keeping it off
+pub fn has_sprite_layer() -> bool {
+    backend() == Backend::Ltdc && !bandwidth_saver()
+}
+
+// Switch where drawing goes. The panel is told which interface to take
+// pixels from and LTDC scan-out follows; the caller redraws the screen.
+// LTDC needs SDRAM, so boards without it can only use SPI.
+pub fn set_backend(backend: Backend) -> Result<(), DisplayError> {
+    if !is_registered() {
+        return Err(DisplayError::NotRegistered);
+    }
+    match backend {
+        Backend::Ltdc => {
+            if !sdram::available() {
+                return Err(DisplayError::NoSdram);
+            }
+            ili9341::enter_rgb_mode();
+            with(|display| display.lcd_driver.set_enabled(true))?;
+        }
+        Backend::Spi => {
+            with(|display| display.lcd_driver.set_enabled(false))?;
+            ili9341::enter_spi_mode();
+        }
+    }
+    unsafe { BACKEND = backend };
+    Ok(())
+}
+
+// Boot could not bring up SDRAM or LTDC: draw over SPI instead. Only before
+// the display is first used, as nothing is switched over.
+pub fn fall_back_to_spi() {
+    unsafe { BACKEND = Backend::Spi };
+}
+
+// Global display instance for C compatibility, holding the one LcdDriver
+// boot built; empty until `register`
+static DISPLAY: ThreadOnly<Display> = ThreadOnly::new();
+
+// Hand over the driver boot set LTDC up with (or attached to, for SPI
+// rendering). Only the first is kept: a second would program the same
+// registers behind the first one's back.
+pub fn register(lcd_driver: LcdDriver) -> Result<(), DisplayError> {
+    DISPLAY
+        .put(Display::new(lcd_driver))
+        .map_err(|_| DisplayError::AlreadyRegistered)
+}
+
+pub fn is_registered() -> bool {
+    DISPLAY.is_set()
+}
+
+// Run `f` on the display. Before `register` there is no driver to do it
+// with, and the wrappers below draw or set nothing and say so.
+fn with<R>(f: impl FnOnce(&mut Display) -> R) -> Result<R, DisplayError> {
+    DISPLAY.with(f).ok_or(DisplayError::NotRegistered)
+}
+
+// Development builds treat a drawing area the game should never produce
+// (see `Rect::draw_area`) as a bug and panic; `production` builds clip it
+// like any other. What is on screen, None for nothing.
+const AREA_CHECK: AreaCheck = if cfg!(feature = "production") {
+    AreaCheck::Clip
+} else {
+    AreaCheck::Strict
+};
+
+fn check_area(rect: Rect) -> Option<Rect> {
+    match rect.draw_area(AREA_CHECK) {
+        Ok(on_screen) => on_screen,
+        Err(error) => panic!("{}", error.as_str()),
+    }
+}
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<ThreadOnly<Display>>()
+    + core::mem::size_of::<Plane>()
+    + core::mem::size_of::<Option<u32>>()
+    + core::mem::size_of::<bool>();
+
+// Set once a C call has done nothing for want of a driver; only that
+// first one is logged, as the caller is never told
+static C_CALL_DROPPED: AtomicBool = AtomicBool::new(false);
+
+// The C wrappers below have nowhere to return an error to
+fn from_c(name: &str, result: Result<(), DisplayError>) {
+    if let Err(error) = result {
+        if !C_CALL_DROPPED.swap(true, Ordering::Relaxed) {
+            log::warn!("display: {} from C did nothing, {}", name, error);
         }
-        DISPLAY.as_mut().unwrap()
     }
 }
 
 // C-compatible function wrappers for interfacing with legacy C code
 #[no_mangle]
 pub extern "C" fn init() {
-    let display = get_display();
-    display.init();
+    from_c("init", with(|display| display.init()));
 }
 
 #[no_mangle]
 pub extern "C" fn draw_image(x: Coord, w: u32, y: Coord, h: u32, image_data: *const u16) {
-    let image_data = unsafe { core::slice::from_raw_parts(image_data, (w * h) as usize) };
-    let display = get_display();
-    display.draw_image(x, w, y, h, image_data);
+    let image_data = unsafe { core::slice::from_raw_parts(image_data, w as usize * h as usize) };
+    from_c(
+        "draw_image",
+        draw_image_rust(Rect::new(x, y, w, h), image_data),
+    );
 }
 
 #[no_mangle]
 pub extern "C" fn set_background_color(bg_color: u16) {
-    let display = get_display();
-    display.set_background_color(bg_color);
+    from_c("set_background_color", set_background_color_rust(bg_color));
 }
 
 #[no_mangle]
 pub extern "C" fn draw_rect_angle(x: Coord, w: u32, y: Coord, h: u32, color: u16) {
-    let display = get_display();
-    display.draw_rect_angle(x, w, y, h, color);
+    from_c(
+        "draw_rect_angle",
+        draw_rect_angle_rust(Rect::new(x, y, w, h), color),
+    );
 }
 
 #[no_mangle]
 pub extern "C" fn write_string(x: Coord, y: Coord, c_str: *const c_char, color: u16, bgcolor: u16) {
     let c_str = unsafe { ffi::CStr::from_ptr(c_str) };
-    let display = get_display();
-    display.write_string(x, y, c_str, color, bgcolor);
+    from_c(
+        "write_string",
+        write_string_rust(x, y, c_str, color, bgcolor),
+    );
 }
 
 // Rust-friendly wrapper functions that don't require extern "C"
-pub fn draw_image_rust(x: Coord, w: u32, y: Coord, h: u32, image_data: &[u16]) {
-    let display = get_display();
-    display.draw_image(x, w, y, h, image_data);
+pub fn draw_image_rust(rect: Rect, image_data: &[u16]) -> Result<(), DisplayError> {
+    with(|display| display.draw_image(rect, image_data))
+}
+
+pub fn set_background_color_rust(bg_color: u16) -> Result<(), DisplayError> {
+    with(|display| display.set_background_color(Rgb565(bg_color)))
+}
+
+pub fn draw_rect_angle_rust(rect: Rect, color: u16) -> Result<(), DisplayError> {
+    with(|display| display.draw_rect_angle(rect, Rgb565(color)))
+}
+
+pub fn write_string_rust(
+    x: Coord,
+    y: Coord,
+    c_str: &ffi::CStr,
+    color: u16,
+    bgcolor: u16,
+) -> Result<(), DisplayError> {
+    with(|display| display.write_string(x, y, c_str, Rgb565(color), Rgb565(bgcolor)))
+}
+
+pub fn fill_rows_rust(rect: Rect, row_color: &dyn Fn(Coord) -> Rgb565) -> Result<(), DisplayError> {
+    with(|display| display.fill_rows(rect, row_color))
+}
+
+pub fn draw_tiled_rust(rect: Rect, tile: &Image) -> Result<(), DisplayError> {
+    with(|display| display.draw_tiled(rect, tile))
+}
+
+// Draw every sprite of `batch` and present them together
+pub fn draw_batch<const N: usize>(batch: &SpriteBatch<N>) {
+    let _render = profiler::scope(Phase::Render);
+    let mut target = render_target();
+    batch.draw(&mut target);
+    target.present();
+}
+
+// Draw an asset image at `scale` with `key` pixels left transparent
+pub fn draw_sprite_rust(x: Coord, y: Coord, image: &Image, key: u16, scale: Scale) {
+    check_area(Rect::new(x, y, scale.of(image.w), scale.of(image.h)));
+    let mut target = render_target();
+    target.blit(x, y, image, ImageTransform::FLIP_Y.scaled(scale), Some(key));
+    target.present();
+}
+
+pub fn set_sprite_position(x: Coord, y: Coord) -> Result<(), DisplayError> {
+    with(|display| display.set_sprite_position(x, y))
+}
+
+pub fn set_sprite_alpha(alpha: u8) -> Result<(), DisplayError> {
+    with(|display| display.set_sprite_alpha(alpha))
+}
+
+pub fn show_overlay(alpha: u8) -> Result<(), DisplayError> {
+    with(|display| display.show_overlay(alpha))
+}
+
+pub fn plane() -> Plane {
+    unsafe { PLANE }
+}
+
+pub fn set_plane(plane: Plane) -> Result<(), DisplayError> {
+    with(|display| display.set_plane(plane))
+}
+
+pub fn set_gamma(profile: GammaProfile) -> Result<(), DisplayError> {
+    with(|display| display.set_gamma(profile))
+}
+
+pub fn invert_colors(invert: bool) -> Result<(), DisplayError> {
+    with(|display| display.invert_colors(invert))
+}
+
+pub fn set_sprite_palette(palette: &Palette) -> Result<(), DisplayError> {
+    with(|display| display.set_sprite_palette(palette))
+}
+
+pub fn hide_overlay() -> Result<(), DisplayError> {
+    with(|display| display.hide_overlay())
+}
+
+pub fn set_brightness(level: u8) -> Result<(), DisplayError> {
+    with(|display| display.set_brightness(level))
+}
+
+pub fn set_backdrop(color: Argb8888) -> Result<(), DisplayError> {
+    with(|display| display.set_backdrop(color))
+}
+
+pub fn set_frame_offset(dx: Coord, dy: Coord) -> Result<(), DisplayError> {
+    with(|display| display.set_frame_offset(dx, dy))
+}
+
+pub fn set_overlay_offset(dx: Coord, dy: Coord) -> Result<(), DisplayError> {
+    with(|display| display.set_overlay_offset(dx, dy))
 }
 
-pub fn set_background_color_rust(bg_color: u16) {
-    let display = get_display();
-    display.set_background_color(bg_color);
+pub fn set_layers_visible(on: bool) -> Result<(), DisplayError> {
+    with(|display| display.set_layers_visible(on))
 }
 
-pub fn draw_rect_angle_rust(x: Coord, w: u32, y: Coord, h: u32, color: u16) {
-    let display = get_display();
-    display.draw_rect_angle(x, w, y, h, color);
+// Err with SPI rendering, or before the display is registered
+pub fn set_bandwidth_saver(on: bool) -> Result<(), DisplayError> {
+    with(|display| display.set_bandwidth_saver(on))?
 }
 
-pub fn write_string_rust(x: Coord, y: Coord, c_str: &ffi::CStr, color: u16, bgcolor: u16) {
-    let display = get_display();
-    display.write_string(x, y, c_str, color, bgcolor);
+pub fn init_rust() -> Result<(), DisplayError> {
+    with(|display| display.init())
 }
 
-pub fn init_rust() {
-    let display = get_display();
-    display.init();
+// LTDC bring-up stays in main, which owns the driver; this covers the panel
+pub struct DisplaySubsystem;
+
+impl Subsystem for DisplaySubsystem {
+    fn name(&self) -> &'static str {
+        "display"
+    }
+
+    // A panel that does not confirm the init sequence is reported, but the
+    // rest still comes up: it may just be unable to answer on MISO
+    fn init(&self) -> Result<(), HwError> {
+        // A plain RGB panel has no controller to set up over SPI
+        let panel = if lcd::panel().ili9341 {
+            ili9341::init()
+        } else {
+            Ok(())
+        };
+        backlight::init();
+        if backend() == Backend::Spi {
+            ili9341::enter_spi_mode();
+            return panel;
+        }
+
+        // Make sure LTDC timing, pixel clock and the panel's RGB interface agree
+        let panel_mismatches = ltdc_check::run_at_boot();
+        if cfg!(debug_assertions) && panel_mismatches != 0 {
+            diagnostics::draw_page();
+            clock::delay_ms(3000);
+        }
+        panel
+    }
+
+    fn health_check(&self) -> Health {
+        let (_, mismatches) = ltdc_check::found();
+        if backend() == Backend::Spi {
+            return if ili9341::responded() {
+                Health::Ok
+            } else {
+                Health::Failed("panel not responding")
+            };
+        }
+        let enabled = with(|display| display.lcd_driver.is_enabled());
+        if enabled.is_err() {
+            Health::Failed("no LCD driver")
+        } else if enabled == Ok(false) {
+            Health::Failed("LTDC off")
+        } else if lcd::panel().ili9341 && !ili9341::responded() {
+            Health::Failed("panel not responding")
+        } else if !sdram::spot_check() {
+            Health::Failed("SDRAM spot check")
+        } else if mismatches != 0 {
+            Health::Degraded("panel config")
+        } else if LcdDriver::layer1_format() != LAYER1_FORMAT && !bandwidth_saver() {
+            Health::Degraded("underruns, 16bpp")
+        } else {
+            Health::Ok
+        }
+    }
+
+    fn suspend(&self) {
+        ili9341::sleep();
+        if backend() == Backend::Ltdc {
+            let _ = with(|display| display.lcd_driver.set_enabled(false));
+        }
+    }
+
+    fn resume(&self) {
+        if backend() == Backend::Ltdc {
+            let _ = with(|display| display.lcd_driver.set_enabled(true));
+        }
+        ili9341::wake();
+    }
+
+    // Not the boot sequence again, which can stop on the diagnostics page:
+    // put the panel to sleep and wake it, turning LTDC back on
+    fn restart(&self) -> Result<(), HwError> {
+        self.suspend();
+        self.resume();
+        Ok(())
+    }
 }
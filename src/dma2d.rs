@@ -0,0 +1,121 @@
+//! DMA2D block copies between framebuffers
+//!
+//! Memory-to-memory mode only: a block `width` pixels wide and `rows` high
+//! goes from one buffer to another in the same format, each side with its
+//! own line length, so any rectangle of one framebuffer can land anywhere
+//! in another. `start_copy` returns as soon as the transfer is under way;
+//! the CPU can get on with other pixels and `wait` before the next one.
+//!
+//! `set_cpu_only` turns every copy down as if the unit had hung, so callers
+//! take their CPU fallback; ab_check draws the same frame both ways.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::error::HwError;
+use crate::framebuffer;
+use crate::lcd::PixelFormat;
+use crate::resources;
+
+// CR.MODE for memory-to-memory without conversion
+const MODE_M2M: u8 = 0;
+// Polls before a transfer counts as stuck; a full screen takes far fewer
+const WAIT_POLLS: u32 = 2_000_000;
+
+// Refuse every copy, see set_cpu_only
+static mut CPU_ONLY: bool = false;
+
+/// One side of a copy: the first pixel and the pixels from one row start
+/// to the next
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Block {
+    pub addr: u32,
+    pub stride: u32,
+}
+
+// Clock the peripheral; call once after SDRAM is up
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.ahb1enr.modify(|_, w| w.dma2den().set_bit());
+    // Settle after enabling the clock before touching its registers
+    let _ = dp.RCC.ahb1enr.read();
+}
+
+pub fn set_cpu_only(on: bool) {
+    unsafe { CPU_ONLY = on };
+}
+
+pub fn cpu_only() -> bool {
+    unsafe { CPU_ONLY }
+}
+
+pub fn busy() -> bool {
+    resources::pac().DMA2D.cr.read().start().bit_is_set()
+}
+
+// Start copying a `width` x `rows` block of `format` pixels from `src` to
+// `dst`, after any copy still running. Rows up to `width` pixels long
+// (16384 at most) and at most 65535 of them.
+pub fn start_copy(
+    src: Block,
+    dst: Block,
+    width: u32,
+    rows: u32,
+    format: PixelFormat,
+) -> Result<(), HwError> {
+    if cpu_only() {
+        return Err(HwError::NoResponse);
+    }
+    wait()?;
+    if width == 0 || rows == 0 {
+        return Ok(());
+    }
+    // The source may have just been drawn by the CPU
+    framebuffer::flush();
+    let dma2d = &resources::pac().DMA2D;
+    let cm = format as u8;
+    unsafe {
+        dma2d.fgmar.write(|w| w.bits(src.addr));
+        dma2d
+            .fgor
+            .write(|w| w.lo().bits((src.stride - width) as u16));
+        dma2d.fgpfccr.write(|w| w.cm().bits(cm));
+        dma2d.omar.write(|w| w.bits(dst.addr));
+        dma2d
+            .oor
+            .write(|w| w.lo().bits((dst.stride - width) as u16));
+        dma2d.opfccr.write(|w| w.cm().bits(cm));
+        dma2d
+            .nlr
+            .write(|w| w.pl().bits(width as u16).nl().bits(rows as u16));
+        dma2d.ifcr.write(|w| w.ctcif().set_bit().cteif().set_bit());
+        dma2d
+            .cr
+            .write(|w| w.mode().bits(MODE_M2M).start().set_bit());
+    }
+    Ok(())
+}
+
+// Block until no copy is running
+pub fn wait() -> Result<(), HwError> {
+    let mut polls = WAIT_POLLS;
+    while busy() {
+        polls -= 1;
+        if polls == 0 {
+            resources::pac().DMA2D.cr.modify(|_, w| w.abort().set_bit());
+            return Err(HwError::Timeout);
+        }
+    }
+    Ok(())
+}
+
+// A whole copy, start to finish
+pub fn copy(
+    src: Block,
+    dst: Block,
+    width: u32,
+    rows: u32,
+    format: PixelFormat,
+) -> Result<(), HwError> {
+    start_copy(src, dst, width, rows, format)?;
+    wait()
+}
@@ -1,287 +1,111 @@
-use core::slice;
+use core_logic::test_pattern::Pattern;
 
-use crate::lcd::{LAYER1_BASE, LAYER2_BASE, LAYER2_H, LAYER2_W, LCD_HEIGHT, LCD_WIDTH};
+use crate::color::Argb8888;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::lcd::{LAYER2_H, LAYER2_W};
 
 pub fn layer1_checkerboard() {
-    // Restore original double-buffering approach
-    fill_simple_checkerboard(LAYER1_BASE);
-    fill_simple_checkerboard(crate::lcd::LcdDriver::layer1_back_addr());
+    layer1_pattern(Pattern::Checkerboard);
 }
 
-// Test different pattern complexities to isolate the cause
-fn fill_simple_checkerboard(base: u32) {
-    let pixels = (LCD_WIDTH * LCD_HEIGHT) as usize;
-    let buf = unsafe { slice::from_raw_parts_mut(base as *mut u32, pixels) };
-
-    cortex_m::asm::dsb(); // Data Synchronization Barrier
-
-    // Test with gentler colors to reduce electrical noise
-    let color1 = 0xFF404040; // Dark gray (less contrast)
-    let color2 = 0xFFC0C0C0; // Light gray (less contrast)
-    let square_size = 64; // Larger squares = lower frequency transitions
+// `pattern` on both Layer 1 buffers, so it stays up whichever is scanned out
+pub fn layer1_pattern(pattern: Pattern) {
+    fill_pattern(&mut FrameBuffer::layer1(), pattern);
+    fill_pattern(&mut FrameBuffer::layer1_back(), pattern);
+}
 
-    for row in 0..LCD_HEIGHT {
-        for col in 0..LCD_WIDTH {
-            let i = (row * LCD_WIDTH + col) as usize;
+// Opaque black on both Layer 1 buffers, behind the boot splash
+pub fn layer1_black() {
+    for mut fb in [FrameBuffer::layer1(), FrameBuffer::layer1_back()] {
+        let black = fb.encode_argb(Argb8888::BLACK);
+        fb.fill(black);
+    }
+    framebuffer::flush();
+}
 
-            // Larger, gentler checkerboard
-            let row_square = (row / square_size) & 1;
-            let col_square = (col / square_size) & 1;
-            let is_light = (row_square ^ col_square) != 0;
+// A test pattern over the whole buffer, in its own panel coordinates
+fn fill_pattern(fb: &mut FrameBuffer, pattern: Pattern) {
+    framebuffer::flush();
 
-            buf[i] = if is_light { color2 } else { color1 };
-        }
-    }
+    let (width, height) = (fb.width(), fb.height());
+    fb.fill_striped(pattern.band(height), |col, row| {
+        pattern.argb(col, row, width, height)
+    });
 
-    cortex_m::asm::dsb(); // Ensure writes complete
+    framebuffer::flush();
     cortex_m::asm::isb(); // Instruction barrier
 }
 
-fn fill_checkerboard_to(base: u32) {
-    let pixels = (LCD_WIDTH * LCD_HEIGHT) as usize;
-    let buf = unsafe { slice::from_raw_parts_mut(base as *mut u32, pixels) };
+fn fill_checkerboard_to(fb: &mut FrameBuffer) {
+    let width = fb.width();
+    let height = fb.height();
 
     // Ensure memory coherency before writing
-    cortex_m::asm::dsb(); // Data Synchronization Barrier
-    let cel_count = (LCD_WIDTH >> 5) + (LCD_HEIGHT >> 5);
-    for row in 0..LCD_HEIGHT {
-        for col in 0..LCD_WIDTH {
-            let i = (row * LCD_WIDTH + col) as usize;
-            let cel = (row >> 5) + (col >> 5);
-            let mut a: u8 = if (cel & 1) != 0 { 0 } else { 0xFF };
-            let mut r: u8 = (row * 0xFF / LCD_HEIGHT) as u8;
-            let mut g: u8 = (col * 0xFF / LCD_WIDTH) as u8;
-            let mut b: u8 = (0xFF * (cel_count - cel - 1) / cel_count) as u8;
-            if (cel & 3) == 0 {
-                b = 0;
-            }
-            if row % 32 == 0 || col % 32 == 0 {
-                r = if a != 0 { 0xFF } else { 0 };
-                g = r;
-                b = r;
-                a = 0xFF;
-            }
-            let pix = ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
-            buf[i] = pix;
+    framebuffer::flush();
+    let cel_count = (width >> 5) + (height >> 5);
+    fb.fill_with(|col, row| {
+        let cel = (row >> 5) + (col >> 5);
+        let mut a: u8 = if (cel & 1) != 0 { 0 } else { 0xFF };
+        let mut r: u8 = (row * 0xFF / height) as u8;
+        let mut g: u8 = (col * 0xFF / width) as u8;
+        let mut b: u8 = (0xFF * (cel_count - cel - 1) / cel_count) as u8;
+        if (cel & 3) == 0 {
+            b = 0;
         }
-    }
+        if row.is_multiple_of(32) || col.is_multiple_of(32) {
+            r = if a != 0 { 0xFF } else { 0 };
+            g = r;
+            b = r;
+            a = 0xFF;
+        }
+        ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+    });
 
     // Ensure all writes complete before LTDC reads
-    cortex_m::asm::dsb(); // Data Synchronization Barrier
+    framebuffer::flush();
     cortex_m::asm::isb(); // Instruction Synchronization Barrier
 }
 
 // Clear Layer 1 to fully transparent (for start screen)
 pub fn clear_layer1() {
-    let pixels = (LCD_WIDTH * LCD_HEIGHT) as usize;
-    let buf = unsafe { slice::from_raw_parts_mut(LAYER1_BASE as *mut u32, pixels) };
-    // Clear to fully transparent
-    for p in buf.iter_mut() {
-        *p = 0;
-    }
+    FrameBuffer::layer1().fill(0);
 
     // Memory barrier to ensure writes complete
-    cortex_m::asm::dsb();
+    framebuffer::flush();
 }
 
 // Clear Layer 2 to fully transparent (for display functions)
 pub fn clear_layer2() {
-    let pixels = (LAYER2_W * LAYER2_H) as usize;
-    let buf = unsafe { slice::from_raw_parts_mut(LAYER2_BASE as *mut u32, pixels) };
-    // Clear to fully transparent
-    for p in buf.iter_mut() {
-        *p = 0;
-    }
+    FrameBuffer::layer2().fill(0);
 
     // Memory barrier to ensure writes complete
-    cortex_m::asm::dsb();
+    framebuffer::flush();
 }
 
 // Put checkerboard pattern on Layer 2 as background
 pub fn layer2_checkerboard() {
-    fill_simple_checkerboard(LAYER2_BASE);
+    fill_pattern(&mut FrameBuffer::layer2(), Pattern::Checkerboard);
 }
 
 pub fn layer2_sprite() {
-    let pixels = (LAYER2_W * LAYER2_H) as usize;
-    let buf = unsafe { slice::from_raw_parts_mut(LAYER2_BASE as *mut u32, pixels) };
+    let mut fb = FrameBuffer::layer2();
     // Clear to fully transparent
-    for p in buf.iter_mut() {
-        *p = 0;
-    }
+    fb.fill(0);
 
     // Draw a full-size rectangle with a color gradient, fully opaque inside
-    let rx0 = 0;
-    let ry0 = 0;
     let rw = LAYER2_W.max(1);
     let rh = LAYER2_H.max(1);
-    for y in 0..rh {
-        for x in 0..rw {
-            let gx = ((x * 255) / rw) as u8; // 0..255
-            let gy = ((y * 255) / rh) as u8; // 0..255
-            let r = gx;
-            let g = gy;
-            let mut b = 255u8.saturating_sub(((gx as u16 + gy as u16) / 2) as u8);
-            // 1px border brighter
-            if x == 0 || y == 0 || x == rw - 1 || y == rh - 1 {
-                b = 255;
-            }
-            let a: u8 = 0xFF; // fully opaque interior
-            let idx = ((ry0 + y) * LAYER2_W + (rx0 + x)) as usize;
-            buf[idx] = ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32);
-        }
-    }
-}
-
-// --- Simple drawing helpers on Layer1 (ARGB8888) ---
-// Only needed for the optional FPS overlay
-#[cfg(feature = "overlay")]
-#[inline]
-fn fb1_mut_at(base: u32) -> &'static mut [u32] {
-    let pixels = (LCD_WIDTH * LCD_HEIGHT) as usize;
-    unsafe { slice::from_raw_parts_mut(base as *mut u32, pixels) }
-}
-
-#[cfg(feature = "overlay")]
-fn draw_rect_buf(buf: &mut [u32], x: u32, y: u32, w: u32, h: u32, color: u32) {
-    let x = x.min(LCD_WIDTH);
-    let y = y.min(LCD_HEIGHT);
-    let w = w.min(LCD_WIDTH.saturating_sub(x));
-    let h = h.min(LCD_HEIGHT.saturating_sub(y));
-    if w == 0 || h == 0 {
-        return;
-    }
-    let stride = LCD_WIDTH as usize;
-    for row in 0..h {
-        let base = (y + row) as usize * stride + x as usize;
-        for col in 0..w {
-            buf[base + col as usize] = color;
+    fb.fill_with(|x, y| {
+        let gx = ((x * 255) / rw) as u8; // 0..255
+        let gy = ((y * 255) / rh) as u8; // 0..255
+        let r = gx;
+        let g = gy;
+        let mut b = 255u8.saturating_sub(((gx as u16 + gy as u16) / 2) as u8);
+        // 1px border brighter
+        if x == 0 || y == 0 || x == rw - 1 || y == rh - 1 {
+            b = 255;
         }
-    }
-}
-
-// #[cfg(feature = "overlay")]
-// pub fn draw_image_buf(buf: &mut [u32], x: u32, y: u32, w: u32, h: u32, colors: [u32]) {
-//     let x = x.min(LCD_WIDTH);
-//     let y = y.min(LCD_HEIGHT);
-//     let w = w.min(LCD_WIDTH.saturating_sub(x));
-//     let h = h.min(LCD_HEIGHT.saturating_sub(y));
-
-//     if w == 0 || h == 0 { return; }
-
-//     let color_index = 0;
-
-//     let stride = LCD_WIDTH as usize;
-//     for row in 0..h {
-//         let base = (y + row) as usize * stride + x as usize;
-//         for col in 0..w {
-//             buf[base + col as usize] = colors[color_index = (color_index + 1) % colors.len()];
-//         }
-//     }
-// }
-// 7-segment digit at (x,y), scaled by s
-#[cfg(feature = "overlay")]
-fn draw_digit_buf(buf: &mut [u32], x: u32, y: u32, digit: u8, s: u32, color: u32) {
-    let t = s; // thickness
-    let lh = 4 * s; // vertical segment length
-    let lw = 4 * s; // horizontal segment length
-                    // top-left of digit area
-                    // Segments positions
-                    //  A
-                    // F B
-                    //  G
-                    // E C
-                    //  D
-    let ax = x + s;
-    let ay = y;
-    let bx = x + 5 * s;
-    let by = y + s;
-    let cx = x + 5 * s;
-    let cy = y + 5 * s;
-    let dx = x + s;
-    let dy = y + 9 * s;
-    let ex = x;
-    let ey = y + 5 * s;
-    let fx = x;
-    let fy = y + s;
-    let gx = x + s;
-    let gy = y + 4 * s;
-
-    // segment mask per digit: A B C D E F G (bit 6..0)
-    let mask = match digit {
-        0 => 0b1111110,
-        1 => 0b0110000,
-        2 => 0b1101101,
-        3 => 0b1111001,
-        4 => 0b0110011,
-        5 => 0b1011011,
-        6 => 0b1011111,
-        7 => 0b1110000,
-        8 => 0b1111111,
-        9 => 0b1111011,
-        _ => 0,
-    };
-    if (mask & (1 << 6)) != 0 {
-        draw_seg_h_buf(buf, ax, ay, lw, t, color);
-    } // A
-    if (mask & (1 << 5)) != 0 {
-        draw_seg_v_buf(buf, bx, by, lh, t, color);
-    } // B
-    if (mask & (1 << 4)) != 0 {
-        draw_seg_v_buf(buf, cx, cy, lh, t, color);
-    } // C
-    if (mask & (1 << 3)) != 0 {
-        draw_seg_h_buf(buf, dx, dy, lw, t, color);
-    } // D
-    if (mask & (1 << 2)) != 0 {
-        draw_seg_v_buf(buf, ex, ey, lh, t, color);
-    } // E
-    if (mask & (1 << 1)) != 0 {
-        draw_seg_v_buf(buf, fx, fy, lh, t, color);
-    } // F
-    if (mask & (1 << 0)) != 0 {
-        draw_seg_h_buf(buf, gx, gy, lw, t, color);
-    } // G
-}
-
-#[cfg(feature = "overlay")]
-fn draw_seg_h_buf(buf: &mut [u32], x: u32, y: u32, len: u32, thick: u32, color: u32) {
-    draw_rect_buf(buf, x, y, len, thick, color);
-}
-#[cfg(feature = "overlay")]
-fn draw_seg_v_buf(buf: &mut [u32], x: u32, y: u32, len: u32, thick: u32, color: u32) {
-    draw_rect_buf(buf, x, y, thick, len, color);
-}
-
-#[cfg(feature = "overlay")]
-pub fn draw_fps_overlay(fps: u32) {
-    // Draw onto the back buffer, then present at VBlank to avoid mid-scan writes
-    let back_addr = lcd::layer1_back_addr();
-    let buf = fb1_mut_at(back_addr);
-
-    let s = 2; // scale
-    let x0 = 4;
-    let y0 = 4;
-    let box_w = 6 * s * 3 + s * 4; // 3 digits + spacing
-    let box_h = 10 * s + s * 2;
-    draw_rect_buf(buf, x0, y0, box_w, box_h, 0xC0000000);
-
-    let v = fps.min(999);
-    let d2 = (v / 100) as u8;
-    let d1 = ((v / 10) % 10) as u8;
-    let d0 = (v % 10) as u8;
-    let white = 0xFFFFFFFF;
-    let mut x = x0 + s;
-    if v >= 100 {
-        draw_digit_buf(buf, x, y0 + s, d2, s, white);
-    }
-    x += 6 * s + s;
-    if v >= 10 {
-        draw_digit_buf(buf, x, y0 + s, d1, s, white);
-    }
-    x += 6 * s + s;
-    draw_digit_buf(buf, x, y0 + s, d0, s, white);
-
-    // Disable buffer swapping to prevent vertical line artifacts from uninitialized back buffer
-    // lcd::swap_layer1_buffers();
+        let a: u8 = 0xFF; // fully opaque interior
+        ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+    });
 }
@@ -0,0 +1,251 @@
+//! Course editor, opened with a long press on the stats page
+//!
+//! The playfield is laid out as a timeline: `COLUMNS` obstacles a page, left
+//! to right, each drawn with its opening where the course will have it.
+//! Touching a column grabs that obstacle's opening and dragging moves it up
+//! and down; touching the first empty column adds an obstacle there. The
+//! toolbar under the field pages back and forth, deletes the last obstacle,
+//! steps through the `courses::SLOTS` save slots (dropping unsaved changes),
+//! saves to flash and plays the course. A long press on the button leaves.
+//!
+//! Everything is drawn on the Layer 2 overlay, shown opaque, and only when
+//! something changed.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use core_logic::course::{self, Pattern};
+use core_logic::lane::Lane;
+
+use crate::button::ButtonEvent;
+use crate::color;
+use crate::config::{Coord, LCD_HEIGHT, LCD_WIDTH, OBSTACLE_WIDTH};
+use crate::courses;
+use crate::display;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::lang::{self, Msg};
+use crate::log;
+use crate::theme;
+use crate::touch;
+
+// Obstacles shown a page
+const COLUMNS: usize = 6;
+const COLUMN_WIDTH: Coord = LCD_WIDTH as Coord / COLUMNS as Coord;
+// Names of the tools after the two paging arrows, in the active language
+const TOOLS: [Msg; 4] = [Msg::Delete, Msg::Slot, Msg::Save, Msg::Play];
+const TOOL_COUNT: usize = TOOLS.len() + 2;
+const TOOLS_PER_ROW: usize = 3;
+const TOOL_WIDTH: Coord = LCD_WIDTH as Coord / TOOLS_PER_ROW as Coord;
+const TOOL_HEIGHT: Coord = 40;
+const TOOLBAR_TOP: Coord = LCD_HEIGHT as Coord - 2 * TOOL_HEIGHT;
+// Inset of a tool's face from its cell
+const TOOL_INSET: Coord = 3;
+const TOOL_FACE: Rgb565 = Rgb565::new(6, 14, 10);
+const EMPTY_COLUMN: Rgb565 = Rgb565::new(2, 6, 6);
+const HINT: Rgb565 = Rgb565::new(20, 40, 20);
+
+const TOOL_PREV: usize = 0;
+const TOOL_NEXT: usize = 1;
+const TOOL_DELETE: usize = 2;
+const TOOL_SLOT: usize = 3;
+const TOOL_SAVE: usize = 4;
+const TOOL_PLAY: usize = 5;
+
+/// How the editor was left
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Exit {
+    Back,
+    // The course being edited is to be played
+    Play,
+}
+
+pub struct Editor {
+    slot: usize,
+    pattern: Pattern,
+    page: usize,
+    // The obstacle whose opening is being dragged
+    drag: Option<usize>,
+    // Still touching since the last touch began, so a tool fires once
+    held: bool,
+    // Shown in the header until the next change
+    note: Option<&'static str>,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let editor = Editor {
+            slot: 0,
+            pattern: courses::get(0),
+            page: 0,
+            drag: None,
+            held: false,
+            note: None,
+        };
+        editor.draw();
+        let _ = display::show_overlay(0xFF);
+        editor
+    }
+
+    // One frame of the editor; Some once it is left
+    pub fn update(&mut self, button: Option<ButtonEvent>) -> Option<Exit> {
+        if button == Some(ButtonEvent::Long) {
+            return Some(Exit::Back);
+        }
+        let Some((x, y)) = touch::read() else {
+            self.held = false;
+            self.drag = None;
+            return None;
+        };
+        let field = Lane::FULL.field();
+        let began = !self.held;
+        self.held = true;
+        if y >= field.y && y < field.y + field.h as Coord {
+            let before = self.pattern;
+            self.touch_field(x, y - field.y, began);
+            if self.pattern != before {
+                self.note = None;
+                self.draw();
+            }
+            None
+        } else if began && y >= TOOLBAR_TOP {
+            let tool = (y - TOOLBAR_TOP) / TOOL_HEIGHT * TOOLS_PER_ROW as Coord + x / TOOL_WIDTH;
+            self.use_tool(tool as usize)
+        } else {
+            None
+        }
+    }
+
+    // Touch at `x`, `row` rows down the field: grab the obstacle in that
+    // column, or add one, and put its opening's middle at `row`
+    fn touch_field(&mut self, x: Coord, row: Coord, began: bool) {
+        let gap = Lane::FULL.gap_bottom - Lane::FULL.gap_top;
+        let top = (row - gap / 2).clamp(0, course::MAX_ROW as Coord) as u8;
+        if began {
+            let step = self.page * COLUMNS + (x / COLUMN_WIDTH) as usize;
+            if step == self.pattern.len() && self.pattern.push(top) {
+                log::debug!("course step {} added", step);
+            }
+            self.drag = (step < self.pattern.len()).then_some(step);
+        }
+        if let Some(step) = self.drag {
+            self.pattern.set(step, top);
+        }
+    }
+
+    fn use_tool(&mut self, tool: usize) -> Option<Exit> {
+        match tool {
+            TOOL_PREV => self.page = self.page.saturating_sub(1),
+            TOOL_NEXT => self.page = (self.page + 1).min(self.last_page()),
+            TOOL_DELETE => {
+                self.pattern.pop();
+                self.page = self.page.min(self.last_page());
+                self.note = None;
+            }
+            TOOL_SLOT => {
+                self.slot = (self.slot + 1) % courses::SLOTS;
+                self.pattern = courses::get(self.slot);
+                self.page = 0;
+                self.note = None;
+            }
+            TOOL_SAVE => {
+                self.note = Some(match courses::save(self.slot, &self.pattern) {
+                    Ok(()) => lang::text(Msg::Saved),
+                    Err(()) => {
+                        log::error!("course save failed");
+                        lang::text(Msg::SaveFailed)
+                    }
+                });
+            }
+            TOOL_PLAY => {
+                courses::set_playing(&self.pattern);
+                return Some(Exit::Play);
+            }
+            _ => return None,
+        }
+        self.draw();
+        None
+    }
+
+    // The page holding the column a new obstacle would go in
+    fn last_page(&self) -> usize {
+        self.pattern.len().min(course::MAX_STEPS - 1) / COLUMNS
+    }
+
+    fn draw(&self) {
+        let mut fb = FrameBuffer::overlay();
+        let theme = theme::current();
+        let field = Lane::FULL.field();
+        fb.fill(fb.encode_rgb565(color::BLACK));
+
+        let big = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+        let small = MonoTextStyle::new(&FONT_6X10, HINT);
+        let left = TextStyleBuilder::new().baseline(Baseline::Middle).build();
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+
+        let mut header: FmtBuf<32> = FmtBuf::new();
+        let _ = write!(header, "{} {}  ", lang::text(Msg::Course), self.slot + 1);
+        let _ = match self.note {
+            Some(note) => header.write_str(note),
+            None => write!(header, "{}/{}", self.pattern.len(), course::MAX_STEPS),
+        };
+        let header_y = field.y / 2;
+        let _ = Text::with_text_style(header.as_str(), Point::new(4, header_y), big, left)
+            .draw(&mut fb);
+
+        let sky = fb.encode_rgb565(theme.background);
+        let pipe = fb.encode_rgb565(theme.pipe);
+        let empty = fb.encode_rgb565(EMPTY_COLUMN.into_storage());
+        let gap = Lane::FULL.gap_bottom - Lane::FULL.gap_top;
+        let inset = (COLUMN_WIDTH - OBSTACLE_WIDTH as Coord) / 2;
+        for column in 0..COLUMNS {
+            let step = self.page * COLUMNS + column;
+            let x = column as Coord * COLUMN_WIDTH;
+            let label_y = field.y + field.h as Coord + 8;
+            let Some(row) = self.pattern.row(step) else {
+                fb.fill_rect(x + 1, field.y, COLUMN_WIDTH as u32 - 2, field.h, empty);
+                if step == self.pattern.len() && !self.pattern.is_full() {
+                    let middle = Point::new(x + COLUMN_WIDTH / 2, field.y + field.h as Coord / 2);
+                    let _ = Text::with_text_style("+", middle, big, centered).draw(&mut fb);
+                }
+                continue;
+            };
+            fb.fill_rect(x, field.y, COLUMN_WIDTH as u32, field.h, sky);
+            let top = field.y + row as Coord;
+            fb.fill_rect(x + inset, field.y, OBSTACLE_WIDTH, row as u32, pipe);
+            let below = field.h - row as u32 - gap as u32;
+            fb.fill_rect(x + inset, top + gap, OBSTACLE_WIDTH, below, pipe);
+            let mut label: FmtBuf<4> = FmtBuf::new();
+            let _ = write!(label, "{}", step + 1);
+            let at = Point::new(x + COLUMN_WIDTH / 2, label_y);
+            let _ = Text::with_text_style(label.as_str(), at, small, centered).draw(&mut fb);
+        }
+
+        let face = fb.encode_rgb565(TOOL_FACE.into_storage());
+        for i in 0..TOOL_COUNT {
+            let name = match i {
+                TOOL_PREV => "<",
+                TOOL_NEXT => ">",
+                _ => lang::text(TOOLS[i - TOOL_DELETE]),
+            };
+            let x = (i % TOOLS_PER_ROW) as Coord * TOOL_WIDTH;
+            let y = TOOLBAR_TOP + (i / TOOLS_PER_ROW) as Coord * TOOL_HEIGHT;
+            let w = (TOOL_WIDTH - 2 * TOOL_INSET) as u32;
+            let h = (TOOL_HEIGHT - 2 * TOOL_INSET) as u32;
+            fb.fill_rect(x + TOOL_INSET, y + TOOL_INSET, w, h, face);
+            let middle = Point::new(x + TOOL_WIDTH / 2, y + TOOL_HEIGHT / 2);
+            let _ = Text::with_text_style(name, middle, big, centered).draw(&mut fb);
+        }
+
+        framebuffer::flush();
+    }
+}
@@ -0,0 +1,76 @@
+//! Screen shake, score pop and toasts
+//!
+//! The game starts a shake when the bird dies, a pop when a point is
+//! scored and a toast on a near miss; the timing is `core_logic::effects`. `update`, once a frame,
+//! moves Layer 1 for the shake (see `display::set_frame_offset`), and the
+//! HUD draws the score at `score_percent` of its size and a note while
+//! `toast_shown`. Both are LTDC only:
+//! SPI rendering has no layer to move and shows the score without the HUD.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::effects::{self, EffectKind, Effects};
+
+use crate::clock;
+use crate::config::Coord;
+use crate::display;
+use crate::game_events::GameEvent;
+
+static mut EFFECTS: Effects = Effects::new();
+// Offset Layer 1 was last moved to, so it is only reprogrammed on a change
+static mut OFFSET: (Coord, Coord) = (0, 0);
+
+pub fn shake() {
+    unsafe { EFFECTS.start(EffectKind::Shake, clock::millis()) };
+}
+
+pub fn pop() {
+    unsafe { EFFECTS.start(EffectKind::Pop, clock::millis()) };
+}
+
+// A point pops the score, a near miss puts up a toast; any crash shakes, a shield breaking too (which
+// the game asks for itself)
+pub fn on_game_event(event: GameEvent) {
+    match event {
+        GameEvent::ScorePoint { .. } => pop(),
+        GameEvent::Death { .. } => shake(),
+        GameEvent::NearMiss { .. } => unsafe { EFFECTS.start(EffectKind::Toast, clock::millis()) },
+        GameEvent::Flap { .. } | GameEvent::StateChange { .. } => {}
+    }
+}
+
+// Stop everything and put the picture back where it belongs
+pub fn clear() {
+    unsafe { EFFECTS.clear() };
+    apply((0, 0));
+}
+
+// Call once a frame
+pub fn update() {
+    let now = clock::millis();
+    let effects = unsafe { &mut EFFECTS };
+    effects.expire(now);
+    apply(effects.offset(now));
+}
+
+// Opacity of the edge warning now, for a bird `closeness` (0..=255) near
+// the ceiling or the ground
+pub fn edge_alpha(closeness: u8) -> u8 {
+    effects::edge_alpha(closeness, clock::millis())
+}
+
+// Size to draw the score at, in percent
+pub fn score_percent() -> u32 {
+    unsafe { EFFECTS.score_percent(clock::millis()) }
+}
+
+pub fn toast_shown() -> bool {
+    unsafe { EFFECTS.toast_shown(clock::millis()) }
+}
+
+fn apply(offset: (Coord, Coord)) {
+    if unsafe { OFFSET } != offset {
+        let _ = display::set_frame_offset(offset.0, offset.1);
+        unsafe { OFFSET = offset };
+    }
+}
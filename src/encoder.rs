@@ -0,0 +1,143 @@
+//! Rotary encoder for the menus
+//!
+//! A KY-040 style encoder: A on PD12 (TIM4_CH1, AF2), B on PB7 (TIM4_CH2,
+//! AF2), its push switch on PG3, all three switching to ground against the
+//! internal pull-ups. TIM3 already drives the backlight, so TIM4 counts the
+//! quadrature in encoder mode 3, four counts a detent, with the inputs
+//! filtered against contact bounce; nothing runs on the CPU until `take`
+//! looks at the counter. PD12 is also the panel's unused RDX line, which
+//! idles high as the pull-up holds it.
+//!
+//! Each detent comes out of `take` as a `MenuNav`, clockwise moving on,
+//! and so does each press of the switch. The pause menu scrolls with it and
+//! adjusts values on it (see `Game`), and the tilt wizard picks its
+//! sensitivity with it. Nothing can tell whether an encoder is fitted; one
+//! that is not just never turns.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::input::{Detents, Lockout};
+
+use crate::clock;
+use crate::resources;
+
+const A_LINE: u32 = 12;
+const B_LINE: u32 = 7;
+const SWITCH_LINE: u32 = 3;
+const COUNTS_PER_DETENT: u16 = 4;
+const SWITCH_DEBOUNCE_MS: u32 = 20;
+
+/// What the encoder asks of a menu
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MenuNav {
+    // One detent clockwise
+    Next,
+    // One detent counterclockwise
+    Prev,
+    // The switch went down
+    Press,
+}
+
+static mut DETENTS: Detents = Detents::new(0, COUNTS_PER_DETENT);
+static mut SWITCH: Lockout = Lockout::new(SWITCH_DEBOUNCE_MS);
+
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.ahb1enr.modify(|_, w| {
+        w.gpioben()
+            .enabled()
+            .gpioden()
+            .enabled()
+            .gpiogen()
+            .enabled()
+    });
+    dp.RCC.apb1enr.modify(|_, w| w.tim4en().enabled());
+
+    // PD12 and PB7 to AF2 with pull-ups, PG3 input with pull-up
+    let af = |line: u32| 0b10 << (line * 2);
+    let up = |line: u32| 0b01 << (line * 2);
+    let field = |line: u32| 0b11 << (line * 2);
+    dp.GPIOD
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(A_LINE) | af(A_LINE)) });
+    dp.GPIOD
+        .pupdr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(A_LINE) | up(A_LINE)) });
+    dp.GPIOD
+        .afrh
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0xF << 16) | 2 << 16) });
+    dp.GPIOB
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(B_LINE) | af(B_LINE)) });
+    dp.GPIOB
+        .pupdr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(B_LINE) | up(B_LINE)) });
+    dp.GPIOB
+        .afrl
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0xF << 28) | 2 << 28) });
+    dp.GPIOG
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(SWITCH_LINE)) });
+    dp.GPIOG
+        .pupdr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !field(SWITCH_LINE) | up(SWITCH_LINE)) });
+
+    let tim = &dp.TIM4;
+    tim.cr1.modify(|_, w| w.cen().clear_bit());
+    // CKD = /4 for the filter clock: 84 MHz / 4 = 21 MHz
+    tim.cr1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << 8) | 0b10 << 8) });
+    // CC1S = CC2S = 01 (TI1, TI2), IC1F = IC2F = 1111 (fDTS / 32, 8 samples)
+    tim.ccmr1_input()
+        .write(|w| unsafe { w.bits(0b01 | 0b1111 << 4 | 0b01 << 8 | 0b1111 << 12) });
+    // Both inputs rising-edge polarity
+    tim.ccer.write(|w| unsafe { w.bits(0) });
+    // SMS = 011: count on both edges of both inputs
+    tim.smcr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !0b111 | 0b011) });
+    tim.arr.write(|w| unsafe { w.bits(0xFFFF) });
+    tim.cnt.write(|w| unsafe { w.bits(0) });
+    tim.cr1.modify(|_, w| w.cen().set_bit());
+    clock::delay_us(10);
+
+    clear();
+}
+
+fn count() -> u16 {
+    let dp = resources::pac();
+    dp.TIM4.cnt.read().bits() as u16
+}
+
+pub fn switch_down() -> bool {
+    let dp = resources::pac();
+    dp.GPIOG.idr.read().bits() & (1 << SWITCH_LINE) == 0
+}
+
+// The next thing the encoder did, oldest first: a press, then detents one
+// at a time, so a quick turn between two frames plays out over the next few
+pub fn take() -> Option<MenuNav> {
+    let (detents, switch) = unsafe { (&mut DETENTS, &mut SWITCH) };
+    if switch.sample(switch_down(), clock::millis()) == Some(true) {
+        return Some(MenuNav::Press);
+    }
+    detents.update(count());
+    match detents.take() {
+        1 => Some(MenuNav::Next),
+        -1 => Some(MenuNav::Prev),
+        _ => None,
+    }
+}
+
+// Forget turns and presses made while nothing was looking, for a menu
+// opening
+pub fn clear() {
+    unsafe {
+        DETENTS = Detents::new(count(), COUNTS_PER_DETENT);
+        SWITCH.reset(switch_down());
+    }
+}
+
+// The raw counter, for the shell
+pub fn raw_count() -> u16 {
+    count()
+}
@@ -0,0 +1,241 @@
+//! Things that move about the playfield
+//!
+//! The bird, the pickups and the world they fly through (obstacles, sky
+//! and ground, see `world`) are each an `Entity`. The game steps them
+//! together a fixed tick at a time (see `timestep`): every `update` first,
+//! then whatever rules tie them together (collisions, scoring, spawning). Once a frame, however many ticks it
+//! ran, every `draw` goes into one `Renderer`, which carries how far the
+//! clock is into the next tick so positions can be blended between the last
+//! two. A new kind of object only has to implement the trait and join the
+//! game's entity list. Its `depth` says which layer it is on: the game draws
+//! the list back to front, and scenery behind the obstacles (`backdrop`)
+//! scrolls at its layer's rate.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::scroll::Depth;
+use core_logic::skyline::Skyline;
+use core_logic::sprite_batch::SpriteBatch;
+
+use crate::backdrop;
+
+use crate::color::{self, Rgb565};
+use crate::config::{Coord, Rect, LCD_WIDTH};
+use crate::display::{self, Target};
+use crate::framebuffer::{Image, ImageTransform, RenderBackend, Scale};
+use crate::lane::Lane;
+use crate::profiler::{self, Phase, Scope};
+use crate::sky;
+use crate::sprite_cache::Cached;
+
+pub trait Entity {
+    // Move on by `dt` ticks; nothing is drawn
+    fn update(&mut self, dt: u32);
+
+    // The area of the screen it covers now
+    fn bounds(&self) -> Rect;
+
+    // The layer it is on; most things move with the obstacles
+    fn depth(&self) -> Depth {
+        Depth::PLAY
+    }
+
+    // Show it `renderer.alpha()` of the way from where it was a tick ago to
+    // where it now is, erasing what is left of it where it was drawn last
+    fn draw(&self, renderer: &mut Renderer);
+}
+
+// Outline every area a frame drew, for the debug menu. A thing erases where
+// it was drawn last, so the outlines go with it as it moves on.
+static mut SHOW_DIRTY: bool = false;
+const DIRTY_COLOR: u16 = color::GREEN;
+// Areas remembered a frame; any more are not outlined
+const MAX_DIRTY: usize = 32;
+
+pub fn show_dirty_rects(on: bool) {
+    unsafe { SHOW_DIRTY = on };
+}
+
+pub fn dirty_rects_shown() -> bool {
+    unsafe { SHOW_DIRTY }
+}
+
+// One frame's drawing in a lane. Fills are clipped to the lane, images to
+// the screen; everything goes to the same render target and is presented
+// together by `finish`.
+pub struct Renderer {
+    target: Target,
+    lane: Lane,
+    alpha: u8,
+    // Towers on the layer behind, painted in with the sky
+    skyline: Option<Skyline>,
+    // What was drawn, while `show_dirty_rects` is on
+    dirty: [Option<Rect>; MAX_DIRTY],
+    _render: Scope,
+}
+
+impl Renderer {
+    // Drawing things where they are
+    pub fn new(lane: Lane) -> Self {
+        Self::between_ticks(lane, 255)
+    }
+
+    // Drawing things `alpha` of a tick on from their last positions
+    pub fn between_ticks(lane: Lane, alpha: u8) -> Self {
+        Renderer {
+            target: display::render_target(),
+            lane,
+            alpha,
+            skyline: None,
+            dirty: [None; MAX_DIRTY],
+            _render: profiler::scope(Phase::Render),
+        }
+    }
+
+    fn touched(&mut self, rect: Rect) {
+        if !dirty_rects_shown() {
+            return;
+        }
+        if let Some(slot) = self.dirty.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(rect);
+        }
+    }
+
+    pub fn lane(&self) -> Lane {
+        self.lane
+    }
+
+    // From here on `fill_sky` paints `skyline`'s towers in over the sky
+    pub fn set_skyline(&mut self, skyline: Option<Skyline>) {
+        self.skyline = skyline;
+    }
+
+    // 0..=255 from a tick ago to now; see `timestep::lerp`
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, rgb565: u16) {
+        if let Some(r) = rect.intersection(&self.lane.rect()) {
+            self.touched(r);
+            self.target.fill_rect(r.x, r.y, r.w, r.h, rgb565);
+        }
+    }
+
+    // The one-pixel border of `rect`
+    pub fn outline_rect(&mut self, rect: Rect, rgb565: u16) {
+        for edge in rect.outline() {
+            self.fill_rect(edge, rgb565);
+        }
+    }
+
+    // `rgb565` over `rect` at `alpha`, 0..=255, letting what is under show
+    pub fn blend_rect(&mut self, rect: Rect, rgb565: u16, alpha: u8) {
+        if let Some(r) = rect.intersection(&self.lane.rect()) {
+            self.touched(r);
+            self.target.blend_rect(r.x, r.y, r.w, r.h, rgb565, alpha);
+        }
+    }
+
+    // Cover `rect` with `tile`, repeating from the clipped corner
+    pub fn fill_tiled(&mut self, rect: Rect, tile: &Image) {
+        if let Some(r) = rect.intersection(&self.lane.rect()) {
+            self.touched(r);
+            self.target.fill_tiled(r.x, r.y, r.w, r.h, tile);
+        }
+    }
+
+    // As `fill_tiled`, starting `skip` (columns, rows) into the tile at the
+    // top left of `rect`, wherever the lane clips it
+    pub fn fill_tiled_from(&mut self, rect: Rect, tile: &Image, skip: (u32, u32)) {
+        if let Some(r) = rect.intersection(&self.lane.rect()) {
+            let skip = (
+                skip.0 + (r.x - rect.x) as u32,
+                skip.1 + (r.y - rect.y) as u32,
+            );
+            self.touched(r);
+            self.target.fill_tiled_from(r.x, r.y, r.w, r.h, tile, skip);
+        }
+    }
+
+    // Paint the sky back over `rect`
+    pub fn fill_sky(&mut self, rect: Rect) {
+        let Some(r) = rect.intersection(&self.lane.rect()) else {
+            return;
+        };
+        self.touched(r);
+        let sky = sky::current();
+        let field = self.lane.field();
+        self.target
+            .fill_rows(r.x, r.y, r.w, r.h, &|y| sky.row_color(y, field).0);
+        let Some(skyline) = self.skyline else {
+            return;
+        };
+        let ground = self.lane.y(self.lane.ground);
+        for tower in skyline.towers(ground, LCD_WIDTH) {
+            if let Some(t) = tower.intersection(&r) {
+                let color = |y| backdrop::tower_color(sky.row_color(y, field)).0;
+                self.target.fill_rows(t.x, t.y, t.w, t.h, &color);
+            }
+        }
+    }
+
+    // An asset image, stored bottom row first
+    pub fn draw_image(&mut self, x: Coord, y: Coord, image: &Image) {
+        self.touched(Rect::new(x, y, image.w, image.h));
+        self.target.blit(x, y, image, ImageTransform::FLIP_Y, None);
+    }
+
+    // An asset image with its SPRITE_KEY pixels left out
+    pub fn draw_sprite(&mut self, x: Coord, y: Coord, image: &Image) {
+        self.draw_sprite_scaled(x, y, image, Scale::ONE);
+    }
+
+    // The same, drawn at `scale`
+    pub fn draw_sprite_scaled(&mut self, x: Coord, y: Coord, image: &Image, scale: Scale) {
+        let transform = ImageTransform::FLIP_Y.scaled(scale);
+        let (w, h) = transform.size(image);
+        self.touched(Rect::new(x, y, w, h));
+        let key = Some(color::SPRITE_KEY);
+        self.target.blit(x, y, image, transform, key);
+    }
+
+    // Several sprites in one pass, clipped to the batch's rectangle
+    pub fn draw_batch<const N: usize>(&mut self, batch: &SpriteBatch<N>) {
+        for sprite in batch.sprites() {
+            self.touched(sprite.rect());
+        }
+        batch.draw(&mut self.target);
+    }
+
+    // A sprite already converted by the sprite cache
+    pub fn draw_cached(&mut self, x: Coord, y: Coord, cached: &Cached) {
+        self.touched(Rect::new(x, y, cached.w, cached.h));
+        self.target.blit_argb(x, y, cached.w, cached.pixels);
+    }
+
+    // A glyph of the score font, background and all; see `text_field`
+    pub fn draw_glyph(&mut self, x: Coord, y: Coord, ch: u8, rgb565: u16, bg565: u16) {
+        let (w, h) = display::glyph_size();
+        self.touched(Rect::new(x, y, w, h));
+        display::draw_glyph(&mut self.target, x, y, ch, Rgb565(rgb565), Rgb565(bg565));
+    }
+
+    // Make the frame's drawing visible
+    pub fn finish(mut self) {
+        for rect in self.dirty.into_iter().flatten() {
+            for edge in rect.outline() {
+                self.target
+                    .fill_rect(edge.x, edge.y, edge.w, edge.h, DIRTY_COLOR);
+            }
+        }
+        self.target.present();
+    }
+}
+
+// Draw one entity in `lane` on its own, outside a frame's renderer
+pub fn draw_now(entity: &dyn Entity, lane: Lane) {
+    let mut renderer = Renderer::new(lane);
+    entity.draw(&mut renderer);
+    renderer.finish();
+}
@@ -0,0 +1,40 @@
+//! Hardware bring-up errors
+//!
+//! Init functions that can find the hardware missing or misbehaving return
+//! `Result<_, HwError>`. The caller knows which part it was bringing up, so
+//! the error only says what went wrong; `boot_report` pairs the two.
+#![allow(dead_code)]
+
+use core::fmt;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum HwError {
+    // A status flag did not come up in time
+    Timeout,
+    // Nothing answered, or something other than the expected device did
+    NoResponse,
+    // The bus is held busy, e.g. a slave keeping SDA low
+    BusStuck,
+    // A clock the peripheral runs from is not running
+    ClockNotReady,
+    // Memory read back something other than what was written
+    SelfTestFailed,
+}
+
+impl HwError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HwError::Timeout => "timeout",
+            HwError::NoResponse => "no answer",
+            HwError::BusStuck => "bus stuck",
+            HwError::ClockNotReady => "no clock",
+            HwError::SelfTestFailed => "test failed",
+        }
+    }
+}
+
+impl fmt::Display for HwError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,139 @@
+//! Runs the main loop as cooperative async tasks
+//!
+//! `main` pins its tasks and hands them to `run`, which polls them with the
+//! core_logic `Executor` and sleeps in WFI when none has anything to do.
+//! What the tasks wait on:
+//! - `sleep_ms`: SysTick wakes the task once the time is up
+//! - `next_vblank`: the LTDC line interrupt wakes the task at vertical blank
+//! - `yield_now` (core_logic): back round after the other tasks, which the
+//!   async I2C reads use between looks at the bus
+//!
+//! Each wait keeps its task's `Waker` in a `Shared` slot, taken and woken
+//! from the interrupt.
+#![allow(dead_code)]
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use core_logic::executor::{Executor, Task};
+
+use crate::clock;
+use crate::lcd::LcdDriver;
+use crate::profiler::{self, Phase};
+use crate::resources::Shared;
+
+// Game, periodic jobs (see scheduler) and the serial link
+pub const TASKS: usize = 3;
+// Tasks asleep at once; past this a sleeper polls every pass instead
+const TIMER_SLOTS: usize = 4;
+
+static EXECUTOR: Executor<TASKS> = Executor::new();
+
+// (started, milliseconds, waker) per sleeping task
+type Timers = [Option<(u32, u32, Waker)>; TIMER_SLOTS];
+
+static TIMERS: Shared<Timers> = Shared::of([const { None }; TIMER_SLOTS]);
+static VBLANK: Shared<Waker> = Shared::new();
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Executor<TASKS>>()
+    + core::mem::size_of::<Shared<Timers>>()
+    + core::mem::size_of::<Shared<Waker>>();
+
+pub fn run(tasks: &mut [Task<'_>; TASKS]) -> ! {
+    loop {
+        while EXECUTOR.poll(tasks) {}
+        // With interrupts masked a wake cannot slip in between the check
+        // and the WFI; a pending interrupt still ends the WFI
+        let _idle = profiler::scope(Phase::Idle);
+        cortex_m::interrupt::free(|_| {
+            if !EXECUTOR.has_woken() {
+                cortex_m::asm::wfi();
+            }
+        });
+    }
+}
+
+// Wake sleepers whose time is up; from SysTick
+pub fn on_tick(now: u32) {
+    TIMERS.lock(|timers| {
+        for slot in timers.iter_mut() {
+            let due = matches!(slot, Some((start, ms, _)) if now.wrapping_sub(*start) >= *ms);
+            if let Some((_, _, waker)) = slot.take_if(|_| due) {
+                waker.wake();
+            }
+        }
+    });
+}
+
+// Wake the task waiting for vertical blank; from the LTDC line interrupt
+pub fn on_vblank() {
+    if let Some(waker) = VBLANK.take() {
+        waker.wake();
+    }
+}
+
+pub struct Sleep {
+    start: u32,
+    ms: u32,
+}
+
+pub fn sleep_ms(ms: u32) -> Sleep {
+    Sleep {
+        start: clock::millis(),
+        ms,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if clock::millis().wrapping_sub(self.start) >= self.ms {
+            return Poll::Ready(());
+        }
+        let (start, ms) = (self.start, self.ms);
+        TIMERS.lock(|timers| {
+            let slot = timers
+                .iter()
+                .position(|slot| matches!(slot, Some((_, _, w)) if w.will_wake(cx.waker())))
+                .or_else(|| timers.iter().position(Option::is_none));
+            match slot {
+                Some(i) => timers[i] = Some((start, ms, cx.waker().clone())),
+                None => cx.waker().wake_by_ref(),
+            }
+        });
+        Poll::Pending
+    }
+}
+
+pub struct NextVblank {
+    seen: u32,
+}
+
+// The start of the next vertical blank; at once with the LTDC off
+pub fn next_vblank() -> NextVblank {
+    NextVblank {
+        seen: LcdDriver::vblank_count(),
+    }
+}
+
+impl Future for NextVblank {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if !LcdDriver::attach().is_enabled() {
+            return Poll::Ready(());
+        }
+        // The count is checked again with interrupts masked, so a blank
+        // between the check and keeping the waker is not slept through
+        cortex_m::interrupt::free(|_| {
+            if LcdDriver::vblank_count() != self.seen {
+                return Poll::Ready(());
+            }
+            VBLANK.put(cx.waker().clone());
+            Poll::Pending
+        })
+    }
+}
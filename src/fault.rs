@@ -0,0 +1,269 @@
+//! Fault diagnostics screen
+//!
+//! BusFault, UsageFault and MemManage are left disabled in SHCSR so they
+//! escalate to HardFault, whose handler gets the stacked exception frame from
+//! cortex-m-rt. The original fault class and cause are recovered from CFSR.
+//! The report goes out over the log and onto Layer 1, unless the fault
+//! address is in SDRAM, in which case drawing would just fault again.
+//!
+//! A canary painted at the bottom of the stack is checked both here and,
+//! once per frame, from the main loop, so an overflow into .bss is caught
+//! before it corrupts game state silently.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use cortex_m::peripheral::SCB;
+use cortex_m_rt::{exception, ExceptionFrame};
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+use core_logic::blackbox::Event;
+
+use crate::blackbox;
+use crate::config::Coord;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::FrameBuffer;
+use crate::lcd::LcdDriver;
+use crate::log;
+use crate::sdram::SDRAM_BASE;
+
+const SDRAM_END: u32 = SDRAM_BASE + 8 * 1024 * 1024;
+
+const CANARY: u32 = 0xC0FF_EE55;
+pub const CANARY_WORDS: usize = 8;
+
+const LINE_HEIGHT: Coord = 12;
+
+// CFSR bits, MemManage / BusFault / UsageFault status in one register
+const MMARVALID: u32 = 1 << 7;
+const BFARVALID: u32 = 1 << 15;
+// CCR: trap integer divide by zero instead of returning 0
+const CCR_DIV_0_TRP: u32 = 1 << 4;
+
+extern "C" {
+    // Lowest address the stack may grow down to (cortex-m-rt linker script)
+    static mut _stack_end: u32;
+}
+
+/// Everything shown on the fault screen
+pub struct FaultReport {
+    // None for a stack overflow found by the canary check
+    pub frame: Option<[u32; 8]>,
+    pub cfsr: u32,
+    pub hfsr: u32,
+    pub mmfar: u32,
+    pub bfar: u32,
+    pub canary_ok: bool,
+}
+
+impl FaultReport {
+    fn capture(frame: Option<[u32; 8]>) -> Self {
+        let scb = unsafe { &*SCB::PTR };
+        Self {
+            frame,
+            cfsr: scb.cfsr.read(),
+            hfsr: scb.hfsr.read(),
+            mmfar: scb.mmfar.read(),
+            bfar: scb.bfar.read(),
+            canary_ok: stack_canary_ok(),
+        }
+    }
+
+    // The faulting data address, when the fault status says it is valid
+    pub fn fault_address(&self) -> Option<u32> {
+        if self.cfsr & BFARVALID != 0 {
+            Some(self.bfar)
+        } else if self.cfsr & MMARVALID != 0 {
+            Some(self.mmfar)
+        } else {
+            None
+        }
+    }
+
+    // One-line explanation, most specific cause first
+    pub fn cause(&self) -> &'static str {
+        const CAUSES: [(u32, &str); 14] = [
+            (1 << 0, "MemManage: instruction fetch"),
+            (1 << 1, "MemManage: data access"),
+            (1 << 3, "MemManage: unstacking"),
+            (1 << 4, "MemManage: stacking"),
+            (1 << 8, "BusFault: instruction fetch"),
+            (1 << 9, "BusFault: precise data"),
+            (1 << 10, "BusFault: imprecise data"),
+            (1 << 11, "BusFault: unstacking"),
+            (1 << 12, "BusFault: stacking"),
+            (1 << 16, "UsageFault: undefined instr"),
+            (1 << 17, "UsageFault: invalid state"),
+            (1 << 18, "UsageFault: invalid PC"),
+            (1 << 24, "UsageFault: unaligned"),
+            (1 << 25, "UsageFault: divide by zero"),
+        ];
+        if self.frame.is_none() {
+            return "Stack overflow (canary)";
+        }
+        if !self.canary_ok {
+            return "Stack overflow";
+        }
+        if let Some(&(_, cause)) = CAUSES.iter().find(|(bit, _)| self.cfsr & bit != 0) {
+            return cause;
+        }
+        if self.hfsr & (1 << 1) != 0 {
+            return "HardFault: vector table read";
+        }
+        "HardFault"
+    }
+
+    fn in_sdram(&self) -> bool {
+        matches!(self.fault_address(), Some(addr) if (SDRAM_BASE..SDRAM_END).contains(&addr))
+    }
+}
+
+// Trap divide by zero and paint the stack canary; call early at boot
+pub fn init() {
+    unsafe {
+        let scb = &*SCB::PTR;
+        scb.ccr.modify(|r| r | CCR_DIV_0_TRP);
+
+        let bottom = core::ptr::addr_of_mut!(_stack_end);
+        for i in 0..CANARY_WORDS {
+            core::ptr::write_volatile(bottom.add(i), CANARY);
+        }
+    }
+}
+
+pub fn stack_canary_ok() -> bool {
+    let bottom = core::ptr::addr_of!(_stack_end);
+    (0..CANARY_WORDS).all(|i| unsafe { core::ptr::read_volatile(bottom.add(i)) } == CANARY)
+}
+
+// Call once per frame; stops on the fault screen if the stack overflowed
+pub fn check_stack() {
+    if !stack_canary_ok() {
+        halt(&FaultReport::capture(None));
+    }
+}
+
+#[exception]
+unsafe fn HardFault(ef: &ExceptionFrame) -> ! {
+    let frame = [
+        ef.r0(),
+        ef.r1(),
+        ef.r2(),
+        ef.r3(),
+        ef.r12(),
+        ef.lr(),
+        ef.pc(),
+        ef.xpsr(),
+    ];
+    halt(&FaultReport::capture(Some(frame)))
+}
+
+fn halt(report: &FaultReport) -> ! {
+    cortex_m::interrupt::disable();
+    blackbox::record(Event::Fault(report.frame.map_or(0, |frame| frame[6])));
+    log_report(report);
+    if !report.in_sdram() {
+        draw_report(report);
+    }
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
+fn log_report(report: &FaultReport) {
+    log::error!("FAULT {}", report.cause());
+    if let Some(f) = report.frame {
+        log::error!(
+            "r0 {:08x} r1 {:08x} r2 {:08x} r3 {:08x}",
+            f[0],
+            f[1],
+            f[2],
+            f[3]
+        );
+        log::error!(
+            "r12 {:08x} lr {:08x} pc {:08x} xpsr {:08x}",
+            f[4],
+            f[5],
+            f[6],
+            f[7]
+        );
+    }
+    log::error!(
+        "CFSR {:08x} HFSR {:08x} MMFAR {:08x} BFAR {:08x} canary {}",
+        report.cfsr,
+        report.hfsr,
+        report.mmfar,
+        report.bfar,
+        if report.canary_ok { "ok" } else { "BAD" }
+    );
+}
+
+fn draw_report(report: &FaultReport) {
+    // Only Layer 1 should be visible, at full brightness
+    let lcd = LcdDriver::attach();
+    lcd.set_layer2_alpha(0);
+    lcd.set_layer1_alpha(0xFF);
+
+    let mut fb = FrameBuffer::layer1();
+    let red = fb.encode_rgb565(0xA000);
+    fb.fill(red);
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::WHITE)
+        .background_color(Rgb565::new(20, 0, 0))
+        .build();
+
+    let mut y = 4;
+    let mut line: FmtBuf<40> = FmtBuf::new();
+    let mut draw_line = |line: &mut FmtBuf<40>| {
+        let _ = Text::with_baseline(line.as_str(), Point::new(4, y), style, Baseline::Top)
+            .draw(&mut fb);
+        y += LINE_HEIGHT;
+        line.clear();
+    };
+
+    let _ = write!(line, "FAULT");
+    draw_line(&mut line);
+    let _ = write!(line, "{}", report.cause());
+    draw_line(&mut line);
+    draw_line(&mut line);
+
+    if let Some(f) = report.frame {
+        let names = ["r0", "r1", "r2", "r3", "r12", "lr", "pc", "xpsr"];
+        for (name, value) in names.iter().zip(f.iter()) {
+            let _ = write!(line, "{:<5}{:08x}", name, value);
+            draw_line(&mut line);
+        }
+        draw_line(&mut line);
+    }
+
+    let _ = write!(line, "CFSR {:08x}", report.cfsr);
+    draw_line(&mut line);
+    let _ = write!(line, "HFSR {:08x}", report.hfsr);
+    draw_line(&mut line);
+    match report.fault_address() {
+        Some(addr) => {
+            let _ = write!(line, "addr {:08x}", addr);
+        }
+        None => {
+            let _ = write!(line, "addr unknown");
+        }
+    }
+    draw_line(&mut line);
+    let _ = write!(
+        line,
+        "stack canary {}",
+        if report.canary_ok {
+            "ok"
+        } else {
+            "OVERWRITTEN"
+        }
+    );
+    draw_line(&mut line);
+
+    cortex_m::asm::dsb();
+}
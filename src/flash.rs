@@ -0,0 +1,187 @@
+//! Internal flash erase and program, for data kept in sectors the linker
+//! does not use
+//!
+//! `memory.x` stops the FLASH region short of the last five 128 KB sectors
+//! of bank 2: sector 19 is `ARTWORK`, 20 and 21 are `STORE` (the pair of
+//! sectors `store` keeps the settings and courses in) and 22 and 23 form
+//! `SPARE`, so saving a screenshot never erases the settings or a course.
+//! `ARTWORK` is only ever written by the debug probe, never from here. The
+//! CPU stalls on instruction fetches while a sector is being erased, so
+//! callers should not expect the game to keep running meanwhile.
+//!
+//! Programming is word-wide, which needs a 2.7-3.6 V supply. The power
+//! voltage detector watches for the supply sagging below 2.7 V, and nothing
+//! is erased or programmed while it does: a brownout on the way is better
+//! met with the old data intact. Each word is programmed and checked, and
+//! each erase set up and started, with interrupts masked, and the watchdog
+//! is refreshed before every word and around every erase.
+#![allow(dead_code)]
+
+use crate::iwdg;
+use crate::log;
+use crate::resources;
+use crate::sdram::arena::Region;
+
+pub const SECTOR_SIZE: u32 = 128 * 1024;
+// Sectors as numbered in the reference manual
+const STORE_FIRST_SECTOR: u8 = 20;
+const SPARE_FIRST_SECTOR: u8 = 22;
+const SPARE_SECTORS: u8 = 2;
+
+// Written with the probe, not by `program`; see artwork.rs
+pub const ARTWORK: Region = Region {
+    base: 0x0816_0000,
+    size: SECTOR_SIZE,
+};
+
+// A and B; before the store, 20 held the courses and 21 the settings
+pub const STORE: [Region; 2] = [
+    Region {
+        base: 0x0818_0000,
+        size: SECTOR_SIZE,
+    },
+    Region {
+        base: 0x081A_0000,
+        size: SECTOR_SIZE,
+    },
+];
+
+pub const SPARE: Region = Region {
+    base: 0x081C_0000,
+    size: SPARE_SECTORS as u32 * SECTOR_SIZE,
+};
+
+// Everything program() may write: STORE and SPARE follow on one from the
+// next
+const WRITABLE: Region = Region {
+    base: STORE[0].base,
+    size: STORE[0].size + STORE[1].size + SPARE.size,
+};
+
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+// PSIZE = x32
+const PSIZE_WORD: u8 = 0b10;
+// SR error flags: PGSERR, PGPERR, PGAERR, WRPERR
+const SR_ERRORS: u32 = 0xF0;
+// PVD threshold 2.7 V, the least word-wide programming is specified for
+const PVD_LEVEL: u8 = 0b101;
+
+// Watch the supply from here on; before the first erase or program
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.apb1enr.modify(|_, w| w.pwren().enabled());
+    dp.PWR
+        .cr
+        .modify(|_, w| unsafe { w.pls().bits(PVD_LEVEL) }.pvde().set_bit());
+}
+
+// The supply is high enough to program and erase
+pub fn supply_ok() -> bool {
+    let dp = resources::pac();
+    dp.PWR.cr.read().pvde().bit_is_clear() || dp.PWR.csr.read().pvdo().bit_is_clear()
+}
+
+fn unlock() {
+    let dp = resources::pac();
+    if dp.FLASH.cr.read().lock().bit_is_set() {
+        dp.FLASH.keyr.write(|w| w.key().bits(KEY1));
+        dp.FLASH.keyr.write(|w| w.key().bits(KEY2));
+    }
+}
+
+fn lock() {
+    let dp = resources::pac();
+    dp.FLASH.cr.modify(|_, w| w.lock().set_bit());
+}
+
+// Wait for the current operation; clears and reports any error flags
+fn wait() -> Result<(), ()> {
+    let dp = resources::pac();
+    while dp.FLASH.sr.read().bsy().bit_is_set() {}
+    let errors = dp.FLASH.sr.read().bits() & SR_ERRORS;
+    if errors != 0 {
+        dp.FLASH.sr.write(|w| unsafe { w.bits(errors) });
+        return Err(());
+    }
+    Ok(())
+}
+
+// Erase every sector of SPARE
+pub fn erase_spare() -> Result<(), ()> {
+    erase_sectors(SPARE_FIRST_SECTOR, SPARE_SECTORS)
+}
+
+// Erase sector A (0) or B (1) of STORE
+pub fn erase_store(sector: usize) -> Result<(), ()> {
+    erase_sectors(STORE_FIRST_SECTOR + sector.min(1) as u8, 1)
+}
+
+fn erase_sectors(first: u8, count: u8) -> Result<(), ()> {
+    if !supply_ok() {
+        log::warn!("supply low, flash erase refused");
+        return Err(());
+    }
+    let dp = resources::pac();
+    unlock();
+    let mut result = Ok(());
+    for sector in first..first + count {
+        // Bank 2 sectors 12..23 are encoded as 0b1_0000 + (n - 12)
+        let snb = if sector >= 12 {
+            0x10 + sector - 12
+        } else {
+            sector
+        };
+        // Each 128 KB sector takes 1-2 s, with the CPU stalled throughout
+        iwdg::feed();
+        cortex_m::interrupt::free(|_| {
+            dp.FLASH
+                .cr
+                .write(|w| unsafe { w.ser().set_bit().snb().bits(snb).psize().bits(PSIZE_WORD) });
+            dp.FLASH.cr.modify(|_, w| w.strt().set_bit());
+        });
+        result = wait();
+        iwdg::feed();
+        if result.is_err() {
+            break;
+        }
+    }
+    dp.FLASH.cr.modify(|_, w| w.ser().clear_bit());
+    lock();
+    result
+}
+
+// Program words starting at `addr`, which must be word aligned, inside
+// STORE or SPARE, and erased
+pub fn program(addr: u32, words: impl IntoIterator<Item = u32>) -> Result<(), ()> {
+    if !supply_ok() {
+        log::warn!("supply low, flash program refused");
+        return Err(());
+    }
+    let dp = resources::pac();
+    let end = WRITABLE.base + WRITABLE.size;
+    unlock();
+    dp.FLASH
+        .cr
+        .write(|w| w.pg().set_bit().psize().bits(PSIZE_WORD));
+    let mut result = Ok(());
+    let mut addr = addr;
+    for word in words {
+        if addr < WRITABLE.base || addr + 4 > end {
+            result = Err(());
+            break;
+        }
+        iwdg::feed();
+        result = cortex_m::interrupt::free(|_| {
+            unsafe { core::ptr::write_volatile(addr as *mut u32, word) };
+            wait()
+        });
+        if result.is_err() {
+            break;
+        }
+        addr += 4;
+    }
+    dp.FLASH.cr.modify(|_, w| w.pg().clear_bit());
+    lock();
+    result
+}
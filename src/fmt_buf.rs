@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+
+use core::ffi::CStr;
+use core::fmt;
+
+/// Fixed-capacity text buffer implementing `core::fmt::Write`
+///
+/// Lets reports and status screens use `write!` without an allocator. The
+/// buffer always keeps one spare byte so it can be handed to the display as a
+/// nul-terminated C string. Output that does not fit is silently truncated.
+pub struct FmtBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> FmtBuf<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.buf[0] = 0;
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole UTF-8 sequences are ever copied in, see write_str
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+
+    pub fn as_cstr(&self) -> &CStr {
+        CStr::from_bytes_until_nul(&self.buf).unwrap_or(c"")
+    }
+}
+
+impl<const N: usize> fmt::Write for FmtBuf<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Reserve the last byte for the nul terminator
+        let room = N.saturating_sub(1) - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        self.buf[self.len] = 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! Frame recording for regression tests on the host
+//!
+//! While recording (`rec on` in the shell) every presented frame goes out
+//! over the telemetry link as one `KIND_FRAME` message: a checksum of
+//! Layer 1 from the CRC unit, taken once retro mode and the raster effects
+//! have put the picture there, and the input the game acted on that frame.
+//! A host script keeps the stream and compares it with one from another
+//! firmware build to find where rendering first differs.
+//!
+//! Recording also gives every game started meanwhile the same random seed
+//! (`seed`) in place of the time, so two builds fed the same input draw the
+//! same obstacles, coins and particles. Otherwise each game takes the time
+//! once, when it is set up (`new_game`), so whatever is made later in the
+//! same game comes from the seed a replay keeps.
+//!
+//! `KIND_FRAME` payload, little-endian:
+//!
+//! ```text
+//! frame u32 | layer1 crc u32 | state u8 | score u16 | player_y i16 |
+//! ticks u8 | button u8 | flags u8 | tilt_y i16 | steer_y i16
+//! ```
+//!
+//! `state` is the `GameState` in declaration order. `button` is 0 for
+//! none, then 1 short, 2 long, 3 hold. `flags` bits, from bit 0: pressed,
+//! double tap, shake, free fall, flap. `tilt_y` and `steer_y` are `NONE`
+//! when there was nothing, `steer_y` being where the input sent the bird
+//! while running.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::button::ButtonEvent;
+use crate::clock;
+use crate::config::Coord;
+use crate::framebuffer::FrameBuffer;
+use crate::game::GameState;
+use crate::input_events::Frame;
+use crate::telemetry;
+
+// Seed games get while recording, unless `rec on` names one
+pub const DEFAULT_SEED: u32 = 1;
+// Stands for a missing row
+pub const NONE: i16 = i16::MIN;
+const PAYLOAD_LEN: usize = 20;
+
+#[derive(Copy, Clone)]
+struct Record {
+    frame: u32,
+    seed: u32,
+    // This frame's input, as it is noted
+    input: Frame,
+    ticks: u8,
+    steer: Option<(Coord, bool)>,
+}
+
+static mut RECORDING: Option<Record> = None;
+// The time the game being played was set up
+static mut GAME_SEED: u32 = 0;
+
+// Start recording, from frame 0; games started from now on use `seed`
+pub fn start(seed: u32) {
+    unsafe {
+        RECORDING = Some(Record {
+            frame: 0,
+            seed,
+            input: Frame::default(),
+            ticks: 0,
+            steer: None,
+        })
+    };
+}
+
+pub fn stop() {
+    unsafe { RECORDING = None };
+}
+
+pub fn is_recording() -> bool {
+    unsafe { RECORDING.is_some() }
+}
+
+// Frames sent since recording started
+pub fn frames() -> u32 {
+    unsafe { RECORDING.map_or(0, |record| record.frame) }
+}
+
+// A new game is being set up; take its seed from the time
+pub fn new_game() {
+    unsafe { GAME_SEED = clock::millis() };
+}
+
+// Seed for the game's random numbers: fixed while recording, else the time
+// the game was set up
+pub fn seed() -> u32 {
+    unsafe { RECORDING.map_or(GAME_SEED, |record| record.seed) }
+}
+
+// The input drained for this frame
+pub fn note_input(input: &Frame) {
+    if let Some(record) = unsafe { RECORDING.as_mut() } {
+        record.input = *input;
+    }
+}
+
+// Where the running game's input sent the bird, whether it flapped and how
+// many ticks the frame ran
+pub fn note_steer(y: Coord, flap: bool, ticks: u32) {
+    if let Some(record) = unsafe { RECORDING.as_mut() } {
+        record.steer = Some((y, flap));
+        record.ticks = ticks.min(u8::MAX as u32) as u8;
+    }
+}
+
+fn row(y: Option<Coord>) -> [u8; 2] {
+    y.map_or(NONE, |y| {
+        y.clamp(-i16::MAX as Coord, i16::MAX as Coord) as i16
+    })
+    .to_le_bytes()
+}
+
+// Send the frame just presented; call once a frame after everything that
+// draws to Layer 1
+pub fn present(state: GameState, score: u32, player_y: Coord) {
+    let Some(record) = (unsafe { RECORDING.as_mut() }) else {
+        return;
+    };
+    let crc = FrameBuffer::layer1().checksum();
+    let input = record.input;
+    let button = match input.button {
+        None => 0u8,
+        Some(ButtonEvent::Short) => 1,
+        Some(ButtonEvent::Long) => 2,
+        Some(ButtonEvent::Hold) => 3,
+    };
+    let flap = record.steer.is_some_and(|(_, flap)| flap);
+    let flags = [
+        input.pressed,
+        input.double_tap,
+        input.shake,
+        input.free_fall,
+        flap,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0u8, |flags, (bit, &on)| flags | (on as u8) << bit);
+
+    let mut payload = [0u8; PAYLOAD_LEN];
+    payload[0..4].copy_from_slice(&record.frame.to_le_bytes());
+    payload[4..8].copy_from_slice(&crc.to_le_bytes());
+    payload[8] = state as u8;
+    payload[9..11].copy_from_slice(&(score.min(u16::MAX as u32) as u16).to_le_bytes());
+    payload[11..13].copy_from_slice(&row(Some(player_y)));
+    payload[13] = record.ticks;
+    payload[14] = button;
+    payload[15] = flags;
+    payload[16..18].copy_from_slice(&row(input.tilt_y));
+    payload[18..20].copy_from_slice(&row(record.steer.map(|(y, _)| y)));
+    telemetry::send(telemetry::KIND_FRAME, &payload);
+
+    record.frame = record.frame.wrapping_add(1);
+    record.input = Frame::default();
+    record.ticks = 0;
+    record.steer = None;
+}
@@ -0,0 +1,1004 @@
+#![allow(dead_code)]
+
+use core::convert::Infallible;
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+
+use crate::color::{self, Argb8888};
+use crate::crc;
+use crate::dma2d::{self, Block};
+use crate::lcd::{self, HudBuffer, LcdDriver, PixelFormat, DISPLAY_MEMORY, HUD_H};
+use crate::raster;
+use crate::retro;
+use crate::sdram;
+use crate::sdram::arena::FramebufferRegion;
+use crate::transition;
+
+pub use core_logic::render::{Image, ImageTransform, Mirror, RenderBackend, Scale};
+
+use core_logic::config::ORIENTATION;
+use core_logic::fill;
+use core_logic::geometry::Orientation;
+use core_logic::palette::Palette;
+use core_logic::raster::{shift_into, Line};
+use core_logic::rect::Rect;
+
+// How game coordinates are turned onto the panel; see Display::set_orientation
+static mut CURRENT: Orientation = ORIENTATION;
+
+pub fn orientation() -> Orientation {
+    unsafe { CURRENT }
+}
+
+pub fn set_orientation(orientation: Orientation) {
+    unsafe { CURRENT = orientation };
+}
+
+// How game coordinates are turned onto buffer memory: the orientation, and
+// a quarter turn more on a panel mounted turned (see lcd::panel)
+pub fn scan_orientation() -> Orientation {
+    orientation().turned(lcd::panel().turn())
+}
+
+// Make every pixel stored so far visible to whatever reads SDRAM next:
+// LTDC scan-out, DMA2D, or a layer whose shadow registers are about to be
+// latched. The fence keeps the compiler from moving stores past the call,
+// the DSB waits for them to leave the write buffer. The Cortex-M4 has no
+// data cache, so there is nothing to clean. `present`, the screens that
+// draw straight into a buffer, and lcd.rs before every reload call it.
+pub fn flush() {
+    compiler_fence(Ordering::SeqCst);
+    cortex_m::asm::dsb();
+}
+
+// The HUD strip in panel pixels, as the game is turned now
+pub fn hud_panel_size() -> (u32, u32) {
+    let (surface_w, surface_h) = lcd::surface();
+    let (game_w, _) = scan_orientation().game_size(surface_w, surface_h);
+    scan_orientation().game_size(game_w, HUD_H)
+}
+
+/// Layer framebuffer in SDRAM seen through game coordinates
+///
+/// This is the only place that turns an SDRAM address into a slice. Every
+/// drawing path goes through the bounds-checked methods below, and game
+/// coordinates are mapped to panel memory in one place (`to_panel`, `clip`)
+/// through `geometry`, so callers never repeat the orientation math. Every
+/// buffer, the small sprite layer included, is turned by the current
+/// `scan_orientation()` within its own area, and the screen-shaped ones are
+/// laid out the way the panel scans them (`lcd::laid_out`).
+///
+/// Pixel values passed to `set_pixel`, `fill` and `fill_rect` are native to
+/// the buffer's format; get them from `encode_argb` or `encode_rgb565`. An
+/// indexed (L8) sprite layer takes palette indices instead, and images go in
+/// with `blit_indexed`.
+///
+/// A reduced-resolution buffer (retro mode) still takes full-size game
+/// coordinates; they are shifted right by `shift` on the way in.
+///
+/// Each view is made from one of lcd.rs's typed `FramebufferRegion`s, so its
+/// size and format come from the buffer's type rather than from constants
+/// passed alongside an address, and `scale` only compiles for two buffers
+/// in the same format.
+///
+/// Without SDRAM (SPI rendering on a board that lacks it) every buffer is
+/// empty, so drawing into one clips to nothing instead of faulting.
+///
+/// Single pixels go in and come out with volatile accesses (`put`, `get`),
+/// so none is elided or merged however the drawing code is inlined. Spans,
+/// row copies and fills go through the slice a word at a time for speed;
+/// like every other store they are ordered against the display hardware
+/// by `flush`.
+pub struct FrameBuffer {
+    base: u32,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    shift: u32,
+}
+
+impl FrameBuffer {
+    // Framebuffer currently scanned out by LTDC Layer 1
+    pub fn layer1() -> Self {
+        Self::of(&DISPLAY_MEMORY.layer1)
+    }
+
+    // Layer 1 buffer that is not being scanned out (double buffering)
+    pub fn layer1_back() -> Self {
+        let mem = &DISPLAY_MEMORY;
+        if LcdDriver::layer1_back_addr() == mem.layer1_b.base {
+            Self::of(&mem.layer1_b)
+        } else {
+            Self::of(&mem.layer1)
+        }
+    }
+
+    // The small sprite layer
+    pub fn layer2() -> Self {
+        Self::of(&DISPLAY_MEMORY.layer2)
+    }
+
+    // Full-screen buffer Layer 2 shows while a menu is open
+    pub fn overlay() -> Self {
+        Self::of(&DISPLAY_MEMORY.overlay)
+    }
+
+    // Strip Layer 2 shows over the score bar while the HUD is up: the game's
+    // full width, HUD_H rows, laid out as the game is turned now
+    pub fn hud() -> Self {
+        let (w, h) = hud_panel_size();
+        let hud = Self::of(&DISPLAY_MEMORY.hud);
+        // Turned either way the strip has no more pixels than the buffer
+        debug_assert!(w * h <= HudBuffer::PIXELS);
+        if hud.width == 0 {
+            return hud;
+        }
+        Self {
+            width: w,
+            height: h,
+            ..hud
+        }
+    }
+
+    // Half-resolution Layer 1 stand-in used while retro mode is on
+    pub fn retro() -> Self {
+        Self {
+            shift: 1,
+            ..Self::of(&DISPLAY_MEMORY.retro)
+        }
+    }
+
+    // Where game drawing goes: Layer 1, the retro buffer when retro mode
+    // is on (retro::present copies it to Layer 1) or the back buffer while
+    // a raster effect or screen transition is (raster::present or
+    // transition::present copies it)
+    pub fn render_target() -> Self {
+        if retro::enabled() {
+            Self::retro()
+        } else if raster::active() || transition::active() {
+            Self::of(&DISPLAY_MEMORY.layer1_b)
+        } else {
+            Self::layer1()
+        }
+    }
+
+    // The whole of a buffer carved out of SDRAM (lcd.rs and others), in the
+    // format its pixels are in now
+    pub fn of<const W: u32, const H: u32, P: lcd::Pixel>(
+        region: &FramebufferRegion<W, H, P>,
+    ) -> Self {
+        let present = sdram::available();
+        let (w, h) = lcd::laid_out(W, H);
+        Self {
+            base: region.base,
+            width: if present { w } else { 0 },
+            height: if present { h } else { 0 },
+            format: P::format(),
+            shift: 0,
+        }
+    }
+
+    // Nearest-neighbor copy of all of `src` onto all of `dst`, which must
+    // be in the same format; see `scale_into`
+    pub fn scale<const SW: u32, const SH: u32, const DW: u32, const DH: u32, P: lcd::Pixel>(
+        src: &FramebufferRegion<SW, SH, P>,
+        dst: &FramebufferRegion<DW, DH, P>,
+        scanlines: bool,
+    ) {
+        Self::of(src).scale_into(&mut Self::of(dst), scanlines);
+    }
+
+    // Copy `src` to `dst` a scanline at a time, each bent as `line_at`
+    // says; see raster.rs
+    pub fn copy_raster<const W: u32, const H: u32, P: lcd::Pixel>(
+        src: &FramebufferRegion<W, H, P>,
+        dst: &FramebufferRegion<W, H, P>,
+        line_at: impl Fn(u32) -> Line,
+    ) {
+        Self::of(src).raster_into(&mut Self::of(dst), line_at);
+    }
+
+    // Copy panel columns `src_x..src_x + w` of `src` to `dst_x..` of `dst`,
+    // every row, by DMA2D; by the CPU if DMA2D hangs
+    pub fn copy_columns<const W: u32, const H: u32, P: lcd::Pixel>(
+        src: &FramebufferRegion<W, H, P>,
+        dst: &FramebufferRegion<W, H, P>,
+        src_x: u32,
+        dst_x: u32,
+        w: u32,
+    ) {
+        let (mut from, mut to) = (Self::of(src), Self::of(dst));
+        let w = w.min(from.width.saturating_sub(src_x.max(dst_x)));
+        if w == 0 {
+            return;
+        }
+        let bpp = from.format.bytes_per_pixel();
+        let block = |fb: &FrameBuffer, x: u32| Block {
+            addr: fb.base + x * bpp,
+            stride: fb.width,
+        };
+        let copied = dma2d::copy(
+            block(&from, src_x),
+            block(&to, dst_x),
+            w,
+            from.height,
+            from.format,
+        );
+        if copied.is_err() {
+            let width = from.width as usize;
+            for row in 0..from.height as usize {
+                let (a, b) = (row * width + src_x as usize, row * width + dst_x as usize);
+                let w = w as usize;
+                match bpp {
+                    1 => {
+                        to.pixels::<u8>()[b..b + w].copy_from_slice(&from.pixels::<u8>()[a..a + w])
+                    }
+                    2 => to.pixels::<u16>()[b..b + w]
+                        .copy_from_slice(&from.pixels::<u16>()[a..a + w]),
+                    _ => to.pixels::<u32>()[b..b + w]
+                        .copy_from_slice(&from.pixels::<u32>()[a..a + w]),
+                }
+            }
+        }
+        flush();
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    // Every pixel through the CRC unit as the buffer stores them, a word at
+    // a time; 0xFFFF_FFFF for an empty buffer
+    pub fn checksum(&self) -> u32 {
+        let bytes = self.width * self.height * self.format.bytes_per_pixel();
+        // SAFETY: as in `pixels`, read-only; every buffer lcd.rs lays out is
+        // word aligned and a whole number of words long
+        let words = unsafe { slice::from_raw_parts(self.base as *const u32, (bytes / 4) as usize) };
+        crc::hw_crc32(words)
+    }
+
+    // Only ARGB8888, RGB565 and (for the sprite layer) L8 buffers are ever
+    // allocated in lcd.rs
+    fn is_16bpp(&self) -> bool {
+        self.format == PixelFormat::Rgb565
+    }
+
+    fn is_8bpp(&self) -> bool {
+        self.format == PixelFormat::L8
+    }
+
+    // Convert an ARGB8888 color to this buffer's native pixel value
+    pub fn encode_argb(&self, color: Argb8888) -> u32 {
+        if self.is_16bpp() {
+            color.to_rgb565().0 as u32
+        } else {
+            color.0
+        }
+    }
+
+    // Convert an RGB565 color to this buffer's native pixel value
+    pub fn encode_rgb565(&self, rgb565: u16) -> u32 {
+        if self.is_16bpp() {
+            rgb565 as u32
+        } else {
+            color::Rgb565(rgb565).to_argb8888().0
+        }
+    }
+
+    fn pixels<T>(&mut self) -> &mut [T] {
+        debug_assert_eq!(
+            core::mem::size_of::<T>() as u32,
+            self.format.bytes_per_pixel()
+        );
+        // SAFETY: every constructor points at a region reserved for exactly
+        // width * height pixels of `format` by the SDRAM layout in lcd.rs,
+        // callers pick T to match `format`, and the slice is empty unless
+        // SDRAM has been initialized
+        unsafe {
+            slice::from_raw_parts_mut(self.base as *mut T, (self.width * self.height) as usize)
+        }
+    }
+
+    // One pixel, bounds-checked against the buffer
+    fn put<T>(&mut self, idx: usize, value: T) {
+        if let Some(pixel) = self.pixels::<T>().get_mut(idx) {
+            // SAFETY: a valid, aligned reference into the buffer
+            unsafe { ptr::write_volatile(pixel, value) };
+        }
+    }
+
+    fn get<T: Copy>(&mut self, idx: usize) -> Option<T> {
+        let pixel = self.pixels::<T>().get(idx)?;
+        // SAFETY: as in put
+        Some(unsafe { ptr::read_volatile(pixel) })
+    }
+
+    fn store(&mut self, idx: usize, native: u32) {
+        if self.is_8bpp() {
+            self.put(idx, native as u8);
+        } else if self.is_16bpp() {
+            self.put(idx, native as u16);
+        } else {
+            self.put(idx, native);
+        }
+    }
+
+    // Fill `len` pixels from `start`, a word at a time where it can
+    fn store_span(&mut self, start: usize, len: usize, native: u32) {
+        if self.is_8bpp() {
+            fill::fill_u8(&mut self.pixels::<u8>()[start..start + len], native as u8);
+        } else if self.is_16bpp() {
+            fill::fill_u16(&mut self.pixels::<u16>()[start..start + len], native as u16);
+        } else {
+            self.pixels::<u32>()[start..start + len].fill(native);
+        }
+    }
+
+    // Copy the panel row starting at `first` down over the `rows - 1` under it
+    fn repeat_rows(&mut self, first: usize, rows: usize) {
+        let width = self.width as usize;
+        if self.is_8bpp() {
+            fill::repeat_rows(self.pixels::<u8>(), width, first, rows);
+        } else if self.is_16bpp() {
+            fill::repeat_rows(self.pixels::<u16>(), width, first, rows);
+        } else {
+            fill::repeat_rows(self.pixels::<u32>(), width, first, rows);
+        }
+    }
+
+    // The buffer's size in (reduced) game coordinates
+    // The buffer's size in game coordinates, turned as the game is now
+    pub fn game_size(&self) -> (u32, u32) {
+        scan_orientation().game_size(self.width, self.height)
+    }
+
+    // Map a game coordinate to a panel memory index, or None when off-screen
+    fn to_panel(&self, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = (x >> self.shift, y >> self.shift);
+        let (gw, gh) = self.game_size();
+        if x < 0 || y < 0 || x as u32 >= gw || y as u32 >= gh {
+            return None;
+        }
+        let (px, py) = scan_orientation().map((x, y), (self.width, self.height));
+        Some((py as u32 * self.width + px as u32) as usize)
+    }
+
+    // Clip a game rectangle to the buffer, returning the panel rectangle
+    // (x, y, w, h) it covers or None if empty
+    fn clip(&self, x: i32, y: i32, w: u32, h: u32) -> Option<(u32, u32, u32, u32)> {
+        let (gw, gh) = self.game_size();
+        let x0 = (x.max(0) >> self.shift) as i64;
+        let y0 = (y.max(0) >> self.shift) as i64;
+        let x1 = ((x as i64 + w as i64) >> self.shift).min(gw as i64);
+        let y1 = ((y as i64 + h as i64) >> self.shift).min(gh as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        let game = (x0 as i32, y0 as i32, (x1 - x0) as u32, (y1 - y0) as u32);
+        let (px, py, pw, ph) = scan_orientation().map_rect(game, (self.width, self.height))?;
+        Some((px as u32, py as u32, pw, ph))
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, native: u32) {
+        if let Some(idx) = self.to_panel(x, y) {
+            self.store(idx, native);
+        }
+    }
+
+    // Read back one pixel as RGB565, or None when off-screen or indexed
+    pub fn get_pixel_rgb565(&mut self, x: i32, y: i32) -> Option<u16> {
+        let idx = self.to_panel(x, y)?;
+        if self.is_8bpp() {
+            None
+        } else if self.is_16bpp() {
+            self.get::<u16>(idx)
+        } else {
+            self.get::<u32>(idx)
+                .map(|argb| Argb8888(argb).to_rgb565().0)
+        }
+    }
+
+    // How many pixels differ from `other`'s, a buffer of the same size and
+    // format, and the (column, row) in memory of the first that does
+    pub fn diff(&mut self, other: &mut FrameBuffer) -> (u32, Option<(u32, u32)>) {
+        debug_assert!(self.format == other.format && self.width == other.width);
+        let len = (self.width * self.height).min(other.width * other.height) as usize;
+        let mut count = 0;
+        let mut first = None;
+        for idx in 0..len {
+            let differs = match self.format.bytes_per_pixel() {
+                1 => self.get::<u8>(idx) != other.get::<u8>(idx),
+                2 => self.get::<u16>(idx) != other.get::<u16>(idx),
+                _ => self.get::<u32>(idx) != other.get::<u32>(idx),
+            };
+            if differs {
+                count += 1;
+                let at = (idx as u32 % self.width, idx as u32 / self.width);
+                first.get_or_insert(at);
+            }
+        }
+        (count, first)
+    }
+
+    pub fn fill(&mut self, native: u32) {
+        let len = (self.width * self.height) as usize;
+        self.store_span(0, len, native);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, native: u32) {
+        let Some((x, y, w, h)) = self.clip(x, y, w, h) else {
+            return;
+        };
+        // Whole panel rows are one span
+        if w == self.width {
+            self.store_span((y * w) as usize, (w * h) as usize, native);
+            return;
+        }
+        for row in y..y + h {
+            let start = (row * self.width + x) as usize;
+            self.store_span(start, w as usize, native);
+        }
+    }
+
+    // Tint a game rectangle `alpha` of the way (0..=255) to an RGB565
+    // color over what is there; an indexed buffer just takes the color
+    pub fn blend_rect(&mut self, x: i32, y: i32, w: u32, h: u32, rgb565: u16, alpha: u8) {
+        let color = color::Rgb565(rgb565);
+        for row in y..y + h as i32 {
+            for col in x..x + w as i32 {
+                let mixed = match self.get_pixel_rgb565(col, row) {
+                    Some(under) => color::Rgb565(under).lerp(color, alpha),
+                    None => color,
+                };
+                let native = self.encode_rgb565(mixed.0);
+                self.set_pixel(col, row, native);
+            }
+        }
+    }
+
+    // Paint every pixel with an ARGB8888 color computed from its position
+    pub fn fill_with(&mut self, argb_at: impl FnMut(u32, u32) -> u32) {
+        self.fill_striped(1, argb_at);
+    }
+
+    // As `fill_with`, for a pattern that only changes every `band` panel
+    // rows: the first row of each band is worked out and copied down the rest
+    pub fn fill_striped(&mut self, band: u32, mut argb_at: impl FnMut(u32, u32) -> u32) {
+        let band = band.max(1);
+        for row in (0..self.height).step_by(band as usize) {
+            let first = (row * self.width) as usize;
+            for col in 0..self.width {
+                let native = self.encode_argb(Argb8888(argb_at(col, row)));
+                self.store(first + col as usize, native);
+            }
+            self.repeat_rows(first, band.min(self.height - row) as usize);
+        }
+    }
+
+    // Copy an RGB565 image to (x, y), clipping at the buffer edges
+    pub fn blit(&mut self, x: i32, y: i32, image: &Image, transform: ImageTransform) {
+        self.blit_with(x, y, image, transform, None, false);
+    }
+
+    // Like blit, but pixels matching `key` are skipped so whatever is already
+    // in the buffer (transparent, for a cleared sprite layer) shows through
+    pub fn blit_keyed(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        key: u16,
+    ) {
+        self.blit_with(x, y, image, transform, Some(key), false);
+    }
+
+    // Like blit_keyed, with every pixel blended halfway toward `key` so the
+    // image looks translucent over a background of that color
+    pub fn blit_faded(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        key: u16,
+    ) {
+        self.blit_with(x, y, image, transform, Some(key), true);
+    }
+
+    fn blit_with(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        fade: bool,
+    ) {
+        if key.is_none() && self.blit_upscaled(x, y, image, transform) {
+            return;
+        }
+        let (w, h) = transform.size(image);
+        // A reduced buffer only needs every (1 << shift)-th source pixel
+        let step = 1 << self.shift;
+        for row in (0..h).step_by(step) {
+            for col in (0..w).step_by(step) {
+                let Some(rgb565) = image.pixel(col, row, transform) else {
+                    continue;
+                };
+                if key == Some(rgb565) {
+                    continue;
+                }
+                let rgb565 = match key {
+                    // Halfway towards the key color
+                    Some(key) if fade => color::Rgb565(rgb565).average(color::Rgb565(key)).0,
+                    _ => rgb565,
+                };
+                let native = self.encode_rgb565(rgb565);
+                self.set_pixel(x + col as i32, y + row as i32, native);
+            }
+        }
+    }
+
+    // An unkeyed image at a whole factor above one, all of it on a full
+    // size portrait buffer: the CPU draws the first row of each group of
+    // repeated rows, then one DMA2D copy per further row of the group fills
+    // the rest, the source and destination strides skipping the group. False,
+    // with nothing drawn, when it does not apply; if DMA2D hangs part way the
+    // CPU draws what is left.
+    fn blit_upscaled(&mut self, x: i32, y: i32, image: &Image, transform: ImageTransform) -> bool {
+        let factor = match transform.scale.whole() {
+            Some(factor) if factor > 1 => factor,
+            _ => return false,
+        };
+        let (w, h) = transform.size(image);
+        let fits = x >= 0 && y >= 0 && x as u32 + w <= self.width && y as u32 + h <= self.height;
+        if !fits || self.shift != 0 || self.is_8bpp() || scan_orientation() != Orientation::Portrait
+        {
+            return false;
+        }
+        let (x, y) = (x as u32, y as u32);
+        let draw_row = |fb: &mut FrameBuffer, row: u32| {
+            for col in 0..w {
+                if let Some(rgb565) = image.pixel(col, row, transform) {
+                    let native = fb.encode_rgb565(rgb565);
+                    fb.store(((y + row) * fb.width + x + col) as usize, native);
+                }
+            }
+        };
+        for group in 0..image.h {
+            draw_row(self, group * factor);
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let block = |row: u32| Block {
+            addr: self.base + ((y + row) * self.width + x) * bpp,
+            stride: self.width * factor,
+        };
+        for k in 1..factor {
+            if dma2d::copy(block(0), block(k), w, image.h, self.format).is_err() {
+                for group in 0..image.h {
+                    for k in 1..factor {
+                        draw_row(self, group * factor + k);
+                    }
+                }
+                break;
+            }
+        }
+        flush();
+        true
+    }
+
+    // The part `region` of an image drawn with its top left at (x, y) (see
+    // RenderBackend::blit_region). The panel index of the region's first
+    // pixel and the steps to the next column and row are worked out once
+    // for the orientation, and the format once, rather than per pixel.
+    pub fn blit_region(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        region: Rect,
+    ) {
+        // The caller clipped to its own bounds; these are the buffer's
+        let (gw, gh) = RenderBackend::size(self);
+        let Some(region) = region
+            .offset(x, y)
+            .clamp_to(gw, gh)
+            .map(|r| r.offset(-x, -y))
+        else {
+            return;
+        };
+        // A reduced buffer skips source pixels; take it a pixel at a time
+        if self.shift != 0 || self.is_8bpp() {
+            for row in region.y..region.y + region.h as i32 {
+                for col in region.x..region.x + region.w as i32 {
+                    match image.pixel(col as u32, row as u32, transform) {
+                        Some(rgb565) if key != Some(rgb565) => {
+                            RenderBackend::set_pixel(self, x + col, y + row, rgb565)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            return;
+        }
+        let (w, h) = (self.width, self.height);
+        let at = |gx: i32, gy: i32| {
+            let (px, py) = scan_orientation().map((gx, gy), (w, h));
+            py as isize * w as isize + px as isize
+        };
+        let (x0, y0) = (x + region.x, y + region.y);
+        let first = at(x0, y0);
+        let steps = (at(x0 + 1, y0) - first, at(x0, y0 + 1) - first);
+        let copy = Region {
+            image,
+            transform,
+            key,
+            region,
+            first,
+            steps,
+        };
+        if self.is_16bpp() {
+            copy.copy_to(self.pixels::<u16>(), |rgb565| rgb565);
+        } else {
+            copy.copy_to(self.pixels::<u32>(), |rgb565| {
+                color::Rgb565(rgb565).to_argb8888().0
+            });
+        }
+    }
+
+    // ARGB8888 pixels, top row first, `w` to a row; those with alpha 0 are
+    // left out (see sprite_cache)
+    pub fn blit_argb(&mut self, x: i32, y: i32, w: u32, pixels: &[u32]) {
+        if w == 0 {
+            return;
+        }
+        let step = 1 << self.shift;
+        let h = pixels.len() as u32 / w;
+        for row in (0..h).step_by(step) {
+            for col in (0..w).step_by(step) {
+                let argb = Argb8888(pixels[(row * w + col) as usize]);
+                if argb.alpha() == 0 {
+                    continue;
+                }
+                let native = self.encode_argb(argb);
+                self.set_pixel(x + col as i32, y + row as i32, native);
+            }
+        }
+    }
+
+    // Write `image` into an indexed buffer as `palette` indices; colors not
+    // in the palette become index 0
+    pub fn blit_indexed(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        palette: &Palette,
+    ) {
+        debug_assert!(self.is_8bpp());
+        let mut indices = palette.indices(image, transform);
+        let (w, h) = transform.size(image);
+        for row in 0..h {
+            for col in 0..w {
+                let index = indices.next().unwrap_or(0);
+                self.set_pixel(x + col as i32, y + row as i32, index as u32);
+            }
+        }
+    }
+
+    // Fill a rectangle by repeating an image (stored bottom row first) from
+    // `skip` (columns, rows) into it at the top-left corner, clipping at the
+    // buffer edges
+    pub fn fill_tiled_from(
+        &mut self,
+        x: i32,
+        y: i32,
+        w: u32,
+        h: u32,
+        tile: &Image,
+        skip: (u32, u32),
+    ) {
+        if tile.w == 0 || tile.h == 0 {
+            return;
+        }
+        let step = 1 << self.shift;
+        for row in (0..h).step_by(step) {
+            let tile_row = tile.h - 1 - (row + skip.1) % tile.h;
+            for col in (0..w).step_by(step) {
+                let Some(rgb565) = tile.at((col + skip.0) % tile.w, tile_row) else {
+                    continue;
+                };
+                let native = self.encode_rgb565(rgb565);
+                self.set_pixel(x + col as i32, y + row as i32, native);
+            }
+        }
+    }
+
+    // Nearest-neighbor copy of the whole buffer into `dst` (same pixel
+    // format) at whatever size `dst` is. With `scanlines`, every odd
+    // destination row is drawn at half brightness.
+    fn scale_into(&mut self, dst: &mut FrameBuffer, scanlines: bool) {
+        debug_assert!(self.format == dst.format);
+        for row in 0..dst.height {
+            let src_row = row * self.height / dst.height;
+            let dim = scanlines && row & 1 == 1;
+            for col in 0..dst.width {
+                let src_col = col * self.width / dst.width;
+                let src = (src_row * self.width + src_col) as usize;
+                let dst_idx = (row * dst.width + col) as usize;
+                if self.is_16bpp() {
+                    let p = self.get::<u16>(src).unwrap_or(0);
+                    dst.put(dst_idx, if dim { color::Rgb565(p).halve().0 } else { p });
+                } else {
+                    let p = self.get::<u32>(src).unwrap_or(0);
+                    dst.put(dst_idx, if dim { Argb8888(p).halve().0 } else { p });
+                }
+            }
+        }
+        flush();
+    }
+
+    // Plain runs of rows go over by DMA2D while the CPU copies the bent
+    // ones; if DMA2D hangs the CPU copies the run instead
+    fn raster_into(&mut self, dst: &mut FrameBuffer, line_at: impl Fn(u32) -> Line) {
+        debug_assert!(self.format == dst.format && self.width == dst.width);
+        let width = self.width;
+        let row_bytes = width * self.format.bytes_per_pixel();
+        let mut plain_from = 0;
+        for row in 0..=self.height {
+            let line = if row < self.height {
+                line_at(row)
+            } else {
+                Line::PLAIN
+            };
+            if row < self.height && line.is_plain() {
+                continue;
+            }
+            if row > plain_from {
+                let src = Block {
+                    addr: self.base + plain_from * row_bytes,
+                    stride: width,
+                };
+                let dst_block = Block {
+                    addr: dst.base + plain_from * row_bytes,
+                    stride: width,
+                };
+                let rows = row - plain_from;
+                if dma2d::start_copy(src, dst_block, width, rows, self.format).is_err() {
+                    for plain in plain_from..row {
+                        self.raster_line(dst, plain, &Line::PLAIN);
+                    }
+                }
+            }
+            if row < self.height {
+                self.raster_line(dst, row, &line);
+            }
+            plain_from = row + 1;
+        }
+        let _ = dma2d::wait();
+        flush();
+    }
+
+    fn raster_line(&mut self, dst: &mut FrameBuffer, row: u32, line: &Line) {
+        let start = (row * self.width) as usize;
+        let end = start + self.width as usize;
+        let span = line.start as usize..(line.start + line.len).min(self.width) as usize;
+        if self.is_16bpp() {
+            let out = &mut dst.pixels::<u16>()[start..end];
+            shift_into(&self.pixels::<u16>()[start..end], out, line);
+            if let Some((tint, alpha)) = line.tint {
+                for p in &mut out[span] {
+                    *p = color::Rgb565(*p).lerp(tint, alpha).0;
+                }
+            }
+        } else {
+            let out = &mut dst.pixels::<u32>()[start..end];
+            shift_into(&self.pixels::<u32>()[start..end], out, line);
+            if let Some((tint, alpha)) = line.tint {
+                for p in &mut out[span] {
+                    let under = Argb8888(*p);
+                    *p = under
+                        .lerp(tint.to_argb8888(), alpha)
+                        .with_alpha(under.alpha())
+                        .0;
+                }
+            }
+        }
+    }
+}
+
+// Part of an image on its way into panel memory: where its first pixel
+// goes and how far on the next column and the next row are
+struct Region<'a, 'b> {
+    image: &'a Image<'b>,
+    transform: ImageTransform,
+    key: Option<u16>,
+    region: Rect,
+    first: isize,
+    steps: (isize, isize),
+}
+
+impl Region<'_, '_> {
+    fn copy_to<T>(&self, pixels: &mut [T], encode: impl Fn(u16) -> T) {
+        let Region { image, region, .. } = *self;
+        for row in 0..region.h {
+            let src_row = region.y as u32 + row;
+            let mut idx = self.first + row as isize * self.steps.1;
+            for col in 0..region.w {
+                let src_col = region.x as u32 + col;
+                let rgb565 = image.pixel(src_col, src_row, self.transform);
+                match (rgb565, pixels.get_mut(idx as usize)) {
+                    (Some(rgb565), Some(pixel)) if self.key != Some(rgb565) => {
+                        // SAFETY: as in FrameBuffer::put
+                        unsafe { ptr::write_volatile(pixel, encode(rgb565)) }
+                    }
+                    _ => {}
+                }
+                idx += self.steps.0;
+            }
+        }
+    }
+}
+
+// The game's render backend on the board; pixels are RGB565 here and
+// converted to the buffer's format
+impl RenderBackend for FrameBuffer {
+    fn size(&self) -> (u32, u32) {
+        let (w, h) = self.game_size();
+        (w << self.shift, h << self.shift)
+    }
+
+    fn set_pixel(&mut self, x: i32, y: i32, rgb565: u16) {
+        let native = self.encode_rgb565(rgb565);
+        FrameBuffer::set_pixel(self, x, y, native);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: u32, h: u32, rgb565: u16) {
+        let native = self.encode_rgb565(rgb565);
+        FrameBuffer::fill_rect(self, x, y, w, h, native);
+    }
+
+    fn blit(&mut self, x: i32, y: i32, image: &Image, transform: ImageTransform, key: Option<u16>) {
+        self.blit_with(x, y, image, transform, key, false);
+    }
+
+    fn blit_region(
+        &mut self,
+        x: i32,
+        y: i32,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        region: Rect,
+    ) {
+        FrameBuffer::blit_region(self, x, y, image, transform, key, region);
+    }
+
+    fn fill_tiled_from(&mut self, x: i32, y: i32, w: u32, h: u32, tile: &Image, skip: (u32, u32)) {
+        FrameBuffer::fill_tiled_from(self, x, y, w, h, tile, skip);
+    }
+
+    // Scan-out reads SDRAM directly; just make sure the writes have landed
+    fn present(&mut self) {
+        flush();
+    }
+}
+
+// Drawing happens in game coordinates, so a reduced buffer reports the full size
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        let (w, h) = self.game_size();
+        Size::new(w << self.shift, h << self.shift)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let native = self.encode_rgb565(RawU16::from(color).into_inner());
+            self.set_pixel(point.x, point.y, native);
+        }
+
+        flush();
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let native = self.encode_rgb565(RawU16::from(color).into_inner());
+        self.fill_rect(
+            area.top_left.x,
+            area.top_left.y,
+            area.size.width,
+            area.size.height,
+            native,
+        );
+
+        flush();
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let native = self.encode_rgb565(RawU16::from(color).into_inner());
+        self.fill(native);
+
+        flush();
+        Ok(())
+    }
+}
+
+/// Draws through to a framebuffer blown up to `percent` of the size, with
+/// the origin moved to `center`; for large text from the small fonts
+pub struct Scaled<'a> {
+    fb: &'a mut FrameBuffer,
+    percent: u32,
+    center: Point,
+}
+
+impl<'a> Scaled<'a> {
+    pub fn new(fb: &'a mut FrameBuffer, percent: u32, center: Point) -> Self {
+        Scaled {
+            fb,
+            percent,
+            center,
+        }
+    }
+
+    // Where a drawn coordinate lands, rounding down so blocks meet
+    fn scale(&self, v: i32) -> i32 {
+        (v * self.percent as i32).div_euclid(100)
+    }
+}
+
+impl OriginDimensions for Scaled<'_> {
+    fn size(&self) -> Size {
+        OriginDimensions::size(self.fb)
+    }
+}
+
+impl DrawTarget for Scaled<'_> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let native = self.fb.encode_rgb565(RawU16::from(color).into_inner());
+            let (x0, y0) = (self.scale(point.x), self.scale(point.y));
+            let (x1, y1) = (self.scale(point.x + 1), self.scale(point.y + 1));
+            self.fb.fill_rect(
+                self.center.x + x0,
+                self.center.y + y0,
+                (x1 - x0).max(1) as u32,
+                (y1 - y0).max(1) as u32,
+                native,
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,118 +1,1386 @@
 #![allow(dead_code)]
 
-use core::ffi;
+use core::cell::RefCell;
+use core::fmt::Write;
 
+use core_logic::controls::{ControlScheme, Controls};
+use core_logic::latency::Kind;
+use core_logic::leaderboard::Initials;
+use core_logic::mode::{GameMode, ModeId};
+use core_logic::powerup::{PowerUp, Powers};
+use core_logic::practice::Practice;
+use core_logic::replay::{self as replay_format, Header};
+use core_logic::rules::{self, Edge};
+use core_logic::sky::{POINTS_PER_CYCLE, POINTS_PER_PHASE};
+use core_logic::time_attack::Clock;
+use core_logic::timestep::{FixedStep, FULL_SPEED};
+use core_logic::tuning::TuningParams;
+
+#[cfg(all(feature = "agent-api", debug_assertions))]
+use crate::agent;
+use crate::artwork;
 use crate::assets;
-use crate::color;
-use crate::config::PLAYER_Y_MAX;
-use crate::config::PLAYER_Y_MIN;
+use crate::audio;
+use crate::backdrop::Backdrop;
+use crate::backlight;
+use crate::button::ButtonEvent;
+use crate::calibration::Wizard;
+use crate::clock;
+use crate::color::{self, Argb8888};
 use crate::config::{self, Coord};
+use crate::courses;
+use crate::debug_menu::DebugMenu;
 use crate::display;
-use crate::display::DISPLAY_HEIGHT;
-use crate::display::DISPLAY_WIDTH;
-use crate::obstacle;
+use crate::editor::{Editor, Exit as EditorExit};
+use crate::effects;
+use crate::encoder::{self, MenuNav};
+use crate::entity::{self, Entity, Renderer};
+use crate::fmt_buf::FmtBuf;
+use crate::frame_record;
+use crate::framebuffer::{ImageTransform, Scale};
+use crate::game_events::{self, GameEvent};
+use crate::ghost;
+use crate::ground;
+use crate::hud;
+use crate::input_device::{DemoInputDevice, InputMux};
+use crate::input_events::{self, Inputs};
+use crate::lane::{self, LaneDraw};
+use crate::lang::{self, Msg};
+use crate::latency;
+use crate::leaderboard_page;
+use crate::log;
+use crate::particles::{self, Effect, Trail};
+use crate::pickup::Pickups;
 use crate::player;
+use crate::power;
+use crate::profile_picker::ProfilePicker;
+use crate::profiler;
+use crate::profiles;
+use crate::raster;
+use crate::replay;
+use crate::retro::{self, RetroMode};
+use crate::score_link::{self, Mode};
+use crate::screensaver;
+use crate::screenshot;
+use crate::settings;
+use crate::settings_page::{Exit, SettingsPage};
+use crate::sky;
+use crate::sprite_cache;
+use crate::sprites::{self, SpriteId};
+use crate::stats::{self, Board};
+use crate::stats_page;
+use crate::system;
+use crate::test_pattern;
+use crate::text_field::TextField;
+use crate::theme;
+use crate::transition::{self, Transition};
+use crate::ui::{self, Focus, Nav, Ui};
+use crate::versus::Versus;
+use crate::world::World;
 
-// Simple tick counter for timing
-static mut TICK_COUNTER: u32 = 0;
+// The game `main` holds for good, for the memory budget report. It is in
+// main's frame, which never returns, rather than in .bss.
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<RefCell<Game<InputMux>>>();
 
-// Get current system tick (placeholder for HAL_GetTick)
+// Current system tick in milliseconds (HAL_GetTick equivalent)
 fn get_tick() -> u32 {
-    unsafe {
-        TICK_COUNTER += 1;
-        TICK_COUNTER
+    clock::millis()
+}
+
+pub use core_logic::state::GameState;
+
+// Where flaps come from while running
+#[derive(Copy, Clone, PartialEq)]
+pub enum InputMode {
+    Tilt,
+    Touch,
+    Joystick,
+    Button,
+    // The external trigger pin; flaps like the button
+    Trigger,
+}
+
+impl InputMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InputMode::Tilt => "tilt",
+            InputMode::Touch => "touch",
+            InputMode::Joystick => "joystick",
+            InputMode::Button => "button",
+            InputMode::Trigger => "trigger",
+        }
+    }
+
+    // Its name in the pause menu, in the player's language
+    pub fn msg(self) -> Msg {
+        match self {
+            InputMode::Tilt => Msg::Tilt,
+            InputMode::Touch => Msg::Touch,
+            InputMode::Joystick => Msg::Joystick,
+            InputMode::Button => Msg::Button,
+            InputMode::Trigger => Msg::Trigger,
+        }
+    }
+
+    // Whether the device says where the bird goes, not only when it flaps
+    pub fn steers(self) -> bool {
+        !matches!(self, InputMode::Button | InputMode::Trigger)
     }
 }
 
-#[derive(PartialEq)]
-pub enum GameState {
-    Initializing,
-    Start,
-    Running,
-    End,
-    Halt,
+// Title screen idle time before the demo game starts
+const ATTRACT_IDLE_MS: u32 = 10_000;
+// Title screen idle time before the backlight dims, and how far
+const START_DIM_MS: u32 = 5_000;
+const START_DIM_PERCENT: u8 = 30;
+const DIM_FADE_MS: u32 = 1_000;
+const UNDIM_FADE_MS: u32 = 200;
+// Time without real input, demo included, before the title screen sleeps
+const SLEEP_IDLE_MS: u32 = 60_000;
+// Top of the double-size bird on the title screen, under the countdown text
+const START_BIRD_Y: Coord = 150;
+
+// Game logic rate. The speeds in `config` were tuned per 60 Hz frame, so
+// ticking at the display's old frame rate keeps the game playing as it did.
+const TICK_HZ: u32 = 60;
+// Most ticks run in one frame; a longer stall is dropped, not caught up
+const MAX_TICKS: u32 = 4;
+// Ticks the game stands still for when the bird clears an obstacle
+const HIT_STOP_TICKS: u32 = 3;
+// Frames a star keeps the world under water
+const UNDERWATER_FRAMES: u32 = 180;
+
+// Pause menu entries, in display order
+const MENU_RESUME: usize = 0;
+const MENU_RESTART: usize = 1;
+const MENU_MODE: usize = 2;
+const MENU_INPUT: usize = 3;
+const MENU_CONTROLS: usize = 4;
+const MENU_BRIGHTNESS: usize = 5;
+const MENU_RETRO: usize = 6;
+const MENU_THEME: usize = 7;
+const MENU_GAMMA: usize = 8;
+const MENU_INVERT: usize = 9;
+const MENU_SETTINGS: usize = 10;
+const MENU_ITEMS: usize = 11;
+
+// A kind of game the pause menu's Mode item can pick
+struct Setup {
+    name: Msg,
+    mode: ModeId,
+    // A crash goes back to the last obstacle passed instead of ending the run
+    practice: bool,
+    // Split screen, always classic
+    two_player: bool,
+    // The custom course last played or picked in the editor
+    course: bool,
 }
 
+// What the Mode item steps through, in order
+const SETUPS: [Setup; 7] = [
+    Setup {
+        name: Msg::Classic,
+        mode: ModeId::Classic,
+        practice: false,
+        two_player: false,
+        course: false,
+    },
+    Setup {
+        name: Msg::Runner,
+        mode: ModeId::Runner,
+        practice: false,
+        two_player: false,
+        course: false,
+    },
+    Setup {
+        name: Msg::Signature,
+        mode: ModeId::Signature,
+        practice: false,
+        two_player: false,
+        course: false,
+    },
+    Setup {
+        name: Msg::TimeAttack,
+        mode: ModeId::TimeAttack,
+        practice: false,
+        two_player: false,
+        course: false,
+    },
+    Setup {
+        name: Msg::Practice,
+        mode: ModeId::Classic,
+        practice: true,
+        two_player: false,
+        course: false,
+    },
+    Setup {
+        name: Msg::TwoPlayers,
+        mode: ModeId::Classic,
+        practice: false,
+        two_player: true,
+        course: false,
+    },
+    Setup {
+        name: Msg::CourseMode,
+        mode: ModeId::Classic,
+        practice: false,
+        two_player: false,
+        course: true,
+    },
+];
+
+// Where a practice or time attack run goes back to on a crash: the moment
+// the bird last passed an obstacle, or the start
+struct Checkpoint {
+    world: World,
+    score: u32,
+    bird_y: Coord,
+}
+
+// Backlight percentage per brightness step
+const BRIGHTNESS_LEVELS: [u8; 4] = [100, 75, 50, 25];
+
+// The step at or just above a stored percentage
+fn brightness_step(percent: u8) -> usize {
+    BRIGHTNESS_LEVELS
+        .iter()
+        .rposition(|&level| level >= percent)
+        .unwrap_or(0)
+}
+// Whether the encoder can turn pause menu `item`'s value in place
+fn adjustable(item: usize) -> bool {
+    item == MENU_BRIGHTNESS
+}
+// Constant alpha of the pause overlay, leaving the game visible beneath
+const OVERLAY_ALPHA: u8 = 0xC0;
+// LTDC background the screen washes out to when the bird dies
+const FLASH_COLOR: Argb8888 = Argb8888::WHITE;
+
 pub trait InputDevice {
     type Error;
     fn init(&mut self) -> Result<(), Self::Error>;
     fn log_data(&mut self) {}
     fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error>;
+    // In button mode the game flaps on short presses and ignores `is_tap`
+    fn mode(&self) -> InputMode {
+        InputMode::Tilt
+    }
+    // Switch to the next usable device, if the implementation has several
+    fn cycle(&mut self) {}
+}
+
+/// Obstacle opening, in game coordinates
+#[derive(Copy, Clone)]
+pub struct Gap {
+    pub x: Coord,
+    pub top: Coord,
+    pub bottom: Coord,
+}
+
+/// Everything an outside observer needs to play, sampled once per frame
+pub struct GameSnapshot {
+    pub player_y: Coord,
+    pub player_vy: Coord,
+    // Upcoming gaps, nearest first
+    pub gaps: [Option<Gap>; 2],
+    pub score: u32,
 }
 
 pub struct Game<T: InputDevice> {
     state: GameState,
     score: u32,
     countdown_start_time: u32,
-    obstacle: obstacle::Obstacle,
+    // Digit on the countdown overlay, 0 before the first one is drawn
+    countdown_digit: u32,
+    death_start_time: u32,
+    // Game time played at the crash; the death animation runs on game time
+    death_played: u32,
+    // When the current game started running, for the play time total
+    run_start: u32,
+    // Where the bird was when it hit something
+    death_y: Coord,
+    // The obstacle, sky and ground
+    world: World,
+    // Towers far behind the obstacles
+    backdrop: Backdrop,
+    // Coins and stars scrolling with the obstacles
+    pickups: Pickups,
+    // Feathers, sparkles and the crash burst
+    trail: Trail,
     player: player::Player,
+    // Paces the logic while running, apart from how often frames come
+    timestep: FixedStep,
+    // Turns the input into where the bird goes
+    controls: Controls,
+    // Gameplay numbers from the settings, changed from the shell
+    tuning: TuningParams,
+    menu: Focus,
+    // The encoder is turning the focused menu item's value, not the focus
+    adjusting: bool,
+    brightness: usize,
+    // Set while the attract-mode demo is playing
+    demo: Option<DemoInputDevice>,
+    // Index into SETUPS of the next game; `versus` is set while a split
+    // screen one is being played, `practice` while a practice run is,
+    // `clock` while a time attack one is, and `checkpoint` for either
+    setup: usize,
+    versus: Option<Versus>,
+    practice: Option<Practice>,
+    clock: Option<Clock>,
+    checkpoint: Option<Checkpoint>,
+    powers: Powers,
+    // The score, and the time attack clock, drawn into the score bar while
+    // the HUD is not up
+    score_text: TextField<3>,
+    timer_text: TextField<4>,
+    // The leaderboard on its page, while that is up
+    board: Board,
+    // Set while the settings page is up over the pause menu
+    settings_page: Option<SettingsPage>,
+    // Set while the tilt calibration wizard is up
+    calibration: Option<Wizard>,
+    // Set while the course editor is up
+    editor: Option<Editor>,
+    // Initials being entered for a run that made the leaderboard, and
+    // the rank it made
+    initials: Option<(Initials, usize)>,
+    // Set while the debug menu is up
+    debug: Option<DebugMenu>,
+    // Set while the profile picker is up
+    picker: Option<ProfilePicker>,
+    idle_since: u32,
+    // Last button press or tap, for sleeping when nobody is around
+    last_input: u32,
+    // Backlight turned down on the idle title screen
+    dimmed: bool,
+    // Gestures in progress on the queued input events
+    inputs: Inputs,
     pub input_device: T,
 }
 
 impl<T: InputDevice> Game<T> {
-    pub fn init(mut input_device: T) -> Result<Self, T::Error> {
-        input_device.init()?;
+    // An input device that fails to come up leaves the button, which the
+    // game polls anyway
+    pub fn init(mut input_device: T) -> Self {
+        if input_device.init().is_err() {
+            log::warn!("input device init failed, flap with the button");
+        }
+        // Who is playing first
+        let mut game = Game::with_device(input_device);
+        game.state = GameState::Profiles;
+        game
+    }
+
+    // Start the game over on `input_device`, already up (see `system`):
+    // whatever the last game left on the screen, in the effects, in the
+    // music or in the sprite cache goes, and a fresh title screen comes up
+    pub fn warm_restart(input_device: T) -> Self {
+        log::info!("warm restart");
+        test_pattern::hide();
+        screensaver::wake();
+        audio::stop_music();
+        let _ = display::hide_overlay();
+        hud::hide();
+        effects::clear();
+        particles::clear();
+        raster::clear();
+        ghost::hide();
+        let _ = display::set_backdrop(Argb8888::BLACK);
+        let _ = display::set_brightness(backlight::layer_alpha());
+        sprite_cache::clear();
+        input_events::clear();
+        let game = Game::with_device(input_device);
+        backlight::set_brightness(BRIGHTNESS_LEVELS[game.brightness]);
+        game
+    }
 
-        let game = Game {
+    // A new game on `input_device`, which is up or has failed to come up
+    fn with_device(input_device: T) -> Self {
+        let tuning = settings::get().tuning;
+        frame_record::new_game();
+        let world = Game::<T>::tuned_world(&tuning, ModeId::Classic.mode());
+        let velocity = world.velocity();
+        Game {
             state: GameState::Initializing,
             score: 0,
             countdown_start_time: 0,
-            obstacle: obstacle::Obstacle::init(),
-            player: player::Player::init(),
+            countdown_digit: 0,
+            death_start_time: 0,
+            death_played: 0,
+            run_start: 0,
+            death_y: 0,
+            backdrop: Backdrop::new(world.lane(), velocity),
+            world,
+            pickups: Pickups::new(velocity),
+            trail: Trail::new(),
+            player: Game::<T>::tuned_player(&tuning),
+            timestep: FixedStep::new(TICK_HZ, MAX_TICKS),
+            controls: Controls::new(settings::get().controls),
+            tuning,
+            menu: Focus::new(),
+            adjusting: false,
+            brightness: brightness_step(settings::get().brightness),
+            demo: None,
+            setup: 0,
+            versus: None,
+            practice: None,
+            clock: None,
+            checkpoint: None,
+            powers: Powers::new(),
+            score_text: TextField::new(),
+            timer_text: TextField::new(),
+            board: Board::Main,
+            settings_page: None,
+            calibration: None,
+            editor: None,
+            initials: None,
+            debug: None,
+            picker: None,
+            idle_since: 0,
+            last_input: 0,
+            dimmed: false,
+            inputs: Inputs::new(),
             input_device,
-        };
-
-        Ok(game)
+        }
     }
 
     pub fn update(&mut self) {
+        // Devices without an interrupt queue their events when sampled; the
+        // running game samples its own further down, and the editor reads
+        // the touchscreen itself
+        if (self.state != GameState::Running || self.demo.is_some())
+            && self.state != GameState::Editor
+        {
+            let _ = self.input_device.is_tap(0, 239);
+        }
+        let input = self.inputs.drain();
+        frame_record::note_input(&input);
+        // Holding the button past a long press saves a screenshot to flash,
+        // except on the stats page, where it opens the debug menu
+        let button = match input.button {
+            Some(ButtonEvent::Hold) if self.state != GameState::Stats => {
+                let _ = screenshot::save();
+                None
+            }
+            other => other,
+        };
+        effects::update();
+        let knob = encoder::take();
+
+        // A test pattern has the screen until a long press or a touch; a
+        // press or the knob steps to the next one
+        if test_pattern::shown().is_some() {
+            if button == Some(ButtonEvent::Long) || input.pressed || knob == Some(MenuNav::Press) {
+                self.end_test_pattern();
+            } else if button == Some(ButtonEvent::Short) || knob == Some(MenuNav::Next) {
+                test_pattern::next();
+            } else if knob == Some(MenuNav::Prev) {
+                test_pattern::prev();
+            }
+            test_pattern::tick();
+            return;
+        }
+
+        // A menu left alone dims, creeps and goes blank; the input that
+        // brings it back does nothing else
+        if matches!(
+            self.state,
+            GameState::Paused
+                | GameState::Stats
+                | GameState::Leaderboard
+                | GameState::Debug
+                | GameState::Initials
+                | GameState::Profiles
+        ) {
+            let touched = button.is_some() || input.pressed || input.double_tap || knob.is_some();
+            if screensaver::update(touched) {
+                return;
+            }
+        }
+
         match self.state {
             GameState::Initializing => {
                 Game::<T>::draw_start_screen();
-                self.state = GameState::Start;
+                self.idle_since = get_tick();
+                self.set_state(GameState::Ready);
+            }
+            // Over the title screen; a profile's settings take a fresh game
+            // to come into force throughout
+            GameState::Profiles => {
+                let picker = self.picker.get_or_insert_with(|| {
+                    Game::<T>::draw_start_screen();
+                    let picker = ProfilePicker::new();
+                    let _ = display::show_overlay(OVERLAY_ALPHA);
+                    picker
+                });
+                if let Some(slot) = picker.update(button, knob) {
+                    self.picker = None;
+                    let _ = display::hide_overlay();
+                    profiles::select(slot);
+                    if slot.is_some() {
+                        system::request_warm_restart();
+                    }
+                    self.set_state(GameState::Initializing);
+                }
+            }
+            GameState::Ready => {
+                let idle = get_tick().wrapping_sub(self.idle_since);
+                let real_input = button.is_some() || input.pressed;
+                if real_input {
+                    self.last_input = get_tick();
+                }
+                if button == Some(ButtonEvent::Long) {
+                    self.undim();
+                    stats_page::draw();
+                    let _ = display::show_overlay(OVERLAY_ALPHA);
+                    self.set_state(GameState::Stats);
+                } else if real_input {
+                    self.undim();
+                    self.set_state(GameState::Start);
+                } else if get_tick().wrapping_sub(self.last_input) >= SLEEP_IDLE_MS {
+                    self.sleep();
+                } else if idle >= ATTRACT_IDLE_MS {
+                    self.undim();
+                    self.start_demo();
+                } else if idle >= START_DIM_MS && !self.dimmed {
+                    let level = BRIGHTNESS_LEVELS[self.brightness];
+                    backlight::fade_to(level * START_DIM_PERCENT / 100, DIM_FADE_MS);
+                    self.dimmed = true;
+                }
+            }
+            GameState::Stats => {
+                // A long press opens the course editor, a press the
+                // leaderboard; a touch goes back to a fresh title screen.
+                // Still held from the title screen, it opens the debug menu.
+                if button == Some(ButtonEvent::Hold) {
+                    self.debug = Some(DebugMenu::new());
+                    self.set_state(GameState::Debug);
+                } else if button == Some(ButtonEvent::Long) {
+                    self.editor = Some(Editor::new());
+                    self.set_state(GameState::Editor);
+                } else if button == Some(ButtonEvent::Short) {
+                    self.board = Board::Main;
+                    leaderboard_page::draw(self.board);
+                    self.set_state(GameState::Leaderboard);
+                } else if button.is_some() || input.pressed {
+                    let _ = display::hide_overlay();
+                    self.set_state(GameState::Initializing);
+                }
+            }
+            // A press turns to the time attack runs, anything else goes back
+            GameState::Leaderboard => {
+                if button == Some(ButtonEvent::Short) && self.board == Board::Main {
+                    self.board = Board::TimeAttack;
+                    leaderboard_page::draw(self.board);
+                } else if button.is_some() || input.pressed {
+                    let _ = display::hide_overlay();
+                    self.set_state(GameState::Initializing);
+                }
+            }
+            GameState::Debug => {
+                let done = match self.debug.as_mut() {
+                    Some(menu) => menu.update(button, knob),
+                    None => true,
+                };
+                if done {
+                    self.debug = None;
+                    let _ = display::hide_overlay();
+                    self.set_state(GameState::Initializing);
+                }
             }
             GameState::Start => {
                 if self.run_countdown() {
-                    // Only set background once when transitioning to running state
-                    Game::<T>::set_background();
-                    self.state = GameState::Running;
+                    transition::start(Transition::Wipe);
+                    if self.setup().two_player {
+                        sky::reset();
+                        let versus = Versus::new();
+                        versus.draw();
+                        self.versus = Some(versus);
+                    } else {
+                        // Only set background once when transitioning to running state
+                        Game::<T>::draw_playfield();
+                        self.score_text.invalidate();
+                        self.timer_text.invalidate();
+                        self.world.redraw();
+                        self.backdrop.redraw();
+                        hud::show();
+                        self.player.show();
+                        if self.races_ghost() {
+                            ghost::start();
+                        }
+                        if self.setup().practice {
+                            self.practice = Some(Practice::new());
+                            self.save_checkpoint();
+                        }
+                        if self.setup().mode == ModeId::TimeAttack {
+                            self.clock = Some(Clock::new(TICK_HZ));
+                            self.save_checkpoint();
+                        }
+                        replay::start(self.replay_header());
+                    }
+                    profiler::begin_session();
+                    latency::begin_session();
+                    self.run_start = get_tick();
+                    self.set_state(GameState::Running);
                 }
             }
 
             GameState::Running => {
+                // Any real input ends the demo and goes back to the title
+                if self.demo.is_some() && (button.is_some() || input.pressed) {
+                    self.last_input = get_tick();
+                    self.restart();
+                    return;
+                }
+
+                // Long press pauses, and so does dropping the board; short
+                // presses are flaps in button mode
+                if button == Some(ButtonEvent::Long) || (input.free_fall && self.demo.is_none()) {
+                    self.pause();
+                    return;
+                }
+
+                profiler::mark_frame();
+                self.timestep.set_scale(self.powers.time_scale());
+                let ticks = self.timestep.advance(clock::micros());
+                if let Some(versus) = self.versus.as_mut() {
+                    // Player 1 on the input device, player 2 on the button;
+                    // with the device in button mode both birds share it
+                    let (y_min, y_max) = versus.input_range(0);
+                    let mode = self.input_device.mode();
+                    let p1 = match self.input_device.is_tap(y_min, y_max) {
+                        Ok(input) if mode.steers() => input,
+                        Ok((_, tap)) if mode == InputMode::Trigger => (versus.flap_y(0), tap),
+                        _ => (versus.flap_y(0), button == Some(ButtonEvent::Short)),
+                    };
+                    let p2 = (versus.flap_y(1), button == Some(ButtonEvent::Short));
+                    if versus.update([p1, p2], ticks) {
+                        self.set_state(GameState::End);
+                    }
+                    return;
+                }
+
+                // The last point may have moved the sky on; repaint it before
+                // anything is drawn over it this frame
+                if sky::update(self.score) {
+                    let lane = self.world.lane();
+                    lane.fill_sky(lane.field());
+                    self.world.redraw();
+                    self.backdrop.redraw();
+                    self.player.show();
+                    ghost::redraw();
+                }
+                // Haze over the ground in the heat of the day
+                raster::set_heat(self.score % POINTS_PER_CYCLE < POINTS_PER_PHASE);
+
                 let (_, player_curr_y) = self.player.get_xy();
 
-                if let Ok(data) = self.input_device.is_tap(0, 239) {
-                    let new_y = data.0;
-                    let is_tap = data.1;
+                // A new tilt or stick sample this frame, to be steered on
+                let moved = input.tilt_y.is_some();
+                let snapshot = self.snapshot();
+                let input = match self.demo.as_mut() {
+                    Some(demo) => {
+                        demo.observe(&snapshot);
+                        demo.is_tap(0, 239)
+                    }
+                    None => self.input_device.is_tap(0, 239).map_err(|_| ()),
+                };
 
-                    if is_tap {
-                        self.player
-                            .move_player(new_y.clamp(PLAYER_Y_MIN, PLAYER_Y_MAX));
-                    } else {
-                        self.player.move_player(player_curr_y);
+                if let Ok(data) = input {
+                    let flap_y = player_curr_y - self.tuning.flap_lift;
+                    let (new_y, is_tap) = match self.input_device.mode() {
+                        _ if self.demo.is_some() => data,
+                        InputMode::Button => (flap_y, button == Some(ButtonEvent::Short)),
+                        InputMode::Trigger => (flap_y, data.1),
+                        _ => data,
+                    };
+
+                    // A flap from the host agent overrides the accelerometer
+                    #[cfg(all(feature = "agent-api", debug_assertions))]
+                    let (new_y, is_tap) = match agent::take_flap(player_curr_y) {
+                        Some(y) => (y, true),
+                        None => (new_y, is_tap),
+                    };
+
+                    frame_record::note_steer(new_y, is_tap, ticks);
+                    if self.demo.is_none() {
+                        replay::frame(new_y, is_tap, ticks);
+                        if is_tap {
+                            latency::acted(Kind::Tap);
+                        }
+                        if moved && self.input_device.mode().steers() {
+                            latency::acted(Kind::Steer);
+                        }
+                    }
+                    if self.controls.input((new_y, is_tap)) {
+                        game_events::publish(GameEvent::Flap {
+                            bird: self.player.bird().rect(),
+                        });
+                    }
+
+                    // Each tick the controls steer, everything moves, then
+                    // the rules see what that did; a crash ends the run there
+                    let (y_min, y_max) = self.world.lane().player_y_range();
+                    for tick in 0..ticks {
+                        let (_, y) = self.player.get_xy();
+                        if let Some(next) = self.controls.tick(y) {
+                            self.player.steer(next.clamp(y_min, y_max));
+                        }
+                        self.world.obstacle_mut().set_score(self.score);
+                        self.backdrop.follow(self.world.velocity());
+                        for entity in self.entities() {
+                            entity.update(1);
+                        }
+
+                        if self.races_ghost() {
+                            let (_, y) = self.player.get_xy();
+                            ghost::record(y, is_tap && tick == 0);
+                            ghost::step();
+                        }
+
+                        // A hit-stop drops the rest of this frame's ticks
+                        self.apply_rules();
+                        if self.state != GameState::Running || self.timestep.is_held() {
+                            break;
+                        }
                     }
                 } else {
                     panic!("Input device error");
                 }
 
-                self.obstacle.move_obstacle();
+                // Close to the ceiling or the ground, that edge flashes red
+                let (ceiling, ground) = self.edge_warning();
+                self.world.warn_ground(ground);
+
+                // Drawn once, between the last two ticks
+                let mut renderer =
+                    Renderer::between_ticks(self.world.lane(), self.timestep.alpha());
+                for entity in self.entities_back_to_front() {
+                    entity.draw(&mut renderer);
+                }
+
+                // The score bar under the HUD goes blank, so the score is
+                // drawn whole again if the HUD goes
+                if hud::is_shown() {
+                    let attempts = self.practice.map(|practice| practice.attempts());
+                    let seconds = self.clock.map(|clock| clock.seconds_left());
+                    let input = self.input_device.mode();
+                    hud::update(self.score, attempts, seconds, &self.powers, input, ceiling);
+                    self.score_text.invalidate();
+                    self.timer_text.invalidate();
+                } else {
+                    let top = self.world.lane().score_height - config::SCORE_BOARD_HEIGHT as Coord;
+                    self.show_score(&mut renderer, 96, top.max(0));
+                    self.show_timer(&mut renderer, 176, top.max(0));
+                }
+                renderer.finish();
+
+                #[cfg(all(feature = "agent-api", debug_assertions))]
+                agent::publish(&self.snapshot());
+            }
 
-                if self.is_collison() {
-                    self.state = GameState::End;
+            GameState::Dying => {
+                // Slow motion: the flash, the fall and the burst all play
+                // on the slowed clock
+                let real = get_tick().wrapping_sub(self.death_start_time);
+                self.timestep.set_scale(transition::slowmo_scale(real));
+                let ticks = self.timestep.advance(clock::micros());
+                let elapsed = self.timestep.played_ms().wrapping_sub(self.death_played);
+                let level = backlight::layer_alpha();
+                if elapsed < transition::FLASH_MS {
+                    let _ = display::set_brightness(transition::flash_alpha(elapsed, level));
+                } else {
+                    let _ = display::set_backdrop(Argb8888::BLACK);
+                    let _ = display::set_brightness(level);
                 }
 
-                self.update_score();
+                // The crash burst plays on while the world stands still;
+                // drawn before the bird so it stays on top
+                self.trail.update(ticks);
+                let lane = self.world.lane();
+                entity::draw_now(&self.trail, lane);
+
+                let floor = lane.y(lane.ground) - config::PLAYER_HEIGHT as Coord;
+                self.player
+                    .set_y(transition::fall_y(self.death_y, elapsed, floor));
+
+                if elapsed >= transition::DEATH_MS {
+                    self.set_state(GameState::End);
+                }
+            }
+
+            GameState::Paused if self.settings_page.is_some() => {
+                let exit = self
+                    .settings_page
+                    .as_mut()
+                    .and_then(|page| page.update(button, knob));
+                match exit {
+                    Some(Exit::Back) => {
+                        self.settings_page = None;
+                        self.draw_pause_menu(None);
+                    }
+                    Some(Exit::Calibrate) => {
+                        self.settings_page = None;
+                        self.calibration = Some(Wizard::new());
+                        self.set_state(GameState::Calibrating);
+                    }
+                    None => {}
+                }
+            }
+            // A double tap, on the button or the screen, skips the menu
+            GameState::Paused if input.double_tap => self.resume(),
+            // The encoder's switch on a value starts turning it, and
+            // anything pressed stops
+            GameState::Paused if self.adjusting => match (knob, button) {
+                (Some(MenuNav::Next), _) => self.adjust_menu_item(self.menu.selected(), true),
+                (Some(MenuNav::Prev), _) => self.adjust_menu_item(self.menu.selected(), false),
+                (Some(MenuNav::Press), _) | (None, Some(_)) => {
+                    self.finish_adjusting();
+                    self.draw_pause_menu(None);
+                }
+                (None, None) => {}
+            },
+            GameState::Paused => {
+                let nav = match knob {
+                    Some(MenuNav::Press) if adjustable(self.menu.selected()) => {
+                        self.adjusting = true;
+                        self.draw_pause_menu(None);
+                        None
+                    }
+                    Some(MenuNav::Next) => Some(Nav::Next),
+                    Some(MenuNav::Prev) => Some(Nav::Prev),
+                    Some(MenuNav::Press) => Some(Nav::Activate),
+                    None => Nav::from_button(button),
+                };
+                if let Some(nav) = nav {
+                    if let Some(item) = self.draw_pause_menu(Some(nav)) {
+                        self.select_menu_item(item);
+                    }
+                }
+            }
+
+            GameState::Calibrating => {
+                let done = match self.calibration.as_mut() {
+                    Some(wizard) => wizard.update(button, knob),
+                    None => true,
+                };
+                // The wizard covered the game; start over like a theme change
+                if done {
+                    self.calibration = None;
+                    self.restart();
+                }
+            }
+
+            GameState::Editor => {
+                let exit = match self.editor.as_mut() {
+                    Some(editor) => editor.update(button),
+                    None => Some(EditorExit::Back),
+                };
+                if let Some(exit) = exit {
+                    self.editor = None;
+                    if exit == EditorExit::Play {
+                        self.setup = SETUPS.iter().position(|s| s.course).unwrap_or(0);
+                    }
+                    self.restart();
+                }
             }
 
             GameState::End => {
+                // The demo just loops back to the title screen
+                if self.demo.is_some() {
+                    self.restart();
+                    return;
+                }
+
+                let play_ms = get_tick().wrapping_sub(self.run_start);
+                if let Some(versus) = self.versus.as_ref() {
+                    let [p1, p2] = versus.scores();
+                    stats::record_session(profiler::summary(), p1.max(p2), play_ms);
+                    profiles::record_game(p1.max(p2));
+                    score_link::report(Mode::Versus, p1.max(p2), play_ms);
+                    versus.draw_winner();
+                    let _ = display::show_overlay(OVERLAY_ALPHA);
+                    self.set_state(GameState::Halt);
+                    return;
+                }
+                stats::record_session(profiler::summary(), self.score, play_ms);
+                profiles::record_game(self.score);
+                let mode = match self.setup().mode {
+                    ModeId::Classic => Mode::Solo,
+                    ModeId::Runner => Mode::Runner,
+                    ModeId::Signature => Mode::Signature,
+                    ModeId::TimeAttack => Mode::TimeAttack,
+                };
+                score_link::report(mode, self.score, play_ms);
+                transition::start(Transition::Fade);
+                self.player.hide();
+                hud::hide();
+                effects::clear();
+                particles::clear();
                 Game::<T>::draw_game_over_screen();
-                self.show_score(96, 156);
-                self.state = GameState::Halt;
+                self.score_text.invalidate();
+                let mut renderer = Renderer::new(lane::full());
+                self.show_score(&mut renderer, 96, 156);
+                renderer.finish();
+                // A solo run good enough for its leaderboard is signed first
+                let rank = stats::leaderboard(self.board()).rank(self.score);
+                match rank {
+                    Some(rank) if self.practice.is_none() => {
+                        let last = profiles::initials()
+                            .unwrap_or_else(|| stats::last_initials(self.board()));
+                        let initials = Initials::new(last);
+                        leaderboard_page::draw_entry(&initials, rank);
+                        let _ = display::show_overlay(OVERLAY_ALPHA);
+                        self.initials = Some((initials, rank));
+                        self.set_state(GameState::Initials);
+                    }
+                    _ => self.set_state(GameState::Halt),
+                }
+            }
+
+            // A press or a turn of the knob changes the letter, a long
+            // press or the knob's switch keeps it
+            GameState::Initials => {
+                let board = self.board();
+                let Some((initials, rank)) = self.initials.as_mut() else {
+                    self.set_state(GameState::Halt);
+                    return;
+                };
+                let done = match (knob, button) {
+                    (Some(MenuNav::Next), _) => {
+                        initials.step(true);
+                        false
+                    }
+                    (Some(MenuNav::Prev), _) => {
+                        initials.step(false);
+                        false
+                    }
+                    (Some(MenuNav::Press), _) | (None, Some(ButtonEvent::Long)) => {
+                        initials.confirm()
+                    }
+                    (None, Some(_)) => {
+                        initials.step(true);
+                        false
+                    }
+                    (None, None) if input.pressed => {
+                        initials.step(true);
+                        false
+                    }
+                    (None, None) => return,
+                };
+                if done {
+                    stats::record_run(board, self.score, initials.letters());
+                    profiles::sign(initials.letters());
+                    self.initials = None;
+                    let _ = display::hide_overlay();
+                    self.last_input = get_tick();
+                    self.set_state(GameState::Halt);
+                } else {
+                    leaderboard_page::draw_entry(initials, *rank);
+                }
+            }
+
+            // Shaking the board starts over
+            GameState::Halt => {
+                if input.shake {
+                    self.last_input = get_tick();
+                    self.restart();
+                }
+            }
+        }
+    }
+
+    fn set_state(&mut self, next: GameState) {
+        let from = self.state;
+        // Time spent outside the game is not played
+        if next == GameState::Running && from != GameState::Running {
+            self.timestep.reset();
+            self.timestep.set_scale(FULL_SPEED);
+        }
+        self.state = next;
+        if next != from {
+            log::debug!("{} -> {}", from.name(), next.name());
+            screensaver::wake();
+            game_events::publish(GameEvent::StateChange { from, to: next });
+        }
+    }
+
+    // Bring the backlight back up after the title screen dimmed it
+    fn undim(&mut self) {
+        if self.dimmed {
+            backlight::fade_to(BRIGHTNESS_LEVELS[self.brightness], UNDIM_FADE_MS);
+            self.dimmed = false;
+        }
+    }
+
+    // Power down until someone comes back, then start on a fresh title
+    fn sleep(&mut self) {
+        power::sleep();
+        self.inputs = Inputs::new();
+        self.dimmed = false;
+        backlight::set_brightness(BRIGHTNESS_LEVELS[self.brightness]);
+        self.last_input = get_tick();
+        self.set_state(GameState::Initializing);
+    }
+
+    // Play a game on autopilot to show what the game looks like
+    fn start_demo(&mut self) {
+        self.demo = Some(DemoInputDevice::new());
+        // The autopilot points where the bird should be
+        self.controls = Controls::new(ControlScheme::DirectTilt);
+        Game::<T>::draw_playfield();
+        self.score_text.invalidate();
+        self.world.redraw();
+        self.backdrop.redraw();
+        hud::show();
+        self.player.show();
+        self.set_state(GameState::Running);
+    }
+
+    // Flash the screen white and let the bird drop before the game-over screen
+    fn begin_death(&mut self) {
+        if self.races_ghost() {
+            ghost::finish(self.score);
+        }
+        replay::finish(self.score);
+        let (_, player_y) = self.player.get_xy();
+        self.death_y = player_y;
+        self.death_start_time = get_tick();
+        self.death_played = self.timestep.played_ms();
+        let _ = display::set_backdrop(FLASH_COLOR);
+        let _ = display::set_brightness(0);
+        self.set_state(GameState::Dying);
+    }
+
+    // Solo classic games record a run and race the best one; the demo does
+    // not, a runner's obstacles would not match a classic recording and a
+    // practice run is not one go
+    fn races_ghost(&self) -> bool {
+        let setup = self.setup();
+        self.demo.is_none() && setup.mode == ModeId::Classic && !setup.practice && !setup.course
+    }
+
+    fn setup(&self) -> &'static Setup {
+        &SETUPS[self.setup]
+    }
+
+    // How the run starting now is set up, for its replay
+    fn replay_header(&self) -> Header {
+        let mut flags = 0;
+        if self.setup().practice {
+            flags |= replay_format::PRACTICE;
+        }
+        if self.setup().course {
+            flags |= replay_format::COURSE;
+        }
+        Header {
+            mode: self.setup().mode,
+            scheme: self.controls.scheme(),
+            flags,
+            seed: frame_record::seed(),
+            tuning: self.tuning,
+            velocity: self.world.velocity(),
+            frames: 0,
+            score: 0,
+        }
+    }
+
+    // The leaderboard the run being played goes on
+    fn board(&self) -> Board {
+        match self.clock {
+            Some(_) => Board::TimeAttack,
+            None => Board::Main,
+        }
+    }
+
+    // The full-screen playfield for a game of `mode`, its opening and speed
+    // tuned
+    fn tuned_world(tuning: &TuningParams, mode: &'static dyn GameMode) -> World {
+        let mut world = World::new(tuning.lane(lane::full()), mode);
+        world.set_velocity(tuning.velocity(world.velocity()));
+        world
+    }
+
+    fn tuned_player(tuning: &TuningParams) -> player::Player {
+        let mut player = player::Player::init();
+        player.set_gravity(tuning.gravity);
+        player
+    }
+
+    // The rules of the game set up
+    fn mode(&self) -> &'static dyn GameMode {
+        if self.setup().course {
+            courses::playing()
+        } else {
+            self.setup().mode.mode()
+        }
+    }
+
+    // Practice and time attack: keep where everything is now to come back to
+    fn save_checkpoint(&mut self) {
+        let (_, bird_y) = self.player.get_xy();
+        self.checkpoint = Some(Checkpoint {
+            world: self.world.clone(),
+            score: self.score,
+            bird_y,
+        });
+    }
+
+    // Practice: after a crash, put the world, the score and the bird back
+    // to the checkpoint and try again. The speed stays as it now is. Time
+    // attack keeps the score and pays for the crash in time instead.
+    fn respawn(&mut self) {
+        let Some(checkpoint) = self.checkpoint.as_ref() else {
+            return;
+        };
+        let velocity = self.world.velocity();
+        self.world = checkpoint.world.clone();
+        self.world.set_velocity(velocity);
+        self.pickups = Pickups::new(velocity);
+        if self.clock.is_none() {
+            self.score = checkpoint.score;
+        }
+        self.player = Game::<T>::tuned_player(&self.tuning);
+        self.player.set_y(checkpoint.bird_y);
+        self.controls.set_scheme(self.controls.scheme());
+        if let Some(practice) = self.practice.as_mut() {
+            practice.respawned();
+        }
+        if let Some(clock) = self.clock.as_mut() {
+            clock.crashed();
+        }
+        let lane = self.world.lane();
+        lane.fill_sky(lane.field());
+        self.world.redraw();
+        self.backdrop.redraw();
+        self.player.show();
+    }
+
+    fn pause(&mut self) {
+        self.menu.reset();
+        self.adjusting = false;
+        encoder::clear();
+        self.draw_pause_menu(None);
+        let _ = display::show_overlay(OVERLAY_ALPHA);
+        self.set_state(GameState::Paused);
+    }
+
+    fn resume(&mut self) {
+        self.finish_adjusting();
+        let _ = display::hide_overlay();
+        // Split-screen birds live in Layer 1, which the menu did not touch
+        if self.versus.is_none() {
+            self.player.show();
+        }
+        // Time spent in the menu is not a frame
+        profiler::restart_interval();
+        self.set_state(GameState::Running);
+    }
+
+    // Take the test pattern down, back to a fresh title screen
+    pub fn end_test_pattern(&mut self) {
+        test_pattern::hide();
+        self.restart();
+    }
+
+    // Back to the start screen with a fresh game
+    fn restart(&mut self) {
+        // A run given up part way is still the last one
+        replay::finish(self.score);
+        frame_record::new_game();
+        transition::start(Transition::Slide);
+        let _ = display::hide_overlay();
+        self.player.hide();
+        hud::hide();
+        effects::clear();
+        particles::clear();
+        raster::clear();
+        self.trail = Trail::new();
+        self.score = 0;
+        self.countdown_start_time = 0;
+        self.countdown_digit = 0;
+        let _ = display::set_backdrop(Argb8888::BLACK);
+        let _ = display::set_brightness(backlight::layer_alpha());
+        self.world = Game::<T>::tuned_world(&self.tuning, self.mode());
+        self.backdrop = Backdrop::new(self.world.lane(), self.world.velocity());
+        self.pickups = Pickups::new(self.world.velocity());
+        self.player = Game::<T>::tuned_player(&self.tuning);
+        self.controls = Controls::new(settings::get().controls);
+        ghost::hide();
+        self.demo = None;
+        self.versus = None;
+        self.practice = None;
+        self.clock = None;
+        self.powers = Powers::new();
+        self.checkpoint = None;
+        self.initials = None;
+        self.set_state(GameState::Initializing);
+    }
+
+    fn select_menu_item(&mut self, item: usize) {
+        match item {
+            MENU_RESUME => self.resume(),
+            MENU_RESTART => self.restart(),
+            MENU_MODE => {
+                self.setup = (self.setup + 1) % SETUPS.len();
+                self.restart();
+            }
+            MENU_INPUT => {
+                self.input_device.cycle();
+                self.draw_pause_menu(None);
+            }
+            MENU_CONTROLS => {
+                let scheme = self.controls.scheme().next();
+                self.controls.set_scheme(scheme);
+                settings::update(|settings| settings.controls = scheme);
+                self.draw_pause_menu(None);
+            }
+            MENU_BRIGHTNESS => {
+                self.brightness = (self.brightness + 1) % BRIGHTNESS_LEVELS.len();
+                let level = BRIGHTNESS_LEVELS[self.brightness];
+                backlight::set_brightness(level);
+                settings::update(|settings| settings.brightness = level);
+                self.draw_pause_menu(None);
+            }
+            MENU_RETRO => {
+                retro::cycle();
+                self.draw_pause_menu(None);
+            }
+            // The screen is painted in the old colors; start over in the new
+            MENU_THEME => {
+                theme::cycle();
+                self.restart();
             }
+            MENU_GAMMA => {
+                let gamma = settings::get().gamma.next();
+                let _ = display::set_gamma(gamma);
+                settings::update(|settings| settings.gamma = gamma);
+                self.draw_pause_menu(None);
+            }
+            MENU_INVERT => {
+                let invert = !settings::get().invert;
+                let _ = display::invert_colors(invert);
+                settings::update(|settings| settings.invert = invert);
+                self.draw_pause_menu(None);
+            }
+            MENU_SETTINGS => self.settings_page = Some(SettingsPage::new()),
+            _ => {}
+        }
+    }
 
-            GameState::Halt => {}
+    // Turn the value of menu `item` a step up or down with the encoder.
+    // Applied at once and saved when the turning is done.
+    fn adjust_menu_item(&mut self, item: usize, up: bool) {
+        if item == MENU_BRIGHTNESS {
+            // Levels run brightest first
+            let last = BRIGHTNESS_LEVELS.len() - 1;
+            self.brightness = if up {
+                self.brightness.saturating_sub(1)
+            } else {
+                (self.brightness + 1).min(last)
+            };
+            backlight::set_brightness(BRIGHTNESS_LEVELS[self.brightness]);
         }
+        self.draw_pause_menu(None);
+    }
+
+    fn finish_adjusting(&mut self) {
+        if !core::mem::take(&mut self.adjusting) {
+            return;
+        }
+        let level = BRIGHTNESS_LEVELS[self.brightness];
+        if settings::get().brightness != level {
+            settings::update(|settings| settings.brightness = level);
+        }
+    }
+
+    // Draw the pause menu, moved on by `nav`; the item `nav` activated
+    fn draw_pause_menu(&mut self, nav: Option<Nav>) -> Option<usize> {
+        let mut input: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            input,
+            "{}: {}",
+            lang::text(Msg::Input),
+            lang::text(self.input_device.mode().msg())
+        );
+        let mut controls: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            controls,
+            "{}: {}",
+            lang::text(Msg::Controls),
+            lang::text(self.controls.scheme().msg())
+        );
+        let mut mode: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            mode,
+            "{}: {}",
+            lang::text(Msg::Mode),
+            lang::text(self.setup().name)
+        );
+        let mut brightness: FmtBuf<24> = FmtBuf::new();
+        let level = BRIGHTNESS_LEVELS[self.brightness];
+        let bright = lang::text(Msg::Bright);
+        let _ = if self.adjusting {
+            write!(brightness, "< {}: {}% >", bright, level)
+        } else {
+            write!(brightness, "{}: {}%", bright, level)
+        };
+
+        let mut retro: FmtBuf<24> = FmtBuf::new();
+        let retro_mode = match retro::mode() {
+            RetroMode::Off => Msg::Off,
+            RetroMode::On => Msg::On,
+            RetroMode::Scanlines => Msg::RetroLines,
+        };
+        let _ = write!(
+            retro,
+            "{}: {}",
+            lang::text(Msg::Retro),
+            lang::text(retro_mode)
+        );
+        let mut look: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            look,
+            "{}: {}",
+            lang::text(Msg::Theme),
+            lang::text(theme::id().msg())
+        );
+        let mut gamma: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            gamma,
+            "{}: {}",
+            lang::text(Msg::Gamma),
+            lang::text(settings::get().gamma.msg())
+        );
+        let mut invert: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            invert,
+            "{}: {}",
+            lang::text(Msg::Invert),
+            lang::text(if settings::get().invert {
+                Msg::On
+            } else {
+                Msg::Off
+            })
+        );
+
+        let mut ui = Ui::begin(&mut self.menu, nav, ui::centered_top(MENU_ITEMS + 1));
+        ui.label(lang::text(Msg::Paused));
+        let chosen = ui.list(&[
+            lang::text(Msg::Resume),
+            lang::text(Msg::Restart),
+            mode.as_str(),
+            input.as_str(),
+            controls.as_str(),
+            brightness.as_str(),
+            retro.as_str(),
+            look.as_str(),
+            gamma.as_str(),
+            invert.as_str(),
+            lang::text(Msg::Settings),
+        ]);
+        ui.end();
+        chosen
     }
 
     pub fn draw_game_over_screen() {
@@ -122,16 +1390,26 @@ impl<T: InputDevice> Game<T> {
 
     pub fn draw_start_screen() {
         Game::<T>::set_background();
-        //display::draw_image(40, 160, 40, 80, assets::GAME_NAME_IMG_DATA.as_ptr());
-        display::draw_image(
-            0,
-            DISPLAY_WIDTH,
-            0,
-            DISPLAY_HEIGHT,
-            assets::GAME_NAME_IMG_DATA.as_ptr(),
-        );
-        let text = c"Game Starts In";
-        display::write_string(0, 120, text.as_ptr(), color::RED, color::BACKGROUND);
+        artwork::draw_title();
+        let mut text: FmtBuf<32> = FmtBuf::new();
+        let _ = text.write_str(lang::text(Msg::GameStartsIn));
+        let theme = theme::current();
+        let _ = display::write_string_rust(0, 120, text.as_cstr(), theme.caption, theme.background);
+        // The bird at twice its size under the countdown
+        if let Some(bird) = sprites::sprite(SpriteId::Bird) {
+            let (w, _) = ImageTransform::FLIP_Y.scaled(Scale::DOUBLE).size(&bird);
+            let x = (config::LCD_WIDTH as Coord - w as Coord) / 2;
+            display::draw_sprite_rust(x, START_BIRD_Y, &bird, color::SPRITE_KEY, Scale::DOUBLE);
+        }
+    }
+
+    // The starting sky between the scoreboard and the ground, then the rest
+    // of the scene, for a game starting
+    fn draw_playfield() {
+        sky::reset();
+        let lane = lane::full();
+        lane.fill_sky(lane.field());
+        Game::<T>::set_background();
     }
 
     pub fn set_background() {
@@ -139,117 +1417,215 @@ impl<T: InputDevice> Game<T> {
         // Only draw the game elements on top of it
 
         //1. print the scoreboard area (without clearing the background)
-        print_score_card_background();
-
-        //3. print the plant
-        display::draw_image(
-            0,
-            60,
-            210,
-            config::PLANTS_HEIGHT,
-            assets::PLANT_IMG_DATA.as_ptr(),
-        );
-        display::draw_image(
-            60,
-            60,
-            210,
-            config::PLANTS_HEIGHT,
-            assets::PLANT_IMG_DATA.as_ptr(),
-        );
-        display::draw_image(
-            120,
-            60,
-            210,
-            config::PLANTS_HEIGHT,
-            assets::PLANT_IMG_DATA.as_ptr(),
-        );
-        display::draw_image(
-            180,
-            60,
-            210,
-            config::PLANTS_HEIGHT,
-            assets::PLANT_IMG_DATA.as_ptr(),
-        );
+        ground::draw_score_bar(lane::full());
+
+        //3. print the plant, scrolled back to the start
+        ground::draw(lane::full());
     }
 
-    //returns 'true' if countdown is over , otherwise 'false'
+    // Show the 3-2-1 overlay; returns 'true' once the countdown is over
     fn run_countdown(&mut self) -> bool {
+        let now = get_tick();
         if self.countdown_start_time == 0 {
-            self.countdown_start_time = get_tick();
+            self.countdown_start_time = now;
         }
 
-        let elapsed = get_tick() - self.countdown_start_time;
-        let number = if elapsed < 1000 {
-            c"3"
-        } else if elapsed < 2000 {
-            c"2"
-        } else if elapsed < 3000 {
-            c"1"
-        } else {
-            self.countdown_start_time = 0;
-            return true;
-        };
+        let elapsed = now.wrapping_sub(self.countdown_start_time);
+        match transition::countdown_digit(elapsed) {
+            Some(digit) => {
+                if digit != self.countdown_digit {
+                    transition::draw_countdown(digit);
+                    // Bring the overlay up once its first digit is drawn
+                    if self.countdown_digit == 0 {
+                        let _ = display::show_overlay(transition::COUNTDOWN_ALPHA);
+                    }
+                    self.countdown_digit = digit;
+                }
+                false
+            }
+            None => {
+                let _ = display::hide_overlay();
+                self.countdown_start_time = 0;
+                self.countdown_digit = 0;
+                true
+            }
+        }
+    }
 
-        display::write_string(112, 156, number.as_ptr(), color::BLACK, color::BACKGROUND);
+    // What the entities did to each other this frame: a new obstacle may
+    // bring a pickup, the bird may have crashed, scored or won
+    fn apply_rules(&mut self) {
+        let obstacle = self.world.obstacle();
+        if obstacle.arrived() {
+            self.pickups.spawn(obstacle.pair());
+        }
+        // Just back at a checkpoint, nothing counts as a crash
+        let mut counts = self
+            .practice
+            .as_mut()
+            .is_none_or(|practice| practice.tick());
+        if let Some(clock) = self.clock.as_mut() {
+            counts &= clock.tick();
+        }
+        self.powers.tick();
+        let shrunk = self.powers.remaining(PowerUp::Shrink).is_some();
+        self.player.set_shrunk(shrunk);
+        if counts && self.is_collison() && !self.shielded() {
+            let respawn = self.practice.is_some() || self.clock.is_some();
+            game_events::publish(GameEvent::Death {
+                bird: self.player.bird().rect(),
+                respawn,
+            });
+            if respawn {
+                self.respawn();
+                return;
+            }
+            self.begin_death();
+        }
+        self.update_score();
+        // Reaching the goal, or the end of the clock, ends the run straight
+        // away
+        let time_up = self.clock.is_some_and(|clock| clock.is_up());
+        if self.state == GameState::Running && (self.mode().won(self.score) || time_up) {
+            self.set_state(GameState::End);
+        }
+    }
 
-        false
+    // Everything on the single-player playfield, in drawing order; the
+    // particles go first so everything covers them, and the bird last so a
+    // software-drawn one stays on top
+    fn entities(&mut self) -> [&mut dyn Entity; 5] {
+        [
+            &mut self.trail,
+            &mut self.world,
+            &mut self.backdrop,
+            &mut self.pickups,
+            &mut self.player,
+        ]
     }
 
-    fn update_score(&mut self) {
-        let (player_x, _) = self.player.get_xy();
-        let (x_top, _) = self.obstacle.get_xy_top();
+    // Further back layers first; on a layer, in list order
+    fn entities_back_to_front(&mut self) -> [&mut dyn Entity; 5] {
+        let mut entities = self.entities();
+        for i in 1..entities.len() {
+            let mut j = i;
+            while j > 0 && entities[j - 1].depth().z > entities[j].depth().z {
+                entities.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+        entities
+    }
 
-        if player_x > (x_top + config::OBSTACLE_WIDTH as Coord) && !self.obstacle.already_scored {
+    fn update_score(&mut self) {
+        // The hitbox collisions are judged with
+        let bird = self
+            .player
+            .bird()
+            .shrunk(self.powers.inset() + self.tuning.hitbox_inset);
+        let pair = self.world.obstacle_mut().pair_mut();
+        rules::track_clearance(&bird, pair);
+        if rules::passed(self.player.bird(), pair) {
             self.score += 1;
-            self.obstacle.already_scored = true;
+            if let Some(rows) = pair.closest.filter(|_| rules::near_miss(pair)) {
+                self.score += rules::NEAR_MISS_BONUS;
+                game_events::publish(GameEvent::NearMiss {
+                    bird: self.player.bird().rect(),
+                    rows,
+                });
+            }
+            self.timestep.hold(HIT_STOP_TICKS);
+            let (x_top, _) = self.world.obstacle().get_xy_top();
+            game_events::publish(GameEvent::ScorePoint {
+                x: x_top,
+                bird: self.player.bird().rect(),
+                score: self.score,
+                pickup: false,
+            });
+            if let Some(practice) = self.practice.as_mut() {
+                practice.passed();
+            }
+            if self.practice.is_some() || self.clock.is_some() {
+                self.save_checkpoint();
+            }
         }
 
-        self.show_score(96, 0);
+        if let Some(collected) = self.pickups.collect(self.player.bird()) {
+            self.score += collected.points;
+            game_events::publish(GameEvent::ScorePoint {
+                x: collected.x,
+                bird: self.player.bird().rect(),
+                score: self.score,
+                pickup: true,
+            });
+            // A star puts the world under water for a while
+            if collected.star {
+                raster::underwater(UNDERWATER_FRAMES);
+            }
+            if let Some(power) = collected.power {
+                self.powers.grant(power);
+            }
+        }
     }
 
-    fn is_collison(&self) -> bool {
-        //1. check collison with the ground
-        let (_, player_y) = self.player.get_xy();
-        let hits_ground = (player_y + config::PLAYER_HEIGHT as Coord) >= config::GROUND_Y_POS;
-
-        //2. check collision against the obstacles
-        let (player_x, player_y) = self.player.get_xy();
-        let (top_obstacle_x, top_obstacle_y) = self.obstacle.get_xy_top();
-        let (btm_obstacle_x, btm_obstacle_y) = self.obstacle.get_xy_bottom();
-        let (top_obstacle_h, _) = self.obstacle.get_height();
-
-        let is_horizontal_overlap_with_top = ((player_x + config::PLAYER_WIDTH as Coord)
-            > top_obstacle_x)
-            && (player_x < top_obstacle_x + config::OBSTACLE_WIDTH as Coord);
-
-        let is_horizontal_overlap_with_btm = ((player_x + config::PLAYER_WIDTH as Coord)
-            > btm_obstacle_x)
-            && (player_x < btm_obstacle_x + config::OBSTACLE_WIDTH as Coord);
-
-        let is_hits_top = player_y <= top_obstacle_y + top_obstacle_h as Coord;
-        let is_hits_bottom = (player_y + config::PLAYER_HEIGHT as Coord) >= btm_obstacle_y;
-
-        if hits_ground {
-            return true;
+    // Let a shield take the crash; it breaks with a shake the first time
+    fn shielded(&mut self) -> bool {
+        let whole = self.powers.remaining(PowerUp::Shield).is_some();
+        if !self.powers.absorb() {
+            return false;
         }
-
-        if is_horizontal_overlap_with_top && is_hits_top {
-            return true;
+        if whole {
+            effects::shake();
+            let (cx, cy) = self.bird_center();
+            particles::emit(Effect::Burst, cx, cy);
         }
+        true
+    }
 
-        if is_horizontal_overlap_with_btm && is_hits_bottom {
-            return true;
+    fn bird_center(&self) -> (Coord, Coord) {
+        let bird = self.player.bird().rect();
+        (bird.x + bird.w as Coord / 2, bird.y + bird.h as Coord / 2)
+    }
+
+    // Opacity of the warning along the ceiling and along the ground; only
+    // in a mode where flying into them is a crash
+    fn edge_warning(&self) -> (u8, u8) {
+        if !self.mode().deadly_edges() {
+            return (0, 0);
         }
+        let bird = self
+            .player
+            .bird()
+            .shrunk(self.powers.inset() + self.tuning.hitbox_inset);
+        match rules::edge_warning(&bird, self.world.lane().field()) {
+            Some((Edge::Ceiling, closeness)) => (effects::edge_alpha(closeness), 0),
+            Some((Edge::Ground, closeness)) => (0, effects::edge_alpha(closeness)),
+            None => (0, 0),
+        }
+    }
 
-        false
+    fn is_collison(&self) -> bool {
+        self.mode().crashed(
+            &self
+                .player
+                .bird()
+                .shrunk(self.powers.inset() + self.tuning.hitbox_inset),
+            self.world.obstacle().pair(),
+            self.world.lane().field(),
+        )
     }
 
-    fn show_score(&self, x: config::Coord, y: config::Coord) {
-        let mut buf = [0u8; 4];
+    // Only the digits that changed since it was drawn last are drawn
+    fn show_score(&mut self, renderer: &mut Renderer, x: Coord, y: Coord) {
+        let mut buf = [0u8; 3];
 
-        let score = self.score;
+        // A practice run counts tries at the obstacle ahead instead
+        let score = match self.practice {
+            Some(practice) => practice.attempts(),
+            None => self.score,
+        };
 
-        if score >= 1000 {
+        if score >= 1000 || self.mode().won(score) {
             buf[0] = b'W';
             buf[1] = b'I';
             buf[2] = b'N';
@@ -259,28 +1635,86 @@ impl<T: InputDevice> Game<T> {
             buf[2] = b'0' + (score % 10) as u8;
         }
 
-        buf[3] = b'\0';
+        let theme = theme::current();
+        self.score_text
+            .draw(renderer, (x, y), &buf, (theme.score_text, theme.score_box));
+    }
 
-        let score_str = ffi::CStr::from_bytes_with_nul(&buf);
-        display::write_string(
-            x,
-            y,
-            score_str.unwrap().as_ptr(),
-            color::BLACK,
-            color::SCORE,
+    // The time attack clock, minutes and seconds, as the HUD has it
+    fn show_timer(&mut self, renderer: &mut Renderer, x: Coord, y: Coord) {
+        let Some(clock) = self.clock else {
+            return;
+        };
+        let seconds = clock.seconds_left();
+        let mut text: FmtBuf<8> = FmtBuf::new();
+        let _ = write!(text, "{}:{:02}", seconds / 60, seconds % 60);
+        let theme = theme::current();
+        self.timer_text.draw(
+            renderer,
+            (x, y),
+            text.as_str().as_bytes(),
+            (theme.score_text, theme.score_box),
         );
     }
 
-    pub fn is_over(&self) -> bool {
-        // match self.state {
-        //     GameState::Halt => true,
-        //     _ => false,
-        // }
-        matches!(self.state, GameState::Halt)
+    pub fn snapshot(&self) -> GameSnapshot {
+        let (_, player_y) = self.player.get_xy();
+        let (x, top, bottom) = self.world.obstacle().get_gap();
+
+        // Only one obstacle pair is on the playfield at a time; it wraps back
+        // to the right edge once passed, so there is never a second gap yet
+        GameSnapshot {
+            player_y,
+            player_vy: self.player.get_velocity(),
+            gaps: [Some(Gap { x, top, bottom }), None],
+            score: self.score,
+        }
     }
-}
 
-fn print_score_card_background() {
-    display::draw_rect_angle(0, 240, 0, 28, color::WHITE);
-    display::draw_rect_angle(0, 240, 28, 2, color::BLACK);
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    // Obstacle speed in 1/SUBPIXELS of a pixel per tick (see
+    // `core_logic::scroll`)
+    pub fn obstacle_velocity(&self) -> u32 {
+        match self.versus.as_ref() {
+            Some(versus) => versus.obstacle_velocity(),
+            None => self.world.velocity(),
+        }
+    }
+
+    pub fn set_obstacle_velocity(&mut self, velocity: u32) {
+        self.world.set_velocity(velocity);
+        self.pickups.set_velocity(velocity);
+        if let Some(versus) = self.versus.as_mut() {
+            versus.set_obstacle_velocity(velocity);
+        }
+    }
+
+    pub fn tuning(&self) -> TuningParams {
+        self.tuning
+    }
+
+    // Keep `tuning` in the settings; the bird's gravity, the flap, the speed
+    // and the hitbox change at once, the opening from the next game
+    pub fn set_tuning(&mut self, tuning: TuningParams) {
+        if tuning.speed_percent != self.tuning.speed_percent {
+            // Rescaled rather than recomputed, keeping any speed-up so far
+            let velocity =
+                self.obstacle_velocity() * tuning.speed_percent / self.tuning.speed_percent;
+            self.set_obstacle_velocity(velocity.max(1));
+        }
+        self.tuning = tuning;
+        self.player.set_gravity(tuning.gravity);
+        settings::update(|settings| settings.tuning = tuning);
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.state.is_over()
+    }
 }
@@ -0,0 +1,46 @@
+//! The game's event bus
+//!
+//! `Game` publishes flaps, points, crashes and state changes here (see
+//! core_logic::events) and the subsystems that react to them subscribe in
+//! `init`: sound, the shake and score pop, particles, the black box and,
+//! for the host agent, telemetry. Something new that wants to hear of the game adds its
+//! handler there instead of another call in `Game::update`.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::events::{EventBus, Subscriber};
+
+#[cfg(all(feature = "agent-api", debug_assertions))]
+use crate::agent;
+use crate::audio;
+use crate::blackbox;
+use crate::effects;
+use crate::log;
+use crate::particles;
+
+pub use core_logic::events::GameEvent;
+
+// Room for the built-in subscribers and a few more
+const SLOTS: usize = 8;
+
+static mut BUS: EventBus<SLOTS> = EventBus::new();
+
+pub fn init() {
+    subscribe(audio::on_game_event);
+    subscribe(effects::on_game_event);
+    subscribe(particles::on_game_event);
+    subscribe(blackbox::on_game_event);
+    #[cfg(all(feature = "agent-api", debug_assertions))]
+    subscribe(agent::on_game_event);
+}
+
+// A subscriber that finds no free slot is left out, and said so
+pub fn subscribe(subscriber: Subscriber) {
+    if unsafe { BUS.subscribe(subscriber) }.is_err() {
+        log::warn!("game event bus full");
+    }
+}
+
+pub fn publish(event: GameEvent) {
+    unsafe { BUS.publish(event) };
+}
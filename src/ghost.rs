@@ -0,0 +1,209 @@
+//! Ghost of the best run
+//!
+//! Every single-player run is recorded one byte per frame (see `encode`)
+//! into an SDRAM buffer. A run that beats the best so far becomes the
+//! ghost. Later runs replay it frame by frame as a faded bird drawn into
+//! Layer 1 behind the real one. Layer 2 already holds the real bird, so the
+//! ghost is blended in software. Only SDRAM holds the recordings, so the
+//! ghost is gone after a power cycle, and there is none without SDRAM or
+//! while rendering over SPI.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::color;
+use crate::config::{Coord, Rect, INIT_PLAYER_POS_X, PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::display::{self, Backend};
+use crate::framebuffer::{FrameBuffer, ImageTransform};
+use crate::lane::{self, LaneDraw};
+use crate::lcd::DISPLAY_MEMORY;
+use crate::log;
+use crate::profiler::{self, Phase};
+use crate::sdram;
+use crate::sdram::arena::{Arena, Region};
+use crate::sprites::{self, SpriteId};
+
+// Per recording: long enough for several minutes of play
+const BUFFER_BYTES: u32 = 64 * 1024;
+
+// Two buffers that swap roles: one holds the best run, the other records
+const AFTER_SPRITES: Region = Region {
+    base: sprites::SLOTS.end(),
+    size: DISPLAY_MEMORY.free.end() - sprites::SLOTS.end(),
+};
+pub const BUFFERS: Region = Arena::new(AFTER_SPRITES).alloc(2 * BUFFER_BYTES, 4);
+
+// Frame byte: bit 7 flap, bits 0-6 the change in y (-63..=63). ESCAPE in
+// the low bits means the absolute y follows as two little-endian bytes.
+const FLAP: u8 = 0x80;
+const ESCAPE: u8 = 0x40;
+const DELTA_MAX: Coord = 63;
+
+// Ghost x, the same column the real bird flies in
+const GHOST_X: Coord = INIT_PLAYER_POS_X;
+
+#[derive(Copy, Clone)]
+struct Best {
+    buffer: usize,
+    len: u32,
+    score: u32,
+}
+
+struct State {
+    recording: usize,
+    len: u32,
+    last_y: Coord,
+    // Recording ran out of room; the run can not become the ghost
+    overflowed: bool,
+    best: Option<Best>,
+    // Playback position in the best buffer, and where the ghost is drawn
+    cursor: u32,
+    ghost_y: Option<Coord>,
+}
+
+static mut STATE: State = State {
+    recording: 0,
+    len: 0,
+    last_y: 0,
+    overflowed: false,
+    best: None,
+    cursor: 0,
+    ghost_y: None,
+};
+
+fn buffer_base(index: usize) -> u32 {
+    BUFFERS.base + index as u32 * BUFFER_BYTES
+}
+
+fn push(state: &mut State, byte: u8) {
+    if state.len >= BUFFER_BYTES {
+        state.overflowed = true;
+        return;
+    }
+    let addr = buffer_base(state.recording) + state.len;
+    unsafe { core::ptr::write_volatile(addr as *mut u8, byte) };
+    state.len += 1;
+}
+
+fn read(index: usize, offset: u32) -> u8 {
+    unsafe { core::ptr::read_volatile((buffer_base(index) + offset) as *const u8) }
+}
+
+// Begin a run: start recording into the free buffer and rewind the ghost
+pub fn start() {
+    let state = unsafe { &mut STATE };
+    state.recording = match state.best {
+        Some(best) => 1 - best.buffer,
+        None => 0,
+    };
+    state.len = 0;
+    state.overflowed = false;
+    state.cursor = 0;
+    state.ghost_y = None;
+}
+
+// Record this frame's bird position and whether it flapped
+pub fn record(y: Coord, flap: bool) {
+    if !sdram::available() {
+        return;
+    }
+    let state = unsafe { &mut STATE };
+    let flap = if flap { FLAP } else { 0 };
+    let delta = y - state.last_y;
+    if state.len == 0 || delta.abs() > DELTA_MAX {
+        push(state, flap | ESCAPE);
+        for byte in (y as i16).to_le_bytes() {
+            push(state, byte);
+        }
+    } else {
+        push(state, flap | (delta as u8 & 0x7F));
+    }
+    state.last_y = y;
+}
+
+// The run is over; keep it as the ghost if it beat the best one
+pub fn finish(score: u32) {
+    let state = unsafe { &mut STATE };
+    erase(state);
+    if state.overflowed || state.len == 0 {
+        return;
+    }
+    if state.best.is_none_or(|best| score > best.score) {
+        state.best = Some(Best {
+            buffer: state.recording,
+            len: state.len,
+            score,
+        });
+        log::info!("ghost: new best run, {} bytes", state.len);
+    }
+}
+
+// Next recorded frame of the best run: (y, flapped), None once it ends
+fn next_frame(state: &mut State) -> Option<(Coord, bool)> {
+    let best = state.best?;
+    if state.cursor >= best.len {
+        return None;
+    }
+    let byte = read(best.buffer, state.cursor);
+    state.cursor += 1;
+    let y = if byte & 0x7F == ESCAPE {
+        let y = i16::from_le_bytes([
+            read(best.buffer, state.cursor),
+            read(best.buffer, state.cursor + 1),
+        ]);
+        state.cursor += 2;
+        y as Coord
+    } else {
+        // Sign-extend the 7-bit delta
+        let delta = ((byte << 1) as i8 >> 1) as Coord;
+        state.ghost_y.unwrap_or(0) + delta
+    };
+    Some((y, byte & FLAP != 0))
+}
+
+fn erase(state: &mut State) {
+    if let Some(y) = state.ghost_y.take() {
+        let rect = Rect::new(GHOST_X, y, PLAYER_WIDTH, PLAYER_HEIGHT);
+        lane::full().fill_sky(rect);
+    }
+}
+
+// Advance the ghost one frame and redraw it; it vanishes when its run ended
+pub fn step() {
+    if display::backend() != Backend::Ltdc {
+        return;
+    }
+    let state = unsafe { &mut STATE };
+    let Some((y, _)) = next_frame(state) else {
+        erase(state);
+        return;
+    };
+    if state.ghost_y == Some(y) {
+        return;
+    }
+    let _render = profiler::scope(Phase::Render);
+    erase(state);
+    if let Some(bird) = sprites::sprite(SpriteId::Bird) {
+        FrameBuffer::render_target().blit_faded(
+            GHOST_X,
+            y,
+            &bird,
+            ImageTransform::FLIP_Y,
+            color::SPRITE_KEY,
+        );
+    }
+    state.ghost_y = Some(y);
+}
+
+// The picture under the ghost was repainted; draw it again on the next step
+pub fn redraw() {
+    unsafe { STATE.ghost_y = None };
+}
+
+// Drop the ghost from the screen without touching the recordings
+pub fn hide() {
+    erase(unsafe { &mut STATE });
+}
+
+pub fn best_score() -> Option<u32> {
+    unsafe { STATE.best.map(|best| best.score) }
+}
@@ -0,0 +1,52 @@
+//! The strips above and below a lane's playfield
+//!
+//! The score bar is a flat band along the top of the lane with a rule under
+//! it; the ground is the plant sprite tiled along the bottom. While a game
+//! runs the ground is part of the tile map and scrolls with the obstacles
+//! (see `world`), over the theme's water or lava if it has any (see
+//! `strip`). Both are also where the bird dies: see `rules::collides`.
+#![allow(dead_code)]
+
+use crate::config::{Coord, Rect, LCD_WIDTH};
+use crate::entity::Renderer;
+use crate::lane::{Lane, LaneDraw};
+use crate::sprites::{self, SpriteId};
+use crate::strip;
+use crate::theme;
+
+// Height of the rule under the score bar
+const RULE_HEIGHT: Coord = 2;
+
+// Score bar background, in the theme's colors
+pub fn draw_score_bar(lane: Lane) {
+    let theme = theme::current();
+    let bar = (lane.score_height - RULE_HEIGHT) as u32;
+    lane.fill_rect(Rect::new(0, lane.top, LCD_WIDTH, bar), theme.scoreboard);
+    lane.fill_rect(
+        Rect::new(
+            0,
+            lane.y(lane.score_height - RULE_HEIGHT),
+            LCD_WIDTH,
+            RULE_HEIGHT as u32,
+        ),
+        theme.scoreboard_rule,
+    );
+}
+
+// The ground scrolled back to the start, for the still screens; in a game
+// `World` draws it
+pub fn draw(lane: Lane) {
+    let Some(plant) = sprites::sprite(SpriteId::Plant).filter(|plant| plant.w > 0) else {
+        return;
+    };
+    let rows = (lane.height - lane.ground) as u32;
+    let mut renderer = Renderer::new(lane);
+    let ground = Rect::new(0, lane.y(lane.ground), LCD_WIDTH, rows);
+    if let Some(rect) = strip::above(lane, ground) {
+        renderer.fill_tiled(rect, &plant);
+    }
+    if let Some(phase) = strip::phase() {
+        strip::draw(&mut renderer, lane, phase);
+    }
+    renderer.finish();
+}
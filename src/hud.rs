@@ -0,0 +1,315 @@
+//! Score bar HUD on LTDC Layer 2
+//!
+//! In the full-screen game Layer 2 is turned into a strip over the score
+//! bar (`display::Plane::Hud`) showing the score, a pause hint, the input
+//! in use with the tilt sensor's state, and the backup battery. The strip
+//! has per-pixel alpha, so the score bar on Layer 1 shows through its
+//! background. A point scored pops the score (`effects::pop`), and for a
+//! moment after a near miss a note takes the input's place. In a
+//! practice run the strip turns blue and counts tries at the obstacle
+//! ahead in place of the score, and in a time attack run the seconds left
+//! take the input's place, red for the last few. Power-ups the bird has
+//! show left of the score, each with a bar of the time it has left; the
+//! theme says how large the score is. With the bird close to the ceiling,
+//! which is the strip's bottom edge, red rises from it, stronger the closer
+//! (the ground's warning is drawn by `world`). With the `wifi` link up,
+//! three bars in the top right corner show how it is doing. The strip is
+//! redrawn only when something on it changes and
+//! never touches Layer 1, so a point scored does not dirty the playfield.
+//! The bird, which otherwise has Layer 2, is drawn into Layer 1 meanwhile.
+//!
+//! Menus still borrow Layer 2 for the overlay; hiding that brings the HUD
+//! back. With SPI rendering there is no Layer 2, and under the bandwidth
+//! saver (`display::set_bandwidth_saver`) it is off through play; either
+//! way `show` does nothing and the game draws the score into the picture
+//! instead.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use core_logic::effects::edge_fade;
+use core_logic::esp_at::Status as WifiStatus;
+use core_logic::pickup::PICKUP_SIZE;
+use core_logic::powerup::{PowerUp, Powers};
+use core_logic::time_attack::LOW_S;
+
+use crate::battery;
+use crate::color::{self, Argb8888};
+use crate::config::Coord;
+use crate::display::{self, Plane};
+use crate::effects;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::{self, FrameBuffer, ImageTransform, Scaled};
+use crate::game::InputMode;
+use crate::lang::{self, Msg};
+use crate::lcd::HUD_H;
+use crate::mpu6050;
+use crate::sprites;
+use crate::theme;
+use crate::wifi;
+
+// Behind everything on the strip; the score bar shows through
+const BAND: Argb8888 = Argb8888(0x6000_0000);
+const PRACTICE_BAND: Argb8888 = Argb8888(0x8020_40C0);
+const ICON: Argb8888 = Argb8888(0xFFFF_FFFF);
+const CHARGED: Argb8888 = Argb8888(0xFF40_D040);
+const LOW: Argb8888 = Argb8888(0xFFE0_4030);
+// Battery percentage drawn as low
+const LOW_PERCENT: u8 = 20;
+const DIM: Rgb565 = Rgb565::new(20, 40, 20);
+const TOAST: Rgb565 = Rgb565::new(31, 50, 0);
+const TRACK: Argb8888 = Argb8888(0xFF40_4040);
+// The edge warning, its alpha set by how close the bird is
+const WARNING: Argb8888 = Argb8888(0xFFFF_2020);
+const WARNING_ROWS: u32 = 8;
+// Where the power-ups start, one every POWER_STEP pixels, and their bars'
+// full length
+const POWER_X: Coord = 50;
+const POWER_STEP: Coord = 17;
+const BAR_W: u32 = PICKUP_SIZE;
+// Bar length below which a power-up is running out
+const BAR_LOW: u32 = BAR_W / 4;
+
+#[derive(Copy, Clone, PartialEq)]
+struct Status {
+    score: u32,
+    // Tries at the obstacle ahead, in a practice run
+    attempts: Option<u32>,
+    // Seconds left, in a time attack run
+    seconds: Option<u32>,
+    // Each power-up's bar, in pixels, while the bird has it
+    powers: [Option<u32>; PowerUp::ALL.len()],
+    input: InputMode,
+    // Tilt sensor answering
+    sensor: bool,
+    // Backup battery charge, percent
+    battery: Option<u8>,
+    // Score upload link, when there is one
+    wifi: Option<WifiStatus>,
+    // Score size in percent, as the theme has it and more while it pops
+    score_percent: u32,
+    // The near-miss note is up
+    toast: bool,
+    // Opacity of the warning along the bottom edge, 0 for none
+    ceiling: u8,
+}
+
+struct State {
+    // What the strip shows; None redraws it on the next update
+    drawn: Option<Status>,
+    sensor: bool,
+    battery: Option<u8>,
+    wifi: Option<WifiStatus>,
+}
+
+static mut STATE: State = State {
+    drawn: None,
+    sensor: false,
+    battery: None,
+    wifi: None,
+};
+
+// Give Layer 2 to the HUD; it is drawn on the first `update`
+pub fn show() {
+    unsafe { STATE.drawn = None };
+    poll(mpu6050::is_present());
+    FrameBuffer::hud().fill(0);
+    let _ = display::set_plane(Plane::Hud);
+}
+
+// Give Layer 2 back to the bird sprite, hidden until it is shown
+pub fn hide() {
+    let _ = display::set_plane(Plane::Sprite);
+}
+
+pub fn is_shown() -> bool {
+    display::plane() == Plane::Hud
+}
+
+// Take the sensor's state, as the sampling last found it, read the battery
+// and see how the score link is doing; the scheduler's HUD job, while the
+// HUD is up
+pub fn poll(sensor: bool) {
+    if !is_shown() {
+        return;
+    }
+    let state = unsafe { &mut STATE };
+    state.sensor = sensor;
+    state.battery = battery::read_millivolts().map(battery::percent);
+    state.wifi = Some(wifi::status()).filter(|status| *status != WifiStatus::Off);
+}
+
+// Call once a frame while the HUD is up; `attempts` only in a practice run,
+// `seconds` only in a time attack one, `ceiling` the opacity of the edge
+// warning
+pub fn update(
+    score: u32,
+    attempts: Option<u32>,
+    seconds: Option<u32>,
+    powers: &Powers,
+    input: InputMode,
+    ceiling: u8,
+) {
+    let state = unsafe { &mut STATE };
+
+    // Only a whole pixel of bar more or less redraws the strip
+    let powers = PowerUp::ALL.map(|power| {
+        powers
+            .remaining(power)
+            .map(|left| (BAR_W * left as u32).div_ceil(255))
+    });
+    let status = Status {
+        score,
+        attempts,
+        seconds,
+        powers,
+        input,
+        sensor: state.sensor,
+        battery: state.battery,
+        wifi: state.wifi,
+        score_percent: effects::score_percent() * theme::current().score_percent / 100,
+        toast: effects::toast_shown(),
+        ceiling,
+    };
+    if state.drawn != Some(status) {
+        draw(&status);
+        state.drawn = Some(status);
+    }
+}
+
+fn draw(status: &Status) {
+    let mut fb = FrameBuffer::hud();
+    let band = fb.encode_argb(match status.attempts {
+        Some(_) => PRACTICE_BAND,
+        None => BAND,
+    });
+    fb.fill(band);
+    let width = fb.size().width as Coord;
+    let middle = HUD_H as Coord / 2;
+
+    // Red rising from the ceiling, under everything else on the strip
+    if status.ceiling != 0 {
+        for row in 0..WARNING_ROWS {
+            let alpha = edge_fade(status.ceiling, row, WARNING_ROWS);
+            let argb = fb.encode_argb(WARNING.with_alpha(alpha));
+            fb.fill_rect(0, HUD_H as Coord - 1 - row as Coord, width as u32, 1, argb);
+        }
+    }
+
+    // Pause hint: a long press pauses
+    let icon = fb.encode_argb(ICON);
+    fb.fill_rect(8, middle - 6, 4, 12, icon);
+    fb.fill_rect(16, middle - 6, 4, 12, icon);
+
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let mut line: FmtBuf<8> = FmtBuf::new();
+    if let Some(attempts) = status.attempts {
+        let _ = write!(line, "x{:02}", attempts.min(99));
+        let label = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+        let left = TextStyleBuilder::new().baseline(Baseline::Middle).build();
+        let _ = Text::with_text_style(lang::text(Msg::Try), Point::new(28, middle), label, left)
+            .draw(&mut fb);
+    } else if status.score >= 1000 {
+        let _ = write!(line, "WIN");
+    } else {
+        let _ = write!(line, "{:03}", status.score);
+    }
+    // Power-ups, packed from the left, each over its bar
+    let powers = PowerUp::ALL.iter().zip(status.powers);
+    let held = powers.filter_map(|(power, bar)| Some((*power, bar?)));
+    for (slot, (power, bar)) in held.enumerate() {
+        let x = POWER_X + slot as Coord * POWER_STEP;
+        let image = sprites::power_up(power);
+        fb.blit_keyed(
+            x,
+            middle - 10,
+            &image,
+            ImageTransform::FLIP_Y,
+            color::SPRITE_KEY,
+        );
+        fb.fill_rect(x, middle + 6, BAR_W, 3, fb.encode_argb(TRACK));
+        let fill = if bar <= BAR_LOW { LOW } else { ICON };
+        fb.fill_rect(x, middle + 6, bar, 3, fb.encode_argb(fill));
+    }
+
+    let big = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let center = Point::new(width / 2, middle);
+    let mut scaled = Scaled::new(&mut fb, status.score_percent, center);
+    let _ = Text::with_text_style(line.as_str(), Point::zero(), big, centered).draw(&mut scaled);
+
+    // Input in use, in red when it is the tilt sensor and that has gone
+    let label = match status.input {
+        InputMode::Tilt => Msg::InputTilt,
+        InputMode::Touch => Msg::InputTouch,
+        InputMode::Joystick => Msg::InputStick,
+        InputMode::Button => Msg::InputButton,
+        InputMode::Trigger => Msg::InputTrigger,
+    };
+    let label = lang::text(label);
+    let (label, color) = if status.toast {
+        (lang::text(Msg::CloseCall), TOAST)
+    } else if status.input == InputMode::Tilt && !status.sensor {
+        (label, Rgb565::RED)
+    } else {
+        (label, DIM)
+    };
+    let right = TextStyleBuilder::new()
+        .alignment(Alignment::Right)
+        .baseline(Baseline::Middle)
+        .build();
+    if let Some(seconds) = status.seconds {
+        line.clear();
+        let _ = write!(line, "{}:{:02}", seconds / 60, seconds % 60);
+        let color = if seconds <= LOW_S {
+            Rgb565::RED
+        } else {
+            Rgb565::WHITE
+        };
+        let timer = MonoTextStyle::new(&FONT_10X20, color);
+        let _ = Text::with_text_style(line.as_str(), Point::new(width - 32, middle), timer, right)
+            .draw(&mut fb);
+    } else {
+        let small = MonoTextStyle::new(&FONT_6X10, color);
+        let _ = Text::with_text_style(label, Point::new(width - 32, middle), small, right)
+            .draw(&mut fb);
+    }
+
+    // Battery outline with its nub, filled to the charge left
+    if let Some(percent) = status.battery {
+        let x = width - 26;
+        fb.fill_rect(x, middle - 5, 18, 10, icon);
+        fb.fill_rect(x + 18, middle - 2, 2, 4, icon);
+        fb.fill_rect(x + 1, middle - 4, 16, 8, band);
+        let charge = if percent <= LOW_PERCENT { LOW } else { CHARGED };
+        let fill = 14 * percent as u32 / 100;
+        fb.fill_rect(x + 2, middle - 3, fill.max(1), 6, fb.encode_argb(charge));
+    }
+
+    // Score link: green when on the access point, white while sending, red
+    // when it cannot join, grey while it tries
+    if let Some(wifi) = status.wifi {
+        let color = match wifi {
+            WifiStatus::Online => CHARGED,
+            WifiStatus::Sending => ICON,
+            WifiStatus::Offline => LOW,
+            WifiStatus::Joining | WifiStatus::Off => TRACK,
+        };
+        let argb = fb.encode_argb(color);
+        for bar in 0..3 {
+            let h = 2 + 2 * bar as u32;
+            fb.fill_rect(width - 9 + 3 * bar, 8 - h as Coord, 2, h, argb);
+        }
+    }
+
+    framebuffer::flush();
+}
@@ -0,0 +1,112 @@
+//! Hardware presence report, shown when the button is held at boot
+//!
+//! Probes each part the board carries and lists it with PASS or FAIL and
+//! what the probe read back: the SDRAM size, the MPU6050 and STMPE811 IDs,
+//! the LTDC refresh and pixel clock, and the bus clocks. `boot_report`
+//! only lists what failed; this shows what answered too, for telling a
+//! missing part from a broken one. A button press moves on to the game.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use crate::button;
+use crate::clock;
+use crate::color;
+use crate::config::*;
+use crate::display;
+use crate::error::HwError;
+use crate::fmt_buf::FmtBuf;
+use crate::input_events;
+use crate::lcd::LcdDriver;
+use crate::ltdc_check;
+use crate::mpu6050;
+use crate::sdram;
+use crate::touch;
+
+// The display font is 16x26: the part and its verdict on one line, the
+// detail under it one column in
+const LINE_HEIGHT: Coord = 26;
+const VERDICT_X: Coord = 10 * 16;
+const INDENT: Coord = 16;
+
+type Detail = FmtBuf<24>;
+
+// Probe everything, show the table and wait for a press
+pub fn run() {
+    let _ = display::set_background_color_rust(color::BLACK);
+    write_line(0, 0, "Hardware", color::WHITE);
+
+    let rows: [(&str, Result<Detail, HwError>); 5] = [
+        (
+            "SDRAM",
+            sdram::probe().map(|size| detail(format_args!("{}K", size / 1024))),
+        ),
+        (
+            "MPU6050",
+            mpu6050::probe().map(|id| detail(format_args!("id {:#04x}", id))),
+        ),
+        (
+            "Touch",
+            touch::probe().map(|id| detail(format_args!("id {:#06x}", id))),
+        ),
+        ("LTDC", ltdc()),
+        ("Clocks", clocks()),
+    ];
+    for (i, (part, result)) in rows.iter().enumerate() {
+        let y = LINE_HEIGHT * (2 * i as Coord + 1);
+        write_line(0, y, part, color::WHITE);
+        let (verdict, shade, text) = match result {
+            Ok(detail) => ("PASS", color::GREEN, detail.as_str()),
+            Err(error) => ("FAIL", color::RED, error.as_str()),
+        };
+        write_line(VERDICT_X, y, verdict, shade);
+        write_line(INDENT, y + LINE_HEIGHT, text, shade);
+    }
+
+    // The button is still down from asking for the report
+    while button::is_down() {}
+    input_events::clear();
+    while !input_events::take_button_press() {
+        clock::delay_ms(10);
+    }
+}
+
+// Refresh rate and pixel clock
+fn ltdc() -> Result<Detail, HwError> {
+    let hz = LcdDriver::attach().probe()?;
+    let pclk = ltdc_check::pixel_clock_hz();
+    Ok(detail(format_args!(
+        "{}Hz {}.{}M",
+        hz,
+        pclk / 1_000_000,
+        pclk / 100_000 % 10
+    )))
+}
+
+// SYSCLK/HCLK/PCLK1/PCLK2 in MHz; FAIL unless the core is at full speed
+fn clocks() -> Result<Detail, HwError> {
+    let f = clock::report();
+    if f.sysclk != clock::SYSCLK_HZ {
+        return Err(HwError::ClockNotReady);
+    }
+    let mhz = |hz: u32| hz / 1_000_000;
+    Ok(detail(format_args!(
+        "{}/{}/{}/{}M",
+        mhz(f.sysclk),
+        mhz(f.hclk),
+        mhz(f.pclk1),
+        mhz(f.pclk2)
+    )))
+}
+
+fn detail(args: core::fmt::Arguments) -> Detail {
+    let mut text = Detail::new();
+    let _ = text.write_fmt(args);
+    text
+}
+
+fn write_line(x: Coord, y: Coord, text: &str, color: u16) {
+    let mut line = FmtBuf::<24>::new();
+    let _ = line.write_str(text);
+    let _ = display::write_string_rust(x, y, line.as_cstr(), color, color::BLACK);
+}
@@ -1,21 +1,37 @@
 #![allow(dead_code)]
 
+use core::fmt;
+
 use stm32f4::stm32f429 as pac;
 
-// Simple delay function for I2C timing
-fn delay_us(us: u32) {
-    // Rough delay based on 168MHz system clock
-    let cycles = 168 * us;
-    for _ in 0..cycles {
-        cortex_m::asm::nop();
-    }
-}
+use core_logic::blackbox::Event;
+use core_logic::executor::yield_now;
+
+use crate::blackbox;
+use crate::clock::{self, delay_us, CYCLES_PER_US};
+use crate::error::HwError;
+use crate::log;
+use crate::profiler::{self, Phase};
+use crate::resources;
 
 const I2C_TIMEOUT: u32 = 100_000; // Timeout counter
+                                  // Longest an async transfer waits on any one step; at 100 kHz a byte takes
+                                  // 90 us, and other tasks may run for a frame between looks
+const ASYNC_TIMEOUT_US: u32 = 40_000;
+
+// An async I2C1 transfer is part way through; the blocking calls must not
+// start another on top of it
+static mut I2C1_ASYNC: bool = false;
+
+// True while an async transfer holds I2C1
+pub fn i2c1_held() -> bool {
+    unsafe { I2C1_ASYNC }
+}
 
 // Reset I2C1 peripheral (useful for recovery from stuck state)
 pub fn reset_i2c1() {
-    let dp = unsafe { pac::Peripherals::steal() };
+    log::warn!("resetting I2C1");
+    let dp = resources::pac();
 
     // Disable I2C1
     dp.I2C1.cr1.modify(|_, w| w.pe().disabled());
@@ -31,9 +47,27 @@ pub fn reset_i2c1() {
     init_i2c1_registers();
 }
 
+// Leave both buses idle ahead of a reset. A transfer part way through
+// belongs to a task that will not run again, so it is cut off with a stop
+// condition rather than waited for; then the peripherals are switched off.
+pub fn shutdown() {
+    let dp = resources::pac();
+    for i2c in [&*dp.I2C1, &*dp.I2C3] {
+        if i2c.sr2.read().busy().bit_is_set() {
+            i2c.cr1.modify(|_, w| w.stop().set_bit());
+            let mut timeout = I2C_TIMEOUT;
+            while i2c.sr2.read().busy().bit_is_set() && timeout > 0 {
+                timeout -= 1;
+            }
+        }
+        i2c.cr1.modify(|_, w| w.pe().disabled());
+    }
+    unsafe { I2C1_ASYNC = false };
+}
+
 // Configure I2C1 registers (extracted for reuse)
 fn init_i2c1_registers() {
-    let dp = unsafe { pac::Peripherals::steal() };
+    let dp = resources::pac();
 
     // Configure I2C1 for 100kHz
     // APB1 clock is 42MHz, for 100kHz I2C: CCR = 42MHz / (2 * 100kHz) = 210
@@ -46,8 +80,8 @@ fn init_i2c1_registers() {
 }
 
 // I2C1 on PB8 (SCL) and PB9 (SDA) for MPU6050
-pub fn init_i2c1() {
-    let dp = unsafe { pac::Peripherals::steal() };
+pub fn init_i2c1() -> Result<(), HwError> {
+    let dp = resources::pac();
 
     // Enable clocks
     dp.RCC.ahb1enr.modify(|_, w| w.gpioben().enabled());
@@ -89,10 +123,25 @@ pub fn init_i2c1() {
 
     // Configure I2C1 registers
     init_i2c1_registers();
+
+    // A line held low (a slave stuck mid-transfer, missing pull-ups) shows
+    // as a busy bus with nobody having sent a start
+    delay_us(10);
+    if dp.I2C1.sr2.read().busy().bit_is_set() {
+        return Err(HwError::BusStuck);
+    }
+    log::info!("I2C1 up at 100 kHz on PB8/PB9");
+    Ok(())
 }
 
 pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()> {
-    let dp = unsafe { pac::Peripherals::steal() };
+    // Fails rather than wait: the holder is a task that cannot run until
+    // this returns
+    if i2c1_held() {
+        return Err(());
+    }
+    let _i2c = profiler::scope(Phase::I2c);
+    let dp = resources::pac();
     let i2c = &dp.I2C1;
 
     // Wait until bus is free with timeout
@@ -100,6 +149,7 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
     while i2c.sr2.read().busy().bit_is_set() {
         timeout -= 1;
         if timeout == 0 {
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
     }
@@ -113,6 +163,7 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
     while !i2c.sr1.read().sb().bit_is_set() {
         timeout -= 1;
         if timeout == 0 {
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
     }
@@ -127,6 +178,7 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
         if timeout == 0 {
             // Generate stop condition on error
             i2c.cr1.modify(|_, w| w.stop().set_bit());
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
     }
@@ -141,6 +193,7 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
         timeout -= 1;
         if timeout == 0 {
             i2c.cr1.modify(|_, w| w.stop().set_bit());
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
     }
@@ -154,6 +207,7 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
         timeout -= 1;
         if timeout == 0 {
             i2c.cr1.modify(|_, w| w.stop().set_bit());
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
     }
@@ -166,7 +220,11 @@ pub fn i2c1_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()>
 }
 
 pub fn i2c1_read_reg(device_addr: u8, reg_addr: u8) -> Result<u8, ()> {
-    let dp = unsafe { pac::Peripherals::steal() };
+    if i2c1_held() {
+        return Err(());
+    }
+    let _i2c = profiler::scope(Phase::I2c);
+    let dp = resources::pac();
     let i2c = &dp.I2C1;
 
     // Wait until bus is free
@@ -223,7 +281,11 @@ pub fn i2c1_read_reg(device_addr: u8, reg_addr: u8) -> Result<u8, ()> {
 }
 
 pub fn i2c1_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Result<(), ()> {
-    let dp = unsafe { pac::Peripherals::steal() };
+    if i2c1_held() {
+        return Err(());
+    }
+    let _i2c = profiler::scope(Phase::I2c);
+    let dp = resources::pac();
     let i2c = &dp.I2C1;
 
     if buffer.is_empty() {
@@ -236,9 +298,7 @@ pub fn i2c1_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Resu
     if ltdc_enabled {
         dp.LTDC.gcr.modify(|_, w| w.ltdcen().clear_bit());
         // Small delay for LTDC to settle
-        for _ in 0..1000 {
-            cortex_m::asm::nop();
-        }
+        delay_us(10);
     }
 
     // Wait until bus is free with timeout
@@ -246,21 +306,21 @@ pub fn i2c1_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Resu
     while i2c.sr2.read().busy().bit_is_set() {
         timeout -= 1;
         if timeout == 0 {
-            // Debug: Check I2C status registers to understand why bus is stuck
-            let _sr1 = i2c.sr1.read().bits();
-            let _sr2 = i2c.sr2.read().bits();
+            // Check I2C status registers to understand why bus is stuck
+            let sr1 = i2c.sr1.read().bits();
+            let sr2 = i2c.sr2.read().bits();
+            log::warn!("I2C1 bus stuck busy, SR1 {:#06x} SR2 {:#06x}", sr1, sr2);
             // Bus is stuck busy - could be LTDC interference or I2C bus error
             // Try to reset I2C peripheral
             i2c.cr1.modify(|_, w| w.pe().clear_bit()); // Disable I2C
-            for _ in 0..1000 {
-                cortex_m::asm::nop();
-            } // Short delay
+            delay_us(10); // Short delay
             i2c.cr1.modify(|_, w| w.pe().set_bit()); // Re-enable I2C
                                                      // Re-enable LTDC before returning error
             if ltdc_enabled {
-                let dp = unsafe { pac::Peripherals::steal() };
+                let dp = resources::pac();
                 dp.LTDC.gcr.modify(|_, w| w.ltdcen().set_bit());
             }
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
         cortex_m::asm::nop(); // Prevent tight loop
@@ -276,9 +336,10 @@ pub fn i2c1_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Resu
         if timeout == 0 {
             // Re-enable LTDC before returning error
             if ltdc_enabled {
-                let dp = unsafe { pac::Peripherals::steal() };
+                let dp = resources::pac();
                 dp.LTDC.gcr.modify(|_, w| w.ltdcen().set_bit());
             }
+            log::debug!("I2C1 timeout, device {:#04x}", device_addr);
             return Err(());
         }
         cortex_m::asm::nop();
@@ -338,9 +399,399 @@ pub fn i2c1_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Resu
 
     // EXPERIMENTAL: Re-enable LTDC if it was previously enabled
     if ltdc_enabled {
-        let dp = unsafe { pac::Peripherals::steal() };
+        let dp = resources::pac();
         dp.LTDC.gcr.modify(|_, w| w.ltdcen().set_bit());
     }
 
     Ok(())
 }
+
+// Like `wait`, but gives the other tasks a turn between looks
+async fn wait_async(device_addr: u8, mut done: impl FnMut() -> bool) -> Result<(), ()> {
+    let start = clock::cycles();
+    while !done() {
+        if clock::cycles().wrapping_sub(start) > ASYNC_TIMEOUT_US * CYCLES_PER_US {
+            log::debug!("I2C1 async timeout, device {:#04x}", device_addr);
+            return Err(());
+        }
+        yield_now().await;
+    }
+    Ok(())
+}
+
+// `i2c1_read_bytes` as an async task step: every wait yields, so a read
+// overlaps the game's update and drawing instead of stalling them, and the
+// blocking calls fail until it is done. The
+// tail follows the reference manual's N > 2 sequence, which holds the bus
+// with the clock stretched while NACK and STOP are set, so a late look
+// loses nothing. Reads of one or two bytes take the blocking path.
+pub async fn i2c1_read_bytes_async(
+    device_addr: u8,
+    reg_addr: u8,
+    buffer: &mut [u8],
+) -> Result<(), ()> {
+    if buffer.len() < 3 || i2c1_held() {
+        return i2c1_read_bytes(device_addr, reg_addr, buffer);
+    }
+    let dp = resources::pac();
+    let i2c = &dp.I2C1;
+
+    unsafe { I2C1_ASYNC = true };
+    let mut result = wait_async(device_addr, || i2c.sr2.read().busy().bit_is_clear()).await;
+    if result.is_ok() {
+        result = read_async(i2c, device_addr, reg_addr, buffer).await;
+        if result.is_err() {
+            i2c.cr1.modify(|_, w| w.stop().set_bit());
+        }
+    }
+    i2c.cr1.modify(|_, w| w.ack().set_bit());
+    unsafe { I2C1_ASYNC = false };
+    noted(1, device_addr, result)
+}
+
+async fn read_async(
+    i2c: &Regs,
+    device_addr: u8,
+    reg_addr: u8,
+    buffer: &mut [u8],
+) -> Result<(), ()> {
+    let sr1_set = |flag: fn(&pac::i2c1::sr1::R) -> bool| move || flag(&i2c.sr1.read());
+
+    i2c.cr1.modify(|_, w| w.ack().set_bit().start().set_bit());
+    wait_async(device_addr, sr1_set(|r| r.sb().bit_is_set())).await?;
+    i2c.dr.write(|w| w.dr().bits(device_addr << 1));
+    wait_async(
+        device_addr,
+        sr1_set(|r| r.addr().bit_is_set() || r.af().bit_is_set()),
+    )
+    .await?;
+    if i2c.sr1.read().af().bit_is_set() {
+        i2c.sr1.modify(|_, w| w.af().clear_bit());
+        return Err(());
+    }
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+    i2c.dr.write(|w| w.dr().bits(reg_addr));
+    wait_async(device_addr, sr1_set(|r| r.btf().bit_is_set())).await?;
+
+    i2c.cr1.modify(|_, w| w.start().set_bit());
+    wait_async(device_addr, sr1_set(|r| r.sb().bit_is_set())).await?;
+    i2c.dr.write(|w| w.dr().bits((device_addr << 1) | 1));
+    wait_async(device_addr, sr1_set(|r| r.addr().bit_is_set())).await?;
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+
+    let (head, tail) = buffer.split_at_mut(buffer.len() - 3);
+    for byte in head {
+        wait_async(device_addr, sr1_set(|r| r.rx_ne().bit_is_set())).await?;
+        *byte = i2c.dr.read().dr().bits();
+    }
+    // Third from last in DR, second from last in the shift register
+    wait_async(device_addr, sr1_set(|r| r.btf().bit_is_set())).await?;
+    i2c.cr1.modify(|_, w| w.ack().clear_bit());
+    tail[0] = i2c.dr.read().dr().bits();
+    i2c.cr1.modify(|_, w| w.stop().set_bit());
+    tail[1] = i2c.dr.read().dr().bits();
+    wait_async(device_addr, sr1_set(|r| r.rx_ne().bit_is_set())).await?;
+    tail[2] = i2c.dr.read().dr().bits();
+    Ok(())
+}
+
+// I2C3 on PA8 (SCL) and PC9 (SDA), where the DISCO board wires the STMPE811
+// touch controller. Unlike the I2C1 routines above, every wait is bounded
+// and a NACKed address is reported, so probing an absent device is safe.
+pub fn init_i2c3() {
+    let dp = resources::pac();
+
+    dp.RCC
+        .ahb1enr
+        .modify(|_, w| w.gpioaen().enabled().gpiocen().enabled());
+    dp.RCC.apb1enr.modify(|_, w| w.i2c3en().enabled());
+
+    // AF4, open drain with pull-ups, as for I2C1
+    dp.GPIOA.moder.modify(|_, w| w.moder8().alternate());
+    dp.GPIOA.afrh.modify(|_, w| w.afrh8().bits(4));
+    dp.GPIOA.otyper.modify(|_, w| w.ot8().open_drain());
+    dp.GPIOA.pupdr.modify(|_, w| w.pupdr8().pull_up());
+    dp.GPIOA.ospeedr.modify(|_, w| w.ospeedr8().medium_speed());
+    dp.GPIOC.moder.modify(|_, w| w.moder9().alternate());
+    dp.GPIOC.afrh.modify(|_, w| w.afrh9().bits(4));
+    dp.GPIOC.otyper.modify(|_, w| w.ot9().open_drain());
+    dp.GPIOC.pupdr.modify(|_, w| w.pupdr9().pull_up());
+    dp.GPIOC.ospeedr.modify(|_, w| w.ospeedr9().medium_speed());
+
+    dp.RCC.apb1rstr.modify(|_, w| w.i2c3rst().set_bit());
+    delay_us(10);
+    dp.RCC.apb1rstr.modify(|_, w| w.i2c3rst().clear_bit());
+    delay_us(100);
+
+    // Same 100 kHz timing as I2C1, both are on APB1
+    let i2c = &dp.I2C3;
+    i2c.cr2.modify(|_, w| unsafe { w.freq().bits(42) });
+    i2c.ccr.modify(|_, w| unsafe { w.ccr().bits(210) });
+    i2c.trise.modify(|_, w| w.trise().bits(43));
+    i2c.cr1.modify(|_, w| w.pe().enabled());
+    log::info!("I2C3 up at 100 kHz on PA8/PC9");
+}
+
+type Regs = pac::i2c1::RegisterBlock;
+
+/// A device address: the usual 7 bits, or 10
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Address {
+    Seven(u8),
+    Ten(u16),
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Seven(address) => write!(f, "{:#04x}", address),
+            Address::Ten(address) => write!(f, "{:#05x} (10-bit)", address),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Bus {
+    // PB8/PB9, the MPU6050
+    I2c1,
+    // PA8/PC9, the STMPE811 touch controller
+    I2c3,
+}
+
+impl Bus {
+    pub fn name(self) -> &'static str {
+        match self {
+            Bus::I2c1 => "I2C1",
+            Bus::I2c3 => "I2C3",
+        }
+    }
+
+    fn regs(self) -> &'static Regs {
+        let dp = resources::pac();
+        match self {
+            Bus::I2c1 => &dp.I2C1,
+            Bus::I2c3 => &dp.I2C3,
+        }
+    }
+
+    // Whether a transfer can start here now: the peripheral is on and no
+    // async transfer holds it
+    fn ready(self) -> Result<&'static Regs, HwError> {
+        let i2c = self.regs();
+        if i2c.cr1.read().pe().bit_is_clear() {
+            return Err(HwError::ClockNotReady);
+        }
+        if self == Bus::I2c1 && i2c1_held() {
+            return Err(HwError::Timeout);
+        }
+        Ok(i2c)
+    }
+}
+
+fn wait(address: Address, mut done: impl FnMut() -> bool) -> Result<(), ()> {
+    let mut timeout = I2C_TIMEOUT;
+    while !done() {
+        timeout -= 1;
+        if timeout == 0 {
+            log::debug!("I2C timeout, device {}", address);
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
+// A NACK, cleared, with the bus released
+fn nacked(i2c: &Regs) -> bool {
+    if i2c.sr1.read().af().bit_is_clear() {
+        return false;
+    }
+    i2c.sr1.modify(|_, w| w.af().clear_bit());
+    i2c.cr1.modify(|_, w| w.stop().set_bit());
+    true
+}
+
+// Start (or repeated start) and send the address; NACK releases the bus.
+// A 10-bit address goes as a header carrying its top two bits, then the
+// low byte. Reading from one takes a write first: the repeated start for
+// the read sends only the header (RM0090, 10-bit master receiver).
+fn start(i2c: &Regs, address: Address, read: bool) -> Result<(), ()> {
+    i2c.cr1.modify(|_, w| w.start().set_bit());
+    wait(address, || i2c.sr1.read().sb().bit_is_set())?;
+    match address {
+        Address::Seven(address) => i2c.dr.write(|w| w.dr().bits((address << 1) | read as u8)),
+        Address::Ten(bits) => {
+            let header = 0xF0 | ((bits >> 7) as u8 & 0x06);
+            i2c.dr.write(|w| w.dr().bits(header | read as u8));
+            if !read {
+                wait(address, || {
+                    let sr1 = i2c.sr1.read();
+                    sr1.add10().bit_is_set() || sr1.af().bit_is_set()
+                })?;
+                if nacked(i2c) {
+                    return Err(());
+                }
+                i2c.dr.write(|w| w.dr().bits(bits as u8));
+            }
+        }
+    }
+    wait(address, || {
+        let sr1 = i2c.sr1.read();
+        sr1.addr().bit_is_set() || sr1.af().bit_is_set()
+    })?;
+    if nacked(i2c) {
+        return Err(());
+    }
+    Ok(())
+}
+
+fn write_reg_on(i2c: &Regs, address: Address, reg_addr: u8, data: u8) -> Result<(), ()> {
+    let _i2c = profiler::scope(Phase::I2c);
+    wait(address, || i2c.sr2.read().busy().bit_is_clear())?;
+    start(i2c, address, false)?;
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+    for byte in [reg_addr, data] {
+        i2c.dr.write(|w| w.dr().bits(byte));
+        wait(address, || i2c.sr1.read().tx_e().bit_is_set())?;
+    }
+    wait(address, || i2c.sr1.read().btf().bit_is_set())?;
+    i2c.cr1.modify(|_, w| w.stop().set_bit());
+    Ok(())
+}
+
+fn read_bytes_on(i2c: &Regs, address: Address, reg_addr: u8, buffer: &mut [u8]) -> Result<(), ()> {
+    let _i2c = profiler::scope(Phase::I2c);
+    if buffer.is_empty() {
+        return Ok(());
+    }
+
+    wait(address, || i2c.sr2.read().busy().bit_is_clear())?;
+    start(i2c, address, false)?;
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+    i2c.dr.write(|w| w.dr().bits(reg_addr));
+    wait(address, || i2c.sr1.read().btf().bit_is_set())?;
+
+    i2c.cr1.modify(|_, w| w.ack().set_bit());
+    start(i2c, address, true)?;
+    let last = buffer.len() - 1;
+    if last == 0 {
+        // NACK and stop have to be set before ADDR is cleared
+        i2c.cr1.modify(|_, w| w.ack().clear_bit());
+    }
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+    let mut result = Ok(());
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        if i == last {
+            i2c.cr1.modify(|_, w| w.ack().clear_bit().stop().set_bit());
+        }
+        result = wait(address, || i2c.sr1.read().rx_ne().bit_is_set());
+        if result.is_err() {
+            i2c.cr1.modify(|_, w| w.stop().set_bit());
+            break;
+        }
+        *byte = i2c.dr.read().dr().bits();
+    }
+    i2c.cr1.modify(|_, w| w.ack().set_bit());
+    result
+}
+
+pub fn i2c3_write_reg(device_addr: u8, reg_addr: u8, data: u8) -> Result<(), ()> {
+    let dp = resources::pac();
+    write_reg_on(&dp.I2C3, Address::Seven(device_addr), reg_addr, data)
+}
+
+pub fn i2c3_read_bytes(device_addr: u8, reg_addr: u8, buffer: &mut [u8]) -> Result<(), ()> {
+    let dp = resources::pac();
+    let result = read_bytes_on(&dp.I2C3, Address::Seven(device_addr), reg_addr, buffer);
+    noted(3, device_addr, result)
+}
+
+// `result`, a failure first put in the black box. Only the reads the game
+// keeps making go through here: the sensor sample and the touch poll.
+fn noted<T>(bus: u8, address: u8, result: Result<T, ()>) -> Result<T, ()> {
+    if result.is_err() {
+        blackbox::record(Event::I2c { bus, address });
+    }
+    result
+}
+
+pub fn i2c3_read_reg(device_addr: u8, reg_addr: u8) -> Result<u8, ()> {
+    let mut byte = [0];
+    i2c3_read_bytes(device_addr, reg_addr, &mut byte)?;
+    Ok(byte[0])
+}
+
+// Register access on either bus at any address, 10-bit included, with
+// every wait bounded
+pub fn write_reg(bus: Bus, address: Address, reg_addr: u8, data: u8) -> Result<(), ()> {
+    let i2c = bus.ready().map_err(|_| ())?;
+    write_reg_on(i2c, address, reg_addr, data)
+}
+
+pub fn read_bytes(bus: Bus, address: Address, reg_addr: u8, buffer: &mut [u8]) -> Result<(), ()> {
+    let i2c = bus.ready().map_err(|_| ())?;
+    read_bytes_on(i2c, address, reg_addr, buffer)
+}
+
+// The 7-bit addresses a scan tries; the rest are reserved
+pub const SCAN_FIRST: u8 = 0x08;
+pub const SCAN_LAST: u8 = 0x77;
+
+/// Which 7-bit addresses answered a scan, a bit each
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct DeviceMap(pub u128);
+
+impl DeviceMap {
+    pub fn contains(&self, address: u8) -> bool {
+        address < 128 && self.0 & (1 << address) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..128).filter(|&address| self.contains(address))
+    }
+}
+
+// The addresses in hex, "68 41", or "none"
+impl fmt::Display for DeviceMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+        for (i, address) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", address)?;
+        }
+        Ok(())
+    }
+}
+
+// Whether a device acknowledges `address` on `bus`; nothing is written
+// past the address
+pub fn probe(bus: Bus, address: Address) -> Result<bool, HwError> {
+    let i2c = bus.ready()?;
+    let _i2c = profiler::scope(Phase::I2c);
+    wait(address, || i2c.sr2.read().busy().bit_is_clear()).map_err(|_| HwError::BusStuck)?;
+    if start(i2c, address, false).is_err() {
+        return Ok(false);
+    }
+    let _ = i2c.sr2.read(); // Clear ADDR flag
+    i2c.cr1.modify(|_, w| w.stop().set_bit());
+    Ok(true)
+}
+
+// Address every device from SCAN_FIRST to SCAN_LAST on `bus` and note
+// which answer. A miswired part shows as missing or at the wrong address.
+pub fn scan(bus: Bus) -> Result<DeviceMap, HwError> {
+    let mut map = DeviceMap::default();
+    for address in SCAN_FIRST..=SCAN_LAST {
+        if probe(bus, Address::Seven(address))? {
+            map.0 |= 1 << address;
+        }
+    }
+    Ok(map)
+}
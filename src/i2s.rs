@@ -0,0 +1,155 @@
+//! I2S audio output for an external DAC (PCM5102A-style, no control port)
+//!
+//! SPI2 runs in I2S master transmit mode, fed by DMA1 stream 4 in double
+//! buffer mode. Each time DMA finishes one half it switches to the other by
+//! itself and the transfer-complete interrupt refills the idle half from the
+//! audio mixer, so playback never waits on the game loop.
+//!
+//! Pins (expansion header): PB12 = WS, PB13 = CK, PB15 = SD, all AF5.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::audio;
+use crate::resources;
+
+// Output rate; PLLI2S below is solved for this value
+pub const OUTPUT_RATE: u32 = 16_000;
+
+// Stereo frames per DMA half (16 ms at 16 kHz)
+const FRAMES: usize = 256;
+
+static mut BUFFERS: [[i16; FRAMES * 2]; 2] = [[0; FRAMES * 2]; 2];
+
+// PLLI2S: 1 MHz VCO input (HSE / PLLM) * 213 / 2 = 106.5 MHz I2S clock.
+// 16-bit stereo without MCLK: Fs = I2SCLK / (32 * (2 * DIV + ODD)),
+// 106.5 MHz / (32 * 208) = 16.0 kHz
+const PLLI2S_N: u16 = 213;
+const PLLI2S_R: u8 = 2;
+const I2S_DIV: u8 = 104;
+
+pub fn init() {
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+
+    // I2S kernel clock from PLLI2S
+    rcc.plli2scfgr
+        .modify(|_, w| unsafe { w.plli2sn().bits(PLLI2S_N).plli2sr().bits(PLLI2S_R) });
+    rcc.cr.modify(|_, w| w.plli2son().on());
+    while rcc.cr.read().plli2srdy().is_not_ready() {}
+
+    rcc.ahb1enr
+        .modify(|_, w| w.gpioben().enabled().dma1en().enabled());
+    rcc.apb1enr.modify(|_, w| w.spi2en().enabled());
+
+    // PB12/PB13/PB15 to AF5, fast push-pull
+    let gpiob = &dp.GPIOB;
+    for pin in [12u32, 13, 15] {
+        gpiob.moder.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+        });
+        gpiob.ospeedr.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+        });
+        let idx = pin - 8;
+        gpiob
+            .afrh
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0xF << (idx * 4))) | (5 << (idx * 4))) });
+    }
+
+    // Philips I2S, 16-bit data in 16-bit channel, master transmit
+    let spi = &dp.SPI2;
+    spi.i2scfgr.write(|w| {
+        w.i2smod()
+            .i2smode()
+            .i2scfg()
+            .master_tx()
+            .i2sstd()
+            .philips()
+            .ckpol()
+            .idle_low()
+            .datlen()
+            .sixteen_bit()
+            .chlen()
+            .sixteen_bit()
+    });
+    spi.i2spr
+        .write(|w| unsafe { w.i2sdiv().bits(I2S_DIV).odd().even().mckoe().disabled() });
+    spi.cr2.modify(|_, w| w.txdmaen().enabled());
+
+    // Prime both halves so the first buffer out is already mixed audio
+    unsafe {
+        audio::mix(&mut BUFFERS[0], OUTPUT_RATE);
+        audio::mix(&mut BUFFERS[1], OUTPUT_RATE);
+    }
+
+    // DMA1 stream 4 channel 0 = SPI2_TX, circular double buffer
+    let stream = &dp.DMA1.st[4];
+    stream.cr.modify(|_, w| w.en().disabled());
+    while stream.cr.read().en().is_enabled() {}
+    stream
+        .par
+        .write(|w| unsafe { w.pa().bits(&spi.dr as *const _ as u32) });
+    unsafe {
+        stream
+            .m0ar
+            .write(|w| w.m0a().bits(BUFFERS[0].as_ptr() as u32));
+        stream
+            .m1ar
+            .write(|w| w.m1a().bits(BUFFERS[1].as_ptr() as u32));
+    }
+    stream.ndtr.write(|w| w.ndt().bits((FRAMES * 2) as u16));
+    stream.cr.write(|w| {
+        w.chsel()
+            .bits(0)
+            .dbm()
+            .enabled()
+            .ct()
+            .memory0()
+            .pl()
+            .high()
+            .msize()
+            .bits16()
+            .psize()
+            .bits16()
+            .minc()
+            .incremented()
+            .pinc()
+            .fixed()
+            .circ()
+            .enabled()
+            .dir()
+            .memory_to_peripheral()
+            .tcie()
+            .enabled()
+    });
+    stream.cr.modify(|_, w| w.en().enabled());
+
+    spi.i2scfgr.modify(|_, w| w.i2se().enabled());
+
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::DMA1_STREAM4) };
+}
+
+// Stop output and leave the DAC silent
+pub fn stop() {
+    let dp = resources::pac();
+    cortex_m::peripheral::NVIC::mask(pac::Interrupt::DMA1_STREAM4);
+    dp.SPI2.i2scfgr.modify(|_, w| w.i2se().disabled());
+    dp.DMA1.st[4].cr.modify(|_, w| w.en().disabled());
+}
+
+#[interrupt]
+fn DMA1_STREAM4() {
+    let dp = resources::pac();
+    dp.DMA1.hifcr.write(|w| w.ctcif4().set_bit());
+
+    // CT names the half DMA is reading now; refill the one it just left
+    let idle = if dp.DMA1.st[4].cr.read().ct().is_memory0() {
+        1
+    } else {
+        0
+    };
+    unsafe { audio::mix(&mut BUFFERS[idle], OUTPUT_RATE) };
+}
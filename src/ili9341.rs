@@ -0,0 +1,450 @@
+//! ILI9341 panel driver on the DISCO board
+//!
+//! Everything sent to the panel controller goes through here: the init
+//! sequence, sleep, gamma, the orientation of GRAM writes, the switch between
+//! the LTDC RGB bus and SPI rendering, and the register reads behind the
+//! self test. Commands go out on SPI5 with CS on PC2 and D/CX on PD13.
+#![allow(static_mut_refs)]
+
+use core_logic::geometry::Orientation;
+pub use core_logic::settings::GammaProfile;
+use stm32f4::stm32f429 as pac;
+
+use crate::config::ORIENTATION as ORIENTATION_AT_BOOT;
+use crate::error::HwError;
+use crate::log;
+use crate::resources;
+use crate::spi::{Baud, DmaWrite, Spi5, SpiBus};
+
+const ILI_PWR_CTL_1: u8 = 0xc0;
+const ILI_PWR_CTL_2: u8 = 0xc1;
+const ILI_VCOM_CTL_1: u8 = 0xc5;
+const ILI_VCOM_CTL_2: u8 = 0xc7;
+const ILI_MEM_ACC_CTL: u8 = 0x36;
+const ILI_RGB_IFC_CTL: u8 = 0xb0;
+const ILI_IFC_CTL: u8 = 0xf6;
+const ILI_GAMMA_SET: u8 = 0x26;
+const ILI_POS_GAMMA: u8 = 0xe0;
+const ILI_NEG_GAMMA: u8 = 0xe1;
+const ILI_SLEEP_OUT: u8 = 0x11;
+const ILI_INVERSION_OFF: u8 = 0x20;
+const ILI_INVERSION_ON: u8 = 0x21;
+const ILI_DISP_ON: u8 = 0x29;
+const ILI_SLEEP_IN: u8 = 0x10;
+const ILI_DISP_OFF: u8 = 0x28;
+const ILI_READ_DISP_ID: u8 = 0x04;
+const ILI_READ_DISP_STATUS: u8 = 0x09;
+const ILI_COL_ADDR_SET: u8 = 0x2a;
+const ILI_PAGE_ADDR_SET: u8 = 0x2b;
+const ILI_MEM_WRITE: u8 = 0x2c;
+const ILI_PIXEL_FORMAT: u8 = 0x3a;
+
+// RDDST bits; after reset the pixel format field reads 18 bit (0b110)
+const STATUS_BOOSTER_ON: u32 = 1 << 31;
+const STATUS_SLEEP_OUT: u32 = 1 << 17;
+const STATUS_DISPLAY_ON: u32 = 1 << 10;
+const STATUS_PIXEL_FORMAT: u32 = 0b111 << 20;
+
+// MADCTL bits: row and column address order, row/column exchange, and BGR
+// color order, which the DISCO panel is wired for
+const MADCTL_MY: u8 = 0x80;
+const MADCTL_MX: u8 = 0x40;
+const MADCTL_MV: u8 = 0x20;
+const MADCTL_BGR: u8 = 0x08;
+
+// Result of display_selftest, for the display health check
+static mut RESPONDED: bool = false;
+// How GRAM writes are turned, and whether GRAM is what the panel shows
+static mut ORIENTATION: Orientation = ORIENTATION_AT_BOOT;
+static mut SPI_MODE: bool = false;
+
+// RGB interface settings sent at init; ltdc_check compares them with the LTDC
+// RGB_IFC_CTL: bypass memory, DE mode, VSPL/HSPL/DPL/EPL all 0
+pub const RGB_IFC_CTL_VALUE: u8 = 0xC0;
+// IFC_CTL: WEMODE, no endian swap, DM = RGB interface, RM = RGB interface
+pub const IFC_CTL_VALUE: [u8; 3] = [0x01, 0x00, 0x06];
+
+// SPI rendering: GRAM written over SPI (RM=0) and shown on the panel's own
+// clock (DM=00), 16 bit pixels; COLMOD 0x66 is the reset value for RGB mode
+const IFC_CTL_SPI: [u8; 3] = [0x01, 0x00, 0x00];
+const PIXEL_FORMAT_SPI: u8 = 0x55;
+const PIXEL_FORMAT_RGB: u8 = 0x66;
+
+// Parameters of PGAMCTRL and NGAMCTRL
+pub const GAMMA_LEN: usize = 15;
+
+/// Positive and negative gamma correction tables, uploaded together
+pub struct GammaTables {
+    pub pos: [u8; GAMMA_LEN],
+    pub neg: [u8; GAMMA_LEN],
+}
+
+// Gamma curves; statics so DMA can read them straight from flash
+// The ST demo's curve, sent at init
+static STANDARD: GammaTables = GammaTables {
+    pos: [
+        0x0F, 0x29, 0x24, 0x0C, 0x0E, 0x09, 0x4E, 0x78, 0x3C, 0x09, 0x13, 0x05, 0x17, 0x11, 0x00,
+    ],
+    neg: [
+        0x00, 0x16, 0x1B, 0x04, 0x11, 0x07, 0x31, 0x33, 0x42, 0x05, 0x0C, 0x0A, 0x28, 0x2F, 0x0F,
+    ],
+};
+// Steeper midtones, for more contrast
+static VIVID: GammaTables = GammaTables {
+    pos: [
+        0x0F, 0x31, 0x2B, 0x0C, 0x0E, 0x08, 0x4E, 0xF1, 0x37, 0x07, 0x10, 0x03, 0x0E, 0x09, 0x00,
+    ],
+    neg: [
+        0x00, 0x0E, 0x14, 0x03, 0x11, 0x07, 0x31, 0xC1, 0x48, 0x08, 0x0F, 0x0C, 0x31, 0x36, 0x0F,
+    ],
+};
+// Midtones lifted, for a brighter picture at low backlight
+static SOFT: GammaTables = GammaTables {
+    pos: [
+        0x0F, 0x29, 0x24, 0x0C, 0x0E, 0x09, 0x4E, 0x56, 0x3C, 0x09, 0x13, 0x05, 0x17, 0x11, 0x00,
+    ],
+    neg: [
+        0x00, 0x16, 0x1B, 0x04, 0x11, 0x07, 0x31, 0x55, 0x42, 0x05, 0x0C, 0x0A, 0x28, 0x2F, 0x0F,
+    ],
+};
+
+// The tables behind each of the settings' gamma curves
+fn tables(profile: GammaProfile) -> &'static GammaTables {
+    match profile {
+        GammaProfile::Standard => &STANDARD,
+        GammaProfile::Vivid => &VIVID,
+        GammaProfile::Soft => &SOFT,
+    }
+}
+
+// Pins: PC2=CS, PD13=D/CX; SCK/MISO/MOSI belong to spi::Spi5
+
+// Writes run at fpclk/4 (21 MHz). Reads are specified at 6.6 MHz at most, so
+// they drop to fpclk/16 (5.25 MHz)
+const WRITE_BAUD: Baud = Baud::Div4;
+const READ_BAUD: Baud = Baud::Div16;
+
+fn select() {
+    let gpioc = unsafe { &*pac::GPIOC::ptr() };
+    gpioc.bsrr.write(|w| w.br2().set_bit());
+}
+fn deselect() {
+    let gpioc = unsafe { &*pac::GPIOC::ptr() };
+    gpioc.bsrr.write(|w| w.bs2().set_bit());
+}
+fn set_data() {
+    let gpiod = unsafe { &*pac::GPIOD::ptr() };
+    gpiod.bsrr.write(|w| w.bs13().set_bit());
+}
+fn set_cmd() {
+    let gpiod = unsafe { &*pac::GPIOD::ptr() };
+    gpiod.bsrr.write(|w| w.br13().set_bit());
+}
+
+// One chip-select cycle on the panel: a command byte, then data. CS is
+// released when the transaction is dropped, after any DMA data has gone out.
+struct Transaction {
+    spi: Spi5,
+    dma: Option<DmaWrite>,
+}
+
+impl Transaction {
+    fn begin(cmd: u8) -> Self {
+        let mut spi = Spi5::attach();
+        select();
+        set_cmd();
+        spi.write(&[cmd]);
+        set_data();
+        Transaction { spi, dma: None }
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        self.spi.write(data);
+    }
+
+    // Hand `data` to DMA and return; the CPU is free until the drop
+    fn write_dma(&mut self, data: &'static [u8]) {
+        self.dma = Some(self.spi.write_dma(data));
+    }
+
+    // The serial interface puts one dummy clock before read data, so the
+    // reply comes back shifted left a bit
+    fn read(&mut self, buf: &mut [u8]) {
+        self.spi.set_baud(READ_BAUD);
+        self.spi.read(buf);
+        self.spi.set_baud(WRITE_BAUD);
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        self.dma = None;
+        deselect();
+    }
+}
+
+fn lcd_read(cmd: u8, buf: &mut [u8]) {
+    Transaction::begin(cmd).read(buf);
+}
+
+fn lcd_command(cmd: u8, delay_ms: u16, data: &[u8]) {
+    Transaction::begin(cmd).write(data);
+    if delay_ms != 0 {
+        crate::clock::delay_ms(delay_ms as u32);
+    }
+}
+
+// Command whose parameters go out by DMA; for the longer constant tables
+fn lcd_command_dma(cmd: u8, data: &'static [u8]) {
+    Transaction::begin(cmd).write_dma(data);
+}
+
+// Clocks, pins and SPI5; safe to call again
+fn setup_bus() {
+    // Clocks for the control pins; Spi5 handles GPIOF and SPI5
+    let dp = resources::pac();
+    dp.RCC
+        .ahb1enr
+        .modify(|_, w| w.gpiocen().enabled().gpioden().enabled());
+
+    let gpioc = unsafe { &*pac::GPIOC::ptr() };
+    let gpiod = unsafe { &*pac::GPIOD::ptr() };
+
+    // PC2 output
+    gpioc
+        .moder
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << (2 * 2))) | (0b01 << (2 * 2))) });
+    gpioc
+        .ospeedr
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << (2 * 2))) | (0b10 << (2 * 2))) });
+    // PD13 output
+    gpiod
+        .moder
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << (13 * 2))) | (0b01 << (13 * 2))) });
+    gpiod
+        .ospeedr
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << (13 * 2))) | (0b10 << (13 * 2))) });
+
+    // Idle lines and CS high before the bus comes up
+    deselect();
+    set_data();
+    Spi5::init(WRITE_BAUD);
+}
+
+// Send the init sequence, then read the status back to check that the
+// panel took it
+pub fn init() -> Result<(), HwError> {
+    setup_bus();
+
+    // Initialization sequence (exactly as the C demo)
+    lcd_command(ILI_PWR_CTL_1, 0, &[0x10]);
+    lcd_command(ILI_PWR_CTL_2, 0, &[0x10]);
+    lcd_command(ILI_VCOM_CTL_1, 0, &[0x45, 0x15]);
+    lcd_command(ILI_VCOM_CTL_2, 0, &[0x90]);
+    unsafe {
+        SPI_MODE = false;
+    }
+    lcd_command(ILI_MEM_ACC_CTL, 0, &[madctl()]);
+    // RGB interface control and interface control
+    lcd_command(ILI_RGB_IFC_CTL, 0, &[RGB_IFC_CTL_VALUE]);
+    lcd_command(ILI_IFC_CTL, 0, &IFC_CTL_VALUE);
+    set_gamma(GammaProfile::Standard);
+    lcd_command(ILI_SLEEP_OUT, 5, &[]);
+    lcd_command(ILI_DISP_ON, 0, &[]);
+
+    let status = read_display_status();
+    let awake = STATUS_SLEEP_OUT | STATUS_DISPLAY_ON;
+    if status == u32::MAX || status & awake != awake {
+        return Err(HwError::NoResponse);
+    }
+    Ok(())
+}
+
+// Blank the panel and put it in sleep mode (RGB input is ignored meanwhile)
+pub fn sleep() {
+    lcd_command(ILI_DISP_OFF, 0, &[]);
+    lcd_command(ILI_SLEEP_IN, 5, &[]);
+}
+
+// Leave sleep mode; the datasheet asks for 120 ms before the next command
+pub fn wake() {
+    lcd_command(ILI_SLEEP_OUT, 120, &[]);
+    lcd_command(ILI_DISP_ON, 0, &[]);
+}
+
+// Switch to one of the built-in gamma curves; takes effect on the next
+// refresh, in RGB and SPI mode alike
+pub fn set_gamma(profile: GammaProfile) {
+    let tables = tables(profile);
+    lcd_command(ILI_GAMMA_SET, 0, &[0x01]);
+    lcd_command_dma(ILI_POS_GAMMA, &tables.pos);
+    lcd_command_dma(ILI_NEG_GAMMA, &tables.neg);
+}
+
+// Upload custom positive and negative gamma tables over gamma curve 1, for
+// tuning a panel from the shell
+pub fn upload_gamma(tables: &GammaTables) {
+    lcd_command(ILI_GAMMA_SET, 0, &[0x01]);
+    lcd_command(ILI_POS_GAMMA, 0, &tables.pos);
+    lcd_command(ILI_NEG_GAMMA, 0, &tables.neg);
+}
+
+// Display inversion on or off
+pub fn set_inversion(on: bool) {
+    lcd_command(
+        if on {
+            ILI_INVERSION_ON
+        } else {
+            ILI_INVERSION_OFF
+        },
+        0,
+        &[],
+    );
+}
+
+// MADCTL for the current mode. The RGB bus bypasses GRAM and the LTDC
+// picture is already turned in the framebuffer, so there it stays native;
+// GRAM writes are turned by the panel instead.
+fn madctl() -> u8 {
+    if !unsafe { SPI_MODE } {
+        return MADCTL_BGR;
+    }
+    MADCTL_BGR
+        | match unsafe { ORIENTATION } {
+            Orientation::Portrait => 0,
+            Orientation::Landscape => MADCTL_MV | MADCTL_MY,
+            Orientation::PortraitFlipped => MADCTL_MY | MADCTL_MX,
+            Orientation::LandscapeFlipped => MADCTL_MV | MADCTL_MX,
+        }
+}
+
+// Turn GRAM writes to `orientation`, so write_pixels takes game
+// coordinates; in RGB mode it is kept for the next enter_spi_mode
+pub fn set_orientation(orientation: Orientation) {
+    unsafe {
+        ORIENTATION = orientation;
+    }
+    lcd_command(ILI_MEM_ACC_CTL, 0, &[madctl()]);
+}
+
+pub fn orientation() -> Orientation {
+    unsafe { ORIENTATION }
+}
+
+// Take pixels from GRAM written over SPI instead of the LTDC RGB bus
+pub fn enter_spi_mode() {
+    unsafe {
+        SPI_MODE = true;
+    }
+    lcd_command(ILI_MEM_ACC_CTL, 0, &[madctl()]);
+    lcd_command(ILI_PIXEL_FORMAT, 0, &[PIXEL_FORMAT_SPI]);
+    lcd_command(ILI_IFC_CTL, 0, &IFC_CTL_SPI);
+    // Bypass off, so the panel refreshes from GRAM
+    lcd_command(ILI_RGB_IFC_CTL, 0, &[RGB_IFC_CTL_VALUE & 0x7F]);
+}
+
+// Back to the RGB interface settings init sends
+pub fn enter_rgb_mode() {
+    unsafe {
+        SPI_MODE = false;
+    }
+    lcd_command(ILI_MEM_ACC_CTL, 0, &[madctl()]);
+    lcd_command(ILI_PIXEL_FORMAT, 0, &[PIXEL_FORMAT_RGB]);
+    lcd_command(ILI_RGB_IFC_CTL, 0, &[RGB_IFC_CTL_VALUE]);
+    lcd_command(ILI_IFC_CTL, 0, &IFC_CTL_VALUE);
+}
+
+// Write RGB565 pixels into GRAM over the w x h window at (x, y), row by row,
+// in the coordinates set_orientation gives. Only meaningful after
+// enter_spi_mode; the caller clips to the screen.
+pub fn write_pixels(x: u16, y: u16, w: u16, h: u16, pixels: impl Iterator<Item = u16>) {
+    if w == 0 || h == 0 {
+        return;
+    }
+    let (x1, y1) = (x + w - 1, y + h - 1);
+    lcd_command(
+        ILI_COL_ADDR_SET,
+        0,
+        &[(x >> 8) as u8, x as u8, (x1 >> 8) as u8, x1 as u8],
+    );
+    lcd_command(
+        ILI_PAGE_ADDR_SET,
+        0,
+        &[(y >> 8) as u8, y as u8, (y1 >> 8) as u8, y1 as u8],
+    );
+
+    // Batch the bytes so the SPI transmit buffer stays full
+    let mut tx = Transaction::begin(ILI_MEM_WRITE);
+    let mut chunk = [0u8; 64];
+    let mut len = 0;
+    for pixel in pixels.take(w as usize * h as usize) {
+        chunk[len..len + 2].copy_from_slice(&pixel.to_be_bytes());
+        len += 2;
+        if len == chunk.len() {
+            tx.write(&chunk);
+            len = 0;
+        }
+    }
+    tx.write(&chunk[..len]);
+}
+
+// RDDID: manufacturer, version and module ID bytes, 24 bits
+pub fn read_display_id() -> u32 {
+    let mut raw = [0u8; 4];
+    lcd_read(ILI_READ_DISP_ID, &mut raw);
+    (u32::from_be_bytes(raw) >> 7) & 0x00FF_FFFF
+}
+
+// RDDST: the 32-bit display status word
+pub fn read_display_status() -> u32 {
+    let mut raw = [0u8; 5];
+    lcd_read(ILI_READ_DISP_STATUS, &mut raw);
+    let bits = raw.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    (bits >> 7) as u32
+}
+
+// Check that the controller answers on SPI before LTDC starts driving it.
+// A missing or unpowered panel leaves MISO pulled up (all ones); a shorted
+// line reads all zeros. Either way the pixel format field, 18 bit after
+// reset, cannot be right.
+pub fn display_selftest() -> Result<(), HwError> {
+    setup_bus();
+    let id = read_display_id();
+    let status = read_display_status();
+    let format = (status & STATUS_PIXEL_FORMAT) >> 20;
+    let responded = status != 0 && status != u32::MAX && format != 0 && format != 0b111;
+    unsafe { RESPONDED = responded };
+    if !responded {
+        log::error!(
+            "ILI9341 not responding on SPI5 (status {:08x}); check the panel connection",
+            status
+        );
+        return Err(HwError::NoResponse);
+    }
+    log::info!(
+        "ILI9341 id {:06x} status {:08x}: booster {}, sleep {}, display {}",
+        id,
+        status,
+        if status & STATUS_BOOSTER_ON != 0 {
+            "on"
+        } else {
+            "off"
+        },
+        if status & STATUS_SLEEP_OUT != 0 {
+            "out"
+        } else {
+            "in"
+        },
+        if status & STATUS_DISPLAY_ON != 0 {
+            "on"
+        } else {
+            "off"
+        }
+    );
+    Ok(())
+}
+
+// Whether the last display_selftest got an answer
+pub fn responded() -> bool {
+    unsafe { RESPONDED }
+}
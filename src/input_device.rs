@@ -1,5 +1,17 @@
-use crate::config::Coord;
-use crate::game::InputDevice;
+use core_logic::input::InputEvent;
+use core_logic::joystick::Joystick;
+pub use core_logic::settings::TiltCalibration;
+use core_logic::tilt_map::TiltMap;
+
+use crate::adc;
+use crate::config::{Coord, FLAP_LIFT, LCD_HEIGHT, PLAYER_HEIGHT};
+use crate::game::{GameSnapshot, InputDevice, InputMode};
+use crate::input_events::{self, InputSource};
+use crate::log;
+use crate::mpu6050;
+use crate::settings;
+use crate::touch;
+use crate::trigger;
 
 /// Shared accelerometer data structure for all InputDevice implementations
 ///
@@ -9,6 +21,7 @@ use crate::game::InputDevice;
 /// Values are in raw accelerometer units, typically:
 /// - Range: -32768 to 32767 for ±2g scale
 /// - 1g ≈ 16384 units
+#[derive(Copy, Clone)]
 pub struct AccelData {
     pub accel_x: i32, // X-axis acceleration
     pub accel_y: i32, // Y-axis acceleration
@@ -23,7 +36,8 @@ pub struct AccelData {
 /// # Parameters
 /// - `accel_data`: Raw accelerometer reading
 /// - `y_min`, `y_max`: Game coordinate bounds
-/// - `tilt_threshold`: Minimum acceleration to register as "tapped" (typically 8000-12000)
+/// - `calibration`: Resting offsets and tilt threshold
+/// - `map`: Which axis steers, which way round, its dead zone and curve
 ///
 /// # Returns
 /// - `(mapped_y, is_tilted)`: Y coordinate and whether significant tilt was detected
@@ -31,19 +45,23 @@ pub fn accel_to_game_coords(
     accel_data: &AccelData,
     y_min: Coord,
     y_max: Coord,
-    tilt_threshold: i32,
+    calibration: &TiltCalibration,
+    map: &TiltMap,
 ) -> (Coord, bool) {
-    let is_tilted = accel_data.accel_y.abs() > tilt_threshold;
+    let rest = calibration.apply([accel_data.accel_x, accel_data.accel_y, accel_data.accel_z]);
+    let accel_y = map.pick(rest);
+    let is_tilted = accel_y.abs() > calibration.threshold;
+    let accel_y = map.shape(accel_y);
 
     // Map accelerometer Y value to screen Y coordinate
     // Scale from accelerometer range (-32768 to 32767) to screen range (y_min to y_max)
-    let normalized_y = if accel_data.accel_y > 0 {
+    let normalized_y = if accel_y > 0 {
         // Positive tilt maps to upper part of range
-        let scaled = (accel_data.accel_y * (y_max - y_min)) / 32767;
+        let scaled = (accel_y * (y_max - y_min)) / 32767;
         y_min + scaled.min(y_max - y_min)
     } else {
         // Negative tilt maps to lower part of range
-        let scaled = ((-accel_data.accel_y) * (y_max - y_min)) / 32767;
+        let scaled = ((-accel_y) * (y_max - y_min)) / 32767;
         y_max - scaled.min(y_max - y_min)
     };
 
@@ -74,12 +92,81 @@ impl InputDevice for DummyInputDevice {
     }
 }
 */
+/// Autopilot for attract mode
+///
+/// Flaps whenever the bird is below the center of the next gap and otherwise
+/// sinks slowly toward it. The game feeds it a snapshot every frame through
+/// `observe` before asking `is_tap`.
+pub struct DemoInputDevice {
+    player_y: Coord,
+    target_y: Option<Coord>,
+}
+
+// How fast the autopilot lets the bird drop toward the gap, per frame
+const DEMO_SINK: Coord = 2;
+
+impl DemoInputDevice {
+    pub fn new() -> Self {
+        Self {
+            player_y: 0,
+            target_y: None,
+        }
+    }
+
+    pub fn observe(&mut self, snapshot: &GameSnapshot) {
+        self.player_y = snapshot.player_y;
+        // Aim the top of the bird so its center lines up with the gap center
+        self.target_y =
+            snapshot.gaps[0].map(|gap| (gap.top + gap.bottom) / 2 - PLAYER_HEIGHT as Coord / 2);
+    }
+}
+
+impl InputDevice for DemoInputDevice {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
+        let y = self.player_y;
+        let Some(target) = self.target_y else {
+            return Ok((y, false));
+        };
+
+        let new_y = if y > target {
+            // Below the gap center: flap, without overshooting it
+            (y - FLAP_LIFT).max(target)
+        } else if y < target {
+            (y + DEMO_SINK).min(target)
+        } else {
+            return Ok((y, false));
+        };
+        Ok((new_y.clamp(y_min, y_max), true))
+    }
+}
+
+// Percent of each new tilt reading the bird's position takes, the rest
+// staying where it was; 100 follows the sensor as it is
+static mut TILT_ALPHA: i32 = 100;
+
+pub fn set_tilt_alpha(percent: i32) {
+    unsafe { TILT_ALPHA = percent.clamp(1, 100) }
+}
+
+pub fn tilt_alpha() -> i32 {
+    unsafe { TILT_ALPHA }
+}
+
 // Real input device using MPU6050
-pub struct Mpu6050InputDevice;
+pub struct Mpu6050InputDevice {
+    // The position last reported, for the smoothing
+    smoothed: Option<Coord>,
+}
 
 impl Mpu6050InputDevice {
     pub fn new() -> Self {
-        Self
+        Self { smoothed: None }
     }
 }
 
@@ -91,13 +178,30 @@ impl InputDevice for Mpu6050InputDevice {
     }
 
     fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
-        match crate::mpu6050::read_accel_data() {
+        // The sensor task's last sample if it is keeping up, else read now
+        let sample = match mpu6050::latest_accel() {
+            Some(accel) => Ok(accel),
+            None => mpu6050::read_accel_data(),
+        };
+        match sample {
             Ok(accel_data) => {
-                // Use the shared helper function with appropriate threshold
-                let tilt_threshold = 8000; // Adjust based on sensitivity needed
-                let (mapped_y, is_tilted) =
-                    accel_to_game_coords(&accel_data, y_min, y_max, tilt_threshold);
-                Ok((mapped_y, is_tilted))
+                let settings = settings::get();
+                let calibration = settings
+                    .tilt
+                    .at_temperature(mpu6050::temperature(), mpu6050::drift_at);
+                let (mapped_y, is_tilted) = accel_to_game_coords(
+                    &accel_data,
+                    y_min,
+                    y_max,
+                    &calibration,
+                    &settings.tilt_map,
+                );
+                let y = match self.smoothed {
+                    Some(y) => y + (mapped_y - y) * tilt_alpha() / 100,
+                    None => mapped_y,
+                };
+                self.smoothed = Some(y);
+                Ok((y, is_tilted))
             }
             Err(_) => {
                 // If MPU6050 read fails, return no tap and center position
@@ -107,6 +211,297 @@ impl InputDevice for Mpu6050InputDevice {
     }
 }
 
+// Touchscreen: the bird follows the finger up and down while it is pressed
+pub struct TouchInputDevice;
+
+impl TouchInputDevice {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl InputDevice for TouchInputDevice {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        touch::init()
+    }
+
+    fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
+        match touch::read() {
+            Some((_, y)) => {
+                let y = y_min + y * (y_max - y_min) / (LCD_HEIGHT as Coord - 1);
+                Ok((y, true))
+            }
+            None => Ok(((y_min + y_max) / 2, false)),
+        }
+    }
+
+    fn mode(&self) -> InputMode {
+        InputMode::Touch
+    }
+}
+
+// Readings averaged for the stick's resting position
+const STICK_CENTER_SAMPLES: u32 = 8;
+
+/// Analog thumb-stick wired to the expansion header (see `adc`)
+///
+/// `init` looks for it and takes where it rests as its centre, so the
+/// stick must be left alone at boot; the reach of each axis is learned as
+/// it is pushed (core_logic::joystick).
+pub struct JoystickInputDevice {
+    stick: Option<Joystick>,
+}
+
+impl JoystickInputDevice {
+    pub fn new() -> Self {
+        Self { stick: None }
+    }
+
+    fn read() -> Option<(u16, u16)> {
+        Some((
+            adc::read(adc::STICK_X_CHANNEL)?,
+            adc::read(adc::STICK_Y_CHANNEL)?,
+        ))
+    }
+}
+
+impl InputDevice for JoystickInputDevice {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        if !adc::probe_stick() {
+            return Err(());
+        }
+        let (mut x, mut y) = (0, 0);
+        for _ in 0..STICK_CENTER_SAMPLES {
+            let (raw_x, raw_y) = Self::read().ok_or(())?;
+            x += raw_x as u32;
+            y += raw_y as u32;
+        }
+        let (x, y) = (x / STICK_CENTER_SAMPLES, y / STICK_CENTER_SAMPLES);
+        self.stick = Some(Joystick::centered(x as u16, y as u16));
+        Ok(())
+    }
+
+    fn log_data(&mut self) {
+        if let (Some(stick), Some((x, y))) = (self.stick, Self::read()) {
+            log::debug!(
+                "stick x {} ({}..{} centre {}) y {} ({}..{} centre {})",
+                x,
+                stick.x.min,
+                stick.x.max,
+                stick.x.center,
+                y,
+                stick.y.min,
+                stick.y.max,
+                stick.y.center
+            );
+        }
+    }
+
+    fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
+        let middle = ((y_min + y_max) / 2, false);
+        let (Some(stick), Some((x, y))) = (self.stick.as_mut(), Self::read()) else {
+            return Ok(middle);
+        };
+        Ok(stick.sample(x, y, y_min, y_max))
+    }
+
+    fn mode(&self) -> InputMode {
+        InputMode::Joystick
+    }
+}
+
+// External trigger (see `trigger`): each press is one flap
+pub struct TriggerInputDevice {
+    // `trigger::presses` at the last sample
+    seen: u32,
+}
+
+impl TriggerInputDevice {
+    pub fn new() -> Self {
+        Self { seen: 0 }
+    }
+}
+
+impl InputDevice for TriggerInputDevice {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        self.seen = trigger::presses();
+        Ok(())
+    }
+
+    fn log_data(&mut self) {
+        let config = trigger::config();
+        log::debug!(
+            "trigger {} (active {}, {} ms), {} presses",
+            if trigger::is_down() { "down" } else { "up" },
+            config.level_str(),
+            config.debounce_ms,
+            trigger::presses()
+        );
+    }
+
+    // Down when it was pressed since the last sample; the game puts the
+    // bird itself, as for the button
+    fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
+        trigger::poll();
+        let presses = trigger::presses();
+        let tapped = presses != self.seen;
+        self.seen = presses;
+        Ok(((y_min + y_max) / 2, tapped))
+    }
+
+    fn mode(&self) -> InputMode {
+        InputMode::Trigger
+    }
+}
+
+/// Every input device on the board behind one `InputDevice`
+///
+/// `init` probes which devices answer, and the pause menu steps through
+/// them with `cycle`, so a missing MPU6050 leaves touch, the thumb-stick
+/// or the button instead of an uncontrollable game. The button is always
+/// there; its flaps are handled by the game, which reads its events for
+/// the menu anyway. The external trigger cannot be probed, so it is always
+/// offered too, after the button. Each sample of the active device queues
+/// its edges and moves as input events, next to the button's; the button
+/// and the trigger queue their own from their interrupts.
+pub struct InputMux {
+    tilt: Mpu6050InputDevice,
+    touch: TouchInputDevice,
+    joystick: JoystickInputDevice,
+    trigger: TriggerInputDevice,
+    // Indexed like MUX_MODES
+    present: [bool; 5],
+    active: usize,
+    // The active device's state at the last sample
+    was_down: bool,
+    last_y: Option<Coord>,
+}
+
+// Preference order when probing
+const MUX_MODES: [InputMode; 5] = [
+    InputMode::Tilt,
+    InputMode::Touch,
+    InputMode::Joystick,
+    InputMode::Button,
+    InputMode::Trigger,
+];
+
+// The source a mode's presses are queued as by the mux; None for the
+// button and the trigger, which queue their own
+fn source(mode: InputMode) -> Option<InputSource> {
+    match mode {
+        InputMode::Tilt => Some(InputSource::Tilt),
+        InputMode::Touch => Some(InputSource::Touch),
+        InputMode::Joystick => Some(InputSource::Stick),
+        InputMode::Button | InputMode::Trigger => None,
+    }
+}
+
+impl InputMux {
+    pub fn new() -> Self {
+        Self {
+            tilt: Mpu6050InputDevice::new(),
+            touch: TouchInputDevice::new(),
+            joystick: JoystickInputDevice::new(),
+            trigger: TriggerInputDevice::new(),
+            present: [false, false, false, true, true],
+            active: MUX_MODES.len() - 1,
+            was_down: false,
+            last_y: None,
+        }
+    }
+
+    // Queue what changed since the last sample of `source`
+    fn push_events(&mut self, source: InputSource, (y, down): (Coord, bool)) {
+        if down != self.was_down {
+            if down {
+                input_events::pressed(source);
+            } else {
+                input_events::released(source);
+            }
+            self.was_down = down;
+        }
+        let positional = matches!(source, InputSource::Tilt | InputSource::Stick);
+        if positional && self.last_y != Some(y) {
+            input_events::push(InputEvent::TiltChanged { y });
+            self.last_y = Some(y);
+        }
+    }
+}
+
+impl InputDevice for InputMux {
+    type Error = ();
+
+    fn init(&mut self) -> Result<(), Self::Error> {
+        self.present[0] = mpu6050::is_present() && self.tilt.init().is_ok();
+        self.present[1] = self.touch.init().is_ok();
+        self.present[2] = self.joystick.init().is_ok();
+        let _ = self.trigger.init();
+        self.active = self.present.iter().position(|&p| p).unwrap_or(3);
+        let yes_no = |present: bool| if present { "yes" } else { "no" };
+        log::info!(
+            "input: tilt {}, touch {}, stick {}, using {}",
+            yes_no(self.present[0]),
+            yes_no(self.present[1]),
+            yes_no(self.present[2]),
+            self.mode().as_str()
+        );
+        Ok(())
+    }
+
+    fn log_data(&mut self) {
+        match self.mode() {
+            InputMode::Tilt => self.tilt.log_data(),
+            InputMode::Touch => self.touch.log_data(),
+            InputMode::Joystick => self.joystick.log_data(),
+            InputMode::Trigger => self.trigger.log_data(),
+            InputMode::Button => {}
+        }
+    }
+
+    fn is_tap(&mut self, y_min: Coord, y_max: Coord) -> Result<(Coord, bool), Self::Error> {
+        let (source, sample) = match self.mode() {
+            InputMode::Tilt => (InputSource::Tilt, self.tilt.is_tap(y_min, y_max)?),
+            InputMode::Touch => (InputSource::Touch, self.touch.is_tap(y_min, y_max)?),
+            InputMode::Joystick => (InputSource::Stick, self.joystick.is_tap(y_min, y_max)?),
+            InputMode::Trigger => return self.trigger.is_tap(y_min, y_max),
+            InputMode::Button => return Ok(((y_min + y_max) / 2, false)),
+        };
+        self.push_events(source, sample);
+        Ok(sample)
+    }
+
+    fn mode(&self) -> InputMode {
+        MUX_MODES[self.active]
+    }
+
+    fn cycle(&mut self) {
+        // The old device's press ends with it
+        if let Some(source) = source(self.mode()).filter(|_| self.was_down) {
+            input_events::released(source);
+        }
+        self.was_down = false;
+        self.last_y = None;
+        for step in 1..=MUX_MODES.len() {
+            let next = (self.active + step) % MUX_MODES.len();
+            if self.present[next] {
+                self.active = next;
+                // Presses made while it was not in use are not flaps
+                if self.mode() == InputMode::Trigger {
+                    let _ = self.trigger.init();
+                }
+                return;
+            }
+        }
+    }
+}
+
 // Example: How other accelerometer-based input devices could use AccelData
 /*
 pub struct LSM6DS3InputDevice;
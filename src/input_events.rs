@@ -0,0 +1,196 @@
+//! The input event queue and what the game reads from it each frame
+//!
+//! The button and the external trigger push their presses and releases from
+//! their EXTI interrupts; the
+//! touchscreen and accelerometer have no usable interrupt while playing, so
+//! `InputMux` pushes their edges whenever it samples them. The sensor task
+//! pushes the accelerometer's shake and free-fall detections. `Inputs`
+//! drains the lot once a frame into a `Frame`: the button's gesture, a
+//! double tap on any source, whether the touchscreen or tilt went down, and
+//! whether the board was shaken or dropped.
+#![allow(dead_code)]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use core_logic::input::{EventQueue, Gesture, Gestures, InputEvent, Motion};
+use core_logic::latency::Kind;
+
+use crate::button::ButtonEvent;
+use crate::clock;
+use crate::config::Coord;
+use crate::latency;
+
+pub use core_logic::input::InputSource;
+
+// A few frames' worth of edges, bounce included
+const QUEUE_LEN: usize = 32;
+
+static QUEUE: EventQueue<QUEUE_LEN> = EventQueue::new();
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize =
+    core::mem::size_of::<EventQueue<QUEUE_LEN>>() + core::mem::size_of::<AtomicU32>();
+
+// Queue an event from any context. Pushes go in with interrupts masked,
+// which makes the thread and the button interrupt one producer. Anything
+// that can steer or flap the bird is stamped for the latency measurement.
+pub fn push(event: InputEvent) {
+    cortex_m::interrupt::free(|_| {
+        match event {
+            InputEvent::Pressed { .. } | InputEvent::Released { .. } => latency::arrived(Kind::Tap),
+            InputEvent::TiltChanged { .. } => latency::arrived(Kind::Steer),
+            InputEvent::Moved { .. } => {}
+        }
+        if !QUEUE.push(event) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+}
+
+pub fn pressed(source: InputSource) {
+    push(InputEvent::Pressed {
+        source,
+        at: clock::millis(),
+    });
+}
+
+pub fn released(source: InputSource) {
+    push(InputEvent::Released {
+        source,
+        at: clock::millis(),
+    });
+}
+
+pub fn moved(motion: Motion) {
+    push(InputEvent::Moved {
+        motion,
+        at: clock::millis(),
+    });
+}
+
+// Events lost to a full queue
+pub fn dropped() -> u32 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+// Throw away everything queued
+pub fn clear() {
+    while QUEUE.pop().is_some() {}
+}
+
+// True if the button went down since the queue was last read; for the
+// boot screens, before the game reads the queue
+pub fn take_button_press() -> bool {
+    let mut pressed = false;
+    while let Some(event) = QUEUE.pop() {
+        if let InputEvent::Pressed {
+            source: InputSource::Button,
+            ..
+        } = event
+        {
+            pressed = true;
+        }
+    }
+    pressed
+}
+
+/// What came in since the last frame
+#[derive(Copy, Clone, Default)]
+pub struct Frame {
+    // The button's gesture, as the menus and button mode know it
+    pub button: Option<ButtonEvent>,
+    // Any source tapped twice in quick succession
+    pub double_tap: bool,
+    // The touchscreen, the tilt, the stick or the trigger went down; the
+    // button speaks through `button`
+    pub pressed: bool,
+    // Where the tilt or the stick last put the bird
+    pub tilt_y: Option<Coord>,
+    // The board was shaken, or is falling
+    pub shake: bool,
+    pub free_fall: bool,
+}
+
+/// Gesture state per source, fed from the queue
+pub struct Inputs {
+    button: Gestures,
+    touch: Gestures,
+    tilt: Gestures,
+    stick: Gestures,
+    trigger: Gestures,
+}
+
+impl Inputs {
+    pub const fn new() -> Self {
+        Inputs {
+            button: Gestures::new(),
+            touch: Gestures::new(),
+            tilt: Gestures::new(),
+            stick: Gestures::new(),
+            trigger: Gestures::new(),
+        }
+    }
+
+    fn gestures(&mut self, source: InputSource) -> &mut Gestures {
+        match source {
+            InputSource::Button => &mut self.button,
+            InputSource::Touch => &mut self.touch,
+            InputSource::Tilt => &mut self.tilt,
+            InputSource::Stick => &mut self.stick,
+            InputSource::Trigger => &mut self.trigger,
+        }
+    }
+
+    // Take everything queued. One button gesture is acted on per frame;
+    // draining stops at it, so a second waits for the next frame rather
+    // than being lost.
+    pub fn drain(&mut self) -> Frame {
+        let mut frame = Frame::default();
+        while frame.button.is_none() {
+            let Some(event) = QUEUE.pop() else {
+                break;
+            };
+            let (source, gesture) = match event {
+                InputEvent::Pressed { source, at } => {
+                    self.gestures(source).press(at);
+                    frame.pressed |= source != InputSource::Button;
+                    (source, None)
+                }
+                InputEvent::Released { source, at } => (source, self.gestures(source).release(at)),
+                InputEvent::TiltChanged { y } => {
+                    frame.tilt_y = Some(y);
+                    continue;
+                }
+                InputEvent::Moved { motion, .. } => {
+                    frame.shake |= motion == Motion::Shake;
+                    frame.free_fall |= motion == Motion::FreeFall;
+                    continue;
+                }
+            };
+            frame.double_tap |= gesture == Some(Gesture::DoubleTap);
+            if source == InputSource::Button {
+                frame.button = gesture.map(button_event);
+            }
+        }
+        if frame.button.is_none() {
+            frame.button = self.button.poll(clock::millis()).map(button_event);
+        }
+        frame
+    }
+}
+
+impl Default for Inputs {
+    fn default() -> Self {
+        Inputs::new()
+    }
+}
+
+// A double tap is still a tap to everything that only wants taps
+fn button_event(gesture: Gesture) -> ButtonEvent {
+    match gesture {
+        Gesture::Tap | Gesture::DoubleTap => ButtonEvent::Short,
+        Gesture::LongPress => ButtonEvent::Long,
+        Gesture::Hold => ButtonEvent::Hold,
+    }
+}
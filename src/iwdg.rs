@@ -0,0 +1,156 @@
+//! Independent watchdog with a frame heartbeat
+//!
+//! The IWDG counts down from the LSI (~32 kHz) and resets the chip unless it
+//! is refreshed within `TIMEOUT_MS`. The main loop calls `heartbeat` once
+//! per pass, which refreshes it only if that pass (update plus render) came
+//! in under `FRAME_BUDGET_MS`. A bus that locks up mid-frame, or a loop that
+//! keeps blowing its budget, therefore ends in a reset rather than a frozen
+//! screen.
+//!
+//! The IWDG cannot be stopped once started, so `start` is the last thing
+//! before the main loop. A flash sector erase stalls the CPU for a second or
+//! more, so `flash` refreshes the watchdog before each one. The counter is
+//! frozen while a debugger has the core halted.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use core_logic::blackbox::ResetCause;
+
+use crate::clock::{self, CYCLES_PER_MS};
+use crate::config::{Coord, LCD_WIDTH};
+use crate::framebuffer::FrameBuffer;
+use crate::log;
+use crate::resources;
+
+// LSI / 64 = 500 Hz, so the reload value counts 2 ms ticks. The LSI may run
+// anywhere from 17 to 47 kHz, making the real timeout 2.7..7.5 s
+const TICKS_PER_S: u32 = 500;
+pub const TIMEOUT_MS: u32 = 4000;
+const RELOAD: u16 = (TIMEOUT_MS * TICKS_PER_S / 1000) as u16;
+
+// A frame slower than this is treated as stuck and does not refresh
+pub const FRAME_BUDGET_MS: u32 = 250;
+
+// How long boot shows the recovery notice
+const NOTICE_MS: u32 = 2000;
+
+struct State {
+    running: bool,
+    reset_cause: ResetCause,
+    last_beat: Option<u32>,
+    missed: u32,
+}
+
+static mut STATE: State = State {
+    running: false,
+    reset_cause: ResetCause::Unknown,
+    last_beat: None,
+    missed: 0,
+};
+
+// Record what caused this reset and clear the reset flags so the next
+// boot starts clean; call early at boot
+pub fn check_reset_cause() {
+    let dp = resources::pac();
+    let cause = ResetCause::from_csr(dp.RCC.csr.read().bits());
+    dp.RCC.csr.modify(|_, w| w.rmvf().set_bit());
+    unsafe { STATE.reset_cause = cause };
+    if cause == ResetCause::Watchdog {
+        log::warn!("recovered from watchdog reset");
+    }
+}
+
+pub fn reset_cause() -> ResetCause {
+    unsafe { STATE.reset_cause }
+}
+
+// True if the last reset came from the watchdog
+pub fn recovered() -> bool {
+    reset_cause() == ResetCause::Watchdog
+}
+
+// Start the watchdog; from here on the main loop has to keep beating
+pub fn start() {
+    let dp = resources::pac();
+    dp.DBGMCU.apb1_fz.modify(|_, w| w.dbg_iwdg_stop().set_bit());
+
+    dp.IWDG.kr.write(|w| w.key().start());
+    dp.IWDG.kr.write(|w| w.key().enable());
+    dp.IWDG.pr.write(|w| w.pr().divide_by64());
+    dp.IWDG.rlr.write(|w| w.rl().bits(RELOAD));
+    while dp.IWDG.sr.read().bits() != 0 {}
+    dp.IWDG.kr.write(|w| w.key().reset());
+
+    let state = unsafe { &mut STATE };
+    state.running = true;
+    state.last_beat = Some(clock::cycles());
+    log::info!("watchdog armed, {} ms", TIMEOUT_MS);
+}
+
+// Refresh unconditionally, for known long operations outside the frame loop
+pub fn feed() {
+    if unsafe { STATE.running } {
+        let dp = resources::pac();
+        dp.IWDG.kr.write(|w| w.key().reset());
+    }
+}
+
+// Call once at the end of every main loop pass
+pub fn heartbeat() {
+    let state = unsafe { &mut STATE };
+    if !state.running {
+        return;
+    }
+    let now = clock::cycles();
+    let on_time = match state.last_beat {
+        Some(last) => now.wrapping_sub(last) / CYCLES_PER_MS <= FRAME_BUDGET_MS,
+        None => true,
+    };
+    state.last_beat = Some(now);
+    if on_time {
+        feed();
+    } else {
+        state.missed += 1;
+    }
+}
+
+// Start timing afresh, e.g. after sleeping, so the gap is not a missed beat
+pub fn restart_interval() {
+    unsafe { STATE.last_beat = None };
+    feed();
+}
+
+// Frames that ran over budget since the watchdog started
+pub fn missed_beats() -> u32 {
+    unsafe { STATE.missed }
+}
+
+// Tell the player the last session ended in a watchdog reset
+pub fn draw_notice() {
+    let mut fb = FrameBuffer::render_target();
+    let black = fb.encode_rgb565(0x0000);
+    fb.fill_rect(0, 140, LCD_WIDTH, 40, black);
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::YELLOW)
+        .build();
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let _ = Text::with_text_style(
+        "recovered from watchdog reset",
+        Point::new(LCD_WIDTH as Coord / 2, 160),
+        style,
+        centered,
+    )
+    .draw(&mut fb);
+    cortex_m::asm::dsb();
+    clock::delay_ms(NOTICE_MS);
+}
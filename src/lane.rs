@@ -0,0 +1,45 @@
+//! Drawing clipped to a playfield lane
+//!
+//! The lane geometry lives in `core_logic::lane`. `LaneDraw` clips fills to
+//! a lane, so one split-screen player's obstacles never spill into the
+//! other player's half. `full` is the full-screen game's lane with the
+//! margins from the settings.
+#![allow(dead_code)]
+
+pub use core_logic::lane::{Lane, Margins};
+
+use crate::config::*;
+use crate::display;
+use crate::framebuffer::Image;
+use crate::settings;
+use crate::sky;
+
+// Lane::FULL with the score bar and ground the settings give it
+pub fn full() -> Lane {
+    settings::get().margins.lane(Lane::FULL)
+}
+
+pub trait LaneDraw {
+    fn fill_rect(&self, rect: Rect, color: u16);
+    fn fill_tiled(&self, rect: Rect, tile: &Image);
+    // Paint over with the sky, erasing what was there
+    fn fill_sky(&self, rect: Rect);
+}
+
+impl LaneDraw for Lane {
+    fn fill_rect(&self, rect: Rect, color: u16) {
+        if let Some(rect) = rect.intersection(&self.rect()) {
+            let _ = display::draw_rect_angle_rust(rect, color);
+        }
+    }
+
+    fn fill_tiled(&self, rect: Rect, tile: &Image) {
+        if let Some(rect) = rect.intersection(&self.rect()) {
+            let _ = display::draw_tiled_rust(rect, tile);
+        }
+    }
+
+    fn fill_sky(&self, rect: Rect) {
+        sky::fill(self, rect);
+    }
+}
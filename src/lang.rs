@@ -0,0 +1,14 @@
+//! The player's language
+//!
+//! Screens ask for their text by `Msg` here and get it from the table for
+//! the language in the settings (core_logic::lang), so a change from the
+//! settings page or the shell shows on whatever is drawn next.
+#![allow(dead_code)]
+
+pub use core_logic::lang::{Language, Msg};
+
+use crate::settings;
+
+pub fn text(msg: Msg) -> &'static str {
+    settings::get().language.text(msg)
+}
@@ -0,0 +1,41 @@
+//! Input latency, from an input coming in to the frame that acted on it
+//!
+//! The input queue stamps presses and releases as taps and tilt or stick
+//! samples as steers as they are pushed, the game says which of the two a
+//! running frame acted on and the main loop ends the frame once it is on
+//! the panel; see
+//! core_logic::latency. The average and worst of the session (as the
+//! profiler has it, one game) are in the profiler's overlay and its
+//! breakdown.
+#![allow(dead_code)]
+
+use core_logic::latency::{InputLatency, Kind, Report};
+
+use crate::clock;
+use crate::resources::Shared;
+
+// Stamps come from the EXTI interrupts as well as the thread
+static LATENCY: Shared<InputLatency> = Shared::of(InputLatency::new());
+
+// An input of `kind` the bird may act on came in just now
+pub fn arrived(kind: Kind) {
+    LATENCY.lock(|latency| latency.arrived(kind, clock::micros()));
+}
+
+// This frame moved the bird on its input of `kind`
+pub fn acted(kind: Kind) {
+    LATENCY.lock(|latency| latency.acted(kind));
+}
+
+// Call once the frame is out to the panel
+pub fn end_frame() {
+    LATENCY.lock(|latency| latency.end_frame(clock::micros()));
+}
+
+pub fn begin_session() {
+    LATENCY.lock(|latency| latency.reset());
+}
+
+pub fn report() -> Report {
+    LATENCY.lock(|latency| latency.report()).unwrap_or_default()
+}
@@ -1,278 +1,910 @@
 #![allow(dead_code)]
 
 use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::blackbox;
+use crate::clock;
+use crate::color::{Argb8888, Rgb565};
+use crate::error::HwError;
+use crate::executor;
+use crate::framebuffer;
+use crate::log;
+use crate::resources;
+use crate::sdram::arena::{Arena, FramebufferRegion, Region};
+use crate::sdram::SDRAM_ALLOCATABLE;
+use core_logic::blackbox::Event;
+use core_logic::panel::{Panel, Timings};
 
 pub struct LcdDriver {
-    ltdc: pac::LTDC,
+    ltdc: &'static pac::LTDC,
 }
 
+// Screen-sized buffers hold the game's surface, the DISCO panel's size
 pub const LCD_WIDTH: u32 = 240;
 pub const LCD_HEIGHT: u32 = 320;
 
-/*
-Parameter 	Horizontal (DCLKs)	Vertical (Lines)
-Active Display Area (Resolution)	320	240
-Front Porch (FP)	16 (HFP)	4 (VFP)
-Back Porch (BP)	24 (HBP)	4 (VBP)
-Sync Pulse Width (HSYNC/VSYNC)	Varies (e.g., 1-10)	Varies (e.g., 1-10)
-Total (per line/frame)	408 (320+16+24+HSYNC width)	262 (240+4+4+VSYNC width)
-*/
-// ILI9341-compatible timing parameters for STM32F429I-DISCO
-const HSYNC: u32 = 5; // Horizontal sync width
-const HBP: u32 = 24; // Horizontal back porch
-const HFP: u32 = 16; // Horizontal front porch
-const VSYNC: u32 = 5; // Vertical sync width
-const VBP: u32 = 4; // Vertical back porch
-const VFP: u32 = 4; // Vertical front porch (restored to original)
-
-// Framebuffer addresses in SDRAM (must match sdram::SDRAM_BASE)
-pub const LAYER1_BASE: u32 = super::sdram::SDRAM_BASE; // Layer1 full screen
-#[cfg(feature = "l1-16bpp")]
-pub const LAYER1_BPP: u32 = 2; // RGB565
-#[cfg(not(feature = "l1-16bpp"))]
-pub const LAYER1_BPP: u32 = 4; // ARGB8888
-pub const LAYER1_SIZE: u32 = LCD_WIDTH * LCD_HEIGHT * LAYER1_BPP;
-pub const LAYER2_BASE: u32 = LAYER1_BASE + LAYER1_SIZE;
-// Single knob to adjust Layer 2 square size
-pub const LAYER2_SIDE: u32 = 64;
+// The panel LTDC drives (see core_logic::panel): the DISCO board's own,
+// unless the build is for a board with another on its RGB pins
+pub const BOOT_PANEL: Panel = if cfg!(feature = "panel-480x272") {
+    Panel::RGB_480X272
+} else {
+    Panel::DISCO
+};
+
+static mut PANEL: Panel = BOOT_PANEL;
+
+pub fn panel() -> Panel {
+    unsafe { PANEL }
+}
+
+// Drive `panel` instead; takes effect when LcdDriver::new programs the
+// timings
+pub fn select_panel(panel: Panel) {
+    unsafe { PANEL = panel };
+}
+
+// The surface as the panel scans it, width by height
+pub fn surface() -> (u32, u32) {
+    panel().surface()
+}
+
+// How a w x h buffer is laid out in memory: one shaped like the screen
+// (full size or reduced) turned with the surface, anything else as it is
+pub fn laid_out(w: u32, h: u32) -> (u32, u32) {
+    if panel().turned && w * LCD_HEIGHT == h * LCD_WIDTH {
+        (h, w)
+    } else {
+        (w, h)
+    }
+}
+
+// LTDC position of surface column `x` and row `y`: past the sync pulse,
+// the back porch and the letterbox border
+fn h_start(x: u32) -> u32 {
+    let panel = panel();
+    panel.timings.hsync + panel.timings.hbp + panel.origin().0 + x
+}
+
+fn v_start(y: u32) -> u32 {
+    let panel = panel();
+    panel.timings.vsync + panel.timings.vbp + panel.origin().1 + y
+}
+
+// Layer 1 pixel format; everything that touches Layer 1 memory follows this
+pub const LAYER1_FORMAT: PixelFormat = Layer1Pixel::FORMAT;
+// Single knob to adjust Layer 2 square size (holds the 30x30 bird sprite)
+pub const LAYER2_SIDE: u32 = 32;
 pub const LAYER2_W: u32 = LAYER2_SIDE;
 pub const LAYER2_H: u32 = LAYER2_SIDE;
-pub const LAYER2_BPP: u32 = 4; // ARGB8888 for richer colors
-pub const LAYER2_SIZE: u32 = LAYER2_W * LAYER2_H * LAYER2_BPP;
-// Second framebuffer for Layer1 to enable double-buffering and avoid mid-scan writes
-pub const LAYER1_BASE_B: u32 = LAYER2_BASE + LAYER2_SIZE;
+// ARGB8888 for per-pixel alpha around the sprite
+pub type Layer2Pixel = Argb8888Pixel;
+pub const LAYER2_FORMAT: PixelFormat = Layer2Pixel::FORMAT;
+// The small sprite window's format. With `l2-indexed` the bird is stored as
+// 8-bit palette indices, a quarter of the SDRAM reads, and made transparent
+// by the color key instead of per-pixel alpha; the HUD and overlay stay
+// LAYER2_FORMAT.
+#[cfg(feature = "l2-indexed")]
+pub type SpritePixel = L8Pixel;
+#[cfg(not(feature = "l2-indexed"))]
+pub type SpritePixel = Layer2Pixel;
+pub const SPRITE_FORMAT: PixelFormat = SpritePixel::FORMAT;
+// Entries in each layer's color lookup table
+pub const CLUT_SIZE: usize = 256;
+// Rows of the HUD strip Layer 2 covers in the full-screen game: the score bar
+pub const HUD_H: u32 = 30;
+// Along the longer panel side, so the strip fits whichever way the game is
+// turned
+pub const HUD_LEN: u32 = LCD_HEIGHT;
+pub const RETRO_W: u32 = LCD_WIDTH / 2;
+pub const RETRO_H: u32 = LCD_HEIGHT / 2;
+
+// The display buffers by shape and format
+pub type Layer1Buffer = FramebufferRegion<LCD_WIDTH, LCD_HEIGHT, Layer1Pixel>;
+pub type SpriteBuffer = FramebufferRegion<LAYER2_W, LAYER2_H, SpritePixel>;
+pub type OverlayBuffer = FramebufferRegion<LCD_WIDTH, LCD_HEIGHT, Layer2Pixel>;
+pub type HudBuffer = FramebufferRegion<HUD_LEN, HUD_H, Layer2Pixel>;
+pub type RetroBuffer = FramebufferRegion<RETRO_W, RETRO_H, Layer1Pixel>;
+
+/// Display buffers in SDRAM
+pub struct DisplayMemory {
+    pub layer1: Layer1Buffer,
+    pub layer2: SpriteBuffer,
+    // Second framebuffer for Layer1 to enable double-buffering and avoid mid-scan writes
+    pub layer1_b: Layer1Buffer,
+    // Full-screen ARGB8888 buffer Layer 2 switches to for menu overlays
+    pub overlay: OverlayBuffer,
+    // ARGB8888 strip Layer 2 shows over the score bar while the HUD is up
+    pub hud: HudBuffer,
+    // Half-resolution Layer 1 render target for retro mode, upscaled on present
+    pub retro: RetroBuffer,
+    // SDRAM left over for everything else
+    pub free: Region,
+}
+
+pub const DISPLAY_MEMORY: DisplayMemory = {
+    let mut arena = Arena::new(SDRAM_ALLOCATABLE);
+    DisplayMemory {
+        layer1: arena.alloc_framebuffer(),
+        layer2: arena.alloc_framebuffer(),
+        layer1_b: arena.alloc_framebuffer(),
+        overlay: arena.alloc_framebuffer(),
+        hud: arena.alloc_framebuffer(),
+        retro: arena.alloc_framebuffer(),
+        free: arena.remaining(),
+    }
+};
+
+const LAYER1_BASE: u32 = DISPLAY_MEMORY.layer1.base;
+const LAYER1_BASE_B: u32 = DISPLAY_MEMORY.layer1_b.base;
+
 // Track which L1 buffer is currently presented
 static mut L1_FRONT: u32 = LAYER1_BASE;
+// How far the Layer 1 picture is shifted on the panel, see set_layer1_offset
+static mut L1_OFFSET: (i32, i32) = (0, 0);
+
+/// LTDC layer selector
+#[derive(Copy, Clone, PartialEq)]
+pub enum Layer {
+    Layer1,
+    Layer2,
+}
+
+/// The shadowed layer registers `LcdDriver` keeps a copy of
+#[derive(Copy, Clone)]
+enum Reg {
+    Whpcr,
+    Wvpcr,
+    Pfcr,
+    Cfbar,
+    Cfblr,
+    Cfblnr,
+    Cacr,
+    Bfcr,
+    Ckcr,
+}
+
+const REG_COUNT: usize = 9;
+
+/// Raw values last written to one layer's registers, None for any not
+/// written since `LcdDriver::new`. Setters compare against it and leave a
+/// register that already holds the value alone, and only ask for a reload
+/// when something was written, so code that sets the same window or alpha
+/// every frame costs nothing and what is programmed can be read back
+/// without touching the LTDC.
+#[derive(Copy, Clone)]
+struct LayerShadow([Option<u32>; REG_COUNT]);
+
+impl LayerShadow {
+    const UNKNOWN: LayerShadow = LayerShadow([None; REG_COUNT]);
+}
+
+static mut SHADOWS: [LayerShadow; 2] = [LayerShadow::UNKNOWN; 2];
+// Shadow reloads asked for, and setter calls that found nothing to change
+static mut RELOADS: u32 = 0;
+static mut UNCHANGED: u32 = 0;
+
+// WHPCR and WVPCR: first and last position, in LTDC clocks or lines
+fn window_bits(start: u32, len: u32) -> u32 {
+    (start + len - 1) << 16 | start
+}
+
+// CFBLR: CFBP is the pitch in bytes, CFBLL the bytes read per line + 3
+fn line_length_bits(pitch_bytes: u32, line_bytes: u32) -> u32 {
+    pitch_bytes << 16 | (line_bytes + 3)
+}
+
+/// LTDC pixel formats (PFCR encoding)
+#[derive(Copy, Clone, PartialEq)]
+pub enum PixelFormat {
+    Argb8888 = 0,
+    Rgb888 = 1,
+    Rgb565 = 2,
+    Argb1555 = 3,
+    Argb4444 = 4,
+    L8 = 5,
+    Al44 = 6,
+    Al88 = 7,
+}
+
+impl PixelFormat {
+    pub const fn bytes_per_pixel(self) -> u32 {
+        match self {
+            PixelFormat::Argb8888 => 4,
+            PixelFormat::Rgb888 => 3,
+            PixelFormat::Rgb565 | PixelFormat::Argb1555 | PixelFormat::Argb4444 => 2,
+            PixelFormat::Al88 => 2,
+            PixelFormat::L8 | PixelFormat::Al44 => 1,
+        }
+    }
+
+    // Formats whose pixels go through the layer's CLUT
+    pub const fn is_indexed(self) -> bool {
+        matches!(
+            self,
+            PixelFormat::L8 | PixelFormat::Al44 | PixelFormat::Al88
+        )
+    }
+}
+
+/// A pixel format as a type, so framebuffers in different formats are
+/// different types (see `FramebufferRegion`)
+pub trait Pixel: Copy {
+    // The format a buffer of these is laid out for
+    const FORMAT: PixelFormat;
+    const BYTES: u32 = Self::FORMAT.bytes_per_pixel();
+
+    // The format its pixels are in now
+    fn format() -> PixelFormat {
+        Self::FORMAT
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Argb8888Pixel;
+
+impl Pixel for Argb8888Pixel {
+    const FORMAT: PixelFormat = PixelFormat::Argb8888;
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Rgb565Pixel;
+
+impl Pixel for Rgb565Pixel {
+    const FORMAT: PixelFormat = PixelFormat::Rgb565;
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct L8Pixel;
+
+impl Pixel for L8Pixel {
+    const FORMAT: PixelFormat = PixelFormat::L8;
+}
+
+/// Layer 1's pixels: laid out for the build's format, and dropped to RGB565
+/// in place if underrun recovery or the bandwidth saver has to cut bandwidth
+#[derive(Copy, Clone, PartialEq)]
+pub struct Layer1Pixel;
+
+impl Pixel for Layer1Pixel {
+    #[cfg(feature = "l1-16bpp")]
+    const FORMAT: PixelFormat = PixelFormat::Rgb565;
+    #[cfg(not(feature = "l1-16bpp"))]
+    const FORMAT: PixelFormat = PixelFormat::Argb8888;
+
+    fn format() -> PixelFormat {
+        LcdDriver::layer1_format()
+    }
+}
+
+/// How a layer is blended over the layers below it
+#[derive(Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    // Pixel alpha x constant alpha (BF1 = 6, BF2 = 7)
+    PixelAlpha,
+    // Constant alpha only, per-pixel alpha ignored (BF1 = 4, BF2 = 5)
+    ConstantAlpha,
+}
+
+impl BlendMode {
+    fn factors(self) -> (u8, u8) {
+        match self {
+            BlendMode::PixelAlpha => (6, 7),
+            BlendMode::ConstantAlpha => (4, 5),
+        }
+    }
+}
+
+/// Active level of an LTDC sync or enable output (GCR HSPOL/VSPOL/DEPOL)
+#[derive(Copy, Clone, PartialEq)]
+pub enum Polarity {
+    ActiveLow,
+    ActiveHigh,
+}
+
+impl Polarity {
+    fn bit(self) -> bool {
+        self == Polarity::ActiveHigh
+    }
+}
+
+/// Pixel clock edge the panel samples on. Rising drives data with the
+/// inverted input clock (GCR PCPOL = 1), which the ILI9341 in its default
+/// DPL = 0 setting needs.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ClockEdge {
+    Rising,
+    Falling,
+}
+
+/// Signal polarities and Layer 2 behaviour, programmed by `LcdDriver::new`
+/// and changeable at runtime with `LcdDriver::apply_config`
+#[derive(Copy, Clone, PartialEq)]
+pub struct LtdcConfig {
+    pub pixel_clock: ClockEdge,
+    pub hsync: Polarity,
+    pub vsync: Polarity,
+    pub data_enable: Polarity,
+    pub layer2_blend: BlendMode,
+    // Move Layer 2 with an immediate shadow reload instead of at VBlank;
+    // faster to react, but may tear
+    pub layer2_immediate: bool,
+}
+
+impl LtdcConfig {
+    // Matches the ILI9341 RGB interface setup in ili9341; sync and enable
+    // polarities are the GCR reset values
+    pub const DEFAULT: LtdcConfig = LtdcConfig {
+        pixel_clock: ClockEdge::Rising,
+        hsync: Polarity::ActiveLow,
+        vsync: Polarity::ActiveLow,
+        data_enable: Polarity::ActiveLow,
+        layer2_blend: BlendMode::PixelAlpha,
+        layer2_immediate: false,
+    };
+}
+
+impl Default for LtdcConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Window and framebuffer settings for one LTDC layer, in panel pixels
+#[derive(Copy, Clone)]
+pub struct LayerConfig {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+    pub format: PixelFormat,
+    pub base_addr: u32,
+}
+
+impl LayerConfig {
+    // The whole of `buffer` in the top left corner, in the format its type
+    // says; a smaller window over the same buffer starts from this
+    pub fn full<const W: u32, const H: u32, P: Pixel>(buffer: &FramebufferRegion<W, H, P>) -> Self {
+        let (w, h) = laid_out(W, H);
+        LayerConfig {
+            x: 0,
+            y: 0,
+            w,
+            h,
+            format: P::format(),
+            base_addr: buffer.base,
+        }
+    }
+}
+
+// Layer 1 format actually programmed; starts as LAYER1_FORMAT and drops to
+// RGB565 if underrun recovery or the bandwidth saver has to cut bandwidth
+static mut L1_FORMAT: PixelFormat = LAYER1_FORMAT;
+
+// Configuration last programmed by new() or apply_config()
+static mut CONFIG: LtdcConfig = LtdcConfig::DEFAULT;
+
+// LTDC error counters, incremented by the LTDC error interrupt
+static mut UNDERRUNS: u32 = 0;
+static mut TRANSFER_ERRORS: u32 = 0;
+// Underrun count seen by the last service_errors call, and how many frames
+// in a row have had new underruns since
+static mut UNDERRUNS_SEEN: u32 = 0;
+static mut UNDERRUN_STREAK: u32 = 0;
+
+// Vertical blanks counted by the line interrupt, for pacing the main loop
+static mut VBLANKS: u32 = 0;
+
+// Frames in a row with underruns before Layer 1 is switched to RGB565
+const UNDERRUN_STREAK_LIMIT: u32 = 30;
+// Long enough for a few frames at any usable refresh rate
+const PROBE_MS: u32 = 100;
+
+#[derive(Copy, Clone, Default)]
+pub struct LtdcErrors {
+    pub underruns: u32,
+    pub transfer_errors: u32,
+}
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<u32>() * 8
+    + core::mem::size_of::<[LayerShadow; 2]>()
+    + core::mem::size_of::<(i32, i32)>()
+    + core::mem::size_of::<PixelFormat>()
+    + core::mem::size_of::<LtdcConfig>();
 
 impl LcdDriver {
-    pub fn new() -> Self {
-        let dp = unsafe { pac::Peripherals::steal() };
-        let ltdc = dp.LTDC;
+    // Handle to an LTDC that new() already set up, without reprogramming it
+    pub fn attach() -> Self {
+        let dp = resources::pac();
+        Self { ltdc: &dp.LTDC }
+    }
+
+    // Program timings and both layers and start scan-out. Fails without the
+    // PLLSAI pixel clock, which clock::setup_pllsai_for_ltdc starts.
+    pub fn new(config: LtdcConfig) -> Result<Self, HwError> {
+        let dp = resources::pac();
+        if dp.RCC.cr.read().pllsairdy().is_not_ready()
+            || dp.RCC.apb2enr.read().ltdcen().is_disabled()
+        {
+            return Err(HwError::ClockNotReady);
+        }
+        let ltdc = &dp.LTDC;
+        let panel = panel();
+        let Timings {
+            hsync,
+            hbp,
+            hfp,
+            vsync,
+            vbp,
+            vfp,
+        } = panel.timings;
         // Ensure GPIOs are configured for LTDC signals
         Self::setup_ltdc_gpio(); // Configure sync and porch timings
         ltdc.sscr.write(|w| {
             w.hsw()
-                .bits((HSYNC - 1) as u16)
+                .bits((hsync - 1) as u16)
                 .vsh()
-                .bits((VSYNC - 1) as u16)
+                .bits((vsync - 1) as u16)
         });
         ltdc.bpcr.write(|w| {
             w.ahbp()
-                .bits((HSYNC + HBP - 1) as u16)
+                .bits((hsync + hbp - 1) as u16)
                 .avbp()
-                .bits((VSYNC + VBP - 1) as u16)
+                .bits((vsync + vbp - 1) as u16)
         });
         ltdc.awcr.write(|w| {
             w.aaw()
-                .bits((HSYNC + HBP + LCD_WIDTH - 1) as u16)
+                .bits((hsync + hbp + panel.width - 1) as u16)
                 .aah()
-                .bits((VSYNC + VBP + LCD_HEIGHT - 1) as u16)
+                .bits((vsync + vbp + panel.height - 1) as u16)
         });
         ltdc.twcr.write(|w| {
             w.totalw()
-                .bits((HSYNC + HBP + LCD_WIDTH + HFP - 1) as u16)
+                .bits((hsync + hbp + panel.width + hfp - 1) as u16)
                 .totalh()
-                .bits((VSYNC + VBP + LCD_HEIGHT + VFP - 1) as u16)
+                .bits((vsync + vbp + panel.height + vfp - 1) as u16)
         });
 
-        // Polarity: configure pixel clock sampling edge; and optionally HS/VS/DE via feature flags
-        // Match C example: pixel clock on rising edge (active high)
-        #[cfg(feature = "pcpol-falling")]
-        {
-            ltdc.gcr.modify(|_, w| w.pcpol().clear_bit());
-        }
-        #[cfg(not(feature = "pcpol-falling"))]
-        {
-            ltdc.gcr.modify(|_, w| w.pcpol().set_bit());
-        }
-
-        // Optional polarity overrides via features (if none set, keep reset defaults; C example leaves HS/VS/DE defaults)
-        #[cfg(feature = "hsync-high")]
-        {
-            ltdc.gcr.modify(|_, w| w.hspol().set_bit());
-        }
-        #[cfg(feature = "hsync-low")]
-        {
-            ltdc.gcr.modify(|_, w| w.hspol().clear_bit());
-        }
-        #[cfg(feature = "vsync-high")]
-        {
-            ltdc.gcr.modify(|_, w| w.vspol().set_bit());
-        }
-        #[cfg(feature = "vsync-low")]
-        {
-            ltdc.gcr.modify(|_, w| w.vspol().clear_bit());
-        }
-        #[cfg(feature = "de-high")]
-        {
-            ltdc.gcr.modify(|_, w| w.depol().set_bit());
-        }
-        #[cfg(feature = "de-low")]
-        {
-            ltdc.gcr.modify(|_, w| w.depol().clear_bit());
-        }
+        // Clock edge and sync polarities
+        Self::program_polarity(ltdc, &config);
+        unsafe { CONFIG = config };
 
-        // Background color black
+        // Background color black; on a panel bigger than the surface it is
+        // the letterbox border too
         // BCCR: background color components (all zero = black)
         ltdc.bccr
             .write(|w| w.bcblue().bits(0).bcgreen().bits(0).bcred().bits(0));
 
-        // Do not enable LTDC interrupts until a Rust handler is provided
+        // Count FIFO underruns and transfer errors in LCD_TFT_1 below
+        ltdc.ier
+            .modify(|_, w| w.fuie().enabled().terrie().enabled());
+        unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::LCD_TFT_1) };
 
-        // Layer 1 config (ARGB8888, full screen)
-        {
-            let h_start = HSYNC + HBP + 0;
-            let h_stop = HSYNC + HBP + LCD_WIDTH - 1;
-            ltdc.layer1.whpcr.write(|w| {
-                w.whstpos()
-                    .bits(h_start as u16)
-                    .whsppos()
-                    .bits(h_stop as u16)
-            });
-            let v_start = VSYNC + VBP + 0;
-            let v_stop = VSYNC + VBP + LCD_HEIGHT - 1;
-            ltdc.layer1.wvpcr.write(|w| {
-                w.wvstpos()
-                    .bits(v_start as u16)
-                    .wvsppos()
-                    .bits(v_stop as u16)
-            });
+        // Line interrupt on the first line after the active area, i.e. the
+        // start of vertical blanking, counted in LCD_TFT below
+        ltdc.lipcr
+            .write(|w| w.lipos().bits((vsync + vbp + panel.height) as u16));
+        ltdc.ier.modify(|_, w| w.lie().enabled());
+        unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::LCD_TFT) };
+
+        // Whatever the registers held before, every layer register below
+        // is written afresh
+        unsafe { SHADOWS = [LayerShadow::UNKNOWN; 2] };
+        let driver = Self { ltdc };
 
-            // Pixel format selectable: RGB565=2 (when l1-16bpp), else ARGB8888=0
-            #[cfg(feature = "l1-16bpp")]
-            ltdc.layer1.pfcr.write(|w| w.pf().bits(2));
-            #[cfg(not(feature = "l1-16bpp"))]
-            ltdc.layer1.pfcr.write(|w| w.pf().bits(0));
-            // Framebuffer address
-            ltdc.layer1.cfbar.write(|w| w.cfbadd().bits(LAYER1_BASE));
-            // CFBLR: CFBP = pitch in bytes, CFBLL = pitch in bytes + 3
-            let pitch_bytes = LCD_WIDTH * LAYER1_BPP;
-            let pitch = pitch_bytes as u16;
-            let line_len = (pitch_bytes + 3) as u16;
-            ltdc.layer1
-                .cfblr
-                .write(|w| w.cfbp().bits(pitch).cfbll().bits(line_len));
-            // Number of lines
-            ltdc.layer1
-                .cfblnr
-                .write(|w| w.cfblnbr().bits(LCD_HEIGHT as u16));
+        // Layer 1 config (full screen, LAYER1_FORMAT)
+        {
+            driver.program_layer(Layer::Layer1, LayerConfig::full(&DISPLAY_MEMORY.layer1));
             // Alpha and blending
-            ltdc.layer1.cacr.write(|w| w.consta().bits(0xFF));
-            ltdc.layer1.bfcr.write(|w| unsafe {
-                w.bf1()
-                    .bits(6) // BF1: pixel alpha x const alpha
-                    .bf2()
-                    .bits(7) // BF2: pixel alpha x const alpha
-            });
+            driver.write_reg(Layer::Layer1, Reg::Cacr, 0xFF);
+            driver.program_blend(Layer::Layer1, BlendMode::PixelAlpha);
             // Enable layer
             ltdc.layer1.cr.modify(|_, w| w.len().set_bit());
         }
 
-        // Layer 2 config (ARGB8888, 64x64)
+        // Layer 2 config (SPRITE_FORMAT, LAYER2_SIDE square)
         {
-            let h_start = HSYNC + HBP + 0;
-            let h_stop = HSYNC + HBP + LAYER2_W - 1;
-            ltdc.layer2.whpcr.write(|w| {
-                w.whstpos()
-                    .bits(h_start as u16)
-                    .whsppos()
-                    .bits(h_stop as u16)
-            });
-            let v_start = VSYNC + VBP + 0;
-            let v_stop = VSYNC + VBP + LAYER2_H - 1;
-            ltdc.layer2.wvpcr.write(|w| {
-                w.wvstpos()
-                    .bits(v_start as u16)
-                    .wvsppos()
-                    .bits(v_stop as u16)
-            });
-
-            // Pixel format: ARGB8888 = 0 (per LTDC PFCR encoding)
-            ltdc.layer2.pfcr.write(|w| w.pf().bits(0));
-            // Framebuffer address
-            ltdc.layer2.cfbar.write(|w| w.cfbadd().bits(LAYER2_BASE));
-            // CFBLR for layer2: CFBP = pitch in bytes, CFBLL = pitch in bytes + 3
-            let pitch_bytes = LAYER2_W * LAYER2_BPP;
-            let pitch = pitch_bytes as u16;
-            let line_len = (pitch_bytes + 3) as u16;
-            ltdc.layer2
-                .cfblr
-                .write(|w| w.cfbp().bits(pitch).cfbll().bits(line_len));
-            // Number of lines
-            ltdc.layer2
-                .cfblnr
-                .write(|w| w.cfblnbr().bits(LAYER2_H as u16));
+            driver.program_layer(Layer::Layer2, LayerConfig::full(&DISPLAY_MEMORY.layer2));
             // Alpha and blending
-            #[cfg(feature = "l2-opaque")]
-            {
-                // Use constant alpha only: BF1=CA, BF2=1-CA
-                ltdc.layer2.cacr.write(|w| w.consta().bits(0xFF));
-                ltdc.layer2.bfcr.write(|w| unsafe {
-                    w.bf1()
-                        .bits(4) // CA
-                        .bf2()
-                        .bits(5) // 1-CA
-                });
-            }
-            #[cfg(not(feature = "l2-opaque"))]
-            {
-                // Default: pixel alpha x const alpha
-                ltdc.layer2.cacr.write(|w| w.consta().bits(0xFF));
-                ltdc.layer2
-                    .bfcr
-                    .write(|w| unsafe { w.bf1().bits(6).bf2().bits(7) });
-            }
+            driver.write_reg(Layer::Layer2, Reg::Cacr, 0xFF);
+            driver.program_blend(Layer::Layer2, config.layer2_blend);
             // Enable layer
             ltdc.layer2.cr.modify(|_, w| w.len().set_bit());
         }
 
         // Reload shadow regs (vertical blank reload) before enabling
+        framebuffer::flush();
         ltdc.srcr.modify(|_, w| w.vbr().set_bit());
         // Enable LTDC
         ltdc.gcr.modify(|_, w| w.ltdcen().set_bit());
-        debug_assert!(ltdc.gcr.read().ltdcen().bit_is_set());
+        if ltdc.gcr.read().ltdcen().bit_is_clear() {
+            return Err(HwError::NoResponse);
+        }
+        log::debug!(
+            "LTDC on, {} panel {}x{}, layer 1 at {} bytes/pixel",
+            panel.name,
+            panel.width,
+            panel.height,
+            Self::layer1_format().bytes_per_pixel()
+        );
+
+        Ok(driver)
+    }
+
+    // Write `bits` to a layer register unless the shadow says it holds them
+    // already; true if it was written
+    fn write_reg(&self, layer: Layer, reg: Reg, bits: u32) -> bool {
+        let shadow = unsafe { &mut SHADOWS[layer as usize].0[reg as usize] };
+        if *shadow == Some(bits) {
+            return false;
+        }
+        let regs = self.layer_regs(layer);
+        // SAFETY: every caller builds `bits` from the register's field layout
+        unsafe {
+            match reg {
+                Reg::Whpcr => regs.whpcr.write(|w| w.bits(bits)),
+                Reg::Wvpcr => regs.wvpcr.write(|w| w.bits(bits)),
+                Reg::Pfcr => regs.pfcr.write(|w| w.bits(bits)),
+                Reg::Cfbar => regs.cfbar.write(|w| w.bits(bits)),
+                Reg::Cfblr => regs.cfblr.write(|w| w.bits(bits)),
+                Reg::Cfblnr => regs.cfblnr.write(|w| w.bits(bits)),
+                Reg::Cacr => regs.cacr.write(|w| w.bits(bits)),
+                Reg::Bfcr => regs.bfcr.write(|w| w.bits(bits)),
+                Reg::Ckcr => regs.ckcr.write(|w| w.bits(bits)),
+            }
+        }
+        *shadow = Some(bits);
+        true
+    }
 
-        Self { ltdc }
+    // Several registers of one layer; true if any was written
+    fn write_regs(&self, layer: Layer, writes: &[(Reg, u32)]) -> bool {
+        writes.iter().fold(false, |changed, &(reg, bits)| {
+            self.write_reg(layer, reg, bits) || changed
+        })
+    }
+
+    // The value last written to a layer register, if known
+    fn shadow(layer: Layer, reg: Reg) -> Option<u32> {
+        unsafe { SHADOWS[layer as usize].0[reg as usize] }
+    }
+
+    // Latch the shadow registers if a setter wrote any: at the next VBlank,
+    // or straight away with `immediate`
+    fn reload_if(&self, changed: bool, immediate: bool) {
+        if !changed {
+            unsafe { UNCHANGED = UNCHANGED.wrapping_add(1) };
+            return;
+        }
+        unsafe { RELOADS = RELOADS.wrapping_add(1) };
+        // The new address may point at a buffer just drawn: its pixels
+        // must be in SDRAM before the LTDC starts fetching from it
+        framebuffer::flush();
+        if immediate {
+            self.ltdc.srcr.modify(|_, w| w.imr().set_bit());
+        } else {
+            self.ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+        }
+    }
+
+    // (reloads, unchanged) since boot: shadow reloads asked for, and setter
+    // calls that found every register already as wanted
+    pub fn reload_counts() -> (u32, u32) {
+        unsafe { (RELOADS, UNCHANGED) }
     }
 
     pub fn set_layer2_position(&self, x: u32, y: u32) {
         use core::cmp::min;
-        let ltdc = &self.ltdc;
+        // The window may have been resized by configure_layer
+        let (layer2_w, layer2_h) = self.layer_size(Layer::Layer2);
         // Constrain to screen bounds
-        let x = min(x, LCD_WIDTH.saturating_sub(LAYER2_W));
-        let y = min(y, LCD_HEIGHT.saturating_sub(LAYER2_H));
-        let h_start = HSYNC + HBP + x;
-        let h_stop = h_start + LAYER2_W - 1;
-        let v_start = VSYNC + VBP + y;
-        let v_stop = v_start + LAYER2_H - 1;
-        ltdc.layer2.whpcr.write(|w| {
-            w.whstpos()
-                .bits(h_start as u16)
-                .whsppos()
-                .bits(h_stop as u16)
+        let (width, height) = surface();
+        let x = min(x, width.saturating_sub(layer2_w));
+        let y = min(y, height.saturating_sub(layer2_h));
+        let moved = self.write_regs(
+            Layer::Layer2,
+            &[
+                (Reg::Whpcr, window_bits(h_start(x), layer2_w)),
+                (Reg::Wvpcr, window_bits(v_start(y), layer2_h)),
+            ],
+        );
+        // Apply position update
+        self.reload_if(moved, unsafe { CONFIG.layer2_immediate });
+    }
+
+    // Layer 1 constant alpha; below 0xFF the black background shows through,
+    // which dims the whole picture
+    pub fn set_layer1_alpha(&self, alpha: u8) {
+        let changed = self.write_reg(Layer::Layer1, Reg::Cacr, alpha as u32);
+        // Apply at next VBlank
+        self.reload_if(changed, false);
+    }
+
+    // LTDC background color (RGB888) shown wherever no layer is opaque.
+    // BCCR is not shadowed, so this takes effect immediately.
+    pub fn set_background_color(&self, rgb: u32) {
+        self.ltdc.bccr.write(|w| {
+            w.bcred()
+                .bits((rgb >> 16) as u8)
+                .bcgreen()
+                .bits((rgb >> 8) as u8)
+                .bcblue()
+                .bits(rgb as u8)
         });
-        ltdc.layer2.wvpcr.write(|w| {
-            w.wvstpos()
-                .bits(v_start as u16)
-                .wvsppos()
-                .bits(v_stop as u16)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.ltdc.gcr.read().ltdcen().bit_is_set()
+    }
+
+    // Start or stop LTDC scan-out (and with it the SDRAM reads)
+    pub fn set_enabled(&self, on: bool) {
+        self.ltdc.gcr.modify(|_, w| w.ltdcen().bit(on));
+    }
+
+    // Show or hide a layer, keeping its settings; latched at VBlank
+    pub fn set_layer_enabled(&self, layer: Layer, on: bool) {
+        let regs = self.layer_regs(layer);
+        let was_on = regs.cr.read().len().bit_is_set();
+        regs.cr.modify(|_, w| w.len().bit(on));
+        self.reload_if(was_on != on, false);
+    }
+
+    pub fn set_layer2_alpha(&self, alpha: u8) {
+        let changed = self.write_reg(Layer::Layer2, Reg::Cacr, alpha as u32);
+        // Apply at next VBlank
+        self.reload_if(changed, false);
+    }
+
+    fn layer_regs(&self, layer: Layer) -> &pac::ltdc::LAYER {
+        match layer {
+            Layer::Layer1 => &self.ltdc.layer1,
+            Layer::Layer2 => &self.ltdc.layer2,
+        }
+    }
+
+    // Current window size of a layer, from the shadow of WHPCR/WVPCR or,
+    // before they have been written, read back from them
+    pub fn layer_size(&self, layer: Layer) -> (u32, u32) {
+        let regs = self.layer_regs(layer);
+        let whpcr = Self::shadow(layer, Reg::Whpcr).unwrap_or_else(|| regs.whpcr.read().bits());
+        let wvpcr = Self::shadow(layer, Reg::Wvpcr).unwrap_or_else(|| regs.wvpcr.read().bits());
+        let len = |bits: u32| ((bits >> 16 & 0xFFF) + 1).saturating_sub(bits & 0xFFF);
+        (len(whpcr), len(wvpcr))
+    }
+
+    // Reprogram a layer's window, pixel format and framebuffer at runtime.
+    // The change is latched at the next VBlank so scan-out never sees a half
+    // configured layer; the caller must keep the framebuffer at `base_addr`
+    // large enough for w * h pixels in the new format. FrameBuffer draws
+    // Layer 1 in whatever format was set last, but Layer 2 always in
+    // LAYER2_FORMAT.
+    pub fn configure_layer(&self, layer: Layer, config: LayerConfig) {
+        // Clip the window to the surface
+        let (width, height) = surface();
+        let x = config.x.min(width - 1);
+        let y = config.y.min(height - 1);
+        let config = LayerConfig {
+            x,
+            y,
+            w: config.w.clamp(1, width - x),
+            h: config.h.clamp(1, height - y),
+            ..config
+        };
+        let changed = self.program_layer(layer, config);
+        if layer == Layer::Layer1 {
+            unsafe {
+                L1_FRONT = config.base_addr;
+                L1_FORMAT = config.format;
+                L1_OFFSET = (0, 0);
+            }
+        }
+        self.reload_if(changed, false);
+    }
+
+    // Change how a layer blends over the ones below, latched at VBlank
+    pub fn set_blend_mode(&self, layer: Layer, mode: BlendMode) {
+        let changed = self.program_blend(layer, mode);
+        self.reload_if(changed, false);
+    }
+
+    // Make pixels of one RGB888 color in `layer` transparent, compared after
+    // any CLUT lookup; latched at VBlank
+    pub fn set_color_key(&self, layer: Layer, rgb: u32) {
+        let regs = self.layer_regs(layer);
+        let changed = self.write_reg(layer, Reg::Ckcr, rgb & 0x00FF_FFFF);
+        let was_on = regs.cr.read().colken().bit_is_set();
+        regs.cr.modify(|_, w| w.colken().set_bit());
+        self.reload_if(changed || !was_on, false);
+    }
+
+    pub fn clear_color_key(&self, layer: Layer) {
+        let regs = self.layer_regs(layer);
+        let was_on = regs.cr.read().colken().bit_is_set();
+        regs.cr.modify(|_, w| w.colken().clear_bit());
+        self.reload_if(was_on, false);
+    }
+
+    // Load RGB888 `palette` into the layer's CLUT from index 0 (256 entries
+    // at most, the rest are ignored) and turn the lookup on. The CLUT is not
+    // shadowed: load it while the layer is hidden or in vertical blanking,
+    // or the frame being scanned out may show a mix of old and new colors.
+    // The lookup only applies to the indexed formats (L8, AL44, AL88).
+    pub fn load_clut(&self, layer: Layer, palette: &[u32]) {
+        let regs = self.layer_regs(layer);
+        for (index, &rgb) in palette.iter().take(CLUT_SIZE).enumerate() {
+            let entry = (index as u32) << 24 | (rgb & 0x00FF_FFFF);
+            regs.clutwr.write(|w| unsafe { w.bits(entry) });
+        }
+        regs.cr.modify(|_, w| w.cluten().set_bit());
+        self.reload_if(true, false);
+    }
+
+    // Write window, format and framebuffer registers (shadowed until
+    // reload); true if any changed
+    fn program_layer(&self, layer: Layer, config: LayerConfig) -> bool {
+        // Lines are read whole, so the pitch is the line length
+        let pitch_bytes = config.w * config.format.bytes_per_pixel();
+        self.write_regs(
+            layer,
+            &[
+                (Reg::Whpcr, window_bits(h_start(config.x), config.w)),
+                (Reg::Wvpcr, window_bits(v_start(config.y), config.h)),
+                (Reg::Pfcr, config.format as u32),
+                (Reg::Cfbar, config.base_addr),
+                (Reg::Cfblr, line_length_bits(pitch_bytes, pitch_bytes)),
+                (Reg::Cfblnr, config.h),
+            ],
+        )
+    }
+
+    fn program_polarity(ltdc: &pac::LTDC, config: &LtdcConfig) {
+        ltdc.gcr.modify(|_, w| {
+            w.pcpol()
+                .bit(config.pixel_clock == ClockEdge::Rising)
+                .hspol()
+                .bit(config.hsync.bit())
+                .vspol()
+                .bit(config.vsync.bit())
+                .depol()
+                .bit(config.data_enable.bit())
         });
-        // Apply position update
-        #[cfg(feature = "l2-immediate")]
-        {
-            ltdc.srcr.modify(|_, w| w.imr().set_bit());
+    }
+
+    // Configuration currently programmed
+    pub fn config() -> LtdcConfig {
+        unsafe { CONFIG }
+    }
+
+    // Reprogram polarities and Layer 2 blending on a running LTDC. The
+    // polarity bits are not shadowed and take effect on the next pixel.
+    pub fn apply_config(&self, config: LtdcConfig) {
+        Self::program_polarity(self.ltdc, &config);
+        let changed = self.program_blend(Layer::Layer2, config.layer2_blend);
+        self.reload_if(changed, false);
+        unsafe { CONFIG = config };
+    }
+
+    // BFCR: BF1 in bits 8-10, BF2 in bits 0-2; true if it changed
+    fn program_blend(&self, layer: Layer, mode: BlendMode) -> bool {
+        let (bf1, bf2) = mode.factors();
+        self.write_reg(layer, Reg::Bfcr, (bf1 as u32) << 8 | bf2 as u32)
+    }
+
+    // Pixel format Layer 1 is currently scanned out in
+    pub fn layer1_format() -> PixelFormat {
+        unsafe { L1_FORMAT }
+    }
+
+    // Error counters since boot
+    // Vertical blanks since the LTDC was set up; wraps
+    pub fn vblank_count() -> u32 {
+        unsafe { core::ptr::read_volatile(&raw const VBLANKS) }
+    }
+
+    pub fn error_counts() -> LtdcErrors {
+        unsafe {
+            LtdcErrors {
+                underruns: UNDERRUNS,
+                transfer_errors: TRANSFER_ERRORS,
+            }
         }
-        #[cfg(not(feature = "l2-immediate"))]
-        {
-            ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+    }
+
+    // Call once per frame. The interrupt already re-latches the shadow
+    // registers after every error; if underruns keep coming anyway, Layer 1
+    // is dropped to RGB565 to halve its share of SDRAM bandwidth.
+    pub fn service_errors() {
+        let underruns = unsafe { UNDERRUNS };
+        let streak = unsafe {
+            if underruns != UNDERRUNS_SEEN {
+                // Into the black box once per run of frames with underruns
+                if UNDERRUN_STREAK == 0 {
+                    blackbox::record(Event::Underrun(underruns));
+                }
+                UNDERRUN_STREAK += 1;
+            } else {
+                UNDERRUN_STREAK = 0;
+            }
+            UNDERRUNS_SEEN = underruns;
+            UNDERRUN_STREAK
+        };
+
+        if streak >= UNDERRUN_STREAK_LIMIT && Self::layer1_format() == PixelFormat::Argb8888 {
+            log::warn!(
+                "{} frames with LTDC underruns ({} total), layer 1 -> RGB565",
+                streak,
+                underruns
+            );
+            Self::set_layer1_format(PixelFormat::Rgb565);
+            unsafe { UNDERRUN_STREAK = 0 };
         }
     }
 
-    pub fn set_layer2_alpha(&self, alpha: u8) {
-        let ltdc = &self.ltdc;
-        ltdc.layer2.cacr.write(|w| w.consta().bits(alpha));
-        // Apply at next VBlank
-        ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+    // Repack the Layer 1 buffers between ARGB8888 and RGB565 in place and
+    // reprogram the layer, so the picture survives the switch. Going back
+    // up only works for buffers laid out for ARGB8888; anything else is
+    // left as it is.
+    pub fn set_layer1_format(format: PixelFormat) {
+        let from = Self::layer1_format();
+        let mem = &DISPLAY_MEMORY;
+        match (from, format) {
+            (PixelFormat::Argb8888, PixelFormat::Rgb565) => {
+                Self::repack_to_rgb565(&mem.layer1);
+                Self::repack_to_rgb565(&mem.layer1_b);
+                Self::repack_to_rgb565(&mem.retro);
+            }
+            (PixelFormat::Rgb565, PixelFormat::Argb8888) if LAYER1_FORMAT == format => {
+                Self::repack_to_argb8888(&mem.layer1);
+                Self::repack_to_argb8888(&mem.layer1_b);
+                Self::repack_to_argb8888(&mem.retro);
+            }
+            _ => return,
+        }
+        framebuffer::flush();
+
+        let driver = Self::attach();
+        let pitch_bytes = surface().0 * format.bytes_per_pixel();
+        let changed = driver.write_regs(
+            Layer::Layer1,
+            &[
+                (Reg::Pfcr, format as u32),
+                (Reg::Cfblr, line_length_bits(pitch_bytes, pitch_bytes)),
+            ],
+        );
+        driver.reload_if(changed, false);
+        unsafe { L1_FORMAT = format };
+    }
+
+    // ARGB8888 pixels of a Layer 1 buffer to RGB565, packed from the start
+    fn repack_to_rgb565<const W: u32, const H: u32>(buffer: &FramebufferRegion<W, H, Layer1Pixel>) {
+        let src = buffer.base as *const u32;
+        let dst = buffer.base as *mut u16;
+        // Walking forward, each 16-bit write lands at or below the 32-bit
+        // word it came from, so no source pixel is overwritten before use
+        for i in 0..FramebufferRegion::<W, H, Layer1Pixel>::PIXELS as usize {
+            unsafe {
+                let argb = core::ptr::read_volatile(src.add(i));
+                core::ptr::write_volatile(dst.add(i), Argb8888(argb).to_rgb565().0);
+            }
+        }
+    }
+
+    // The reverse: RGB565 packed from the start back out to ARGB8888
+    fn repack_to_argb8888<const W: u32, const H: u32>(
+        buffer: &FramebufferRegion<W, H, Layer1Pixel>,
+    ) {
+        let src = buffer.base as *const u16;
+        let dst = buffer.base as *mut u32;
+        // Walking backward, each 32-bit write only covers 16-bit pixels
+        // further on, which are done already
+        for i in (0..FramebufferRegion::<W, H, Layer1Pixel>::PIXELS as usize).rev() {
+            unsafe {
+                let rgb565 = core::ptr::read_volatile(src.add(i));
+                core::ptr::write_volatile(dst.add(i), Rgb565(rgb565).to_argb8888().0);
+            }
+        }
     }
 
     // Return the current front and back addresses for Layer1
@@ -286,14 +918,81 @@ impl LcdDriver {
         }
     }
 
+    // Shift the whole Layer 1 picture by (dx, dy) panel pixels, for screen
+    // shake. The window moves over by the shift and shrinks to match, and
+    // scan-out starts that far into the framebuffer when going left or up,
+    // so the picture itself stays put in memory and the strip it uncovers
+    // shows the background color. Latched at VBlank; (0, 0) puts it back.
+    pub fn set_layer1_offset(&self, dx: i32, dy: i32) {
+        let (full_w, full_h) = surface();
+        let dx = dx.clamp(1 - full_w as i32, full_w as i32 - 1);
+        let dy = dy.clamp(1 - full_h as i32, full_h as i32 - 1);
+        unsafe { L1_OFFSET = (dx, dy) };
+        let width = full_w - dx.unsigned_abs();
+        let height = full_h - dy.unsigned_abs();
+        // The pitch stays a full line; only the part read from each changes
+        let bpp = Self::layer1_format().bytes_per_pixel();
+        let changed = self.write_regs(
+            Layer::Layer1,
+            &[
+                (Reg::Whpcr, window_bits(h_start(dx.max(0) as u32), width)),
+                (Reg::Wvpcr, window_bits(v_start(dy.max(0) as u32), height)),
+                (Reg::Cfbar, Self::layer1_scan_addr(unsafe { L1_FRONT })),
+                (Reg::Cfblr, line_length_bits(full_w * bpp, width * bpp)),
+                (Reg::Cfblnr, height),
+            ],
+        );
+        self.reload_if(changed, false);
+    }
+
+    // Program Layer 2 as the full-screen `config` shifted (dx, dy) panel
+    // pixels, the way set_layer1_offset shifts Layer 1: the window shrinks
+    // by the shift and scan-out skips what moved off the left and top.
+    // configure_layer puts it back.
+    pub fn set_layer2_offset(&self, config: LayerConfig, dx: i32, dy: i32) {
+        let dx = dx.clamp(1 - config.w as i32, config.w as i32 - 1);
+        let dy = dy.clamp(1 - config.h as i32, config.h as i32 - 1);
+        let width = config.w - dx.unsigned_abs();
+        let height = config.h - dy.unsigned_abs();
+        let bpp = config.format.bytes_per_pixel();
+        let skip = ((-dy).max(0) as u32 * config.w + (-dx).max(0) as u32) * bpp;
+        let changed = self.write_regs(
+            Layer::Layer2,
+            &[
+                (
+                    Reg::Whpcr,
+                    window_bits(h_start(config.x + dx.max(0) as u32), width),
+                ),
+                (
+                    Reg::Wvpcr,
+                    window_bits(v_start(config.y + dy.max(0) as u32), height),
+                ),
+                (Reg::Pfcr, config.format as u32),
+                (Reg::Cfbar, config.base_addr + skip),
+                (Reg::Cfblr, line_length_bits(config.w * bpp, width * bpp)),
+                (Reg::Cfblnr, height),
+            ],
+        );
+        self.reload_if(changed, false);
+    }
+
+    // Where scan-out of the Layer 1 buffer at `base` starts with the current
+    // offset: past the columns and rows shifted off the left and top
+    fn layer1_scan_addr(base: u32) -> u32 {
+        let (dx, dy) = unsafe { L1_OFFSET };
+        let bpp = Self::layer1_format().bytes_per_pixel();
+        let skip_x = (-dx).max(0) as u32;
+        let skip_y = (-dy).max(0) as u32;
+        base + (skip_y * surface().0 + skip_x) * bpp
+    }
+
     // Swap Layer1 front/back by updating CFBAR to the back buffer and latching on VBlank
     #[cfg(feature = "overlay")]
     pub fn swap_layer1_buffers(&self) {
-        let ltdc = &self.ltdc;
         let new_front = Self::layer1_back_addr();
-        ltdc.layer1.cfbar.write(|w| w.cfbadd().bits(new_front));
+        let changed = self.write_reg(Layer::Layer1, Reg::Cfbar, Self::layer1_scan_addr(new_front));
         // Latch address change at next VBlank
-        ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+        self.reload_if(changed, false);
         // Update tracker
         unsafe {
             L1_FRONT = new_front;
@@ -302,8 +1001,8 @@ impl LcdDriver {
 
     fn setup_ltdc_gpio() {
         // Enable GPIO clocks: A,B,C,D,F,G
-        let dp = unsafe { pac::Peripherals::steal() };
-        let rcc = dp.RCC;
+        let dp = resources::pac();
+        let rcc = &dp.RCC;
         rcc.ahb1enr.modify(|_, w| {
             w.gpioaen()
                 .enabled()
@@ -394,6 +1093,24 @@ impl LcdDriver {
         set_af(pa, 4, 14);
     }
 
+    // For the hardware report: the refresh rate in Hz, counted from the
+    // vertical blanks seen over PROBE_MS
+    pub fn probe(&self) -> Result<u32, HwError> {
+        let rcc = &resources::pac().RCC;
+        if rcc.cr.read().pllsairdy().is_not_ready() {
+            return Err(HwError::ClockNotReady);
+        }
+        if !self.is_enabled() {
+            return Err(HwError::NoResponse);
+        }
+        let start = Self::vblank_count();
+        clock::delay_ms(PROBE_MS);
+        match Self::vblank_count().wrapping_sub(start) {
+            0 => Err(HwError::Timeout),
+            frames => Ok(frames * 1000 / PROBE_MS),
+        }
+    }
+
     // --- Debug helpers ---
     #[allow(dead_code)]
     pub fn ltdc_status(&self) -> (u32, u32) {
@@ -402,3 +1119,32 @@ impl LcdDriver {
         (ltdc.isr.read().bits(), ltdc.ier.read().bits())
     }
 }
+
+// LTDC error interrupt: count, clear, and re-latch the shadow registers so a
+// glitched reload does not stick for the rest of the session
+#[interrupt]
+fn LCD_TFT_1() {
+    let dp = resources::pac();
+    let ltdc = &dp.LTDC;
+    let isr = ltdc.isr.read();
+    unsafe {
+        if isr.fuif().bit_is_set() {
+            UNDERRUNS = UNDERRUNS.wrapping_add(1);
+        }
+        if isr.terrif().bit_is_set() {
+            TRANSFER_ERRORS = TRANSFER_ERRORS.wrapping_add(1);
+        }
+    }
+    ltdc.icr.write(|w| w.cfuif().clear().cterrif().clear());
+    framebuffer::flush();
+    ltdc.srcr.modify(|_, w| w.vbr().set_bit());
+}
+
+// Line interrupt at the start of vertical blanking
+#[interrupt]
+fn LCD_TFT() {
+    let dp = resources::pac();
+    dp.LTDC.icr.write(|w| w.clif().clear());
+    unsafe { VBLANKS = VBLANKS.wrapping_add(1) };
+    executor::on_vblank();
+}
@@ -0,0 +1,77 @@
+//! Leaderboard page and the initials entry for a run that made it
+//!
+//! The page opens with a press on the stats page, and a press there turns
+//! it to the time attack runs; the entry comes up over the game-over screen
+//! when a run beats the tenth best of its board. Both are drawn on the
+//! Layer 2 overlay like the stats page, the table from the persistent store
+//! (see `stats`).
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use core_logic::leaderboard::{Initials, INITIALS};
+
+use crate::config::Coord;
+use crate::fmt_buf::FmtBuf;
+use crate::lang::{self, Msg};
+use crate::rtc::DateTime;
+use crate::stats::{self, Board};
+use crate::ui::{self, Ui};
+
+const ENTRY_TOP: Coord = 70;
+
+pub fn draw(board: Board) {
+    let table = stats::leaderboard(board);
+    let entries = table.entries();
+    // The title and a row per entry, or a caption for an empty table
+    let mut ui = Ui::screen(ui::centered_top(entries.len().max(1) + 1));
+    ui.label(lang::text(match board {
+        Board::Main => Msg::TopTen,
+        Board::TimeAttack => Msg::TimeAttackTopTen,
+    }));
+    if entries.is_empty() {
+        ui.caption(lang::text(Msg::NoRunsYet));
+    }
+    let mut line: FmtBuf<32> = FmtBuf::new();
+    for (rank, entry) in entries.iter().enumerate() {
+        let at = DateTime::from_timestamp(entry.at);
+        line.clear();
+        let _ = write!(
+            line,
+            "{:>2} {} {:>5} {:02}-{:02}",
+            rank + 1,
+            entry.initials(),
+            entry.score,
+            at.month,
+            at.day
+        );
+        ui.label(line.as_str());
+    }
+    ui.end();
+}
+
+// The initials being entered for a run at `rank`, counting from 0, a
+// caret under the letter being changed; the score is on the game-over
+// screen showing through
+pub fn draw_entry(initials: &Initials, rank: usize) {
+    let mut ui = Ui::screen(ENTRY_TOP);
+    ui.label(lang::text(Msg::MadeTopTen));
+    let mut line: FmtBuf<8> = FmtBuf::new();
+    let _ = write!(line, "{}", rank + 1);
+    ui.value(lang::text(Msg::Rank), line.as_str());
+    ui.space(1);
+
+    // Letters spaced out, the caret row as wide so both center alike
+    let mut letters: FmtBuf<8> = FmtBuf::new();
+    let mut caret: FmtBuf<8> = FmtBuf::new();
+    for (i, &letter) in initials.letters().iter().enumerate() {
+        let gap = if i + 1 < INITIALS { " " } else { "" };
+        let _ = write!(letters, "{}{}", letter as char, gap);
+        let mark = if i == initials.cursor() { '^' } else { ' ' };
+        let _ = write!(caret, "{}{}", mark, gap);
+    }
+    ui.label(letters.as_str());
+    ui.label(caret.as_str());
+    ui.caption(lang::text(Msg::PressLetterHoldNext));
+    ui.end();
+}
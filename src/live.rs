@@ -0,0 +1,132 @@
+//! Live tuning over RTT
+//!
+//! The `Rtt` job hands `poll` whatever the host has typed into the RTT
+//! down-channel. Whole lines are `core_logic::live` commands: `set` changes
+//! the running game at once, with no restart, and answers with the value
+//! or what it takes instead; `watch` picks the variables streamed back up
+//! the channel, a `name=value` line per poll:
+//!
+//! ```text
+//! > set gravity 2
+//! gravity 2
+//! > watch y vy fps
+//! y=142 vy=-3 fps=60
+//! ```
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use core_logic::live::{self, Command, Key, ParseError, Var, Watched};
+
+use crate::fmt_buf::FmtBuf;
+use crate::game::{Game, InputDevice};
+use crate::input_device;
+use crate::profiler;
+use crate::rtt;
+use crate::settings;
+
+const LINE_LEN: usize = 48;
+
+// The line typed so far, `TYPED` bytes of it
+static mut LINE: [u8; LINE_LEN] = [0; LINE_LEN];
+static mut TYPED: usize = 0;
+static mut WATCHED: Watched = Watched::NONE;
+
+// Run the commands that came in, then send the watched variables
+pub fn poll<T: InputDevice>(game: &mut Game<T>) {
+    let mut bytes = [0u8; 16];
+    loop {
+        let count = rtt::read(&mut bytes);
+        if count == 0 {
+            break;
+        }
+        for &byte in &bytes[..count] {
+            push(game, byte);
+        }
+    }
+    stream(game);
+}
+
+fn push<T: InputDevice>(game: &mut Game<T>, byte: u8) {
+    unsafe {
+        match byte {
+            b'\r' | b'\n' => {
+                let line = core::str::from_utf8(&LINE[..TYPED]).unwrap_or("");
+                if !line.trim().is_empty() {
+                    run(game, line);
+                }
+                TYPED = 0;
+            }
+            // A line too long for any command is cut short and refused
+            _ if TYPED < LINE_LEN => {
+                LINE[TYPED] = byte;
+                TYPED += 1;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn run<T: InputDevice>(game: &mut Game<T>, line: &str) {
+    let mut out: FmtBuf<96> = FmtBuf::new();
+    let _ = match live::parse(line) {
+        Ok(Command::Set(key, value)) => {
+            set(game, key, value);
+            writeln!(out, "{} {}", key.name(), value)
+        }
+        Ok(Command::Watch(watched)) => {
+            unsafe { WATCHED = watched };
+            write!(out, "watching")
+                .and_then(|_| {
+                    watched
+                        .iter()
+                        .try_for_each(|var| write!(out, " {}", var.name()))
+                })
+                .and_then(|_| writeln!(out))
+        }
+        Err(ParseError::Usage) => writeln!(out, "set <key> <value> | watch [<var>...]"),
+        Err(ParseError::Name) => writeln!(out, "keys: gravity flap gap speed inset tilt alpha")
+            .and_then(|_| writeln!(out, "vars: score y vy speed fps")),
+        Err(ParseError::Value(key)) => {
+            let (min, max) = key.range();
+            writeln!(out, "{} {}-{}", key.name(), min, max)
+        }
+    };
+    rtt::write(out.as_str().as_bytes());
+}
+
+fn set<T: InputDevice>(game: &mut Game<T>, key: Key, value: i32) {
+    match key {
+        Key::Tuning(param) => {
+            let mut tuning = game.tuning();
+            // In range, `parse` checked
+            let _ = tuning.set(param, value);
+            game.set_tuning(tuning);
+        }
+        Key::Tilt => settings::update(|settings| settings.tilt.threshold = value),
+        Key::Alpha => input_device::set_tilt_alpha(value),
+    }
+}
+
+fn stream<T: InputDevice>(game: &Game<T>) {
+    let watched = unsafe { WATCHED };
+    if watched.is_empty() {
+        return;
+    }
+    let snapshot = game.snapshot();
+    let mut out: FmtBuf<64> = FmtBuf::new();
+    for var in watched.iter() {
+        let value = match var {
+            Var::Score => game.score() as i32,
+            Var::Y => snapshot.player_y,
+            Var::Vy => snapshot.player_vy,
+            Var::Speed => game.obstacle_velocity() as i32,
+            Var::Fps => profiler::breakdown().fps() as i32,
+        };
+        let gap = if out.as_str().is_empty() { "" } else { " " };
+        let _ = write!(out, "{}{}={}", gap, var.name(), value);
+    }
+    let _ = writeln!(out);
+    rtt::write(out.as_str().as_bytes());
+}
@@ -0,0 +1,157 @@
+//! Logging over ITM stimulus port 0 (SWO)
+//!
+//! `log::error!` .. `log::trace!` take `format_args!` style arguments. Whether
+//! a call site logs is decided at compile time from the build's `MAX_LEVEL`
+//! and the per-module overrides in `MODULE_LEVELS`; a filtered-out call, or
+//! any call without the `log-itm` feature, compiles to nothing.
+//!
+//! Output only happens once the debug probe has enabled ITM and stimulus
+//! port 0 (e.g. probe-rs / OpenOCD with SWO capture), so a board without a
+//! probe attached never blocks on a full FIFO.
+#![allow(dead_code)]
+#![allow(unused_macros)]
+#![allow(unused_imports)]
+
+use core::fmt;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+// Most verbose level any module may log at
+pub const MAX_LEVEL: Level = if cfg!(debug_assertions) {
+    Level::Debug
+} else {
+    Level::Info
+};
+
+// Per-module verbosity, by module name under the crate root. Anything not
+// listed logs up to MAX_LEVEL.
+const MODULE_LEVELS: &[(&str, Level)] = &[
+    // Every retry of the MPU6050 bring-up times out several transfers
+    ("i2c", Level::Warn),
+];
+
+// Compile-time filter used by the macros
+pub const fn enabled(level: Level, module_path: &str) -> bool {
+    cfg!(feature = "log-itm")
+        && level as u8 <= MAX_LEVEL as u8
+        && level as u8 <= module_level(module_path) as u8
+}
+
+const fn module_level(module_path: &str) -> Level {
+    let path = strip_crate(module_path).as_bytes();
+    let mut i = 0;
+    while i < MODULE_LEVELS.len() {
+        let (name, level) = MODULE_LEVELS[i];
+        if bytes_eq(path, name.as_bytes()) {
+            return level;
+        }
+        i += 1;
+    }
+    MAX_LEVEL
+}
+
+// "flappy_bird_fresh::i2c" -> "i2c"
+const fn strip_crate(module_path: &str) -> &str {
+    let bytes = module_path.as_bytes();
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b':' && bytes[i + 1] == b':' {
+            let (_, rest) = bytes.split_at(i + 2);
+            return match core::str::from_utf8(rest) {
+                Ok(rest) => rest,
+                Err(_) => module_path,
+            };
+        }
+        i += 1;
+    }
+    module_path
+}
+
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// Emit one line; only reached from call sites that passed `enabled`
+pub fn write(level: Level, module_path: &str, args: fmt::Arguments) {
+    #[cfg(feature = "log-itm")]
+    {
+        let mut cp = unsafe { cortex_m::Peripherals::steal() };
+        let itm = &mut cp.ITM;
+        let itm_on = itm.tcr.read() & 1 != 0;
+        let port_on = itm.ter[0].read() & 1 != 0;
+        if itm_on && port_on {
+            cortex_m::itm::write_fmt(
+                &mut itm.stim[0],
+                format_args!(
+                    "{:<5} {}: {}\n",
+                    level.as_str(),
+                    strip_crate(module_path),
+                    args
+                ),
+            );
+        }
+    }
+    #[cfg(not(feature = "log-itm"))]
+    let _ = (level, module_path, args);
+}
+
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {{
+        const ON: bool = $crate::log::enabled($level, module_path!());
+        if ON {
+            $crate::log::write($level, module_path!(), format_args!($($arg)*));
+        }
+    }};
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Error, $($arg)*) };
+}
+
+// Exported as `warn`; the bare name clashes with the built-in attribute
+macro_rules! warn_ {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Warn, $($arg)*) };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Info, $($arg)*) };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Debug, $($arg)*) };
+}
+
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::log::log!($crate::log::Level::Trace, $($arg)*) };
+}
+
+pub(crate) use {debug, error, info, log, trace, warn_ as warn};
@@ -0,0 +1,251 @@
+//! Boot-time LTDC / ILI9341 consistency check
+//!
+//! The LTDC timing registers, the PLLSAI pixel clock and the ILI9341 RGB
+//! interface settings have to agree, otherwise the panel shows a shifted or
+//! rolling picture with no other symptom. This derives what the registers
+//! should hold from the panel profile and reports each disagreement; the
+//! ILI9341 settings only count on a panel that has one.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt;
+
+use crate::clock;
+use crate::ili9341;
+use crate::lcd;
+use crate::resources;
+use core_logic::panel::Panel;
+
+/// Timing the panel is meant to run with, in pixels / lines
+pub struct PanelProfile {
+    pub width: u32,
+    pub height: u32,
+    pub hsync: u32,
+    pub hbp: u32,
+    pub hfp: u32,
+    pub vsync: u32,
+    pub vbp: u32,
+    pub vfp: u32,
+    // ILI9341 DOTCLK limit (100 ns minimum cycle)
+    pub pclk_max_hz: u32,
+    // Slowest refresh that does not visibly flicker
+    pub min_refresh_hz: u32,
+    // Driven through an ILI9341's RGB interface
+    pub ili9341: bool,
+}
+
+// Slowest refresh that does not visibly flicker
+pub const MIN_REFRESH_HZ: u32 = 50;
+
+// The panel selected at startup (see lcd::panel)
+pub fn profile() -> PanelProfile {
+    PanelProfile::of(lcd::panel())
+}
+
+impl PanelProfile {
+    pub const fn of(panel: Panel) -> Self {
+        PanelProfile {
+            width: panel.width,
+            height: panel.height,
+            hsync: panel.timings.hsync,
+            hbp: panel.timings.hbp,
+            hfp: panel.timings.hfp,
+            vsync: panel.timings.vsync,
+            vbp: panel.timings.vbp,
+            vfp: panel.timings.vfp,
+            pclk_max_hz: panel.pclk_max_hz,
+            min_refresh_hz: MIN_REFRESH_HZ,
+            ili9341: panel.ili9341,
+        }
+    }
+
+    pub const fn total_width(&self) -> u32 {
+        self.hsync + self.hbp + self.width + self.hfp
+    }
+
+    pub const fn total_height(&self) -> u32 {
+        self.vsync + self.vbp + self.height + self.vfp
+    }
+
+    pub const fn pclk_min_hz(&self) -> u32 {
+        self.pclk_hz_at(self.min_refresh_hz)
+    }
+
+    // Pixel clock for `refresh_hz` frames a second
+    pub const fn pclk_hz_at(&self, refresh_hz: u32) -> u32 {
+        self.total_width() * self.total_height() * refresh_hz
+    }
+
+    // Frames a second at `pclk_hz`
+    pub const fn refresh_hz_at(&self, pclk_hz: u32) -> u32 {
+        pclk_hz / (self.total_width() * self.total_height())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Mismatch {
+    // LTDC register field holds a different value than the profile implies
+    Register {
+        name: &'static str,
+        expected: u32,
+        actual: u32,
+    },
+    PixelClock {
+        hz: u32,
+        min_hz: u32,
+        max_hz: u32,
+    },
+    // LTDC signal polarity disagrees with the ILI9341 RGB_IFC_CTL setting
+    Polarity {
+        signal: &'static str,
+    },
+    // ILI9341 is not set up for DE-mode RGB input
+    InterfaceMode,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatch::Register {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{} {} want {}", name, actual, expected),
+            Mismatch::PixelClock { hz, min_hz, max_hz } => write!(
+                f,
+                "PCLK {}k not {}k-{}k",
+                hz / 1000,
+                min_hz / 1000,
+                max_hz / 1000
+            ),
+            Mismatch::Polarity { signal } => write!(f, "{} polarity differs", signal),
+            Mismatch::InterfaceMode => write!(f, "ILI9341 not in RGB DE mode"),
+        }
+    }
+}
+
+// Pixel clock from the PLLSAI configuration actually programmed
+pub fn pixel_clock_hz() -> u32 {
+    clock::report().ltdc
+}
+
+// Compare the programmed hardware against `profile`, calling `report` for
+// every disagreement. Returns the number found.
+pub fn check(profile: &PanelProfile, mut report: impl FnMut(Mismatch)) -> usize {
+    let dp = resources::pac();
+    let ltdc = &dp.LTDC;
+    let mut count = 0;
+    let mut mismatch = |m: Mismatch| {
+        count += 1;
+        report(m);
+    };
+    let mut field = |name: &'static str, expected: u32, actual: u32| {
+        if expected != actual {
+            mismatch(Mismatch::Register {
+                name,
+                expected,
+                actual,
+            });
+        }
+    };
+
+    // Each LTDC timing register holds an accumulated position minus one
+    let sscr = ltdc.sscr.read();
+    field("HSW", profile.hsync - 1, sscr.hsw().bits() as u32);
+    field("VSH", profile.vsync - 1, sscr.vsh().bits() as u32);
+    let bpcr = ltdc.bpcr.read();
+    field(
+        "AHBP",
+        profile.hsync + profile.hbp - 1,
+        bpcr.ahbp().bits() as u32,
+    );
+    field(
+        "AVBP",
+        profile.vsync + profile.vbp - 1,
+        bpcr.avbp().bits() as u32,
+    );
+    let awcr = ltdc.awcr.read();
+    field(
+        "AAW",
+        profile.hsync + profile.hbp + profile.width - 1,
+        awcr.aaw().bits() as u32,
+    );
+    field(
+        "AAH",
+        profile.vsync + profile.vbp + profile.height - 1,
+        awcr.aah().bits() as u32,
+    );
+    let twcr = ltdc.twcr.read();
+    field(
+        "TOTALW",
+        profile.total_width() - 1,
+        twcr.totalw().bits() as u32,
+    );
+    field(
+        "TOTALH",
+        profile.total_height() - 1,
+        twcr.totalh().bits() as u32,
+    );
+
+    let hz = pixel_clock_hz();
+    let (min_hz, max_hz) = (profile.pclk_min_hz(), profile.pclk_max_hz);
+    if hz < min_hz || hz > max_hz {
+        mismatch(Mismatch::PixelClock { hz, min_hz, max_hz });
+    }
+    if !profile.ili9341 {
+        return count;
+    }
+
+    // RGB_IFC_CTL: RCM (bits 6:5) = 10 selects DE mode; bits 3:0 are
+    // VSPL, HSPL, DPL, EPL with 0 meaning active low sync, rising-edge
+    // sampling and active high enable
+    let rgb_ifc = ili9341::RGB_IFC_CTL_VALUE;
+    let ifc = ili9341::IFC_CTL_VALUE;
+    let rgb_mode = (ifc[2] >> 1) & 1 == 1 && (ifc[2] >> 2) & 0b11 == 0b01;
+    if (rgb_ifc >> 5) & 0b11 != 0b10 || !rgb_mode {
+        mismatch(Mismatch::InterfaceMode);
+    }
+
+    let gcr = ltdc.gcr.read();
+    let panel_bit = |bit: u8| (rgb_ifc >> bit) & 1 == 1;
+    if gcr.vspol().bit_is_set() != panel_bit(3) {
+        mismatch(Mismatch::Polarity { signal: "VSYNC" });
+    }
+    if gcr.hspol().bit_is_set() != panel_bit(2) {
+        mismatch(Mismatch::Polarity { signal: "HSYNC" });
+    }
+    // The panel samples on the rising edge when DPL = 0, which needs the
+    // LTDC to drive data with an inverted pixel clock (PCPOL = 1)
+    if gcr.pcpol().bit_is_set() == panel_bit(1) {
+        mismatch(Mismatch::Polarity { signal: "DOTCLK" });
+    }
+    if gcr.depol().bit_is_set() != panel_bit(0) {
+        mismatch(Mismatch::Polarity { signal: "DE" });
+    }
+
+    count
+}
+
+const MAX_KEPT: usize = 8;
+
+// Results of the boot-time check, for the diagnostics page
+static mut FOUND: [Option<Mismatch>; MAX_KEPT] = [None; MAX_KEPT];
+static mut FOUND_COUNT: usize = 0;
+
+// Run the check against the selected panel's profile and keep the results
+pub fn run_at_boot() -> usize {
+    unsafe {
+        FOUND = [None; MAX_KEPT];
+        FOUND_COUNT = check(&profile(), |m| {
+            if let Some(slot) = FOUND.iter_mut().find(|s| s.is_none()) {
+                *slot = Some(m);
+            }
+        });
+        FOUND_COUNT
+    }
+}
+
+// Mismatches found by run_at_boot (at most MAX_KEPT) and the total count
+pub fn found() -> (impl Iterator<Item = Mismatch>, usize) {
+    unsafe { (FOUND.iter().flatten().copied(), FOUND_COUNT) }
+}
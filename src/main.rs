@@ -2,29 +2,136 @@
 #![no_main]
 #![allow(dead_code)]
 
+use core::cell::RefCell;
+use core::pin::pin;
+
+use core_logic::executor::Task;
+use core_logic::input::Motion;
 use cortex_m_rt::entry;
 use panic_halt as _;
 use stm32f4 as _;
 
+mod ab_check;
+mod adc;
+#[cfg(all(feature = "agent-api", debug_assertions))]
+mod agent;
+mod artwork;
+mod asset_check;
 mod assets;
+mod audio;
+mod backdrop;
+mod backlight;
+mod battery;
+mod bench;
+mod blackbox;
+mod boot_report;
+mod budget;
+mod button;
+mod calibration;
 mod clock;
 mod color;
 mod config;
+mod courses;
+mod crc;
+#[cfg(feature = "dac-audio")]
+mod dac;
+mod debug_menu;
+mod diagnostics;
 mod display;
+mod dma2d;
 mod draw;
+mod editor;
+mod effects;
+mod encoder;
+mod entity;
+mod error;
+mod executor;
+mod fault;
+mod flash;
+mod fmt_buf;
+mod frame_record;
+mod framebuffer;
 mod game;
+mod game_events;
+mod ghost;
+mod ground;
+mod hud;
+mod hw_report;
 mod i2c;
+#[cfg(feature = "i2s-audio")]
+mod i2s;
+mod ili9341;
 mod input_device;
+mod input_events;
+mod iwdg;
+mod lane;
+mod lang;
+mod latency;
 mod lcd;
-mod lcd_spi;
+mod leaderboard_page;
+mod live;
+mod log;
+mod ltdc_check;
+mod memory;
 mod mpu6050;
 mod obstacle;
+mod particles;
+mod pickup;
 mod player;
+mod power;
+mod profile_picker;
+mod profiler;
+mod profiles;
+mod raster;
+mod replay;
+mod resources;
+mod retro;
+mod rtc;
+mod rtt;
+mod scheduler;
+mod score_link;
+mod screensaver;
+mod screenshot;
 mod sdram;
+mod serial;
+mod settings;
+mod settings_page;
+mod shell;
+mod sky;
+mod spi;
+mod spi_render;
+mod splash;
+mod sprite_cache;
+mod sprites;
+mod stats;
+mod stats_page;
+mod store;
+mod strip;
+mod subsystem;
+mod system;
+mod telemetry;
+mod test_pattern;
+mod text;
+mod text_field;
+mod theme;
+mod touch;
+mod transition;
+mod trigger;
+mod ui;
+mod usb;
+mod versus;
+mod wifi;
+mod world;
 
 // Import the types we need
+use error::HwError;
 use game::Game;
-use input_device::Mpu6050InputDevice;
+use input_device::InputMux;
+use scheduler::Job;
+use settings::FrameRate;
+
+// Well inside the time USART1 takes to fill its receive ring
+const SERIAL_POLL_MS: u32 = 10;
 // Dummy input device for now
 /* struct DummyInputDevice;
 
@@ -51,80 +158,265 @@ impl InputDevice for DummyInputDevice {
 
 #[entry]
 fn main() -> ! {
-    let lcd_driver = init();
+    init();
 
-    // Re-enable display module - LTDC timing changes may have broken pure LTDC mode
-    display::register_driver(&lcd_driver);
     display::init(); // Initialize display module
 
     // Test display functions - draw a simple test image
     // This will help verify that draw_image is working with LTDC framebuffer
     let test_image: [u16; 4] = [0xF800, 0x07E0, 0x001F, 0xFFFF]; // Red, Green, Blue, White
-    display::draw_image_rust(50, 2, 50, 2, &test_image);
+    let _ = display::draw_image_rust(config::Rect::new(50, 50, 2, 2), &test_image);
+
+    // Holding the button through boot asks for the full hardware report
+    if button::is_down() {
+        hw_report::run();
+    }
+
+    // List whatever did not come up before the game starts without it
+    boot_report::draw();
 
-    let input: Mpu6050InputDevice = Mpu6050InputDevice::new();
-    let _game_instance: &mut Game<Mpu6050InputDevice> =
-        &mut Game::init(input).expect("Failed to initialize game");
+    // Warn about art or sounds that no longer match what was built
+    asset_check::verify();
+    asset_check::draw();
 
-    // Minimal test loop - just show checkerboard without game updates
+    // Say so if the last session was cut short by the watchdog
+    if iwdg::recovered() {
+        iwdg::draw_notice();
+    }
+
+    // Offer what led up to it, if the last session was cut short
+    blackbox::offer();
+
+    // Show frame times and where flash and static RAM are going before the
+    // game takes over
+    #[cfg(feature = "mem-report")]
+    {
+        diagnostics::draw_page();
+        clock::delay_ms(3000);
+    }
+
+    // Fade in the logo with the version under it, then on to the title
+    splash::run();
+
+    // Tilt, touch or button, whichever are fitted; switchable when paused
+    let input: InputMux = InputMux::new();
+    game_events::init();
+    let game = RefCell::new(Game::init(input));
+
+    // From here on a frame that hangs (e.g. a locked I2C bus) resets the board
+    iwdg::start();
+
+    let mut tasks: [Task; executor::TASKS] = [
+        pin!(game_task(&game)),
+        pin!(periodic_task(&game)),
+        pin!(serial_task(&game)),
+    ];
+    executor::run(&mut tasks)
+}
+
+// A frame per vertical blank, or every other one in the battery saver:
+// update and draw the game, then present it
+async fn game_task(game: &RefCell<Game<InputMux>>) {
     loop {
-        _game_instance.update(); // Disable game updates for testing
-                                 // clock::delay_ms(1000); // Very slow for debugging
+        let frame_start = clock::millis();
+        // Asked for from the debug menu or the shell, between frames
+        if system::take_warm_restart() {
+            let mut game = game.borrow_mut();
+            let input = core::mem::replace(&mut game.input_device, InputMux::new());
+            *game = Game::warm_restart(input);
+        }
+        game.borrow_mut().update();
+
+        // Retro mode draws at half resolution; scale it up onto Layer 1
+        retro::present();
+
+        // Bend the picture onto Layer 1 while a raster effect is on
+        raster::present();
+
+        // Bring the next screen in while one screen changes to another
+        transition::present();
+
+        // Checksum what is now on Layer 1 for the host, while recording
+        {
+            let game = game.borrow();
+            frame_record::present(game.state(), game.score(), game.snapshot().player_y);
+        }
+
+        // Recover from persistent LTDC underruns
+        lcd::LcdDriver::service_errors();
+
+        // Stop on the fault screen if the stack ran into .bss
+        fault::check_stack();
+
+        // The other tasks run, or the core sleeps, until the next frame
+        next_frame(settings::get().frame_rate, frame_start).await;
+
+        // The frame is on the panel: time the input it acted on, then close
+        // its update/render/I2C/idle split
+        latency::end_frame();
+        profiler::end_frame();
+
+        // Refresh the watchdog if this frame stayed within its budget
+        iwdg::heartbeat();
+    }
+}
+
+// Wait out the frame begun at `frame_start`: `rate`'s vertical blanks, or
+// with scan-out off (SPI rendering), when they come at once, its length
+async fn next_frame(rate: FrameRate, frame_start: u32) {
+    for _ in 0..rate.vblanks() {
+        executor::next_vblank().await;
+    }
+    if !lcd::LcdDriver::attach().is_enabled() {
+        let spent = clock::millis().wrapping_sub(frame_start);
+        if spent < rate.frame_ms() {
+            executor::sleep_ms(rate.frame_ms() - spent).await;
+        }
     }
 }
 
-fn init() -> lcd::LcdDriver {
+// The scheduler's periodic jobs, run while the game task waits for its
+// next frame: keep a recent accelerometer and gyro sample for the tilt
+// input (slowly while the sensor does not answer), pass on shakes and free
+// falls as input events, poll the HUD, telemetry and backlight, and restart
+// subsystems that have failed
+async fn periodic_task(game: &RefCell<Game<InputMux>>) {
+    let mut sensor = true;
+    loop {
+        match scheduler::next().await {
+            Job::Sensor => {
+                sensor = mpu6050::sample().await.is_ok();
+                let ms = if sensor {
+                    scheduler::SENSOR_MS
+                } else {
+                    scheduler::SENSOR_RETRY_MS
+                };
+                scheduler::set_interval(Job::Sensor, ms);
+            }
+            Job::Motion if sensor => {
+                if let Ok(status) = mpu6050::motion_status() {
+                    if status.shake {
+                        input_events::moved(Motion::Shake);
+                    }
+                    if status.free_fall {
+                        input_events::moved(Motion::FreeFall);
+                    }
+                }
+            }
+            Job::Motion => {}
+            Job::Hud => hud::poll(sensor),
+            Job::Telemetry => {
+                let game = game.borrow();
+                telemetry::send_status(game.state(), game.score());
+            }
+            Job::Backlight => backlight::update(),
+            Job::Rtt => live::poll(&mut game.borrow_mut()),
+            Job::Supervise => {
+                subsystem::supervise();
+            }
+        }
+    }
+}
+
+// Console commands and host telemetry frames from USART1 and USB
+async fn serial_task(game: &RefCell<Game<InputMux>>) {
+    loop {
+        shell::poll(&mut game.borrow_mut());
+        replay::poll();
+        wifi::poll();
+        executor::sleep_ms(SERIAL_POLL_MS).await;
+    }
+}
+
+fn init() {
     // Configure system clocks to 168MHz from HSE to match C demo
     //clock::setup_system_clocks_168mhz();
     // SysTick and base clocks
+    // Fault screen and stack canary before anything that could fault
+    fault::init();
+    memory::paint_stack();
+    iwdg::check_reset_cause();
+    // The probe may already be looking for the RTT block
+    rtt::init();
+
     let cp = cortex_m::Peripherals::take().unwrap();
     let _syst = clock::setup(cp.SYST);
 
     // Setup clocks first before initializing LTDC
     clock::setup_system_clocks_168mhz();
     clock::setup_pllsai_for_ltdc();
+    let _ = clock::validate().inspect_err(|&error| boot_report::record("Clocks", error));
 
-    // Initialize SDRAM for framebuffers
-    sdram::init();
-
-    // Setup LTDC and framebuffers
-    // Layer 1 will be used for everything (start screen, game elements)
-    draw::layer1_checkerboard(); // Initialize with checkerboard as base
+    // Rendering over SPI needs neither SDRAM nor LTDC; leave both off so
+    // the game runs on boards without them
+    let lcd_driver = if display::backend() == display::Backend::Spi {
+        let _ = ili9341::display_selftest();
+        lcd::LcdDriver::attach()
+    } else {
+        // Without SDRAM or LTDC the picture can still go over SPI
+        init_ltdc().unwrap_or_else(|_| {
+            display::fall_back_to_spi();
+            lcd::LcdDriver::attach()
+        })
+    };
+    // Keep Layer 2 fully opaque
+    lcd_driver.set_layer2_alpha(0xFF);
+    // The display keeps the one driver from here on; everything else draws
+    // through it
+    display::register(lcd_driver).expect("LCD driver registered twice");
 
-    // Clear Layer 2 (64x64 layer for small UI elements if needed)
-    draw::clear_layer2();
+    // Storage, panel, sensors, button, audio and host link. The MPU6050 is
+    // not critical for the display, so boot carries on if it fails
+    subsystem::init_all();
+    boot_report::scan_buses();
+    // Backup SRAM is on now; note this boot after what the last one left
+    blackbox::init();
 
-    // Create LCD driver (this will configure LTDC)
-    let lcd_driver = lcd::LcdDriver::new();
+    // Settings as last saved, then the colors and art they choose
+    settings::load();
+    courses::load();
+    theme::restore();
+}
 
-    // Initialize SPI display
-    lcd_spi::init(); // Initialize I2C and MPU6050
-    i2c::init_i2c1();
+// SDRAM framebuffers and LTDC scan-out; failures go into the boot report
+fn init_ltdc() -> Result<lcd::LcdDriver, HwError> {
+    // Initialize SDRAM for framebuffers
+    sdram::init().inspect_err(|&error| boot_report::record("SDRAM", error))?;
 
-    // Small delay for I2C to stabilize
-    clock::delay_ms(50);
+    // Prove the display buffers hold data before LTDC starts scanning them
+    let mem = &lcd::DISPLAY_MEMORY;
+    let display_bytes = mem.free.base - mem.layer1.base;
+    if let Err(fault) = sdram::self_test(mem.layer1.base, display_bytes) {
+        log::error!(
+            "SDRAM self-test failed at {:#010x}: wrote {:#010x}, read {:#010x}",
+            fault.addr,
+            fault.expected,
+            fault.actual
+        );
+        boot_report::record("SDRAM", HwError::SelfTestFailed);
+    }
+    sdram::arm_spot_check();
+    dma2d::init();
 
-    // Try to initialize MPU6050, with I2C reset on failure
-    let mut mpu_init_attempts = 3;
-    loop {
-        let mpu_init_result = mpu6050::init();
-        if mpu_init_result.is_ok() {
-            break;
-        }
+    // Buffers come from separate const arenas; make sure none of them collide
+    let layout = sdram::arena::check_disjoint(&memory::sdram_regions());
+    if let Err((a, b)) = layout {
+        log::error!("SDRAM regions {} and {} overlap", a, b);
+        debug_assert!(false, "SDRAM regions overlap");
+    }
 
-        mpu_init_attempts -= 1;
-        if mpu_init_attempts == 0 {
-            // If all attempts fail, continue anyway (MPU6050 is not critical for display)
-            break;
-        }
+    // Setup LTDC and framebuffers
+    // Layer 1 will be used for everything (start screen, game elements)
+    draw::layer1_black(); // Backdrop for the boot splash
 
-        // Reset I2C and try again
-        i2c::reset_i2c1();
-        clock::delay_ms(100);
-    }
+    // Clear Layer 2 (64x64 layer for small UI elements if needed)
+    draw::clear_layer2();
 
-    // Keep Layer 2 fully opaque
-    lcd_driver.set_layer2_alpha(0xFF);
+    // Make sure the panel controller answers before LTDC starts driving it;
+    // boot carries on either way so the log and serial console stay usable
+    let _ = ili9341::display_selftest();
 
-    lcd_driver
+    // Create LCD driver (this will configure LTDC)
+    lcd::LcdDriver::new(lcd::LtdcConfig::default())
+        .inspect_err(|&error| boot_report::record("LTDC", error))
 }
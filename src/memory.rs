@@ -0,0 +1,157 @@
+//! Memory use: stack high-water mark, static RAM and SDRAM
+//!
+//! `paint_stack` fills the unused stack with a pattern at boot. How deep
+//! the stack has been since shows as how far up from the bottom the pattern
+//! is still intact. Static RAM comes from the cortex-m-rt linker symbols,
+//! SDRAM from the regions the const arenas handed out. The profiler overlay
+//! shows the short version, the shell's `mem` the whole report.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::{self, Write};
+use core::ptr::{addr_of, read_volatile, write_volatile};
+
+use crate::ab_check;
+use crate::fault;
+use crate::ghost;
+use crate::lcd;
+use crate::replay;
+use crate::sdram::arena::Region;
+use crate::sdram::{self, SDRAM_SIZE};
+use crate::sprite_cache;
+use crate::sprites;
+
+const PAINT: u32 = 0xCDCD_CDCD;
+// Left unpainted under the stack pointer, for paint_stack's own frame
+const PAINT_MARGIN: u32 = 64;
+
+extern "C" {
+    // Section bounds and the stack (cortex-m-rt linker script)
+    static __sdata: u32;
+    static __edata: u32;
+    static __sbss: u32;
+    static __ebss: u32;
+    static __suninit: u32;
+    static __euninit: u32;
+    static _stack_start: u32;
+    static mut _stack_end: u32;
+}
+
+// Top of the stretch found intact at the last scan; the stack has never
+// been below it
+static mut LOW_WATER: u32 = 0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Usage {
+    // All of the stack, and what has not been touched since boot
+    pub stack_bytes: u32,
+    pub stack_free: u32,
+    pub data_bytes: u32,
+    pub bss_bytes: u32,
+    pub uninit_bytes: u32,
+    // SDRAM handed out, of SDRAM_SIZE
+    pub sdram_used: u32,
+}
+
+impl Usage {
+    pub fn static_bytes(&self) -> u32 {
+        self.data_bytes + self.bss_bytes + self.uninit_bytes
+    }
+
+    pub fn stack_used(&self) -> u32 {
+        self.stack_bytes - self.stack_free
+    }
+}
+
+// Every buffer placed in SDRAM, by whoever placed it
+pub fn sdram_regions() -> [(&'static str, Region); 13] {
+    let mem = &lcd::DISPLAY_MEMORY;
+    [
+        ("layer1", mem.layer1.region()),
+        ("layer2", mem.layer2.region()),
+        ("layer1_b", mem.layer1_b.region()),
+        ("overlay", mem.overlay.region()),
+        ("hud", mem.hud.region()),
+        ("retro", mem.retro.region()),
+        ("sprites", sprites::SLOTS),
+        ("ghost", ghost::BUFFERS),
+        ("sprite cache", sprite_cache::CACHE),
+        ("replay", replay::BUFFER),
+        ("ab dma2d", ab_check::CAPTURES.0.region()),
+        ("ab cpu", ab_check::CAPTURES.1.region()),
+        ("spot check", sdram::SPOT_CHECK),
+    ]
+}
+
+// Lowest stack word not kept for the fault canary
+fn stack_floor() -> u32 {
+    addr_of!(_stack_end) as u32 + 4 * fault::CANARY_WORDS as u32
+}
+
+// Paint the unused stack; call once early at boot, after `fault::init`
+pub fn paint_stack() {
+    let top = cortex_m::register::msp::read() - PAINT_MARGIN;
+    let mut addr = stack_floor();
+    while addr < top {
+        unsafe { write_volatile(addr as *mut u32, PAINT) };
+        addr += 4;
+    }
+    unsafe { LOW_WATER = top };
+}
+
+// Bytes of stack never touched since boot
+pub fn stack_free() -> u32 {
+    let floor = stack_floor();
+    let mut addr = floor;
+    unsafe {
+        while addr < LOW_WATER && read_volatile(addr as *const u32) == PAINT {
+            addr += 4;
+        }
+        LOW_WATER = addr;
+    }
+    addr - floor
+}
+
+fn span(start: *const u32, end: *const u32) -> u32 {
+    end as u32 - start as u32
+}
+
+pub fn usage() -> Usage {
+    Usage {
+        stack_bytes: span(addr_of!(_stack_end), addr_of!(_stack_start)),
+        stack_free: stack_free(),
+        data_bytes: span(addr_of!(__sdata), addr_of!(__edata)),
+        bss_bytes: span(addr_of!(__sbss), addr_of!(__ebss)),
+        uninit_bytes: span(addr_of!(__suninit), addr_of!(__euninit)),
+        sdram_used: sdram_regions().iter().map(|(_, region)| region.size).sum(),
+    }
+}
+
+// The whole report, for the serial console
+pub fn write_report(out: &mut impl Write) -> fmt::Result {
+    let usage = usage();
+    write!(
+        out,
+        "stack {} of {} used, {} free\r\n",
+        usage.stack_used(),
+        usage.stack_bytes,
+        usage.stack_free
+    )?;
+    write!(
+        out,
+        "static {}: data {} bss {} uninit {}\r\n",
+        usage.static_bytes(),
+        usage.data_bytes,
+        usage.bss_bytes,
+        usage.uninit_bytes
+    )?;
+    write!(out, "sdram {} of {}\r\n", usage.sdram_used, SDRAM_SIZE)?;
+    for (name, region) in sdram_regions() {
+        write!(
+            out,
+            "  {:<10} {:#010x} {:>8}\r\n",
+            name, region.base, region.size
+        )?;
+    }
+    Ok(())
+}
@@ -1,7 +1,11 @@
 #![allow(dead_code)]
 
+use crate::clock;
+use crate::error::HwError;
 use crate::i2c;
 use crate::input_device::AccelData;
+use crate::log;
+use crate::subsystem::{Health, Subsystem};
 
 const MPU6050_ADDR: u8 = 0x68;
 
@@ -11,6 +15,61 @@ const PWR_MGMT_1: u8 = 0x6B;
 const GYRO_CONFIG: u8 = 0x1B;
 const ACCEL_CONFIG: u8 = 0x1C;
 const ACCEL_XOUT_H: u8 = 0x3B;
+const TEMP_OUT_H: u8 = 0x41;
+const FF_THR: u8 = 0x1D;
+const FF_DUR: u8 = 0x1E;
+const MOT_THR: u8 = 0x1F;
+const MOT_DUR: u8 = 0x20;
+const INT_PIN_CFG: u8 = 0x37;
+const INT_ENABLE: u8 = 0x38;
+const INT_STATUS: u8 = 0x3A;
+
+// PWR_MGMT_1 SLEEP bit
+const SLEEP: u8 = 0x40;
+const INIT_ATTEMPTS: u32 = 3;
+
+// INT_PIN_CFG: hold INT high until INT_STATUS is read
+const LATCH_INT_EN: u8 = 0x20;
+// INT_ENABLE and INT_STATUS free-fall and motion detection
+const FF_EN: u8 = 0x80;
+const MOT_EN: u8 = 0x40;
+const FF_INT: u8 = 0x80;
+const MOT_INT: u8 = 0x40;
+// ACCEL_CONFIG 5 Hz high-pass, which motion detection works on
+const ACCEL_HPF_5HZ: u8 = 0x01;
+// Motion that wakes the board: 2 mg per step, 1 ms per step
+const WAKE_THRESHOLD: u8 = 20;
+const WAKE_DURATION_MS: u8 = 40;
+// In play: a shake is a hard jolt, well past what tilting to steer does;
+// a free fall is every axis near zero g for a while, 2 mg per step
+const SHAKE_THRESHOLD: u8 = 200;
+const SHAKE_DURATION_MS: u8 = 20;
+const FREE_FALL_THRESHOLD: u8 = 100;
+const FREE_FALL_DURATION_MS: u8 = 50;
+
+// The die temperature moves slowly; re-read it at most this often
+const TEMP_INTERVAL_MS: u32 = 1000;
+// A sample from the sensor task older than this is not used
+const SAMPLE_STALE_MS: u32 = 50;
+
+// Nominal zero-g drift per axis (x, y, z) in counts, against degrees C,
+// relative to 25 C: the datasheet's +-35 mg (x, y) and +-60 mg (z) over
+// -40..85 C, taken as linear. Only differences between two temperatures
+// are used, so the reference point does not matter.
+const DRIFT_TABLE: [(i32, [i32; 3]); 5] = [
+    (-20, [-207, -207, -351]),
+    (0, [-115, -115, -195]),
+    (25, [0, 0, 0]),
+    (50, [115, 115, 195]),
+    (85, [276, 276, 468]),
+];
+
+/// What the motion detectors saw since they were last asked
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct MotionStatus {
+    pub shake: bool,
+    pub free_fall: bool,
+}
 
 pub struct Mpu6050Data {
     pub accel_x: i32,
@@ -22,11 +81,30 @@ pub struct Mpu6050Data {
     pub gyro_z: i32,
 }
 
+impl Mpu6050Data {
+    pub fn temp_centi_c(&self) -> i32 {
+        temp_to_centi_c(self.temp)
+    }
+}
+
+// Raw TEMP_OUT to hundredths of a degree C: raw / 340 + 36.53, per the
+// register map
+pub fn temp_to_centi_c(raw: i32) -> i32 {
+    raw * 100 / 340 + 3653
+}
+
 pub fn init() -> Result<(), ()> {
     // Check WHO_AM_I register
     match i2c::i2c1_read_reg(MPU6050_ADDR, WHO_AM_I) {
         Ok(id) if id == 0x68 => {}
-        _ => return Err(()),
+        Ok(id) => {
+            log::error!("MPU6050 WHO_AM_I {:#04x}, expected 0x68", id);
+            return Err(());
+        }
+        Err(()) => {
+            log::warn!("MPU6050 not answering on I2C1");
+            return Err(());
+        }
     }
 
     // Wake up the MPU6050 (exit sleep mode)
@@ -38,23 +116,90 @@ pub fn init() -> Result<(), ()> {
     // Set accelerometer range to ±2g
     i2c::i2c1_write_reg(MPU6050_ADDR, ACCEL_CONFIG, 0x00)?;
 
+    arm_motion_events()?;
+
+    log::info!("MPU6050 ready, +-2g / +-250 dps");
     Ok(())
 }
 
+// Have the sensor watch for shakes and free falls, for `motion_status`.
+// The high-pass filter only feeds the detectors, so the tilt readings do
+// not change.
+pub fn arm_motion_events() -> Result<(), ()> {
+    let write = |reg, value| i2c::i2c1_write_reg(MPU6050_ADDR, reg, value);
+    write(ACCEL_CONFIG, ACCEL_HPF_5HZ)?;
+    write(MOT_THR, SHAKE_THRESHOLD)?;
+    write(MOT_DUR, SHAKE_DURATION_MS)?;
+    write(FF_THR, FREE_FALL_THRESHOLD)?;
+    write(FF_DUR, FREE_FALL_DURATION_MS)?;
+    write(INT_ENABLE, MOT_EN | FF_EN)?;
+    i2c::i2c1_read_reg(MPU6050_ADDR, INT_STATUS).map(|_| ())
+}
+
+// Whether a shake or a free fall was detected since the last call;
+// reading INT_STATUS clears it
+pub fn motion_status() -> Result<MotionStatus, ()> {
+    let status = i2c::i2c1_read_reg(MPU6050_ADDR, INT_STATUS)?;
+    Ok(MotionStatus {
+        shake: status & MOT_INT != 0,
+        free_fall: status & FF_INT != 0,
+    })
+}
+
+// Raise INT on motion, for waking from sleep. The breakout's INT pin has
+// to be wired to the pin `power` watches.
+pub fn arm_motion_wake() -> Result<(), ()> {
+    let write = |reg, value| i2c::i2c1_write_reg(MPU6050_ADDR, reg, value);
+    write(PWR_MGMT_1, 0x00)?;
+    write(ACCEL_CONFIG, ACCEL_HPF_5HZ)?;
+    write(MOT_THR, WAKE_THRESHOLD)?;
+    write(MOT_DUR, WAKE_DURATION_MS)?;
+    write(INT_PIN_CFG, LATCH_INT_EN)?;
+    write(INT_ENABLE, MOT_EN)?;
+    // Drop anything latched before now
+    i2c::i2c1_read_reg(MPU6050_ADDR, INT_STATUS).map(|_| ())
+}
+
+// Back to the in-play shake and free-fall thresholds
+pub fn disarm_motion_wake() {
+    let _ = i2c::i2c1_write_reg(MPU6050_ADDR, INT_ENABLE, 0x00);
+    let _ = i2c::i2c1_write_reg(MPU6050_ADDR, INT_PIN_CFG, 0x00);
+    let _ = arm_motion_events();
+}
+
+// Whether an MPU6050 answers on I2C1
+pub fn is_present() -> bool {
+    matches!(i2c::i2c1_read_reg(MPU6050_ADDR, WHO_AM_I), Ok(0x68))
+}
+
+// For the hardware report: WHO_AM_I, Ok only if it is an MPU6050's
+pub fn probe() -> Result<u8, HwError> {
+    match i2c::i2c1_read_reg(MPU6050_ADDR, WHO_AM_I) {
+        Ok(0x68) => Ok(0x68),
+        Ok(_) | Err(()) => Err(HwError::NoResponse),
+    }
+}
+
+// One signed 16-bit register pair; sign-extended so tilting the other way
+// reads negative
+fn be_i16(bytes: &[u8]) -> i32 {
+    i16::from_be_bytes([bytes[0], bytes[1]]) as i32
+}
+
 pub fn read_data() -> Result<Mpu6050Data, ()> {
     let mut buffer = [0u8; 14];
 
     // Read all data registers at once (ACCEL_XOUT_H to GYRO_ZOUT_L)
     i2c::i2c1_read_bytes(MPU6050_ADDR, ACCEL_XOUT_H, &mut buffer)?;
 
-    // Convert bytes to i32 values (big-endian)
-    let accel_x = ((buffer[0] as i32) << 8) | (buffer[1] as i32);
-    let accel_y = ((buffer[2] as i32) << 8) | (buffer[3] as i32);
-    let accel_z = ((buffer[4] as i32) << 8) | (buffer[5] as i32);
-    let temp = ((buffer[6] as i32) << 8) | (buffer[7] as i32);
-    let gyro_x = ((buffer[8] as i32) << 8) | (buffer[9] as i32);
-    let gyro_y = ((buffer[10] as i32) << 8) | (buffer[11] as i32);
-    let gyro_z = ((buffer[12] as i32) << 8) | (buffer[13] as i32);
+    // Big-endian two's complement
+    let accel_x = be_i16(&buffer[0..2]);
+    let accel_y = be_i16(&buffer[2..4]);
+    let accel_z = be_i16(&buffer[4..6]);
+    let temp = be_i16(&buffer[6..8]);
+    let gyro_x = be_i16(&buffer[8..10]);
+    let gyro_y = be_i16(&buffer[10..12]);
+    let gyro_z = be_i16(&buffer[12..14]);
 
     Ok(Mpu6050Data {
         accel_x,
@@ -66,16 +211,73 @@ pub fn read_data() -> Result<Mpu6050Data, ()> {
         gyro_z,
     })
 }
+
+// Raw register read, for poking at the sensor from the console
+pub fn read_registers(reg: u8, buffer: &mut [u8]) -> Result<(), ()> {
+    i2c::i2c1_read_bytes(MPU6050_ADDR, reg, buffer)
+}
+
+// Die temperature in hundredths of a degree C
+pub fn read_temperature() -> Result<i32, ()> {
+    let mut buffer = [0u8; 2];
+    i2c::i2c1_read_bytes(MPU6050_ADDR, TEMP_OUT_H, &mut buffer)?;
+    Ok(temp_to_centi_c(be_i16(&buffer)))
+}
+
+static mut TEMPERATURE: Option<(u32, i32)> = None;
+
+// Recent die temperature in hundredths of a degree C, re-read once
+// TEMP_INTERVAL_MS has passed; None if the sensor has never answered
+pub fn temperature() -> Option<i32> {
+    let now = clock::millis();
+    let cached = unsafe { TEMPERATURE };
+    if let Some((at, centi_c)) = cached {
+        if now.wrapping_sub(at) < TEMP_INTERVAL_MS {
+            return Some(centi_c);
+        }
+    }
+    match read_temperature() {
+        Ok(centi_c) => {
+            unsafe { TEMPERATURE = Some((now, centi_c)) };
+            Some(centi_c)
+        }
+        Err(()) => cached.map(|(_, centi_c)| centi_c),
+    }
+}
+
+// Expected zero-g offset per axis at `centi_c`, from DRIFT_TABLE: linear
+// between entries, flat past either end
+pub fn drift_at(centi_c: i32) -> [i32; 3] {
+    let (first, last) = (DRIFT_TABLE[0], DRIFT_TABLE[DRIFT_TABLE.len() - 1]);
+    if centi_c <= first.0 * 100 {
+        return first.1;
+    }
+    if centi_c >= last.0 * 100 {
+        return last.1;
+    }
+    let mut drift = [0; 3];
+    for pair in DRIFT_TABLE.windows(2) {
+        let ((t0, d0), (t1, d1)) = (pair[0], pair[1]);
+        let (t0, t1) = (t0 * 100, t1 * 100);
+        if centi_c <= t1 {
+            for axis in 0..3 {
+                drift[axis] = d0[axis] + (d1[axis] - d0[axis]) * (centi_c - t0) / (t1 - t0);
+            }
+            break;
+        }
+    }
+    drift
+}
+
 pub fn read_accel_data() -> Result<AccelData, ()> {
     let mut buffer = [0u8; 6];
 
     // Read accelerometer registers (ACCEL_XOUT_H to ACCEL_ZOUT_L)
     i2c::i2c1_read_bytes(MPU6050_ADDR, ACCEL_XOUT_H, &mut buffer)?;
 
-    // Convert bytes to i32 values (big-endian)
-    let accel_x = ((buffer[0] as i32) << 8) | (buffer[1] as i32);
-    let accel_y = ((buffer[2] as i32) << 8) | (buffer[3] as i32);
-    let accel_z = ((buffer[4] as i32) << 8) | (buffer[5] as i32);
+    let accel_x = be_i16(&buffer[0..2]);
+    let accel_y = be_i16(&buffer[2..4]);
+    let accel_z = be_i16(&buffer[4..6]);
 
     Ok(AccelData {
         accel_x,
@@ -83,3 +285,87 @@ pub fn read_accel_data() -> Result<AccelData, ()> {
         accel_z,
     })
 }
+
+// When the sensor task sampled, the accelerometer and the gyro rates
+// (x, y, z)
+static mut LATEST: Option<(u32, AccelData, [i32; 3])> = None;
+
+// Read the accelerometer and gyro without blocking the other tasks and
+// keep the result for `latest_accel` and `latest_gyro`
+pub async fn sample() -> Result<(), ()> {
+    let mut buffer = [0u8; 14];
+    i2c::i2c1_read_bytes_async(MPU6050_ADDR, ACCEL_XOUT_H, &mut buffer).await?;
+    let accel = AccelData {
+        accel_x: be_i16(&buffer[0..2]),
+        accel_y: be_i16(&buffer[2..4]),
+        accel_z: be_i16(&buffer[4..6]),
+    };
+    let gyro = [
+        be_i16(&buffer[8..10]),
+        be_i16(&buffer[10..12]),
+        be_i16(&buffer[12..14]),
+    ];
+    unsafe { LATEST = Some((clock::millis(), accel, gyro)) };
+    Ok(())
+}
+
+// The last sample the sensor task took, if it is recent
+pub fn latest_accel() -> Option<AccelData> {
+    let (at, accel, _) = unsafe { LATEST }?;
+    (clock::millis().wrapping_sub(at) < SAMPLE_STALE_MS).then_some(accel)
+}
+
+// Gyro rates (x, y, z) from the last sample, raw at +-250 degrees a second,
+// if it is recent
+pub fn latest_gyro() -> Option<[i32; 3]> {
+    let (at, _, gyro) = unsafe { LATEST }?;
+    (clock::millis().wrapping_sub(at) < SAMPLE_STALE_MS).then_some(gyro)
+}
+
+pub struct SensorSubsystem;
+
+impl Subsystem for SensorSubsystem {
+    fn name(&self) -> &'static str {
+        "sensors"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        let bus = i2c::init_i2c1();
+
+        // Small delay for I2C to stabilize
+        clock::delay_ms(50);
+
+        // Try to initialize MPU6050, with I2C reset on failure
+        for attempt in 1..=INIT_ATTEMPTS {
+            if init().is_ok() {
+                return Ok(());
+            }
+            if attempt < INIT_ATTEMPTS {
+                i2c::reset_i2c1();
+                clock::delay_ms(100);
+            }
+        }
+        // A bus that was stuck from the start says more than the silence
+        Err(bus.err().unwrap_or(HwError::NoResponse))
+    }
+
+    fn health_check(&self) -> Health {
+        // Mid-sample the sensor task has just had an answer
+        if i2c::i2c1_held() {
+            return Health::Ok;
+        }
+        match i2c::i2c1_read_reg(MPU6050_ADDR, WHO_AM_I) {
+            Ok(0x68) => Health::Ok,
+            Ok(_) => Health::Failed("wrong WHO_AM_I"),
+            Err(()) => Health::Failed("no MPU6050"),
+        }
+    }
+
+    fn suspend(&self) {
+        let _ = i2c::i2c1_write_reg(MPU6050_ADDR, PWR_MGMT_1, SLEEP);
+    }
+
+    fn resume(&self) {
+        let _ = i2c::i2c1_write_reg(MPU6050_ADDR, PWR_MGMT_1, 0x00);
+    }
+}
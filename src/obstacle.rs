@@ -1,103 +1,124 @@
-use crate::color;
-use crate::config::*;
-use crate::display;
+use core_logic::mode::{Classic, GameMode};
+use core_logic::obstacle::{GapPreview, ObstaclePair};
+use core_logic::tilemap::Tile;
 
+use crate::config::*;
+use crate::entity::Renderer;
+use crate::frame_record;
+use crate::lane::{self, Lane};
+use crate::settings;
+use crate::sprites;
+use crate::theme;
+
+// An obstacle pair (core_logic) as the game moves it; `World` draws it
+#[derive(Clone)]
 pub struct Obstacle {
-    x_top: Coord,
-    y_top: Coord,
-    x_btm: Coord,
-    y_btm: Coord,
-    speed: u32,
-    height_top: u32,
-    height_btm: u32,
-     pub already_scored: bool,
+    pair: ObstaclePair,
+    // Where it was a tick ago, to draw it between ticks from
+    before: ObstaclePair,
+    // Picks the next kind, for `score`
+    mode: &'static dyn GameMode,
+    score: u32,
+    // A new obstacle came in on the last update
+    arrived: bool,
 }
 
 impl Obstacle {
     pub fn init() -> Self {
+        Self::init_in(lane::full(), &Classic)
+    }
+
+    // Obstacle pair spanning the playfield of `lane`, shaped as `mode`
+    // starts, moving at the speed the difficulty setting starts games at
+    pub fn init_in(lane: Lane, mode: &'static dyn GameMode) -> Self {
+        let mut pair = ObstaclePair::new(lane);
+        pair.start_with(mode);
+        pair.set_velocity(settings::get().difficulty.velocity());
+        pair.reseed(frame_record::seed());
         Obstacle {
-            x_top: 240,
-            y_top: 0 + SCORE_BOARD_HEIGHT as Coord,
-            x_btm: 240,
-            y_btm: 180,
-            speed: SPEED,
-            height_top: 100,
-            height_btm: 60 - PLANTS_HEIGHT,
-            already_scored:  false,
+            pair,
+            before: pair,
+            mode,
+            score: 0,
+            arrived: false,
         }
     }
 
-    fn draw(&self) {
-        self.draw_top();
-        self.draw_bottom();
+    // Move on by `dt` ticks, having the mode pick the next kind for the
+    // score once off the left edge. `moved` hears the whole pixels each tick went.
+    pub fn update(&mut self, dt: u32, mut moved: impl FnMut(u32)) {
+        self.arrived = false;
+        for _ in 0..dt {
+            self.before = self.pair;
+            moved(self.pair.advance());
+            self.arrived |= self.pair.wrap_with(self.score, self.mode);
+        }
     }
 
-    fn clear_top(&self, x: Coord, width: u32) {
-        display::draw_rect_angle(x, width, self.y_top, self.height_top, color::BACKGROUND);
+    // Where it is drawn `alpha` of a tick on from a tick ago
+    pub fn shown(&self, alpha: u8) -> ObstaclePair {
+        self.pair.between(&self.before, alpha)
     }
 
-    fn clear_bottom(&self, x: Coord, width: u32) {
-        display::draw_rect_angle(x, width, self.y_btm, self.height_btm, color::BACKGROUND);
+    pub fn set_score(&mut self, score: u32) {
+        self.score = score;
     }
 
-    fn clear(&self) {
-        self.clear_top(self.x_top + OBSTACLE_WIDTH as Coord, self.speed);
-        self.clear_bottom(self.x_btm + OBSTACLE_WIDTH as Coord, self.speed);
-
-        if self.x_top <= LCD_BIGIN {
-            self.clear_top(LCD_BIGIN, OBSTACLE_WIDTH);
-        }
+    // Whether a new obstacle came in on the last update
+    pub fn arrived(&self) -> bool {
+        self.arrived
+    }
 
-        if self.x_btm <= LCD_BIGIN {
-            self.clear_bottom(LCD_BIGIN, OBSTACLE_WIDTH);
-        }
+    pub fn pair(&self) -> &ObstaclePair {
+        &self.pair
     }
 
-    fn draw_top(&self) {
-        display::draw_rect_angle(
-            self.x_top,
-            OBSTACLE_WIDTH,
-            self.y_top,
-            self.height_top,
-            color::BLACK,
-        );
+    pub fn pair_mut(&mut self) -> &mut ObstaclePair {
+        &mut self.pair
     }
 
-    fn draw_bottom(&self) {
-        display::draw_rect_angle(
-            self.x_btm,
-            OBSTACLE_WIDTH,
-            self.y_btm,
-            self.height_btm,
-            color::BLACK,
-        );
+    // The obstacle that comes in after this one, as the mode would pick it
+    // for the score now
+    pub fn peek_next(&self) -> ObstaclePair {
+        self.pair.peek_next(self.score, self.mode)
     }
 
-    pub fn move_obstacle(&mut self) {
-        self.x_top -= self.speed as Coord;
-        self.x_btm -= self.speed as Coord;
-        self.draw();
-        self.clear();
+    // The next opening, while its pipe is about to come on
+    pub fn gap_preview(&self) -> Option<GapPreview> {
+        self.pair.gap_preview(self.score, self.mode)
+    }
 
-        if self.x_top <= LCD_BIGIN {
-            self.x_top = LCD_END;
-            self.already_scored = false;
-        }
+    pub fn get_xy_top(&self) -> (Coord, Coord) {
+        self.pair.get_xy_top()
+    }
 
-        if self.x_btm <= LCD_BIGIN {
-            self.x_btm = LCD_END;
-        }
+    // Opening the player has to fly through: (x, top edge, bottom edge)
+    pub fn get_gap(&self) -> (Coord, Coord, Coord) {
+        self.pair.get_gap()
     }
 
-    pub fn get_xy_top(&self) -> (Coord, Coord) {
-        (self.x_top, self.y_top)
+    // Speed in 1/SUBPIXELS of a pixel per frame
+    pub fn velocity(&self) -> u32 {
+        self.pair.scroll().velocity()
     }
 
-    pub fn get_xy_bottom(&self) -> (Coord, Coord) {
-        (self.x_btm, self.y_btm)
+    pub fn set_velocity(&mut self, velocity: u32) {
+        self.pair.set_velocity(velocity);
     }
+}
 
-    pub fn get_height(&self) -> (u32, u32) {
-        (self.height_top, self.height_btm)
+// Draw `rect`, a span of pipe body or cap in tile column `column` counted
+// from the obstacle's left edge. The art is an obstacle wide and repeats
+// down the span from its top, so one strip covers a pipe of any height;
+// without art the span is the theme's flat color.
+pub fn draw_pipe(renderer: &mut Renderer, rect: Rect, tile: Tile, column: u32) {
+    let theme = theme::current();
+    let (art, color) = match tile {
+        Tile::PipeCap => (sprites::cap_tile(), theme.pipe_cap),
+        _ => (sprites::obstacle_tile(), theme.pipe),
+    };
+    match art {
+        Some(art) => renderer.fill_tiled_from(rect, &art, (column * TILE, 0)),
+        None => renderer.fill_rect(rect, color),
     }
 }
@@ -0,0 +1,130 @@
+//! Particles in the full-screen game
+//!
+//! `on_game_event` throws off feathers on a flap, sparkles where a pickup
+//! is collected and a burst when the bird crashes, and `emit` anything else,
+//! such as a shield breaking; the pool and its physics are core_logic
+//! `Particles`. `Trail` is the entity that steps and draws them: each frame
+//! the squares drawn last are painted back with the sky and the live ones
+//! blended in over it, fading as they run out. They stay inside the
+//! playfield, so the score bar and the ground are never painted over, and
+//! are drawn first so the obstacles and the bird cover them.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::cell::Cell;
+
+use core_logic::particles::{Particles, MAX_PARTICLES, PARTICLE_SIZE};
+
+use crate::config::{Coord, Rect, LCD_WIDTH};
+use crate::entity::{Entity, Renderer};
+use crate::frame_record;
+use crate::game_events::GameEvent;
+
+pub use core_logic::particles::Effect;
+
+static mut PARTICLES: Particles = Particles::new(1);
+static mut SEEDED: bool = false;
+
+// Throw off `effect` from game point (x, y)
+pub fn emit(effect: Effect, x: Coord, y: Coord) {
+    let particles = unsafe { &mut PARTICLES };
+    // Seeded on first use so no two runs look quite alike
+    if !unsafe { SEEDED } {
+        *particles = Particles::new(frame_record::seed());
+        unsafe { SEEDED = true };
+    }
+    particles.emit(effect, x, y);
+}
+
+pub fn on_game_event(event: GameEvent) {
+    let center = |bird: Rect| (bird.x + bird.w as Coord / 2, bird.y + bird.h as Coord / 2);
+    match event {
+        // From the bird's tail
+        GameEvent::Flap { bird } => emit(Effect::Feathers, bird.x, bird.y + bird.h as Coord / 2),
+        GameEvent::ScorePoint {
+            bird, pickup: true, ..
+        } => {
+            let (x, y) = center(bird);
+            emit(Effect::Sparkle, x, y);
+        }
+        GameEvent::NearMiss { bird, .. } => {
+            let (x, y) = center(bird);
+            emit(Effect::Sparkle, x, y);
+        }
+        // A practice run's bird is put straight back instead
+        GameEvent::Death {
+            bird,
+            respawn: false,
+        } => {
+            let (x, y) = center(bird);
+            emit(Effect::Burst, x, y);
+        }
+        _ => {}
+    }
+}
+
+// Drop every particle; the next frame the trail erases what it drew
+pub fn clear() {
+    unsafe { PARTICLES.clear() };
+}
+
+/// The particles as an entity of the playfield
+pub struct Trail {
+    // Squares drawn last frame, to paint over with the sky
+    drawn: Cell<[Option<Rect>; MAX_PARTICLES]>,
+}
+
+impl Trail {
+    pub fn new() -> Self {
+        Trail {
+            drawn: Cell::new([None; MAX_PARTICLES]),
+        }
+    }
+}
+
+impl Default for Trail {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Entity for Trail {
+    fn update(&mut self, dt: u32) {
+        let particles = unsafe { &mut PARTICLES };
+        for _ in 0..dt {
+            particles.step();
+        }
+    }
+
+    // The rows any of them take up, across the screen
+    fn bounds(&self) -> Rect {
+        let particles = unsafe { &PARTICLES };
+        let rows = particles.iter().map(|p| p.xy().1).fold(None, |rows, y| {
+            Some(match rows {
+                Some((top, bottom)) => (y.min(top), (y + PARTICLE_SIZE as Coord).max(bottom)),
+                None => (y, y + PARTICLE_SIZE as Coord),
+            })
+        });
+        let (top, bottom) = rows.unwrap_or((0, 0));
+        Rect::new(0, top, LCD_WIDTH, (bottom - top) as u32)
+    }
+
+    fn draw(&self, renderer: &mut Renderer) {
+        for rect in self.drawn.get().iter().flatten() {
+            renderer.fill_sky(*rect);
+        }
+        let field = renderer.lane().field();
+        let mut drawn = [None; MAX_PARTICLES];
+        let particles = unsafe { &PARTICLES };
+        for (particle, drawn) in particles.iter().zip(drawn.iter_mut()) {
+            let (x, y) = particle.xy();
+            let square = Rect::new(x, y, PARTICLE_SIZE, PARTICLE_SIZE);
+            let Some(rect) = square.intersection(&field) else {
+                continue;
+            };
+            renderer.blend_rect(rect, particle.color, particle.alpha());
+            *drawn = Some(rect);
+        }
+        self.drawn.set(drawn);
+    }
+}
@@ -0,0 +1,176 @@
+//! Bonus pickups in the full-screen game
+//!
+//! `Pickups` holds the few coins, stars and power-ups on screen (core_logic
+//! `Pickup`) and draws them into Layer 1 inside the obstacle openings. Each
+//! frame a pickup is erased with the sky and drawn again where it has moved
+//! to, the same way the obstacles are.
+#![allow(dead_code)]
+
+use core::cell::Cell;
+
+use core_logic::bird::Bird;
+use core_logic::obstacle::ObstaclePair;
+use core_logic::pickup::{Pickup, PickupKind};
+use core_logic::powerup::PowerUp;
+use core_logic::rng::Rng;
+use core_logic::scroll::Scroller;
+use core_logic::sprite_batch::{Sprite, SpriteBatch};
+use core_logic::timestep::lerp;
+
+use crate::color;
+use crate::config::{Coord, Rect, LCD_WIDTH};
+use crate::entity::{Entity, Renderer};
+use crate::frame_record;
+use crate::framebuffer::ImageTransform;
+use crate::sprites::{self, SpriteId};
+
+// One obstacle is on screen at a time, so this leaves room for a pickup
+// still floating away while the next one comes in
+const MAX_PICKUPS: usize = 4;
+
+/// What the bird picked up in one tick
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Collected {
+    pub points: u32,
+    // Where the first of them was
+    pub x: Coord,
+    pub star: bool,
+    pub power: Option<PowerUp>,
+}
+
+pub struct Pickups {
+    slots: [Option<Pickup>; MAX_PICKUPS],
+    // Where each slot was a tick ago, to draw it between ticks from
+    before: [Option<Rect>; MAX_PICKUPS],
+    // Where each was drawn last, to erase it from
+    drawn: Cell<[Option<Rect>; MAX_PICKUPS]>,
+    // Scrolls at the obstacles' speed
+    scroll: Scroller,
+    rng: Rng,
+}
+
+impl Pickups {
+    // Scrolling at `velocity`, in 1/SUBPIXELS of a pixel per tick
+    pub fn new(velocity: u32) -> Self {
+        Pickups {
+            slots: [None; MAX_PICKUPS],
+            before: [None; MAX_PICKUPS],
+            drawn: Cell::new([None; MAX_PICKUPS]),
+            scroll: Scroller::new(velocity),
+            rng: Rng::new(frame_record::seed()),
+        }
+    }
+
+    pub fn set_velocity(&mut self, velocity: u32) {
+        self.scroll.set_velocity(velocity);
+    }
+
+    // Maybe put a pickup in one of the openings of `obstacle`, which has
+    // just come back at the right edge
+    pub fn spawn(&mut self, obstacle: &ObstaclePair) {
+        let power = PowerUp::pick(self.rng.next_u32()).map(PickupKind::Power);
+        let Some(kind) = power.or_else(|| PickupKind::pick(self.rng.next_u32())) else {
+            return;
+        };
+        let gap = self.rng.below(2) as usize;
+        let Some(pickup) =
+            Pickup::in_gap(kind, obstacle, gap).or_else(|| Pickup::in_gap(kind, obstacle, 0))
+        else {
+            return;
+        };
+        if let Some(slot) = self.slots.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(pickup);
+        }
+    }
+
+    // Collect those `bird` touches
+    pub fn collect(&mut self, bird: &Bird) -> Option<Collected> {
+        let mut collected: Option<Collected> = None;
+        for pickup in self.slots.iter_mut().flatten() {
+            if let Some(points) = pickup.collect(bird) {
+                let total = collected.get_or_insert(Collected {
+                    points: 0,
+                    x: pickup.rect().x,
+                    star: false,
+                    power: None,
+                });
+                total.points += points;
+                match pickup.kind {
+                    PickupKind::Star => total.star = true,
+                    PickupKind::Power(power) => total.power = Some(power),
+                    PickupKind::Coin => {}
+                }
+            }
+        }
+        collected
+    }
+}
+
+impl Entity for Pickups {
+    fn update(&mut self, dt: u32) {
+        for _ in 0..dt {
+            let dx = self.scroll.step();
+            for (slot, before) in self.slots.iter_mut().zip(self.before.iter_mut()) {
+                *before = slot.map(|pickup| pickup.rect());
+                if let Some(pickup) = slot.as_mut() {
+                    pickup.advance(dx);
+                    if pickup.is_gone() {
+                        *slot = None;
+                    }
+                }
+            }
+        }
+    }
+
+    // The rows any of them take up, across the screen
+    fn bounds(&self) -> Rect {
+        let mut rows: Option<(Coord, Coord)> = None;
+        for rect in self.slots.iter().flatten().map(Pickup::rect) {
+            let bottom = rect.y + rect.h as Coord;
+            rows = Some(match rows {
+                Some((top, end)) => (top.min(rect.y), end.max(bottom)),
+                None => (rect.y, bottom),
+            });
+        }
+        let (top, bottom) = rows.unwrap_or((0, 0));
+        Rect::new(0, top, LCD_WIDTH, (bottom - top) as u32)
+    }
+
+    fn draw(&self, renderer: &mut Renderer) {
+        for rect in self.drawn.get().iter().flatten() {
+            renderer.fill_sky(*rect);
+        }
+        // The pickups go in together once the old ones are erased
+        let mut batch: SpriteBatch<MAX_PICKUPS> = SpriteBatch::new(Rect::SCREEN);
+        let mut drawn = [None; MAX_PICKUPS];
+        for ((slot, before), drawn) in self.slots.iter().zip(self.before).zip(drawn.iter_mut()) {
+            let Some(pickup) = slot.filter(|pickup| pickup.is_visible()) else {
+                continue;
+            };
+            let mut rect = pickup.rect();
+            if let Some(before) = before {
+                rect.x = lerp(before.x, rect.x, renderer.alpha());
+                rect.y = lerp(before.y, rect.y, renderer.alpha());
+            }
+            let image = match pickup.kind {
+                PickupKind::Coin => sprites::sprite(SpriteId::Coin),
+                PickupKind::Star => sprites::sprite(SpriteId::Star),
+                PickupKind::Power(power) => Some(sprites::power_up(power)),
+            };
+            if let Some(image) = image {
+                let sprite = Sprite {
+                    image,
+                    x: rect.x,
+                    y: rect.y,
+                    transform: ImageTransform::FLIP_Y,
+                    key: Some(color::SPRITE_KEY),
+                };
+                // One slot per pickup, so the batch has room
+                let _ = batch.push(sprite);
+                *drawn = Some(rect);
+            }
+        }
+        renderer.draw_batch(&batch);
+        self.drawn.set(drawn);
+    }
+}
@@ -1,65 +1,310 @@
-use crate::assets;
+use core::cell::Cell;
+
+use core_logic::bird::Bird;
+use core_logic::lean::{self, Lean};
+use core_logic::palette::Palette;
+use core_logic::timestep::lerp;
+
+use crate::assets::hitboxes;
 use crate::color;
-use crate::{config::*, display};
+use crate::config::*;
+use crate::display;
+use crate::entity::{Entity, Renderer};
+use crate::framebuffer::{self, FrameBuffer, ImageTransform, Scale};
+use crate::hud;
+use crate::lane::{self, Lane, LaneDraw};
+use crate::log;
+use crate::mpu6050;
+use crate::profiler::{self, Phase};
+use crate::sprite_cache;
+use crate::sprites::{self, SpriteId};
 
+// The bird (core_logic) and how it is shown
 pub struct Player {
-    x: Coord,
-    y: Coord,
+    bird: Bird,
     w: u32,
     h: u32,
+    // Split-screen birds are drawn into Layer 1 within their lane, as there
+    // is only the one hardware sprite
+    lane: Option<Lane>,
+    // Where `steer` wants the bird next update
+    target: Option<Coord>,
+    // Height a tick ago, to draw between ticks from, and where it was drawn
+    // last, to erase a software-drawn bird from
+    before_y: Coord,
+    drawn_y: Cell<Coord>,
+    // Steps the bird was turned when last drawn, and the lean it is eased
+    // along on each frame
+    drawn_turn: Cell<i8>,
+    lean: Cell<Lean>,
+    // Half size while the shrink power-up lasts, centered in the box; the
+    // size wanted and the size last drawn
+    scale: Scale,
+    drawn_scale: Cell<Scale>,
+    // Draw the hitbox over the bird (`show_hitboxes`)
+    outlined: bool,
+}
+
+// Hitbox outlines for tuning; set from the shell, taken up by the next
+// bird made
+static mut SHOW_HITBOXES: bool = false;
+const HITBOX_COLOR: u16 = color::RED;
+
+pub fn show_hitboxes(on: bool) {
+    unsafe { SHOW_HITBOXES = on };
+}
+
+pub fn hitboxes_shown() -> bool {
+    unsafe { SHOW_HITBOXES }
 }
 
 impl Player {
     pub fn init() -> Self {
         Player {
-            x: INIT_PLAYER_POS_X,
-            y: INIT_PLAYER_POS_Y,
+            bird: Bird::default().with_body(hitboxes::BIRD),
             w: PLAYER_WIDTH,
             h: PLAYER_HEIGHT,
+            lane: None,
+            target: None,
+            before_y: INIT_PLAYER_POS_Y,
+            drawn_y: Cell::new(INIT_PLAYER_POS_Y),
+            drawn_turn: Cell::new(0),
+            lean: Cell::new(Lean::default()),
+            scale: Scale::ONE,
+            drawn_scale: Cell::new(Scale::ONE),
+            outlined: hitboxes_shown(),
         }
     }
 
-    pub fn move_player(&mut self, new_y: Coord) {
-        let old_y = self.y;
+    // Software-drawn bird starting at the top of `lane`'s playfield
+    pub fn init_in(lane: Lane) -> Self {
+        let (y_min, _) = lane.player_y_range();
+        Player {
+            bird: Bird::new(INIT_PLAYER_POS_X, y_min + 10).with_body(hitboxes::BIRD),
+            lane: Some(lane),
+            before_y: y_min + 10,
+            drawn_y: Cell::new(y_min + 10),
+            ..Self::init()
+        }
+    }
 
-        if old_y == new_y {
-            self.y += GRAVITY;
-        } else {
-            self.y = new_y;
+    pub fn bird(&self) -> &Bird {
+        &self.bird
+    }
+
+    // Without the hardware sprite (split screen, SPI rendering, Layer 2
+    // taken by the HUD) the bird is drawn into the picture and erased by
+    // painting the background over it; so is one with its hitbox outlined,
+    // for the outline to go on top
+    fn software(&self) -> bool {
+        self.lane.is_some() || !display::has_sprite_layer() || hud::is_shown() || self.outlined
+    }
+
+    // The hitbox to outline with the bird drawn at `y`
+    fn outline_at(&self, y: Coord) -> Option<Rect> {
+        let (x, _) = self.bird.xy();
+        self.outlined.then(|| self.bird.body().offset(x, y))
+    }
+
+    // Steps the bird is turned now
+    fn turn(&self) -> i8 {
+        self.lean.get().turn()
+    }
+
+    // Ease the lean on by a frame, towards how fast the bird is going and
+    // how the board is being turned, and give the steps to draw it at
+    fn ease_lean(&self) -> i8 {
+        let rate = mpu6050::latest_gyro().map_or(0, |[_, _, z]| z);
+        let mut lean = self.lean.get();
+        lean.ease_to(lean::target(self.bird.velocity(), rate));
+        self.lean.set(lean);
+        lean.turn()
+    }
+
+    // Drawn at half size while shrunk; picked up on the next frame drawn
+    pub fn set_shrunk(&mut self, shrunk: bool) {
+        self.scale = if shrunk { Scale::HALF } else { Scale::ONE };
+    }
+
+    // How the bird art is drawn now, and where in its box it goes
+    fn transform(&self) -> (ImageTransform, Coord, Coord) {
+        let inset = |len: u32| (len - self.scale.of(len)) as Coord / 2;
+        (
+            ImageTransform::FLIP_Y.scaled(self.scale),
+            inset(self.w),
+            inset(self.h),
+        )
+    }
+
+    // Drawn at a different size than it is now
+    fn resized(&self) -> bool {
+        self.drawn_scale.get() != self.scale
+    }
+
+    // The bird turned `turn` steps, from the sprite cache; upright from
+    // flash when the cache has nothing for it
+    fn draw_bird(&self, renderer: &mut Renderer, x: Coord, y: Coord, turn: i8) {
+        self.drawn_turn.set(turn);
+        self.drawn_scale.set(self.scale);
+        let (transform, dx, dy) = self.transform();
+        match sprite_cache::get(SpriteId::Bird, transform, turn) {
+            Some(cached) => renderer.draw_cached(x + dx, y + dy, &cached),
+            None => {
+                if let Some(bird) = sprites::sprite(SpriteId::Bird) {
+                    renderer.draw_sprite_scaled(x + dx, y + dy, &bird, self.scale);
+                }
+            }
         }
+    }
 
-        self.draw();
-        self.clear(old_y);
+    // Put the bird, turned `turn` steps, into the hardware sprite's buffer.
+    // An indexed sprite layer keeps it upright, as its palette is made from
+    // the flash art.
+    fn fill_sprite(&self, turn: i8) {
+        self.drawn_turn.set(turn);
+        self.drawn_scale.set(self.scale);
+        let (transform, dx, dy) = self.transform();
+        let mut sprite = FrameBuffer::layer2();
+        sprite.fill(0);
+        let cached = sprite_cache::get(SpriteId::Bird, transform, turn);
+        if let (Some(cached), false) = (cached, sprite.format().is_indexed()) {
+            sprite.blit_argb(dx, dy, cached.w, cached.pixels);
+        } else if let Some(bird) = sprites::sprite(SpriteId::Bird) {
+            if sprite.format().is_indexed() {
+                // Index 0 is the key, which the layer's color key hides
+                match Palette::from_image(&bird, color::SPRITE_KEY) {
+                    Some(palette) => {
+                        sprite.blit_indexed(dx, dy, &bird, transform, &palette);
+                        let _ = display::set_sprite_palette(&palette);
+                    }
+                    None => log::warn!("bird sprite has too many colors for the CLUT"),
+                }
+            } else {
+                sprite.blit_keyed(dx, dy, &bird, transform, color::SPRITE_KEY);
+            }
+        }
+        framebuffer::flush();
     }
 
-    fn draw(&self) {
-        display::draw_image(
-            self.x,
-            self.w,
-            self.y,
-            self.h,
-            assets::BIRD_IMG_DATA.as_ptr(),
-        );
+    fn erase(&self, x: Coord, y: Coord) {
+        let lane = self.lane.unwrap_or_else(lane::full);
+        lane.fill_sky(Rect::new(x, y, self.w, self.h));
     }
 
-    fn clear(&self, old_y: Coord) {
-        let change_of_y = self.y - old_y;
-        let clear_y = if change_of_y.is_negative() {
-            self.y + PLAYER_HEIGHT as Coord
+    // The bird lives on LTDC Layer 2 and is composited by hardware, so moving
+    // it is just a window update and Layer 1 never needs repainting under it
+    pub fn show(&self) {
+        let (x, y) = self.bird.xy();
+        if self.software() {
+            self.redraw(y);
+            return;
+        }
+        self.fill_sprite(self.turn());
+        let _ = display::set_sprite_position(x, y);
+        let _ = display::set_sprite_alpha(0xFF);
+    }
+
+    pub fn hide(&self) {
+        let (x, _) = self.bird.xy();
+        if self.software() {
+            self.erase(x, self.drawn_y.get());
         } else {
-            old_y
-        };
+            let _ = display::set_sprite_alpha(0);
+        }
+    }
 
-        display::draw_rect_angle(
-            self.x,
-            PLAYER_WIDTH,
-            clear_y,
-            change_of_y.unsigned_abs(),
-            color::BACKGROUND,
-        );
+    // Show the bird where it now is: the sprite window, or a redraw after
+    // erasing it from where it was drawn last
+    fn place(&mut self) {
+        let (x, y) = self.bird.xy();
+        self.before_y = y;
+        if !self.software() {
+            let _ = display::set_sprite_position(x, y);
+        } else if self.drawn_y.get() != y {
+            self.redraw(self.drawn_y.get());
+        }
+    }
+
+    fn redraw(&self, old_y: Coord) {
+        let _render = profiler::scope(Phase::Render);
+        let (x, y) = self.bird.xy();
+        self.erase(x, old_y);
+        let mut renderer = Renderer::new(self.lane.unwrap_or_else(lane::full));
+        self.draw_bird(&mut renderer, x, y, self.turn());
+        renderer.finish();
+        if let Some(hitbox) = self.outline_at(y) {
+            for edge in hitbox.outline() {
+                let _ = display::draw_rect_angle_rust(edge, HITBOX_COLOR);
+            }
+        }
+        self.drawn_y.set(y);
+    }
+
+    // Sink `gravity` rows a frame when left alone
+    pub fn set_gravity(&mut self, gravity: Coord) {
+        self.bird = self.bird.with_gravity(gravity);
+    }
+
+    // Where the bird should go on the next update; without a call it stays
+    // put (and sinks with its gravity)
+    pub fn steer(&mut self, new_y: Coord) {
+        self.target = Some(new_y);
+    }
+
+    pub fn move_player(&mut self, new_y: Coord) {
+        self.bird.move_to(new_y);
+        self.place();
+    }
+
+    // Place the bird directly, ignoring gravity (death animation)
+    pub fn set_y(&mut self, y: Coord) {
+        self.bird.set_y(y);
+        self.place();
     }
 
     pub fn get_xy(&self) -> (Coord, Coord) {
-        (self.x, self.y)
+        self.bird.xy()
+    }
+
+    pub fn get_velocity(&self) -> Coord {
+        self.bird.velocity()
+    }
+}
+
+impl Entity for Player {
+    // The steered move on the first tick, then staying put
+    fn update(&mut self, dt: u32) {
+        for _ in 0..dt {
+            let (_, y) = self.bird.xy();
+            self.before_y = y;
+            self.bird.move_to(self.target.take().unwrap_or(y));
+        }
+    }
+
+    fn bounds(&self) -> Rect {
+        self.bird.rect()
+    }
+
+    // Between the last two ticks, leaning (see `ease_lean`). The hardware
+    // sprite just moves, refilled when the tilt changes; a software bird is
+    // erased and drawn again when it has moved or turned.
+    fn draw(&self, renderer: &mut Renderer) {
+        let (x, y) = self.bird.xy();
+        let y = lerp(self.before_y, y, renderer.alpha());
+        let drawn_y = self.drawn_y.replace(y);
+        let turn = self.ease_lean();
+        let turned = turn != self.drawn_turn.get() || self.resized();
+        if !self.software() {
+            if turned {
+                self.fill_sprite(turn);
+            }
+            let _ = display::set_sprite_position(x, y);
+        } else if drawn_y != y || turned {
+            renderer.fill_sky(Rect::new(x, drawn_y, self.w, self.h));
+            self.draw_bird(renderer, x, y, turn);
+            if let Some(hitbox) = self.outline_at(y) {
+                renderer.outline_rect(hitbox, HITBOX_COLOR);
+            }
+        }
     }
 }
@@ -0,0 +1,223 @@
+//! Idle between frames and sleep when nobody is playing
+//!
+//! Between frames the game task waits for the next LTDC vertical blank
+//! (`executor::next_vblank`), which paces the game to the panel refresh;
+//! with nothing else to run the executor halts the core in WFI instead of
+//! spinning. `idle_until_vblank` is the same wait for code outside a task.
+//!
+//! `sleep` is for the idle title screen. It blanks the display, suspends the
+//! subsystems, parks the SDRAM in self-refresh and enters STOP mode. Any of
+//! these wakes it through an EXTI event:
+//! - the user button (PA0)
+//! - a touch (STMPE811 INT, PA15)
+//! - motion (MPU6050 INT, wired to PC11)
+//!
+//! Clocks, SDRAM, LTDC and subsystems then come back up in reverse order.
+//!
+//! The IWDG keeps counting in STOP, so the RTC wakeup timer brings the core
+//! out every `WAKE_PERIOD_S` to refresh it before going back to sleep.
+//! Without a running RTC the core sleeps in plain WFI instead, woken by
+//! SysTick.
+#![allow(dead_code)]
+
+use crate::backlight;
+use crate::button;
+use crate::clock;
+use crate::iwdg;
+use crate::lcd::LcdDriver;
+use crate::log;
+use crate::mpu6050;
+use crate::profiler::{self, Phase};
+use crate::resources;
+use crate::rtc;
+use crate::sdram;
+use crate::subsystem;
+use crate::touch;
+
+// Well inside the shortest IWDG timeout the LSI tolerance allows
+const WAKE_PERIOD_S: u16 = 1;
+
+// EXTI lines: button PA0, MPU6050 INT PC11, touch INT PA15, RTC wakeup
+const BUTTON_LINE: u32 = 0;
+const MOTION_LINE: u32 = 11;
+const TOUCH_LINE: u32 = 15;
+const RTC_WAKEUP_LINE: u32 = 22;
+
+// SYSCFG_EXTICRx port codes
+const PORT_A: u32 = 0;
+const PORT_C: u32 = 2;
+
+// Wait for the next vertical blank; returns at once with the LTDC off
+pub fn idle_until_vblank() {
+    let _idle = profiler::scope(Phase::Idle);
+    if !LcdDriver::attach().is_enabled() {
+        return;
+    }
+    let seen = LcdDriver::vblank_count();
+    while LcdDriver::vblank_count() == seen {
+        cortex_m::asm::wfi();
+    }
+}
+
+// Route `line` of `port` to EXTI as a wake-up event on the given edge
+fn arm_line(line: u32, port: u32, rising: bool) {
+    let dp = resources::pac();
+    let shift = (line % 4) * 4;
+    let set_port = |r: u32| (r & !(0xF << shift)) | (port << shift);
+    match line / 4 {
+        0 => dp
+            .SYSCFG
+            .exticr1
+            .modify(|r, w| unsafe { w.bits(set_port(r.bits())) }),
+        1 => dp
+            .SYSCFG
+            .exticr2
+            .modify(|r, w| unsafe { w.bits(set_port(r.bits())) }),
+        2 => dp
+            .SYSCFG
+            .exticr3
+            .modify(|r, w| unsafe { w.bits(set_port(r.bits())) }),
+        _ => dp
+            .SYSCFG
+            .exticr4
+            .modify(|r, w| unsafe { w.bits(set_port(r.bits())) }),
+    }
+    arm_event(line, rising);
+}
+
+fn arm_event(line: u32, rising: bool) {
+    let dp = resources::pac();
+    let bit = 1 << line;
+    if rising {
+        dp.EXTI
+            .rtsr
+            .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    } else {
+        dp.EXTI
+            .ftsr
+            .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    }
+    dp.EXTI.pr.write(|w| unsafe { w.bits(bit) });
+    dp.EXTI.emr.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+}
+
+fn disarm_events() {
+    let dp = resources::pac();
+    let lines =
+        (1 << BUTTON_LINE) | (1 << MOTION_LINE) | (1 << TOUCH_LINE) | (1 << RTC_WAKEUP_LINE);
+    dp.EXTI
+        .emr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !lines) });
+    dp.EXTI
+        .rtsr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !lines) });
+    dp.EXTI
+        .ftsr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !lines) });
+    dp.EXTI.pr.write(|w| unsafe { w.bits(lines) });
+}
+
+// PA15 input with pull-up (STMPE811 INT, open drain) and PC11 input with
+// pull-down (MPU6050 INT, push-pull active high)
+fn setup_wake_pins() {
+    let dp = resources::pac();
+    dp.RCC
+        .ahb1enr
+        .modify(|_, w| w.gpioaen().enabled().gpiocen().enabled());
+    dp.RCC.apb2enr.modify(|_, w| w.syscfgen().enabled());
+    dp.GPIOA
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (TOUCH_LINE * 2))) });
+    dp.GPIOA.pupdr.modify(|r, w| unsafe {
+        w.bits((r.bits() & !(0b11 << (TOUCH_LINE * 2))) | (0b01 << (TOUCH_LINE * 2)))
+    });
+    dp.GPIOC
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (MOTION_LINE * 2))) });
+    dp.GPIOC.pupdr.modify(|r, w| unsafe {
+        w.bits((r.bits() & !(0b11 << (MOTION_LINE * 2))) | (0b10 << (MOTION_LINE * 2)))
+    });
+}
+
+fn touch_int_active() -> bool {
+    let dp = resources::pac();
+    dp.GPIOA.idr.read().bits() & (1 << TOUCH_LINE) == 0
+}
+
+fn motion_int_active() -> bool {
+    let dp = resources::pac();
+    dp.GPIOC.idr.read().bits() & (1 << MOTION_LINE) != 0
+}
+
+// STOP with the main regulator in low-power mode, until an EXTI event
+fn enter_stop() {
+    let dp = resources::pac();
+    dp.PWR
+        .cr
+        .modify(|_, w| w.pdds().clear_bit().lpds().set_bit().cwuf().set_bit());
+    let mut cp = unsafe { cortex_m::Peripherals::steal() };
+    cp.SCB.set_sleepdeep();
+    // Clear the event latch first so only a new event wakes the core
+    cortex_m::asm::dsb();
+    cortex_m::asm::sev();
+    cortex_m::asm::wfe();
+    cortex_m::asm::wfe();
+    cp.SCB.clear_sleepdeep();
+}
+
+// Sleep until the button, a touch or motion wakes the board. The caller
+// redraws the screen and restores the backlight afterwards.
+pub fn sleep() {
+    log::info!("sleeping");
+    backlight::set_brightness(0);
+    subsystem::suspend_all();
+    sdram::enter_self_refresh();
+
+    setup_wake_pins();
+    arm_line(BUTTON_LINE, PORT_A, true);
+    let touch = touch::arm_wake().is_ok();
+    if touch {
+        arm_line(TOUCH_LINE, PORT_A, false);
+    }
+    let motion = mpu6050::is_present() && mpu6050::arm_motion_wake().is_ok();
+    if motion {
+        arm_line(MOTION_LINE, PORT_C, true);
+    }
+    let stop = rtc::start_wakeup(WAKE_PERIOD_S).is_ok();
+    if stop {
+        arm_event(RTC_WAKEUP_LINE, true);
+    }
+
+    let woken =
+        || button::is_down() || (touch && touch_int_active()) || (motion && motion_int_active());
+    while !woken() {
+        iwdg::feed();
+        if stop {
+            enter_stop();
+            rtc::clear_wakeup();
+            let dp = resources::pac();
+            dp.EXTI
+                .pr
+                .write(|w| unsafe { w.bits(1 << RTC_WAKEUP_LINE) });
+        } else {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    rtc::stop_wakeup();
+    disarm_events();
+    if stop {
+        clock::restore_after_stop();
+    }
+    sdram::exit_self_refresh();
+    if motion {
+        mpu6050::disarm_motion_wake();
+    }
+    if touch {
+        touch::disarm_wake();
+    }
+    subsystem::resume_all();
+    iwdg::restart_interval();
+    profiler::restart_interval();
+    log::info!("awake");
+}
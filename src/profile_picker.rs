@@ -0,0 +1,87 @@
+//! Who is playing: the profile picker at boot
+//!
+//! One line for each profile slot (`profiles`), its initials, best score
+//! and achievements held, or "new" for an empty one, and one for a guest.
+//! A press moves down the list and a long press or the knob's switch picks,
+//! as in the other menus. It is drawn with `ui` on the overlay, over the
+//! title screen.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use core_logic::profile::{Achievement, SLOTS};
+
+use crate::button::ButtonEvent;
+use crate::encoder::MenuNav;
+use crate::fmt_buf::FmtBuf;
+use crate::lang::{self, Msg};
+use crate::profiles;
+use crate::ui::{self, Focus, Nav, Ui};
+
+// The slots, then the guest
+const ITEM_GUEST: usize = SLOTS;
+const ITEMS: usize = SLOTS + 1;
+
+pub struct ProfilePicker {
+    focus: Focus,
+}
+
+impl ProfilePicker {
+    pub fn new() -> Self {
+        let mut picker = ProfilePicker {
+            focus: Focus::new(),
+        };
+        picker.draw(None);
+        picker
+    }
+
+    // One frame of the picker; once something is picked, the slot picked
+    // or None for the guest
+    pub fn update(
+        &mut self,
+        button: Option<ButtonEvent>,
+        knob: Option<MenuNav>,
+    ) -> Option<Option<usize>> {
+        let nav = match knob {
+            Some(MenuNav::Next) => Some(Nav::Next),
+            Some(MenuNav::Prev) => Some(Nav::Prev),
+            Some(MenuNav::Press) => Some(Nav::Activate),
+            None => Nav::from_button(button),
+        }?;
+        match self.draw(Some(nav))? {
+            ITEM_GUEST => Some(None),
+            slot => Some(Some(slot)),
+        }
+    }
+
+    // Draw the picker, moved on by `nav`; the item `nav` activated
+    fn draw(&mut self, nav: Option<Nav>) -> Option<usize> {
+        let lines: [FmtBuf<24>; SLOTS] = core::array::from_fn(|slot| {
+            let mut line = FmtBuf::new();
+            let _ = match profiles::get(slot) {
+                Some(profile) => write!(
+                    line,
+                    "{} {} {} {}/{}",
+                    profile.initials(),
+                    lang::text(Msg::Best),
+                    profile.best_score,
+                    profile.achievements.count(),
+                    Achievement::ALL.len()
+                ),
+                None => write!(line, "{}: {}", slot + 1, lang::text(Msg::NewProfile)),
+            };
+            line
+        });
+        let mut labels = [""; ITEMS];
+        for (label, line) in labels.iter_mut().zip(&lines) {
+            *label = line.as_str();
+        }
+        labels[ITEM_GUEST] = lang::text(Msg::Guest);
+
+        let mut ui = Ui::begin(&mut self.focus, nav, ui::centered_top(ITEMS + 1));
+        ui.label(lang::text(Msg::WhoIsPlaying));
+        let chosen = ui.list(&labels);
+        ui.end();
+        chosen
+    }
+}
@@ -0,0 +1,341 @@
+//! Frame-time profiler
+//!
+//! Frame times are measured with the DWT cycle counter and collected into a
+//! histogram of 1 ms bins for the current session (one game, from the end of
+//! the countdown to the crash). Percentiles are read back from the histogram,
+//! so they are exact to the bin width.
+//!
+//! Separately, every pass of the main loop is split into phases: time spent
+//! inside `scope(Phase::..)` guards placed in the display, I2C and delay
+//! code, with update as whatever is left of the frame. The split is averaged
+//! over `WINDOW` frames and can be drawn on screen (from the debug menu, or
+//! from boot with feature `overlay`) or streamed over the serial console
+//! (`prof on`). The overlay also shows the session's input latency
+//! (`latency`) and memory use (`memory`) in further columns.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use crate::clock::{self, CYCLES_PER_MS, CYCLES_PER_US};
+use crate::config::{Coord, LCD_HEIGHT, PLANTS_HEIGHT};
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::FrameBuffer;
+use crate::latency;
+use crate::memory;
+use crate::serial::Writer;
+
+// Bins 0..BINS-1 cover 0-1 ms, 1-2 ms, ...; the last bin also takes anything
+// slower
+pub const BINS: usize = 64;
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct FrameSummary {
+    pub frames: u32,
+    pub p50_ms: u8,
+    pub p95_ms: u8,
+    pub p99_ms: u8,
+    pub max_ms: u8,
+}
+
+struct Session {
+    histogram: [u32; BINS],
+    frames: u32,
+    last_cycles: Option<u32>,
+}
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize =
+    core::mem::size_of::<Session>() + core::mem::size_of::<Phases>();
+
+static mut SESSION: Session = Session {
+    histogram: [0; BINS],
+    frames: 0,
+    last_cycles: None,
+};
+
+// Forget the previous session's frames
+pub fn begin_session() {
+    unsafe {
+        SESSION.histogram = [0; BINS];
+        SESSION.frames = 0;
+        SESSION.last_cycles = None;
+    }
+}
+
+// Start timing afresh, e.g. after a pause, so the gap is not counted
+pub fn restart_interval() {
+    unsafe { SESSION.last_cycles = None };
+}
+
+// Call once per frame; records the time since the previous call
+pub fn mark_frame() {
+    let now = clock::cycles();
+    let session = unsafe { &mut SESSION };
+    if let Some(last) = session.last_cycles {
+        let ms = (now.wrapping_sub(last) / CYCLES_PER_MS) as usize;
+        session.histogram[ms.min(BINS - 1)] += 1;
+        session.frames += 1;
+    }
+    session.last_cycles = Some(now);
+}
+
+// Smallest bin upper edge that covers `per_mille` of the frames
+fn percentile(histogram: &[u32; BINS], frames: u32, per_mille: u32) -> u8 {
+    if frames == 0 {
+        return 0;
+    }
+    let target = (frames as u64 * per_mille as u64).div_ceil(1000) as u32;
+    let mut seen = 0;
+    for (bin, &count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return (bin + 1) as u8;
+        }
+    }
+    BINS as u8
+}
+
+pub fn summary() -> FrameSummary {
+    let session = unsafe { &SESSION };
+    let max_ms = session
+        .histogram
+        .iter()
+        .rposition(|&count| count != 0)
+        .map_or(0, |bin| bin + 1) as u8;
+
+    FrameSummary {
+        frames: session.frames,
+        p50_ms: percentile(&session.histogram, session.frames, 500),
+        p95_ms: percentile(&session.histogram, session.frames, 950),
+        p99_ms: percentile(&session.histogram, session.frames, 990),
+        max_ms,
+    }
+}
+
+/// Part of a frame measured by a `Scope`
+#[derive(Copy, Clone, PartialEq)]
+pub enum Phase {
+    // Drawing into the framebuffers
+    Render,
+    // Waiting on the MPU6050
+    I2c,
+    // Busy-wait delays
+    Idle,
+}
+
+const PHASE_COUNT: usize = 3;
+
+// Frames averaged into one published breakdown
+pub const WINDOW: u32 = 32;
+
+/// Average time per frame over the last window, in microseconds
+#[derive(Copy, Clone, Default)]
+pub struct Breakdown {
+    pub frame_us: u32,
+    pub update_us: u32,
+    pub render_us: u32,
+    pub i2c_us: u32,
+    pub idle_us: u32,
+}
+
+impl Breakdown {
+    pub fn fps(&self) -> u32 {
+        1_000_000 / self.frame_us.max(1)
+    }
+}
+
+struct Phases {
+    frame_start: Option<u32>,
+    // Open scopes; only the outermost one is counted, so nested phases are
+    // not counted twice
+    depth: u32,
+    // Cycles per phase over the current window, and the window's total
+    spent: [u32; PHASE_COUNT],
+    total: u32,
+    frames: u32,
+    last: Breakdown,
+    streaming: bool,
+    overlay: bool,
+}
+
+static mut PHASES: Phases = Phases {
+    frame_start: None,
+    depth: 0,
+    spent: [0; PHASE_COUNT],
+    total: 0,
+    frames: 0,
+    last: Breakdown {
+        frame_us: 0,
+        update_us: 0,
+        render_us: 0,
+        i2c_us: 0,
+        idle_us: 0,
+    },
+    streaming: false,
+    overlay: cfg!(feature = "overlay"),
+};
+
+/// Counts the time until it is dropped against one phase
+pub struct Scope {
+    phase: Phase,
+    start: u32,
+    outermost: bool,
+}
+
+pub fn scope(phase: Phase) -> Scope {
+    let phases = unsafe { &mut PHASES };
+    phases.depth += 1;
+    Scope {
+        phase,
+        start: clock::cycles(),
+        outermost: phases.depth == 1,
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let phases = unsafe { &mut PHASES };
+        phases.depth -= 1;
+        if self.outermost {
+            let spent = clock::cycles().wrapping_sub(self.start);
+            phases.spent[self.phase as usize] += spent;
+        }
+    }
+}
+
+// Call at the end of every main loop pass
+pub fn end_frame() {
+    let now = clock::cycles();
+    let phases = unsafe { &mut PHASES };
+    let Some(start) = phases.frame_start.replace(now) else {
+        return;
+    };
+    phases.total += now.wrapping_sub(start);
+    phases.frames += 1;
+    if phases.frames < WINDOW {
+        return;
+    }
+
+    let per_frame_us = |cycles: u32| cycles / phases.frames / CYCLES_PER_US;
+    let render_us = per_frame_us(phases.spent[Phase::Render as usize]);
+    let i2c_us = per_frame_us(phases.spent[Phase::I2c as usize]);
+    let idle_us = per_frame_us(phases.spent[Phase::Idle as usize]);
+    let frame_us = per_frame_us(phases.total);
+    phases.last = Breakdown {
+        frame_us,
+        update_us: frame_us.saturating_sub(render_us + i2c_us + idle_us),
+        render_us,
+        i2c_us,
+        idle_us,
+    };
+    phases.spent = [0; PHASE_COUNT];
+    phases.total = 0;
+    phases.frames = 0;
+
+    if phases.streaming {
+        write_breakdown(&mut Writer);
+    }
+    if phases.overlay {
+        draw_overlay();
+    }
+}
+
+pub fn breakdown() -> Breakdown {
+    unsafe { PHASES.last }
+}
+
+// Send every new breakdown over the serial link
+pub fn set_streaming(on: bool) {
+    unsafe { PHASES.streaming = on };
+}
+
+// Draw every new breakdown over the plants strip. Turned off, the last one
+// stays until the strip is next drawn.
+pub fn show_overlay(on: bool) {
+    unsafe { PHASES.overlay = on };
+}
+
+pub fn overlay_shown() -> bool {
+    unsafe { PHASES.overlay }
+}
+
+pub fn write_breakdown(out: &mut impl core::fmt::Write) {
+    let b = breakdown();
+    let lat = latency::report();
+    let _ = write!(
+        out,
+        "frame {}us ({} fps) update {}us render {}us i2c {}us idle {}us \
+         input avg {}us max {}us ({})\r\n",
+        b.frame_us,
+        b.fps(),
+        b.update_us,
+        b.render_us,
+        b.i2c_us,
+        b.idle_us,
+        lat.average_us,
+        lat.worst_us,
+        lat.samples
+    );
+}
+
+// Three lines over the plants strip at the bottom of Layer 1, which the game
+// draws once and never touches again: frame times on the left, input
+// latency in the middle, stack, static RAM and SDRAM use on the right
+fn draw_overlay() {
+    use embedded_graphics::mono_font::ascii::FONT_6X10;
+    use embedded_graphics::mono_font::MonoTextStyleBuilder;
+    use embedded_graphics::pixelcolor::Rgb565;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::text::{Baseline, Text};
+
+    const LINE_HEIGHT: Coord = 10;
+    let _render = scope(Phase::Render);
+    let b = breakdown();
+    let lat = latency::report();
+    let mem = memory::usage();
+    let mut fb = FrameBuffer::layer1();
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Rgb565::WHITE)
+        .background_color(Rgb565::BLACK)
+        .build();
+
+    let mut y = (LCD_HEIGHT - PLANTS_HEIGHT) as Coord;
+    let mut line: FmtBuf<40> = FmtBuf::new();
+    // Pad so a shorter line covers the previous one
+    let pad = |line: &mut FmtBuf<40>, to: usize| {
+        while line.as_str().len() < to {
+            let _ = line.write_str(" ");
+        }
+    };
+    let mut draw_line = |line: &mut FmtBuf<40>| {
+        pad(line, 40);
+        let _ = Text::with_baseline(line.as_str(), Point::new(0, y), style, Baseline::Top)
+            .draw(&mut fb);
+        y += LINE_HEIGHT;
+        line.clear();
+    };
+
+    // Latency in whole milliseconds, to fit
+    let ms = |us: u32| us.div_ceil(1000).min(999);
+    let _ = write!(line, "{:>2}fps {:>5}us", b.fps(), b.frame_us);
+    pad(&mut line, 20);
+    let _ = write!(line, " in {:>4}", lat.samples.min(9999));
+    pad(&mut line, 29);
+    let _ = write!(line, " stk {:>6}", mem.stack_free);
+    draw_line(&mut line);
+    let _ = write!(line, "upd {:>5} rnd {:>5}", b.update_us, b.render_us);
+    pad(&mut line, 20);
+    let _ = write!(line, " avg{:>3}ms", ms(lat.average_us));
+    pad(&mut line, 29);
+    let _ = write!(line, " ram {:>6}", mem.static_bytes());
+    draw_line(&mut line);
+    let _ = write!(line, "i2c {:>5} idl {:>5}", b.i2c_us, b.idle_us);
+    pad(&mut line, 20);
+    let _ = write!(line, " max{:>3}ms", ms(lat.worst_us));
+    pad(&mut line, 29);
+    let _ = write!(line, " sdr {:>5}K", mem.sdram_used / 1024);
+    draw_line(&mut line);
+}
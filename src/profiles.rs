@@ -0,0 +1,130 @@
+//! Player profiles, kept in the flash store
+//!
+//! Up to `profile::SLOTS` people sharing the board each get a profile
+//! (core_logic::profile) in a store record of its own: their initials, best
+//! score, games and achievements, then their settings as `settings` stores
+//! them. Each record carries the journal's CRC, so a save cut short costs
+//! only that profile its newest change and never touches the others.
+//!
+//! The picker (`profile_picker`) comes up at boot. Picking a profile puts
+//! its settings in force, and from then on the settings saved, the games
+//! finished and the initials signed on the leaderboard go into it as well.
+//! A slot with nothing in it is made fresh from the settings as they are.
+//! A guest plays on the settings as they are and keeps nothing of their own.
+#![allow(dead_code)]
+
+use core_logic::leaderboard::INITIALS;
+use core_logic::profile::{Achievements, Profile, SLOTS, WORDS};
+
+use crate::log;
+use crate::resources::ThreadOnly;
+use crate::settings::{self, Settings};
+use crate::store;
+
+// The slot being played and its profile; None for a guest
+static ACTIVE: ThreadOnly<Option<(usize, Profile)>> = ThreadOnly::of(None);
+
+// The profile stored in `slot`, and its settings if this firmware reads
+// their version
+fn read(slot: usize) -> Option<(Profile, Option<Settings>)> {
+    let mut words = [0; store::MAX_PAYLOAD];
+    let len = store::read(store::profile(slot), &mut words)?;
+    let profile = Profile::from_words(&words[..len])?;
+    Some((profile, settings::from_payload(&words[WORDS..len])))
+}
+
+fn write(slot: usize, profile: &Profile, settings: &Settings) -> Result<(), ()> {
+    let mut words = [0; store::MAX_PAYLOAD];
+    words[..WORDS].copy_from_slice(&profile.to_words());
+    let len = settings::payload(settings, &mut words[WORDS..]).ok_or(())?;
+    store::write(store::profile(slot), &words[..WORDS + len])
+}
+
+// The profile in `slot`, if one was ever made there
+pub fn get(slot: usize) -> Option<Profile> {
+    read(slot % SLOTS).map(|(profile, _)| profile)
+}
+
+// The slot being played and its profile; None for a guest
+pub fn active() -> Option<(usize, Profile)> {
+    ACTIVE.with(|active| *active).flatten()
+}
+
+fn set_active(active: Option<(usize, Profile)>) {
+    ACTIVE.with(|slot| *slot = active);
+}
+
+// Play as the profile in `slot`, or as a guest for None. The profile's
+// settings are put in force; the game should start over to pick them all
+// up (see `system::request_warm_restart`).
+pub fn select(slot: Option<usize>) {
+    set_active(None);
+    let Some(slot) = slot.map(|slot| slot % SLOTS) else {
+        log::info!("playing as a guest");
+        return;
+    };
+    let profile = match read(slot) {
+        Some((profile, Some(stored))) => {
+            settings::replace(&stored);
+            profile
+        }
+        // Settings of a version this firmware does not read: the current
+        // ones take their place
+        Some((profile, None)) => profile,
+        None => Profile::new(slot),
+    };
+    set_active(Some((slot, profile)));
+    save();
+    log::info!("playing as {}", profile.initials());
+}
+
+// Store the profile playing, with the current settings; nothing for a guest
+pub fn save() {
+    if let Some((slot, profile)) = active() {
+        if write(slot, &profile, &settings::get()).is_err() {
+            log::error!("profile save failed");
+        }
+    }
+}
+
+// Fold a finished game of `score` into the profile playing; the
+// achievements it newly earned
+pub fn record_game(score: u32) -> Achievements {
+    let Some((slot, mut profile)) = active() else {
+        return Achievements::NONE;
+    };
+    let earned = profile.record_game(score);
+    set_active(Some((slot, profile)));
+    save();
+    for achievement in earned.iter() {
+        log::info!("{} earned {}", profile.initials(), achievement.name());
+    }
+    earned
+}
+
+// Initials of the profile playing, to sign a run with
+pub fn initials() -> Option<[u8; INITIALS]> {
+    active().map(|(_, profile)| profile.initials)
+}
+
+// The profile playing signed a run as `initials`, and goes by them now
+pub fn sign(initials: [u8; INITIALS]) {
+    let Some((slot, mut profile)) = active() else {
+        return;
+    };
+    if profile.initials != initials {
+        profile.initials = initials;
+        set_active(Some((slot, profile)));
+        save();
+    }
+}
+
+// Forget the profile in `slot`; one being played goes on as a guest
+pub fn clear(slot: usize) -> Result<(), ()> {
+    let slot = slot % SLOTS;
+    if active().is_some_and(|(active, _)| active == slot) {
+        set_active(None);
+    }
+    // An empty record reads back as no profile
+    store::write(store::profile(slot), &[])
+}
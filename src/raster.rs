@@ -0,0 +1,147 @@
+//! Raster effects on Layer 1
+//!
+//! While an effect is on, game drawing lands in the Layer 1 back buffer
+//! (`layer1_b`) and `present` copies it to the scanned-out buffer once per
+//! frame a scanline at a time, bending each line as the effect says (see
+//! core_logic::raster). Runs of lines the effect leaves alone go across in
+//! one DMA2D transfer while the CPU works on the next bent line. Once the
+//! effect ends, the last picture is copied across plain and drawing goes
+//! back to Layer 1 itself.
+//!
+//! A burst effect (`underwater`) runs for a set number of frames and then
+//! gives way to the ambient one (`set_heat`), if that is on.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::config::{Coord, LCD_WIDTH};
+use core_logic::raster::{Line, RasterEffect, Shimmer, Wave};
+
+use crate::display::{self, Backend};
+use crate::framebuffer::{scan_orientation, FrameBuffer};
+use crate::lane;
+use crate::lcd::{self, DISPLAY_MEMORY};
+use crate::profiler::{self, Phase};
+use crate::sdram;
+use crate::transition;
+
+// Game rows of haze over the ground
+const HEAT_ROWS: u32 = 24;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Effect {
+    Wave(Wave),
+    Shimmer(Shimmer),
+}
+
+impl RasterEffect for Effect {
+    fn line(&self, row: u32, frame: u32) -> Line {
+        match self {
+            Effect::Wave(wave) => wave.line(row, frame),
+            Effect::Shimmer(shimmer) => shimmer.line(row, frame),
+        }
+    }
+}
+
+// The burst effect and its frames left, and the ambient one
+static mut BURST: Option<(Effect, u32)> = None;
+static mut AMBIENT: Option<Effect> = None;
+// Frames the current effect has been showing
+static mut FRAME: u32 = 0;
+// Whether drawing goes to the back buffer
+static mut ACTIVE: bool = false;
+
+// Whether game drawing goes to the back buffer now
+pub fn active() -> bool {
+    unsafe { ACTIVE }
+}
+
+fn current() -> Option<Effect> {
+    unsafe { BURST.map(|(effect, _)| effect).or(AMBIENT) }
+}
+
+// Both buffers live in SDRAM and only LTDC shows them
+fn available() -> bool {
+    sdram::available() && display::backend() == Backend::Ltdc
+}
+
+// Carry the picture on screen into the back buffer before drawing moves
+// there; a screen transition already has it there
+fn begin() {
+    if active() || !available() {
+        return;
+    }
+    if !transition::active() {
+        let mem = &DISPLAY_MEMORY;
+        FrameBuffer::copy_raster(&mem.layer1, &mem.layer1_b, |_| Line::PLAIN);
+    }
+    unsafe {
+        ACTIVE = true;
+        FRAME = 0;
+    }
+}
+
+// Sway the screen as if under water for `frames` frames
+pub fn underwater(frames: u32) {
+    begin();
+    unsafe { BURST = Some((Effect::Wave(Wave::underwater(lcd::surface().0)), frames)) };
+}
+
+// Haze over the ground until turned off
+pub fn set_heat(on: bool) {
+    if on == unsafe { AMBIENT.is_some() } {
+        return;
+    }
+    if on {
+        begin();
+        unsafe { AMBIENT = Some(heat()) };
+    } else {
+        unsafe { AMBIENT = None };
+    }
+}
+
+// The scanlines over the ground as the game is turned now
+fn heat() -> Effect {
+    let lane = lane::full();
+    let top = lane.y(lane.ground) - HEAT_ROWS as Coord;
+    let band = (0, top, LCD_WIDTH, HEAT_ROWS);
+    let (x, y, w, h) = scan_orientation()
+        .map_rect(band, lcd::surface())
+        .unwrap_or_default();
+    let y = y.max(0) as u32;
+    Effect::Shimmer(Shimmer::heat(y, y + h, x.max(0) as u32, w))
+}
+
+// Stop every effect; the next `present` puts the plain picture back
+pub fn clear() {
+    unsafe {
+        BURST = None;
+        AMBIENT = None;
+    }
+}
+
+// Copy the back buffer to Layer 1 through the effect; call once per frame
+// after anything else that draws the game (retro::present). A screen
+// transition holds the effect still until it is over.
+pub fn present() {
+    if !active() || transition::active() {
+        return;
+    }
+    let _render = profiler::scope(Phase::Render);
+    let mem = &DISPLAY_MEMORY;
+    let Some(effect) = current() else {
+        FrameBuffer::copy_raster(&mem.layer1_b, &mem.layer1, |_| Line::PLAIN);
+        unsafe { ACTIVE = false };
+        return;
+    };
+    let frame = unsafe { FRAME };
+    FrameBuffer::copy_raster(&mem.layer1_b, &mem.layer1, |row| effect.line(row, frame));
+    unsafe {
+        FRAME = FRAME.wrapping_add(1);
+        if let Some((_, frames)) = BURST.as_mut() {
+            *frames = frames.saturating_sub(1);
+            if *frames == 0 {
+                BURST = None;
+            }
+        }
+    }
+}
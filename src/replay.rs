@@ -0,0 +1,186 @@
+//! Replay of the last run, for a host to fetch
+//!
+//! Every single-player run that is not the demo is recorded in the format
+//! of core_logic::replay into an SDRAM buffer: the header at the start,
+//! written when the run ends, the frames after it as they are played. The
+//! run stays there until the next one starts. A host asks for it with
+//! `KIND_REPLAY_REQ` (or `replay send` in the shell) and gets it back over
+//! the telemetry link, a chunk each time the serial task polls so the game
+//! never waits on the line:
+//!
+//! ```text
+//! KIND_REPLAY      offset u32 | up to CHUNK bytes of the replay
+//! KIND_REPLAY_END  len u32 | crc u32
+//! ```
+//!
+//! `crc` is the CRC unit's CRC-32/MPEG-2 of the replay as little-endian
+//! words, the last zero-padded (core_logic::crc::mpeg2 on the host); `len`
+//! is 0 when there is no finished run to send. There is no replay without
+//! SDRAM.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::replay::{self, Encoder, Frame, Header, HEADER_LEN, MAX_FRAME_LEN};
+
+use crate::config::Coord;
+use crate::crc;
+use crate::lcd::DISPLAY_MEMORY;
+use crate::log;
+use crate::sdram;
+use crate::sdram::arena::{Arena, Region};
+use crate::sprite_cache;
+use crate::telemetry;
+
+// Header and frames: over 15 minutes of play at a byte a frame
+const BUFFER_BYTES: u32 = 64 * 1024;
+// Replay bytes in one KIND_REPLAY message, after its offset
+const CHUNK: usize = telemetry::MAX_PAYLOAD - 4;
+
+const AFTER_CACHE: Region = Region {
+    base: sprite_cache::CACHE.end(),
+    size: DISPLAY_MEMORY.free.end() - sprite_cache::CACHE.end(),
+};
+pub const BUFFER: Region = Arena::new(AFTER_CACHE).alloc(BUFFER_BYTES, 4);
+
+struct State {
+    header: Option<Header>,
+    // Bytes in the buffer, the header's included
+    len: u32,
+    encoder: Encoder,
+    recording: bool,
+    // The run ended with the header written; it can be sent
+    finished: bool,
+    // Next offset to send, while a host is being sent the replay
+    sending: Option<u32>,
+}
+
+static mut STATE: State = State {
+    header: None,
+    len: 0,
+    encoder: Encoder::new(),
+    recording: false,
+    finished: false,
+    sending: None,
+};
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<State>();
+
+fn write(offset: u32, bytes: &[u8]) {
+    for (i, &byte) in bytes.iter().enumerate() {
+        let addr = BUFFER.base + offset + i as u32;
+        unsafe { core::ptr::write_volatile(addr as *mut u8, byte) };
+    }
+}
+
+fn read(offset: u32) -> u8 {
+    unsafe { core::ptr::read_volatile((BUFFER.base + offset) as *const u8) }
+}
+
+// Begin recording a run set up as `header` says; its frames and score are
+// filled in as it goes
+pub fn start(header: Header) {
+    if !sdram::available() {
+        return;
+    }
+    let state = unsafe { &mut STATE };
+    state.header = Some(Header {
+        frames: 0,
+        score: 0,
+        ..header
+    });
+    state.len = HEADER_LEN as u32;
+    state.encoder = Encoder::new();
+    state.recording = true;
+    state.finished = false;
+    state.sending = None;
+}
+
+// One running frame: where the input sent the bird, whether it tapped and
+// the ticks the frame ran
+pub fn frame(y: Coord, tap: bool, ticks: u32) {
+    let state = unsafe { &mut STATE };
+    if !state.recording {
+        return;
+    }
+    let Some(header) = state.header.as_mut() else {
+        return;
+    };
+    if header.flags & replay::TRUNCATED != 0 {
+        return;
+    }
+    let mut bytes = [0; MAX_FRAME_LEN];
+    let frame = Frame {
+        y,
+        tap,
+        ticks: ticks.min(7) as u8,
+    };
+    let len = state.encoder.encode(frame, &mut bytes);
+    if state.len + len as u32 > BUFFER_BYTES {
+        header.flags |= replay::TRUNCATED;
+        return;
+    }
+    write(state.len, &bytes[..len]);
+    state.len += len as u32;
+    header.frames += 1;
+}
+
+// The run is over at `score`; write its header so it can be sent
+pub fn finish(score: u32) {
+    let state = unsafe { &mut STATE };
+    if !state.recording {
+        return;
+    }
+    state.recording = false;
+    let Some(header) = state.header.as_mut() else {
+        return;
+    };
+    header.score = score;
+    write(0, &header.to_bytes());
+    state.finished = true;
+    log::info!("replay: {} frames, {} bytes", header.frames, state.len);
+}
+
+// The last finished run, if there is one to send
+pub fn last() -> Option<(Header, u32)> {
+    let state = unsafe { &STATE };
+    if !state.finished {
+        return None;
+    }
+    state.header.map(|header| (header, state.len))
+}
+
+pub fn is_sending() -> bool {
+    unsafe { STATE.sending.is_some() }
+}
+
+// Send the last run to the host from the start, over the next polls
+pub fn request() {
+    unsafe { STATE.sending = Some(0) };
+}
+
+// Send the next chunk, if a replay is being sent; call from the serial task
+pub fn poll() {
+    let state = unsafe { &mut STATE };
+    let Some(offset) = state.sending else {
+        return;
+    };
+    let len = if state.finished { state.len } else { 0 };
+    if offset >= len {
+        let crc = crc::hw_crc32_bytes((0..len).map(read));
+        let mut payload = [0u8; 8];
+        payload[0..4].copy_from_slice(&len.to_le_bytes());
+        payload[4..8].copy_from_slice(&crc.to_le_bytes());
+        telemetry::send(telemetry::KIND_REPLAY_END, &payload);
+        state.sending = None;
+        return;
+    }
+    let count = (len - offset).min(CHUNK as u32);
+    let mut payload = [0u8; 4 + CHUNK];
+    payload[0..4].copy_from_slice(&offset.to_le_bytes());
+    for i in 0..count {
+        payload[4 + i as usize] = read(offset + i);
+    }
+    telemetry::send(telemetry::KIND_REPLAY, &payload[..4 + count as usize]);
+    state.sending = Some(offset + count);
+}
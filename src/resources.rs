@@ -0,0 +1,157 @@
+//! Peripherals and driver state shared between thread and interrupt code
+//!
+//! The device peripherals are taken once, the first time anything asks,
+//! and lent out by `pac()` from then on; nothing conjures its own copy
+//! with `steal()`. Register reads and writes through the shared reference
+//! are single volatile accesses. A read-modify-write of a register that an
+//! interrupt handler also changes goes inside `critical`.
+//!
+//! State wrappers, for statics that used to be `static mut`:
+//! - `Shared<T>` for state both sides use: `lock` runs with interrupts
+//!   masked, so keep it short
+//! - `ThreadOnly<T>` for state only thread code uses, such as the display:
+//!   put in once by whoever builds it, then no masking, so it suits long
+//!   work, and using it from an interrupt handler is a panic rather than a
+//!   race
+#![allow(dead_code)]
+
+use core::cell::{RefCell, UnsafeCell};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use cortex_m::interrupt::{CriticalSection, Mutex};
+use cortex_m::peripheral::scb::VectActive;
+use cortex_m::peripheral::SCB;
+use stm32f4::stm32f429 as pac;
+
+struct Device(UnsafeCell<MaybeUninit<pac::Peripherals>>);
+
+// Written once, inside a critical section, before READY is set; only read
+// after
+unsafe impl Sync for Device {}
+
+static DEVICE: Device = Device(UnsafeCell::new(MaybeUninit::uninit()));
+static READY: AtomicBool = AtomicBool::new(false);
+
+// The device peripherals
+pub fn pac() -> &'static pac::Peripherals {
+    if !READY.load(Ordering::Acquire) {
+        cortex_m::interrupt::free(|_| {
+            if !READY.load(Ordering::Relaxed) {
+                let taken = pac::Peripherals::take().expect("device peripherals taken twice");
+                unsafe { (*DEVICE.0.get()).write(taken) };
+                READY.store(true, Ordering::Release);
+            }
+        });
+    }
+    unsafe { (*DEVICE.0.get()).assume_init_ref() }
+}
+
+// Run `f` with interrupts masked, for read-modify-writes an interrupt
+// handler could interleave with
+pub fn critical<R>(f: impl FnOnce(&pac::Peripherals) -> R) -> R {
+    cortex_m::interrupt::free(|_| f(pac()))
+}
+
+pub fn in_interrupt() -> bool {
+    SCB::vect_active() != VectActive::ThreadMode
+}
+
+/// State used from both thread and interrupt context
+pub struct Shared<T> {
+    inner: Mutex<RefCell<Option<T>>>,
+}
+
+impl<T> Shared<T> {
+    pub const fn new() -> Self {
+        Shared {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    pub const fn of(value: T) -> Self {
+        Shared {
+            inner: Mutex::new(RefCell::new(Some(value))),
+        }
+    }
+
+    pub fn put(&self, value: T) -> Option<T> {
+        cortex_m::interrupt::free(|cs| self.cell(cs).replace(Some(value)))
+    }
+
+    pub fn take(&self) -> Option<T> {
+        cortex_m::interrupt::free(|cs| self.cell(cs).take())
+    }
+
+    // Run `f` on the value with interrupts masked; None while there is none
+    pub fn lock<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        cortex_m::interrupt::free(|cs| self.cell(cs).borrow_mut().as_mut().map(f))
+    }
+
+    fn cell<'cs>(&'cs self, cs: &'cs CriticalSection) -> &'cs RefCell<Option<T>> {
+        self.inner.borrow(cs)
+    }
+}
+
+impl<T> Default for Shared<T> {
+    fn default() -> Self {
+        Shared::new()
+    }
+}
+
+/// State only thread code uses, empty until it is put in
+pub struct ThreadOnly<T> {
+    inner: RefCell<Option<T>>,
+}
+
+// Every access checks it is in thread mode, and thread mode is one context
+unsafe impl<T> Sync for ThreadOnly<T> {}
+
+impl<T> ThreadOnly<T> {
+    pub const fn new() -> Self {
+        ThreadOnly {
+            inner: RefCell::new(None),
+        }
+    }
+
+    // Already holding `value`, for state that is there from the start
+    pub const fn of(value: T) -> Self {
+        ThreadOnly {
+            inner: RefCell::new(Some(value)),
+        }
+    }
+
+    // Store `value`, once; a second one is handed back
+    pub fn put(&self, value: T) -> Result<(), T> {
+        assert!(!in_interrupt(), "thread-only state used from an interrupt");
+        cortex_m::interrupt::free(|_| {
+            let mut inner = self.inner.borrow_mut();
+            match *inner {
+                Some(_) => Err(value),
+                None => {
+                    *inner = Some(value);
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    pub fn is_set(&self) -> bool {
+        assert!(!in_interrupt(), "thread-only state used from an interrupt");
+        self.inner.borrow().is_some()
+    }
+
+    // Run `f` on the value, or None before it is put in. Panics from an
+    // interrupt handler, or if `f` comes back round to the same value.
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        assert!(!in_interrupt(), "thread-only state used from an interrupt");
+        let mut value = self.inner.borrow_mut();
+        value.as_mut().map(f)
+    }
+}
+
+impl<T> Default for ThreadOnly<T> {
+    fn default() -> Self {
+        ThreadOnly::new()
+    }
+}
@@ -0,0 +1,78 @@
+//! Retro low-resolution mode
+//!
+//! While on, game drawing lands in a 120x160 buffer (half the panel in each
+//! direction; the game runs portrait) and `present` blows it up 2x into
+//! Layer 1 once per frame. That is a quarter of the pixels for every draw
+//! call, plus the chunky look. The bird stays on Layer 2 at full resolution.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::framebuffer::FrameBuffer;
+use crate::lcd::DISPLAY_MEMORY;
+use crate::profiler::{self, Phase};
+use crate::raster;
+use crate::transition;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum RetroMode {
+    Off,
+    On,
+    // Every other panel row darkened, like a CRT
+    Scanlines,
+}
+
+static mut MODE: RetroMode = RetroMode::Off;
+
+pub fn mode() -> RetroMode {
+    unsafe { MODE }
+}
+
+pub fn enabled() -> bool {
+    mode() != RetroMode::Off
+}
+
+pub fn set_mode(mode: RetroMode) {
+    // Carry the current picture into the small buffer so switching on does
+    // not start from stale contents
+    if !enabled() && mode != RetroMode::Off {
+        let mem = &DISPLAY_MEMORY;
+        let shown = if raster::active() || transition::active() {
+            &mem.layer1_b
+        } else {
+            &mem.layer1
+        };
+        FrameBuffer::scale(shown, &mem.retro, false);
+    }
+    unsafe { MODE = mode };
+    if mode != RetroMode::Off {
+        present();
+    }
+}
+
+// Off -> On -> Scanlines -> Off, for the settings menu
+pub fn cycle() -> RetroMode {
+    let next = match mode() {
+        RetroMode::Off => RetroMode::On,
+        RetroMode::On => RetroMode::Scanlines,
+        RetroMode::Scanlines => RetroMode::Off,
+    };
+    set_mode(next);
+    next
+}
+
+// Upscale the retro buffer onto Layer 1, or onto the back buffer for
+// raster::present or transition::present to take on from there; call once
+// per frame
+pub fn present() {
+    if enabled() {
+        let _render = profiler::scope(Phase::Render);
+        let scanlines = mode() == RetroMode::Scanlines;
+        let mem = &DISPLAY_MEMORY;
+        let dst = if raster::active() || transition::active() {
+            &mem.layer1_b
+        } else {
+            &mem.layer1
+        };
+        FrameBuffer::scale(&mem.retro, dst, scanlines);
+    }
+}
@@ -0,0 +1,347 @@
+//! Real-time clock (calendar in the backup domain)
+//!
+//! Runs from the 32.768 kHz LSE crystal when one is fitted and starts,
+//! otherwise from the internal ~32 kHz LSI, which drifts by minutes a day
+//! and stops without main power. Once started the RTC keeps counting across
+//! resets, so `init` leaves a running calendar alone. The date starts at
+//! 2000-01-01 00:00:00 until set, e.g. with the console `time` command.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt;
+
+use stm32f4::stm32f429 as pac;
+
+use crate::clock;
+use crate::error::HwError;
+use crate::log;
+use crate::resources;
+use crate::subsystem::{Health, Subsystem};
+
+// LSE crystals take up to a couple of seconds to start; give up after this
+const LSE_STARTUP_MS: u32 = 2_000;
+
+// RCC_BDCR RTCSEL values
+const RTCSEL_LSE: u8 = 0b01;
+const RTCSEL_LSI: u8 = 0b10;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Source {
+    Lse,
+    Lsi,
+    None,
+}
+
+static mut SOURCE: Source = Source::None;
+
+/// Calendar date and time, year 2000-2099
+#[derive(Copy, Clone, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    pub const EPOCH: DateTime = DateTime {
+        year: 2000,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+
+    pub fn is_valid(&self) -> bool {
+        (2000..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    // Seconds since 2000-01-01 00:00:00
+    pub fn timestamp(&self) -> u32 {
+        let days = days_since_epoch(self.year, self.month, self.day);
+        days * 86_400 + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+
+    pub fn from_timestamp(ts: u32) -> DateTime {
+        let mut days = ts / 86_400;
+        let secs = ts % 86_400;
+        let mut year = 2000;
+        while days >= days_in_year(year) {
+            days -= days_in_year(year);
+            year += 1;
+        }
+        let mut month = 1;
+        while days >= days_in_month(year, month) as u32 {
+            days -= days_in_month(year, month) as u32;
+            month += 1;
+        }
+        DateTime {
+            year,
+            month,
+            day: days as u8 + 1,
+            hour: (secs / 3600) as u8,
+            minute: (secs / 60 % 60) as u8,
+            second: (secs % 60) as u8,
+        }
+    }
+
+    // Monday = 1 .. Sunday = 7, as the RTC stores it
+    fn weekday(&self) -> u8 {
+        // 2000-01-01 was a Saturday
+        ((days_since_epoch(self.year, self.month, self.day) + 5) % 7 + 1) as u8
+    }
+
+    // "YYYY-MM-DD HH:MM:SS"
+    pub fn parse(date: &str, time: &str) -> Option<DateTime> {
+        let mut d = date.split('-').map(str::parse::<u16>);
+        let mut t = time.split(':').map(str::parse::<u8>);
+        let dt = DateTime {
+            year: d.next()?.ok()?,
+            month: d.next()?.ok()? as u8,
+            day: d.next()?.ok()? as u8,
+            hour: t.next()?.ok()?,
+            minute: t.next()?.ok()?,
+            second: t.next().unwrap_or(Ok(0)).ok()?,
+        };
+        dt.is_valid().then_some(dt)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+fn is_leap(year: u16) -> bool {
+    // Exact for 2000-2099
+    year.is_multiple_of(4)
+}
+
+fn days_in_year(year: u16) -> u32 {
+    if is_leap(year) {
+        366
+    } else {
+        365
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn days_since_epoch(year: u16, month: u8, day: u8) -> u32 {
+    let years: u32 = (2000..year).map(days_in_year).sum();
+    let months: u32 = (1..month).map(|m| days_in_month(year, m) as u32).sum();
+    years + months + day as u32 - 1
+}
+
+fn bcd(value: u8) -> u32 {
+    (((value / 10) << 4) | (value % 10)) as u32
+}
+
+fn from_bcd(bits: u32) -> u8 {
+    ((bits >> 4) * 10 + (bits & 0xF)) as u8
+}
+
+pub fn source() -> Source {
+    unsafe { SOURCE }
+}
+
+pub fn init() -> Result<(), ()> {
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+    // Backup domain write access
+    rcc.apb1enr.modify(|_, w| w.pwren().enabled());
+    dp.PWR.cr.modify(|_, w| w.dbp().set_bit());
+
+    let bdcr = rcc.bdcr.read();
+    if bdcr.rtcen().bit_is_set() && dp.RTC.isr.read().inits().bit_is_set() {
+        let source = match bdcr.rtcsel().bits() {
+            RTCSEL_LSE => Source::Lse,
+            RTCSEL_LSI => {
+                // LSI is not in the backup domain; restart it after reset
+                rcc.csr.modify(|_, w| w.lsion().on());
+                while rcc.csr.read().lsirdy().is_not_ready() {}
+                Source::Lsi
+            }
+            _ => Source::None,
+        };
+        unsafe { SOURCE = source };
+        return if source == Source::None {
+            Err(())
+        } else {
+            Ok(())
+        };
+    }
+
+    rcc.bdcr.modify(|_, w| w.lseon().on());
+    let start = clock::millis();
+    while rcc.bdcr.read().lserdy().is_not_ready() {
+        if clock::millis().wrapping_sub(start) > LSE_STARTUP_MS {
+            break;
+        }
+    }
+    let (sel, source, prediv_s) = if rcc.bdcr.read().lserdy().is_ready() {
+        (RTCSEL_LSE, Source::Lse, 255)
+    } else {
+        log::warn!("RTC: no LSE, falling back to LSI");
+        rcc.bdcr.modify(|_, w| w.lseon().off());
+        rcc.csr.modify(|_, w| w.lsion().on());
+        while rcc.csr.read().lsirdy().is_not_ready() {}
+        // LSI is nominally 32 kHz
+        (RTCSEL_LSI, Source::Lsi, 249)
+    };
+    rcc.bdcr
+        .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << 8)) | ((sel as u32) << 8)) });
+    rcc.bdcr.modify(|_, w| w.rtcen().enabled());
+    unsafe { SOURCE = source };
+
+    // 1 Hz calendar: clock / (PREDIV_A + 1) / (PREDIV_S + 1)
+    with_init_mode(|rtc| {
+        rtc.prer.write(|w| w.prediv_s().bits(prediv_s));
+        rtc.prer.modify(|_, w| w.prediv_a().bits(127));
+        write_calendar(rtc, &DateTime::EPOCH);
+    });
+    log::info!(
+        "RTC started on {}",
+        if source == Source::Lse { "LSE" } else { "LSI" }
+    );
+    Ok(())
+}
+
+// Run `f` with the calendar stopped and writable
+fn with_init_mode(f: impl FnOnce(&pac::RTC)) {
+    let dp = resources::pac();
+    let rtc = &dp.RTC;
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+    rtc.isr.modify(|_, w| w.init().set_bit());
+    while rtc.isr.read().initf().bit_is_clear() {}
+    f(rtc);
+    rtc.isr
+        .modify(|_, w| w.init().clear_bit().rsf().clear_bit());
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+}
+
+// Set the wakeup flag, which is EXTI line 22, every `seconds`; used to
+// leave STOP mode periodically. Err without a running RTC.
+pub fn start_wakeup(seconds: u16) -> Result<(), ()> {
+    if source() == Source::None || seconds == 0 {
+        return Err(());
+    }
+    let dp = resources::pac();
+    let rtc = &dp.RTC;
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+    rtc.cr.modify(|_, w| w.wute().clear_bit());
+    while rtc.isr.read().wutwf().bit_is_clear() {}
+    rtc.wutr.write(|w| w.wut().bits(seconds - 1));
+    // ck_spre, the 1 Hz calendar clock
+    rtc.cr
+        .modify(|_, w| unsafe { w.wucksel().bits(0b100).wutie().set_bit().wute().set_bit() });
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+    clear_wakeup();
+    Ok(())
+}
+
+pub fn stop_wakeup() {
+    if source() == Source::None {
+        return;
+    }
+    let dp = resources::pac();
+    let rtc = &dp.RTC;
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+    rtc.cr
+        .modify(|_, w| w.wute().clear_bit().wutie().clear_bit());
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+    clear_wakeup();
+}
+
+// Acknowledge a wakeup so the next one raises EXTI line 22 again
+pub fn clear_wakeup() {
+    let dp = resources::pac();
+    dp.RTC.isr.modify(|_, w| w.wutf().clear_bit());
+}
+
+fn write_calendar(rtc: &pac::RTC, dt: &DateTime) {
+    let tr = (bcd(dt.hour) << 16) | (bcd(dt.minute) << 8) | bcd(dt.second);
+    let dr = (bcd((dt.year - 2000) as u8) << 16)
+        | ((dt.weekday() as u32) << 13)
+        | (bcd(dt.month) << 8)
+        | bcd(dt.day);
+    rtc.tr.write(|w| unsafe { w.bits(tr) });
+    rtc.dr.write(|w| unsafe { w.bits(dr) });
+}
+
+pub fn set(dt: &DateTime) {
+    if source() == Source::None {
+        return;
+    }
+    with_init_mode(|rtc| write_calendar(rtc, dt));
+}
+
+pub fn now() -> DateTime {
+    let dp = resources::pac();
+    let rtc = &dp.RTC;
+    if source() == Source::None {
+        return DateTime::EPOCH;
+    }
+    // Wait for the shadow registers after init or a set
+    while rtc.isr.read().rsf().bit_is_clear() {}
+    // Reading TR freezes DR until DR is read
+    let tr = rtc.tr.read().bits();
+    let dr = rtc.dr.read().bits();
+    DateTime {
+        year: 2000 + from_bcd((dr >> 16) & 0xFF) as u16,
+        month: from_bcd((dr >> 8) & 0x1F),
+        day: from_bcd(dr & 0x3F),
+        hour: from_bcd((tr >> 16) & 0x3F),
+        minute: from_bcd((tr >> 8) & 0x7F),
+        second: from_bcd(tr & 0x7F),
+    }
+}
+
+// Seconds since 2000-01-01, for timestamps kept in the stats store
+pub fn timestamp() -> u32 {
+    now().timestamp()
+}
+
+pub struct RtcSubsystem;
+
+impl Subsystem for RtcSubsystem {
+    fn name(&self) -> &'static str {
+        "rtc"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        init().map_err(|()| HwError::ClockNotReady)
+    }
+
+    fn health_check(&self) -> Health {
+        match source() {
+            Source::Lse => Health::Ok,
+            Source::Lsi => Health::Degraded("on LSI"),
+            Source::None => Health::Failed("no clock"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! SEGGER RTT: a debug probe's window into RAM
+//!
+//! The `_SEGGER_RTT` control block describes one up-channel (board to host)
+//! and one down-channel (host to board), each a ring buffer in RAM. A probe
+//! finds the block by its ID and reads and writes the rings through the
+//! debug port while the core runs, so no UART, pins or halting are needed;
+//! probe-rs, OpenOCD and J-Link all speak it. `init` writes the ID last, so
+//! a probe scanning RAM never finds a half-built block.
+//!
+//! The up-channel drops what does not fit (SEGGER's no-block-skip mode):
+//! with no probe attached nobody empties it, and the game must not wait.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::ptr::{self, addr_of, addr_of_mut};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+const UP_SIZE: usize = 1024;
+const DOWN_SIZE: usize = 64;
+
+// Skip writes that do not fit
+const MODE_NO_BLOCK_SKIP: u32 = 0;
+
+#[repr(C)]
+struct Channel {
+    name: *const u8,
+    buffer: *mut u8,
+    size: u32,
+    // The writer's offset, advanced by whoever fills the ring
+    write: u32,
+    // The reader's offset, advanced by whoever empties it
+    read: u32,
+    flags: u32,
+}
+
+impl Channel {
+    const EMPTY: Channel = Channel {
+        name: ptr::null(),
+        buffer: ptr::null_mut(),
+        size: 0,
+        write: 0,
+        read: 0,
+        flags: 0,
+    };
+}
+
+#[repr(C)]
+struct ControlBlock {
+    id: [u8; 16],
+    max_up: u32,
+    max_down: u32,
+    up: Channel,
+    down: Channel,
+}
+
+#[no_mangle]
+#[used]
+static mut _SEGGER_RTT: ControlBlock = ControlBlock {
+    id: [0; 16],
+    max_up: 1,
+    max_down: 1,
+    up: Channel::EMPTY,
+    down: Channel::EMPTY,
+};
+
+static mut UP: [u8; UP_SIZE] = [0; UP_SIZE];
+static mut DOWN: [u8; DOWN_SIZE] = [0; DOWN_SIZE];
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<ControlBlock>() + UP_SIZE + DOWN_SIZE;
+
+const ID: &[u8; 10] = b"SEGGER RTT";
+const NAME: &[u8] = b"Terminal\0";
+
+pub fn init() {
+    unsafe {
+        let block = addr_of_mut!(_SEGGER_RTT);
+        ptr::write_volatile(
+            addr_of_mut!((*block).up),
+            Channel {
+                name: NAME.as_ptr(),
+                buffer: addr_of_mut!(UP) as *mut u8,
+                size: UP_SIZE as u32,
+                flags: MODE_NO_BLOCK_SKIP,
+                ..Channel::EMPTY
+            },
+        );
+        ptr::write_volatile(
+            addr_of_mut!((*block).down),
+            Channel {
+                name: NAME.as_ptr(),
+                buffer: addr_of_mut!(DOWN) as *mut u8,
+                size: DOWN_SIZE as u32,
+                flags: MODE_NO_BLOCK_SKIP,
+                ..Channel::EMPTY
+            },
+        );
+        compiler_fence(Ordering::SeqCst);
+        // Back to front, so the first byte only matches once the rest is in
+        let id = addr_of_mut!((*block).id) as *mut u8;
+        for (i, &byte) in ID.iter().enumerate().rev() {
+            ptr::write_volatile(id.add(i), byte);
+        }
+    }
+}
+
+// Queue all of `bytes` for the host, or none if the ring has no room for
+// them; returns whether they went
+pub fn write(bytes: &[u8]) -> bool {
+    unsafe {
+        let channel = addr_of_mut!(_SEGGER_RTT.up);
+        let size = UP_SIZE as u32;
+        let read = ptr::read_volatile(addr_of!((*channel).read));
+        let mut write = ptr::read_volatile(addr_of!((*channel).write));
+        // One slot stays empty, or a full ring would look empty
+        let free = (read + size - write - 1) % size;
+        if bytes.len() as u32 > free {
+            return false;
+        }
+        for &byte in bytes {
+            ptr::write_volatile(addr_of_mut!(UP[write as usize]), byte);
+            write = (write + 1) % size;
+        }
+        compiler_fence(Ordering::SeqCst);
+        ptr::write_volatile(addr_of_mut!((*channel).write), write);
+    }
+    true
+}
+
+// Take what the host has written, up to `buf`'s length; returns how much
+pub fn read(buf: &mut [u8]) -> usize {
+    unsafe {
+        let channel = addr_of_mut!(_SEGGER_RTT.down);
+        let size = DOWN_SIZE as u32;
+        let write = ptr::read_volatile(addr_of!((*channel).write));
+        let mut read = ptr::read_volatile(addr_of!((*channel).read));
+        compiler_fence(Ordering::SeqCst);
+        let mut count = 0;
+        while read != write && count < buf.len() {
+            buf[count] = ptr::read_volatile(addr_of!(DOWN[read as usize]));
+            read = (read + 1) % size;
+            count += 1;
+        }
+        ptr::write_volatile(addr_of_mut!((*channel).read), read);
+        count
+    }
+}
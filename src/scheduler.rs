@@ -0,0 +1,123 @@
+//! Periodic jobs for the main loop
+//!
+//! Things that want doing every so often, not every frame, are `Job`s
+//! with an interval in the core_logic `Scheduler`. SysTick ticks it and
+//! wakes the periodic task in `main`, which waits on `next` and runs
+//! whichever job came due, so the game task only draws frames. Cheaper than
+//! a task per job: one waker, and a bit per job in the interrupt.
+#![allow(dead_code)]
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use core_logic::scheduler::Scheduler;
+
+use crate::clock;
+use crate::resources::Shared;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Job {
+    // Accelerometer and gyro sample for the tilt input
+    Sensor,
+    // The accelerometer's shake and free-fall detectors
+    Motion,
+    // Battery and sensor state shown on the HUD
+    Hud,
+    // Status frame for the host, when asked for
+    Telemetry,
+    // A step of a backlight fade
+    Backlight,
+    // Live tuning commands in, watched variables out, over RTT
+    Rtt,
+    // Every subsystem's health, restarting any that failed
+    Supervise,
+}
+
+impl Job {
+    const ALL: [Job; JOBS] = [
+        Job::Sensor,
+        Job::Motion,
+        Job::Hud,
+        Job::Telemetry,
+        Job::Backlight,
+        Job::Rtt,
+        Job::Supervise,
+    ];
+}
+
+const JOBS: usize = 7;
+
+// 100 Hz tilt sampling
+pub const SENSOR_MS: u32 = 10;
+// How long to leave the accelerometer after it fails to answer
+pub const SENSOR_RETRY_MS: u32 = 1000;
+const MOTION_MS: u32 = 50;
+const HUD_MS: u32 = 100;
+const TELEMETRY_MS: u32 = 1000;
+// 50 Hz, smooth enough for a fade
+const BACKLIGHT_MS: u32 = 20;
+// 10 Hz, as fast as a person reads a streamed value
+const RTT_MS: u32 = 100;
+// Often enough that a failed subsystem is back within seconds
+const SUPERVISE_MS: u32 = 5_000;
+
+// Intervals in `Job` order
+static SCHEDULER: Shared<Scheduler<JOBS>> = Shared::of(Scheduler::new([
+    SENSOR_MS,
+    MOTION_MS,
+    HUD_MS,
+    TELEMETRY_MS,
+    BACKLIGHT_MS,
+    RTT_MS,
+    SUPERVISE_MS,
+]));
+static WAKER: Shared<Waker> = Shared::new();
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize =
+    core::mem::size_of::<Shared<Scheduler<JOBS>>>() + core::mem::size_of::<Shared<Waker>>();
+
+// Mark the jobs that came due and wake the task running them; from SysTick
+pub fn on_tick(now: u32) {
+    if SCHEDULER.lock(|scheduler| scheduler.tick(now)) == Some(true) {
+        if let Some(waker) = WAKER.take() {
+            waker.wake();
+        }
+    }
+}
+
+// Run `job` every `ms` from now on
+pub fn set_interval(job: Job, ms: u32) {
+    let now = clock::millis();
+    SCHEDULER.lock(|scheduler| {
+        if scheduler.interval(job as usize) != ms {
+            scheduler.set_interval(job as usize, ms, now);
+        }
+    });
+}
+
+pub struct NextJob;
+
+// The next job due, at once if one is waiting
+pub fn next() -> NextJob {
+    NextJob
+}
+
+impl Future for NextJob {
+    type Output = Job;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Job> {
+        // Taking and keeping the waker with interrupts masked, so a tick in
+        // between is not missed
+        cortex_m::interrupt::free(|_| {
+            match SCHEDULER.lock(|scheduler| scheduler.take()).flatten() {
+                Some(job) => Poll::Ready(Job::ALL[job]),
+                None => {
+                    WAKER.put(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
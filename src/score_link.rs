@@ -0,0 +1,79 @@
+//! Run results to a host PC as plain text lines
+//!
+//! Every finished game sends one line on the serial link (the ST-LINK
+//! virtual COM port, 115200 8N1), so a terminal logging to a file keeps a
+//! record of every run:
+//!
+//! ```text
+//! run mode=solo score=12 best=30 ms=43210 difficulty=normal controls=tilt theme=Day sound=on
+//! ```
+//!
+//! Fields are space-separated `key=value` pairs after the `run` tag; `ms`
+//! is the time played and `best` the stored high score after this run. The
+//! host can send these commands, each a line of its own:
+//!
+//! - `reset-best`: clear the stored high score; answered `ok reset-best`
+//!
+//! Unlike the shell these work in production builds too. With the `wifi`
+//! feature each run is also posted to a score server (`wifi`).
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use crate::serial::Writer;
+use crate::settings;
+use crate::stats;
+use crate::theme;
+use crate::wifi;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Mode {
+    Solo,
+    Versus,
+    Runner,
+    Signature,
+    TimeAttack,
+}
+
+impl Mode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Mode::Solo => "solo",
+            Mode::Versus => "versus",
+            Mode::Runner => "runner",
+            Mode::Signature => "signature",
+            Mode::TimeAttack => "time-attack",
+        }
+    }
+}
+
+// Send the result of a finished game, and post it through `wifi` when that
+// is on; after stats::record_session, so `best` already counts it
+pub fn report(mode: Mode, score: u32, play_ms: u32) {
+    wifi::upload(mode.as_str(), score, play_ms);
+    let settings = settings::get();
+    let _ = write!(
+        Writer,
+        "run mode={} score={} best={} ms={} difficulty={} controls={} theme={} sound={}\r\n",
+        mode.as_str(),
+        score,
+        stats::load().best_score,
+        play_ms,
+        settings.difficulty.as_str(),
+        settings.controls.as_str(),
+        theme::current().name,
+        if settings.sound { "on" } else { "off" },
+    );
+}
+
+// Carry out `line` if it is a host command; false if it is not one
+pub fn command(line: &str) -> bool {
+    match line {
+        "reset-best" => {
+            stats::reset_best();
+            let _ = write!(Writer, "ok reset-best\r\n");
+            true
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,88 @@
+//! Burn-in protection for the menu screens
+//!
+//! The pause menu and the stats page stay up for as long as nobody touches
+//! the board. After `Settings::screensaver_s` of that the backlight dims
+//! and both layers start creeping a couple of pixels a minute, and a few
+//! minutes later the layers go off and the backlight with them; the
+//! schedule is core_logic::screensaver. The title screen has its own
+//! dimming and sleeps instead.
+//!
+//! The game calls `update` once a frame on a menu screen. Input puts the
+//! screen back, and the press that did it goes no further, so a press on a
+//! dark screen cannot pick a menu item nobody could see. Any change of
+//! game state calls `wake` too, which also restarts the idle time.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::screensaver::{self, Stage};
+
+use crate::backlight;
+use crate::clock;
+use crate::display;
+use crate::settings;
+
+// Backlight while dimmed, in percent of the level it was at
+const DIM_PERCENT: u8 = 30;
+const DIM_FADE_MS: u32 = 1_000;
+const WAKE_FADE_MS: u32 = 200;
+
+static mut SINCE: u32 = 0;
+static mut STAGE: Stage = Stage::Awake;
+// Backlight level to go back to
+static mut LEVEL: u8 = backlight::FULL;
+
+// One frame of a menu screen, `touched` if any input came in it. True when
+// that input only woke the screen and should be dropped.
+pub fn update(touched: bool) -> bool {
+    if touched {
+        return wake();
+    }
+    let idle = clock::millis().wrapping_sub(unsafe { SINCE });
+    let next = screensaver::stage(idle, settings::get().screensaver_s);
+    let stage = unsafe { STAGE };
+    if next == stage {
+        return false;
+    }
+    if stage == Stage::Awake {
+        let level = backlight::brightness();
+        unsafe { LEVEL = level };
+        let dimmed = level as u32 * DIM_PERCENT as u32 / 100;
+        backlight::fade_to(dimmed as u8, DIM_FADE_MS);
+    }
+    match next {
+        Stage::Awake => {}
+        Stage::Dimmed((dx, dy)) => shift(dx, dy),
+        Stage::Blank => {
+            backlight::set_brightness(0);
+            let _ = display::set_layers_visible(false);
+        }
+    }
+    unsafe { STAGE = next };
+    false
+}
+
+// Put the screen back as it was and start the idle time over; true if it
+// was dimmed or blank
+pub fn wake() -> bool {
+    unsafe { SINCE = clock::millis() };
+    let stage = unsafe { STAGE };
+    if stage == Stage::Awake {
+        return false;
+    }
+    if stage == Stage::Blank {
+        let _ = display::set_layers_visible(true);
+    }
+    shift(0, 0);
+    backlight::fade_to(unsafe { LEVEL }, WAKE_FADE_MS);
+    unsafe { STAGE = Stage::Awake };
+    true
+}
+
+pub fn stage() -> Stage {
+    unsafe { STAGE }
+}
+
+fn shift(dx: i32, dy: i32) {
+    let _ = display::set_frame_offset(dx, dy);
+    let _ = display::set_overlay_offset(dx, dy);
+}
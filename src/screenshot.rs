@@ -0,0 +1,171 @@
+//! Layer 1 screenshots, streamed over the serial link or kept in flash
+//!
+//! `stream` sends what is on screen right now; `save` copies it into the
+//! spare flash sectors so it can be fetched later with `stream_saved`, after
+//! a reset or without a host attached when the shot was taken (holding the
+//! button for `input::HOLD_MS` saves one). Either way the output is one
+//! header line followed by the pixels, row by row from the top left:
+//!
+//! ```text
+//! SHOT 240 320 rgb565 153600\r\n<153600 bytes, little-endian RGB565>
+//! SHOT 240 320 ppm 230415\r\n<binary PPM: P6 header and RGB888>
+//! ```
+//!
+//! Only Layer 1 is captured; the overlay and sprite layer are composited by
+//! the LTDC and never exist in memory as one image.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use crate::config::{LCD_HEIGHT, LCD_WIDTH};
+use crate::crc;
+use crate::flash;
+use crate::framebuffer::FrameBuffer;
+use crate::log;
+use crate::serial::{self, Writer};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Format {
+    Rgb565,
+    Ppm,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "raw" | "rgb565" => Some(Format::Rgb565),
+            "ppm" => Some(Format::Ppm),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Rgb565 => "rgb565",
+            Format::Ppm => "ppm",
+        }
+    }
+}
+
+const PIXELS: u32 = LCD_WIDTH * LCD_HEIGHT;
+const MAGIC: u32 = 0x5448_5331; // "SHT1"
+
+// Saved shot: this header at the start of flash::SPARE, pixels right after
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Header {
+    magic: u32,
+    width: u32,
+    height: u32,
+    crc: u32,
+}
+
+const HEADER_WORDS: u32 = (core::mem::size_of::<Header>() / 4) as u32;
+const PIXELS_BASE: u32 = flash::SPARE.base + HEADER_WORDS * 4;
+
+const _: () = assert!(
+    HEADER_WORDS * 4 + PIXELS * 2 <= flash::SPARE.size,
+    "screenshot does not fit the spare flash sectors"
+);
+
+fn live_pixel(fb: &mut FrameBuffer, i: u32) -> u16 {
+    fb.get_pixel_rgb565((i % LCD_WIDTH) as i32, (i / LCD_WIDTH) as i32)
+        .unwrap_or(0)
+}
+
+fn saved_pixel(i: u32) -> u16 {
+    unsafe { core::ptr::read_volatile((PIXELS_BASE + i * 2) as *const u16) }
+}
+
+// Blocks for the whole transfer: about 13 s for RGB565 at 115200 baud
+fn send(format: Format, mut pixel: impl FnMut(u32) -> u16) {
+    let ppm_header_len = 15; // "P6\n240 320\n255\n"
+    let len = match format {
+        Format::Rgb565 => PIXELS * 2,
+        Format::Ppm => ppm_header_len + PIXELS * 3,
+    };
+    let _ = write!(
+        Writer,
+        "SHOT {} {} {} {}\r\n",
+        LCD_WIDTH,
+        LCD_HEIGHT,
+        format.name(),
+        len
+    );
+    if format == Format::Ppm {
+        let _ = write!(Writer, "P6\n{} {}\n255\n", LCD_WIDTH, LCD_HEIGHT);
+    }
+    for i in 0..PIXELS {
+        let rgb565 = pixel(i);
+        match format {
+            Format::Rgb565 => serial::write(&rgb565.to_le_bytes()),
+            Format::Ppm => {
+                // Replicate the high bits so full scale stays 255
+                let r = (rgb565 >> 11) as u8 & 0x1F;
+                let g = (rgb565 >> 5) as u8 & 0x3F;
+                let b = rgb565 as u8 & 0x1F;
+                serial::write(&[
+                    (r << 3) | (r >> 2),
+                    (g << 2) | (g >> 4),
+                    (b << 3) | (b >> 2),
+                ]);
+            }
+        }
+    }
+    serial::flush();
+}
+
+// Send the current Layer 1 contents
+pub fn stream(format: Format) {
+    let mut fb = FrameBuffer::layer1();
+    send(format, |i| live_pixel(&mut fb, i));
+}
+
+fn saved_header() -> Option<Header> {
+    let header = unsafe { core::ptr::read_volatile(flash::SPARE.base as *const Header) };
+    (header.magic == MAGIC && header.width == LCD_WIDTH && header.height == LCD_HEIGHT)
+        .then_some(header)
+}
+
+fn pixel_crc(mut pixel: impl FnMut(u32) -> u16) -> u32 {
+    crc::crc32((0..PIXELS).flat_map(|i| pixel(i).to_le_bytes()))
+}
+
+// Whether flash holds an intact saved shot
+pub fn saved() -> bool {
+    matches!(saved_header(), Some(header) if header.crc == pixel_crc(saved_pixel))
+}
+
+// Copy Layer 1 into flash, replacing any earlier shot. Takes a few seconds,
+// most of it erasing.
+pub fn save() -> Result<(), ()> {
+    let mut fb = FrameBuffer::layer1();
+    let crc = pixel_crc(|i| live_pixel(&mut fb, i));
+
+    flash::erase_spare()?;
+    let pixels = (0..PIXELS / 2).map(|i| {
+        let lo = live_pixel(&mut fb, i * 2) as u32;
+        let hi = live_pixel(&mut fb, i * 2 + 1) as u32;
+        lo | (hi << 16)
+    });
+    flash::program(PIXELS_BASE, pixels)?;
+    // Header last, so an interrupted save never looks valid
+    flash::program(flash::SPARE.base, [MAGIC, LCD_WIDTH, LCD_HEIGHT, crc])?;
+
+    if saved() {
+        log::info!("screenshot saved to flash");
+        Ok(())
+    } else {
+        log::error!("screenshot readback mismatch");
+        Err(())
+    }
+}
+
+// Send the shot kept in flash
+pub fn stream_saved(format: Format) -> Result<(), ()> {
+    if !saved() {
+        return Err(());
+    }
+    send(format, saved_pixel);
+    Ok(())
+}
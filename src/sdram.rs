@@ -6,7 +6,58 @@
 use cortex_m::asm;
 use stm32f4::stm32f429 as pac;
 
+use crate::clock;
+use crate::crc;
+use crate::error::HwError;
+use crate::resources;
+
+pub mod arena;
+
+use arena::Region;
+
 pub const SDRAM_BASE: u32 = 0xD000_0000; // Bank2 base
+pub const SDRAM_SIZE: u32 = 8 * 1024 * 1024; // IS42S16400J, 64 Mbit
+
+// Polls of SDSR.BUSY per mode register command
+const COMMAND_TIMEOUT: u32 = 100_000;
+
+// Top 4 KB hold a fixed pattern for the runtime spot check
+pub const SPOT_CHECK_BASE: u32 = SDRAM_BASE + SDRAM_SIZE - SPOT_CHECK_SIZE;
+pub const SPOT_CHECK_SIZE: u32 = 4096;
+pub const SPOT_CHECK: Region = Region {
+    base: SPOT_CHECK_BASE,
+    size: SPOT_CHECK_SIZE,
+};
+// What buffers may be allocated from
+pub const SDRAM_ALLOCATABLE: Region = Region {
+    base: SDRAM_BASE,
+    size: SDRAM_SIZE - SPOT_CHECK_SIZE,
+};
+static mut SPOT_CHECK_CRC: Option<u32> = None;
+// Set by init; boards or builds without SDRAM (SPI rendering) never set it
+static mut READY: bool = false;
+// Outcome of the last self_test, for the diagnostics page
+static mut LAST_FAULT: Option<SdramFault> = None;
+
+/// Which part of the self-test found the fault
+#[derive(Copy, Clone, PartialEq)]
+pub enum SdramTest {
+    // Walking ones on one word: a stuck or shorted DQ line
+    DataBus,
+    // Power-of-two offsets aliasing each other: a stuck or shorted address line
+    AddressBus,
+    // Every word holding its own address (and then its complement)
+    Cells,
+}
+
+/// First mismatch found by `self_test`
+#[derive(Copy, Clone)]
+pub struct SdramFault {
+    pub test: SdramTest,
+    pub addr: u32,
+    pub expected: u32,
+    pub actual: u32,
+}
 
 // Configure one GPIO pin to AF12 FMC: mode=AF, high speed, push-pull, no pull
 macro_rules! cfg_pin_af12 {
@@ -47,10 +98,10 @@ macro_rules! cfg_pins_af12 {
     }};
 }
 
-pub fn init() {
+pub fn init() -> Result<(), HwError> {
     // Safety: we do raw peripheral register writes at startup
-    let dp = unsafe { pac::Peripherals::steal() };
-    let rcc = dp.RCC;
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
 
     // Enable GPIO clocks: B,C,D,E,F,G
     rcc.ahb1enr.modify(|_, w| {
@@ -84,7 +135,7 @@ pub fn init() {
     rcc.ahb3enr.modify(|_, w| w.fmcen().enabled());
     asm::nop();
 
-    let fmc = dp.FMC;
+    let fmc = &dp.FMC;
 
     // SDCR configuration (matching C settings):
     // RPIPE=1CLK, SDCLK=2xHCLK, CAS=3 cycles, NB=4 banks, MWID=16-bit, NR=12 row, NC=8 col
@@ -134,8 +185,9 @@ pub fn init() {
             .nrfs().bits(0)
             .mrd().bits(0)
         );
+        wait_not_busy(fmc)?;
         // Delay >= 100us
-        delay_cycles(16_000); // ~100us at 168MHz (approx)
+        clock::delay_us(100);
 
         // Command: PALL (precharge all)
         fmc.sdcmr.write(|w| w
@@ -144,6 +196,7 @@ pub fn init() {
             .nrfs().bits(0)
             .mrd().bits(0)
         );
+        wait_not_busy(fmc)?;
 
         // Command: Auto-refresh, 4 cycles
         fmc.sdcmr.write(|w| w
@@ -152,6 +205,7 @@ pub fn init() {
             .nrfs().bits(4) // 4 refresh cycles
             .mrd().bits(0)
         );
+        wait_not_busy(fmc)?;
 
         // Command: Load Mode Register
         // MRD value: BL=2 (001), BT=0 (seq), CAS=3 (011), OM=00, WB=1 (single) => 0x231
@@ -162,14 +216,191 @@ pub fn init() {
             .nrfs().bits(1)
             .mrd().bits(mrd)
         );
+        wait_not_busy(fmc)?;
 
         // Set refresh rate
         // SDRTR[13:1] COUNTER = 683
         fmc.sdrtr.modify(|_, w| w.reie().clear_bit().count().bits(683));
+        READY = true;
+    }
+    Ok(())
+}
+
+// SDSR.BUSY is set while a command goes out; if it never clears, the FMC is
+// not clocked or not configured. A missing chip only shows in self_test.
+fn wait_not_busy(fmc: &pac::FMC) -> Result<(), HwError> {
+    for _ in 0..COMMAND_TIMEOUT {
+        if fmc.sdsr.read().busy().is_not_busy() {
+            return Ok(());
+        }
+    }
+    Err(HwError::Timeout)
+}
+
+// Whether init has brought the SDRAM up; nothing may touch it before
+pub fn available() -> bool {
+    unsafe { READY }
+}
+
+// Park the SDRAM in self-refresh so it keeps its contents while the FMC
+// clock is stopped (STOP mode). Nothing may access it until exit_self_refresh.
+pub fn enter_self_refresh() {
+    if !available() {
+        return;
+    }
+    let dp = resources::pac();
+    let fmc = &dp.FMC;
+    fmc.sdcmr.write(|w| unsafe {
+        w.mode()
+            .bits(0b101)
+            .ctb2()
+            .set_bit()
+            .nrfs()
+            .bits(0)
+            .mrd()
+            .bits(0)
+    });
+    while !fmc.sdsr.read().modes2().is_self_refresh() {}
+}
+
+// Back to normal mode; call once HCLK is running at full speed again
+pub fn exit_self_refresh() {
+    if !available() {
+        return;
+    }
+    let dp = resources::pac();
+    let fmc = &dp.FMC;
+    fmc.sdcmr.write(|w| unsafe {
+        w.mode()
+            .bits(0b000)
+            .ctb2()
+            .set_bit()
+            .nrfs()
+            .bits(0)
+            .mrd()
+            .bits(0)
+    });
+    while !fmc.sdsr.read().modes2().is_normal() {}
+}
+
+fn write_word(addr: u32, value: u32) {
+    unsafe { core::ptr::write_volatile(addr as *mut u32, value) };
+}
+
+fn read_word(addr: u32) -> u32 {
+    unsafe { core::ptr::read_volatile(addr as *const u32) }
+}
+
+fn check(test: SdramTest, addr: u32, expected: u32) -> Result<(), SdramFault> {
+    let actual = read_word(addr);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(SdramFault {
+            test,
+            addr,
+            expected,
+            actual,
+        })
     }
 }
 
-fn delay_cycles(mut n: i32) {
-    // Crude busy loop
-    while n != 0 { asm::nop(); n -= 1; }
+// Destructive test of [base, base + len): data bus, address bus, then every
+// cell. Run after init() and before LTDC starts reading the region; the
+// caller redraws whatever lived there.
+pub fn self_test(base: u32, len: u32) -> Result<(), SdramFault> {
+    let result = run_tests(base, len);
+    unsafe { LAST_FAULT = result.err() };
+    result
+}
+
+pub fn last_fault() -> Option<SdramFault> {
+    unsafe { LAST_FAULT }
+}
+
+fn run_tests(base: u32, len: u32) -> Result<(), SdramFault> {
+    let words = len / 4;
+    if words == 0 {
+        return Ok(());
+    }
+
+    // Data bus: walk a single one through all 32 bits of the first word
+    for bit in 0..32 {
+        write_word(base, 1 << bit);
+        check(SdramTest::DataBus, base, 1 << bit)?;
+    }
+
+    // Address bus: fill the power-of-two word offsets, then disturb each in
+    // turn and make sure no other offset changed with it
+    const PATTERN: u32 = 0xAAAA_AAAA;
+    const ANTI: u32 = 0x5555_5555;
+    let offsets =
+        || core::iter::successors(Some(1u32), |o| o.checked_mul(2)).take_while(move |&o| o < words);
+    write_word(base, PATTERN);
+    for offset in offsets() {
+        write_word(base + offset * 4, PATTERN);
+    }
+    write_word(base, ANTI);
+    for offset in offsets() {
+        check(SdramTest::AddressBus, base + offset * 4, PATTERN)?;
+    }
+    write_word(base, PATTERN);
+    for victim in offsets() {
+        write_word(base + victim * 4, ANTI);
+        check(SdramTest::AddressBus, base, PATTERN)?;
+        for other in offsets().filter(|&o| o != victim) {
+            check(SdramTest::AddressBus, base + other * 4, PATTERN)?;
+        }
+        write_word(base + victim * 4, PATTERN);
+    }
+
+    // Cells: address-in-address, then the complement so every bit of every
+    // word has held both a 0 and a 1
+    for invert in [0, u32::MAX] {
+        for i in 0..words {
+            let addr = base + i * 4;
+            write_word(addr, addr ^ invert);
+        }
+        for i in 0..words {
+            let addr = base + i * 4;
+            check(SdramTest::Cells, addr, addr ^ invert)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Fill the spot-check block with a fixed pattern and remember its CRC
+pub fn arm_spot_check() {
+    for i in 0..SPOT_CHECK_SIZE / 4 {
+        write_word(SPOT_CHECK_BASE + i * 4, i.wrapping_mul(0x9E37_79B9));
+    }
+    unsafe { SPOT_CHECK_CRC = Some(spot_check_crc()) };
+}
+
+fn spot_check_crc() -> u32 {
+    let words = (0..SPOT_CHECK_SIZE / 4).map(|i| read_word(SPOT_CHECK_BASE + i * 4));
+    crc::crc32(words.flat_map(u32::to_le_bytes))
+}
+
+// For the hardware report: up, through its self-test and still holding
+// the spot-check block. Ok carries the size in bytes.
+pub fn probe() -> Result<u32, HwError> {
+    if !available() {
+        return Err(HwError::NoResponse);
+    }
+    if last_fault().is_some() || !spot_check() {
+        return Err(HwError::SelfTestFailed);
+    }
+    Ok(SDRAM_SIZE)
+}
+
+// Cheap runtime check that SDRAM is still holding data (refresh, timing):
+// recompute the CRC of the block written by arm_spot_check. Returns true
+// when it was never armed.
+pub fn spot_check() -> bool {
+    match unsafe { SPOT_CHECK_CRC } {
+        Some(expected) => spot_check_crc() == expected,
+        None => true,
+    }
 }
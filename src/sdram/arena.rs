@@ -0,0 +1,119 @@
+//! Bump allocator over the external SDRAM
+//!
+//! SDRAM buffers all live for the whole run, so there is no free. The
+//! allocator is `const`: modules lay out their buffers in `const` items, an
+//! arena that runs out fails the build, and `check_disjoint` catches regions
+//! handed out by different arenas overlapping at startup.
+//!
+//! Framebuffers carry their width, height and pixel format in their type,
+//! so a buffer's size and line stride follow from what it is, and code
+//! that needs two buffers of the same shape or format says so in its
+//! signature instead of trusting constants to line up.
+#![allow(dead_code)]
+
+use core::marker::PhantomData;
+
+use crate::lcd::Pixel;
+
+// Framebuffers start on a 64-byte boundary so LTDC/DMA2D bursts never
+// straddle a line
+pub const FRAMEBUFFER_ALIGN: u32 = 64;
+
+/// A span of SDRAM
+#[derive(Copy, Clone, PartialEq)]
+pub struct Region {
+    pub base: u32,
+    pub size: u32,
+}
+
+impl Region {
+    pub const fn end(&self) -> u32 {
+        self.base + self.size
+    }
+
+    pub const fn overlaps(&self, other: &Region) -> bool {
+        self.size != 0 && other.size != 0 && self.base < other.end() && other.base < self.end()
+    }
+}
+
+/// A region holding a `W` x `H` image of `P` pixels
+#[derive(Copy, Clone, PartialEq)]
+pub struct FramebufferRegion<const W: u32, const H: u32, P: Pixel> {
+    pub base: u32,
+    pixel: PhantomData<P>,
+}
+
+impl<const W: u32, const H: u32, P: Pixel> FramebufferRegion<W, H, P> {
+    pub const WIDTH: u32 = W;
+    pub const HEIGHT: u32 = H;
+    pub const PIXELS: u32 = W * H;
+    // Bytes from one line to the next
+    pub const STRIDE: u32 = W * P::BYTES;
+    pub const SIZE: u32 = Self::STRIDE * H;
+
+    pub const fn size(&self) -> u32 {
+        Self::SIZE
+    }
+
+    pub const fn region(&self) -> Region {
+        Region {
+            base: self.base,
+            size: Self::SIZE,
+        }
+    }
+}
+
+pub struct Arena {
+    next: u32,
+    end: u32,
+}
+
+impl Arena {
+    pub const fn new(region: Region) -> Self {
+        Self {
+            next: region.base,
+            end: region.end(),
+        }
+    }
+
+    // `align` must be a power of two
+    pub const fn alloc(&mut self, size: u32, align: u32) -> Region {
+        let base = (self.next + align - 1) & !(align - 1);
+        assert!(base + size <= self.end, "SDRAM arena exhausted");
+        self.next = base + size;
+        Region { base, size }
+    }
+
+    // Room for a framebuffer of the type asked for
+    pub const fn alloc_framebuffer<const W: u32, const H: u32, P: Pixel>(
+        &mut self,
+    ) -> FramebufferRegion<W, H, P> {
+        let region = self.alloc(FramebufferRegion::<W, H, P>::SIZE, FRAMEBUFFER_ALIGN);
+        FramebufferRegion {
+            base: region.base,
+            pixel: PhantomData,
+        }
+    }
+
+    // Everything not handed out yet
+    pub const fn remaining(&self) -> Region {
+        Region {
+            base: self.next,
+            size: self.end - self.next,
+        }
+    }
+}
+
+// Names of the first two overlapping regions, if any
+pub fn check_disjoint(
+    regions: &[(&'static str, Region)],
+) -> Result<(), (&'static str, &'static str)> {
+    for (i, (name_a, a)) in regions.iter().enumerate() {
+        for (name_b, b) in &regions[i + 1..] {
+            if a.overlaps(b) {
+                return Err((name_a, name_b));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! USART1 byte link to the host (ST-LINK virtual COM port)
+//!
+//! Receive and transmit both go through ring buffers serviced by the USART1
+//! interrupt, so neither the game loop nor the sender waits on the line
+//! unless the transmit buffer is full. The telemetry protocol and the
+//! console shell share the link; `shell::poll` routes received bytes.
+//! With the `usb-serial` feature the USB virtual COM port (`usb`) carries
+//! the same traffic: what is written here goes out on both, and either
+//! one's input is read.
+//!
+//! Pins: PA9 = TX, PA10 = RX, both AF7, 115200 8N1.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::error::HwError;
+use crate::resources;
+use crate::subsystem::{Health, Subsystem};
+use crate::usb;
+
+const BAUD: u32 = 115_200;
+// APB2 runs at SYSCLK / 2, see clock::setup_system_clocks_168mhz
+const APB2_HZ: u32 = 84_000_000;
+
+const RX_SIZE: usize = 256;
+const TX_SIZE: usize = 512;
+
+/// Single-producer, single-consumer byte queue shared with the interrupt
+///
+/// One side only moves `head`, the other only `tail`, so no lock is needed
+/// as long as each index is written with a single store.
+pub(crate) struct Ring<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    tail: usize,
+}
+
+impl<const N: usize> Ring<N> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            head: 0,
+            tail: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, byte: u8) -> bool {
+        let next = (self.head + 1) % N;
+        if next == unsafe { core::ptr::read_volatile(&self.tail) } {
+            return false;
+        }
+        self.buf[self.head] = byte;
+        unsafe { core::ptr::write_volatile(&mut self.head, next) };
+        true
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<u8> {
+        if self.tail == unsafe { core::ptr::read_volatile(&self.head) } {
+            return None;
+        }
+        let byte = self.buf[self.tail];
+        unsafe { core::ptr::write_volatile(&mut self.tail, (self.tail + 1) % N) };
+        Some(byte)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        unsafe { core::ptr::read_volatile(&self.head) == core::ptr::read_volatile(&self.tail) }
+    }
+}
+
+static mut RX: Ring<RX_SIZE> = Ring::new();
+static mut TX: Ring<TX_SIZE> = Ring::new();
+// Bytes lost to a full receive buffer or a hardware overrun
+static RX_DROPPED: AtomicU32 = AtomicU32::new(0);
+
+// USART1 was switched off by suspend and should come back on resume
+static LINK_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Ring<RX_SIZE>>()
+    + core::mem::size_of::<Ring<TX_SIZE>>()
+    + core::mem::size_of::<AtomicU32>()
+    + core::mem::size_of::<AtomicBool>();
+
+pub fn init() {
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+    rcc.ahb1enr.modify(|_, w| w.gpioaen().enabled());
+    rcc.apb2enr.modify(|_, w| w.usart1en().enabled());
+
+    // PA9/PA10 to AF7
+    let gpioa = &dp.GPIOA;
+    for pin in [9u32, 10] {
+        gpioa.moder.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+        });
+        let idx = pin - 8;
+        gpioa
+            .afrh
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0xF << (idx * 4))) | (7 << (idx * 4))) });
+    }
+
+    // 16x oversampling: BRR = f_ck / baud with mantissa/fraction packing
+    let usart = &dp.USART1;
+    usart
+        .brr
+        .write(|w| unsafe { w.bits((APB2_HZ + BAUD / 2) / BAUD) });
+    usart.cr1.write(|w| {
+        w.ue()
+            .enabled()
+            .te()
+            .enabled()
+            .re()
+            .enabled()
+            .rxneie()
+            .enabled()
+    });
+
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::USART1) };
+}
+
+pub fn is_enabled() -> bool {
+    let dp = resources::pac();
+    dp.USART1.cr1.read().ue().is_enabled()
+}
+
+// Next received byte, if any, from USART1 and then USB
+pub fn read() -> Option<u8> {
+    unsafe { RX.pop() }.or_else(usb::read_byte)
+}
+
+pub fn rx_dropped() -> u32 {
+    RX_DROPPED.load(Ordering::Relaxed)
+}
+
+// Queue bytes for transmission, waiting for room when the buffer is full.
+// Does nothing while the link is down, so callers need not check. A copy
+// goes to the USB port, which never waits.
+pub fn write(bytes: &[u8]) {
+    usb::write_bytes(bytes);
+    if !is_enabled() {
+        return;
+    }
+    // The interrupt turns TXEIE off when the queue runs dry, so turning it
+    // on is a read-modify-write it must not land in the middle of
+    let drain = || resources::critical(|dp| dp.USART1.cr1.modify(|_, w| w.txeie().enabled()));
+    for &byte in bytes {
+        while !unsafe { TX.push(byte) } {
+            // Full: make sure the interrupt is draining it
+            drain();
+        }
+    }
+    drain();
+}
+
+// Wait until everything queued has left the shift register
+pub fn flush() {
+    if !is_enabled() {
+        return;
+    }
+    let dp = resources::pac();
+    while !unsafe { TX.is_empty() } {}
+    while dp.USART1.sr.read().tc().bit_is_clear() {}
+}
+
+/// `core::fmt::Write` adapter, for `write!` straight onto the link
+pub struct Writer;
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[interrupt]
+fn USART1() {
+    let dp = resources::pac();
+    let usart = &dp.USART1;
+    let sr = usart.sr.read();
+
+    if sr.rxne().bit_is_set() || sr.ore().bit_is_set() {
+        // Reading DR also clears an overrun
+        if sr.ore().bit_is_set() {
+            RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+        let byte = usart.dr.read().dr().bits() as u8;
+        if !unsafe { RX.push(byte) } {
+            RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    if sr.txe().bit_is_set() && usart.cr1.read().txeie().is_enabled() {
+        match unsafe { TX.pop() } {
+            Some(byte) => usart.dr.write(|w| w.dr().bits(byte as u16)),
+            None => usart.cr1.modify(|_, w| w.txeie().disabled()),
+        }
+    }
+}
+
+pub struct SerialSubsystem;
+
+impl Subsystem for SerialSubsystem {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        // Only brought up when something uses the host link
+        if cfg!(any(
+            not(feature = "production"),
+            all(feature = "agent-api", debug_assertions),
+            feature = "sprite-reload"
+        )) {
+            init();
+        }
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        if !is_enabled() {
+            Health::Disabled
+        } else if rx_dropped() > 0 {
+            Health::Degraded("rx overrun")
+        } else {
+            Health::Ok
+        }
+    }
+
+    fn suspend(&self) {
+        if is_enabled() {
+            // Let the last byte out first
+            flush();
+            resources::critical(|dp| dp.USART1.cr1.modify(|_, w| w.ue().disabled()));
+            LINK_SUSPENDED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn resume(&self) {
+        if LINK_SUSPENDED.load(Ordering::Relaxed) {
+            resources::critical(|dp| dp.USART1.cr1.modify(|_, w| w.ue().enabled()));
+            LINK_SUSPENDED.store(false, Ordering::Relaxed);
+        }
+    }
+}
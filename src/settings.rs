@@ -0,0 +1,164 @@
+//! User settings, kept in flash
+//!
+//! The options the player can tune live in one `Settings` value, stored as
+//! a small versioned record in the flash `store`, which sees to it that a
+//! save cut short by a reset leaves the one before. A save only programs a
+//! few words; a sector is erased (a second or two with the CPU stalled)
+//! only when one fills up, every few thousand saves. A missing or
+//! unknown-version record means defaults.
+//!
+//! Record payload, in words:
+//!   0     format version
+//!   1..   fields, one per word; how many depends on the version, so older
+//!         records still read after an upgrade
+//!
+//! The words and every version's migration are core_logic::settings, where
+//! they are tested on the host and on the target.
+//!
+//! Before the store the settings were appended as blocks of their own to
+//! what is now the store's sector B, MAGIC and version, the fields, then
+//! hw_crc32 of the words before it, the last intact one winning. Those are
+//! still read when the store has no settings yet.
+//!
+//! The profile playing (`profiles`) keeps a copy of its player's settings,
+//! saved with them each time, and picking a profile puts its copy back.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+pub use core_logic::settings::{from_payload, payload, Difficulty, FrameRate, Settings};
+
+use core_logic::settings::{field_count, HEADER_WORDS, MAGIC, MAX_BLOCK_WORDS};
+
+use crate::audio;
+use crate::backlight;
+use crate::crc;
+use crate::flash;
+use crate::ili9341;
+use crate::log;
+use crate::profiles;
+use crate::sdram::arena::Region;
+use crate::store;
+use crate::trigger;
+
+// Erased flash reads as all ones
+const ERASED: u32 = 0xFFFF_FFFF;
+
+static mut CURRENT: Settings = Settings::DEFAULT;
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Settings>();
+
+pub fn get() -> Settings {
+    unsafe { CURRENT }
+}
+
+// The sector the settings blocks of older firmware are in
+const LEGACY: Region = flash::STORE[1];
+
+fn read_word(offset: u32) -> u32 {
+    // SAFETY: the store is internal flash outside the program image and
+    // always readable; callers keep `offset` inside it
+    unsafe { core::ptr::read_volatile((LEGACY.base + offset) as *const u32) }
+}
+
+// The block at `offset` and its length in words. None when the words there
+// are not a block this firmware can read, in which case nothing after it
+// can be found either.
+fn read_block(offset: u32) -> Option<([u32; MAX_BLOCK_WORDS], usize)> {
+    if read_word(offset) != MAGIC {
+        return None;
+    }
+    let len = HEADER_WORDS + field_count(read_word(offset + 4))? + 1;
+    if offset + len as u32 * 4 > LEGACY.size {
+        return None;
+    }
+    let mut block = [0; MAX_BLOCK_WORDS];
+    for (i, word) in block[..len].iter_mut().enumerate() {
+        *word = read_word(offset + i as u32 * 4);
+    }
+    Some((block, len))
+}
+
+fn decode(block: &[u32]) -> Option<Settings> {
+    let (body, crc) = block.split_at(block.len() - 1);
+    if crc[0] != crc::hw_crc32(body) {
+        return None;
+    }
+    Settings::from_words(body[1], &body[HEADER_WORDS..])
+}
+
+// The newest intact block older firmware left. A block whose save was cut
+// short fails its CRC and is passed over.
+fn legacy() -> Option<Settings> {
+    let mut newest = None;
+    let mut offset = 0;
+    while offset < LEGACY.size {
+        if read_word(offset) == ERASED {
+            break;
+        }
+        let Some((block, len)) = read_block(offset) else {
+            break;
+        };
+        if let Some(settings) = decode(&block[..len]) {
+            newest = Some(settings);
+        }
+        offset += len as u32 * 4;
+    }
+    newest
+}
+
+// Read the stored settings, or defaults, and apply them. Call at boot once
+// the backlight and audio are up.
+pub fn load() {
+    let mut words = [0; store::MAX_PAYLOAD];
+    let stored = match store::read(store::SETTINGS, &mut words) {
+        Some(len) => from_payload(&words[..len]).unwrap_or_else(|| {
+            log::warn!("settings record unreadable, using defaults");
+            Settings::DEFAULT
+        }),
+        None => legacy().unwrap_or(Settings::DEFAULT),
+    };
+    unsafe { CURRENT = stored };
+    apply(&stored);
+}
+
+fn apply(settings: &Settings) {
+    backlight::set_brightness(settings.brightness);
+    audio::set_enabled(settings.sound);
+    audio::set_music_enabled(settings.music);
+    // Straight to the panel; this runs before the display module is up
+    ili9341::set_gamma(settings.gamma);
+    ili9341::set_inversion(settings.invert);
+    trigger::configure(settings.trigger);
+}
+
+// Store `settings` as the current ones, and as the profile playing's.
+// Nothing is applied; callers change what they set themselves.
+pub fn save(settings: &Settings) -> Result<(), ()> {
+    unsafe { CURRENT = *settings };
+    let mut words = [0; store::MAX_PAYLOAD];
+    let len = payload(settings, &mut words).ok_or(())?;
+    let saved = store::write(store::SETTINGS, &words[..len]);
+    profiles::save();
+    saved
+}
+
+// Put a profile's `settings` in force: stored as the current ones and
+// applied as at boot. What is read when it is used (the theme, the
+// tuning, the controls) follows with the next game.
+pub fn replace(settings: &Settings) {
+    apply(settings);
+    if save(settings).is_err() {
+        log::error!("settings save failed");
+    }
+}
+
+// Change the current settings and store them; a failed save is logged and
+// the change still holds until reset
+pub fn update(change: impl FnOnce(&mut Settings)) {
+    let mut settings = get();
+    change(&mut settings);
+    if save(&settings).is_err() {
+        log::error!("settings save failed");
+    }
+}
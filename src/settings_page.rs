@@ -0,0 +1,216 @@
+//! Settings: the page the pause menu's "Settings" opens
+//!
+//! What the pause menu has no room for, each line "Name: value" and picked
+//! to step the value on, applied and saved at once as the shell's commands
+//! do. Tilt calibration starts from here too, and "Back" returns to the
+//! pause menu. It is drawn with `ui` on the overlay, over the paused game.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use core_logic::tilt_map::TiltMap;
+
+use crate::audio;
+use crate::button::ButtonEvent;
+use crate::encoder::MenuNav;
+use crate::fmt_buf::FmtBuf;
+use crate::lang::{self, Msg};
+use crate::settings;
+use crate::ui::{self, Focus, Nav, Ui};
+
+const ITEM_FRAME_RATE: usize = 0;
+const ITEM_LANGUAGE: usize = 1;
+const ITEM_TILT_AXIS: usize = 2;
+const ITEM_TILT_REVERSED: usize = 3;
+const ITEM_DEAD_ZONE: usize = 4;
+const ITEM_TILT_CURVE: usize = 5;
+const ITEM_SOUND: usize = 6;
+const ITEM_MUSIC: usize = 7;
+const ITEM_GAP_PREVIEW: usize = 8;
+const ITEM_DIFFICULTY: usize = 9;
+const ITEM_CALIBRATE: usize = 10;
+const ITEM_BACK: usize = 11;
+const ITEMS: usize = 12;
+
+/// How the page was left
+#[derive(Copy, Clone, PartialEq)]
+pub enum Exit {
+    Back,
+    Calibrate,
+}
+
+pub struct SettingsPage {
+    focus: Focus,
+}
+
+impl SettingsPage {
+    pub fn new() -> Self {
+        let mut page = SettingsPage {
+            focus: Focus::new(),
+        };
+        page.draw(None);
+        page
+    }
+
+    // One frame of the page; how it was left, once it is
+    pub fn update(&mut self, button: Option<ButtonEvent>, knob: Option<MenuNav>) -> Option<Exit> {
+        let nav = match knob {
+            Some(MenuNav::Next) => Some(Nav::Next),
+            Some(MenuNav::Prev) => Some(Nav::Prev),
+            Some(MenuNav::Press) => Some(Nav::Activate),
+            None => Nav::from_button(button),
+        }?;
+        let item = self.draw(Some(nav))?;
+        self.select(item)
+    }
+
+    fn select(&mut self, item: usize) -> Option<Exit> {
+        match item {
+            ITEM_FRAME_RATE => {
+                let rate = settings::get().frame_rate.next();
+                settings::update(|settings| settings.frame_rate = rate);
+            }
+            // The page redraws in the new language
+            ITEM_LANGUAGE => {
+                let language = settings::get().language.next();
+                settings::update(|settings| settings.language = language);
+            }
+            // How the board is mounted, read on the next tilt sample
+            ITEM_TILT_AXIS | ITEM_TILT_REVERSED | ITEM_DEAD_ZONE | ITEM_TILT_CURVE => {
+                let mut map = settings::get().tilt_map;
+                match item {
+                    ITEM_TILT_AXIS => map.axis = map.axis.next(),
+                    ITEM_TILT_REVERSED => map.invert = !map.invert,
+                    ITEM_DEAD_ZONE => map.dead_zone = TiltMap::next_dead_zone(map.dead_zone),
+                    _ => map.curve = map.curve.next(),
+                }
+                settings::update(|settings| settings.tilt_map = map);
+            }
+            // Off silences the effects and the song both
+            ITEM_SOUND => {
+                let on = !settings::get().sound;
+                audio::set_enabled(on);
+                settings::update(|settings| settings.sound = on);
+            }
+            // Off stops the song; on waits for the next one to start
+            ITEM_MUSIC => {
+                let on = !settings::get().music;
+                audio::set_music_enabled(on);
+                settings::update(|settings| settings.music = on);
+            }
+            ITEM_GAP_PREVIEW => {
+                let on = !settings::get().gap_preview;
+                settings::update(|settings| settings.gap_preview = on);
+            }
+            // The obstacles' speed, from the next game
+            ITEM_DIFFICULTY => {
+                let difficulty = settings::get().difficulty.next();
+                settings::update(|settings| settings.difficulty = difficulty);
+            }
+            ITEM_CALIBRATE => return Some(Exit::Calibrate),
+            ITEM_BACK => return Some(Exit::Back),
+            _ => {}
+        }
+        self.draw(None);
+        None
+    }
+
+    // Draw the page, moved on by `nav`; the item `nav` activated
+    fn draw(&mut self, nav: Option<Nav>) -> Option<usize> {
+        let current = settings::get();
+        let mut frame_rate: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            frame_rate,
+            "{}: {}",
+            lang::text(Msg::FrameRate),
+            current.frame_rate.as_str()
+        );
+        let mut language: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            language,
+            "{}: {}",
+            lang::text(Msg::Language),
+            current.language.as_str()
+        );
+        let map = current.tilt_map;
+        let mut axis: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            axis,
+            "{}: {}",
+            lang::text(Msg::TiltAxis),
+            lang::text(map.axis.msg())
+        );
+        let mut reversed: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            reversed,
+            "{}: {}",
+            lang::text(Msg::TiltReversed),
+            lang::text(if map.invert { Msg::On } else { Msg::Off })
+        );
+        let mut dead_zone: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            dead_zone,
+            "{}: {}",
+            lang::text(Msg::DeadZone),
+            map.dead_zone
+        );
+        let mut curve: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            curve,
+            "{}: {}",
+            lang::text(Msg::TiltCurve),
+            lang::text(map.curve.msg())
+        );
+        let mut sound: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            sound,
+            "{}: {}",
+            lang::text(Msg::Sound),
+            lang::text(if current.sound { Msg::On } else { Msg::Off })
+        );
+        let mut music: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            music,
+            "{}: {}",
+            lang::text(Msg::Music),
+            lang::text(if current.music { Msg::On } else { Msg::Off })
+        );
+        let mut preview: FmtBuf<24> = FmtBuf::new();
+        let _ = write!(
+            preview,
+            "{}: {}",
+            lang::text(Msg::GapPreview),
+            lang::text(if current.gap_preview {
+                Msg::On
+            } else {
+                Msg::Off
+            })
+        );
+        let mut difficulty: FmtBuf<32> = FmtBuf::new();
+        let _ = write!(
+            difficulty,
+            "{}: {}",
+            lang::text(Msg::Difficulty),
+            lang::text(current.difficulty.msg())
+        );
+
+        // No heading: with one, the items would run off the screen
+        let mut ui = Ui::begin(&mut self.focus, nav, ui::centered_top(ITEMS));
+        let chosen = ui.list(&[
+            frame_rate.as_str(),
+            language.as_str(),
+            axis.as_str(),
+            reversed.as_str(),
+            dead_zone.as_str(),
+            curve.as_str(),
+            sound.as_str(),
+            music.as_str(),
+            preview.as_str(),
+            difficulty.as_str(),
+            lang::text(Msg::CalibrateTilt),
+            lang::text(Msg::Back),
+        ]);
+        ui.end();
+        chosen
+    }
+}
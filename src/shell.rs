@@ -0,0 +1,1238 @@
+//! Line-based command shell on the serial console
+//!
+//! Type a command and press enter in any terminal on the ST-LINK virtual COM
+//! port (115200 8N1). `help` lists the commands. Input is echoed, and
+//! backspace works. Bytes that belong to telemetry frames are passed to the
+//! telemetry parser instead, and lines that are `score_link` host commands
+//! go there. Production builds leave the shell out but still take the host
+//! commands, without echo.
+//!
+//! `shot` output is described in the screenshot module.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+
+use core_logic::config::Coord;
+use core_logic::profile as profile_format;
+use core_logic::replay as replay_format;
+use core_logic::screensaver;
+use core_logic::scroll;
+use core_logic::test_pattern::Pattern;
+use core_logic::tilt_map::{Axis, Curve, MAX_DEAD_ZONE};
+use core_logic::tuning::{Param, TuningParams};
+
+use crate::ab_check;
+use crate::adc;
+use crate::artwork;
+use crate::audio;
+use crate::backdrop;
+use crate::bench;
+use crate::blackbox;
+use crate::budget;
+use crate::clock::{self, Mco};
+use crate::display::{self, Backend};
+use crate::encoder;
+use crate::frame_record;
+use crate::game::{Game, GameState, InputDevice};
+use crate::i2c::{self, Bus};
+use crate::ili9341::{self, GammaProfile, GammaTables, GAMMA_LEN};
+use crate::lane::Margins;
+use crate::lang::Language;
+use crate::lcd::{self, BlendMode, ClockEdge, LcdDriver, Polarity};
+use crate::ltdc_check;
+use crate::memory;
+use crate::mpu6050;
+use crate::player;
+use crate::profiler;
+use crate::profiles;
+use crate::replay;
+use crate::rtc::{self, DateTime};
+use crate::score_link;
+use crate::screenshot::{self, Format};
+use crate::sdram::{self, SPOT_CHECK_BASE, SPOT_CHECK_SIZE};
+use crate::serial::{self, Writer};
+use crate::settings::{self, Difficulty, FrameRate};
+use crate::sprite_cache;
+use crate::stats::{self, Board};
+use crate::system;
+use crate::telemetry;
+use crate::test_pattern;
+use crate::trigger::{self, TriggerConfig};
+use crate::wifi;
+
+const ENABLED: bool = !cfg!(feature = "production");
+
+const LINE_MAX: usize = 64;
+const PROMPT: &str = "> ";
+
+// Fastest obstacle speed the shell accepts, in pixels per frame; speeds
+// can be set to hundredths of a pixel (2.15)
+const MAX_SPEED: u32 = 8;
+// Fastest panel refresh the shell sets the pixel clock for
+const MAX_REFRESH_HZ: u32 = 200;
+
+static mut LINE: [u8; LINE_MAX] = [0; LINE_MAX];
+static mut LINE_LEN: usize = 0;
+
+// Drain received bytes; the serial task calls this every few milliseconds
+pub fn poll<T: InputDevice>(game: &mut Game<T>) {
+    while let Some(byte) = serial::read() {
+        if telemetry::feed(byte) {
+            continue;
+        }
+        let line = unsafe { &mut LINE };
+        let len = unsafe { &mut LINE_LEN };
+        match byte {
+            b'\r' | b'\n' => {
+                echo(b"\r\n");
+                if let Ok(text) = core::str::from_utf8(&line[..*len]) {
+                    let text = text.trim();
+                    if !score_link::command(text) && ENABLED {
+                        run(game, text);
+                    }
+                }
+                *len = 0;
+                echo(PROMPT.as_bytes());
+            }
+            // Backspace / DEL
+            0x08 | 0x7F if *len > 0 => {
+                *len -= 1;
+                echo(b"\x08 \x08");
+            }
+            0x20..=0x7E if *len < LINE_MAX => {
+                line[*len] = byte;
+                *len += 1;
+                echo(&[byte]);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Terminal feedback, which production builds leave out
+fn echo(bytes: &[u8]) {
+    if ENABLED {
+        serial::write(bytes);
+    }
+}
+
+fn run<T: InputDevice>(game: &mut Game<T>, line: &str) {
+    let mut out = Writer;
+    let mut args = line.split_ascii_whitespace();
+    let Some(command) = args.next() else {
+        return;
+    };
+    match command {
+        "help" => {
+            let _ = write!(
+                out,
+                "score              score and game state\r\n\
+                 speed [1-{}]        show or set obstacle speed (2.1)\r\n\
+                 mpu <reg> [count]  read MPU6050 registers (hex)\r\n\
+                 i2c [1|3]          scan the I2C buses for devices\r\n\
+                 ltdc [sig value]   show or set LTDC polarity/blend (ltdc hs high)\r\n\
+                 prof [on|off]      frame time split, or stream it\r\n\
+                 mem                stack, static RAM, SDRAM, and flash and RAM by module\r\n\
+                 panel              panel in use; ILI9341 ID and status over SPI\r\n\
+                 gamma [name]       show or pick the panel gamma curve\r\n\
+                 gamma <pos> <neg>  upload 15-byte gamma tables (hex)\r\n\
+                 invert [on|off]    show or set inverted panel colors\r\n\
+                 sound [on|off]     all sound, effects and music\r\n\
+                 music [on|off]     background music during a run\r\n\
+                 difficulty [easy|normal|hard] obstacle speed, from the next game\r\n\
+                 preview [on|off]   show the next gap before its pipe\r\n\
+                 render [ltdc|spi]  show or switch where the game draws\r\n\
+                 bandwidth [on|off] 16bpp Layer 1, no Layer 2, slower pclk\r\n\
+                 sdram              SDRAM self-test on the spot-check block\r\n\
+                 bench [name]       drawing benchmarks, or one (bench blit-x)\r\n\
+                 abcheck            draw Layer 1 by DMA2D and by CPU, count differences\r\n\
+                 pattern [name|next|off] panel test patterns in place of the game\r\n\
+                 shot [raw|ppm]     dump Layer 1\r\n\
+                 rec [on [seed]|off] stream frame checksums and input\r\n\
+                 replay [send]      the last run's replay, or send it to the host\r\n\
+                 hitbox [on|off]    outline the bird's hitbox from the next game\r\n\
+                 backdrop [on|off]  far towers behind the obstacles from the next game\r\n\
+                 cache              sprite cache slots, hits and misses\r\n\
+                 fps [60|30]        show or cap the frame rate (30 saves power)\r\n\
+                 stick              raw thumb-stick readings (PA7 x, PC3 y)\r\n\
+                 knob               rotary encoder count and switch\r\n\
+                 trigger [high|low] [ms] show or set the PE2 trigger level, debounce\r\n\
+                 lang [en|de|es]    show or pick the language of the screens\r\n\
+                 saver [s|off]      seconds before an idle menu dims, then blanks\r\n\
+                 margins [top bottom] score bar and ground heights, from the next game\r\n\
+                 tune [name value]  show, set or reset gravity, flap, gap, speed, inset\r\n\
+                 tilt [name value]  show or set the tilt axis, invert, deadzone, curve\r\n\
+                 clocks             bus and pixel clocks read back from the RCC\r\n\
+                 mco [hse|pll|off]  clock out on PA8 for a scope (stops touch)\r\n\
+                 pclk [hz]          show or set the LTDC pixel clock\r\n\
+                 refresh [hz]       show or set the pixel clock by frame rate\r\n\
+                 reset-best         clear the stored high score\r\n\
+                 top [time] [clear] show or empty the leaderboard, or the time attack one\r\n\
+                 profile [1-3|guest] [clear] list, play as or forget player profiles\r\n\
+                 wifi               score upload link, access point and server\r\n\
+                 time [date time]   show or set the RTC (2026-01-31 18:05:00)\r\n\
+                 shot save          keep Layer 1 in flash\r\n\
+                 shot load [raw|ppm] dump the shot kept in flash\r\n\
+                 art [x y]          draw the flashed artwork, centered or at x y\r\n\
+                 blackbox [clear]   events and errors kept through resets\r\n\
+                 restart            start the game over, hardware left as it is\r\n\
+                 reset              quiet the hardware and reset the board\r\n",
+                MAX_SPEED
+            );
+        }
+        "score" => {
+            let _ = write!(
+                out,
+                "score {} state {}\r\n",
+                game.score(),
+                game.state().name()
+            );
+        }
+        "speed" => match args.next().map(scroll::parse) {
+            None => speed(&mut out, game.obstacle_velocity()),
+            Some(Some(velocity))
+                if (scroll::pixels(1)..=scroll::pixels(MAX_SPEED)).contains(&velocity) =>
+            {
+                game.set_obstacle_velocity(velocity);
+                speed(&mut out, velocity);
+            }
+            Some(_) => {
+                let _ = write!(out, "speed must be 1-{}\r\n", MAX_SPEED);
+            }
+        },
+        "mpu" => mpu(&mut out, args.next(), args.next()),
+        "i2c" => match args.next() {
+            None => {
+                scan(&mut out, Bus::I2c1);
+                scan(&mut out, Bus::I2c3);
+            }
+            Some("1") => scan(&mut out, Bus::I2c1),
+            Some("3") => scan(&mut out, Bus::I2c3),
+            Some(_) => {
+                let _ = write!(out, "usage: i2c [1|3]\r\n");
+            }
+        },
+        "ltdc" => ltdc(&mut out, args.next(), args.next()),
+        "panel" => {
+            let panel = lcd::panel();
+            let (x, y) = panel.origin();
+            let _ = write!(
+                out,
+                "{} {}x{} game at {},{}\r\n",
+                panel.name, panel.width, panel.height, x, y
+            );
+            if panel.ili9341 {
+                let _ = write!(
+                    out,
+                    "id {:06x} status {:08x}\r\n",
+                    ili9341::read_display_id(),
+                    ili9341::read_display_status()
+                );
+            }
+        }
+        "gamma" => gamma(&mut out, args.next(), args.next()),
+        "reset" => system::soft_reset(),
+        "restart" => {
+            system::request_warm_restart();
+            let _ = write!(out, "restarting the game\r\n");
+        }
+        "sound" => {
+            let on = match args.next() {
+                None => settings::get().sound,
+                Some(arg @ ("on" | "off")) => {
+                    let on = arg == "on";
+                    audio::set_enabled(on);
+                    settings::update(|settings| settings.sound = on);
+                    on
+                }
+                Some(_) => {
+                    let _ = write!(out, "usage: sound [on|off]\r\n");
+                    return;
+                }
+            };
+            let _ = write!(out, "sound {}\r\n", if on { "on" } else { "off" });
+        }
+        "difficulty" => match args.next() {
+            None => {
+                let difficulty = settings::get().difficulty;
+                let _ = write!(out, "difficulty {}\r\n", difficulty.as_str());
+            }
+            Some(name) => match Difficulty::parse(name) {
+                Some(difficulty) => {
+                    settings::update(|settings| settings.difficulty = difficulty);
+                    let _ = write!(out, "difficulty {}\r\n", difficulty.as_str());
+                }
+                None => {
+                    let _ = write!(out, "difficulty easy|normal|hard\r\n");
+                }
+            },
+        },
+        "music" => {
+            let on = match args.next() {
+                None => settings::get().music,
+                Some(arg @ ("on" | "off")) => {
+                    let on = arg == "on";
+                    audio::set_music_enabled(on);
+                    settings::update(|settings| settings.music = on);
+                    on
+                }
+                Some(_) => {
+                    let _ = write!(out, "usage: music [on|off]\r\n");
+                    return;
+                }
+            };
+            let _ = write!(out, "music {}\r\n", if on { "on" } else { "off" });
+        }
+        "preview" => {
+            let on = match args.next() {
+                None => settings::get().gap_preview,
+                Some(arg @ ("on" | "off")) => {
+                    let on = arg == "on";
+                    settings::update(|settings| settings.gap_preview = on);
+                    on
+                }
+                Some(_) => {
+                    let _ = write!(out, "usage: preview [on|off]\r\n");
+                    return;
+                }
+            };
+            let _ = write!(out, "gap preview {}\r\n", if on { "on" } else { "off" });
+        }
+        "invert" => {
+            let invert = match args.next() {
+                None => settings::get().invert,
+                Some(arg @ ("on" | "off")) => {
+                    let invert = arg == "on";
+                    let _ = display::invert_colors(invert);
+                    settings::update(|settings| settings.invert = invert);
+                    invert
+                }
+                Some(_) => {
+                    let _ = write!(out, "usage: invert [on|off]\r\n");
+                    return;
+                }
+            };
+            let _ = write!(out, "invert {}\r\n", if invert { "on" } else { "off" });
+        }
+        "render" => render(&mut out, game, args.next()),
+        "bandwidth" => bandwidth(&mut out, args.next()),
+        "sdram" if !sdram::available() => {
+            let _ = write!(out, "no sdram\r\n");
+        }
+        "sdram" => {
+            // The spot-check block is the only SDRAM nothing else lives in,
+            // so the destructive test can run while the game is up
+            match sdram::self_test(SPOT_CHECK_BASE, SPOT_CHECK_SIZE) {
+                Ok(()) => {
+                    let _ = write!(out, "sdram ok\r\n");
+                }
+                Err(fault) => {
+                    let _ = write!(
+                        out,
+                        "sdram FAIL @{:08x} expected {:08x} got {:08x}\r\n",
+                        fault.addr, fault.expected, fault.actual
+                    );
+                }
+            }
+            sdram::arm_spot_check();
+        }
+        "bench" => bench(&mut out, args.next()),
+        "abcheck" if !ab_check::available() => {
+            let _ = write!(out, "no sdram\r\n");
+        }
+        "abcheck" => {
+            for case in &ab_check::CASES {
+                let _ = write!(out, "{}\r\n", ab_check::run(case));
+            }
+        }
+        "mem" => {
+            let _ = memory::write_report(&mut out);
+            let _ = budget::write_report(&mut out);
+        }
+        "prof" => match args.next() {
+            None => profiler::write_breakdown(&mut out),
+            Some("on") => profiler::set_streaming(true),
+            Some("off") => profiler::set_streaming(false),
+            Some(_) => {
+                let _ = write!(out, "usage: prof [on|off]\r\n");
+            }
+        },
+        "time" => match (args.next(), args.next()) {
+            (None, _) => {
+                let _ = write!(out, "{}\r\n", rtc::now());
+            }
+            (Some(date), Some(time)) => match DateTime::parse(date, time) {
+                Some(dt) => {
+                    rtc::set(&dt);
+                    let _ = write!(out, "{}\r\n", dt);
+                }
+                None => {
+                    let _ = write!(out, "usage: time YYYY-MM-DD HH:MM[:SS]\r\n");
+                }
+            },
+            (Some(_), None) => {
+                let _ = write!(out, "usage: time YYYY-MM-DD HH:MM[:SS]\r\n");
+            }
+        },
+        "shot" => shot(&mut out, args.next(), args.next()),
+        "art" => art(&mut out, args.next(), args.next()),
+        "rec" => rec(&mut out, args.next(), args.next()),
+        "replay" => replay(&mut out, args.next()),
+        "blackbox" => match args.next() {
+            None => {
+                let _ = blackbox::write(&mut out);
+            }
+            Some("clear") => blackbox::clear(),
+            Some(_) => {
+                let _ = write!(out, "usage: blackbox [clear]\r\n");
+            }
+        },
+        "hitbox" => {
+            match args.next() {
+                None => {}
+                Some("on") => player::show_hitboxes(true),
+                Some("off") => player::show_hitboxes(false),
+                Some(_) => {
+                    let _ = write!(out, "usage: hitbox [on|off]\r\n");
+                    return;
+                }
+            }
+            let shown = if player::hitboxes_shown() {
+                "on"
+            } else {
+                "off"
+            };
+            let _ = write!(out, "hitbox {}\r\n", shown);
+        }
+        "backdrop" => {
+            match args.next() {
+                None => {}
+                Some("on") => backdrop::set_shown(true),
+                Some("off") => backdrop::set_shown(false),
+                Some(_) => {
+                    let _ = write!(out, "usage: backdrop [on|off]\r\n");
+                    return;
+                }
+            }
+            let shown = if backdrop::shown() { "on" } else { "off" };
+            let _ = write!(out, "backdrop {}\r\n", shown);
+        }
+        "fps" => {
+            match args.next().map(|arg| arg.parse::<u32>().ok()) {
+                None => {}
+                Some(Some(60)) => settings::update(|s| s.frame_rate = FrameRate::Full),
+                Some(Some(30)) => settings::update(|s| s.frame_rate = FrameRate::Saver),
+                Some(_) => {
+                    let _ = write!(out, "usage: fps [60|30]\r\n");
+                    return;
+                }
+            }
+            let _ = write!(out, "fps {}\r\n", settings::get().frame_rate.as_str());
+        }
+        "cache" => {
+            let (slots, hits, misses) = sprite_cache::stats();
+            let _ = write!(
+                out,
+                "sprite cache {} slots, {} hits, {} misses\r\n",
+                slots, hits, misses
+            );
+        }
+        "stick" => {
+            let read = |channel| adc::read(channel).map_or(-1, |raw| raw as i32);
+            let _ = write!(
+                out,
+                "stick x {} y {} (-1: no conversion)\r\n",
+                read(adc::STICK_X_CHANNEL),
+                read(adc::STICK_Y_CHANNEL)
+            );
+        }
+        "knob" => {
+            let _ = write!(
+                out,
+                "encoder count {} switch {}\r\n",
+                encoder::raw_count(),
+                if encoder::switch_down() { "down" } else { "up" }
+            );
+        }
+        "trigger" => trigger_cmd(&mut out, args.next(), args.next()),
+        "saver" => saver(&mut out, args.next()),
+        "margins" => margins(&mut out, game, args.next(), args.next()),
+        "pattern" => pattern(&mut out, game, args.next()),
+        "tune" => tune(&mut out, game, args.next(), args.next()),
+        "tilt" => tilt(&mut out, args.next(), args.next()),
+        "top" => top(&mut out, args.next(), args.next()),
+        "profile" => profile(&mut out, args.next(), args.next()),
+        "wifi" => wifi_status(&mut out),
+        "clocks" => {
+            let f = clock::report();
+            let _ = write!(
+                out,
+                "sysclk {} ahb {} apb1 {} apb2 {} ltdc {} (want {}) Hz\r\n",
+                f.sysclk,
+                f.hclk,
+                f.pclk1,
+                f.pclk2,
+                f.ltdc,
+                clock::pixel_clock_hz()
+            );
+        }
+        "mco" => mco(&mut out, args.next()),
+        "pclk" => match args.next().map(str::parse::<u32>) {
+            None => pclk(&mut out),
+            Some(Ok(hz)) if hz > 0 => {
+                clock::set_pixel_clock(hz);
+                pclk(&mut out);
+            }
+            Some(_) => {
+                let _ = write!(out, "usage: pclk <hz>\r\n");
+            }
+        },
+        "refresh" => match args.next().map(str::parse::<u32>) {
+            None => pclk(&mut out),
+            Some(Ok(hz)) if (1..=MAX_REFRESH_HZ).contains(&hz) => {
+                clock::set_pixel_clock(ltdc_check::profile().pclk_hz_at(hz));
+                pclk(&mut out);
+            }
+            Some(_) => {
+                let _ = write!(out, "refresh 1-{}\r\n", MAX_REFRESH_HZ);
+            }
+        },
+        "lang" => match args.next() {
+            None => {
+                let _ = write!(out, "lang {}\r\n", settings::get().language.as_str());
+            }
+            Some(code) => match Language::parse(code) {
+                Some(language) => {
+                    settings::update(|settings| settings.language = language);
+                    let _ = write!(out, "lang {}\r\n", language.as_str());
+                }
+                None => {
+                    let _ = write!(out, "lang en|de|es\r\n");
+                }
+            },
+        },
+        _ => {
+            let _ = write!(out, "unknown command '{}', try help\r\n", command);
+        }
+    }
+}
+
+// Menu screensaver delay in seconds; off is 0
+// Pixel clock as set up and the frame rate it gives the panel
+fn pclk(out: &mut Writer) {
+    let hz = clock::pixel_clock_hz();
+    let _ = write!(
+        out,
+        "pclk {} Hz, {} Hz refresh\r\n",
+        hz,
+        ltdc_check::profile().refresh_hz_at(hz)
+    );
+}
+
+// Clock out on MCO1 (PA8), or give the pin back to the touch controller
+fn mco(out: &mut Writer, arg: Option<&str>) {
+    match arg.map(|name| (name, Mco::parse(name))) {
+        Some(("off", _)) => {
+            clock::set_mco(None);
+            let _ = write!(out, "mco off, touch back on PA8\r\n");
+        }
+        Some((_, Some(source))) => {
+            clock::set_mco(Some(source));
+            let _ = write!(out, "mco {} Hz on PA8\r\n", source.hz());
+        }
+        _ => {
+            let _ = write!(out, "usage: mco hse|pll|off\r\n");
+        }
+    }
+}
+
+// Room kept clear at the top and bottom of the game for bezels that cover
+// the panel's edges
+fn margins<T: InputDevice>(
+    out: &mut Writer,
+    game: &mut Game<T>,
+    top: Option<&str>,
+    bottom: Option<&str>,
+) {
+    let margins = match (top, bottom) {
+        (None, _) => settings::get().margins,
+        (Some(top), Some(bottom)) => {
+            let parsed = top.parse().ok().zip(bottom.parse().ok());
+            match parsed.and_then(|(top, bottom)| Margins::new(top, bottom)) {
+                Some(margins) => {
+                    settings::update(|s| s.margins = margins);
+                    if game.state() == GameState::Ready {
+                        Game::<T>::draw_start_screen();
+                    }
+                    margins
+                }
+                None => {
+                    let (top, bottom) = (Margins::TOP_RANGE, Margins::BOTTOM_RANGE);
+                    let _ = write!(
+                        out,
+                        "margins top {}-{} bottom {}-{}\r\n",
+                        top.0, top.1, bottom.0, bottom.1
+                    );
+                    return;
+                }
+            }
+        }
+        (Some(_), None) => {
+            let _ = write!(out, "margins top bottom\r\n");
+            return;
+        }
+    };
+    let _ = write!(
+        out,
+        "margins top {} bottom {}\r\n",
+        margins.top, margins.bottom
+    );
+}
+
+// Panel test patterns: the one up, or put one up or take it down
+fn pattern<T: InputDevice>(out: &mut Writer, game: &mut Game<T>, arg: Option<&str>) {
+    let pattern = match arg {
+        None => {
+            let _ = match test_pattern::shown() {
+                Some(pattern) => write!(out, "pattern {}\r\n", pattern.name()),
+                None => write!(out, "pattern off\r\n"),
+            };
+            return;
+        }
+        Some("off") => {
+            if test_pattern::shown().is_some() {
+                game.end_test_pattern();
+            }
+            let _ = write!(out, "pattern off\r\n");
+            return;
+        }
+        Some("next") => test_pattern::shown().map_or(Pattern::ALL[0], Pattern::next),
+        Some(name) => match Pattern::parse(name) {
+            Some(pattern) => pattern,
+            None => {
+                let _ = write!(out, "pattern");
+                for pattern in Pattern::ALL {
+                    let _ = write!(out, " {}", pattern.name());
+                }
+                let _ = write!(out, " next off\r\n");
+                return;
+            }
+        },
+    };
+    let _ = match test_pattern::show(pattern) {
+        Ok(()) => write!(out, "pattern {}\r\n", pattern.name()),
+        Err(()) => write!(out, "pattern needs ltdc (render ltdc)\r\n"),
+    };
+}
+
+fn saver(out: &mut Writer, arg: Option<&str>) {
+    let seconds = match arg {
+        None => settings::get().screensaver_s,
+        Some("off") => 0,
+        Some(text) => match text.parse::<u16>() {
+            Ok(s) if s <= screensaver::MAX_DELAY_S => s,
+            _ => {
+                let _ = write!(out, "saver 0-{}|off\r\n", screensaver::MAX_DELAY_S);
+                return;
+            }
+        },
+    };
+    if arg.is_some() {
+        settings::update(|settings| settings.screensaver_s = seconds);
+    }
+    match seconds {
+        0 => {
+            let _ = write!(out, "saver off\r\n");
+        }
+        s => {
+            let _ = write!(out, "saver {} s\r\n", s);
+        }
+    }
+}
+
+// Gameplay tuning: every value, or one set
+fn tune<T: InputDevice>(
+    out: &mut Writer,
+    game: &mut Game<T>,
+    name: Option<&str>,
+    value: Option<&str>,
+) {
+    let mut tuning = game.tuning();
+    match (name, value) {
+        (None, _) => {}
+        (Some("reset"), None) => tuning = TuningParams::DEFAULT,
+        (Some(name), Some(value)) => {
+            let Some(param) = Param::parse(name) else {
+                let _ = write!(
+                    out,
+                    "tune gravity|flap|gap|speed|inset <value>, or reset\r\n"
+                );
+                return;
+            };
+            let set = value
+                .parse()
+                .map_err(|_| ())
+                .and_then(|value| tuning.set(param, value).map_err(|_| ()));
+            if set.is_err() {
+                let (min, max) = param.range();
+                let _ = write!(out, "{} {}-{}\r\n", param.name(), min, max);
+                return;
+            }
+        }
+        (Some(_), None) => {
+            let _ = write!(
+                out,
+                "tune gravity|flap|gap|speed|inset <value>, or reset\r\n"
+            );
+            return;
+        }
+    }
+    if name.is_some() {
+        game.set_tuning(tuning);
+    }
+    for param in Param::ALL {
+        let _ = write!(
+            out,
+            "{:<8} {} {}\r\n",
+            param.name(),
+            tuning.get(param),
+            param.unit()
+        );
+    }
+}
+
+// Which accelerometer axis steers the bird and how its reading is shaped
+fn tilt(out: &mut Writer, name: Option<&str>, value: Option<&str>) {
+    let mut map = settings::get().tilt_map;
+    if let Some(name) = name {
+        let set = match (name, value) {
+            ("axis", Some(value)) => Axis::parse(value).map(|axis| map.axis = axis),
+            ("invert", Some(value @ ("on" | "off"))) => {
+                map.invert = value == "on";
+                Some(())
+            }
+            ("deadzone", Some(value)) => value
+                .parse()
+                .ok()
+                .filter(|&counts| counts <= MAX_DEAD_ZONE)
+                .map(|counts| map.dead_zone = counts),
+            ("curve", Some(value)) => Curve::parse(value).map(|curve| map.curve = curve),
+            _ => None,
+        };
+        if set.is_none() {
+            let _ = write!(
+                out,
+                "tilt axis x|y|z, invert on|off, deadzone 0-{}, curve linear|expo\r\n",
+                MAX_DEAD_ZONE
+            );
+            return;
+        }
+        settings::update(|settings| settings.tilt_map = map);
+    }
+    let _ = write!(
+        out,
+        "axis {}{}, deadzone {}, curve {}\r\n",
+        map.axis.name(),
+        if map.invert { " inverted" } else { "" },
+        map.dead_zone,
+        map.curve.name()
+    );
+}
+
+// External trigger: the level that is pressed, then the debounce
+fn trigger_cmd(out: &mut Writer, level: Option<&str>, ms: Option<&str>) {
+    let mut config = settings::get().trigger;
+    if let Some(level) = level {
+        config.active_high = match level {
+            "high" => true,
+            "low" => false,
+            _ => {
+                let _ = write!(out, "usage: trigger [high|low] [ms]\r\n");
+                return;
+            }
+        };
+        if let Some(ms) = ms {
+            match ms.parse::<u16>() {
+                Ok(ms) if ms <= TriggerConfig::MAX_DEBOUNCE_MS => config.debounce_ms = ms,
+                _ => {
+                    let _ = write!(
+                        out,
+                        "debounce is 0-{} ms\r\n",
+                        TriggerConfig::MAX_DEBOUNCE_MS
+                    );
+                    return;
+                }
+            }
+        }
+        trigger::configure(config);
+        settings::update(|s| s.trigger = config);
+    }
+    let _ = write!(
+        out,
+        "trigger active {}, debounce {} ms, {} ({} presses)\r\n",
+        config.level_str(),
+        config.debounce_ms,
+        if trigger::is_down() { "down" } else { "up" },
+        trigger::presses()
+    );
+}
+
+// Frame recording; games started from now on get the seed
+fn rec(out: &mut Writer, arg: Option<&str>, seed: Option<&str>) {
+    match (arg, seed.map(str::parse::<u32>)) {
+        (None, _) => {
+            let _ = match frame_record::is_recording() {
+                true => write!(out, "rec on, {} frames\r\n", frame_record::frames()),
+                false => write!(out, "rec off\r\n"),
+            };
+        }
+        (Some("on"), None) => frame_record::start(frame_record::DEFAULT_SEED),
+        (Some("on"), Some(Ok(seed))) => frame_record::start(seed),
+        (Some("off"), None) => frame_record::stop(),
+        _ => {
+            let _ = write!(out, "usage: rec [on [seed]|off]\r\n");
+        }
+    }
+}
+
+// The last run kept for the host, or send it over the telemetry link
+fn replay(out: &mut Writer, arg: Option<&str>) {
+    match arg {
+        None => {
+            let _ = match replay::last() {
+                Some((header, len)) => write!(
+                    out,
+                    "replay {}, seed {}, score {}, {} frames, {} bytes{}\r\n",
+                    header.mode.as_str(),
+                    header.seed,
+                    header.score,
+                    header.frames,
+                    len,
+                    if header.flags & replay_format::TRUNCATED != 0 {
+                        ", cut short"
+                    } else {
+                        ""
+                    }
+                ),
+                None => write!(out, "no replay\r\n"),
+            };
+        }
+        Some("send") => replay::request(),
+        _ => {
+            let _ = write!(out, "usage: replay [send]\r\n");
+        }
+    }
+}
+
+// `velocity` as pixels per frame to two places
+fn speed(out: &mut Writer, velocity: u32) {
+    let (whole, hundredths) = scroll::hundredths(velocity);
+    let _ = write!(out, "speed {}.{:02}\r\n", whole, hundredths);
+}
+
+fn mpu(out: &mut Writer, reg: Option<&str>, count: Option<&str>) {
+    let Some(Ok(reg)) = reg.map(|r| u8::from_str_radix(r.trim_start_matches("0x"), 16)) else {
+        let _ = write!(out, "usage: mpu <reg> [count]\r\n");
+        return;
+    };
+    let count = match count.map(str::parse::<usize>) {
+        None => 1,
+        Some(Ok(n)) if (1..=16).contains(&n) => n,
+        Some(_) => {
+            let _ = write!(out, "count must be 1-16\r\n");
+            return;
+        }
+    };
+
+    let mut buffer = [0u8; 16];
+    if mpu6050::read_registers(reg, &mut buffer[..count]).is_err() {
+        let _ = write!(out, "i2c error\r\n");
+        return;
+    }
+    let _ = write!(out, "{:02x}:", reg);
+    for byte in &buffer[..count] {
+        let _ = write!(out, " {:02x}", byte);
+    }
+    let _ = write!(out, "\r\n");
+}
+
+// The addresses that answer on `bus`, in hex
+fn scan(out: &mut Writer, bus: Bus) {
+    let _ = match i2c::scan(bus) {
+        Ok(map) => write!(out, "{}: {}\r\n", bus.name(), map),
+        Err(error) => write!(out, "{}: {}\r\n", bus.name(), error),
+    };
+}
+
+// Pick a built-in curve by name, or try out raw tables; raw tables are not
+// saved and the curve in the settings comes back at the next boot
+fn gamma(out: &mut Writer, first: Option<&str>, second: Option<&str>) {
+    let current = settings::get().gamma;
+    match (first, second) {
+        (None, _) => {
+            let _ = write!(out, "gamma {}\r\n", current.as_str());
+        }
+        (Some(name), None) => {
+            let found = (0..)
+                .map_while(GammaProfile::from_u32)
+                .find(|profile| profile.as_str() == name);
+            let Some(profile) = found else {
+                let _ = write!(out, "gamma standard|vivid|soft\r\n");
+                return;
+            };
+            let _ = display::set_gamma(profile);
+            settings::update(|settings| settings.gamma = profile);
+            let _ = write!(out, "gamma {}\r\n", profile.as_str());
+        }
+        (Some(pos), Some(neg)) => match (parse_gamma(pos), parse_gamma(neg)) {
+            (Some(pos), Some(neg)) => {
+                ili9341::upload_gamma(&GammaTables { pos, neg });
+                let _ = write!(out, "gamma uploaded\r\n");
+            }
+            _ => {
+                let _ = write!(out, "tables are {} hex bytes each\r\n", GAMMA_LEN);
+            }
+        },
+    }
+}
+
+// 0F2924... into bytes, exactly GAMMA_LEN of them
+fn parse_gamma(hex: &str) -> Option<[u8; GAMMA_LEN]> {
+    if hex.len() != GAMMA_LEN * 2 {
+        return None;
+    }
+    let mut table = [0; GAMMA_LEN];
+    for (i, byte) in table.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(table)
+}
+
+// pclk rise|fall, hs/vs/de low|high, l2 pixel|const, l2move vblank|now
+// Switch rendering backend; the title screen is redrawn on the new one,
+// anything else on the next screen change
+fn render<T: InputDevice>(out: &mut Writer, game: &mut Game<T>, arg: Option<&str>) {
+    let backend = match arg {
+        None => display::backend(),
+        Some("ltdc") => Backend::Ltdc,
+        Some("spi") => Backend::Spi,
+        Some(_) => {
+            let _ = write!(out, "render ltdc|spi\r\n");
+            return;
+        }
+    };
+    if arg.is_some() {
+        if let Err(error) = display::set_backend(backend) {
+            let _ = write!(out, "render: {}\r\n", error);
+            return;
+        }
+        if game.state() == GameState::Ready {
+            Game::<T>::draw_start_screen();
+        }
+    }
+    let name = match backend {
+        Backend::Ltdc => "ltdc",
+        Backend::Spi => "spi",
+    };
+    let _ = write!(out, "render {}\r\n", name);
+}
+
+// Bandwidth saver, for LTDC underruns while the game keeps SDRAM busy
+fn bandwidth(out: &mut Writer, arg: Option<&str>) {
+    let on = match arg {
+        None => display::bandwidth_saver(),
+        Some("on") => true,
+        Some("off") => false,
+        Some(_) => {
+            let _ = write!(out, "bandwidth on|off\r\n");
+            return;
+        }
+    };
+    if let Some(Err(error)) = arg.map(|_| display::set_bandwidth_saver(on)) {
+        let _ = write!(out, "bandwidth saver: {}\r\n", error);
+        return;
+    }
+    let bpp = LcdDriver::layer1_format().bytes_per_pixel() * 8;
+    let _ = write!(
+        out,
+        "bandwidth saver {}, layer 1 {}bpp, pclk {} Hz\r\n",
+        if on { "on" } else { "off" },
+        bpp,
+        clock::pixel_clock_hz()
+    );
+}
+
+fn ltdc(out: &mut Writer, signal: Option<&str>, value: Option<&str>) {
+    let mut config = LcdDriver::config();
+    let level = |v| match v {
+        "low" => Some(Polarity::ActiveLow),
+        "high" => Some(Polarity::ActiveHigh),
+        _ => None,
+    };
+    if let Some(signal) = signal {
+        let value = value.unwrap_or("");
+        let edge = |v| match v {
+            "rise" => Some(ClockEdge::Rising),
+            "fall" => Some(ClockEdge::Falling),
+            _ => None,
+        };
+        let blend = |v| match v {
+            "pixel" => Some(BlendMode::PixelAlpha),
+            "const" => Some(BlendMode::ConstantAlpha),
+            _ => None,
+        };
+        let reload = |v| match v {
+            "vblank" => Some(false),
+            "now" => Some(true),
+            _ => None,
+        };
+        let applied = match signal {
+            "pclk" => edge(value).map(|e| config.pixel_clock = e),
+            "hs" => level(value).map(|p| config.hsync = p),
+            "vs" => level(value).map(|p| config.vsync = p),
+            "de" => level(value).map(|p| config.data_enable = p),
+            "l2" => blend(value).map(|m| config.layer2_blend = m),
+            "l2move" => reload(value).map(|r| config.layer2_immediate = r),
+            _ => None,
+        };
+        if applied.is_none() {
+            let _ = write!(
+                out,
+                "usage: ltdc [pclk rise|fall] [hs|vs|de low|high] [l2 pixel|const] [l2move vblank|now]\r\n"
+            );
+            return;
+        }
+        LcdDriver::attach().apply_config(config);
+    }
+
+    let level_name = |p| {
+        if p == Polarity::ActiveHigh {
+            "high"
+        } else {
+            "low"
+        }
+    };
+    let _ = write!(
+        out,
+        "pclk {} hs {} vs {} de {} l2 {} l2move {}\r\n",
+        if config.pixel_clock == ClockEdge::Rising {
+            "rise"
+        } else {
+            "fall"
+        },
+        level_name(config.hsync),
+        level_name(config.vsync),
+        level_name(config.data_enable),
+        if config.layer2_blend == BlendMode::PixelAlpha {
+            "pixel"
+        } else {
+            "const"
+        },
+        if config.layer2_immediate {
+            "now"
+        } else {
+            "vblank"
+        }
+    );
+    let (reloads, unchanged) = LcdDriver::reload_counts();
+    let _ = write!(
+        out,
+        "reloads {} (skipped {} unchanged)\r\n",
+        reloads, unchanged
+    );
+}
+
+// Draw what flash::ARTWORK holds over whatever is on screen
+fn art(out: &mut Writer, x: Option<&str>, y: Option<&str>) {
+    let image = match artwork::image() {
+        Ok(image) => image,
+        Err(err) => {
+            let _ = write!(out, "no artwork: {:?}\r\n", err);
+            return;
+        }
+    };
+    let (x, y) = match (x.map(str::parse::<Coord>), y.map(str::parse::<Coord>)) {
+        (None, None) => artwork::centered(&image),
+        (Some(Ok(x)), Some(Ok(y))) => (x, y),
+        _ => {
+            let _ = write!(out, "usage: art [x y]\r\n");
+            return;
+        }
+    };
+    artwork::draw(&image, x, y);
+    let _ = write!(
+        out,
+        "art {}x{} at {},{}\r\n",
+        image.width, image.height, x, y
+    );
+}
+
+fn shot(out: &mut Writer, first: Option<&str>, second: Option<&str>) {
+    let format = |name: Option<&str>| match name {
+        None => Some(Format::Rgb565),
+        Some(name) => Format::parse(name),
+    };
+    match first {
+        Some("save") => {
+            let result = if screenshot::save().is_ok() {
+                "saved"
+            } else {
+                "flash error"
+            };
+            let _ = write!(out, "{}\r\n", result);
+        }
+        Some("load") => match format(second) {
+            Some(format) => {
+                if screenshot::stream_saved(format).is_err() {
+                    let _ = write!(out, "no shot in flash\r\n");
+                }
+            }
+            None => {
+                let _ = write!(out, "format must be raw or ppm\r\n");
+            }
+        },
+        name => match format(name) {
+            Some(format) => screenshot::stream(format),
+            None => {
+                let _ = write!(out, "usage: shot [raw|ppm|save|load]\r\n");
+            }
+        },
+    }
+}
+
+fn bench(out: &mut Writer, name: Option<&str>) {
+    let benches = match name.map(bench::find) {
+        None => &bench::BENCHES[..],
+        Some(Some(one)) => core::slice::from_ref(one),
+        Some(None) => {
+            let _ = write!(out, "usage: bench [name], one of:");
+            for one in &bench::BENCHES {
+                let _ = write!(out, " {}", one.name);
+            }
+            let _ = write!(out, "\r\n");
+            return;
+        }
+    };
+    for one in benches {
+        let _ = write!(out, "{}\r\n", bench::run(one));
+    }
+}
+
+// Playing as a profile puts its settings in force, so the game starts over
+fn profile(out: &mut Writer, arg: Option<&str>, clear: Option<&str>) {
+    let slot = arg.and_then(|arg| arg.parse::<usize>().ok());
+    match (arg, slot, clear) {
+        (None, ..) => {}
+        (Some("guest"), _, None) => {
+            profiles::select(None);
+            system::request_warm_restart();
+        }
+        (_, Some(n @ 1..=profile_format::SLOTS), None) => {
+            profiles::select(Some(n - 1));
+            system::request_warm_restart();
+        }
+        (_, Some(n @ 1..=profile_format::SLOTS), Some("clear")) => {
+            if profiles::clear(n - 1).is_err() {
+                let _ = write!(out, "save failed\r\n");
+            }
+        }
+        _ => {
+            let _ = write!(
+                out,
+                "usage: profile [1-{}|guest] [clear]\r\n",
+                profile_format::SLOTS
+            );
+            return;
+        }
+    }
+    let active = profiles::active().map(|(slot, _)| slot);
+    for slot in 0..profile_format::SLOTS {
+        let mark = if active == Some(slot) { '*' } else { ' ' };
+        let Some(profile) = profiles::get(slot) else {
+            let _ = write!(out, "{}{} empty\r\n", mark, slot + 1);
+            continue;
+        };
+        let _ = write!(
+            out,
+            "{}{} {} best {} games {}",
+            mark,
+            slot + 1,
+            profile.initials(),
+            profile.best_score,
+            profile.games
+        );
+        for achievement in profile.achievements.iter() {
+            let _ = write!(out, ", {}", achievement.name());
+        }
+        let _ = write!(out, "\r\n");
+    }
+    if active.is_none() {
+        let _ = write!(out, "playing as a guest\r\n");
+    }
+}
+
+fn wifi_status(out: &mut Writer) {
+    if !wifi::is_enabled() {
+        let _ = write!(
+            out,
+            "wifi off ({})\r\n",
+            if cfg!(feature = "wifi") {
+                "no WIFI_SSID in the build"
+            } else {
+                "built without the wifi feature"
+            }
+        );
+        return;
+    }
+    let config = wifi::config();
+    let (waiting, sent, dropped) = wifi::counts();
+    let _ = write!(
+        out,
+        "wifi {} ssid {} server {}:{}{}\r\n\
+         scores waiting {} sent {} dropped {}, rx dropped {}\r\n",
+        wifi::status().as_str(),
+        config.ssid,
+        config.host,
+        config.port,
+        config.path,
+        waiting,
+        sent,
+        dropped,
+        wifi::rx_dropped()
+    );
+}
+
+fn top(out: &mut Writer, arg: Option<&str>, clear: Option<&str>) {
+    let (board, arg) = match arg {
+        Some("time") => (Board::TimeAttack, clear),
+        _ => (Board::Main, arg),
+    };
+    match arg {
+        None => {}
+        Some("clear") => stats::clear_leaderboard(board),
+        Some(_) => {
+            let _ = write!(out, "usage: top [time] [clear]\r\n");
+            return;
+        }
+    }
+    let table = stats::leaderboard(board);
+    if table.entries().is_empty() {
+        let _ = write!(out, "no runs yet\r\n");
+    }
+    for (rank, entry) in table.entries().iter().enumerate() {
+        let at = DateTime::from_timestamp(entry.at);
+        let _ = write!(
+            out,
+            "{:>2} {} {:>5} {:04}-{:02}-{:02} {:02}:{:02}\r\n",
+            rank + 1,
+            entry.initials(),
+            entry.score,
+            at.year,
+            at.month,
+            at.day,
+            at.hour,
+            at.minute
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! The sky behind the playfield
+//!
+//! Themes with `day_night` set run the sky through the day/night cycle in
+//! `core_logic::sky` as the score goes up; the others keep their flat
+//! background. Everything that paints sky, whether the whole playfield or
+//! the strip behind a moving obstacle, asks `fill` so the rows line up with
+//! the gradient. The playfield is only repainted when the sky changes, at
+//! most once per point.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+pub use core_logic::sky::Sky;
+
+use crate::color::Rgb565;
+use crate::config::Rect;
+use crate::display;
+use crate::lane::Lane;
+use crate::theme;
+
+static mut CURRENT: Option<Sky> = None;
+
+fn sky_at(score: u32) -> Sky {
+    let theme = theme::current();
+    if theme.day_night {
+        Sky::at(score)
+    } else {
+        Sky::flat(Rgb565(theme.background))
+    }
+}
+
+pub fn current() -> Sky {
+    unsafe { CURRENT }.unwrap_or_else(|| sky_at(0))
+}
+
+// Back to the sky a game starts with, for a new game or theme
+pub fn reset() {
+    unsafe { CURRENT = Some(sky_at(0)) };
+}
+
+// Follow the score. True when the sky changed and the playfield needs
+// repainting.
+pub fn update(score: u32) -> bool {
+    let next = sky_at(score);
+    let changed = current() != next;
+    unsafe { CURRENT = Some(next) };
+    changed
+}
+
+// Paint the part of `rect` inside `lane` with the sky, the gradient
+// stretched over the lane's playfield
+pub fn fill(lane: &Lane, rect: Rect) {
+    let Some(rect) = rect.intersection(&lane.rect()) else {
+        return;
+    };
+    let sky = current();
+    let field = lane.field();
+    let _ = display::fill_rows_rust(rect, &|y| sky.row_color(y, field));
+}
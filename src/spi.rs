@@ -0,0 +1,258 @@
+//! SPI5 master, the ILI9341 control bus on the DISCO board
+//!
+//! `SpiBus` is the byte-level interface device drivers are written
+//! against: full-duplex transfers, multi-byte writes and a settable clock.
+//! `Spi5` implements it on PF7 (SCK), PF8 (MISO) and PF9 (MOSI); chip select
+//! and other control lines belong to the device driver.
+//!
+//! Writes of `'static` data can also go out through DMA2 stream 4 (channel
+//! 2, SPI5_TX) with `write_dma`, which returns at once. The `DmaWrite`
+//! handle it returns reports completion and, when dropped, waits for the
+//! last byte and puts the peripheral back in polled mode.
+#![allow(dead_code)]
+
+use stm32f4::stm32f429 as pac;
+
+use crate::resources;
+
+// SPI5 sits on APB2
+const APB2_HZ: u32 = 84_000_000;
+
+// Pins on GPIOF, all AF5
+const SCK: u32 = 7;
+const MISO: u32 = 8;
+const MOSI: u32 = 9;
+
+// DMA2 stream 4 channel 2 carries SPI5_TX
+const DMA_STREAM: usize = 4;
+const DMA_CHANNEL: u8 = 2;
+// NDTR is 16 bits wide
+pub const DMA_MAX_LEN: usize = u16::MAX as usize;
+
+/// SPI clock as a divider of the APB2 clock
+#[derive(Copy, Clone, PartialEq)]
+pub enum Baud {
+    Div2 = 0,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+    Div256,
+}
+
+impl Baud {
+    pub const fn hz(self) -> u32 {
+        APB2_HZ >> (self as u32 + 1)
+    }
+}
+
+pub trait SpiBus {
+    fn set_baud(&mut self, baud: Baud);
+    // Clock one byte out and return the byte clocked in
+    fn transfer(&mut self, byte: u8) -> u8;
+    // Clock bytes out, ignoring what comes back; returns once they are sent
+    fn write(&mut self, data: &[u8]) {
+        for &b in data {
+            self.transfer(b);
+        }
+    }
+    // Clock zeros out and keep what comes back
+    fn read(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = self.transfer(0x00);
+        }
+    }
+}
+
+pub struct Spi5 {
+    regs: &'static pac::SPI5,
+}
+
+impl Spi5 {
+    // Clocks, pins and the peripheral: master, mode 0, 8 bit, software NSS.
+    // Safe to call again; the last call's baud rate wins.
+    pub fn init(baud: Baud) -> Self {
+        let dp = resources::pac();
+        dp.RCC
+            .ahb1enr
+            .modify(|_, w| w.gpiofen().enabled().dma2en().enabled());
+        dp.RCC.apb2enr.modify(|_, w| w.spi5en().enabled());
+
+        let gpiof = &dp.GPIOF;
+        for pin in [SCK, MISO, MOSI] {
+            gpiof.moder.modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+            });
+            gpiof.ospeedr.modify(|r, w| unsafe {
+                w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+            });
+            if pin < 8 {
+                gpiof.afrl.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0xF << (pin * 4))) | (5 << (pin * 4)))
+                });
+            } else {
+                let idx = pin - 8;
+                gpiof.afrh.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0xF << (idx * 4))) | (5 << (idx * 4)))
+                });
+            }
+        }
+        // Pull MISO up so a device that does not drive it reads as all ones
+        gpiof.pupdr.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << (MISO * 2))) | (0b01 << (MISO * 2)))
+        });
+
+        let spi = &dp.SPI5;
+        spi.cr1.modify(|_, w| w.spe().clear_bit());
+        spi.cr1.modify(|_, w| {
+            w.mstr()
+                .set_bit()
+                .ssm()
+                .set_bit()
+                .ssi()
+                .set_bit()
+                .cpol()
+                .clear_bit()
+                .cpha()
+                .clear_bit()
+                // 2-line unidirectional (full duplex)
+                .bidimode()
+                .clear_bit()
+        });
+        let mut bus = Spi5 { regs: spi };
+        bus.set_baud(baud);
+        bus
+    }
+
+    // Handle to a peripheral init() already set up
+    pub fn attach() -> Self {
+        let dp = resources::pac();
+        Spi5 { regs: &dp.SPI5 }
+    }
+
+    fn wait_idle(&self) {
+        while self.regs.sr.read().txe().bit_is_clear() {}
+        while self.regs.sr.read().bsy().bit_is_set() {}
+    }
+
+    // Drop whatever was clocked in during a write, clearing OVR (DR then SR)
+    fn drain_rx(&self) {
+        let _ = self.regs.dr.read().bits();
+        let _ = self.regs.sr.read().bits();
+    }
+
+    // Start sending `data` by DMA; the CPU is free until the handle is
+    // waited on or dropped. At most DMA_MAX_LEN bytes.
+    pub fn write_dma(&mut self, data: &'static [u8]) -> DmaWrite {
+        debug_assert!(data.len() <= DMA_MAX_LEN, "DMA write too long");
+        let dp = resources::pac();
+        let stream = &dp.DMA2.st[DMA_STREAM];
+        stream.cr.modify(|_, w| w.en().disabled());
+        while stream.cr.read().en().is_enabled() {}
+        clear_dma_flags();
+
+        self.wait_idle();
+        stream
+            .par
+            .write(|w| unsafe { w.pa().bits(&self.regs.dr as *const _ as u32) });
+        stream
+            .m0ar
+            .write(|w| unsafe { w.m0a().bits(data.as_ptr() as u32) });
+        stream
+            .ndtr
+            .write(|w| w.ndt().bits(data.len().min(DMA_MAX_LEN) as u16));
+        stream.cr.write(|w| {
+            w.chsel()
+                .bits(DMA_CHANNEL)
+                .pl()
+                .medium()
+                .msize()
+                .bits8()
+                .psize()
+                .bits8()
+                .minc()
+                .incremented()
+                .pinc()
+                .fixed()
+                .dir()
+                .memory_to_peripheral()
+        });
+        stream.cr.modify(|_, w| w.en().enabled());
+        self.regs.cr2.modify(|_, w| w.txdmaen().set_bit());
+        DmaWrite { _private: () }
+    }
+}
+
+fn clear_dma_flags() {
+    let dp = resources::pac();
+    dp.DMA2.hifcr.write(|w| {
+        w.ctcif4()
+            .set_bit()
+            .chtif4()
+            .set_bit()
+            .cteif4()
+            .set_bit()
+            .cdmeif4()
+            .set_bit()
+            .cfeif4()
+            .set_bit()
+    });
+}
+
+impl SpiBus for Spi5 {
+    fn set_baud(&mut self, baud: Baud) {
+        self.wait_idle();
+        self.regs.cr1.modify(|_, w| w.spe().clear_bit());
+        self.regs.cr1.modify(|_, w| w.br().bits(baud as u8));
+        self.regs.cr1.modify(|_, w| w.spe().set_bit());
+    }
+
+    fn transfer(&mut self, byte: u8) -> u8 {
+        let spi = &self.regs;
+        while spi.sr.read().txe().bit_is_clear() {}
+        spi.dr.write(|w| w.dr().bits(byte.into()));
+        while spi.sr.read().rxne().bit_is_clear() {}
+        spi.dr.read().dr().bits() as u8
+    }
+
+    // Keeps the transmit buffer full instead of waiting out each byte
+    fn write(&mut self, data: &[u8]) {
+        let spi = &self.regs;
+        for &b in data {
+            while spi.sr.read().txe().bit_is_clear() {}
+            spi.dr.write(|w| w.dr().bits(b.into()));
+        }
+        self.wait_idle();
+        self.drain_rx();
+    }
+}
+
+/// A DMA write in flight; dropping it waits for the end of the transfer
+pub struct DmaWrite {
+    _private: (),
+}
+
+impl DmaWrite {
+    // True once every byte has left the shift register
+    pub fn is_done(&self) -> bool {
+        let dp = resources::pac();
+        dp.DMA2.hisr.read().tcif4().bit_is_set()
+            && dp.SPI5.sr.read().txe().bit_is_set()
+            && dp.SPI5.sr.read().bsy().bit_is_clear()
+    }
+
+    pub fn wait(self) {}
+}
+
+impl Drop for DmaWrite {
+    fn drop(&mut self) {
+        while !self.is_done() {}
+        let dp = resources::pac();
+        dp.SPI5.cr2.modify(|_, w| w.txdmaen().clear_bit());
+        dp.DMA2.st[DMA_STREAM].cr.modify(|_, w| w.en().disabled());
+        clear_dma_flags();
+        Spi5::attach().drain_rx();
+    }
+}
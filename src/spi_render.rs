@@ -0,0 +1,160 @@
+//! Drawing straight into the panel's GRAM over SPI
+//!
+//! The fallback for boards or debug setups without SDRAM or LTDC: each
+//! primitive clips to the screen, sets a window and streams RGB565 pixels
+//! with RAMWR (see `ili9341::write_pixels`). The panel turns GRAM writes to
+//! the current orientation itself, so windows are in game coordinates.
+//! Nothing is buffered, so `present` has nothing to do, and there are no
+//! layers: the sprite layer, menu overlay, dimming and retro scaling are
+//! LTDC-only.
+#![allow(dead_code)]
+
+use crate::config::{Coord, Rect, PANEL_HEIGHT, PANEL_WIDTH};
+use crate::framebuffer::{self, Image, ImageTransform, RenderBackend};
+use crate::ili9341;
+
+const PANEL: (u32, u32) = (PANEL_WIDTH, PANEL_HEIGHT);
+
+pub struct SpiPanel;
+
+impl SpiPanel {
+    pub fn new() -> Self {
+        SpiPanel
+    }
+
+    // Send the game rectangle (x, y, w, h), clipped to the screen, to the
+    // panel. `at` gives the color at an offset inside the rectangle; None
+    // leaves the panel pixel alone, which costs a window per run of pixels,
+    // so `opaque` callers that never return None get a single window.
+    fn draw(
+        x: Coord,
+        y: Coord,
+        w: u32,
+        h: u32,
+        opaque: bool,
+        at: impl Fn(u32, u32) -> Option<u16>,
+    ) {
+        let (gw, gh) = framebuffer::orientation().game_size(PANEL.0, PANEL.1);
+        let x0 = x.max(0) as i64;
+        let y0 = y.max(0) as i64;
+        let x1 = (x as i64 + w as i64).min(gw as i64);
+        let y1 = (y as i64 + h as i64).min(gh as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let (x0, y0, x1, y1) = (x0 as Coord, y0 as Coord, x1 as Coord, y1 as Coord);
+        // Color shown at a screen pixel inside the window
+        let color = |col: Coord, row: Coord| at((col - x) as u32, (row - y) as u32);
+
+        if opaque {
+            let pixels = (y0..y1)
+                .flat_map(|row| (x0..x1).map(move |col| (col, row)))
+                .map(|(col, row)| color(col, row).unwrap_or(0));
+            ili9341::write_pixels(
+                x0 as u16,
+                y0 as u16,
+                (x1 - x0) as u16,
+                (y1 - y0) as u16,
+                pixels,
+            );
+            return;
+        }
+        for row in y0..y1 {
+            let mut col = x0;
+            while col < x1 {
+                if color(col, row).is_none() {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col < x1 && color(col, row).is_some() {
+                    col += 1;
+                }
+                ili9341::write_pixels(
+                    start as u16,
+                    row as u16,
+                    (col - start) as u16,
+                    1,
+                    (start..col).map(|c| color(c, row).unwrap_or(0)),
+                );
+            }
+        }
+    }
+}
+
+impl Default for SpiPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderBackend for SpiPanel {
+    fn size(&self) -> (u32, u32) {
+        framebuffer::orientation().game_size(PANEL.0, PANEL.1)
+    }
+
+    fn set_pixel(&mut self, x: Coord, y: Coord, rgb565: u16) {
+        self.fill_rect(x, y, 1, 1, rgb565);
+    }
+
+    fn fill_rect(&mut self, x: Coord, y: Coord, w: u32, h: u32, rgb565: u16) {
+        Self::draw(x, y, w, h, true, |_, _| Some(rgb565));
+    }
+
+    // Pixels matching `key` keep what the panel already shows
+    fn blit(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+    ) {
+        let (w, h) = transform.size(image);
+        Self::draw(x, y, w, h, key.is_none(), |col, row| {
+            image
+                .pixel(col, row, transform)
+                .filter(|&rgb565| key != Some(rgb565))
+        });
+    }
+
+    // One window over just the region
+    fn blit_region(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        image: &Image,
+        transform: ImageTransform,
+        key: Option<u16>,
+        region: Rect,
+    ) {
+        let (x, y) = (x + region.x, y + region.y);
+        let (dx, dy) = (region.x as u32, region.y as u32);
+        Self::draw(x, y, region.w, region.h, key.is_none(), |col, row| {
+            image
+                .pixel(col + dx, row + dy, transform)
+                .filter(|&rgb565| key != Some(rgb565))
+        });
+    }
+
+    fn fill_tiled_from(
+        &mut self,
+        x: Coord,
+        y: Coord,
+        w: u32,
+        h: u32,
+        tile: &Image,
+        skip: (u32, u32),
+    ) {
+        if tile.w == 0 || tile.h == 0 {
+            return;
+        }
+        Self::draw(x, y, w, h, true, |col, row| {
+            let (col, row) = ((col + skip.0) % tile.w, (row + skip.1) % tile.h);
+            tile.pixel(col, row, ImageTransform::FLIP_Y)
+        });
+    }
+
+    // Every primitive already went out to the panel
+    fn present(&mut self) {}
+}
@@ -0,0 +1,110 @@
+//! Boot splash
+//!
+//! The title logo (or artwork flashed in its place, see artwork.rs) fades
+//! in over a black screen, with the firmware version and build hash under
+//! it. The logo is drawn once into the full-screen overlay buffer and only
+//! Layer 2's constant alpha ramps up, so the fade costs no redrawing. The splash ends after SPLASH_MS or on a button press,
+//! leaving the logo on Layer 1 for the start screen to draw over.
+//!
+//! SPI rendering has no layers to fade, so the logo just appears.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::artwork;
+use crate::clock;
+use crate::color;
+use crate::config::*;
+use crate::display::{self, Backend};
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::input_events;
+use crate::power;
+
+// From power-on of the splash to the start screen, unless skipped
+const SPLASH_MS: u32 = 2500;
+// Part of SPLASH_MS spent fading in
+const FADE_MS: u32 = 800;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+// Short git hash set by build.rs; empty when built outside a checkout
+const BUILD_HASH: &str = env!("BUILD_HASH");
+
+// "v0.1.0 3f2a9c1"
+fn version_line() -> FmtBuf<32> {
+    let mut line = FmtBuf::new();
+    let _ = write!(line, "v{} {}", VERSION, BUILD_HASH);
+    line
+}
+
+pub fn run() {
+    let start = clock::millis();
+    let skipped = match display::backend() {
+        Backend::Ltdc => fade_in(start),
+        Backend::Spi => {
+            artwork::draw_title();
+            let line = version_line();
+            let y = LCD_HEIGHT as Coord - 30;
+            let _ = display::write_string_rust(0, y, line.as_cstr(), color::WHITE, color::BLACK);
+            false
+        }
+    };
+    if !skipped {
+        hold(start);
+    }
+    finish();
+}
+
+// Until SPLASH_MS after `start` or a button press
+fn hold(start: u32) {
+    while clock::millis().wrapping_sub(start) < SPLASH_MS {
+        if input_events::take_button_press() {
+            return;
+        }
+        power::idle_until_vblank();
+    }
+}
+
+// Returns true when a button press cut the fade short
+fn fade_in(start: u32) -> bool {
+    let mut fb = FrameBuffer::overlay();
+    artwork::blit_title(&mut fb);
+    let line = version_line();
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Bottom)
+        .build();
+    let bottom = Point::new(LCD_WIDTH as Coord / 2, LCD_HEIGHT as Coord - 4);
+    let _ = Text::with_text_style(line.as_str(), bottom, style, centered).draw(&mut fb);
+    framebuffer::flush();
+
+    loop {
+        let elapsed = clock::millis().wrapping_sub(start);
+        let alpha = (elapsed.min(FADE_MS) * 0xFF / FADE_MS) as u8;
+        let _ = display::show_overlay(alpha);
+        if alpha == 0xFF {
+            return false;
+        }
+        if input_events::take_button_press() {
+            return true;
+        }
+        power::idle_until_vblank();
+    }
+}
+
+// Put the logo on Layer 1 before the overlay goes, so nothing flashes, and
+// leave the sprite layer opaque as the game expects
+fn finish() {
+    if display::backend() == Backend::Ltdc {
+        artwork::draw_title();
+        let _ = display::hide_overlay();
+        let _ = display::set_sprite_alpha(0xFF);
+    }
+}
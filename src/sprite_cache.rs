@@ -0,0 +1,132 @@
+//! SDRAM cache of sprites converted for drawing
+//!
+//! Turning the bird to its tilt is per-pixel math far too slow to repeat
+//! every frame. The first time a sprite is wanted mirrored, scaled and
+//! turned a certain way it is converted into a slot here as ARGB8888, its key-color
+//! pixels and the corners the turn leaves empty fully transparent; after
+//! that it is drawn straight from the slot. Each slot holds an image up to
+//! the sprite upload limit, and there are as many as fit in the SDRAM the
+//! arena has left after the ghost buffers, up to `MAX_SLOTS`. When every
+//! slot is taken the least recently used one is converted over
+//! (core_logic::sprite_cache::Lru).
+//!
+//! Art stored as half of itself (render::Mirror) is converted whole, and
+//! flips that leave it looking the same share a slot.
+//!
+//! Changing theme or uploading a sprite empties the cache.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::slice;
+
+use core_logic::sprite_cache::{turned_from, Key, Lookup, Lru};
+
+use crate::color;
+use crate::framebuffer::{self, ImageTransform};
+use crate::ghost;
+use crate::lcd::DISPLAY_MEMORY;
+use crate::sdram;
+use crate::sdram::arena::{Arena, Region};
+use crate::sprites::{self, SpriteId, MAX_SPRITE_H, MAX_SPRITE_W};
+
+// Enough for every turn of the bird's lean (core_logic::lean) and a few more
+const MAX_SLOTS: usize = 24;
+const SLOT_PIXELS: usize = (MAX_SPRITE_W * MAX_SPRITE_H) as usize;
+const SLOT_BYTES: u32 = SLOT_PIXELS as u32 * 4;
+
+const AFTER_GHOST: Region = Region {
+    base: ghost::BUFFERS.end(),
+    size: DISPLAY_MEMORY.free.end() - ghost::BUFFERS.end(),
+};
+const SLOTS: usize = {
+    let fit = (AFTER_GHOST.size / SLOT_BYTES) as usize;
+    if fit < MAX_SLOTS {
+        fit
+    } else {
+        MAX_SLOTS
+    }
+};
+pub const CACHE: Region = Arena::new(AFTER_GHOST).alloc(SLOTS as u32 * SLOT_BYTES, 4);
+
+static mut LRU: Lru<MAX_SLOTS> = Lru::new(SLOTS);
+// Size of the image in each slot
+static mut SIZES: [(u32, u32); MAX_SLOTS] = [(0, 0); MAX_SLOTS];
+
+/// A converted sprite: ARGB8888, top row first, alpha 0 where nothing is
+/// to be drawn
+#[derive(Copy, Clone)]
+pub struct Cached {
+    pub w: u32,
+    pub h: u32,
+    pub pixels: &'static [u32],
+}
+
+fn slot(index: usize) -> &'static mut [u32] {
+    // SAFETY: each slot is a SLOT_BYTES region of SDRAM in CACHE, reserved
+    // for this module alone; nothing is cached unless SDRAM is initialized
+    unsafe {
+        slice::from_raw_parts_mut(
+            (CACHE.base + index as u32 * SLOT_BYTES) as *mut u32,
+            SLOT_PIXELS,
+        )
+    }
+}
+
+// Sprite `id` as it is now, mirrored by `transform` and turned `turn`
+// steps; None without SDRAM or when the sprite is missing or too big
+pub fn get(id: SpriteId, transform: ImageTransform, turn: i8) -> Option<Cached> {
+    if !sdram::available() {
+        return None;
+    }
+    let image = sprites::sprite(id)?;
+    // Art that is its own mirror image looks the same flipped that way, so
+    // both ways share a slot
+    let key = Key {
+        asset: id as u8,
+        transform: image.mirror.canonical(transform),
+        turn,
+    };
+    let (index, hit) = match unsafe { LRU.lookup(key) }? {
+        Lookup::Hit(index) => (index, true),
+        Lookup::Miss(index) => (index, false),
+    };
+    if !hit {
+        let (w, h) = transform.size(&image);
+        if w * h > SLOT_PIXELS as u32 {
+            unsafe { LRU.forget(index) };
+            return None;
+        }
+        let pixels = slot(index);
+        for row in 0..h {
+            for col in 0..w {
+                let rgb565 = turned_from(w, h, col, row, turn)
+                    .and_then(|(col, row)| image.pixel(col, row, transform))
+                    .filter(|&rgb565| rgb565 != color::SPRITE_KEY);
+                pixels[(row * w + col) as usize] = match rgb565 {
+                    Some(rgb565) => color::Rgb565(rgb565).to_argb8888().0,
+                    None => color::Argb8888::TRANSPARENT.0,
+                };
+            }
+        }
+        framebuffer::flush();
+        unsafe { SIZES[index] = (w, h) };
+    }
+    let (w, h) = unsafe { SIZES[index] };
+    Some(Cached {
+        w,
+        h,
+        pixels: &slot(index)[..(w * h) as usize],
+    })
+}
+
+// Forget every conversion; call when sprite art changes
+pub fn clear() {
+    unsafe { LRU.clear() };
+}
+
+// (slots, hits, misses), for the shell
+pub fn stats() -> (usize, u32, u32) {
+    let lru = unsafe { &LRU };
+    let (hits, misses) = lru.counts();
+    (lru.slots(), hits, misses)
+}
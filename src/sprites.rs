@@ -0,0 +1,252 @@
+//! Sprite registry with SDRAM override slots
+//!
+//! Drawing code looks sprites up here instead of naming flash assets directly.
+//! With the `sprite-reload` feature a replacement can be uploaded over the
+//! telemetry link into an SDRAM slot and swapped in without reflashing:
+//!
+//! ```text
+//! KIND_SPRITE_BEGIN  id u8 | w u8 | h u8 | crc32 u32
+//! KIND_SPRITE_DATA   offset u16 | bytes...   (RGB565 LE, asset row order)
+//! KIND_SPRITE_END    (empty)                 verify CRC, then swap in
+//! ```
+//!
+//! Each command is answered with `KIND_SPRITE_ACK` carrying the command kind
+//! and an `UploadStatus`. Overrides live only in RAM, so every reboot reverts
+//! to the flashed art.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::slice;
+
+use core_logic::pickup::PICKUP_SIZE;
+use core_logic::powerup::PowerUp;
+
+use crate::assets;
+use crate::crc;
+use crate::framebuffer::{Image, Mirror};
+use crate::lcd::DISPLAY_MEMORY;
+use crate::sdram;
+use crate::sdram::arena::{Arena, Region};
+use crate::sprite_cache;
+use crate::theme;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SpriteId {
+    Bird = 0,
+    Plant = 1,
+    // Texture tiled down the pipe bodies, repeating across and down; the
+    // theme's pipe color when not set
+    Obstacle = 2,
+    // Bonus pickups, PICKUP_SIZE square
+    Coin = 3,
+    Star = 4,
+    // Texture of the pipe ends at an opening, as `Obstacle`
+    PipeCap = 5,
+}
+
+const SPRITE_COUNT: usize = 6;
+
+impl SpriteId {
+    fn from_u8(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(SpriteId::Bird),
+            1 => Some(SpriteId::Plant),
+            2 => Some(SpriteId::Obstacle),
+            3 => Some(SpriteId::Coin),
+            4 => Some(SpriteId::Star),
+            5 => Some(SpriteId::PipeCap),
+            _ => None,
+        }
+    }
+}
+
+// Upload size limit, per slot
+pub const MAX_SPRITE_W: u32 = 64;
+pub const MAX_SPRITE_H: u32 = 64;
+const SLOT_PIXELS: usize = (MAX_SPRITE_W * MAX_SPRITE_H) as usize;
+
+const SLOT_BYTES: u32 = (SLOT_PIXELS * 2) as u32;
+// Slots come out of the SDRAM left after the display buffers
+pub const SLOTS: Region =
+    Arena::new(DISPLAY_MEMORY.free).alloc(SLOT_BYTES * SPRITE_COUNT as u32, 4);
+const SLOT_BASE: u32 = SLOTS.base;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum UploadStatus {
+    Ok = 0,
+    BadHeader = 1,
+    BadOffset = 2,
+    CrcMismatch = 3,
+    NotStarted = 4,
+}
+
+#[derive(Copy, Clone)]
+struct Upload {
+    id: SpriteId,
+    w: u32,
+    h: u32,
+    crc: u32,
+}
+
+// Sprite dimensions for each slot holding a verified override
+static mut OVERRIDES: [Option<(u32, u32)>; SPRITE_COUNT] = [None; SPRITE_COUNT];
+static mut UPLOAD: Option<Upload> = None;
+
+fn slot(id: SpriteId) -> &'static mut [u16] {
+    // SAFETY: each slot is a SLOT_BYTES region of SDRAM past every
+    // framebuffer, reserved for this module alone; uploads are refused
+    // unless SDRAM is initialized
+    unsafe {
+        slice::from_raw_parts_mut(
+            (SLOT_BASE + id as u32 * SLOT_BYTES) as *mut u16,
+            SLOT_PIXELS,
+        )
+    }
+}
+
+// Pickups look the same in every theme. The star and shrink art are their
+// own mirror images and kept in part.
+const COIN: Image = Image::new(PICKUP_SIZE, PICKUP_SIZE, &assets::COIN_IMG_DATA);
+const STAR: Image = Image::mirrored(
+    PICKUP_SIZE,
+    PICKUP_SIZE,
+    Mirror::ACROSS,
+    &assets::STAR_IMG_DATA,
+);
+const SHIELD: Image = Image::new(PICKUP_SIZE, PICKUP_SIZE, &assets::SHIELD_IMG_DATA);
+const SLOW: Image = Image::new(PICKUP_SIZE, PICKUP_SIZE, &assets::SLOW_IMG_DATA);
+const SHRINK: Image = Image::mirrored(
+    PICKUP_SIZE,
+    PICKUP_SIZE,
+    Mirror::BOTH,
+    &assets::SHRINK_IMG_DATA,
+);
+
+// A power-up's art, in the openings and on the HUD; not uploadable
+pub fn power_up(power: PowerUp) -> Image<'static> {
+    match power {
+        PowerUp::Shield => SHIELD,
+        PowerUp::SlowTime => SLOW,
+        PowerUp::Shrink => SHRINK,
+    }
+}
+
+// The active theme's art for a sprite
+fn flash_sprite(id: SpriteId) -> Option<Image<'static>> {
+    let theme = theme::current();
+    match id {
+        SpriteId::Bird => Some(theme.bird),
+        SpriteId::Plant => Some(theme.ground),
+        SpriteId::Obstacle => theme.pipe_tile,
+        SpriteId::Coin => Some(COIN),
+        SpriteId::Star => Some(STAR),
+        SpriteId::PipeCap => theme.pipe_cap_tile,
+    }
+}
+
+// Current art for a sprite: an uploaded override if any, else the theme's
+pub fn sprite(id: SpriteId) -> Option<Image<'static>> {
+    match unsafe { OVERRIDES[id as usize] } {
+        Some((w, h)) => Some(Image::new(w, h, &slot(id)[..(w * h) as usize])),
+        None => flash_sprite(id),
+    }
+}
+
+// Pipe body texture, if one has been uploaded or the theme has one
+pub fn obstacle_tile() -> Option<Image<'static>> {
+    sprite(SpriteId::Obstacle).filter(|tile| tile.w > 0 && tile.h > 0)
+}
+
+// Pipe cap texture, likewise
+pub fn cap_tile() -> Option<Image<'static>> {
+    sprite(SpriteId::PipeCap).filter(|tile| tile.w > 0 && tile.h > 0)
+}
+
+// Drop every override and go back to the flashed art
+pub fn revert_all() {
+    unsafe {
+        OVERRIDES = [None; SPRITE_COUNT];
+        UPLOAD = None;
+    }
+}
+
+// Handle one upload command from the telemetry link
+pub fn handle_upload(kind: u8, payload: &[u8]) -> UploadStatus {
+    use crate::telemetry::{KIND_SPRITE_BEGIN, KIND_SPRITE_DATA, KIND_SPRITE_END};
+
+    match kind {
+        KIND_SPRITE_BEGIN => begin(payload),
+        KIND_SPRITE_DATA => data(payload),
+        KIND_SPRITE_END => end(),
+        _ => UploadStatus::BadHeader,
+    }
+}
+
+fn begin(payload: &[u8]) -> UploadStatus {
+    let [id, w, h, c0, c1, c2, c3] = payload else {
+        return UploadStatus::BadHeader;
+    };
+    let Some(id) = SpriteId::from_u8(*id) else {
+        return UploadStatus::BadHeader;
+    };
+    let (w, h) = (*w as u32, *h as u32);
+    if w == 0 || h == 0 || w > MAX_SPRITE_W || h > MAX_SPRITE_H {
+        return UploadStatus::BadHeader;
+    }
+    // The slots are SDRAM
+    if !sdram::available() {
+        return UploadStatus::BadHeader;
+    }
+
+    unsafe {
+        // The slot is about to be overwritten, so stop drawing from it now
+        OVERRIDES[id as usize] = None;
+        sprite_cache::clear();
+        UPLOAD = Some(Upload {
+            id,
+            w,
+            h,
+            crc: u32::from_le_bytes([*c0, *c1, *c2, *c3]),
+        });
+    }
+    UploadStatus::Ok
+}
+
+fn data(payload: &[u8]) -> UploadStatus {
+    let Some(upload) = (unsafe { UPLOAD }) else {
+        return UploadStatus::NotStarted;
+    };
+    let [o0, o1, bytes @ ..] = payload else {
+        return UploadStatus::BadOffset;
+    };
+    let offset = u16::from_le_bytes([*o0, *o1]) as usize;
+    let size = (upload.w * upload.h * 2) as usize;
+    if !offset.is_multiple_of(2) || !bytes.len().is_multiple_of(2) || offset + bytes.len() > size {
+        return UploadStatus::BadOffset;
+    }
+
+    let pixels = &mut slot(upload.id)[offset / 2..(offset + bytes.len()) / 2];
+    for (pixel, pair) in pixels.iter_mut().zip(bytes.chunks_exact(2)) {
+        *pixel = u16::from_le_bytes([pair[0], pair[1]]);
+    }
+    UploadStatus::Ok
+}
+
+fn end() -> UploadStatus {
+    let Some(upload) = (unsafe { UPLOAD.take() }) else {
+        return UploadStatus::NotStarted;
+    };
+    let pixels = &slot(upload.id)[..(upload.w * upload.h) as usize];
+    if crc32(pixels) != upload.crc {
+        return UploadStatus::CrcMismatch;
+    }
+
+    unsafe { OVERRIDES[upload.id as usize] = Some((upload.w, upload.h)) };
+    sprite_cache::clear();
+    UploadStatus::Ok
+}
+
+// CRC-32 (IEEE, as zlib.crc32) over the pixels' little-endian bytes
+fn crc32(pixels: &[u16]) -> u32 {
+    crc::crc32(pixels.iter().flat_map(|p| p.to_le_bytes()))
+}
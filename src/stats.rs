@@ -0,0 +1,280 @@
+//! Persistent stats store in the 4K battery-backed SRAM
+//!
+//! Survives resets and reflashing as long as VBAT stays powered (on the DISCO
+//! board VBAT is tied to VDD, so it survives everything but a power cut). The
+//! record carries a magic number and checksum; anything else found there is
+//! treated as an empty store. It is kept twice over with a sequence number
+//! (core_logic::journal::Slots) and each save overwrites the older copy, so
+//! a reset part way through a save leaves the one before it to read.
+//!
+//! The leaderboard, the ten best runs with the player's initials, has a
+//! record of its own after the stats, so either can be read back without
+//! the other; time attack runs, scored against the clock, have a second
+//! one after it (`Board`). The black box (see blackbox) keeps its ring
+//! further up.
+#![allow(dead_code)]
+
+use core_logic::journal::Slots;
+use core_logic::leaderboard::{Entry, Table, INITIALS};
+
+use crate::error::HwError;
+use crate::flash;
+use crate::profiler::FrameSummary;
+use crate::resources;
+use crate::rtc;
+use crate::subsystem::{Health, Subsystem};
+
+pub const BKPSRAM_BASE: u32 = 0x4002_4000;
+const MAGIC: u32 = 0x5354_4134; // "STA4"
+const BOARD_MAGIC: u32 = 0x544F_5031; // "TOP1"
+                                      // Where the leaderboard record starts, clear of the stats record
+const BOARD_OFFSET: u32 = 0x100;
+// Where the time attack leaderboard starts, after both copies of the other
+const TIME_ATTACK_BOARD_OFFSET: u32 = 0x280;
+
+/// Which leaderboard a run goes on
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Board {
+    // Every solo run but a time attack one
+    Main,
+    TimeAttack,
+}
+
+impl Board {
+    fn offset(self) -> u32 {
+        match self {
+            Board::Main => BOARD_OFFSET,
+            Board::TimeAttack => TIME_ATTACK_BOARD_OFFSET,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Stats {
+    // Games played to the end (the attract-mode demo does not count)
+    pub sessions: u32,
+    // Frame times of the most recent finished session
+    pub last_frames: FrameSummary,
+    // Total time spent in running games
+    pub play_seconds: u32,
+    pub best_score: u32,
+    // When the best score was set, rtc timestamp (0 = never)
+    pub best_at: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Record {
+    magic: u32,
+    stats: Stats,
+    checksum: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct BoardRecord {
+    magic: u32,
+    table: Table,
+    // Initials entered last, to start the next entry from
+    last: [u8; INITIALS],
+    checksum: u32,
+}
+
+// One of the two copies of a record. The record comes first, so copy 0 is
+// where firmware keeping a single copy had the record.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct Kept<T> {
+    record: T,
+    seq: u32,
+}
+
+const _: () = assert!(2 * core::mem::size_of::<Kept<Record>>() as u32 <= BOARD_OFFSET);
+const _: () = assert!(
+    BOARD_OFFSET + 2 * core::mem::size_of::<Kept<BoardRecord>>() as u32 <= TIME_ATTACK_BOARD_OFFSET
+);
+const _: () = assert!(
+    TIME_ATTACK_BOARD_OFFSET + 2 * core::mem::size_of::<Kept<BoardRecord>>() as u32
+        <= crate::blackbox::OFFSET
+);
+
+// Enable access to backup SRAM; call once at boot
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.apb1enr.modify(|_, w| w.pwren().enabled());
+    dp.PWR.cr.modify(|_, w| w.dbp().set_bit());
+    dp.RCC.ahb1enr.modify(|_, w| w.bkpsramen().enabled());
+    // Keep the contents on VBAT alone
+    dp.PWR.csr.modify(|_, w| w.bre().set_bit());
+    while dp.PWR.csr.read().brr().bit_is_clear() {}
+}
+
+fn kept_ptr<T>(offset: u32, copy: usize) -> *mut Kept<T> {
+    ((BKPSRAM_BASE + offset) as *mut Kept<T>).wrapping_add(copy)
+}
+
+fn read_copy<T: Copy>(offset: u32, copy: usize) -> Kept<T> {
+    // SAFETY: backup SRAM is enabled by init, and both copies of each
+    // record are inside the part reserved for it
+    unsafe { core::ptr::read_volatile(kept_ptr(offset, copy)) }
+}
+
+// The sequence number of each copy at `offset` whose record is `valid`
+fn seqs<T: Copy>(offset: u32, valid: impl Fn(&T) -> bool) -> [Option<u32>; 2] {
+    [0, 1].map(|copy| {
+        let kept = read_copy::<T>(offset, copy);
+        valid(&kept.record).then_some(kept.seq)
+    })
+}
+
+// The newest valid copy of the record at `offset`
+fn read_kept<T: Copy>(offset: u32, valid: impl Fn(&T) -> bool) -> Option<T> {
+    let [a, b] = seqs(offset, valid);
+    Some(read_copy::<T>(offset, Slots::newest(a, b)?).record)
+}
+
+// Write `record` over the older copy at `offset`
+fn write_kept<T: Copy>(offset: u32, record: T, valid: impl Fn(&T) -> bool) {
+    let [a, b] = seqs(offset, valid);
+    let (copy, seq) = Slots::next(a, b);
+    // SAFETY: as in read_copy
+    unsafe { core::ptr::write_volatile(kept_ptr(offset, copy), Kept { record, seq }) };
+    cortex_m::asm::dsb();
+}
+
+fn record_valid(record: &Record) -> bool {
+    record.magic == MAGIC && record.checksum == checksum(&record.stats)
+}
+
+fn checksum(stats: &Stats) -> u32 {
+    let f = &stats.last_frames;
+    let words = [
+        stats.sessions,
+        f.frames,
+        u32::from_le_bytes([f.p50_ms, f.p95_ms, f.p99_ms, f.max_ms]),
+        stats.play_seconds,
+        stats.best_score,
+        stats.best_at,
+    ];
+    words.iter().fold(MAGIC, |acc, &w| acc.rotate_left(5) ^ w)
+}
+
+// Stored stats, or defaults if the store was never written or is corrupt
+pub fn load() -> Stats {
+    read_kept(0, record_valid).map_or(Stats::default(), |record| record.stats)
+}
+
+pub fn save(stats: &Stats) {
+    let record = Record {
+        magic: MAGIC,
+        stats: *stats,
+        checksum: checksum(stats),
+    };
+    write_kept(0, record, record_valid);
+}
+
+// Fold a finished session into the store
+pub fn record_session(frames: FrameSummary, score: u32, play_ms: u32) {
+    let mut stats = load();
+    stats.sessions = stats.sessions.wrapping_add(1);
+    stats.last_frames = frames;
+    stats.play_seconds = stats.play_seconds.saturating_add(play_ms / 1000);
+    if score > stats.best_score {
+        stats.best_score = score;
+        stats.best_at = rtc::timestamp();
+    }
+    save(&stats);
+}
+
+// Forget the best score, keeping the rest
+pub fn reset_best() {
+    let mut stats = load();
+    stats.best_score = 0;
+    stats.best_at = 0;
+    save(&stats);
+}
+
+fn board_checksum(table: &Table, last: [u8; INITIALS]) -> u32 {
+    let letters = |l: [u8; INITIALS]| u32::from_le_bytes([l[0], l[1], l[2], 0]);
+    let entries = table
+        .entries()
+        .iter()
+        .flat_map(|entry| [entry.score, letters(entry.initials), entry.at]);
+    let words = [table.entries().len() as u32, letters(last)];
+    words
+        .into_iter()
+        .chain(entries)
+        .fold(BOARD_MAGIC, |acc, w| acc.rotate_left(5) ^ w)
+}
+
+fn board_valid(record: &BoardRecord) -> bool {
+    record.magic == BOARD_MAGIC
+        && record.table.is_valid()
+        && record.checksum == board_checksum(&record.table, record.last)
+}
+
+fn load_board(board: Board) -> Option<BoardRecord> {
+    read_kept(board.offset(), board_valid)
+}
+
+// Stored leaderboard, or an empty one
+pub fn leaderboard(board: Board) -> Table {
+    load_board(board).map_or(Table::new(), |record| record.table)
+}
+
+// Initials entered for the last run that made the leaderboard
+pub fn last_initials(board: Board) -> [u8; INITIALS] {
+    load_board(board).map_or([b'A'; INITIALS], |record| record.last)
+}
+
+fn save_board(board: Board, table: &Table, last: [u8; INITIALS]) {
+    let record = BoardRecord {
+        magic: BOARD_MAGIC,
+        table: *table,
+        last,
+        checksum: board_checksum(table, last),
+    };
+    write_kept(board.offset(), record, board_valid);
+}
+
+// Put a run on the leaderboard, dated now; its rank, or None if it did not
+// make it
+pub fn record_run(board: Board, score: u32, initials: [u8; INITIALS]) -> Option<usize> {
+    let mut table = leaderboard(board);
+    let rank = table.insert(Entry {
+        score,
+        initials,
+        at: rtc::timestamp(),
+    })?;
+    save_board(board, &table, initials);
+    Some(rank)
+}
+
+// Empty the leaderboard
+pub fn clear_leaderboard(board: Board) {
+    save_board(board, &Table::new(), last_initials(board));
+}
+
+pub struct StorageSubsystem;
+
+impl Subsystem for StorageSubsystem {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        init();
+        flash::init();
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        let dp = resources::pac();
+        if dp.PWR.csr.read().brr().bit_is_set() {
+            Health::Ok
+        } else {
+            Health::Failed("backup regulator off")
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Player stats page, opened with a long press on the title screen
+//!
+//! Drawn on the Layer 2 overlay like the pause menu, from the persistent
+//! stats store and the RTC, plus the tilt sensor's temperature when it is
+//! fitted.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use crate::config::Coord;
+use crate::fmt_buf::FmtBuf;
+use crate::lang::{self, Msg};
+use crate::mpu6050;
+use crate::rtc::{self, DateTime};
+use crate::stats;
+use crate::ui::Ui;
+
+// Low enough for the six values and both hints to fit
+const TOP: Coord = 24;
+
+pub fn draw() {
+    let mut ui = Ui::screen(TOP);
+    ui.label(lang::text(Msg::Stats));
+
+    let stats = stats::load();
+    let mut line: FmtBuf<32> = FmtBuf::new();
+
+    let _ = write!(line, "{}", stats.sessions);
+    ui.value(lang::text(Msg::GamesPlayed), line.as_str());
+    line.clear();
+    let t = stats.play_seconds;
+    let _ = write!(line, "{}:{:02}:{:02}", t / 3600, t / 60 % 60, t % 60);
+    ui.value(lang::text(Msg::PlayTime), line.as_str());
+    line.clear();
+    let _ = write!(line, "{}", stats.best_score);
+    ui.value(lang::text(Msg::BestScore), line.as_str());
+    line.clear();
+    if stats.best_at != 0 {
+        let at = DateTime::from_timestamp(stats.best_at);
+        let _ = write!(
+            line,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            at.year, at.month, at.day, at.hour, at.minute
+        );
+    } else {
+        let _ = write!(line, "-");
+    }
+    ui.value(lang::text(Msg::BestSetOn), line.as_str());
+    line.clear();
+    let now = rtc::now();
+    let _ = write!(line, "{:02}:{:02}", now.hour, now.minute);
+    ui.value(lang::text(Msg::Time), line.as_str());
+    // Only with the tilt sensor fitted
+    if let Some(centi_c) = mpu6050::temperature() {
+        line.clear();
+        let sign = if centi_c < 0 { "-" } else { "" };
+        let centi_c = centi_c.unsigned_abs();
+        let _ = write!(line, "{}{}.{} C", sign, centi_c / 100, centi_c / 10 % 10);
+        ui.value(lang::text(Msg::SensorTemp), line.as_str());
+    }
+    ui.caption(lang::text(Msg::PressForTopTen));
+    ui.caption(lang::text(Msg::HoldForEditor));
+
+    ui.end();
+}
@@ -0,0 +1,101 @@
+//! Settings and courses in flash, kept safe from a reset mid-save
+//!
+//! `flash::STORE`'s two sectors hold a `core_logic::journal`: the settings
+//! are one kind of record, each course slot another, each player profile
+//! (`profiles`) another. A save appends a
+//! record with a sequence number and a CRC, and moves everything to the
+//! other sector once the one in use is full, leaving the full one as it was
+//! until the time after; a reset, watchdog or brownout at any point leaves
+//! the last save or the one before it to read back. See `flash` for how the
+//! words themselves are programmed.
+//!
+//! Firmware from before the store kept the courses in sector A and the
+//! settings in sector B in blocks of their own; `settings` and `courses`
+//! still read those if the store has nothing for them, and the first time
+//! the store moves sectors it takes them from what those modules loaded.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::journal::{Journal, JournalError, Sectors};
+use core_logic::profile;
+
+use crate::courses;
+use crate::crc;
+use crate::flash;
+use crate::log;
+use crate::settings;
+
+pub use core_logic::journal::MAX_PAYLOAD;
+
+// The settings, then a kind for each course slot, then one for each
+// profile
+pub const SETTINGS: usize = 0;
+const KINDS: usize = 1 + courses::SLOTS + profile::SLOTS;
+
+pub const fn course(slot: usize) -> usize {
+    1 + slot % courses::SLOTS
+}
+
+pub const fn profile(slot: usize) -> usize {
+    1 + courses::SLOTS + slot % profile::SLOTS
+}
+
+struct FlashPair;
+
+impl Sectors for FlashPair {
+    fn words(&self) -> u32 {
+        flash::SECTOR_SIZE / 4
+    }
+
+    fn read(&self, sector: usize, index: u32) -> u32 {
+        // SAFETY: STORE is internal flash outside the program image and
+        // always readable; the journal keeps `index` inside the sector
+        unsafe { core::ptr::read_volatile((flash::STORE[sector].base + index * 4) as *const u32) }
+    }
+
+    fn erase(&mut self, sector: usize) -> Result<(), JournalError> {
+        flash::erase_store(sector).map_err(|_| JournalError::Flash)
+    }
+
+    fn program(&mut self, sector: usize, index: u32, words: &[u32]) -> Result<(), JournalError> {
+        let addr = flash::STORE[sector].base + index * 4;
+        flash::program(addr, words.iter().copied()).map_err(|_| JournalError::Flash)
+    }
+
+    fn crc(&self, words: &[u32]) -> u32 {
+        crc::hw_crc32(words)
+    }
+}
+
+static mut JOURNAL: Journal<FlashPair, KINDS> = Journal::new(FlashPair);
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Journal<FlashPair, KINDS>>();
+
+// The newest saved payload of `kind` into `payload`, and its length; None
+// if nothing intact was ever saved for it
+pub fn read(kind: usize, payload: &mut [u32]) -> Option<usize> {
+    unsafe { JOURNAL.read(kind, payload) }
+}
+
+// Save `payload` as the newest of `kind`
+pub fn write(kind: usize, payload: &[u32]) -> Result<(), ()> {
+    unsafe { JOURNAL.write(kind, payload, current) }.map_err(|error| {
+        log::error!("store write failed: {:?}", error);
+    })
+}
+
+// What the game holds for `kind`, for kinds the store has no record of yet
+fn current(kind: usize, payload: &mut [u32]) -> Option<usize> {
+    match kind {
+        SETTINGS => settings::payload(&settings::get(), payload),
+        // Profiles were never kept anywhere but the store
+        kind if kind > courses::SLOTS => None,
+        _ => courses::payload(kind - 1, payload),
+    }
+}
+
+// The sector saves go to, for the shell
+pub fn active() -> Option<usize> {
+    unsafe { JOURNAL.scan() }.active()
+}
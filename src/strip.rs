@@ -0,0 +1,62 @@
+//! Water or lava along the bottom of the ground
+//!
+//! Themes with a `strip` give the lowest `ROWS` rows of each lane's ground
+//! to a palette-cycled band (see `core_logic::cycle`); the plants stop
+//! above it. On an indexed layer LTDC would turn the palette for nothing by
+//! rewriting the CLUT, but Layer 1 is true color, so instead the band is
+//! repainted from its indices whenever the phase moves: a remap pass over
+//! a few rows, a handful of times a second.
+#![allow(dead_code)]
+
+use core_logic::cycle::Cycle;
+
+use crate::clock;
+use crate::config::{Coord, Rect, LCD_WIDTH};
+use crate::entity::Renderer;
+use crate::framebuffer::Image;
+use crate::lane::Lane;
+use crate::theme;
+
+pub const ROWS: Coord = 6;
+
+fn cycle() -> Option<Cycle> {
+    theme::current().strip
+}
+
+// The band in `lane`, if the theme has one
+pub fn rect(lane: Lane) -> Option<Rect> {
+    cycle()?;
+    Some(Rect::new(
+        0,
+        lane.y(lane.height - ROWS),
+        LCD_WIDTH,
+        ROWS as u32,
+    ))
+}
+
+// The part of `rect` above the band
+pub fn above(lane: Lane, rect: Rect) -> Option<Rect> {
+    let Some(band) = self::rect(lane) else {
+        return Some(rect);
+    };
+    let bottom = (rect.y + rect.h as Coord).min(band.y);
+    (bottom > rect.y).then(|| Rect::new(rect.x, rect.y, rect.w, (bottom - rect.y) as u32))
+}
+
+// The phase the band should show now
+pub fn phase() -> Option<usize> {
+    cycle().map(|cycle| cycle.phase(clock::millis()))
+}
+
+// Paint the band in `lane` at `phase`, a row at a time
+pub fn draw(renderer: &mut Renderer, lane: Lane, phase: usize) {
+    let (Some(cycle), Some(band)) = (cycle(), rect(lane)) else {
+        return;
+    };
+    let mut colors = [0u16; LCD_WIDTH as usize];
+    for row in 0..ROWS {
+        cycle.fill_row(row, phase, &mut colors);
+        let image = Image::new(LCD_WIDTH, 1, &colors);
+        renderer.draw_image(band.x, band.y + row, &image);
+    }
+}
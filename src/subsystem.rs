@@ -0,0 +1,172 @@
+//! Common lifecycle for the board's subsystems
+//!
+//! Each subsystem module provides a unit struct implementing `Subsystem`;
+//! `REGISTRY` lists them in bring-up order. Boot, diagnostics, the
+//! supervisor and anything that needs to quiet the hardware (sleep, fault
+//! handling) walk the same list instead of calling each module's init/stop
+//! functions by hand.
+//!
+//! The supervisor (`supervise`, the scheduler's `Supervise` job) checks
+//! every subsystem's health every few seconds and restarts one that has
+//! failed, up to `MAX_RESTARTS` times, so one that cannot come back is
+//! left alone instead of being restarted forever.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::audio::AudioSubsystem;
+use crate::boot_report;
+use crate::button::InputSubsystem;
+use crate::display::DisplaySubsystem;
+use crate::error::HwError;
+use crate::iwdg;
+use crate::log;
+use crate::mpu6050::SensorSubsystem;
+use crate::resources::ThreadOnly;
+use crate::rtc::RtcSubsystem;
+use crate::serial::SerialSubsystem;
+use crate::stats::StorageSubsystem;
+use crate::usb::UsbSubsystem;
+use crate::wifi::WifiSubsystem;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Health {
+    Ok,
+    // Not built in or not wanted in this configuration
+    Disabled,
+    // Working, but not as configured
+    Degraded(&'static str),
+    Failed(&'static str),
+}
+
+impl Health {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Health::Ok => "ok",
+            Health::Disabled => "off",
+            Health::Degraded(why) | Health::Failed(why) => why,
+        }
+    }
+}
+
+pub trait Subsystem: Sync {
+    fn name(&self) -> &'static str;
+    fn init(&self) -> Result<(), HwError>;
+    fn health_check(&self) -> Health {
+        Health::Ok
+    }
+    // Stop drawing power / generating traffic, keeping configuration
+    fn suspend(&self) {}
+    // Undo suspend
+    fn resume(&self) {}
+    // Quiesce for good (reset, fault); by default the same as suspend
+    fn shutdown(&self) {
+        self.suspend();
+    }
+    // Bring it back after it failed; by default shut down and start over
+    fn restart(&self) -> Result<(), HwError> {
+        self.shutdown();
+        self.init()
+    }
+}
+
+pub const SUBSYSTEM_COUNT: usize = 9;
+
+// Bring-up order: storage first so boot can record into it, then the RTC
+// that timestamps it, display before anything that may want to report on
+// screen
+static REGISTRY: [&dyn Subsystem; SUBSYSTEM_COUNT] = [
+    &StorageSubsystem,
+    &RtcSubsystem,
+    &DisplaySubsystem,
+    &SensorSubsystem,
+    &InputSubsystem,
+    &AudioSubsystem,
+    &SerialSubsystem,
+    &UsbSubsystem,
+    &WifiSubsystem,
+];
+
+pub fn registry() -> &'static [&'static dyn Subsystem] {
+    &REGISTRY
+}
+
+// Restarts the supervisor gives a failed subsystem before leaving it
+pub const MAX_RESTARTS: u8 = 3;
+
+// Restarts so far, in `REGISTRY` order
+static RESTARTS: ThreadOnly<[u8; SUBSYSTEM_COUNT]> = ThreadOnly::of([0; SUBSYSTEM_COUNT]);
+
+// Restart every subsystem whose health check says it failed and has
+// restarts left. A restart may take longer than a frame (the RTC waits up
+// to two seconds for its crystal), so the watchdog is fed around it.
+// Returns the number restarted.
+pub fn supervise() -> usize {
+    let mut restarted = 0;
+    for (i, subsystem) in REGISTRY.iter().enumerate() {
+        let Health::Failed(why) = subsystem.health_check() else {
+            continue;
+        };
+        let Some(attempt) = RESTARTS
+            .with(|restarts| {
+                let attempt = restarts[i].checked_add(1).filter(|&n| n <= MAX_RESTARTS);
+                restarts[i] = attempt.unwrap_or(restarts[i]);
+                attempt
+            })
+            .flatten()
+        else {
+            continue;
+        };
+        log::warn!(
+            "{} failed ({}), restart {} of {}",
+            subsystem.name(),
+            why,
+            attempt,
+            MAX_RESTARTS
+        );
+        iwdg::feed();
+        if let Err(error) = subsystem.restart() {
+            log::warn!("{} restart: {}", subsystem.name(), error);
+        }
+        iwdg::restart_interval();
+        restarted += 1;
+    }
+    restarted
+}
+
+// Restarts the supervisor has made of each subsystem, in `registry` order
+pub fn restarts() -> [u8; SUBSYSTEM_COUNT] {
+    RESTARTS.with(|restarts| *restarts).unwrap_or_default()
+}
+
+// Initialize every subsystem in order. A failure goes into the boot report
+// and bring-up continues, as the game can run without most of them. Returns
+// the number that failed.
+pub fn init_all() -> usize {
+    let mut failed = 0;
+    for subsystem in REGISTRY.iter() {
+        if let Err(error) = subsystem.init() {
+            boot_report::record(subsystem.name(), error);
+            failed += 1;
+        }
+    }
+    failed
+}
+
+// Suspend in reverse bring-up order
+pub fn suspend_all() {
+    for subsystem in REGISTRY.iter().rev() {
+        subsystem.suspend();
+    }
+}
+
+pub fn resume_all() {
+    for subsystem in REGISTRY.iter() {
+        subsystem.resume();
+    }
+}
+
+pub fn shutdown_all() {
+    for subsystem in REGISTRY.iter().rev() {
+        subsystem.shutdown();
+    }
+}
@@ -0,0 +1,44 @@
+//! Resetting the board, and starting the game over without it
+//!
+//! `soft_reset` is a clean reboot. The subsystems are shut down in reverse
+//! bring-up order, which puts the panel to sleep and stops the LTDC reading
+//! the framebuffers; the I2C buses are released and switched off, and the
+//! SDRAM is parked in self-refresh so no access is cut off part way when
+//! the core resets through the NVIC. The boot after it is an ordinary one.
+//!
+//! A warm restart starts only the game over: a fresh `Game` on the input
+//! device already up, with the sprite cache emptied so the art is made
+//! again as it is drawn. Clocks, SDRAM, the display, the sensors and the
+//! settings stay as they are. The debug menu and the shell ask for one;
+//! the game task carries it out between frames, where nothing is holding
+//! the game.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use crate::backlight;
+use crate::i2c;
+use crate::log;
+use crate::sdram;
+use crate::subsystem;
+
+static mut WARM_RESTART: bool = false;
+
+// Reset the board, with the hardware quiet first
+pub fn soft_reset() -> ! {
+    log::info!("soft reset");
+    backlight::set_brightness(0);
+    subsystem::shutdown_all();
+    i2c::shutdown();
+    sdram::enter_self_refresh();
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+// Start the game over at the end of this frame
+pub fn request_warm_restart() {
+    unsafe { WARM_RESTART = true };
+}
+
+// True once per request; for the game task
+pub fn take_warm_restart() -> bool {
+    unsafe { core::mem::take(&mut WARM_RESTART) }
+}
@@ -0,0 +1,199 @@
+//! Telemetry protocol to a host PC over the serial link
+//!
+//! Every message is framed as
+//!
+//! ```text
+//! SYNC (0xA5) | kind | len | payload[len] | xor of kind, len and payload
+//! ```
+//!
+//! Multi-byte payload fields are little-endian. Frames share the line with
+//! the text console: `shell::poll` offers each received byte to `feed` first,
+//! and anything outside a frame goes to the shell.
+//!
+//! A host that sends `KIND_STATUS_ON` with a payload of 1 gets a
+//! `KIND_STATUS` frame once a second from then on (the scheduler's telemetry
+//! job), until it sends 0: `state u8 | score u16 | fps u8 | uptime_s u32`,
+//! the state a `GameState` in declaration order.
+//!
+//! `KIND_REPLAY_REQ` asks for the last run; it comes back as `KIND_REPLAY`
+//! chunks and a `KIND_REPLAY_END` (see replay).
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::state::GameState;
+
+use crate::clock;
+use crate::profiler;
+use crate::serial;
+
+pub const SYNC: u8 = 0xA5;
+
+// Device -> host
+pub const KIND_GAME_STATE: u8 = 0x01;
+
+pub const KIND_SPRITE_ACK: u8 = 0x02;
+// One presented frame while recording; see frame_record
+pub const KIND_FRAME: u8 = 0x03;
+// A game event for the host agent; see agent
+pub const KIND_GAME_EVENT: u8 = 0x04;
+// Once a second while the host asks for it
+pub const KIND_STATUS: u8 = 0x05;
+// Part of the last run's replay, then its length and checksum; see replay
+pub const KIND_REPLAY: u8 = 0x06;
+pub const KIND_REPLAY_END: u8 = 0x07;
+
+// Host -> device
+pub const KIND_FLAP: u8 = 0x81;
+pub const KIND_SPRITE_BEGIN: u8 = 0x82;
+pub const KIND_SPRITE_DATA: u8 = 0x83;
+pub const KIND_SPRITE_END: u8 = 0x84;
+pub const KIND_STATUS_ON: u8 = 0x85;
+pub const KIND_REPLAY_REQ: u8 = 0x86;
+
+pub const MAX_PAYLOAD: usize = 64;
+
+#[derive(Copy, Clone, PartialEq)]
+enum RxState {
+    Sync,
+    Kind,
+    Len,
+    Payload,
+    Check,
+}
+
+struct Receiver {
+    state: RxState,
+    kind: u8,
+    len: usize,
+    pos: usize,
+    check: u8,
+    payload: [u8; MAX_PAYLOAD],
+}
+
+static mut RX: Receiver = Receiver {
+    state: RxState::Sync,
+    kind: 0,
+    len: 0,
+    pos: 0,
+    check: 0,
+    payload: [0; MAX_PAYLOAD],
+};
+
+// Flap commands received but not yet consumed by the game
+static mut PENDING_FLAPS: u8 = 0;
+// The host asked for status frames
+static mut STATUS_ON: bool = false;
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize =
+    core::mem::size_of::<Receiver>() + core::mem::size_of::<u8>() + core::mem::size_of::<bool>();
+
+// Send one framed message. Payloads longer than MAX_PAYLOAD are truncated.
+pub fn send(kind: u8, payload: &[u8]) {
+    let payload = &payload[..payload.len().min(MAX_PAYLOAD)];
+    let len = payload.len() as u8;
+    let check = payload.iter().fold(kind ^ len, |check, &byte| check ^ byte);
+
+    serial::write(&[SYNC, kind, len]);
+    serial::write(payload);
+    serial::write(&[check]);
+}
+
+// Offer one received byte to the frame parser. Returns false when the byte
+// is not part of a frame, so the caller can hand it to someone else.
+pub fn feed(byte: u8) -> bool {
+    let rx = unsafe { &mut RX };
+    if rx.state == RxState::Sync && byte != SYNC {
+        return false;
+    }
+    if let Some(kind) = rx.push(byte) {
+        handle(kind, &rx.payload[..rx.len]);
+    }
+    true
+}
+
+#[cfg_attr(not(feature = "sprite-reload"), allow(unused_variables))]
+fn handle(kind: u8, payload: &[u8]) {
+    match kind {
+        KIND_FLAP => unsafe { PENDING_FLAPS = PENDING_FLAPS.saturating_add(1) },
+        KIND_STATUS_ON => unsafe { STATUS_ON = payload.first() == Some(&1) },
+        KIND_REPLAY_REQ => crate::replay::request(),
+        #[cfg(feature = "sprite-reload")]
+        KIND_SPRITE_BEGIN | KIND_SPRITE_DATA | KIND_SPRITE_END => {
+            let status = crate::sprites::handle_upload(kind, payload);
+            send(KIND_SPRITE_ACK, &[kind, status as u8]);
+        }
+        _ => {}
+    }
+}
+
+// A status frame, if the host asked for them
+pub fn send_status(state: GameState, score: u32) {
+    if !unsafe { STATUS_ON } {
+        return;
+    }
+    let mut payload = [0u8; 8];
+    payload[0] = state as u8;
+    payload[1..3].copy_from_slice(&(score.min(u16::MAX as u32) as u16).to_le_bytes());
+    payload[3] = profiler::breakdown().fps().min(u8::MAX as u32) as u8;
+    payload[4..8].copy_from_slice(&(clock::millis() / 1000).to_le_bytes());
+    send(KIND_STATUS, &payload);
+}
+
+// Consume one flap command from the host, if any arrived
+pub fn take_flap() -> bool {
+    unsafe {
+        if PENDING_FLAPS > 0 {
+            PENDING_FLAPS -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Receiver {
+    // Feed one byte; returns the frame kind once a frame with a valid checksum
+    // has been received
+    fn push(&mut self, byte: u8) -> Option<u8> {
+        match self.state {
+            RxState::Sync => {
+                if byte == SYNC {
+                    self.state = RxState::Kind;
+                }
+            }
+            RxState::Kind => {
+                self.kind = byte;
+                self.check = byte;
+                self.state = RxState::Len;
+            }
+            RxState::Len => {
+                self.len = byte as usize;
+                self.pos = 0;
+                self.check ^= byte;
+                self.state = if self.len > MAX_PAYLOAD {
+                    RxState::Sync
+                } else if self.len == 0 {
+                    RxState::Check
+                } else {
+                    RxState::Payload
+                };
+            }
+            RxState::Payload => {
+                self.payload[self.pos] = byte;
+                self.pos += 1;
+                self.check ^= byte;
+                if self.pos == self.len {
+                    self.state = RxState::Check;
+                }
+            }
+            RxState::Check => {
+                self.state = RxState::Sync;
+                if byte == self.check {
+                    return Some(self.kind);
+                }
+            }
+        }
+        None
+    }
+}
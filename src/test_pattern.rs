@@ -0,0 +1,97 @@
+//! Test patterns on the panel, for bringing up a new one
+//!
+//! Puts one of `core_logic::test_pattern`'s patterns on both Layer 1
+//! buffers, with Layer 2 out of the way, and keeps it there until it is
+//! taken down; while one is up the game does nothing else (see
+//! `Game::update`). A press or the knob steps through them, a long press or
+//! a touch goes back to the title screen. Started from the debug menu or by
+//! `pattern` in the shell, and only with LTDC scanning out Layer 1: the SPI
+//! renderer has no framebuffer to show.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::test_pattern::{self as patterns, Pattern, BOX_COLOR};
+
+use crate::color::Argb8888;
+use crate::config::Rect;
+use crate::display::{self, Backend};
+use crate::draw;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::hud;
+
+struct Shown {
+    pattern: Pattern,
+    frame: u32,
+    // Where the moving box was last drawn
+    drawn: Option<Rect>,
+}
+
+static mut SHOWN: Option<Shown> = None;
+
+pub fn available() -> bool {
+    display::backend() == Backend::Ltdc
+}
+
+// Put `pattern` up in place of the game
+pub fn show(pattern: Pattern) -> Result<(), ()> {
+    if !available() {
+        return Err(());
+    }
+    // Layer 2 back to a hidden sprite, Layer 1 unshaken and full strength
+    hud::hide();
+    let _ = display::set_frame_offset(0, 0);
+    let _ = display::set_brightness(0xFF);
+    draw::layer1_pattern(pattern);
+    unsafe {
+        SHOWN = Some(Shown {
+            pattern,
+            frame: 0,
+            drawn: None,
+        })
+    };
+    Ok(())
+}
+
+pub fn hide() {
+    unsafe { SHOWN = None };
+}
+
+pub fn shown() -> Option<Pattern> {
+    unsafe { SHOWN.as_ref().map(|shown| shown.pattern) }
+}
+
+pub fn next() {
+    if let Some(pattern) = shown() {
+        let _ = show(pattern.next());
+    }
+}
+
+pub fn prev() {
+    if let Some(pattern) = shown() {
+        let _ = show(pattern.prev());
+    }
+}
+
+// One frame: moves the box on, for the pattern that has one
+pub fn tick() {
+    let Some(shown) = (unsafe { SHOWN.as_mut() }) else {
+        return;
+    };
+    if !shown.pattern.is_animated() {
+        return;
+    }
+    let (w, h) = FrameBuffer::layer1().game_size();
+    let at = patterns::box_at(shown.frame, w, h);
+    let background = Argb8888(shown.pattern.argb(0, 0, w, h));
+    for mut fb in [FrameBuffer::layer1(), FrameBuffer::layer1_back()] {
+        if let Some(old) = shown.drawn {
+            let background = fb.encode_argb(background);
+            fb.fill_rect(old.x, old.y, old.w, old.h, background);
+        }
+        let color = fb.encode_argb(Argb8888(BOX_COLOR));
+        fb.fill_rect(at.x, at.y, at.w, at.h, color);
+    }
+    framebuffer::flush();
+    shown.drawn = Some(at);
+    shown.frame = shown.frame.wrapping_add(1);
+}
@@ -0,0 +1,104 @@
+//! Text in the proportional fonts, through embedded-graphics
+//!
+//! `PropTextStyle` is a `TextRenderer`, so `Text` and its alignment and
+//! baselines work with a `PropFont` just as with the mono fonts.
+#![allow(dead_code)]
+
+use core_logic::font::PropFont;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::renderer::{CharacterStyle, TextMetrics, TextRenderer};
+use embedded_graphics::text::Baseline;
+
+#[derive(Copy, Clone)]
+pub struct PropTextStyle {
+    pub font: &'static PropFont,
+    pub color: Rgb565,
+}
+
+impl PropTextStyle {
+    pub const fn new(font: &'static PropFont, color: Rgb565) -> Self {
+        PropTextStyle { font, color }
+    }
+
+    // Rows from the top of the glyph cells down to `position`
+    fn top(&self, position: Point, baseline: Baseline) -> Point {
+        let height = self.font.height as i32;
+        let down = match baseline {
+            Baseline::Top => 0,
+            Baseline::Bottom => height - 1,
+            Baseline::Middle => (height - 1) / 2,
+            Baseline::Alphabetic => self.font.ascent as i32,
+        };
+        position - Point::new(0, down)
+    }
+}
+
+impl CharacterStyle for PropTextStyle {
+    type Color = Rgb565;
+
+    fn set_text_color(&mut self, color: Option<Rgb565>) {
+        if let Some(color) = color {
+            self.color = color;
+        }
+    }
+}
+
+impl TextRenderer for PropTextStyle {
+    type Color = Rgb565;
+
+    fn draw_string<D>(
+        &self,
+        text: &str,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let mut pen = self.top(position, baseline);
+        for ch in text.chars() {
+            let Some(glyph) = self.font.glyph_or_fallback(ch) else {
+                continue;
+            };
+            let origin = pen;
+            target.draw_iter(
+                self.font
+                    .pixels(glyph)
+                    .map(|(x, y)| Pixel(origin + Point::new(x as i32, y as i32), self.color)),
+            )?;
+            pen.x += glyph.advance as i32;
+        }
+        Ok(Point::new(pen.x, position.y))
+    }
+
+    fn draw_whitespace<D>(
+        &self,
+        width: u32,
+        position: Point,
+        _baseline: Baseline,
+        _target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        Ok(position + Point::new(width as i32, 0))
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        let width = self.font.text_width(text);
+        TextMetrics {
+            bounding_box: Rectangle::new(
+                self.top(position, baseline),
+                Size::new(width, self.font.height as u32),
+            ),
+            next_position: position + Point::new(width as i32, 0),
+        }
+    }
+
+    fn line_height(&self) -> u32 {
+        self.font.height as u32
+    }
+}
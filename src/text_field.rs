@@ -0,0 +1,67 @@
+//! A line of text redrawn a glyph at a time
+//!
+//! `display::write_string` paints every glyph, background and all, each
+//! time it is called, which is a lot of pixels for text that is put up every
+//! frame and rarely changes. A `TextField` draws into the frame's `Renderer`
+//! and only the glyphs that differ from what it drew last (see
+//! `core_logic::glyphs`), so a point scored repaints one or two digits and a
+//! frame without one repaints none. With the debug menu's dirty rects on,
+//! the glyphs redrawn are outlined with the rest of the frame.
+//!
+//! Whoever draws over the field (a new background, the game over screen)
+//! calls `invalidate`; moving it or changing its colors redraws it whole.
+#![allow(dead_code)]
+
+use core_logic::glyphs::ShownText;
+
+use crate::config::Coord;
+use crate::display;
+use crate::entity::Renderer;
+
+pub struct TextField<const N: usize> {
+    shown: ShownText<N>,
+    // Where and in what it was drawn last
+    at: (Coord, Coord),
+    colors: (u16, u16),
+}
+
+impl<const N: usize> TextField<N> {
+    pub const fn new() -> Self {
+        TextField {
+            shown: ShownText::new(),
+            at: (0, 0),
+            colors: (0, 0),
+        }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.shown.invalidate();
+    }
+
+    // `text` at x, y in `rgb565` on `bg565`, up to `N` glyphs
+    pub fn draw(
+        &mut self,
+        renderer: &mut Renderer,
+        (x, y): (Coord, Coord),
+        text: &[u8],
+        (rgb565, bg565): (u16, u16),
+    ) {
+        if self.at != (x, y) || self.colors != (rgb565, bg565) {
+            self.at = (x, y);
+            self.colors = (rgb565, bg565);
+            self.shown.invalidate();
+        }
+        let changed = self.shown.update(text);
+        let (w, _) = display::glyph_size();
+        for i in (0..N).filter(|i| changed & (1 << i) != 0) {
+            let cx = x + (i as u32 * w) as Coord;
+            renderer.draw_glyph(cx, y, self.shown.cell(i), rgb565, bg565);
+        }
+    }
+}
+
+impl<const N: usize> Default for TextField<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
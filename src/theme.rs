@@ -0,0 +1,191 @@
+//! Color and art themes
+//!
+//! A theme is plain data: the colors the game paints with and the flash art
+//! behind each sprite. Drawing code asks `current()` instead of naming
+//! colors or assets, so switching themes is one store. The choice is picked
+//! from the pause menu and kept with the other settings.
+//!
+//! High contrast is for players who find the others hard to make out: a
+//! black sky, white pipes in a thick yellow outline (core_logic::pipe's
+//! outlined art), white text on black and the HUD's score drawn larger.
+//! Inverting the panel's colors (`display::invert_colors`) is a setting of
+//! its own and goes with any theme.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core_logic::cycle::Cycle;
+pub use core_logic::settings::ThemeId;
+use core_logic::settings::THEME_COUNT;
+
+use core_logic::pipe::{self, BODY_PIXELS, BODY_ROWS, CAP_MIRROR, CAP_PIXELS, CAP_ROWS};
+
+use crate::assets;
+use crate::color;
+use crate::config::{OBSTACLE_WIDTH, PLANTS_HEIGHT, PLAYER_HEIGHT, PLAYER_WIDTH};
+use crate::framebuffer::Image;
+use crate::settings;
+use crate::sprite_cache;
+
+pub struct Theme {
+    pub name: &'static str,
+    // Sky behind the playfield; with `day_night` it is only the fallback
+    // and the sky follows the score instead (see sky.rs)
+    pub background: u16,
+    pub day_night: bool,
+    // Strip along the top holding the score, and the rule under it
+    pub scoreboard: u16,
+    pub scoreboard_rule: u16,
+    // Score digits and the box behind them
+    pub score_text: u16,
+    pub score_box: u16,
+    // Size of the score on the HUD, in percent
+    pub score_percent: u32,
+    // Title screen caption
+    pub caption: u16,
+    // Obstacles, when there is no pipe art, and their ends at an opening
+    pub pipe: u16,
+    pub pipe_cap: u16,
+    // Flash art; sprites uploaded over the telemetry link still win
+    pub bird: Image<'static>,
+    pub ground: Image<'static>,
+    // Tiled down the obstacles' pipe body and over their caps, an obstacle
+    // wide (see core_logic::pipe)
+    pub pipe_tile: Option<Image<'static>>,
+    pub pipe_cap_tile: Option<Image<'static>>,
+    // Palette-cycled band under the plants (see strip.rs)
+    pub strip: Option<Cycle>,
+}
+
+const BIRD: Image = Image::new(PLAYER_WIDTH, PLAYER_HEIGHT, &assets::BIRD_IMG_DATA);
+const PLANT: Image = Image::new(60, PLANTS_HEIGHT, &assets::PLANT_IMG_DATA);
+
+// Pipe art for each theme, shaded from its pipe colors
+static DAY_BODY: [u16; BODY_PIXELS] = pipe::body(color::BLACK);
+static DAY_CAP: [u16; CAP_PIXELS] = pipe::cap(0x4208);
+static NIGHT_BODY: [u16; BODY_PIXELS] = pipe::body(0x52AA);
+static NIGHT_CAP: [u16; CAP_PIXELS] = pipe::cap(0x7BCF);
+static GREEN_BODY: [u16; BODY_PIXELS] = pipe::body(0x2DE5);
+static GREEN_CAP: [u16; CAP_PIXELS] = pipe::cap(0x1BA3);
+static RED_BODY: [u16; BODY_PIXELS] = pipe::body(0xC124);
+static RED_CAP: [u16; CAP_PIXELS] = pipe::cap(0x8082);
+static CONTRAST_BODY: [u16; BODY_PIXELS] = pipe::outlined_body(color::WHITE, CONTRAST_OUTLINE);
+static CONTRAST_CAP: [u16; CAP_PIXELS] = pipe::outlined_cap(color::WHITE, CONTRAST_OUTLINE);
+
+// Around the high-contrast pipes, standing out from both them and the sky
+const CONTRAST_OUTLINE: u16 = 0xFFE0;
+
+const fn body(pixels: &'static [u16; BODY_PIXELS]) -> Option<Image<'static>> {
+    Some(Image::new(OBSTACLE_WIDTH, BODY_ROWS as u32, pixels))
+}
+
+const fn cap(pixels: &'static [u16; CAP_PIXELS]) -> Option<Image<'static>> {
+    Some(Image::mirrored(
+        OBSTACLE_WIDTH,
+        CAP_ROWS as u32,
+        CAP_MIRROR,
+        pixels,
+    ))
+}
+
+// The original look
+const DAY: Theme = Theme {
+    name: "Day",
+    background: 0x9F5E,
+    day_night: true,
+    scoreboard: color::WHITE,
+    scoreboard_rule: color::BLACK,
+    score_text: color::BLACK,
+    score_box: 0xE71C,
+    score_percent: 100,
+    caption: color::RED,
+    pipe: color::BLACK,
+    pipe_cap: 0x4208,
+    bird: BIRD,
+    ground: PLANT,
+    pipe_tile: body(&DAY_BODY),
+    pipe_cap_tile: cap(&DAY_CAP),
+    strip: Some(Cycle::WATER),
+};
+
+static THEMES: [Theme; THEME_COUNT] = [
+    DAY,
+    Theme {
+        name: "Night",
+        background: 0x10A6,
+        day_night: false,
+        scoreboard: 0x2104,
+        scoreboard_rule: 0x8410,
+        score_text: color::WHITE,
+        score_box: 0x4208,
+        caption: 0xFFE0,
+        pipe: 0x52AA,
+        pipe_cap: 0x7BCF,
+        pipe_tile: body(&NIGHT_BODY),
+        pipe_cap_tile: cap(&NIGHT_CAP),
+        strip: None,
+        ..DAY
+    },
+    Theme {
+        name: "Green",
+        pipe: 0x2DE5,
+        pipe_cap: 0x1BA3,
+        pipe_tile: body(&GREEN_BODY),
+        pipe_cap_tile: cap(&GREEN_CAP),
+        ..DAY
+    },
+    Theme {
+        name: "Red",
+        pipe: 0xC124,
+        pipe_cap: 0x8082,
+        pipe_tile: body(&RED_BODY),
+        pipe_cap_tile: cap(&RED_CAP),
+        strip: Some(Cycle::LAVA),
+        ..DAY
+    },
+    Theme {
+        name: "High contrast",
+        background: color::BLACK,
+        day_night: false,
+        scoreboard: color::BLACK,
+        scoreboard_rule: color::WHITE,
+        score_text: color::WHITE,
+        score_box: color::BLACK,
+        score_percent: 140,
+        caption: color::WHITE,
+        pipe: color::WHITE,
+        pipe_cap: CONTRAST_OUTLINE,
+        pipe_tile: body(&CONTRAST_BODY),
+        pipe_cap_tile: cap(&CONTRAST_CAP),
+        strip: None,
+        ..DAY
+    },
+];
+
+static mut CURRENT: ThemeId = ThemeId::Day;
+
+pub fn id() -> ThemeId {
+    unsafe { CURRENT }
+}
+
+pub fn current() -> &'static Theme {
+    &THEMES[id() as usize]
+}
+
+// Switch themes and remember the choice; the caller redraws
+pub fn set(id: ThemeId) {
+    unsafe { CURRENT = id };
+    sprite_cache::clear();
+    settings::update(|settings| settings.theme = id);
+}
+
+// Day -> Night -> Green -> Red -> High contrast -> Day, for the pause menu
+pub fn cycle() -> ThemeId {
+    let next = ThemeId::from_u32((id() as u32 + 1) % THEME_COUNT as u32).unwrap_or(ThemeId::Day);
+    set(next);
+    next
+}
+
+// Pick up the stored choice; call once settings are loaded
+pub fn restore() {
+    unsafe { CURRENT = settings::get().theme };
+}
@@ -0,0 +1,156 @@
+//! STMPE811 resistive touch controller on I2C3
+//!
+//! Only the touchscreen block is used, polled while playing. The
+//! controller's interrupt line (PA15 on the DISCO board) is armed only to
+//! wake the board from sleep. Coordinates are raw 12-bit ADC readings;
+//! `read` scales them to the panel's portrait pixels.
+#![allow(dead_code)]
+
+use crate::clock;
+use crate::config::{Coord, PANEL_HEIGHT, PANEL_WIDTH};
+use crate::error::HwError;
+use crate::framebuffer;
+use crate::i2c;
+use crate::log;
+
+const STMPE811_ADDR: u8 = 0x41;
+const CHIP_ID: u16 = 0x0811;
+
+// Register addresses
+const REG_CHIP_ID: u8 = 0x00;
+const SYS_CTRL1: u8 = 0x03;
+const SYS_CTRL2: u8 = 0x04;
+const INT_CTRL: u8 = 0x09;
+const INT_EN: u8 = 0x0A;
+const INT_STA: u8 = 0x0B;
+const GPIO_AF: u8 = 0x17;
+const ADC_CTRL1: u8 = 0x20;
+const ADC_CTRL2: u8 = 0x21;
+const TSC_CTRL: u8 = 0x40;
+const TSC_CFG: u8 = 0x41;
+const FIFO_TH: u8 = 0x4A;
+const FIFO_STA: u8 = 0x4B;
+const FIFO_SIZE: u8 = 0x4C;
+const TSC_FRACT_XYZ: u8 = 0x56;
+const TSC_I_DRIVE: u8 = 0x58;
+const TSC_DATA_XYZ: u8 = 0xD7;
+
+// SYS_CTRL1 soft reset, TSC_CTRL enable and touch-detected status
+const SOFT_RESET: u8 = 0x02;
+const TSC_EN: u8 = 0x01;
+const TOUCH_DET: u8 = 0x80;
+// INT_CTRL global enable (level, active low) and the touch-detect source
+const GLOBAL_INT: u8 = 0x01;
+const INT_TOUCH_DET: u8 = 0x01;
+// FIFO_STA reset bit
+const FIFO_RESET: u8 = 0x01;
+
+// Raw readings at the panel edges; the resistive film does not reach 0 or 4095
+const RAW_MIN: i32 = 200;
+const RAW_MAX: i32 = 3900;
+
+static mut PRESENT: bool = false;
+
+pub fn is_present() -> bool {
+    unsafe { PRESENT }
+}
+
+// For the hardware report: the chip ID, Ok only if it is an STMPE811's.
+// Needs I2C3 up, which `init` does even when the controller is missing.
+pub fn probe() -> Result<u16, HwError> {
+    let mut id = [0u8; 2];
+    i2c::i2c3_read_bytes(STMPE811_ADDR, REG_CHIP_ID, &mut id).map_err(|()| HwError::NoResponse)?;
+    match u16::from_be_bytes(id) {
+        CHIP_ID => Ok(CHIP_ID),
+        _ => Err(HwError::NoResponse),
+    }
+}
+
+// Bring up I2C3 and the controller. Err if no STMPE811 answers.
+pub fn init() -> Result<(), ()> {
+    i2c::init_i2c3();
+
+    let mut id = [0u8; 2];
+    i2c::i2c3_read_bytes(STMPE811_ADDR, REG_CHIP_ID, &mut id)?;
+    let id = u16::from_be_bytes(id);
+    if id != CHIP_ID {
+        log::warn!("touch: unexpected chip ID {:#06x}", id);
+        return Err(());
+    }
+
+    let write = |reg, value| i2c::i2c3_write_reg(STMPE811_ADDR, reg, value);
+    write(SYS_CTRL1, SOFT_RESET)?;
+    clock::delay_ms(10);
+    write(SYS_CTRL1, 0x00)?;
+    // Clock the ADC, touchscreen and GPIO blocks
+    write(SYS_CTRL2, 0x00)?;
+    // Touchscreen pins are not GPIOs
+    write(GPIO_AF, 0x00)?;
+    // 80-cycle sample time, 12 bits; ADC clock 3.25 MHz
+    write(ADC_CTRL1, 0x49)?;
+    clock::delay_ms(2);
+    write(ADC_CTRL2, 0x01)?;
+    // 4-sample average, 500 us touch detect delay, 500 us settling
+    write(TSC_CFG, 0x9A)?;
+    write(FIFO_TH, 0x01)?;
+    write(FIFO_STA, FIFO_RESET)?;
+    write(FIFO_STA, 0x00)?;
+    write(TSC_FRACT_XYZ, 0x01)?;
+    // 50 mA drive
+    write(TSC_I_DRIVE, 0x01)?;
+    write(TSC_CTRL, TSC_EN)?;
+    write(INT_STA, 0xFF)?;
+
+    unsafe { PRESENT = true };
+    log::info!("touch: STMPE811 ready");
+    Ok(())
+}
+
+// Hold INT low while the screen is touched. Err without a controller.
+pub fn arm_wake() -> Result<(), ()> {
+    if !is_present() {
+        return Err(());
+    }
+    let write = |reg, value| i2c::i2c3_write_reg(STMPE811_ADDR, reg, value);
+    write(INT_STA, 0xFF)?;
+    write(INT_EN, INT_TOUCH_DET)?;
+    write(INT_CTRL, GLOBAL_INT)
+}
+
+pub fn disarm_wake() {
+    if !is_present() {
+        return;
+    }
+    let write = |reg, value| i2c::i2c3_write_reg(STMPE811_ADDR, reg, value);
+    let _ = write(INT_CTRL, 0x00);
+    let _ = write(INT_EN, 0x00);
+    let _ = write(INT_STA, 0xFF);
+}
+
+fn scale(raw: i32, size: u32) -> Coord {
+    let span = RAW_MAX - RAW_MIN;
+    ((raw.clamp(RAW_MIN, RAW_MAX) - RAW_MIN) * (size as i32 - 1) / span) as Coord
+}
+
+// Latest touch position in game coordinates, or None when nothing is pressed
+pub fn read() -> Option<(Coord, Coord)> {
+    if !is_present() {
+        return None;
+    }
+    let ctrl = i2c::i2c3_read_reg(STMPE811_ADDR, TSC_CTRL).ok()?;
+    if ctrl & TOUCH_DET == 0 {
+        return None;
+    }
+    if i2c::i2c3_read_reg(STMPE811_ADDR, FIFO_SIZE).ok()? == 0 {
+        return None;
+    }
+    // X in the top 12 bits, Y in the bottom 12; keep only the newest sample
+    let mut data = [0u8; 3];
+    i2c::i2c3_read_bytes(STMPE811_ADDR, TSC_DATA_XYZ, &mut data).ok()?;
+    let _ = i2c::i2c3_write_reg(STMPE811_ADDR, FIFO_STA, FIFO_RESET);
+    let _ = i2c::i2c3_write_reg(STMPE811_ADDR, FIFO_STA, 0x00);
+    let raw_x = ((data[0] as i32) << 4) | (data[1] as i32 >> 4);
+    let raw_y = ((data[1] as i32 & 0x0F) << 8) | data[2] as i32;
+    let panel = (scale(raw_x, PANEL_WIDTH), scale(raw_y, PANEL_HEIGHT));
+    Some(framebuffer::orientation().unmap(panel, (PANEL_WIDTH, PANEL_HEIGHT)))
+}
@@ -0,0 +1,134 @@
+//! Countdown, death and screen change animations
+//!
+//! The timing (`core_logic::anim`, re-exported here) is a function of the
+//! milliseconds elapsed since the animation began, so the game loop keeps
+//! running at full rate while they play instead of sitting in `delay_ms`.
+//! This module draws them.
+//!
+//! A screen change (`start`) leaves the old picture on Layer 1 and sends
+//! game drawing to the back buffer, as a raster effect does; `present`
+//! then brings the new picture across a little more each frame, by the
+//! wipe, slide or fade the change asked for, until it is all there and
+//! drawing goes back to Layer 1.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use embedded_graphics::mono_font::ascii::FONT_10X20;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::backlight;
+use crate::clock;
+use crate::config::{Coord, LCD_HEIGHT, LCD_WIDTH};
+use crate::display::{self, Backend};
+use crate::framebuffer::{self, FrameBuffer, Scaled};
+use crate::lcd::{self, DISPLAY_MEMORY};
+use crate::profiler::{self, Phase};
+use crate::raster;
+use crate::sdram;
+
+use core_logic::anim::{transition_frame, TransitionFrame};
+use core_logic::raster::Line;
+
+pub use core_logic::anim::{
+    countdown_digit, fall_y, flash_alpha, slowmo_scale, Transition, DEATH_MS, FLASH_MS,
+};
+
+// Constant alpha of the countdown overlay
+pub const COUNTDOWN_ALPHA: u8 = 0xE0;
+
+const DISC: Rgb565 = Rgb565::new(2, 6, 6);
+const DIGIT_SCALE: u32 = 4;
+
+// Draw one countdown digit on the overlay buffer: a large numeral on a disc
+// in the middle of the screen, everything else transparent
+pub fn draw_countdown(digit: u32) {
+    let mut fb = FrameBuffer::overlay();
+    fb.fill(0);
+
+    let center = Point::new(LCD_WIDTH as Coord / 2, LCD_HEIGHT as Coord / 2);
+    let _ = Circle::with_center(center, 96)
+        .into_styled(PrimitiveStyle::with_fill(DISC))
+        .draw(&mut fb);
+
+    let text = [b'0' + (digit % 10) as u8];
+    let text = core::str::from_utf8(&text).unwrap_or("?");
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let mut scaled = Scaled::new(&mut fb, DIGIT_SCALE * 100, center);
+    let _ = Text::with_text_style(text, Point::zero(), style, centered).draw(&mut scaled);
+
+    framebuffer::flush();
+}
+
+// The screen change playing and when it started
+static mut PLAYING: Option<(Transition, u32)> = None;
+
+// Whether game drawing goes to the back buffer for a screen change
+pub fn active() -> bool {
+    unsafe { PLAYING.is_some() }
+}
+
+// Change to the next screen by `kind`; call before drawing it. One still
+// playing finishes at once. Without SDRAM and LTDC the screen just changes.
+pub fn start(kind: Transition) {
+    if !sdram::available() || display::backend() != Backend::Ltdc {
+        return;
+    }
+    finish();
+    // The back buffer starts as the picture on screen, unless a raster
+    // effect already draws there
+    if !raster::active() {
+        let mem = &DISPLAY_MEMORY;
+        FrameBuffer::copy_raster(&mem.layer1, &mem.layer1_b, |_| Line::PLAIN);
+    }
+    unsafe { PLAYING = Some((kind, clock::millis())) };
+}
+
+// Bring as much of the new picture across as the change has got to; call
+// once per frame after retro::present
+pub fn present() {
+    let Some((kind, since)) = (unsafe { PLAYING }) else {
+        return;
+    };
+    let _render = profiler::scope(Phase::Render);
+    let mem = &DISPLAY_MEMORY;
+    let width = lcd::surface().0;
+    let elapsed = clock::millis().wrapping_sub(since);
+    match transition_frame(kind, elapsed, width) {
+        None => finish(),
+        Some(TransitionFrame::Wipe(x)) => {
+            FrameBuffer::copy_columns(&mem.layer1_b, &mem.layer1, 0, 0, x);
+        }
+        Some(TransitionFrame::Slide(x)) => {
+            FrameBuffer::copy_columns(&mem.layer1_b, &mem.layer1, 0, width - x, x);
+        }
+        // The picture fades to the black backdrop and back up; the new one
+        // goes in while it is dark
+        Some(TransitionFrame::Fade { alpha, new }) => {
+            if new {
+                FrameBuffer::copy_columns(&mem.layer1_b, &mem.layer1, 0, 0, width);
+            }
+            let level = backlight::layer_alpha() as u32 * alpha as u32 / 255;
+            let _ = display::set_brightness(level as u8);
+        }
+    }
+}
+
+// The whole new picture on screen and drawing back on Layer 1
+fn finish() {
+    let Some((kind, _)) = (unsafe { PLAYING.take() }) else {
+        return;
+    };
+    let mem = &DISPLAY_MEMORY;
+    FrameBuffer::copy_raster(&mem.layer1_b, &mem.layer1, |_| Line::PLAIN);
+    if kind == Transition::Fade {
+        let _ = display::set_brightness(backlight::layer_alpha());
+    }
+}
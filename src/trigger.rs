@@ -0,0 +1,137 @@
+//! External trigger on PE2 of the expansion header
+//!
+//! Anything that holds a line at one level while "pressed" can flap the
+//! bird: an arcade button or foot pedal to ground, or the output of an IR
+//! receiver module. Which level means pressed, and how long the line is
+//! ignored after a change, are the `TriggerConfig` kept in the settings.
+//! The internal pull holds the pin at its idle level, so a bare switch needs
+//! nothing else.
+//!
+//! EXTI line 2 interrupts on both edges. A change that gets past the
+//! debounce (core_logic::input::Lockout) queues a press or release in
+//! `input_events`, as the button does, and counts towards `presses`, so a
+//! tap shorter than a frame still flaps. `poll` looks at the line once a
+//! frame for a change whose edge fell inside the lockout. An IR receiver
+//! chatters for the whole of a remote's code, tens of milliseconds; with a
+//! debounce of 100 ms or so each key press is one flap.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use core_logic::input::Lockout;
+pub use core_logic::settings::TriggerConfig;
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::clock;
+use crate::input_events::{self, InputSource};
+use crate::resources;
+use crate::settings;
+
+const LINE: u32 = 2;
+// SYSCFG_EXTICR1 code for port E
+const PORT_E: u32 = 4;
+
+static mut CONFIG: TriggerConfig = TriggerConfig::DEFAULT;
+static mut LOCKOUT: Lockout = Lockout::new(TriggerConfig::DEFAULT.debounce_ms as u32);
+static PRESSES: AtomicU32 = AtomicU32::new(0);
+
+pub fn init() {
+    let dp = resources::pac();
+    dp.RCC.ahb1enr.modify(|_, w| w.gpioeen().enabled());
+    dp.RCC.apb2enr.modify(|_, w| w.syscfgen().enabled());
+    // PE2 as input
+    dp.GPIOE
+        .moder
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (LINE * 2))) });
+    configure(settings::get().trigger);
+    arm();
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::EXTI2) };
+}
+
+// Take a new level and debounce; the pin's state now is not a press
+pub fn configure(config: TriggerConfig) {
+    let dp = resources::pac();
+    // Pull towards idle: up when pressed is low, down when it is high
+    let pull = if config.active_high { 0b10 } else { 0b01 };
+    dp.GPIOE
+        .pupdr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (LINE * 2)) | pull << (LINE * 2)) });
+    clock::delay_us(10);
+    cortex_m::interrupt::free(|_| unsafe {
+        CONFIG = config;
+        LOCKOUT.set_ms(config.debounce_ms as u32);
+        LOCKOUT.reset(is_down());
+    });
+}
+
+pub fn config() -> TriggerConfig {
+    unsafe { CONFIG }
+}
+
+pub fn is_down() -> bool {
+    let dp = resources::pac();
+    let high = dp.GPIOE.idr.read().bits() & (1 << LINE) != 0;
+    high == unsafe { CONFIG.active_high }
+}
+
+// Presses since boot, wrapping; a change means the trigger went down
+pub fn presses() -> u32 {
+    PRESSES.load(Ordering::Relaxed)
+}
+
+// Catch a change the lockout hid; call once a frame
+pub fn poll() {
+    cortex_m::interrupt::free(|_| update());
+}
+
+fn update() {
+    match unsafe { LOCKOUT.sample(is_down(), clock::millis()) } {
+        Some(true) => {
+            PRESSES.fetch_add(1, Ordering::Relaxed);
+            input_events::pressed(InputSource::Trigger);
+        }
+        Some(false) => input_events::released(InputSource::Trigger),
+        None => {}
+    }
+}
+
+// Interrupt on both edges of PE2. Sleep clears the edges on the way out,
+// so resume calls this again.
+fn arm() {
+    let dp = resources::pac();
+    let bit = 1 << LINE;
+    let shift = LINE * 4;
+    dp.SYSCFG
+        .exticr1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(0xF << shift) | PORT_E << shift) });
+    dp.EXTI
+        .rtsr
+        .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    dp.EXTI
+        .ftsr
+        .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+    dp.EXTI.pr.write(|w| unsafe { w.bits(bit) });
+    dp.EXTI.imr.modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+}
+
+pub fn disarm() {
+    let dp = resources::pac();
+    dp.EXTI
+        .imr
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << LINE)) });
+}
+
+// Like the button: whatever happened while suspended is not a press
+pub fn resume() {
+    cortex_m::interrupt::free(|_| unsafe { LOCKOUT.reset(is_down()) });
+    arm();
+}
+
+#[interrupt]
+fn EXTI2() {
+    let dp = resources::pac();
+    dp.EXTI.pr.write(|w| unsafe { w.bits(1 << LINE) });
+    update();
+}
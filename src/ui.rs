@@ -0,0 +1,255 @@
+//! Immediate-mode widgets for the overlay screens
+//!
+//! A screen builds a `Ui` each time it draws and calls the widgets top to
+//! bottom: `label` for titles, `caption` and `value` for read-only rows,
+//! `button` and `list` for things to pick. Rows stack down from where the
+//! screen starts them, centered across the display, so nothing is placed by
+//! hand. Buttons and list entries take focus in the order they are drawn;
+//! the screen keeps a `Focus` between draws and passes in the button's
+//! gesture as a `Nav`: a short press moves to the next item, wrapping, and
+//! a long press activates the focused one, which its widget returns. A
+//! rotary encoder (see `encoder`) moves either way and activates with its
+//! switch.
+//!
+//! Drawing covers the whole Layer 2 overlay, so screens draw only when they
+//! open and when a `Nav` arrives.
+#![allow(dead_code)]
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder};
+
+use crate::assets::fonts::UI18;
+use crate::button::ButtonEvent;
+use crate::color;
+use crate::config::{Coord, LCD_HEIGHT, LCD_WIDTH};
+use crate::framebuffer::{self, FrameBuffer};
+use crate::text::PropTextStyle;
+
+// Leaves room for a title and eleven entries on the screen
+pub const ROW_HEIGHT: Coord = 26;
+// A caption over a value
+const VALUE_HEIGHT: Coord = 40;
+const HIGHLIGHT: Rgb565 = Rgb565::new(31, 50, 0);
+const CAPTION: Rgb565 = Rgb565::new(20, 40, 20);
+// Inset of the focus bar, and of the arrows on it, from the screen edges
+const BAR_INSET: Coord = 16;
+const MARKER_INSET: Coord = 24;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Nav {
+    Next,
+    // Back one item, wrapping; from the rotary encoder
+    Prev,
+    Activate,
+}
+
+impl Nav {
+    pub fn from_button(button: Option<ButtonEvent>) -> Option<Nav> {
+        match button? {
+            ButtonEvent::Short => Some(Nav::Next),
+            ButtonEvent::Long => Some(Nav::Activate),
+            ButtonEvent::Hold => None,
+        }
+    }
+}
+
+/// Which item of a screen has focus, kept between draws
+pub struct Focus {
+    selected: usize,
+    // Focusable items the last draw had, for wrapping
+    count: usize,
+}
+
+impl Focus {
+    pub const fn new() -> Self {
+        Focus {
+            selected: 0,
+            count: 0,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Focus::new()
+    }
+}
+
+// Top of the first of `rows` rows for them to sit in the middle of the
+// screen
+pub fn centered_top(rows: usize) -> Coord {
+    LCD_HEIGHT as Coord / 2 - ROW_HEIGHT * (rows as Coord - 1) / 2
+}
+
+pub struct Ui<'a> {
+    fb: FrameBuffer,
+    focus: Option<&'a mut Focus>,
+    activate: bool,
+    // Focusable items drawn so far
+    items: usize,
+    // Middle of the next row
+    y: Coord,
+}
+
+impl<'a> Ui<'a> {
+    // Start a screen with focusable items, the first row's middle at `top`.
+    // `nav` moves the focus before anything is drawn.
+    pub fn begin(focus: &'a mut Focus, nav: Option<Nav>, top: Coord) -> Self {
+        if focus.count != 0 {
+            match nav {
+                Some(Nav::Next) => focus.selected = (focus.selected + 1) % focus.count,
+                Some(Nav::Prev) => {
+                    focus.selected = (focus.selected + focus.count - 1) % focus.count
+                }
+                _ => {}
+            }
+        }
+        let mut ui = Ui::screen(top);
+        ui.activate = nav == Some(Nav::Activate);
+        ui.focus = Some(focus);
+        ui
+    }
+
+    // Start a screen with nothing to focus
+    pub fn screen(top: Coord) -> Self {
+        let mut fb = FrameBuffer::overlay();
+        // Dark veil over the game; the layer's constant alpha makes it see-through
+        let veil = fb.encode_argb(color::VEIL);
+        fb.fill(veil);
+        Ui {
+            fb,
+            focus: None,
+            activate: false,
+            items: 0,
+            y: top,
+        }
+    }
+
+    fn centered() -> TextStyle {
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build()
+    }
+
+    fn center_x() -> Coord {
+        LCD_WIDTH as Coord / 2
+    }
+
+    // A title or heading
+    pub fn label(&mut self, text: &str) {
+        let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+        let at = Point::new(Self::center_x(), self.y);
+        let _ = Text::with_text_style(text, at, style, Self::centered()).draw(&mut self.fb);
+        self.y += ROW_HEIGHT;
+    }
+
+    // A line of small print
+    pub fn caption(&mut self, text: &str) {
+        let style = MonoTextStyle::new(&FONT_6X10, CAPTION);
+        let at = Point::new(Self::center_x(), self.y);
+        let _ = Text::with_text_style(text, at, style, Self::centered()).draw(&mut self.fb);
+        self.y += ROW_HEIGHT / 2;
+    }
+
+    // A small caption above a large value
+    pub fn value(&mut self, caption: &str, value: &str) {
+        let small = MonoTextStyle::new(&FONT_6X10, CAPTION);
+        let big = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+        let x = Self::center_x();
+        let _ = Text::with_text_style(caption, Point::new(x, self.y - 8), small, Self::centered())
+            .draw(&mut self.fb);
+        let _ = Text::with_text_style(value, Point::new(x, self.y + 8), big, Self::centered())
+            .draw(&mut self.fb);
+        self.y += VALUE_HEIGHT;
+    }
+
+    // Leave `rows` empty rows
+    pub fn space(&mut self, rows: Coord) {
+        self.y += ROW_HEIGHT * rows;
+    }
+
+    // A focusable row; true when it has focus and was activated
+    pub fn button(&mut self, text: &str) -> bool {
+        let index = self.items;
+        self.items += 1;
+        let focused = self
+            .focus
+            .as_ref()
+            .is_some_and(|focus| focus.selected == index);
+
+        let color = if focused {
+            self.draw_focus_bar();
+            Rgb565::BLACK
+        } else {
+            Rgb565::WHITE
+        };
+        let style = PropTextStyle::new(&UI18, color);
+        let at = Point::new(Self::center_x(), self.y);
+        let _ = Text::with_text_style(text, at, style, Self::centered()).draw(&mut self.fb);
+        self.y += ROW_HEIGHT;
+        focused && self.activate
+    }
+
+    // A button per entry; the index of the one activated
+    pub fn list(&mut self, labels: &[&str]) -> Option<usize> {
+        let mut chosen = None;
+        for (i, label) in labels.iter().enumerate() {
+            if self.button(label) {
+                chosen = Some(i);
+            }
+        }
+        chosen
+    }
+
+    fn draw_focus_bar(&mut self) {
+        let y = self.y;
+        let bar = Rectangle::new(
+            Point::new(BAR_INSET, y - ROW_HEIGHT / 2 + 2),
+            Size::new(LCD_WIDTH - 2 * BAR_INSET as u32, ROW_HEIGHT as u32 - 4),
+        );
+        let _ = bar
+            .into_styled(PrimitiveStyle::with_fill(HIGHLIGHT))
+            .draw(&mut self.fb);
+        let marker = PropTextStyle::new(&UI18, Rgb565::BLACK);
+        let middle = TextStyleBuilder::new().baseline(Baseline::Middle);
+        let _ = Text::with_text_style(
+            "\u{2192}",
+            Point::new(MARKER_INSET, y),
+            marker,
+            middle.build(),
+        )
+        .draw(&mut self.fb);
+        let _ = Text::with_text_style(
+            "\u{2190}",
+            Point::new(LCD_WIDTH as Coord - MARKER_INSET, y),
+            marker,
+            middle.alignment(Alignment::Right).build(),
+        )
+        .draw(&mut self.fb);
+    }
+
+    // Finish the screen, remembering how many items it had; a focus past
+    // the end (the screen lost items) goes back to the first
+    pub fn end(self) {
+        if let Some(focus) = self.focus {
+            focus.count = self.items;
+            if focus.selected >= self.items {
+                focus.selected = 0;
+            }
+        }
+        framebuffer::flush();
+    }
+}
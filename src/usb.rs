@@ -0,0 +1,721 @@
+//! USB CDC-ACM virtual serial port
+//!
+//! The DISCO board's user micro-USB socket (CN6) is wired to the OTG_HS
+//! core's embedded full-speed PHY, so that core runs here in full-speed
+//! device mode; the OTG_FS core has no connector on this board. With the
+//! `usb-serial` feature the board enumerates as a CDC-ACM port, and
+//! `serial` mirrors the host link onto it: telemetry and shell output go to
+//! both, and bytes typed into either reach the shell. No UART adapter or
+//! ST-LINK VCP is needed.
+//!
+//! Everything is driven by the OTG_HS interrupt: control requests on
+//! endpoint 0, received data on bulk OUT 1 into a ring, and transmit data
+//! from a ring onto bulk IN 1 a packet at a time. Interrupt IN 2 carries
+//! CDC notifications, of which none are sent. VBUS is not sensed; the
+//! board is bus powered on this port anyway.
+//!
+//! The core's register map is the Synopsys one the PAC splits awkwardly
+//! between host and device views, so registers are reached by offset.
+//!
+//! Pins: PB14 = DM, PB15 = DP, AF12. PB15 is also the I2S DAC's data
+//! line, so with `i2s-audio` enabled as well the port stays off.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use crate::clock;
+use crate::error::HwError;
+use crate::fmt_buf::FmtBuf;
+use crate::log;
+use crate::resources::{self, Shared};
+use crate::serial::Ring;
+use crate::subsystem::{Health, Subsystem};
+
+const BASE: usize = 0x4004_0000;
+
+// Core global registers
+const GAHBCFG: usize = 0x008;
+const GUSBCFG: usize = 0x00C;
+const GRSTCTL: usize = 0x010;
+const GINTSTS: usize = 0x014;
+const GINTMSK: usize = 0x018;
+const GRXSTSP: usize = 0x020;
+const GRXFSIZ: usize = 0x024;
+const DIEPTXF0: usize = 0x028;
+const GCCFG: usize = 0x038;
+const fn dieptxf(ep: usize) -> usize {
+    0x104 + (ep - 1) * 4
+}
+
+// Device registers
+const DCFG: usize = 0x800;
+const DCTL: usize = 0x804;
+const DIEPMSK: usize = 0x810;
+const DOEPMSK: usize = 0x814;
+const DAINT: usize = 0x818;
+const DAINTMSK: usize = 0x81C;
+const fn diepctl(ep: usize) -> usize {
+    0x900 + ep * 0x20
+}
+const fn diepint(ep: usize) -> usize {
+    0x908 + ep * 0x20
+}
+const fn dieptsiz(ep: usize) -> usize {
+    0x910 + ep * 0x20
+}
+const fn doepctl(ep: usize) -> usize {
+    0xB00 + ep * 0x20
+}
+const fn doepint(ep: usize) -> usize {
+    0xB08 + ep * 0x20
+}
+const fn doeptsiz(ep: usize) -> usize {
+    0xB10 + ep * 0x20
+}
+const PCGCCTL: usize = 0xE00;
+// Each endpoint's transmit FIFO is pushed through its own window; every
+// receive pops through endpoint 0's
+const fn fifo(ep: usize) -> usize {
+    0x1000 + ep * 0x1000
+}
+
+// GINTSTS / GINTMSK
+const RXFLVL: u32 = 1 << 4;
+const USBSUSP: u32 = 1 << 11;
+const USBRST: u32 = 1 << 12;
+const ENUMDNE: u32 = 1 << 13;
+const IEPINT: u32 = 1 << 18;
+const OEPINT: u32 = 1 << 19;
+// GRSTCTL
+const CSRST: u32 = 1 << 0;
+const RXFFLSH: u32 = 1 << 4;
+const TXFFLSH: u32 = 1 << 5;
+const TXFNUM_ALL: u32 = 0x10 << 6;
+const AHBIDL: u32 = 1 << 31;
+// GUSBCFG: full-speed serial transceiver, turnaround for a fast AHB, device
+const PHYSEL: u32 = 1 << 6;
+const TRDT_6: u32 = 6 << 10;
+const FDMOD: u32 = 1 << 30;
+// GCCFG: transceiver on, VBUS not sensed
+const PWRDWN: u32 = 1 << 16;
+const NOVBUSSENS: u32 = 1 << 21;
+// DCFG: full speed on the embedded PHY; address field
+const DSPD_FS: u32 = 0b11;
+const DAD_SHIFT: u32 = 4;
+const DAD_MASK: u32 = 0x7F << DAD_SHIFT;
+// DCTL
+const SDIS: u32 = 1 << 1;
+const CGINAK: u32 = 1 << 8;
+// DIEPCTL / DOEPCTL
+const USBAEP: u32 = 1 << 15;
+const EPTYP_BULK: u32 = 2 << 18;
+const EPTYP_INTERRUPT: u32 = 3 << 18;
+const STALL: u32 = 1 << 21;
+const TXFNUM_SHIFT: u32 = 22;
+const CNAK: u32 = 1 << 26;
+const SD0PID: u32 = 1 << 28;
+const EPENA: u32 = 1 << 31;
+// DIEPINT / DOEPINT
+const XFRC: u32 = 1 << 0;
+const STUP: u32 = 1 << 3;
+// DIEPTSIZ / DOEPTSIZ
+const PKTCNT_SHIFT: u32 = 19;
+const STUPCNT_3: u32 = 3 << 29;
+// GRXSTSP packet status
+const PKT_OUT_DATA: u32 = 2;
+const PKT_SETUP_DATA: u32 = 6;
+
+// Endpoints
+const EP_DATA: usize = 1;
+const EP_NOTIFY: usize = 2;
+const MAX_PACKET: usize = 64;
+const NOTIFY_PACKET: usize = 8;
+
+// FIFO RAM, in words: receive, then each IN endpoint's transmit FIFO
+const RX_FIFO_WORDS: u32 = 128;
+const TX0_FIFO_WORDS: u32 = 64;
+const TX1_FIFO_WORDS: u32 = 64;
+const TX2_FIFO_WORDS: u32 = 16;
+
+// Core reset and AHB idle take a few PHY clocks
+const RESET_TIMEOUT_US: u32 = 1000;
+
+// ST's VID with the PID of its virtual COM port examples, which every OS
+// already binds to a CDC-ACM driver
+const VID: u16 = 0x0483;
+const PID: u16 = 0x5740;
+
+#[rustfmt::skip]
+const DEVICE_DESCRIPTOR: [u8; 18] = [
+    18, 1, 0x00, 0x02, // USB 2.0
+    0x02, 0x00, 0x00, // class from the interfaces (CDC)
+    MAX_PACKET as u8,
+    VID as u8, (VID >> 8) as u8,
+    PID as u8, (PID >> 8) as u8,
+    0x00, 0x01, // device release 1.0
+    1, 2, 3, // manufacturer, product, serial number strings
+    1,
+];
+
+#[rustfmt::skip]
+const CONFIG_DESCRIPTOR: [u8; 67] = [
+    9, 2, 67, 0, 2, 1, 0, 0x80, 50, // 2 interfaces, bus powered, 100 mA
+    // Communications interface with its CDC functional descriptors
+    9, 4, 0, 0, 1, 0x02, 0x02, 0x00, 0,
+    5, 0x24, 0x00, 0x10, 0x01, // header, CDC 1.10
+    5, 0x24, 0x01, 0x00, 1, // call management, data on interface 1
+    4, 0x24, 0x02, 0x02, // ACM: line coding and control line state
+    5, 0x24, 0x06, 0, 1, // union of interfaces 0 and 1
+    7, 5, 0x80 | EP_NOTIFY as u8, 0x03, NOTIFY_PACKET as u8, 0, 16,
+    // Data interface with the bulk pair
+    9, 4, 1, 0, 2, 0x0A, 0x00, 0x00, 0,
+    7, 5, EP_DATA as u8, 0x02, MAX_PACKET as u8, 0, 0,
+    7, 5, 0x80 | EP_DATA as u8, 0x02, MAX_PACKET as u8, 0, 0,
+];
+
+// US English only
+const LANGUAGES: [u8; 4] = [4, 3, 0x09, 0x04];
+const MANUFACTURER: &str = "JHooven";
+const PRODUCT: &str = "Flappy Bird";
+
+// Device 96-bit unique ID, for the serial number string
+const UID_BASE: usize = 0x1FFF_7A10;
+
+// Standard and CDC class requests handled
+const GET_STATUS: u8 = 0x00;
+const CLEAR_FEATURE: u8 = 0x01;
+const SET_FEATURE: u8 = 0x03;
+const SET_ADDRESS: u8 = 0x05;
+const GET_DESCRIPTOR: u8 = 0x06;
+const GET_CONFIGURATION: u8 = 0x08;
+const SET_CONFIGURATION: u8 = 0x09;
+const GET_INTERFACE: u8 = 0x0A;
+const SET_INTERFACE: u8 = 0x0B;
+const SET_LINE_CODING: u8 = 0x20;
+const GET_LINE_CODING: u8 = 0x21;
+const SET_CONTROL_LINE_STATE: u8 = 0x22;
+const SEND_BREAK: u8 = 0x23;
+const REQUEST_TYPE_MASK: u8 = 0x60;
+const REQUEST_STANDARD: u8 = 0x00;
+const REQUEST_CLASS: u8 = 0x20;
+
+const RX_SIZE: usize = 256;
+const TX_SIZE: usize = 1024;
+
+static mut RX: Ring<RX_SIZE> = Ring::new();
+static mut TX: Ring<TX_SIZE> = Ring::new();
+// Bytes lost to a full ring in either direction
+static RX_DROPPED: AtomicU32 = AtomicU32::new(0);
+static TX_DROPPED: AtomicU32 = AtomicU32::new(0);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// Host chose our configuration, and has the port open (DTR)
+static CONFIGURED: AtomicBool = AtomicBool::new(false);
+static PORT_OPEN: AtomicBool = AtomicBool::new(false);
+// A packet is on its way out of bulk IN 1
+static IN_BUSY: AtomicBool = AtomicBool::new(false);
+
+// Endpoint 0's side of the conversation
+struct Control {
+    // Last SETUP packet, and a request still waiting for its OUT data stage
+    setup: [u8; 8],
+    pending_out: Option<u8>,
+    // Whatever the host set last; the port has no baud rate of its own.
+    // 115200 8N1 until then.
+    line_coding: [u8; 7],
+}
+
+static CONTROL: Shared<Control> = Shared::of(Control {
+    setup: [0; 8],
+    pending_out: None,
+    line_coding: [0x00, 0xC2, 0x01, 0x00, 0, 0, 8],
+});
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<Ring<RX_SIZE>>()
+    + core::mem::size_of::<Ring<TX_SIZE>>()
+    + core::mem::size_of::<AtomicU32>() * 2
+    + core::mem::size_of::<AtomicBool>() * 4
+    + core::mem::size_of::<Shared<Control>>();
+
+fn read(offset: usize) -> u32 {
+    unsafe { core::ptr::read_volatile((BASE + offset) as *const u32) }
+}
+
+fn write(offset: usize, value: u32) {
+    unsafe { core::ptr::write_volatile((BASE + offset) as *mut u32, value) };
+}
+
+fn modify(offset: usize, f: impl FnOnce(u32) -> u32) {
+    write(offset, f(read(offset)));
+}
+
+// Wait for GRSTCTL to show `done`, within RESET_TIMEOUT_US
+fn wait_reset(done: impl Fn(u32) -> bool) -> Result<(), HwError> {
+    let start = clock::cycles();
+    while !done(read(GRSTCTL)) {
+        if clock::cycles().wrapping_sub(start) > RESET_TIMEOUT_US * clock::CYCLES_PER_US {
+            return Err(HwError::Timeout);
+        }
+    }
+    Ok(())
+}
+
+pub fn init() -> Result<(), HwError> {
+    let dp = resources::pac();
+    // The PLL's 48 MHz output (PLLQ = 7) clocks the PHY. The ULPI clock
+    // stays off, or the core waits for an external PHY in sleep mode.
+    dp.RCC
+        .ahb1enr
+        .modify(|_, w| w.gpioben().enabled().otghsen().enabled());
+    dp.RCC
+        .ahb1lpenr
+        .modify(|_, w| w.otghsulpilpen().disabled_in_sleep());
+
+    // PB14/PB15 to AF12, very high speed
+    let gpiob = &dp.GPIOB;
+    for pin in [14u32, 15] {
+        gpiob.moder.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << (pin * 2))) | (0b10 << (pin * 2)))
+        });
+        gpiob
+            .ospeedr
+            .modify(|r, w| unsafe { w.bits(r.bits() | (0b11 << (pin * 2))) });
+        let idx = pin - 8;
+        gpiob
+            .afrh
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0xF << (idx * 4))) | (12 << (idx * 4))) });
+    }
+
+    // Select the embedded PHY before the core reset, which it clocks
+    modify(GUSBCFG, |v| v | PHYSEL);
+    wait_reset(|v| v & AHBIDL != 0)?;
+    write(GRSTCTL, CSRST);
+    wait_reset(|v| v & CSRST == 0)?;
+
+    write(GUSBCFG, PHYSEL | TRDT_6 | FDMOD);
+    // Forcing device mode takes effect after about 25 ms
+    clock::delay_ms(25);
+    write(GCCFG, PWRDWN | NOVBUSSENS);
+    write(PCGCCTL, 0);
+    write(DCFG, DSPD_FS);
+
+    write(GRXFSIZ, RX_FIFO_WORDS);
+    let mut start = RX_FIFO_WORDS;
+    write(DIEPTXF0, (TX0_FIFO_WORDS << 16) | start);
+    start += TX0_FIFO_WORDS;
+    write(dieptxf(EP_DATA), (TX1_FIFO_WORDS << 16) | start);
+    start += TX1_FIFO_WORDS;
+    write(dieptxf(EP_NOTIFY), (TX2_FIFO_WORDS << 16) | start);
+    flush_fifos()?;
+
+    write(GINTSTS, u32::MAX);
+    write(
+        GINTMSK,
+        RXFLVL | USBSUSP | USBRST | ENUMDNE | IEPINT | OEPINT,
+    );
+    write(GAHBCFG, 1);
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::OTG_HS) };
+
+    // Pull up DP: the host sees a device arrive
+    modify(DCTL, |v| v & !SDIS);
+    ENABLED.store(true, Ordering::Relaxed);
+    log::info!("usb: cdc-acm up");
+    Ok(())
+}
+
+fn flush_fifos() -> Result<(), HwError> {
+    write(GRSTCTL, TXFFLSH | TXFNUM_ALL);
+    wait_reset(|v| v & TXFFLSH == 0)?;
+    write(GRSTCTL, RXFFLSH);
+    wait_reset(|v| v & RXFFLSH == 0)
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// A terminal on the host has the port open
+pub fn is_open() -> bool {
+    CONFIGURED.load(Ordering::Relaxed) && PORT_OPEN.load(Ordering::Relaxed)
+}
+
+// Next byte from the host, if any
+pub fn read_byte() -> Option<u8> {
+    unsafe { RX.pop() }
+}
+
+// Queue bytes for the host. Nobody may be reading, so rather than wait
+// for room, what does not fit is dropped; nothing is queued while the port
+// is closed.
+pub fn write_bytes(bytes: &[u8]) {
+    if !is_open() {
+        return;
+    }
+    for &byte in bytes {
+        if !unsafe { TX.push(byte) } {
+            TX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    cortex_m::interrupt::free(|_| start_in());
+}
+
+pub fn dropped() -> (u32, u32) {
+    (
+        RX_DROPPED.load(Ordering::Relaxed),
+        TX_DROPPED.load(Ordering::Relaxed),
+    )
+}
+
+// Start the next packet on bulk IN 1 unless one is already going
+fn start_in() {
+    if !CONFIGURED.load(Ordering::Relaxed) || IN_BUSY.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut packet = [0u8; MAX_PACKET];
+    let mut len = 0;
+    while len < MAX_PACKET {
+        match unsafe { TX.pop() } {
+            Some(byte) => {
+                packet[len] = byte;
+                len += 1;
+            }
+            None => break,
+        }
+    }
+    if len == 0 {
+        return;
+    }
+    IN_BUSY.store(true, Ordering::Relaxed);
+    send(EP_DATA, &packet[..len]);
+}
+
+// Program an IN transfer of `data` on `ep` and push it into the FIFO; the
+// FIFOs are sized so a whole control reply or packet always fits
+fn send(ep: usize, data: &[u8]) {
+    let packets = data.len().div_ceil(MAX_PACKET).max(1) as u32;
+    write(dieptsiz(ep), (packets << PKTCNT_SHIFT) | data.len() as u32);
+    modify(diepctl(ep), |v| v | EPENA | CNAK);
+    for chunk in data.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        write(fifo(ep), u32::from_le_bytes(word));
+    }
+}
+
+fn ep0_zlp() {
+    send(0, &[]);
+}
+
+// Refuse a request; the core clears the stall on the next SETUP
+fn ep0_stall() {
+    modify(diepctl(0), |v| v | STALL);
+    modify(doepctl(0), |v| v | STALL);
+}
+
+// Ready endpoint 0 for the next SETUP or OUT data stage
+fn arm_ep0_out() {
+    write(
+        doeptsiz(0),
+        STUPCNT_3 | (1 << PKTCNT_SHIFT) | MAX_PACKET as u32,
+    );
+    modify(doepctl(0), |v| v | EPENA | CNAK);
+}
+
+fn arm_data_out() {
+    write(doeptsiz(EP_DATA), (1 << PKTCNT_SHIFT) | MAX_PACKET as u32);
+    modify(doepctl(EP_DATA), |v| v | EPENA | CNAK);
+}
+
+// Pop `len` bytes off the receive FIFO into `out`, dropping any past its end
+fn read_fifo(len: usize, mut out: impl FnMut(u8)) {
+    for word in 0..len.div_ceil(4) {
+        let bytes = read(fifo(0)).to_le_bytes();
+        for &byte in bytes.iter().take(len - word * 4) {
+            out(byte);
+        }
+    }
+}
+
+fn bus_reset() {
+    let _ = flush_fifos();
+    for ep in 0..4 {
+        write(diepint(ep), 0xFF);
+        write(doepint(ep), 0xFF);
+    }
+    write(DAINTMSK, 1 | (1 << 16));
+    write(DIEPMSK, XFRC);
+    write(DOEPMSK, XFRC | STUP);
+    modify(DCFG, |v| v & !DAD_MASK);
+    arm_ep0_out();
+    CONFIGURED.store(false, Ordering::Relaxed);
+    PORT_OPEN.store(false, Ordering::Relaxed);
+    IN_BUSY.store(false, Ordering::Relaxed);
+    CONTROL.lock(|control| control.pending_out = None);
+}
+
+// Speed is settled: endpoint 0 takes 64-byte packets (MPSIZ 0)
+fn enumerated() {
+    modify(diepctl(0), |v| v & !0b11);
+    modify(DCTL, |v| v | CGINAK);
+}
+
+fn configure_endpoints() {
+    let bulk = USBAEP | EPTYP_BULK | SD0PID | MAX_PACKET as u32;
+    write(diepctl(EP_DATA), bulk | ((EP_DATA as u32) << TXFNUM_SHIFT));
+    write(doepctl(EP_DATA), bulk);
+    write(
+        diepctl(EP_NOTIFY),
+        USBAEP
+            | EPTYP_INTERRUPT
+            | SD0PID
+            | ((EP_NOTIFY as u32) << TXFNUM_SHIFT)
+            | NOTIFY_PACKET as u32,
+    );
+    modify(DAINTMSK, |v| v | (1 << EP_DATA) | (1 << (16 + EP_DATA)));
+    arm_data_out();
+    IN_BUSY.store(false, Ordering::Relaxed);
+}
+
+fn receive() {
+    let status = read(GRXSTSP);
+    let ep = (status & 0xF) as usize;
+    let len = ((status >> 4) & 0x7FF) as usize;
+    match (status >> 17) & 0xF {
+        PKT_SETUP_DATA => {
+            let mut setup = [0u8; 8];
+            let mut i = 0;
+            read_fifo(len, |byte| {
+                if i < setup.len() {
+                    setup[i] = byte;
+                    i += 1;
+                }
+            });
+            CONTROL.lock(|control| control.setup = setup);
+        }
+        PKT_OUT_DATA if ep == EP_DATA => read_fifo(len, |byte| {
+            if !unsafe { RX.push(byte) } {
+                RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }),
+        PKT_OUT_DATA => {
+            let mut data = [0u8; 8];
+            let mut i = 0;
+            read_fifo(len, |byte| {
+                if i < data.len() {
+                    data[i] = byte;
+                    i += 1;
+                }
+            });
+            // The data stage of a control write; a zero-length one is the
+            // host's status stage after an IN reply
+            let coding = CONTROL.lock(|control| {
+                let pending = control.pending_out.take();
+                let coding = len > 0 && pending == Some(SET_LINE_CODING);
+                if coding {
+                    control.line_coding.copy_from_slice(&data[..7]);
+                }
+                coding
+            });
+            if coding == Some(true) {
+                ep0_zlp();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn setup() {
+    let setup = CONTROL.lock(|control| control.setup).unwrap_or_default();
+    let request_type = setup[0];
+    let request = setup[1];
+    let value = u16::from_le_bytes([setup[2], setup[3]]);
+    let length = u16::from_le_bytes([setup[6], setup[7]]) as usize;
+    let reply = |data: &[u8]| send(0, &data[..data.len().min(length)]);
+
+    match (request_type & REQUEST_TYPE_MASK, request) {
+        (REQUEST_STANDARD, GET_DESCRIPTOR) => match (value >> 8, value & 0xFF) {
+            (1, _) => reply(&DEVICE_DESCRIPTOR),
+            (2, _) => reply(&CONFIG_DESCRIPTOR),
+            (3, 0) => reply(&LANGUAGES),
+            (3, 1) => reply(string_descriptor(MANUFACTURER).as_bytes()),
+            (3, 2) => reply(string_descriptor(PRODUCT).as_bytes()),
+            (3, 3) => reply(string_descriptor(serial_number().as_str()).as_bytes()),
+            _ => ep0_stall(),
+        },
+        // The core answers the status stage from the new address already
+        (REQUEST_STANDARD, SET_ADDRESS) => {
+            modify(DCFG, |v| {
+                (v & !DAD_MASK) | ((value as u32 & 0x7F) << DAD_SHIFT)
+            });
+            ep0_zlp();
+        }
+        (REQUEST_STANDARD, SET_CONFIGURATION) => {
+            let on = value == 1;
+            if on {
+                configure_endpoints();
+            }
+            CONFIGURED.store(on, Ordering::Relaxed);
+            ep0_zlp();
+        }
+        (REQUEST_STANDARD, GET_CONFIGURATION) => reply(&[CONFIGURED.load(Ordering::Relaxed) as u8]),
+        (REQUEST_STANDARD, GET_STATUS) => reply(&[0, 0]),
+        (REQUEST_STANDARD, GET_INTERFACE) => reply(&[0]),
+        (REQUEST_STANDARD, CLEAR_FEATURE | SET_FEATURE | SET_INTERFACE) => ep0_zlp(),
+        (REQUEST_CLASS, SET_LINE_CODING) => {
+            CONTROL.lock(|control| control.pending_out = Some(SET_LINE_CODING));
+        }
+        (REQUEST_CLASS, GET_LINE_CODING) => reply(
+            &CONTROL
+                .lock(|control| control.line_coding)
+                .unwrap_or_default(),
+        ),
+        (REQUEST_CLASS, SET_CONTROL_LINE_STATE) => {
+            PORT_OPEN.store(value & 1 != 0, Ordering::Relaxed);
+            ep0_zlp();
+        }
+        (REQUEST_CLASS, SEND_BREAK) => ep0_zlp(),
+        _ => ep0_stall(),
+    }
+}
+
+// A USB string descriptor: length, type 3, then UTF-16LE. Only ASCII is
+// passed in, so each character is its byte and a zero.
+struct StringDescriptor {
+    buf: [u8; 2 + 2 * 24],
+    len: usize,
+}
+
+impl StringDescriptor {
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+fn string_descriptor(text: &str) -> StringDescriptor {
+    let mut descriptor = StringDescriptor {
+        buf: [0; 2 + 2 * 24],
+        len: 2,
+    };
+    for byte in text.bytes().take(24) {
+        descriptor.buf[descriptor.len] = byte;
+        descriptor.len += 2;
+    }
+    descriptor.buf[0] = descriptor.len as u8;
+    descriptor.buf[1] = 3;
+    descriptor
+}
+
+// Eight hex digits folded from the chip's unique ID, so two boards on one
+// host get different port names
+fn serial_number() -> FmtBuf<8> {
+    let uid = |i: usize| unsafe { core::ptr::read_volatile((UID_BASE + i * 4) as *const u32) };
+    let mut text = FmtBuf::new();
+    let _ = write!(text, "{:08X}", uid(0) ^ uid(1) ^ uid(2));
+    text
+}
+
+#[interrupt]
+fn OTG_HS() {
+    let pending = read(GINTSTS) & read(GINTMSK);
+
+    if pending & USBRST != 0 {
+        bus_reset();
+        write(GINTSTS, USBRST);
+    }
+    if pending & ENUMDNE != 0 {
+        enumerated();
+        write(GINTSTS, ENUMDNE);
+    }
+    // Unplugged or the host went to sleep; the port counts as closed until
+    // it is opened again
+    if pending & USBSUSP != 0 {
+        PORT_OPEN.store(false, Ordering::Relaxed);
+        write(GINTSTS, USBSUSP);
+    }
+    // Popping the status word clears RXFLVL once the FIFO is empty
+    while read(GINTSTS) & RXFLVL != 0 {
+        receive();
+    }
+
+    if pending & OEPINT != 0 {
+        let daint = read(DAINT) >> 16;
+        if daint & 1 != 0 {
+            let flags = read(doepint(0));
+            write(doepint(0), flags);
+            if flags & STUP != 0 {
+                setup();
+            }
+            arm_ep0_out();
+        }
+        if daint & (1 << EP_DATA) != 0 {
+            let flags = read(doepint(EP_DATA));
+            write(doepint(EP_DATA), flags);
+            if flags & XFRC != 0 {
+                arm_data_out();
+            }
+        }
+    }
+
+    if pending & IEPINT != 0 {
+        let daint = read(DAINT) & 0xFFFF;
+        if daint & 1 != 0 {
+            write(diepint(0), read(diepint(0)));
+        }
+        if daint & (1 << EP_DATA) != 0 {
+            let flags = read(diepint(EP_DATA));
+            write(diepint(EP_DATA), flags);
+            if flags & XFRC != 0 {
+                IN_BUSY.store(false, Ordering::Relaxed);
+                start_in();
+            }
+        }
+    }
+}
+
+pub struct UsbSubsystem;
+
+impl Subsystem for UsbSubsystem {
+    fn name(&self) -> &'static str {
+        "usb"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        if cfg!(all(feature = "usb-serial", not(feature = "i2s-audio"))) {
+            init()?;
+        }
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        if !is_enabled() {
+            Health::Disabled
+        } else if !CONFIGURED.load(Ordering::Relaxed) {
+            Health::Degraded("no host")
+        } else if dropped() != (0, 0) {
+            Health::Degraded("overrun")
+        } else {
+            Health::Ok
+        }
+    }
+
+    // Look unplugged to the host and stop the PHY
+    fn suspend(&self) {
+        if is_enabled() {
+            modify(DCTL, |v| v | SDIS);
+            modify(GCCFG, |v| v & !PWRDWN);
+        }
+    }
+
+    fn resume(&self) {
+        if is_enabled() {
+            modify(GCCFG, |v| v | PWRDWN);
+            modify(DCTL, |v| v & !SDIS);
+        }
+    }
+}
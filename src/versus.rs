@@ -0,0 +1,222 @@
+//! Two-player split screen
+//!
+//! Each player gets one of the `Lane::SPLIT` lanes with its own bird,
+//! obstacle stream and score. Player 1 flies with the game's input device
+//! (tilt or touch), player 2 with short presses of the user button. A player
+//! who crashes is out and their lane freezes; once both are out the higher
+//! score wins. Both lanes play the classic game.
+#![allow(dead_code)]
+
+use core::fmt::Write;
+
+use core_logic::mode::Classic;
+use core_logic::rules;
+
+use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X10};
+use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+use embedded_graphics::pixelcolor::raw::RawU16;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+
+use crate::audio;
+use crate::color;
+use crate::config::{Coord, FLAP_LIFT, LCD_WIDTH};
+use crate::entity;
+use crate::fmt_buf::FmtBuf;
+use crate::framebuffer::{self, FrameBuffer};
+use crate::ground;
+use crate::lane::Lane;
+use crate::lang::{self, Msg};
+use crate::player::Player;
+use crate::theme;
+use crate::world::World;
+
+pub const PLAYERS: usize = 2;
+
+// One player's half of the screen
+struct Run {
+    lane: Lane,
+    player: Player,
+    world: World,
+    score: u32,
+    alive: bool,
+    was_tapping: bool,
+}
+
+impl Run {
+    fn new(lane: Lane) -> Self {
+        Run {
+            lane,
+            player: Player::init_in(lane),
+            world: World::new(lane, &Classic),
+            score: 0,
+            alive: true,
+            was_tapping: false,
+        }
+    }
+
+    fn draw_background(&self) {
+        ground::draw_score_bar(self.lane);
+        self.world.redraw();
+        entity::draw_now(&self.world, self.lane);
+    }
+
+    // "P1 007" on the scoreboard, "OUT" appended once crashed
+    fn draw_score(&self, index: usize) {
+        let mut text: FmtBuf<16> = FmtBuf::new();
+        let _ = write!(text, "P{} {:03}", index + 1, self.score.min(999));
+        if !self.alive {
+            let _ = write!(text, " OUT");
+        }
+        let theme = theme::current();
+        let style = MonoTextStyleBuilder::new()
+            .font(&FONT_10X20)
+            .text_color(Rgb565::from(RawU16::new(theme.score_text)))
+            .background_color(Rgb565::from(RawU16::new(theme.scoreboard)))
+            .build();
+        let mut fb = FrameBuffer::render_target();
+        let _ = Text::with_baseline(
+            text.as_str(),
+            Point::new(8, self.lane.top),
+            style,
+            Baseline::Top,
+        )
+        .draw(&mut fb);
+    }
+
+    // One frame of this player's game, `ticks` steps of it; the input
+    // steers the first
+    fn update(&mut self, index: usize, (new_y, is_tap): (Coord, bool), ticks: u32) {
+        if !self.alive {
+            return;
+        }
+
+        if is_tap && !self.was_tapping {
+            audio::play(audio::SoundId::Flap);
+        }
+        self.was_tapping = is_tap;
+
+        for tick in 0..ticks {
+            // The world first so the bird is drawn over anything it repaints
+            self.world.step(self.score);
+            let (player_x, player_y) = self.player.get_xy();
+            let (y_min, y_max) = self.lane.player_y_range();
+            self.player.move_player(if is_tap && tick == 0 {
+                new_y.clamp(y_min, y_max)
+            } else {
+                player_y
+            });
+
+            if rules::collides(
+                self.player.bird(),
+                self.world.obstacle().pair(),
+                self.lane.field(),
+            ) {
+                audio::play_at(audio::SoundId::Death, player_x);
+                self.alive = false;
+                self.draw_score(index);
+                return;
+            } else if rules::passed(self.player.bird(), self.world.obstacle_mut().pair_mut()) {
+                self.score += 1;
+                audio::play_at(audio::SoundId::Score, player_x);
+                self.draw_score(index);
+            }
+        }
+    }
+}
+
+pub struct Versus {
+    runs: [Run; PLAYERS],
+}
+
+impl Versus {
+    pub fn new() -> Self {
+        Versus {
+            runs: [Run::new(Lane::SPLIT[0]), Run::new(Lane::SPLIT[1])],
+        }
+    }
+
+    // Paint both lanes and birds from scratch
+    pub fn draw(&self) {
+        for (index, run) in self.runs.iter().enumerate() {
+            run.draw_background();
+            run.draw_score(index);
+            run.player.show();
+        }
+    }
+
+    // Screen rows `InputDevice::is_tap` should map player `index`'s input to
+    pub fn input_range(&self, index: usize) -> (Coord, Coord) {
+        self.runs[index].lane.player_y_range()
+    }
+
+    // Where a button flap takes player `index`
+    pub fn flap_y(&self, index: usize) -> Coord {
+        let (_, y) = self.runs[index].player.get_xy();
+        y - FLAP_LIFT
+    }
+
+    // Advance both lanes by `ticks` logic steps; true once both players
+    // are out
+    pub fn update(&mut self, inputs: [(Coord, bool); PLAYERS], ticks: u32) -> bool {
+        for (index, (run, input)) in self.runs.iter_mut().zip(inputs).enumerate() {
+            run.update(index, input, ticks);
+        }
+        self.runs.iter().all(|run| !run.alive)
+    }
+
+    pub fn scores(&self) -> [u32; PLAYERS] {
+        [self.runs[0].score, self.runs[1].score]
+    }
+
+    pub fn obstacle_velocity(&self) -> u32 {
+        self.runs[0].world.velocity()
+    }
+
+    pub fn set_obstacle_velocity(&mut self, velocity: u32) {
+        for run in self.runs.iter_mut() {
+            run.world.set_velocity(velocity);
+        }
+    }
+
+    // Winner and both scores on the overlay; the caller shows it
+    pub fn draw_winner(&self) {
+        let mut fb = FrameBuffer::overlay();
+        let veil = fb.encode_argb(color::VEIL);
+        fb.fill(veil);
+
+        let [p1, p2] = self.scores();
+        let title = match p1.cmp(&p2) {
+            core::cmp::Ordering::Greater => Msg::Player1Wins,
+            core::cmp::Ordering::Less => Msg::Player2Wins,
+            core::cmp::Ordering::Equal => Msg::Draw,
+        };
+        let title = lang::text(title);
+
+        let center_x = LCD_WIDTH as Coord / 2;
+        let centered = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+        let big = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+        let small = MonoTextStyle::new(&FONT_6X10, Rgb565::new(20, 40, 20));
+        let _ =
+            Text::with_text_style(title, Point::new(center_x, 100), big, centered).draw(&mut fb);
+
+        let mut line: FmtBuf<16> = FmtBuf::new();
+        for (index, score) in [p1, p2].into_iter().enumerate() {
+            let y = 160 + index as Coord * 48;
+            line.clear();
+            let _ = write!(line, "PLAYER {}", index + 1);
+            let _ = Text::with_text_style(line.as_str(), Point::new(center_x, y), small, centered)
+                .draw(&mut fb);
+            line.clear();
+            let _ = write!(line, "{}", score);
+            let _ =
+                Text::with_text_style(line.as_str(), Point::new(center_x, y + 16), big, centered)
+                    .draw(&mut fb);
+        }
+        framebuffer::flush();
+    }
+}
@@ -0,0 +1,361 @@
+//! Scores to a web server through an ESP-01 Wi-Fi module
+//!
+//! With the `wifi` feature an ESP8266 running Espressif's AT firmware,
+//! wired to UART5, joins the access point the build names and each
+//! finished game is posted to the score server as an HTTP form:
+//!
+//! ```text
+//! POST /scores HTTP/1.1
+//! ...
+//! mode=solo&score=12&ms=43210&initials=ABC
+//! ```
+//!
+//! The conversation with the module, retries and backoff included, is
+//! core_logic::esp_at; this module carries its bytes over the UART, both
+//! ways through rings serviced by the UART5 interrupt, and `poll` on the
+//! serial task feeds it. The HUD shows how the link is doing and the shell's
+//! `wifi` says more. Nothing is sent, and the UART stays off, when the
+//! build names no access point:
+//!
+//! ```text
+//! WIFI_SSID=home WIFI_PASSWORD=secret SCORE_HOST=192.168.1.20 \
+//!     SCORE_PORT=8080 SCORE_PATH=/scores cargo build --release --features wifi
+//! ```
+//!
+//! Pins: PC12 = TX to the module's RX, PD2 = RX from its TX, both AF8,
+//! 115200 8N1 (the AT firmware's default); `PINS` moves them. The module
+//! runs on 3.3 V but draws peaks of a few hundred mA, more than the board's
+//! 3V supply spares, so it wants a regulator of its own.
+#![allow(dead_code)]
+#![allow(static_mut_refs)]
+
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use stm32f4::stm32f429 as pac;
+use stm32f4::stm32f429::interrupt;
+
+use core_logic::esp_at::{Client, Config, Status, Upload};
+
+use crate::clock;
+use crate::error::HwError;
+use crate::log;
+use crate::profiles;
+use crate::resources::{self, ThreadOnly};
+use crate::serial::Ring;
+use crate::stats::{self, Board};
+use crate::subsystem::{Health, Subsystem};
+
+const BAUD: u32 = 115_200;
+// APB1 runs at SYSCLK / 4, see clock::setup_system_clocks_168mhz
+const APB1_HZ: u32 = 42_000_000;
+
+const RX_SIZE: usize = 256;
+// Room for a whole request, so sending one never waits
+const TX_SIZE: usize = 512;
+// Longest line kept from the module; the ones that matter are short
+const LINE_SIZE: usize = 48;
+
+/// A GPIO line: its port (0 for A, 1 for B, ...) and pin number
+#[derive(Copy, Clone)]
+pub struct Pin {
+    pub port: usize,
+    pub line: u32,
+}
+
+/// Where UART5 comes out, and the alternate function that takes it there
+pub struct Pins {
+    pub tx: Pin,
+    pub rx: Pin,
+    pub af: u32,
+}
+
+// The only UART whose pins are both free on the DISCO board's headers
+pub const PINS: Pins = Pins {
+    tx: Pin { port: 2, line: 12 },
+    rx: Pin { port: 3, line: 2 },
+    af: 8,
+};
+
+// From the build's environment; an empty SSID leaves the module alone
+const CONFIG: Config = Config {
+    ssid: env_or(option_env!("WIFI_SSID"), ""),
+    password: env_or(option_env!("WIFI_PASSWORD"), ""),
+    host: env_or(option_env!("SCORE_HOST"), "192.168.1.2"),
+    port: port(env_or(option_env!("SCORE_PORT"), "80")),
+    path: env_or(option_env!("SCORE_PATH"), "/scores"),
+};
+
+const fn env_or(value: Option<&'static str>, default: &'static str) -> &'static str {
+    match value {
+        Some(value) => value,
+        None => default,
+    }
+}
+
+// SCORE_PORT as a number; a build with one that is not fails here
+const fn port(text: &str) -> u16 {
+    let bytes = text.as_bytes();
+    let mut port: u16 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "SCORE_PORT is not a number");
+        port = port * 10 + (bytes[i] - b'0') as u16;
+        i += 1;
+    }
+    port
+}
+
+// The line coming in so far
+struct Line {
+    bytes: [u8; LINE_SIZE],
+    len: usize,
+}
+
+// Only the serial task and the game talk to the module; the interrupt
+// handler sees nothing but the rings
+static CLIENT: ThreadOnly<Client> = ThreadOnly::of(Client::new(CONFIG));
+static LINE: ThreadOnly<Line> = ThreadOnly::of(Line {
+    bytes: [0; LINE_SIZE],
+    len: 0,
+});
+static mut RX: Ring<RX_SIZE> = Ring::new();
+static mut TX: Ring<TX_SIZE> = Ring::new();
+// Bytes lost to a full receive buffer or a hardware overrun
+static RX_DROPPED: AtomicU32 = AtomicU32::new(0);
+
+// UART5 was switched off by suspend and should come back on resume
+static LINK_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+// Static RAM held by this module, for the memory budget report
+pub const STATIC_RAM_BYTES: usize = core::mem::size_of::<ThreadOnly<Client>>()
+    + core::mem::size_of::<ThreadOnly<Line>>()
+    + core::mem::size_of::<Ring<RX_SIZE>>()
+    + core::mem::size_of::<Ring<TX_SIZE>>()
+    + core::mem::size_of::<AtomicU32>()
+    + core::mem::size_of::<AtomicBool>();
+
+// GPIO port `port` (0 for A); they all share GPIOA's layout
+fn gpio(port: usize) -> &'static pac::gpioa::RegisterBlock {
+    let base = pac::GPIOA::ptr() as usize + port * 0x400;
+    unsafe { &*(base as *const pac::gpioa::RegisterBlock) }
+}
+
+fn set_af(pin: Pin, af: u32) {
+    let port = gpio(pin.port);
+    let line = pin.line;
+    port.moder.modify(|r, w| unsafe {
+        w.bits((r.bits() & !(0b11 << (line * 2))) | (0b10 << (line * 2)))
+    });
+    if line < 8 {
+        port.afrl.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0xF << (line * 4))) | (af << (line * 4)))
+        });
+    } else {
+        let idx = line - 8;
+        port.afrh
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0xF << (idx * 4))) | (af << (idx * 4))) });
+    }
+}
+
+// UART5 and its pins, then the module reset and the join begun; nothing
+// without an access point to join
+pub fn init() {
+    if !is_configured() {
+        log::info!("wifi: no WIFI_SSID, staying off");
+        return;
+    }
+    let dp = resources::pac();
+    let rcc = &dp.RCC;
+    rcc.ahb1enr
+        .modify(|r, w| unsafe { w.bits(r.bits() | 1 << PINS.tx.port | 1 << PINS.rx.port) });
+    rcc.apb1enr.modify(|_, w| w.uart5en().enabled());
+    set_af(PINS.tx, PINS.af);
+    set_af(PINS.rx, PINS.af);
+
+    let uart = &dp.UART5;
+    uart.brr
+        .write(|w| unsafe { w.bits((APB1_HZ + BAUD / 2) / BAUD) });
+    uart.cr1.write(|w| {
+        w.ue()
+            .enabled()
+            .te()
+            .enabled()
+            .re()
+            .enabled()
+            .rxneie()
+            .enabled()
+    });
+    unsafe { cortex_m::peripheral::NVIC::unmask(pac::Interrupt::UART5) };
+
+    client(|client| client.start(clock::millis()));
+}
+
+pub fn is_configured() -> bool {
+    !CONFIG.ssid.is_empty()
+}
+
+pub fn is_enabled() -> bool {
+    let dp = resources::pac();
+    dp.UART5.cr1.read().ue().is_enabled()
+}
+
+pub fn status() -> Status {
+    client(|client| client.status())
+}
+
+// Scores waiting, sent and dropped
+pub fn counts() -> (usize, u32, u32) {
+    client(|client| client.counts())
+}
+
+pub fn config() -> &'static Config {
+    &CONFIG
+}
+
+pub fn rx_dropped() -> u32 {
+    RX_DROPPED.load(Ordering::Relaxed)
+}
+
+// Post a finished game, signed with the profile playing or else the last
+// initials on the leaderboard; nothing while the module is off
+pub fn upload(mode: &'static str, score: u32, play_ms: u32) {
+    let initials = profiles::initials().unwrap_or_else(|| stats::last_initials(Board::Main));
+    client(|client| {
+        client.queue(Upload {
+            mode,
+            score,
+            play_ms,
+            initials,
+        })
+    });
+}
+
+// Run `f` on the client, which is there from the start
+fn client<R>(f: impl FnOnce(&mut Client) -> R) -> R {
+    CLIENT.with(f).expect("wifi client missing")
+}
+
+// Hand what the module said to the client and send what it wants next;
+// the serial task calls this
+pub fn poll() {
+    if !is_enabled() {
+        return;
+    }
+    let now = clock::millis();
+    client(|client| {
+        LINE.with(|line| {
+            while let Some(byte) = unsafe { RX.pop() } {
+                match byte {
+                    b'\n' => {
+                        if let Ok(text) = core::str::from_utf8(&line.bytes[..line.len]) {
+                            if !text.trim().is_empty() {
+                                client.line(text, now);
+                            }
+                        }
+                        line.len = 0;
+                    }
+                    // The send prompt, "> ", ends no line of its own
+                    b'>' if line.len == 0 => client.line(">", now),
+                    _ if line.len < LINE_SIZE => {
+                        line.bytes[line.len] = byte;
+                        line.len += 1;
+                    }
+                    _ => {}
+                }
+            }
+        });
+        if let Some(command) = client.next(now) {
+            let _ = client.write(command, &mut Writer);
+        }
+    });
+}
+
+// Queue bytes for the module, waiting for room when the buffer is full
+fn write(bytes: &[u8]) {
+    let drain = || resources::critical(|dp| dp.UART5.cr1.modify(|_, w| w.txeie().enabled()));
+    for &byte in bytes {
+        while !unsafe { TX.push(byte) } {
+            drain();
+        }
+    }
+    drain();
+}
+
+struct Writer;
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[interrupt]
+fn UART5() {
+    let dp = resources::pac();
+    let uart = &dp.UART5;
+    let sr = uart.sr.read();
+
+    if sr.rxne().bit_is_set() || sr.ore().bit_is_set() {
+        // Reading DR also clears an overrun
+        if sr.ore().bit_is_set() {
+            RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+        let byte = uart.dr.read().dr().bits() as u8;
+        if !unsafe { RX.push(byte) } {
+            RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    if sr.txe().bit_is_set() && uart.cr1.read().txeie().is_enabled() {
+        match unsafe { TX.pop() } {
+            Some(byte) => uart.dr.write(|w| w.dr().bits(byte as u16)),
+            None => uart.cr1.modify(|_, w| w.txeie().disabled()),
+        }
+    }
+}
+
+pub struct WifiSubsystem;
+
+impl Subsystem for WifiSubsystem {
+    fn name(&self) -> &'static str {
+        "wifi"
+    }
+
+    fn init(&self) -> Result<(), HwError> {
+        if cfg!(feature = "wifi") {
+            init();
+        }
+        Ok(())
+    }
+
+    fn health_check(&self) -> Health {
+        if !is_enabled() {
+            Health::Disabled
+        } else if status() == Status::Offline {
+            Health::Degraded("no access point")
+        } else if rx_dropped() > 0 {
+            Health::Degraded("rx overrun")
+        } else {
+            Health::Ok
+        }
+    }
+
+    // The module keeps its connection; only the UART stops
+    fn suspend(&self) {
+        if is_enabled() {
+            while !unsafe { TX.is_empty() } {}
+            resources::critical(|dp| dp.UART5.cr1.modify(|_, w| w.ue().disabled()));
+            LINK_SUSPENDED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // The client times out whatever was in flight and carries on
+    fn resume(&self) {
+        if LINK_SUSPENDED.load(Ordering::Relaxed) {
+            resources::critical(|dp| dp.UART5.cr1.modify(|_, w| w.ue().enabled()));
+            LINK_SUSPENDED.store(false, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! The scrolling playfield, drawn from the tile map
+//!
+//! A lane's obstacle, sky and ground scroll together, so one `World` moves
+//! them and draws them: the obstacle says where the pipes and openings are,
+//! and `core_logic::tilemap` turns that into columns of tiles. A frame only
+//! redraws the columns holding pipe, the ones a pipe has just uncovered,
+//! and the ground strip, and the water under it when its colors turn; the
+//! rest of the sky is left as it was. Anything
+//! else that paints over the playfield wholesale (a sky change, a theme
+//! change) calls `redraw` so the next frame draws every column. With the
+//! bird close to the ground its top rows are tinted red (`warn_ground`),
+//! over the freshly drawn ground each frame it scrolls. With the gap
+//! preview on, the next opening shows as a faint bar down the right edge
+//! before its pipe comes on, fading as the pipe gets near; the columns under
+//! it are drawn afresh every frame it is up, and the one after, to take it
+//! away again.
+#![allow(dead_code)]
+
+use core::cell::Cell;
+
+use core_logic::effects;
+use core_logic::mode::GameMode;
+use core_logic::tilemap::{Column, Shown, Tile, TileMap};
+
+use crate::color;
+use crate::config::{Coord, Rect, LCD_WIDTH, TILE};
+use crate::entity::{self, Entity, Renderer};
+use crate::lane::Lane;
+use crate::obstacle::{self, Obstacle};
+use crate::settings;
+use crate::sprites::{self, SpriteId};
+use crate::strip;
+
+// Rows of ground the warning tints, fading downward
+const WARNING_ROWS: u32 = 8;
+// The gap preview's bar, in from the right edge, and its strongest opacity
+const PREVIEW_W: u32 = 3;
+const PREVIEW_X: Coord = (LCD_WIDTH - PREVIEW_W - 1) as Coord;
+const PREVIEW_ALPHA: u32 = 96;
+
+// Cloned for a practice run's checkpoint
+#[derive(Clone)]
+pub struct World {
+    lane: Lane,
+    obstacle: Obstacle,
+    map: TileMap,
+    // What the columns showed when last drawn; None draws them all
+    shown: Cell<Option<Shown>>,
+    // Phase the water under the ground was last drawn at
+    strip_phase: Cell<Option<usize>>,
+    // Opacity of the red along the top of the ground, 0 for none
+    ground_warning: Cell<u8>,
+    // The gap preview was drawn last frame
+    preview_shown: Cell<bool>,
+}
+
+impl World {
+    // A new game's playfield in `lane` with `mode`'s obstacles, at the
+    // speed the difficulty setting starts games at
+    pub fn new(lane: Lane, mode: &'static dyn GameMode) -> Self {
+        let obstacle = Obstacle::init_in(lane, mode);
+        World {
+            lane,
+            map: TileMap::new(lane),
+            obstacle,
+            shown: Cell::new(None),
+            strip_phase: Cell::new(None),
+            ground_warning: Cell::new(0),
+            preview_shown: Cell::new(false),
+        }
+    }
+
+    pub fn lane(&self) -> Lane {
+        self.lane
+    }
+
+    pub fn obstacle(&self) -> &Obstacle {
+        &self.obstacle
+    }
+
+    pub fn obstacle_mut(&mut self) -> &mut Obstacle {
+        &mut self.obstacle
+    }
+
+    // How fast the obstacle and the ground move, in 1/SUBPIXELS of a pixel
+    // per tick; the map follows the obstacle pixel for pixel
+    pub fn velocity(&self) -> u32 {
+        self.obstacle.velocity()
+    }
+
+    pub fn set_velocity(&mut self, velocity: u32) {
+        self.obstacle.set_velocity(velocity);
+    }
+
+    // The screen under the playfield was painted over; draw all of it next
+    // time
+    pub fn redraw(&self) {
+        self.shown.set(None);
+    }
+
+    // Tint the top of the ground red at `alpha` from the next frame
+    pub fn warn_ground(&self, alpha: u8) {
+        self.ground_warning.set(alpha);
+    }
+
+    // One frame on its own: scroll, draw, and pick the next obstacle for
+    // `score` once off the left edge. True when a new obstacle has come in.
+    pub fn step(&mut self, score: u32) -> bool {
+        self.obstacle.set_score(score);
+        self.update(1);
+        entity::draw_now(self, self.lane);
+        self.obstacle.arrived()
+    }
+
+    // World column `index` at screen `x`, the obstacle starting in column
+    // `obstacle_column`: its tiles over the playfield if `field`, its
+    // ground if `ground`
+    fn draw_column(
+        &self,
+        renderer: &mut Renderer,
+        column: &Column,
+        at: (u32, Coord),
+        obstacle_column: u32,
+        field: bool,
+        ground: bool,
+    ) {
+        let (index, x) = at;
+        for span in column.spans() {
+            let rect = Rect::new(x, span.top, TILE, (span.bottom - span.top) as u32);
+            match span.tile {
+                Tile::Ground if ground => {
+                    let plant = sprites::sprite(SpriteId::Plant).filter(|p| p.w > 0);
+                    if let (Some(plant), Some(rect)) = (plant, strip::above(self.lane, rect)) {
+                        renderer.fill_tiled_from(rect, &plant, (index * TILE % plant.w, 0));
+                    }
+                }
+                Tile::Ground => {}
+                _ if !field => {}
+                Tile::Sky => renderer.fill_sky(rect),
+                Tile::PipeBody | Tile::PipeCap => obstacle::draw_pipe(
+                    renderer,
+                    rect,
+                    span.tile,
+                    index.saturating_sub(obstacle_column),
+                ),
+            }
+        }
+    }
+}
+
+impl Entity for World {
+    fn update(&mut self, dt: u32) {
+        let map = &mut self.map;
+        self.obstacle.update(dt, |dx| map.advance(dx));
+    }
+
+    // The whole lane: the scoreboard stays put, everything under it moves
+    fn bounds(&self) -> Rect {
+        self.lane.rect()
+    }
+
+    // Columns holding pipe, and ones that held pipe when last drawn, are
+    // drawn over the playfield; the ground wherever the scroll moved
+    fn draw(&self, renderer: &mut Renderer) {
+        let scroll = self.map.scroll_at(renderer.alpha());
+        let before = self.shown.get();
+        let moved = before.is_none_or(|shown| shown.scroll != scroll);
+        let pair = self.obstacle.shown(renderer.alpha());
+        let obstacle = self.map.obstacle_column(self.obstacle.pair());
+        let preview = if settings::get().gap_preview {
+            self.obstacle.gap_preview()
+        } else {
+            None
+        };
+        let under_preview = preview.is_some() || self.preview_shown.get();
+        let mut now = Shown::new(scroll);
+        for (index, x) in TileMap::visible(scroll) {
+            let column = self.map.column(index, obstacle, &pair);
+            let plain = column.is_plain();
+            let field = !plain
+                || before.is_none_or(|shown| !shown.was_plain(x, TILE))
+                || (under_preview && x + TILE as Coord > PREVIEW_X);
+            now.note(index, plain);
+            if field || moved {
+                self.draw_column(renderer, &column, (index, x), obstacle, field, moved);
+            }
+        }
+        self.shown.set(Some(now));
+
+        // Only over ground drawn just now, so the tint never builds up
+        let warning = self.ground_warning.get();
+        if moved && warning != 0 {
+            let top = self.lane.y(self.lane.ground);
+            for row in 0..WARNING_ROWS {
+                let alpha = effects::edge_fade(warning, row, WARNING_ROWS);
+                let rect = Rect::new(0, top + row as Coord, LCD_WIDTH, 1);
+                renderer.blend_rect(rect, color::RED, alpha);
+            }
+        }
+
+        // Over the columns drawn just now, like the warning
+        if let Some(preview) = preview {
+            let alpha = (preview.strength as u32 * PREVIEW_ALPHA / 255) as u8;
+            for (top, bottom) in preview.gaps.into_iter().flatten() {
+                let rect = Rect::new(PREVIEW_X, top, PREVIEW_W, (bottom - top) as u32);
+                renderer.blend_rect(rect, color::WHITE, alpha);
+            }
+        }
+        self.preview_shown.set(preview.is_some());
+
+        let phase = strip::phase();
+        let turned = before.is_none() || phase != self.strip_phase.get();
+        if let Some(phase) = phase.filter(|_| turned) {
+            strip::draw(renderer, self.lane, phase);
+        }
+        self.strip_phase.set(phase);
+    }
+}
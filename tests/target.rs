@@ -0,0 +1,393 @@
+//! Tests that run on the Cortex-M4, on the board or in QEMU
+//!
+//! core_logic's unit tests run on the host (`cargo test-core`). These take
+//! a few of the same rules through as Thumb code, where integer widths,
+//! shifts of negative numbers and wrapping are the target's own: the bird's
+//! fixed-point fall, collision against a pipe pair, how evenly the PRNG
+//! spreads its picks, the settings codec (core_logic::settings) with its
+//! migrations and block checksum, and the SDRAM arena's allocation
+//! (`src/sdram/arena.rs`, built in here as it is). The binary is the
+//! firmware's own target with its own harness, and needs a runner that
+//! serves semihosting, which carries the report and the exit status.
+//! `.cargo/config.toml` sets probe-rs up for the board on the ST-LINK:
+//!
+//! ```text
+//! cargo test --test target
+//! ```
+//!
+//! Without a board, QEMU runs it in place of probe-rs:
+//!
+//! ```text
+//! CARGO_TARGET_THUMBV7EM_NONE_EABIHF_RUNNER="qemu-system-arm -M netduinoplus2 \
+//!     -nographic -semihosting-config enable=on,target=native -kernel" \
+//!     cargo test --test target
+//! ```
+//!
+//! Cases run in turn, printed as they start. Without unwinding, the first
+//! that fails ends the run with its panic message.
+#![no_std]
+#![no_main]
+
+use core::fmt::Write;
+use core::panic::PanicInfo;
+
+use cortex_m_rt::entry;
+use stm32f4::stm32f429 as pac;
+
+use core_logic::bird::Bird;
+use core_logic::config::*;
+use core_logic::controls::{ControlScheme, Controls};
+use core_logic::crc;
+use core_logic::lane::Lane;
+use core_logic::lang::Language;
+use core_logic::obstacle::ObstaclePair;
+use core_logic::rng::Rng;
+use core_logic::rules;
+use core_logic::settings::{
+    field_count, from_payload, payload, Settings, TiltCalibration, FIELDS, HEADER_WORDS, MAGIC,
+    MAX_BLOCK_WORDS, VERSION,
+};
+use core_logic::tuning::{Param, TuningParams};
+
+// The firmware's arena, with the one thing it needs from `lcd`
+#[path = "../src/sdram/arena.rs"]
+mod arena;
+
+mod lcd {
+    pub trait Pixel {
+        const BYTES: u32;
+    }
+}
+
+use arena::{check_disjoint, Arena, FramebufferRegion, Region, FRAMEBUFFER_ALIGN};
+
+struct Case {
+    name: &'static str,
+    run: fn(),
+}
+
+const CASES: [Case; 5] = [
+    Case {
+        name: "physics",
+        run: physics,
+    },
+    Case {
+        name: "collision",
+        run: collision,
+    },
+    Case {
+        name: "prng_distribution",
+        run: prng_distribution,
+    },
+    Case {
+        name: "settings_round_trip",
+        run: settings_round_trip,
+    },
+    Case {
+        name: "sdram_arena",
+        run: sdram_arena,
+    },
+];
+
+#[entry]
+fn main() -> ! {
+    let mut out = semihosting::Writer;
+    let _ = write!(out, "\nrunning {} tests\n", CASES.len());
+    for case in &CASES {
+        let _ = write!(out, "test {} ... ", case.name);
+        (case.run)();
+        let _ = writeln!(out, "ok");
+    }
+    let _ = writeln!(out, "\ntest result: ok. {} passed; 0 failed", CASES.len());
+    semihosting::exit(true)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let _ = writeln!(semihosting::Writer, "FAILED\n{}", info);
+    semihosting::exit(false)
+}
+
+// One flap: up at once, slowing to a stop, then down ever faster until the
+// fastest fall, a whole pixel count every tick
+fn physics() {
+    const START: Coord = 150;
+    // MAX_FALL in controls, sixteenths of a pixel per tick
+    const MAX_FALL_PX: Coord = 6;
+    let mut controls = Controls::new(ControlScheme::FlapOnTap);
+    assert!(controls.input((0, true)));
+    controls.input((0, false));
+
+    let mut y = START;
+    let mut apex = START;
+    let mut last_step = None;
+    for tick in 0..80 {
+        let next = controls.tick(y).unwrap();
+        let step = next - y;
+        match last_step {
+            None => assert!(step < 0, "a flap lifts at once, not by {}", step),
+            // Speed only ever grows downward; a carry can cost a pixel
+            Some(last) => assert!(step >= last - 1, "tick {}: {} after {}", tick, step, last),
+        }
+        assert!(step <= MAX_FALL_PX, "tick {}: fell {}", tick, step);
+        apex = apex.min(next);
+        last_step = Some(step);
+        y = next;
+    }
+    assert!(apex < START - 20, "apex {}", apex);
+    assert_eq!(last_step, Some(MAX_FALL_PX));
+    // The same press from the same height comes out the same
+    let mut again = Controls::new(ControlScheme::FlapOnTap);
+    again.input((0, true));
+    again.input((0, false));
+    let replay = (0..80).fold(START, |y, _| again.tick(y).unwrap());
+    assert_eq!(replay, y);
+}
+
+// A bird in the opening is safe all the way across; higher or lower it
+// hits the pair alongside it
+fn collision() {
+    let lane = Lane::FULL;
+    let field = lane.field();
+    let pair_at = |x: Coord| {
+        let mut pair = ObstaclePair::new(lane);
+        pair.set_speed((LCD_END - x) as u32);
+        pair.advance();
+        pair
+    };
+    let center = (lane.gap_top + lane.gap_bottom) / 2 - PLAYER_HEIGHT as Coord / 2;
+    let in_gap = Bird::new(INIT_PLAYER_POS_X, center);
+    for x in (0..LCD_END).step_by(3) {
+        assert!(!rules::collides(&in_gap, &pair_at(x), field), "x = {}", x);
+    }
+    let alongside = pair_at(INIT_PLAYER_POS_X);
+    let above = Bird::new(INIT_PLAYER_POS_X, lane.gap_top - PLAYER_HEIGHT as Coord);
+    let below = Bird::new(INIT_PLAYER_POS_X, lane.gap_bottom);
+    assert!(rules::collides(&above, &alongside, field));
+    assert!(rules::collides(&below, &alongside, field));
+    let grounded = Bird::new(INIT_PLAYER_POS_X, GROUND_Y_POS - PLAYER_HEIGHT as Coord);
+    assert!(rules::collides(&grounded, &pair_at(LCD_END), field));
+}
+
+// Chi-squared of picks over eight buckets, against 24.3: the 0.1% point
+// for seven degrees of freedom
+fn prng_distribution() {
+    const BUCKETS: usize = 8;
+    const DRAWS: u32 = 8000;
+    for seed in [1, 0x1234_5678, 0xDEAD_BEEF] {
+        let mut rng = Rng::new(seed);
+        let mut counts = [0u32; BUCKETS];
+        for _ in 0..DRAWS {
+            let pick = rng.below(BUCKETS as u32);
+            counts[pick as usize] += 1;
+        }
+        let expected = DRAWS / BUCKETS as u32;
+        // In hundredths, to stay in integers
+        let chi2: u32 = counts
+            .iter()
+            .map(|&n| (n.abs_diff(expected) * 100).pow(2) / expected)
+            .sum::<u32>()
+            / 100;
+        assert!(
+            chi2 < 2430,
+            "seed {:#x}: chi2 {}/100, {:?}",
+            seed,
+            chi2,
+            counts
+        );
+    }
+    // xorshift never lands on zero, the one state it cannot leave
+    let mut rng = Rng::new(0);
+    assert!((0..10_000).all(|_| rng.next_u32() != 0));
+}
+
+// The settings go into the words the firmware stores and come back, a
+// record from before the music and the gap preview were kept loads with
+// those at their defaults, and a block of them checks out by the same CRC
+// the firmware stores. On the board the CRC unit has to agree; QEMU has
+// none and reads back 0.
+fn settings_round_trip() {
+    let mut tuning = TuningParams::DEFAULT;
+    for (param, value) in [
+        (Param::Gravity, -3),
+        (Param::Flap, 37),
+        (Param::Gap, 61),
+        (Param::Speed, 180),
+        (Param::Inset, 5),
+    ] {
+        tuning.set(param, value).unwrap();
+    }
+    assert_eq!(TuningParams::from_u32(tuning.to_u32()), Some(tuning));
+    for scheme in ControlScheme::ALL {
+        assert_eq!(ControlScheme::from_u32(scheme as u32), Some(scheme));
+    }
+    for language in Language::ALL {
+        assert_eq!(Language::from_u32(language as u32), Some(language));
+    }
+    assert_eq!(TuningParams::from_u32(0xFFFF_FFFF), None);
+
+    let changed = Settings {
+        tilt: TiltCalibration {
+            offset: [-300, 12, 16384],
+            threshold: 5000,
+            temp: Some(-850),
+        },
+        controls: ControlScheme::HybridAssist,
+        language: Language::De,
+        tuning,
+        invert: true,
+        music: false,
+        gap_preview: true,
+        ..Settings::DEFAULT
+    };
+    for settings in [Settings::DEFAULT, changed] {
+        let mut words = [0; 1 + FIELDS];
+        let len = payload(&settings, &mut words).unwrap();
+        assert_eq!(from_payload(&words[..len]), Some(settings));
+    }
+    let words = changed.to_words();
+    let v13 = Settings::from_words(13, &words[..field_count(13).unwrap()]);
+    let defaults = Settings::DEFAULT;
+    assert_eq!(
+        v13,
+        Some(Settings {
+            music: defaults.music,
+            gap_preview: defaults.gap_preview,
+            ..changed
+        })
+    );
+
+    // "SETG", the version, the fields, then the checksum of all before it
+    let mut block = [0; MAX_BLOCK_WORDS];
+    block[0] = MAGIC;
+    block[1] = VERSION;
+    block[HEADER_WORDS..MAX_BLOCK_WORDS - 1].copy_from_slice(&words);
+    let (body, sum) = block.split_at_mut(MAX_BLOCK_WORDS - 1);
+    sum[0] = crc::mpeg2(body.iter().copied());
+    let (body, sum) = block.split_at(MAX_BLOCK_WORDS - 1);
+    assert_eq!(crc::mpeg2(body.iter().copied()), sum[0]);
+    assert_eq!(
+        Settings::from_words(body[1], &body[HEADER_WORDS..]),
+        Some(changed)
+    );
+    let mut flipped = block;
+    flipped[HEADER_WORDS] ^= 1 << 7;
+    assert_ne!(
+        crc::mpeg2(flipped[..MAX_BLOCK_WORDS - 1].iter().copied()),
+        sum[0]
+    );
+
+    // SAFETY: nothing else in this binary touches RCC or the CRC unit
+    let dp = unsafe { pac::Peripherals::steal() };
+    dp.RCC.ahb1enr.modify(|_, w| w.crcen().enabled());
+    dp.CRC.cr.write(|w| w.reset().reset());
+    if dp.CRC.dr.read().bits() != 0 {
+        for &word in body {
+            dp.CRC.dr.write(|w| w.dr().bits(word));
+        }
+        assert_eq!(dp.CRC.dr.read().bits(), sum[0]);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct Argb;
+
+impl lcd::Pixel for Argb {
+    const BYTES: u32 = 4;
+}
+
+#[derive(Copy, Clone, PartialEq)]
+struct Rgb565;
+
+impl lcd::Pixel for Rgb565 {
+    const BYTES: u32 = 2;
+}
+
+// Framebuffers come out aligned, the size their type says, one after
+// another in the order asked for, and never overlap
+fn sdram_arena() {
+    const SDRAM: Region = Region {
+        base: 0xD000_0000,
+        size: 0x0080_0000,
+    };
+    let mut arena = Arena::new(SDRAM);
+    let odd = arena.alloc(3, 1);
+    let layer1: FramebufferRegion<240, 320, Argb> = arena.alloc_framebuffer();
+    let layer2: FramebufferRegion<57, 33, Rgb565> = arena.alloc_framebuffer();
+    let table = arena.alloc(100, 4);
+
+    assert_eq!(odd.base, SDRAM.base);
+    assert_eq!(layer1.base % FRAMEBUFFER_ALIGN, 0);
+    assert_eq!(layer1.base, SDRAM.base + FRAMEBUFFER_ALIGN);
+    assert_eq!(layer1.size(), 240 * 320 * 4);
+    assert_eq!(FramebufferRegion::<57, 33, Rgb565>::STRIDE, 57 * 2);
+    assert_eq!(layer2.base % FRAMEBUFFER_ALIGN, 0);
+    assert!(layer2.base >= layer1.region().end());
+    assert_eq!(table.base % 4, 0);
+    assert!(table.base >= layer2.region().end());
+    assert_eq!(arena.remaining().end(), SDRAM.end());
+    assert_eq!(arena.remaining().base, table.end());
+
+    let regions = [
+        ("odd", odd),
+        ("layer1", layer1.region()),
+        ("layer2", layer2.region()),
+        ("table", table),
+    ];
+    assert!(check_disjoint(&regions).is_ok());
+    let stray = Region {
+        base: layer1.region().end() - 4,
+        size: 8,
+    };
+    assert_eq!(
+        check_disjoint(&[("layer1", layer1.region()), ("stray", stray)]),
+        Err(("layer1", "stray"))
+    );
+}
+
+// ARM semihosting: the host the debugger or QEMU runs on does the I/O
+mod semihosting {
+    use core::arch::asm;
+    use core::fmt;
+
+    const SYS_WRITEC: u32 = 0x03;
+    const SYS_EXIT: u32 = 0x18;
+    // Reasons SYS_EXIT takes: a clean exit, and a failure
+    const APPLICATION_EXIT: u32 = 0x2_0026;
+    const RUN_TIME_ERROR: u32 = 0x2_0023;
+
+    fn call(op: u32, arg: u32) -> u32 {
+        let result: u32;
+        // SAFETY: a breakpoint the debugger or QEMU takes as a semihosting
+        // request; without one attached it faults, which ends the run too
+        unsafe {
+            asm!("bkpt #0xab", inout("r0") op => result, in("r1") arg, options(nostack));
+        }
+        result
+    }
+
+    pub struct Writer;
+
+    impl fmt::Write for Writer {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for byte in s.bytes() {
+                call(SYS_WRITEC, &byte as *const u8 as u32);
+            }
+            Ok(())
+        }
+    }
+
+    pub fn exit(success: bool) -> ! {
+        call(
+            SYS_EXIT,
+            if success {
+                APPLICATION_EXIT
+            } else {
+                RUN_TIME_ERROR
+            },
+        );
+        loop {
+            cortex_m::asm::bkpt();
+        }
+    }
+}